@@ -0,0 +1,288 @@
+//! Optional writing of the CDI kinds known to the agent as CDI JSON spec files, so that container
+//! runtimes reading CDI specs from disk (e.g. containerd's CDI registry) can inject discovered devices
+//! directly. It is enabled by setting the `CDI_SPEC_DIRECTORY` environment variable, usually to `/etc/cdi`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use akri_shared::os::env_var::EnvVarQuery;
+use tokio::sync::watch;
+
+use super::cdi;
+
+/// Name of the environment variable that sets the directory CDI spec files are written to
+pub const CDI_SPEC_DIRECTORY_LABEL: &str = "CDI_SPEC_DIRECTORY";
+
+/// Annotation of a CDI kind giving the namespace of its Configuration, so that same-named
+/// Configurations of different namespaces get different spec files
+pub const CDI_KIND_NAMESPACE_ANNOTATION: &str = "akri.sh/configuration-namespace";
+
+/// Returns the directory to write CDI spec files to, if writing them is enabled
+pub fn cdi_spec_directory(env_var_query: &impl EnvVarQuery) -> Option<PathBuf> {
+    env_var_query
+        .get_env_var(CDI_SPEC_DIRECTORY_LABEL)
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Path of the spec file of a CDI kind, i.e `akri-<namespace>_<configuration>.json` for the
+/// `akri.sh/<configuration>` kind of a Configuration in `<namespace>`. Kubernetes names cannot contain
+/// `_`, so the file names of different Configurations never clash.
+fn spec_path(directory: &Path, kind: &cdi::Kind) -> PathBuf {
+    let name = kind
+        .kind
+        .rsplit_once('/')
+        .map_or(kind.kind.as_str(), |(_, name)| name);
+    match kind.annotations.get(CDI_KIND_NAMESPACE_ANNOTATION) {
+        Some(namespace) => directory.join(format!("akri-{}_{}.json", namespace, name)),
+        None => directory.join(format!("akri-{}.json", name)),
+    }
+}
+
+/// Writes the spec file of a CDI kind, through a temporary file so that runtimes never read a partial spec
+pub fn write_cdi_spec(directory: &Path, kind: &cdi::Kind) -> std::io::Result<()> {
+    let path = spec_path(directory, kind);
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec_pretty(kind)?)?;
+    std::fs::rename(tmp_path, path)
+}
+
+/// Returns the spec files (and leftover temporary files) written to the directory by the agent,
+/// including the ones of a previous run
+fn existing_cdi_specs(directory: &Path) -> HashSet<PathBuf> {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(
+                "Failed to list CDI spec directory {}: {}",
+                directory.display(),
+                e
+            );
+            return HashSet::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| {
+                    name.starts_with("akri-")
+                        && (name.ends_with(".json") || name.ends_with(".json.tmp"))
+                })
+        })
+        .collect()
+}
+
+/// Writes the spec files of the given CDI kinds, and removes the previously written ones that are gone
+fn sync_cdi_specs(
+    directory: &Path,
+    kinds: &HashMap<String, cdi::Kind>,
+    written: &mut HashSet<PathBuf>,
+) {
+    let mut current = HashSet::new();
+    for kind in kinds.values() {
+        let path = spec_path(directory, kind);
+        match write_cdi_spec(directory, kind) {
+            Ok(()) => {
+                written.insert(path.clone());
+            }
+            Err(e) => error!("Failed to write CDI spec of {}: {}", kind.kind, e),
+        }
+        current.insert(path);
+    }
+    written.retain(|path| {
+        if current.contains(path) {
+            return true;
+        }
+        match std::fs::remove_file(path) {
+            Ok(()) => false,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => {
+                error!("Failed to remove CDI spec {}: {}", path.display(), e);
+                true
+            }
+        }
+    });
+}
+
+/// Keeps the CDI spec files in the directory in sync with the CDI kinds known to the agent, as
+/// Instances appear and disappear. It is expected to run this as a task.
+pub async fn run_cdi_spec_writer(
+    directory: PathBuf,
+    mut kinds: watch::Receiver<HashMap<String, cdi::Kind>>,
+) {
+    info!("writing CDI specs to {}", directory.display());
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        error!(
+            "Failed to create CDI spec directory {}: {}",
+            directory.display(),
+            e
+        );
+    }
+    // Spec files left by a previous run are removed on the first sync unless their kind is still known
+    let mut written = existing_cdi_specs(&directory);
+    loop {
+        let current = kinds.borrow_and_update().clone();
+        sync_cdi_specs(&directory, &current, &mut written);
+        if kinds.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::os::env_var::MockEnvVarQuery;
+    use std::env::VarError;
+
+    fn make_test_kind() -> cdi::Kind {
+        cdi::Kind {
+            kind: "akri.sh/config-a".to_string(),
+            annotations: HashMap::new(),
+            devices: vec![cdi::Device {
+                name: "a1b2c3".to_string(),
+                annotations: HashMap::new(),
+                container_edits: cdi::ContainerEdit {
+                    env: vec!["CONFIG_A_A1B2C3=/dev/video0".to_string()],
+                    device_nodes: vec![cdi::DeviceNode::from(
+                        akri_discovery_utils::discovery::v0::DeviceSpec {
+                            container_path: "/dev/video0".to_string(),
+                            host_path: "/dev/video0".to_string(),
+                            permissions: "rw".to_string(),
+                        },
+                    )],
+                    mounts: vec![cdi::Mount::from(
+                        akri_discovery_utils::discovery::v0::Mount {
+                            container_path: "/var/lib/data".to_string(),
+                            host_path: "/var/lib/akri/data".to_string(),
+                            read_only: true,
+                        },
+                    )],
+                    hooks: vec![],
+                },
+            }],
+            container_edits: vec![],
+        }
+    }
+
+    #[test]
+    fn test_cdi_spec_directory() {
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(VarError::NotPresent));
+        assert_eq!(cdi_spec_directory(&env), None);
+
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(mockall::predicate::eq(CDI_SPEC_DIRECTORY_LABEL))
+            .returning(|_| Ok("/etc/cdi".to_string()));
+        assert_eq!(cdi_spec_directory(&env), Some(PathBuf::from("/etc/cdi")));
+    }
+
+    #[test]
+    fn test_write_cdi_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        write_cdi_spec(dir.path(), &make_test_kind()).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("akri-config-a.json")).unwrap();
+        let spec: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(
+            spec,
+            serde_json::json!({
+                "cdiVersion": "0.6.0",
+                "kind": "akri.sh/config-a",
+                "devices": [{
+                    "name": "a1b2c3",
+                    "containerEdits": {
+                        "env": ["CONFIG_A_A1B2C3=/dev/video0"],
+                        "deviceNodes": [{
+                            "path": "/dev/video0",
+                            "hostPath": "/dev/video0",
+                            "permissions": "rw",
+                        }],
+                        "mounts": [{
+                            "hostPath": "/var/lib/akri/data",
+                            "containerPath": "/var/lib/data",
+                            "options": ["ro"],
+                        }],
+                    },
+                }],
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<cdi::Kind>(&content).unwrap(),
+            make_test_kind()
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_cdi_spec_namespaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut kind_a = make_test_kind();
+        kind_a.annotations.insert(
+            CDI_KIND_NAMESPACE_ANNOTATION.to_string(),
+            "ns-a".to_string(),
+        );
+        let mut kind_b = make_test_kind();
+        kind_b.annotations.insert(
+            CDI_KIND_NAMESPACE_ANNOTATION.to_string(),
+            "ns-b".to_string(),
+        );
+        write_cdi_spec(dir.path(), &kind_a).unwrap();
+        write_cdi_spec(dir.path(), &kind_b).unwrap();
+
+        assert!(dir.path().join("akri-ns-a_config-a.json").exists());
+        assert!(dir.path().join("akri-ns-b_config-a.json").exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_cdi_spec_writer_removes_stale_specs() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join("akri-ns-a_config-gone.json");
+        let stale_tmp = dir.path().join("akri-ns-a_config-gone.json.tmp");
+        let other = dir.path().join("other-vendor.json");
+        for path in [&stale, &stale_tmp, &other] {
+            std::fs::write(path, "{}").unwrap();
+        }
+        let kind = make_test_kind();
+        let (sender, receiver) = watch::channel(HashMap::from([(kind.kind.clone(), kind.clone())]));
+        let writer = tokio::spawn(run_cdi_spec_writer(dir.path().to_path_buf(), receiver));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert!(!stale.exists());
+        assert!(!stale_tmp.exists());
+        assert!(other.exists());
+        assert!(dir.path().join("akri-config-a.json").exists());
+
+        drop(sender);
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_cdi_spec_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("akri-config-a.json");
+        let (sender, receiver) = watch::channel(HashMap::new());
+        let writer = tokio::spawn(run_cdi_spec_writer(dir.path().to_path_buf(), receiver));
+
+        let kind = make_test_kind();
+        sender.send_replace(HashMap::from([(kind.kind.clone(), kind)]));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(path.exists());
+
+        sender.send_replace(HashMap::new());
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!path.exists());
+
+        drop(sender);
+        writer.await.unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+//! Optional generation of a manifest file describing an allocated device, for brokers that prefer reading a
+//! config file over many environment variables. It is enabled by setting the `DEVICE_MANIFEST_DIRECTORY`
+//! environment variable to a directory available at the same path on the host, where a JSON manifest gets
+//! written for each device upon allocation. The manifest is mounted read-only into the broker container under
+//! `/etc/akri/devices`, and its path given in the `AKRI_DEVICE_MANIFEST` environment variable.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use akri_shared::os::env_var::EnvVarQuery;
+
+use super::cdi;
+
+/// Name of the environment variable that sets the directory device manifests are written to
+pub const DEVICE_MANIFEST_DIRECTORY_LABEL: &str = "DEVICE_MANIFEST_DIRECTORY";
+/// Name of the environment variable holding the path of the device manifest in the broker container
+pub const AKRI_DEVICE_MANIFEST_LABEL: &str = "AKRI_DEVICE_MANIFEST";
+/// Directory device manifests are mounted in, in the broker containers
+pub const DEVICE_MANIFEST_CONTAINER_DIRECTORY: &str = "/etc/akri/devices";
+
+/// Returns the directory to write device manifests to, if writing them is enabled
+pub fn device_manifest_directory(env_var_query: &impl EnvVarQuery) -> Option<PathBuf> {
+    env_var_query
+        .get_env_var(DEVICE_MANIFEST_DIRECTORY_LABEL)
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Description of a device, as given to brokers
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceManifest {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<cdi::Mount>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_nodes: Vec<cdi::DeviceNode>,
+}
+
+impl From<&cdi::Device> for DeviceManifest {
+    fn from(device: &cdi::Device) -> Self {
+        DeviceManifest {
+            name: device.name.clone(),
+            properties: device
+                .container_edits
+                .env
+                .iter()
+                .map(|e| match e.split_once('=') {
+                    Some((k, v)) => (k.to_string(), v.to_string()),
+                    None => (e.to_string(), "".to_string()),
+                })
+                .collect(),
+            mounts: device.container_edits.mounts.clone(),
+            device_nodes: device.container_edits.device_nodes.clone(),
+        }
+    }
+}
+
+/// Name of the manifest file of a device, i.e `akri.sh-<configuration>-<id>.json` for the
+/// `akri.sh/<configuration>-<id>` device
+fn manifest_file_name(device_name: &str) -> String {
+    format!("{}.json", device_name.replace('/', "-"))
+}
+
+/// Writes the manifest of a device, through a temporary file so that brokers never read a partial manifest,
+/// and returns the device with the edits mounting the manifest into its containers
+pub fn with_device_manifest(
+    directory: &Path,
+    device: &cdi::Device,
+) -> std::io::Result<cdi::Device> {
+    let file_name = manifest_file_name(&device.name);
+    let path = directory.join(&file_name);
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(
+        &tmp_path,
+        serde_json::to_vec_pretty(&DeviceManifest::from(device))?,
+    )?;
+    std::fs::rename(tmp_path, &path)?;
+
+    let container_path = format!("{}/{}", DEVICE_MANIFEST_CONTAINER_DIRECTORY, file_name);
+    let mut device = device.clone();
+    device
+        .container_edits
+        .env
+        .push(format!("{}={}", AKRI_DEVICE_MANIFEST_LABEL, container_path));
+    device.container_edits.mounts.push(cdi::Mount {
+        host_path: path.to_string_lossy().into_owned(),
+        container_path,
+        mount_type: None,
+        options: vec!["ro".to_string()],
+    });
+    Ok(device)
+}
+
+/// Removes the manifest of a device, if any
+pub fn remove_device_manifest(directory: &Path, device_name: &str) {
+    match std::fs::remove_file(directory.join(manifest_file_name(device_name))) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => error!("Failed to remove device manifest of {}: {}", device_name, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::os::env_var::MockEnvVarQuery;
+    use std::{collections::HashMap, env::VarError};
+
+    fn make_test_device() -> cdi::Device {
+        cdi::Device {
+            name: "akri.sh/config-a-a1b2c3".to_string(),
+            annotations: HashMap::new(),
+            container_edits: cdi::ContainerEdit {
+                env: vec![
+                    "UDEV_DEVNODE=/dev/video0".to_string(),
+                    "RESOLUTION=1080p".to_string(),
+                ],
+                device_nodes: vec![cdi::DeviceNode {
+                    path: "/dev/video0".to_string(),
+                    host_path: Some("/dev/video0".to_string()),
+                    permissions: Some("rw".to_string()),
+                    ..Default::default()
+                }],
+                mounts: vec![cdi::Mount {
+                    host_path: "/var/lib/akri/data".to_string(),
+                    container_path: "/var/lib/data".to_string(),
+                    mount_type: None,
+                    options: vec!["ro".to_string()],
+                }],
+                hooks: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_device_manifest_directory() {
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(VarError::NotPresent));
+        assert_eq!(device_manifest_directory(&env), None);
+
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(mockall::predicate::eq(DEVICE_MANIFEST_DIRECTORY_LABEL))
+            .returning(|_| Ok("/var/lib/akri-manifests".to_string()));
+        assert_eq!(
+            device_manifest_directory(&env),
+            Some(PathBuf::from("/var/lib/akri-manifests"))
+        );
+    }
+
+    #[test]
+    fn test_with_device_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let device = make_test_device();
+        let edited = with_device_manifest(dir.path(), &device).unwrap();
+
+        let path = dir.path().join("akri.sh-config-a-a1b2c3.json");
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "name": "akri.sh/config-a-a1b2c3",
+                "properties": {
+                    "RESOLUTION": "1080p",
+                    "UDEV_DEVNODE": "/dev/video0",
+                },
+                "mounts": [{
+                    "hostPath": "/var/lib/akri/data",
+                    "containerPath": "/var/lib/data",
+                    "options": ["ro"],
+                }],
+                "deviceNodes": [{
+                    "path": "/dev/video0",
+                    "hostPath": "/dev/video0",
+                    "permissions": "rw",
+                }],
+            })
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The manifest gets mounted read-only and referenced by an environment variable
+        let container_path = "/etc/akri/devices/akri.sh-config-a-a1b2c3.json";
+        assert_eq!(
+            edited.container_edits.env.last().unwrap(),
+            &format!("AKRI_DEVICE_MANIFEST={}", container_path)
+        );
+        assert_eq!(
+            edited.container_edits.mounts.last().unwrap(),
+            &cdi::Mount {
+                host_path: path.to_string_lossy().into_owned(),
+                container_path: container_path.to_string(),
+                mount_type: None,
+                options: vec!["ro".to_string()],
+            }
+        );
+
+        remove_device_manifest(dir.path(), &device.name);
+        assert!(!path.exists());
+        // Removing a missing manifest is not an error
+        remove_device_manifest(dir.path(), &device.name);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod cdi;
+pub mod cdi_spec;
+pub mod device_manifest;
 mod in_memory;
 
 pub use in_memory::InMemoryManager;
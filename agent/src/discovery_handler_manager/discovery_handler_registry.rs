@@ -17,21 +17,28 @@
 //! A Discovery Handler's instance/endpoint sends a new list of discovered devices for a Request:
 #![doc=simple_mermaid::mermaid!("diagrams/dh_device.mmd")]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
-use akri_discovery_utils::discovery::v0::{ByteData, Device, DiscoverRequest};
-use akri_shared::akri::configuration::{Configuration, DiscoveryProperty};
+use akri_discovery_utils::discovery::v0::{ByteData, Device, DiscoverRequest, DiscoverResponse};
+use akri_shared::akri::configuration::{
+    resolve_broker_property_template, Configuration, DiscoveryHandlerInfo, DiscoveryProperty,
+    InstanceNamingStrategy,
+};
 use akri_shared::akri::instance::Instance;
 
 use akri_shared::akri::instance::InstanceSpec;
-use akri_shared::akri::AKRI_PREFIX;
+use akri_shared::akri::{
+    AKRI_DEVICE_ERROR_PROPERTY_NAME, AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME,
+    AKRI_LAST_WARNING_ANNOTATION_NAME, AKRI_LAST_WARNING_ENV_NAME, AKRI_PREFIX,
+};
 use async_trait::async_trait;
 use blake2::digest::{Update, VariableOutput};
 use blake2::VarBlake2b;
 use futures::future::select_all;
 use futures::future::try_join_all;
 use futures::FutureExt;
+use futures::{Stream, StreamExt};
 use itertools::Itertools;
 use kube::core::ObjectMeta;
 use kube_runtime::reflector::ObjectRef;
@@ -44,6 +51,10 @@ use tokio::sync::{broadcast, Mutex, Notify};
 use super::discovery_property_solver::PropertySolver;
 use super::{DiscoveryError, DiscoveryManagerKubeInterface};
 use crate::device_manager::cdi::ContainerEdit;
+use crate::util::metrics::{
+    DEVICE_PROBE_LATENCY_METRIC, DISCOVERY_DEVICES_FOUND_METRIC, DISCOVERY_DURATION_METRIC,
+    DISCOVERY_RESPONSE_TIME_METRIC,
+};
 
 #[cfg(test)]
 use mockall::automock;
@@ -61,26 +72,46 @@ impl DiscoveredDevice {
     /// However, local devices' Instances should have unique hashes even if they have the same id.
     /// To ensure this, the node's name is added to the id before it is hashed.
     fn device_hash(&self) -> String {
-        let (id_to_digest, shared, node_name) = match self {
-            DiscoveredDevice::LocalDevice(d, n) => (d.id.to_owned(), false, n.as_str()),
-            DiscoveredDevice::SharedDevice(d) => (d.id.to_owned(), true, ""),
+        self.device_hash_with_strategy(InstanceNamingStrategy::IdBased, "")
+    }
+
+    /// Generates the digest used to name an Instance according to the given naming strategy, the same
+    /// node handling as [DiscoveredDevice::device_hash] applies to all strategies.
+    fn device_hash_with_strategy(&self, strategy: InstanceNamingStrategy, key: &str) -> String {
+        let (device, node_name) = match self {
+            DiscoveredDevice::LocalDevice(d, n) => (d, n.as_str()),
+            DiscoveredDevice::SharedDevice(d) => (d, ""),
         };
-        let mut id_to_digest = id_to_digest.to_string();
-        // For local devices, include node hostname in id_to_digest so instances have unique names
-        if !shared {
-            id_to_digest = format!("{}{}", id_to_digest, node_name);
+        // For local devices, include node hostname in the digested value so instances have unique names
+        match strategy {
+            InstanceNamingStrategy::PropertyBased if !device.properties.is_empty() => {
+                let properties = device
+                    .properties
+                    .iter()
+                    .sorted()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .join(",");
+                digest(&format!("{}{}", properties, node_name), 3)
+            }
+            InstanceNamingStrategy::HashBased => {
+                digest(&format!("{}/{}{}", key, device.id, node_name), 8)
+            }
+            _ => digest(&format!("{}{}", device.id, node_name), 3),
+        }
+    }
+
+    fn device(&self) -> &Device {
+        match self {
+            DiscoveredDevice::LocalDevice(d, _) => d,
+            DiscoveredDevice::SharedDevice(d) => d,
+        }
+    }
+
+    fn device_mut(&mut self) -> &mut Device {
+        match self {
+            DiscoveredDevice::LocalDevice(d, _) => d,
+            DiscoveredDevice::SharedDevice(d) => d,
         }
-        let mut digest = String::new();
-        let mut hasher = VarBlake2b::new(3).unwrap();
-        hasher.update(id_to_digest);
-        hasher.finalize_variable(|var| {
-            digest = var
-                .iter()
-                .map(|num| format!("{:02x}", num))
-                .collect::<Vec<String>>()
-                .join("")
-        });
-        digest
     }
 
     fn inner(self) -> Device {
@@ -89,19 +120,24 @@ impl DiscoveredDevice {
             DiscoveredDevice::SharedDevice(d) => d,
         }
     }
-}
 
-impl From<DiscoveredDevice> for crate::device_manager::cdi::Device {
-    fn from(value: DiscoveredDevice) -> Self {
-        let hash = value.device_hash();
-        let dev = value.inner();
-        Self {
-            name: hash,
+    fn into_cdi_device(self, name: String) -> crate::device_manager::cdi::Device {
+        let dev = self.inner();
+        crate::device_manager::cdi::Device {
+            name,
             annotations: Default::default(),
             container_edits: crate::device_manager::cdi::ContainerEdit {
                 env: dev
                     .properties
                     .into_iter()
+                    .chain(
+                        dev.last_warning
+                            .map(|w| (AKRI_LAST_WARNING_ENV_NAME.to_string(), w)),
+                    )
+                    .chain(
+                        dev.error
+                            .map(|e| (AKRI_DEVICE_ERROR_PROPERTY_NAME.to_string(), e)),
+                    )
                     .map(|(k, v)| format!("{}={}", k, v))
                     .collect(),
                 device_nodes: dev.device_specs.into_iter().map_into().collect(),
@@ -112,6 +148,27 @@ impl From<DiscoveredDevice> for crate::device_manager::cdi::Device {
     }
 }
 
+fn digest(value: &str, size: usize) -> String {
+    let mut digest = String::new();
+    let mut hasher = VarBlake2b::new(size).unwrap();
+    hasher.update(value);
+    hasher.finalize_variable(|var| {
+        digest = var
+            .iter()
+            .map(|num| format!("{:02x}", num))
+            .collect::<Vec<String>>()
+            .join("")
+    });
+    digest
+}
+
+impl From<DiscoveredDevice> for crate::device_manager::cdi::Device {
+    fn from(value: DiscoveredDevice) -> Self {
+        let hash = value.device_hash();
+        value.into_cdi_device(hash)
+    }
+}
+
 /// This trait represents a discovery handler, no matter if it is an embedded or remote one
 #[async_trait]
 #[cfg_attr(test, automock)]
@@ -129,6 +186,56 @@ pub trait DiscoveryHandlerEndpoint: Send + Sync {
     fn is_closed(&self) -> bool;
 }
 
+/// Records the duration of each discovery pass of a Discovery Handler, as the time between a response of the
+/// stream of its answers and the previous one (or the discover request made at `started` for the first one)
+pub(super) fn record_discovery_duration<S>(
+    handler_name: &str,
+    started: std::time::Instant,
+    stream: S,
+) -> impl Stream<Item = S::Item>
+where
+    S: Stream<Item = Result<DiscoverResponse, tonic::Status>>,
+{
+    let histogram = DISCOVERY_DURATION_METRIC.with_label_values(&[handler_name]);
+    let mut pass_started = started;
+    stream.inspect(move |msg| {
+        if msg.is_ok() {
+            histogram.observe(pass_started.elapsed().as_secs_f64());
+            pass_started = std::time::Instant::now();
+        }
+    })
+}
+
+/// Takes the probe latency Discovery Handlers report in the devices properties out of the stream of their
+/// answers and records it as a metric, it changes on every discovery so it is not passed on to the Instances
+/// and brokers
+pub(super) fn record_probe_latency<S>(handler_name: &str, stream: S) -> impl Stream<Item = S::Item>
+where
+    S: Stream<Item = Result<DiscoverResponse, tonic::Status>>,
+{
+    let histogram = DEVICE_PROBE_LATENCY_METRIC.with_label_values(&[handler_name]);
+    stream.map(move |msg| {
+        msg.map(|mut response| {
+            for device in response.devices.iter_mut() {
+                let Some(latency) = device
+                    .properties
+                    .remove(AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME)
+                else {
+                    continue;
+                };
+                match latency.parse::<u64>() {
+                    Ok(ms) => histogram.observe(ms as f64 / 1000.0),
+                    Err(_) => warn!(
+                        "record_probe_latency - invalid probe latency {:?} reported for device {}",
+                        latency, device.id
+                    ),
+                }
+            }
+            response
+        })
+    })
+}
+
 /// This trait is here to help with testing for code that interract with the discovery handler registry.
 /// This trait represent a request made to a DH (either locally or through gRPC call), it will aggregate the
 /// results across the different registered handlers of that type, and generate the Instance objects for discovered
@@ -138,6 +245,10 @@ pub trait DiscoveryHandlerEndpoint: Send + Sync {
 pub trait DiscoveryHandlerRequest: Sync + Send {
     async fn get_instances(&self) -> Result<Vec<Instance>, DiscoveryError>;
     async fn set_extra_device_properties(&self, extra_device_properties: HashMap<String, String>);
+    async fn set_broker_property_templates(
+        &self,
+        broker_property_templates: HashMap<String, String>,
+    );
 }
 
 /// This trait is here to help with testing for code that interract with the discovery handler registry
@@ -145,16 +256,16 @@ pub trait DiscoveryHandlerRequest: Sync + Send {
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait DiscoveryHandlerRegistry: Sync + Send {
-    /// Create a new request against a specific Discovery Handler type, the DH Registry will ensure it
-    /// gets sent to all registered handlers with this name, present and future, if no DH with that name
-    /// is registered, returns an error.
+    /// Create a new request against specific Discovery Handler types, the DH Registry will ensure it
+    /// gets sent to all registered handlers with these names, present and future, if no DH with any of
+    /// these names is registered, returns an error. The devices discovered by the different types are
+    /// merged as described in [merge_devices].
     async fn new_request(
         &self,
         key: &str,
-        dh_name: &str,
-        dh_details: &str,
-        dh_properties: &[DiscoveryProperty],
+        dh_infos: &[DiscoveryHandlerInfo],
         extra_device_properties: HashMap<String, String>,
+        naming_strategy: InstanceNamingStrategy,
         namespace: &str,
     ) -> Result<(), DiscoveryError>;
 
@@ -166,17 +277,85 @@ pub trait DiscoveryHandlerRegistry: Sync + Send {
 
     /// Register a new endpoint to make it available to all current and future queries
     async fn register_endpoint(&self, endpoint: Arc<dyn DiscoveryHandlerEndpoint>);
+
+    /// Record whether the backend of a registered endpoint is reachable, as reported by the endpoint itself.
+    /// Returns false if no endpoint with this uid is registered.
+    async fn set_backend_liveness(&self, uid: &str, alive: bool, message: &str) -> bool;
+
+    /// Get the reason the backend of a Discovery Handler is down, if all its registered endpoints reported
+    /// it as down. Endpoints that never reported are considered alive.
+    async fn backend_down(&self, dh_name: &str) -> Option<String>;
+
+    /// Get a snapshot of the registered handlers and ongoing requests, for debugging purposes
+    async fn state(&self) -> RegistryState;
+}
+
+/// Snapshot of the state of a [DiscoveryHandlerRegistry]
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryState {
+    /// uids of the registered endpoints, by Discovery Handler name
+    pub handlers: BTreeMap<String, Vec<String>>,
+    /// Ongoing discovery requests, by Configuration name
+    pub requests: BTreeMap<String, RequestState>,
+}
+
+/// Snapshot of the state of a [DiscoveryHandlerRequest]
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestState {
+    pub handler_name: String,
+    /// Number of endpoints the request got sent to
+    pub endpoints: usize,
+    /// Number of devices currently discovered across these endpoints
+    pub devices: usize,
+}
+
+/// Merge the devices discovered for a request, given along with the index of the Discovery Handler that
+/// discovered them. Devices are deduplicated by id, the device reported by the first handler (in the
+/// Configuration's order) is kept, completed with the properties only the other handlers reported.
+fn merge_devices(
+    devices: impl Iterator<Item = (usize, Arc<DiscoveredDevice>)>,
+) -> Vec<Arc<DiscoveredDevice>> {
+    devices
+        // Discovery Handlers may report devices in any order, sort them so that Instances are
+        // always processed in the same order
+        .sorted_by(|(a_index, a), (b_index, b)| {
+            (&a.device().id, a_index).cmp(&(&b.device().id, b_index))
+        })
+        .group_by(|(_, d)| d.device().id.clone())
+        .into_iter()
+        .map(|(_, mut group)| {
+            let (_, first) = group.next().unwrap();
+            group.fold(first, |merged, (_, other)| {
+                let missing: Vec<(String, String)> = other
+                    .device()
+                    .properties
+                    .iter()
+                    .filter(|(k, _)| !merged.device().properties.contains_key(*k))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                if missing.is_empty() {
+                    return merged;
+                }
+                let mut merged = merged.as_ref().clone();
+                merged.device_mut().properties.extend(missing);
+                Arc::new(merged)
+            })
+        })
+        .collect()
 }
 
 /// Real world implementation of the Discovery Handler Request
 struct DHRequestImpl {
-    endpoints: RwLock<Vec<watch::Receiver<Vec<Arc<DiscoveredDevice>>>>>,
+    // Devices discovered by each endpoint, along with the index of the handler it got queried for
+    endpoints: RwLock<Vec<(usize, watch::Receiver<Vec<Arc<DiscoveredDevice>>>)>>,
     notifier: watch::Sender<crate::device_manager::cdi::Kind>,
     key: String,
-    handler_name: String,
-    details: String,
-    properties: Vec<DiscoveryProperty>,
+    handlers: Vec<DiscoveryHandlerInfo>,
     extra_device_properties: RwLock<HashMap<String, String>>,
+    broker_property_templates: RwLock<HashMap<String, String>>,
+    naming_strategy: InstanceNamingStrategy,
     kube_client: Arc<dyn DiscoveryManagerKubeInterface>,
     termination_notifier: Arc<Notify>,
 }
@@ -185,13 +364,16 @@ struct DHRequestImpl {
 impl DiscoveryHandlerRequest for DHRequestImpl {
     async fn get_instances(&self) -> Result<Vec<Instance>, DiscoveryError> {
         let properties = self.extra_device_properties.read().await;
-        Ok(self
-            .endpoints
-            .read()
-            .await
-            .iter()
-            .flat_map(|r| r.borrow().clone().into_iter())
-            .map(|i| self.device_to_instance(i.as_ref(), &properties))
+        let templates = self.broker_property_templates.read().await;
+        let devices =
+            merge_devices(
+                self.endpoints.read().await.iter().flat_map(|(index, r)| {
+                    r.borrow().clone().into_iter().map(move |d| (*index, d))
+                }),
+            );
+        Ok(devices
+            .into_iter()
+            .map(|i| self.device_to_instance(i.as_ref(), &properties, &templates))
             .collect())
     }
 
@@ -207,13 +389,62 @@ impl DiscoveryHandlerRequest for DHRequestImpl {
                 .send_modify(|k| k.container_edits.first_mut().unwrap().env = edit);
         }
     }
+
+    async fn set_broker_property_templates(
+        &self,
+        broker_property_templates: HashMap<String, String>,
+    ) {
+        let mut current = self.broker_property_templates.write().await;
+        if broker_property_templates != *current {
+            *current = broker_property_templates;
+            drop(current);
+            // Templates are resolved per device, so the devices must be built again
+            let devices = self.current_devices(false).await;
+            self.notifier.send_replace(self.cdi_kind(devices).await);
+        }
+    }
 }
 
 impl DHRequestImpl {
+    async fn state(&self) -> RequestState {
+        let endpoints = self.endpoints.read().await;
+        RequestState {
+            handler_name: self.handlers.iter().map(|h| &h.name).join(","),
+            endpoints: endpoints.len(),
+            devices: endpoints.iter().map(|(_, r)| r.borrow().len()).sum(),
+        }
+    }
+
+    /// Resolve the broker property templates for the given device. Templates referencing a property
+    /// the device doesn't have are left out, the device is still made available with a warning
+    /// telling about them, along with the one reported by the discovery handler if any.
+    fn resolve_broker_property_templates(
+        dev: &Device,
+        broker_property_templates: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, Option<String>) {
+        let (resolved, errors): (HashMap<String, String>, Vec<DiscoveryError>) =
+            broker_property_templates
+                .iter()
+                .map(|(k, template)| {
+                    resolve_broker_property_template(template, &dev.properties)
+                        .map(|v| (k.clone(), v))
+                        .map_err(|name| DiscoveryError::UndefinedTemplateReference(k.clone(), name))
+                })
+                .partition_result();
+        let warning = dev
+            .last_warning
+            .iter()
+            .cloned()
+            .chain(errors.iter().map(|e| e.to_string()).sorted())
+            .reduce(|a, b| format!("{}; {}", a, b));
+        (resolved, warning)
+    }
+
     fn device_to_instance(
         &self,
         dev: &DiscoveredDevice,
         extra_device_properties: &HashMap<String, String>,
+        broker_property_templates: &HashMap<String, String>,
     ) -> Instance {
         let (rdev, shared) = match dev {
             DiscoveredDevice::LocalDevice(d, _) => (d, false),
@@ -225,6 +456,22 @@ impl DHRequestImpl {
                 .iter()
                 .map(|(k, v)| (k.clone(), v.clone())),
         );
+        let (resolved, last_warning) =
+            Self::resolve_broker_property_templates(rdev, broker_property_templates);
+        if last_warning != rdev.last_warning {
+            warn!(
+                "Incomplete broker properties for {} of Configuration {}: {}",
+                rdev.id,
+                self.key,
+                last_warning.as_deref().unwrap_or_default()
+            );
+        }
+        properties.extend(resolved);
+        // Devices reported with a non-fatal error are still discovered, the error is kept
+        // alongside their (possibly partial) properties to ease debugging
+        if let Some(error) = &rdev.error {
+            properties.insert(AKRI_DEVICE_ERROR_PROPERTY_NAME.to_string(), error.clone());
+        }
         Instance {
             spec: InstanceSpec {
                 cdi_name: self.get_device_cdi_fqdn(dev),
@@ -234,16 +481,34 @@ impl DHRequestImpl {
                 nodes: Default::default(),
                 device_usage: Default::default(),
                 capacity: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
             },
             metadata: ObjectMeta {
-                name: Some(format!("{}-{}", self.key, dev.device_hash())),
+                name: Some(self.get_device_instance_name(dev)),
+                // Surface non-fatal warnings reported by the discovery handler or met resolving
+                // the broker property templates, the device is still discovered
+                annotations: last_warning
+                    .map(|w| BTreeMap::from([(AKRI_LAST_WARNING_ANNOTATION_NAME.to_string(), w)])),
                 ..Default::default()
             },
         }
     }
 
+    fn get_device_hash(&self, dev: &DiscoveredDevice) -> String {
+        dev.device_hash_with_strategy(self.naming_strategy, &self.key)
+    }
+
+    /// Derives the Instance name of a device according to the Configuration's naming strategy
+    fn get_device_instance_name(&self, dev: &DiscoveredDevice) -> String {
+        match self.naming_strategy {
+            InstanceNamingStrategy::HashBased => self.get_device_hash(dev),
+            _ => format!("{}-{}", self.key, self.get_device_hash(dev)),
+        }
+    }
+
     fn get_device_cdi_fqdn(&self, dev: &DiscoveredDevice) -> String {
-        format!("{}/{}={}", AKRI_PREFIX, self.key, dev.device_hash())
+        format!("{}/{}={}", AKRI_PREFIX, self.key, self.get_device_hash(dev))
     }
 
     async fn watch_devices(
@@ -252,7 +517,7 @@ impl DHRequestImpl {
     ) {
         loop {
             let mut local_endpoints = self.endpoints.write().await.clone();
-            let futures = local_endpoints.iter_mut().map(|e| e.changed().boxed());
+            let futures = local_endpoints.iter_mut().map(|(_, e)| e.changed().boxed());
             select! {
                 (a, index, _) = select_all(futures) => {
                     if a.is_err() {
@@ -264,68 +529,120 @@ impl DHRequestImpl {
                     }
                 },
                 Ok(new_dh_endpoint) = new_dh_receiver.recv() => {
-                    if new_dh_endpoint.get_name() != self.handler_name {
+                    let name = new_dh_endpoint.get_name();
+                    let indexes: Vec<usize> = self
+                        .handlers
+                        .iter()
+                        .positions(|h| h.name == name)
+                        .collect();
+                    if indexes.is_empty() {
                         // We woke up for another kind of DH, let's get back to sleep
                         continue
                     }
-                    if let Ok(q) = self.query(new_dh_endpoint).await {
-                        self.endpoints.write().await.push(q);
+                    for index in indexes {
+                        if let Ok(q) = self.query(index, new_dh_endpoint.clone()).await {
+                            self.endpoints.write().await.push((index, q));
+                        }
                     }
                 },
                 _ = self.notifier.closed() => {
                     return;
                 },
             }
-            let devices: Vec<Arc<DiscoveredDevice>> = self
-                .endpoints
+            let devices = self.current_devices(true).await;
+            DISCOVERY_DEVICES_FOUND_METRIC
+                .with_label_values(&[&self.key])
+                .set(devices.len() as i64);
+            self.notifier.send_replace(self.cdi_kind(devices).await);
+        }
+    }
+
+    /// Devices currently discovered across all endpoints, marking them as seen if `mark_seen` is set
+    async fn current_devices(&self, mark_seen: bool) -> Vec<Arc<DiscoveredDevice>> {
+        merge_devices(
+            self.endpoints
                 .write()
                 .await
                 .iter_mut()
-                .flat_map(|r| r.borrow_and_update().clone().into_iter())
-                .unique_by(|d| self.get_device_cdi_fqdn(d))
-                .collect();
-            self.notifier
-                .send_replace(crate::device_manager::cdi::Kind {
-                    kind: format!("{}/{}", AKRI_PREFIX, self.key),
-                    annotations: Default::default(),
-                    devices: devices
-                        .into_iter()
-                        .map(|d| d.as_ref().clone().into())
-                        .collect(),
-                    container_edits: vec![ContainerEdit {
-                        env: self
-                            .extra_device_properties
-                            .read()
-                            .await
-                            .iter()
-                            .map(|(k, v)| format!("{}={}", k, v))
-                            .collect(),
-                        ..Default::default()
-                    }],
-                });
+                .flat_map(|(index, r)| {
+                    let devices = if mark_seen {
+                        r.borrow_and_update().clone()
+                    } else {
+                        r.borrow().clone()
+                    };
+                    let index = *index;
+                    devices.into_iter().map(move |d| (index, d))
+                }),
+        )
+    }
+
+    /// Build the CDI kind describing the given devices
+    async fn cdi_kind(
+        &self,
+        devices: Vec<Arc<DiscoveredDevice>>,
+    ) -> crate::device_manager::cdi::Kind {
+        let templates = self.broker_property_templates.read().await;
+        crate::device_manager::cdi::Kind {
+            kind: format!("{}/{}", AKRI_PREFIX, self.key),
+            annotations: Default::default(),
+            devices: devices
+                .into_iter()
+                .map(|d| {
+                    let (resolved, last_warning) =
+                        Self::resolve_broker_property_templates(d.device(), &templates);
+                    let mut device = d.as_ref().clone();
+                    device.device_mut().last_warning = last_warning;
+                    let mut cdi_device = device.into_cdi_device(self.get_device_hash(&d));
+                    cdi_device
+                        .container_edits
+                        .env
+                        .extend(resolved.into_iter().map(|(k, v)| format!("{}={}", k, v)));
+                    cdi_device
+                })
+                .collect(),
+            container_edits: vec![ContainerEdit {
+                env: self
+                    .extra_device_properties
+                    .read()
+                    .await
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect(),
+                ..Default::default()
+            }],
         }
     }
 
+    /// Query an endpoint on behalf of the request's handler at the given index
     async fn query(
         &self,
+        index: usize,
         discovery_handler: Arc<dyn DiscoveryHandlerEndpoint>,
     ) -> Result<watch::Receiver<Vec<Arc<DiscoveredDevice>>>, DiscoveryError> {
         let (q_sender, q_receiver) = watch::channel(vec![]);
+        let handler = &self.handlers[index];
         let query_body = DiscoverRequest {
-            discovery_details: self.details.clone(),
-            discovery_properties: self.solve_discovery_properties().await?,
+            discovery_details: handler.discovery_details.clone(),
+            discovery_properties: self
+                .solve_discovery_properties(
+                    handler.discovery_properties.as_deref().unwrap_or_default(),
+                )
+                .await?,
         };
+        let started = std::time::Instant::now();
         discovery_handler.query(q_sender, query_body).await?;
+        DISCOVERY_RESPONSE_TIME_METRIC
+            .with_label_values(&[&self.key])
+            .observe(started.elapsed().as_secs_f64());
         Ok(q_receiver)
     }
 
     async fn solve_discovery_properties(
         &self,
+        properties: &[DiscoveryProperty],
     ) -> Result<HashMap<String, ByteData>, DiscoveryError> {
-        let solved_properties_futures = self
-            .properties
-            .iter()
-            .map(|p| p.solve(self.kube_client.clone()));
+        let solved_properties_futures =
+            properties.iter().map(|p| p.solve(self.kube_client.clone()));
         Ok(try_join_all(solved_properties_futures)
             .await?
             .into_iter()
@@ -339,6 +656,8 @@ pub(super) type LockedMap<T> = Arc<RwLock<HashMap<String, T>>>;
 pub(super) struct DHRegistryImpl {
     requests: LockedMap<Arc<DHRequestImpl>>,
     handlers: LockedMap<HashMap<String, Arc<dyn DiscoveryHandlerEndpoint>>>,
+    // Reasons of the endpoints that reported their backend as down, by endpoint uid
+    backends_down: LockedMap<String>,
     endpoint_notifier: broadcast::Sender<Arc<dyn DiscoveryHandlerEndpoint>>,
     configuration_notifier: mpsc::Sender<ObjectRef<Configuration>>,
     cdi_notifier: Arc<Mutex<watch::Sender<HashMap<String, crate::device_manager::cdi::Kind>>>>,
@@ -356,6 +675,7 @@ impl DHRegistryImpl {
         Self {
             requests: Default::default(),
             handlers: Default::default(),
+            backends_down: Default::default(),
             endpoint_notifier,
             configuration_notifier,
             cdi_notifier: Arc::new(Mutex::new(cdi_notifier)),
@@ -375,7 +695,11 @@ async fn handle_request(
     loop {
         match req_notifier.changed().await {
             Ok(_) => {
-                let kind = req_notifier.borrow_and_update().clone();
+                let mut kind = req_notifier.borrow_and_update().clone();
+                kind.annotations.insert(
+                    crate::device_manager::cdi_spec::CDI_KIND_NAMESPACE_ANNOTATION.to_string(),
+                    namespace.to_string(),
+                );
                 cdi_sender.lock().await.send_modify(|kinds| {
                     kinds.insert(cdi_kind.clone(), kind);
                 });
@@ -409,76 +733,104 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
     async fn new_request(
         &self,
         key: &str,
-        dh_name: &str,
-        dh_details: &str,
-        dh_properties: &[DiscoveryProperty],
+        dh_infos: &[DiscoveryHandlerInfo],
         extra_device_properties: HashMap<String, String>,
+        naming_strategy: InstanceNamingStrategy,
         namespace: &str,
     ) -> Result<(), DiscoveryError> {
-        match self.handlers.read().await.get(dh_name) {
-            Some(handlers) => {
-                let (notifier, _) = watch::channel(Default::default());
-                let terminated = Arc::new(Notify::new());
-                let mut dh_req = DHRequestImpl {
-                    endpoints: Default::default(),
-                    notifier,
-                    key: key.to_string(),
-                    handler_name: dh_name.to_string(),
-                    details: dh_details.to_string(),
-                    properties: dh_properties.to_vec(),
-                    extra_device_properties: RwLock::new(extra_device_properties),
-                    kube_client: self.kube_client.clone(),
-                    termination_notifier: terminated.clone(),
-                };
-                let dh_futures = handlers
-                    .iter()
-                    .map(|(_, handler)| dh_req.query(handler.clone()));
-                let dh_streams: Vec<watch::Receiver<Vec<Arc<DiscoveredDevice>>>> =
-                    try_join_all(dh_futures).await?;
-                dh_req.endpoints = RwLock::new(dh_streams);
-                {
-                    let mut req_w = self.requests.write().await;
-                    req_w.insert(key.to_string(), Arc::new(dh_req));
+        let registered = self.handlers.read().await;
+        let endpoints: Vec<(usize, Arc<dyn DiscoveryHandlerEndpoint>)> = dh_infos
+            .iter()
+            .enumerate()
+            .filter_map(|(index, dh_info)| match registered.get(&dh_info.name) {
+                Some(handlers) => Some(
+                    handlers
+                        .values()
+                        .map(move |handler| (index, handler.clone())),
+                ),
+                None => {
+                    // The handlers that are not registered yet get the request once they register
+                    trace!(
+                        "new_request - no Discovery Handler {} registered yet for {}",
+                        dh_info.name,
+                        key
+                    );
+                    None
                 }
-                let dh_req_ref = self.requests.read().await.get(key).unwrap().to_owned();
-                let local_req_notifier = self
-                    .requests
-                    .read()
-                    .await
-                    .get(key)
-                    .unwrap()
-                    .notifier
-                    .subscribe();
-                let local_config_sender = self.configuration_notifier.to_owned();
-                let local_cdi_sender = self.cdi_notifier.to_owned();
-                let local_key = key.to_owned();
-                let namespace = namespace.to_owned();
-                tokio::spawn(async move {
-                    handle_request(
-                        local_req_notifier,
-                        &local_key,
-                        &namespace,
-                        local_cdi_sender,
-                        local_config_sender,
-                    )
-                    .await
-                });
+            })
+            .flatten()
+            .collect();
+        drop(registered);
+        if endpoints.is_empty() {
+            return Err(DiscoveryError::NoHandler(
+                dh_infos
+                    .iter()
+                    .map(|dh_info| dh_info.name.as_str())
+                    .join(", "),
+            ));
+        }
+        let (notifier, _) = watch::channel(Default::default());
+        let terminated = Arc::new(Notify::new());
+        let mut dh_req = DHRequestImpl {
+            endpoints: Default::default(),
+            notifier,
+            key: key.to_string(),
+            handlers: dh_infos.to_vec(),
+            extra_device_properties: RwLock::new(extra_device_properties),
+            broker_property_templates: Default::default(),
+            naming_strategy,
+            kube_client: self.kube_client.clone(),
+            termination_notifier: terminated.clone(),
+        };
+        let dh_futures = endpoints.into_iter().map(|(index, handler)| {
+            dh_req
+                .query(index, handler)
+                .map(move |res| res.map(|q| (index, q)))
+        });
+        let dh_streams = try_join_all(dh_futures).await?;
+        dh_req.endpoints = RwLock::new(dh_streams);
+        {
+            let mut req_w = self.requests.write().await;
+            req_w.insert(key.to_string(), Arc::new(dh_req));
+        }
+        let dh_req_ref = self.requests.read().await.get(key).unwrap().to_owned();
+        let local_req_notifier = self
+            .requests
+            .read()
+            .await
+            .get(key)
+            .unwrap()
+            .notifier
+            .subscribe();
+        let local_config_sender = self.configuration_notifier.to_owned();
+        let local_cdi_sender = self.cdi_notifier.to_owned();
+        let local_key = key.to_owned();
+        let namespace = namespace.to_owned();
+        tokio::spawn(async move {
+            handle_request(
+                local_req_notifier,
+                &local_key,
+                &namespace,
+                local_cdi_sender,
+                local_config_sender,
+            )
+            .await
+        });
 
-                let local_key = key.to_owned();
-                let notifier_receiver = self.endpoint_notifier.subscribe();
-                let local_req = self.requests.clone();
-                tokio::spawn(async move {
-                    select! {
-                        _ = dh_req_ref
-                        .watch_devices(notifier_receiver) => {},
-                        _ = terminated.notified() => {},
-                    }
-                    local_req.write().await.remove(&local_key);
-                });
-                Ok(())
+        let local_key = key.to_owned();
+        let notifier_receiver = self.endpoint_notifier.subscribe();
+        let local_req = self.requests.clone();
+        tokio::spawn(async move {
+            select! {
+                _ = dh_req_ref
+                .watch_devices(notifier_receiver) => {},
+                _ = terminated.notified() => {},
             }
-            None => Err(DiscoveryError::NoHandler(dh_name.to_string())),
-        }
+            local_req.write().await.remove(&local_key);
+            let _ = DISCOVERY_DEVICES_FOUND_METRIC.remove_label_values(&[&local_key]);
+            let _ = DISCOVERY_RESPONSE_TIME_METRIC.remove_label_values(&[&local_key]);
+        });
+        Ok(())
     }
 
     async fn get_request(&self, key: &str) -> Option<Arc<dyn DiscoveryHandlerRequest>> {
@@ -499,6 +851,8 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
         let name = endpoint.get_name();
         let uid = endpoint.get_uid();
         let _ = self.endpoint_notifier.send(endpoint.clone());
+        // A (re-)registered endpoint is considered alive until it reports otherwise
+        self.backends_down.write().await.remove(&uid);
         {
             let mut w_handlers = self.handlers.write().await;
             match w_handlers.get_mut(&name) {
@@ -517,6 +871,7 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
         // endpoint to close itself when it cannot accept new requests, it is ok for the endpoint to do so
         // reactively after a failure on a new request.
         let local_handlers = self.handlers.clone();
+        let local_backends_down = self.backends_down.clone();
         tokio::spawn(async move {
             endpoint.closed().await;
             let mut w_handlers = local_handlers.write().await;
@@ -524,12 +879,79 @@ impl DiscoveryHandlerRegistry for DHRegistryImpl {
                 // Remove all closed endpoints, we can't remove just the one with our uid, as it
                 // may have registered again in the meantime.
                 v.retain(|_, e| !e.is_closed());
+                if !v.contains_key(&uid) {
+                    local_backends_down.write().await.remove(&uid);
+                }
                 if v.is_empty() {
                     w_handlers.remove(&name);
                 }
             }
         });
     }
+
+    async fn set_backend_liveness(&self, uid: &str, alive: bool, message: &str) -> bool {
+        let registered = self
+            .handlers
+            .read()
+            .await
+            .values()
+            .any(|endpoints| endpoints.contains_key(uid));
+        if !registered {
+            return false;
+        }
+        let mut w_backends_down = self.backends_down.write().await;
+        if alive {
+            if w_backends_down.remove(uid).is_some() {
+                info!("Backend of Discovery Handler {} is back up", uid);
+            }
+        } else if w_backends_down
+            .insert(uid.to_string(), message.to_string())
+            .is_none()
+        {
+            warn!("Backend of Discovery Handler {} is down: {}", uid, message);
+        }
+        true
+    }
+
+    async fn backend_down(&self, dh_name: &str) -> Option<String> {
+        let handlers = self.handlers.read().await;
+        let endpoints = handlers.get(dh_name)?;
+        let backends_down = self.backends_down.read().await;
+        let reasons: Option<Vec<&String>> = endpoints
+            .keys()
+            .sorted()
+            .map(|uid| backends_down.get(uid))
+            .collect();
+        match reasons {
+            Some(reasons) if !reasons.is_empty() => Some(reasons.into_iter().unique().join("; ")),
+            _ => None,
+        }
+    }
+
+    async fn state(&self) -> RegistryState {
+        let handlers = self
+            .handlers
+            .read()
+            .await
+            .iter()
+            .map(|(name, endpoints)| (name.clone(), endpoints.keys().sorted().cloned().collect()))
+            .collect();
+        let requests: Vec<(String, Arc<DHRequestImpl>)> = self
+            .requests
+            .read()
+            .await
+            .iter()
+            .map(|(key, req)| (key.clone(), req.clone()))
+            .collect();
+        let mut request_states = BTreeMap::new();
+        for (key, req) in requests {
+            request_states.insert(key, req.state().await);
+        }
+        RegistryState {
+            handlers,
+            requests: request_states,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -544,6 +966,14 @@ mod tests {
 
     use super::*;
 
+    fn mock_handler_info() -> DiscoveryHandlerInfo {
+        DiscoveryHandlerInfo {
+            name: "mock_handler".to_string(),
+            discovery_details: Default::default(),
+            discovery_properties: None,
+        }
+    }
+
     #[test]
     fn test_discovered_device() {
         let local_device = DiscoveredDevice::LocalDevice(
@@ -552,6 +982,8 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                last_warning: None,
+                error: None,
             },
             "my_node".to_owned(),
         );
@@ -561,6 +993,8 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                last_warning: None,
+                error: None,
             },
             "my_other_node".to_owned(),
         );
@@ -577,6 +1011,8 @@ mod tests {
                 host_path: "host".to_owned(),
                 permissions: "perms".to_owned(),
             }],
+            last_warning: None,
+            error: None,
         });
 
         assert_eq!(
@@ -630,6 +1066,94 @@ mod tests {
         );
     }
 
+    fn naming_request(naming_strategy: InstanceNamingStrategy) -> DHRequestImpl {
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        DHRequestImpl {
+            endpoints: Default::default(),
+            notifier: cdi_notifier,
+            key: "my-config".to_owned(),
+            handlers: vec![mock_handler_info()],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy,
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+        }
+    }
+
+    #[test]
+    fn test_dh_request_impl_naming_strategies() {
+        let device = Device {
+            id: "my_local_device".to_owned(),
+            properties: HashMap::from([
+                ("MY_DEVICE_KEY".to_owned(), "device_value".to_owned()),
+                ("OTHER_KEY".to_owned(), "other_value".to_owned()),
+            ]),
+            mounts: Default::default(),
+            device_specs: Default::default(),
+            last_warning: None,
+            error: None,
+        };
+        let local_device = DiscoveredDevice::LocalDevice(device.clone(), "my_node".to_owned());
+        let is_valid_name = |name: &str| {
+            name.len() <= 63
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        };
+
+        for (strategy, expected_name, expected_cdi_name) in [
+            (
+                InstanceNamingStrategy::IdBased,
+                "my-config-e77db4",
+                "akri.sh/my-config=e77db4",
+            ),
+            (
+                InstanceNamingStrategy::PropertyBased,
+                "my-config-9d845b",
+                "akri.sh/my-config=9d845b",
+            ),
+            (
+                InstanceNamingStrategy::HashBased,
+                "91d8b18331b36c89",
+                "akri.sh/my-config=91d8b18331b36c89",
+            ),
+        ] {
+            let req = naming_request(strategy);
+            let name = req.get_device_instance_name(&local_device);
+            assert_eq!(name, expected_name);
+            assert!(is_valid_name(&name));
+            assert_eq!(req.get_device_cdi_fqdn(&local_device), expected_cdi_name);
+            // Names are stable across requests
+            assert_eq!(
+                naming_request(strategy).get_device_instance_name(&local_device),
+                name
+            );
+            // The CDI device name matches the Instance cdi_name
+            let cdi_device = local_device
+                .clone()
+                .into_cdi_device(req.get_device_hash(&local_device));
+            assert_eq!(
+                format!("{}/{}={}", AKRI_PREFIX, req.key, cdi_device.name),
+                expected_cdi_name
+            );
+        }
+
+        // Property based naming falls back to the device id when there are no properties
+        let no_properties_device = DiscoveredDevice::LocalDevice(
+            Device {
+                properties: Default::default(),
+                ..device
+            },
+            "my_node".to_owned(),
+        );
+        assert_eq!(
+            naming_request(InstanceNamingStrategy::PropertyBased)
+                .get_device_instance_name(&no_properties_device),
+            "my-config-e77db4"
+        );
+    }
+
     #[tokio::test]
     async fn test_dh_request_impl_get_instances() {
         let (_, notifier) = watch::channel(vec![Arc::new(DiscoveredDevice::LocalDevice(
@@ -641,6 +1165,8 @@ mod tests {
                 )]),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                last_warning: None,
+                error: None,
             },
             "my_node".to_owned(),
         ))]);
@@ -650,13 +1176,13 @@ mod tests {
             endpoints,
             notifier: cdi_notifier,
             key: "my_config".to_owned(),
-            handler_name: "mock_handler".to_string(),
-            details: Default::default(),
-            properties: Default::default(),
+            handlers: vec![mock_handler_info()],
             extra_device_properties: RwLock::new(HashMap::from([(
                 "MY_EXTRA_KEY".to_owned(),
                 "value".to_owned(),
             )])),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
             kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
             termination_notifier: Arc::new(Notify::new()),
         };
@@ -679,30 +1205,415 @@ mod tests {
                     shared: false,
                     nodes: Default::default(),
                     device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
                 }
             }]
         );
     }
 
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_with_warning() {
+        let device = DiscoveredDevice::SharedDevice(Device {
+            id: "my_shared_device".to_owned(),
+            properties: HashMap::from([("ENV_KEY".to_owned(), "env_value".to_owned())]),
+            mounts: Default::default(),
+            device_specs: Default::default(),
+            last_warning: Some("authentication failed".to_owned()),
+            error: None,
+        });
+        let (_, notifier) = watch::channel(vec![Arc::new(device.clone())]);
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: RwLock::new(vec![(0, notifier)]),
+            notifier: cdi_notifier,
+            key: "my_config".to_owned(),
+            handlers: vec![mock_handler_info()],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+        };
+
+        // Device is still discovered and the warning is surfaced as an annotation
+        assert_eq!(
+            req.get_instances().await.unwrap(),
+            vec![Instance {
+                metadata: ObjectMeta {
+                    name: Some("my_config-4294ea".to_owned()),
+                    annotations: Some(BTreeMap::from([(
+                        AKRI_LAST_WARNING_ANNOTATION_NAME.to_owned(),
+                        "authentication failed".to_owned()
+                    )])),
+                    ..Default::default()
+                },
+                spec: InstanceSpec {
+                    configuration_name: "my_config".to_owned(),
+                    cdi_name: "akri.sh/my_config=4294ea".to_owned(),
+                    capacity: 0,
+                    broker_properties: HashMap::from([(
+                        "ENV_KEY".to_owned(),
+                        "env_value".to_owned()
+                    )]),
+                    shared: true,
+                    nodes: Default::default(),
+                    device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
+                }
+            }]
+        );
+        // The warning is also exposed to brokers
+        assert_eq!(
+            Into::<cdi::Device>::into(device).container_edits.env,
+            vec![
+                "ENV_KEY=env_value".to_owned(),
+                format!("{}=authentication failed", AKRI_LAST_WARNING_ENV_NAME)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_with_error() {
+        let device = DiscoveredDevice::LocalDevice(
+            Device {
+                id: "my_camera".to_owned(),
+                properties: HashMap::from([("ENV_KEY".to_owned(), "env_value".to_owned())]),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: Some("failed to get device metadata".to_owned()),
+            },
+            "node-a".to_owned(),
+        );
+        let (_, notifier) = watch::channel(vec![Arc::new(device.clone())]);
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: RwLock::new(vec![(0, notifier)]),
+            notifier: cdi_notifier,
+            key: "my_config".to_owned(),
+            handlers: vec![mock_handler_info()],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+        };
+
+        // Instance is still created and the error is surfaced in its properties
+        let instances = req.get_instances().await.unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(
+            instances[0].spec.broker_properties,
+            HashMap::from([
+                ("ENV_KEY".to_owned(), "env_value".to_owned()),
+                (
+                    AKRI_DEVICE_ERROR_PROPERTY_NAME.to_owned(),
+                    "failed to get device metadata".to_owned()
+                ),
+            ])
+        );
+        assert_eq!(instances[0].metadata.annotations, None);
+        assert_eq!(
+            Into::<cdi::Device>::into(device).container_edits.env,
+            vec![
+                "ENV_KEY=env_value".to_owned(),
+                format!(
+                    "{}=failed to get device metadata",
+                    AKRI_DEVICE_ERROR_PROPERTY_NAME
+                )
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_discovery_duration_per_response() {
+        let histogram = DISCOVERY_DURATION_METRIC.with_label_values(&["passHandler"]);
+        let stream = futures::stream::iter(vec![
+            Ok(DiscoverResponse { devices: vec![] }),
+            Err(tonic::Status::unavailable("")),
+            Ok(DiscoverResponse { devices: vec![] }),
+            Ok(DiscoverResponse { devices: vec![] }),
+        ]);
+
+        // Every successful response ends a discovery pass
+        let _: Vec<_> = record_discovery_duration("passHandler", std::time::Instant::now(), stream)
+            .collect()
+            .await;
+        assert_eq!(histogram.get_sample_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_record_probe_latency() {
+        let device = |id: &str, latency: Option<&str>| Device {
+            id: id.to_owned(),
+            properties: HashMap::from_iter(
+                [("ENV_KEY".to_owned(), "env_value".to_owned())]
+                    .into_iter()
+                    .chain(latency.map(|l| {
+                        (
+                            AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME.to_owned(),
+                            l.to_owned(),
+                        )
+                    })),
+            ),
+            mounts: Default::default(),
+            device_specs: Default::default(),
+            last_warning: None,
+            error: None,
+        };
+        let histogram = DEVICE_PROBE_LATENCY_METRIC.with_label_values(&["latencyHandler"]);
+        let stream = futures::stream::iter(vec![Ok(DiscoverResponse {
+            devices: vec![
+                device("camera1", Some("250")),
+                device("camera2", None),
+                device("camera3", Some("not a number")),
+            ],
+        })]);
+
+        // The latency is recorded and taken out of the properties passed on to the Instances
+        let responses: Vec<_> = record_probe_latency("latencyHandler", stream)
+            .collect()
+            .await;
+        let devices = &responses[0].as_ref().unwrap().devices;
+        assert!(devices.iter().all(
+            |d| d.properties == HashMap::from([("ENV_KEY".to_owned(), "env_value".to_owned())])
+        ));
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_sample_sum(), 0.25);
+    }
+
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_ordering() {
+        let device = |id: &str| {
+            Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: id.to_owned(),
+                properties: HashMap::from([("ID".to_owned(), id.to_owned())]),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: None,
+            }))
+        };
+        let instances = |devices: Vec<Arc<DiscoveredDevice>>| async move {
+            let (_, notifier) = watch::channel(devices);
+            let (cdi_notifier, _) = watch::channel(Default::default());
+            let req = DHRequestImpl {
+                endpoints: RwLock::new(vec![(0, notifier)]),
+                notifier: cdi_notifier,
+                key: "my_config".to_owned(),
+                handlers: vec![mock_handler_info()],
+                extra_device_properties: Default::default(),
+                broker_property_templates: Default::default(),
+                naming_strategy: InstanceNamingStrategy::IdBased,
+                kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+                termination_notifier: Arc::new(Notify::new()),
+            };
+            (
+                req.get_instances().await.unwrap(),
+                req.current_devices(false).await,
+            )
+        };
+
+        let (sorted_instances, sorted_devices) = instances(vec![
+            device("camera_a"),
+            device("camera_b"),
+            device("camera_c"),
+        ])
+        .await;
+        let (shuffled_instances, shuffled_devices) = instances(vec![
+            device("camera_c"),
+            device("camera_a"),
+            device("camera_b"),
+        ])
+        .await;
+        assert_eq!(sorted_instances, shuffled_instances);
+        assert_eq!(sorted_devices, shuffled_devices);
+        assert_eq!(
+            sorted_instances
+                .iter()
+                .map(|i| i.spec.broker_properties["ID"].as_str())
+                .collect::<Vec<_>>(),
+            vec!["camera_a", "camera_b", "camera_c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_merges_handlers() {
+        let device = |id: &str, properties: &[(&str, &str)]| {
+            Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: id.to_owned(),
+                properties: properties
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: None,
+            }))
+        };
+        // The second handler's endpoint is listed first, handlers are still merged in the
+        // Configuration's order
+        let (_, custom_notifier) = watch::channel(vec![
+            device("camera_a", &[("BRAND", "custom"), ("RTSP_URL", "rtsp://a")]),
+            device("camera_c", &[("BRAND", "custom")]),
+        ]);
+        let (_, onvif_notifier) = watch::channel(vec![
+            device("camera_a", &[("BRAND", "onvif"), ("IP", "10.0.0.1")]),
+            device("camera_b", &[("BRAND", "onvif")]),
+        ]);
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: RwLock::new(vec![(1, custom_notifier), (0, onvif_notifier)]),
+            notifier: cdi_notifier,
+            key: "my_config".to_owned(),
+            handlers: vec![
+                DiscoveryHandlerInfo {
+                    name: "onvif".to_string(),
+                    discovery_details: Default::default(),
+                    discovery_properties: None,
+                },
+                DiscoveryHandlerInfo {
+                    name: "custom".to_string(),
+                    discovery_details: Default::default(),
+                    discovery_properties: None,
+                },
+            ],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: InstanceNamingStrategy::IdBased,
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+        };
+
+        let instances = req.get_instances().await.unwrap();
+        assert_eq!(
+            instances
+                .iter()
+                .map(|i| i.spec.broker_properties.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                // The device discovered by both handlers gets a single Instance, the first
+                // handler winning on conflicting properties
+                HashMap::from([
+                    ("BRAND".to_string(), "onvif".to_string()),
+                    ("IP".to_string(), "10.0.0.1".to_string()),
+                    ("RTSP_URL".to_string(), "rtsp://a".to_string()),
+                ]),
+                HashMap::from([("BRAND".to_string(), "onvif".to_string())]),
+                HashMap::from([("BRAND".to_string(), "custom".to_string())]),
+            ]
+        );
+        assert_eq!(req.current_devices(false).await.len(), 3);
+        assert_eq!(req.state().await.handler_name, "onvif,custom");
+    }
+
+    #[tokio::test]
+    async fn test_dh_request_impl_get_instances_with_broker_property_templates() {
+        let device = |id: &str, properties: &[(&str, &str)]| {
+            Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: id.to_owned(),
+                properties: properties
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: None,
+            }))
+        };
+        let (_, notifier) = watch::channel(vec![
+            device("camera_a", &[("ONVIF_IP", "10.0.0.1")]),
+            device("camera_b", &[("ONVIF_MAC", "00:11:22:33:44:55")]),
+        ]);
+        let (cdi_notifier, mut cdi_receiver) = watch::channel(Default::default());
+        let req = DHRequestImpl {
+            endpoints: RwLock::new(vec![(0, notifier)]),
+            notifier: cdi_notifier,
+            key: "my_config".to_owned(),
+            handlers: vec![mock_handler_info()],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: InstanceNamingStrategy::IdBased,
+            kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            termination_notifier: Arc::new(Notify::new()),
+        };
+        req.set_broker_property_templates(HashMap::from([(
+            "CAMERA_URL".to_owned(),
+            "rtsp://${ONVIF_IP}/stream".to_owned(),
+        )]))
+        .await;
+
+        let instances = req.get_instances().await.unwrap();
+        assert_eq!(instances.len(), 2);
+        let instance = |property: &str| {
+            instances
+                .iter()
+                .find(|i| i.spec.broker_properties.contains_key(property))
+                .unwrap()
+        };
+        assert_eq!(
+            instance("ONVIF_IP").spec.broker_properties,
+            HashMap::from([
+                ("ONVIF_IP".to_owned(), "10.0.0.1".to_owned()),
+                ("CAMERA_URL".to_owned(), "rtsp://10.0.0.1/stream".to_owned()),
+            ])
+        );
+        assert_eq!(instance("ONVIF_IP").metadata.annotations, None);
+        // The device lacking the referenced property is kept without it, with a warning
+        let warning =
+            "brokerPropertyTemplates' CAMERA_URL references undefined device property ONVIF_IP";
+        assert_eq!(
+            instance("ONVIF_MAC").spec.broker_properties,
+            HashMap::from([("ONVIF_MAC".to_owned(), "00:11:22:33:44:55".to_owned())])
+        );
+        assert_eq!(
+            instance("ONVIF_MAC").metadata.annotations,
+            Some(BTreeMap::from([(
+                AKRI_LAST_WARNING_ANNOTATION_NAME.to_owned(),
+                warning.to_owned()
+            )]))
+        );
+
+        // The resolved template and the warning are set in the devices' environment too
+        assert!(cdi_receiver.has_changed().unwrap());
+        let kind = cdi_receiver.borrow_and_update().clone();
+        assert_eq!(kind.devices.len(), 2);
+        let env: Vec<_> = kind
+            .devices
+            .iter()
+            .flat_map(|d| d.container_edits.env.iter())
+            .collect();
+        assert!(env.contains(&&"CAMERA_URL=rtsp://10.0.0.1/stream".to_owned()));
+        assert!(env.contains(&&format!("{}={}", AKRI_LAST_WARNING_ENV_NAME, warning)));
+    }
+
     #[tokio::test]
     async fn test_dh_request_impl_watch_devices() {
         let (notifier, mut n_rec) = watch::channel(Default::default());
         let (dh_send, dh_rec) = watch::channel(Default::default());
         let req = Arc::new(DHRequestImpl {
-            endpoints: RwLock::new(vec![dh_rec]),
+            endpoints: RwLock::new(vec![(0, dh_rec)]),
             notifier,
             key: "my_config".to_owned(),
-            handler_name: "mock_handler".to_string(),
-            details: "discovery details".to_string(),
-            properties: vec![DiscoveryProperty {
-                name: "property_1".to_string(),
-                value: Some("value_1".to_string()),
-                value_from: None,
+            handlers: vec![DiscoveryHandlerInfo {
+                name: "mock_handler".to_string(),
+                discovery_details: "discovery details".to_string(),
+                discovery_properties: Some(vec![DiscoveryProperty {
+                    name: "property_1".to_string(),
+                    value: Some("value_1".to_string()),
+                    value_from: None,
+                }]),
             }],
             extra_device_properties: RwLock::new(HashMap::from([(
                 "MY_EXTRA_KEY".to_owned(),
                 "value".to_owned(),
             )])),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
             kube_client: Arc::new(MockDiscoveryManagerKubeInterface::new()),
             termination_notifier: Arc::new(Notify::new()),
         });
@@ -718,6 +1629,8 @@ mod tests {
             properties: HashMap::from([("ENV_KEY".to_owned(), "env_value".to_owned())]),
             mounts: vec![],
             device_specs: vec![],
+            last_warning: None,
+            error: None,
         }));
         dh_send.send(vec![new_device.clone()]).unwrap();
 
@@ -726,6 +1639,12 @@ mod tests {
             n_rec.borrow_and_update().devices.clone(),
             vec![new_device.as_ref().clone().into()]
         );
+        assert_eq!(
+            DISCOVERY_DEVICES_FOUND_METRIC
+                .with_label_values(&["my_config"])
+                .get(),
+            1
+        );
 
         let mut new_dh = MockDiscoveryHandlerEndpoint::new();
         let new_dh_senders = Arc::new(std::sync::Mutex::new(vec![]));
@@ -754,6 +1673,13 @@ mod tests {
         assert!(new_dh_sen.send(Arc::new(new_dh)).is_ok());
         tokio::time::sleep(Duration::from_millis(500)).await;
         assert_eq!(req.endpoints.read().await.len(), 2);
+        // The time the new Discovery Handler took to answer is recorded for the Configuration
+        assert!(
+            DISCOVERY_RESPONSE_TIME_METRIC
+                .with_label_values(&["my_config"])
+                .get_sample_count()
+                >= 1
+        );
         new_dh_senders.lock().unwrap().pop();
         tokio::time::sleep(Duration::from_millis(500)).await;
         assert_eq!(req.endpoints.read().await.len(), 1);
@@ -837,6 +1763,119 @@ mod tests {
         assert!(!dh_reg.handlers.read().await.contains_key("mock_handler"))
     }
 
+    #[tokio::test]
+    async fn test_dh_reg_backend_liveness() {
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        let (configuration_notifier, _) = mpsc::channel(2);
+        let dh_reg = DHRegistryImpl::new(
+            Arc::new(MockDiscoveryManagerKubeInterface::new()),
+            cdi_notifier,
+            configuration_notifier,
+        );
+        for uid in ["mock_handler_1", "mock_handler_2"] {
+            let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+            endpoint.expect_get_name().return_const("mock_handler");
+            endpoint.expect_get_uid().return_const(uid);
+            endpoint
+                .expect_closed()
+                .returning(|| Box::pin(futures::future::pending()));
+            dh_reg.register_endpoint(Arc::new(endpoint)).await;
+        }
+        assert_eq!(dh_reg.backend_down("mock_handler").await, None);
+        assert_eq!(dh_reg.backend_down("unknown_handler").await, None);
+        // Unregistered endpoints cannot report
+        assert!(
+            !dh_reg
+                .set_backend_liveness("unknown_handler", false, "unreachable")
+                .await
+        );
+
+        // The backend is only down once all the endpoints report it down
+        assert!(
+            dh_reg
+                .set_backend_liveness("mock_handler_1", false, "unreachable")
+                .await
+        );
+        assert_eq!(dh_reg.backend_down("mock_handler").await, None);
+        assert!(
+            dh_reg
+                .set_backend_liveness("mock_handler_2", false, "unreachable")
+                .await
+        );
+        assert_eq!(
+            dh_reg.backend_down("mock_handler").await,
+            Some("unreachable".to_string())
+        );
+
+        assert!(
+            dh_reg
+                .set_backend_liveness("mock_handler_2", true, "")
+                .await
+        );
+        assert_eq!(dh_reg.backend_down("mock_handler").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_dh_reg_state() {
+        let (cdi_notifier, _) = watch::channel(Default::default());
+        let (configuration_notifier, _) = mpsc::channel(2);
+        let kube_client = Arc::new(MockDiscoveryManagerKubeInterface::new());
+        let dh_reg = DHRegistryImpl::new(kube_client.clone(), cdi_notifier, configuration_notifier);
+        for uid in ["mock_handler_2", "mock_handler_1"] {
+            let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+            endpoint.expect_get_name().return_const("mock_handler");
+            endpoint.expect_get_uid().return_const(uid);
+            endpoint
+                .expect_closed()
+                .returning(|| Box::pin(futures::future::pending()));
+            dh_reg.register_endpoint(Arc::new(endpoint)).await;
+        }
+        let (_devices_sender, devices) =
+            watch::channel(vec![Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: "device".to_string(),
+                properties: Default::default(),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: None,
+            }))]);
+        let (req_not, _) = watch::channel(Default::default());
+        let request = Arc::new(DHRequestImpl {
+            endpoints: RwLock::new(vec![(0, devices)]),
+            notifier: req_not,
+            key: "my-config".to_owned(),
+            handlers: vec![mock_handler_info()],
+            extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
+            kube_client,
+            termination_notifier: Arc::new(Notify::new()),
+        });
+        dh_reg
+            .requests
+            .write()
+            .await
+            .insert("my-config".to_string(), request);
+
+        assert_eq!(
+            dh_reg.state().await,
+            RegistryState {
+                handlers: BTreeMap::from([(
+                    "mock_handler".to_string(),
+                    vec!["mock_handler_1".to_string(), "mock_handler_2".to_string()]
+                )]),
+                requests: BTreeMap::from([(
+                    "my-config".to_string(),
+                    RequestState {
+                        handler_name: "mock_handler".to_string(),
+                        endpoints: 1,
+                        devices: 1,
+                    }
+                )]),
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_dh_reg_get_terminate_request() {
         let (cdi_notifier, _) = watch::channel(Default::default());
@@ -848,10 +1887,10 @@ mod tests {
             endpoints: Default::default(),
             notifier: req_not,
             key: "my-config".to_owned(),
-            handler_name: Default::default(),
-            details: Default::default(),
-            properties: Default::default(),
+            handlers: vec![mock_handler_info()],
             extra_device_properties: Default::default(),
+            broker_property_templates: Default::default(),
+            naming_strategy: Default::default(),
             kube_client,
             termination_notifier: Arc::new(Notify::new()),
         });
@@ -888,10 +1927,9 @@ mod tests {
         assert!(dh_reg
             .new_request(
                 "my-config",
-                "mock_handler",
-                "discovery details",
-                &[],
+                &[mock_handler_info()],
                 HashMap::from([]),
+                Default::default(),
                 "namespace"
             )
             .await
@@ -941,10 +1979,9 @@ mod tests {
         assert!(dh_reg
             .new_request(
                 "my-config",
-                "mock_handler",
-                "discovery details",
-                &[],
+                &[mock_handler_info()],
                 HashMap::from([]),
+                Default::default(),
                 "namespace"
             )
             .await
@@ -963,6 +2000,8 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                last_warning: None,
+                error: None,
             }))])
             .unwrap();
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -981,6 +2020,8 @@ mod tests {
                 properties: Default::default(),
                 mounts: Default::default(),
                 device_specs: Default::default(),
+                last_warning: None,
+                error: None,
             }))])
             .unwrap();
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -995,7 +2036,10 @@ mod tests {
                 "akri.sh/my-config".to_owned(),
                 Kind {
                     kind: "akri.sh/my-config".to_owned(),
-                    annotations: Default::default(),
+                    annotations: HashMap::from([(
+                        crate::device_manager::cdi_spec::CDI_KIND_NAMESPACE_ANNOTATION.to_owned(),
+                        "namespace".to_owned(),
+                    )]),
                     container_edits: vec![ContainerEdit::default()],
                     devices: vec![
                         crate::device_manager::cdi::Device {
@@ -1026,7 +2070,10 @@ mod tests {
                 "akri.sh/my-config".to_owned(),
                 Kind {
                     kind: "akri.sh/my-config".to_owned(),
-                    annotations: Default::default(),
+                    annotations: HashMap::from([(
+                        crate::device_manager::cdi_spec::CDI_KIND_NAMESPACE_ANNOTATION.to_owned(),
+                        "namespace".to_owned(),
+                    )]),
                     container_edits: vec![Default::default()],
                     devices: vec![crate::device_manager::cdi::Device {
                         name: "cb2ad7".to_owned(),
@@ -1046,4 +2093,79 @@ mod tests {
         );
         assert!(cdi_rec.borrow_and_update().clone().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_dh_reg_new_request_late_handler() {
+        let (cdi_notifier, _cdi_rec) = watch::channel(Default::default());
+        let (configuration_notifier, _config_rec) = mpsc::channel(2);
+        let kube_client = Arc::new(MockDiscoveryManagerKubeInterface::new());
+        let dh_reg = DHRegistryImpl::new(kube_client, cdi_notifier, configuration_notifier);
+        let dh_infos = [
+            mock_handler_info(),
+            DiscoveryHandlerInfo {
+                name: "other_handler".to_string(),
+                ..mock_handler_info()
+            },
+        ];
+
+        // None of the handlers of the request are registered
+        assert!(dh_reg
+            .new_request(
+                "my-config",
+                &dh_infos,
+                HashMap::new(),
+                Default::default(),
+                "namespace"
+            )
+            .await
+            .is_err_and(|e| {
+                matches!(e,
+                    DiscoveryError::NoHandler(s) if s == *"mock_handler, other_handler"
+                )
+            }));
+
+        let dev_senders = Arc::new(std::sync::Mutex::new(vec![]));
+        let make_endpoint = |name: &'static str| {
+            let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+            let local_senders = dev_senders.clone();
+            endpoint.expect_get_name().return_const(name);
+            endpoint
+                .expect_get_uid()
+                .return_const(format!("{}_local", name));
+            endpoint
+                .expect_closed()
+                .returning(|| Box::pin(std::future::pending()));
+            endpoint.expect_is_closed().return_const(false);
+            endpoint.expect_query().returning(move |s, _| {
+                local_senders.lock().unwrap().push(s);
+                async { Ok(()) }.boxed()
+            });
+            Arc::new(endpoint)
+        };
+
+        // The request starts with the handlers that are registered...
+        dh_reg
+            .register_endpoint(make_endpoint("mock_handler"))
+            .await;
+        assert!(dh_reg
+            .new_request(
+                "my-config",
+                &dh_infos,
+                HashMap::new(),
+                Default::default(),
+                "namespace"
+            )
+            .await
+            .is_ok());
+        assert_eq!(dev_senders.lock().unwrap().len(), 1);
+
+        // ...and is sent to the other ones once they register
+        dh_reg
+            .register_endpoint(make_endpoint("other_handler"))
+            .await;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(dev_senders.lock().unwrap().len(), 2);
+
+        dh_reg.terminate_request("my-config").await;
+    }
 }
@@ -6,8 +6,9 @@ use akri_discovery_utils::discovery::{
 };
 use akri_shared::os::env_var::{ActualEnvVarQuery, EnvVarQuery};
 use async_trait::async_trait;
+use futures::Stream;
 use tokio::{select, sync::watch};
-use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tokio_stream::StreamExt;
 use tonic::IntoRequest;
 
 /// Label of environment variable that, when set, enables the embedded debug echo discovery handler
@@ -16,7 +17,8 @@ pub const ENABLE_DEBUG_ECHO_LABEL: &str = "ENABLE_DEBUG_ECHO";
 
 use super::{
     discovery_handler_registry::{
-        DiscoveredDevice, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
+        record_discovery_duration, record_probe_latency, DiscoveredDevice,
+        DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
     },
     DiscoveryError,
 };
@@ -34,7 +36,7 @@ impl EmbeddedHandlerEndpoint {
         node_name: String,
         shared: bool,
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
-        mut stream: ReceiverStream<Result<DiscoverResponse, tonic::Status>>,
+        mut stream: impl Stream<Item = Result<DiscoverResponse, tonic::Status>> + Unpin,
     ) {
         loop {
             let msg = select! {
@@ -73,8 +75,12 @@ impl DiscoveryHandlerEndpoint for EmbeddedHandlerEndpoint {
         sender: watch::Sender<Vec<Arc<DiscoveredDevice>>>,
         query_body: DiscoverRequest,
     ) -> Result<(), DiscoveryError> {
+        let started = std::time::Instant::now();
         let stream = match self.handler.discover(query_body.into_request()).await {
-            Ok(r) => r.into_inner(),
+            Ok(r) => record_probe_latency(
+                &self.name,
+                record_discovery_duration(&self.name, started, r.into_inner()),
+            ),
             Err(e) => {
                 match e.code() {
                     tonic::Code::InvalidArgument => {
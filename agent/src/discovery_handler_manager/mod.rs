@@ -28,6 +28,9 @@ pub enum DiscoveryError {
     #[error("discoveryProperties' referenced {0} not found")]
     UnsolvableProperty(&'static str),
 
+    #[error("brokerPropertyTemplates' {0} references undefined device property {1}")]
+    UndefinedTemplateReference(String, String),
+
     #[error(transparent)]
     KubeError(#[from] kube::Error),
 
@@ -1,26 +1,113 @@
-use std::{convert::TryFrom, pin::Pin, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use akri_discovery_utils::discovery::v0::{
     discovery_handler_client::DiscoveryHandlerClient,
     register_discovery_handler_request::EndpointType, registration_server::Registration,
     DiscoverRequest, DiscoverResponse, Empty, RegisterDiscoveryHandlerRequest,
+    ReportBackendLivenessRequest,
+};
+use akri_shared::{
+    os::env_var::{ActualEnvVarQuery, EnvVarQuery},
+    uds::unix_stream,
 };
-use akri_shared::uds::unix_stream;
 use async_trait::async_trait;
 use futures::{Stream, StreamExt, TryFutureExt};
-use tokio::{select, sync::watch};
+use itertools::Itertools;
+use tokio::{select, sync::watch, time::Instant};
 use tokio_stream::StreamExt as _;
 use tonic::{transport::Channel, Request, Response, Status};
 
-use crate::util::stopper::Stopper;
+use crate::util::{
+    metrics::{DISCOVERY_HANDLER_REREGISTRATIONS_METRIC, REGISTERED_DISCOVERY_HANDLERS_METRIC},
+    stopper::Stopper,
+};
 
 use super::{
     discovery_handler_registry::{
-        DiscoveredDevice, DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
+        record_discovery_duration, record_probe_latency, DiscoveredDevice,
+        DiscoveryHandlerEndpoint, DiscoveryHandlerRegistry,
     },
     DiscoveryError,
 };
 
+/// Name of the environment variable that sets the window (in milliseconds) during which the Agent
+/// retries connecting to a Discovery Handler before considering it offline
+pub const DISCOVERY_HANDLER_CONNECT_TIMEOUT_MS_LABEL: &str = "DISCOVERY_HANDLER_CONNECT_TIMEOUT_MS";
+/// Name of the environment variable that sets the delay (in milliseconds) between connection attempts
+pub const DISCOVERY_HANDLER_CONNECT_RETRY_INTERVAL_MS_LABEL: &str =
+    "DISCOVERY_HANDLER_CONNECT_RETRY_INTERVAL_MS";
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+/// Name of the environment variable listing (comma separated) the names of the Discovery Handlers allowed
+/// to register with the Agent, all are allowed if unset or empty
+pub const ALLOWED_DISCOVERY_HANDLERS_LABEL: &str = "ALLOWED_DISCOVERY_HANDLERS";
+/// How long the registration server waits for the directory of its socket to be usable, e.g. while
+/// its volume is still being mounted, before giving up
+const SOCKET_DIRECTORY_TIMEOUT: Duration = Duration::from_secs(30);
+const SOCKET_DIRECTORY_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+/// How long the uid of a closed endpoint is remembered, a Discovery Handler registering it again within
+/// this window (e.g. after restarting) counts as a re-registration
+const REREGISTRATION_WINDOW: Duration = Duration::from_secs(600);
+
+/// Bounds how long the Agent keeps trying to connect to a Discovery Handler, so that a handler
+/// that is slow to start serving after registering does not get considered offline right away.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectRetry {
+    pub timeout: Duration,
+    pub retry_interval: Duration,
+}
+
+impl Default for ConnectRetry {
+    fn default() -> Self {
+        ConnectRetry {
+            timeout: DEFAULT_CONNECT_TIMEOUT,
+            retry_interval: DEFAULT_CONNECT_RETRY_INTERVAL,
+        }
+    }
+}
+
+impl ConnectRetry {
+    /// Gets connection retry settings from the environment, using defaults for unset or invalid values
+    pub fn from_env(env_var_query: &impl EnvVarQuery) -> Self {
+        let default = ConnectRetry::default();
+        let get_duration = |label: &str| {
+            env_var_query
+                .get_env_var(label)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_millis)
+        };
+        ConnectRetry {
+            timeout: get_duration(DISCOVERY_HANDLER_CONNECT_TIMEOUT_MS_LABEL)
+                .unwrap_or(default.timeout),
+            retry_interval: get_duration(DISCOVERY_HANDLER_CONNECT_RETRY_INTERVAL_MS_LABEL)
+                .filter(|interval| !interval.is_zero())
+                .unwrap_or(default.retry_interval),
+        }
+    }
+}
+
+/// Gets the names of the Discovery Handlers allowed to register from the environment, `None` meaning that
+/// all are allowed
+pub fn get_allowed_discovery_handlers(env_var_query: &impl EnvVarQuery) -> Option<HashSet<String>> {
+    let allowed: HashSet<String> = env_var_query
+        .get_env_var(ALLOWED_DISCOVERY_HANDLERS_LABEL)
+        .ok()?
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!allowed.is_empty()).then_some(allowed)
+}
+
 struct NetworkEndpoint {
     name: String,
     endpoint: String,
@@ -28,10 +115,15 @@ struct NetworkEndpoint {
     stopped: Stopper,
     shared: bool,
     node_name: String,
+    connect_retry: ConnectRetry,
 }
 
 impl NetworkEndpoint {
-    fn new(req: RegisterDiscoveryHandlerRequest, node_name: String) -> Self {
+    fn new(
+        req: RegisterDiscoveryHandlerRequest,
+        node_name: String,
+        connect_retry: ConnectRetry,
+    ) -> Self {
         NetworkEndpoint {
             name: req.name,
             endpoint: req.endpoint,
@@ -39,6 +131,33 @@ impl NetworkEndpoint {
             shared: req.shared,
             endpoint_type: EndpointType::try_from(req.endpoint_type).unwrap(),
             node_name,
+            connect_retry,
+        }
+    }
+
+    /// Connects to the Discovery Handler, retrying until the configured timeout elapses
+    async fn connect(&self) -> Result<DiscoveryHandlerClient<Channel>, anyhow::Error> {
+        let deadline = Instant::now() + self.connect_retry.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let error = match tokio::time::timeout(remaining, self.get_client()).await {
+                Ok(Ok(client)) => return Ok(client),
+                Ok(Err(e)) => anyhow::Error::from(e),
+                Err(_) => anyhow::anyhow!(
+                    "connection timed out after {:?}",
+                    self.connect_retry.timeout
+                ),
+            };
+            if Instant::now() + self.connect_retry.retry_interval >= deadline {
+                return Err(error);
+            }
+            trace!(
+                "NetworkEndpoint::connect - failed to connect to {}, retrying in {:?}: {}",
+                self.get_uid(),
+                self.connect_retry.retry_interval,
+                error
+            );
+            tokio::time::sleep(self.connect_retry.retry_interval).await;
         }
     }
 
@@ -111,14 +230,22 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
         if self.stopped.is_stopped() {
             return Err(DiscoveryError::UnavailableDiscoveryHandler(self.get_uid()));
         }
-        let stream = match self.get_client().await {
+        let stream = match self.connect().await {
             Ok(mut discovery_handler_client) => {
                 trace!(
                     "NetworkEndpoint::query - connecting to external {} discovery handler over network",
                     self.name
                 );
+                let started = std::time::Instant::now();
                 match discovery_handler_client.discover(query_body).await {
-                    Ok(device_update_receiver) => device_update_receiver.into_inner(),
+                    Ok(device_update_receiver) => record_probe_latency(
+                        &self.name,
+                        record_discovery_duration(
+                            &self.name,
+                            started,
+                            device_update_receiver.into_inner(),
+                        ),
+                    ),
                     Err(e) => {
                         match e.code() {
                             tonic::Code::InvalidArgument => {
@@ -158,7 +285,7 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
         self.name.to_owned()
     }
     fn get_uid(&self) -> String {
-        format!("{}@{}", self.name, self.endpoint)
+        endpoint_uid(&self.name, &self.endpoint)
     }
 
     async fn closed(&self) {
@@ -169,9 +296,82 @@ impl DiscoveryHandlerEndpoint for NetworkEndpoint {
     }
 }
 
+/// Uid of the endpoint a Discovery Handler registered
+fn endpoint_uid(name: &str, endpoint: &str) -> String {
+    format!("{}@{}", name, endpoint)
+}
+
+/// Versions of the discovery API this agent can talk with
+const SUPPORTED_DISCOVERY_API_VERSIONS: &[&str] = &[akri_discovery_utils::DISCOVERY_API_VERSION];
+
+/// Checks that the discovery API version of a registering Discovery Handler is supported by this agent.
+/// Discovery Handlers that do not set a version predate version negotiation and implement `v0`.
+fn check_api_version(req: &RegisterDiscoveryHandlerRequest) -> Result<(), Status> {
+    let version = match req.api_version.as_str() {
+        "" => "v0",
+        v => v,
+    };
+    if SUPPORTED_DISCOVERY_API_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(Status::failed_precondition(format!(
+            "Discovery Handler {} uses unsupported discovery API version {}, this agent supports {:?}",
+            req.name, version, SUPPORTED_DISCOVERY_API_VERSIONS
+        )))
+    }
+}
+
+/// Checks that the endpoint of a Discovery Handler registering over the network is a valid URI, IPv6 hosts must be
+/// enclosed in brackets (e.g. `http://[fd00::1]:10000`).
+fn check_network_endpoint(req: &RegisterDiscoveryHandlerRequest) -> Result<(), Status> {
+    if req.endpoint_type != EndpointType::Network as i32 {
+        return Ok(());
+    }
+    match req.endpoint.parse::<tonic::transport::Uri>() {
+        Ok(uri) if uri.host().is_some() => Ok(()),
+        _ => Err(Status::invalid_argument(format!(
+            "Discovery Handler {} registered with invalid network endpoint {}",
+            req.name, req.endpoint
+        ))),
+    }
+}
+
+/// Checks that a registering Discovery Handler is on the allow-list of this agent, if any
+fn check_allowed(
+    req: &RegisterDiscoveryHandlerRequest,
+    allowed_handlers: Option<&HashSet<String>>,
+) -> Result<(), Status> {
+    match allowed_handlers {
+        Some(allowed) if !allowed.contains(&req.name) => {
+            Err(Status::permission_denied(format!(
+                "Discovery Handler {} is not allowed to register with this agent, allowed Discovery Handlers are {:?}",
+                req.name,
+                allowed.iter().sorted().collect::<Vec<_>>()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Kind of a Discovery Handler endpoint, as reported in metrics
+fn endpoint_kind(endpoint_type: EndpointType) -> &'static str {
+    match endpoint_type {
+        EndpointType::Uds => "uds",
+        EndpointType::Network => "network",
+    }
+}
+
 struct RegistrationEndpoint {
     inner: Arc<dyn DiscoveryHandlerRegistry>,
     node_name: String,
+    connect_retry: ConnectRetry,
+    // Uids of the endpoints that registered, used to detect re-registrations, with the time their
+    // endpoint closed (None while live). Closed ones are forgotten after the re-registration window.
+    registered_uids: Arc<Mutex<HashMap<String, Option<Instant>>>>,
+    // Currently registered endpoint of each uid, a re-registration replaces the previous endpoint
+    live_endpoints: Arc<Mutex<HashMap<String, Arc<NetworkEndpoint>>>>,
+    // Names of the Discovery Handlers allowed to register, all are if unset
+    allowed_handlers: Option<HashSet<String>>,
 }
 #[async_trait]
 impl Registration for RegistrationEndpoint {
@@ -180,13 +380,122 @@ impl Registration for RegistrationEndpoint {
         request: Request<RegisterDiscoveryHandlerRequest>,
     ) -> Result<Response<Empty>, Status> {
         let req = request.into_inner();
-        self.inner
-            .register_endpoint(Arc::new(NetworkEndpoint::new(req, self.node_name.clone())))
-            .await;
+        if let Err(e) = check_allowed(&req, self.allowed_handlers.as_ref())
+            .and_then(|_| check_api_version(&req))
+            .and_then(|_| check_network_endpoint(&req))
+        {
+            error!("register_discovery_handler - {}", e.message());
+            return Err(e);
+        }
+        let endpoint = Arc::new(NetworkEndpoint::new(
+            req,
+            self.node_name.clone(),
+            self.connect_retry.clone(),
+        ));
+        {
+            let mut registered_uids = self.registered_uids.lock().unwrap();
+            let now = Instant::now();
+            registered_uids.retain(|_, closed| {
+                !closed.is_some_and(|closed| now.duration_since(closed) >= REREGISTRATION_WINDOW)
+            });
+            if registered_uids.insert(endpoint.get_uid(), None).is_some() {
+                DISCOVERY_HANDLER_REREGISTRATIONS_METRIC.inc();
+            }
+        }
+        let registered_metric = REGISTERED_DISCOVERY_HANDLERS_METRIC
+            .with_label_values(&[&endpoint.name, endpoint_kind(endpoint.endpoint_type)]);
+        // An endpoint replacing a live one of the same uid is already counted
+        if self
+            .live_endpoints
+            .lock()
+            .unwrap()
+            .insert(endpoint.get_uid(), endpoint.clone())
+            .is_none()
+        {
+            registered_metric.inc();
+        }
+        let local_endpoint = endpoint.clone();
+        let live_endpoints = self.live_endpoints.clone();
+        let registered_uids = self.registered_uids.clone();
+        tokio::spawn(async move {
+            local_endpoint.closed().await;
+            let mut live_endpoints = live_endpoints.lock().unwrap();
+            let uid = local_endpoint.get_uid();
+            if live_endpoints
+                .get(&uid)
+                .is_some_and(|live| Arc::ptr_eq(live, &local_endpoint))
+            {
+                live_endpoints.remove(&uid);
+                registered_uids
+                    .lock()
+                    .unwrap()
+                    .insert(uid, Some(Instant::now()));
+                registered_metric.dec();
+            }
+        });
+        self.inner.register_endpoint(endpoint).await;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn report_backend_liveness(
+        &self,
+        request: Request<ReportBackendLivenessRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let req = request.into_inner();
+        let uid = endpoint_uid(&req.name, &req.endpoint);
+        if !self
+            .inner
+            .set_backend_liveness(&uid, req.alive, &req.message)
+            .await
+        {
+            return Err(Status::not_found(format!(
+                "Discovery Handler {} reported its backend liveness before registering",
+                uid
+            )));
+        }
         Ok(Response::new(Empty {}))
     }
 }
 
+/// Binds the registration socket, creating its directory if missing and retrying until `timeout`
+/// if the directory is not writable yet.
+async fn bind_registration_socket(
+    socket_path: &str,
+    timeout: Duration,
+) -> Result<tokio::net::UnixListener, std::io::Error> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let bound = async {
+            if let Some(directory) = Path::new(socket_path).parent() {
+                tokio::fs::create_dir_all(directory).await?;
+            }
+            // Delete socket in case previously created/used
+            std::fs::remove_file(socket_path).unwrap_or(());
+            tokio::net::UnixListener::bind(socket_path)
+        }
+        .await;
+        match bound {
+            Ok(uds) => return Ok(uds),
+            Err(e) if Instant::now() < deadline => {
+                warn!(
+                    "bind_registration_socket - unable to bind to socket {} yet: {}",
+                    socket_path, e
+                );
+                tokio::time::sleep(SOCKET_DIRECTORY_RETRY_INTERVAL).await;
+            }
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "unable to bind to registration socket {}, check that its directory exists and is writable: {}",
+                        socket_path, e
+                    ),
+                ))
+            }
+        }
+    }
+}
+
 pub async fn run_registration_server(
     dh_registry: Arc<dyn DiscoveryHandlerRegistry>,
     socket_path: &str,
@@ -200,11 +509,8 @@ pub async fn run_registration_server(
 
     #[cfg(any(test, feature = "agent-full"))]
     super::embedded_handler::register_handlers(dh_registry.as_ref(), node_name.clone()).await;
-    // Delete socket in case previously created/used
-    std::fs::remove_file(socket_path).unwrap_or(());
     let incoming = {
-        let uds =
-            tokio::net::UnixListener::bind(socket_path).expect("Failed to bind to socket path");
+        let uds = bind_registration_socket(socket_path, SOCKET_DIRECTORY_TIMEOUT).await?;
 
         async_stream::stream! {
             loop {
@@ -219,6 +525,10 @@ pub async fn run_registration_server(
                 RegistrationEndpoint {
                     inner: dh_registry,
                     node_name,
+                    connect_retry: ConnectRetry::from_env(&ActualEnvVarQuery {}),
+                    registered_uids: Default::default(),
+                    live_endpoints: Default::default(),
+                    allowed_handlers: get_allowed_discovery_handlers(&ActualEnvVarQuery {}),
                 },
             ),
         )
@@ -236,10 +546,181 @@ pub async fn run_registration_server(
 mod tests {
     use std::time::Duration;
 
-    use akri_discovery_utils::discovery::v0::Device;
+    use akri_discovery_utils::discovery::{
+        mock_discovery_handler::{
+            get_mock_discovery_handler_dir_and_endpoint, run_mock_discovery_handler,
+        },
+        v0::Device,
+    };
+    use mockall::predicate::eq;
     use tokio::sync::mpsc;
 
     use super::*;
+    use crate::discovery_handler_manager::discovery_handler_registry::MockDiscoveryHandlerRegistry;
+    use crate::util::metrics::DISCOVERY_DURATION_METRIC;
+
+    fn make_registration_endpoint(registry: MockDiscoveryHandlerRegistry) -> RegistrationEndpoint {
+        RegistrationEndpoint {
+            inner: Arc::new(registry),
+            node_name: "node-a".to_string(),
+            connect_retry: Default::default(),
+            registered_uids: Default::default(),
+            live_endpoints: Default::default(),
+            allowed_handlers: None,
+        }
+    }
+
+    fn register_request(api_version: &str) -> RegisterDiscoveryHandlerRequest {
+        RegisterDiscoveryHandlerRequest {
+            name: "debugEcho".to_string(),
+            endpoint: "/tmp/debugEcho.sock".to_string(),
+            endpoint_type: EndpointType::Uds as i32,
+            shared: true,
+            api_version: api_version.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_compatible_version() {
+        for version in ["", akri_discovery_utils::DISCOVERY_API_VERSION] {
+            let mut registry = MockDiscoveryHandlerRegistry::new();
+            registry
+                .expect_register_endpoint()
+                .times(1)
+                .returning(|_| ());
+            let endpoint = make_registration_endpoint(registry);
+            assert!(endpoint
+                .register_discovery_handler(Request::new(register_request(version)))
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_incompatible_version() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_register_endpoint().never();
+        let endpoint = make_registration_endpoint(registry);
+        let status = endpoint
+            .register_discovery_handler(Request::new(register_request("v42")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(status.message().contains("v42"));
+    }
+
+    #[tokio::test]
+    async fn test_register_allowed_handlers() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry
+            .expect_register_endpoint()
+            .times(1)
+            .withf(|endpoint| endpoint.get_name() == "debugEcho")
+            .returning(|_| ());
+        let endpoint = RegistrationEndpoint {
+            allowed_handlers: Some(HashSet::from([
+                "debugEcho".to_string(),
+                "onvif".to_string(),
+            ])),
+            ..make_registration_endpoint(registry)
+        };
+        assert!(endpoint
+            .register_discovery_handler(Request::new(register_request("")))
+            .await
+            .is_ok());
+
+        let status = endpoint
+            .register_discovery_handler(Request::new(RegisterDiscoveryHandlerRequest {
+                name: "udev".to_string(),
+                ..register_request("")
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert_eq!(
+            status.message(),
+            "Discovery Handler udev is not allowed to register with this agent, allowed Discovery Handlers are [\"debugEcho\", \"onvif\"]"
+        );
+    }
+
+    #[test]
+    fn test_get_allowed_discovery_handlers() {
+        use akri_shared::os::env_var::MockEnvVarQuery;
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_allowed_discovery_handlers(&env), None);
+
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Ok(" , ".to_string()));
+        assert_eq!(get_allowed_discovery_handlers(&env), None);
+
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(ALLOWED_DISCOVERY_HANDLERS_LABEL))
+            .returning(|_| Ok("onvif, opcua,".to_string()));
+        assert_eq!(
+            get_allowed_discovery_handlers(&env),
+            Some(HashSet::from(["onvif".to_string(), "opcua".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_report_backend_liveness() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry
+            .expect_set_backend_liveness()
+            .with(
+                eq("debugEcho@/tmp/debugEcho.sock"),
+                eq(false),
+                eq("backend unreachable"),
+            )
+            .times(1)
+            .returning(|_, _, _| true);
+        registry
+            .expect_set_backend_liveness()
+            .with(eq("debugEcho@/tmp/other.sock"), eq(true), eq(""))
+            .times(1)
+            .returning(|_, _, _| false);
+        let endpoint = make_registration_endpoint(registry);
+        assert!(endpoint
+            .report_backend_liveness(Request::new(ReportBackendLivenessRequest {
+                name: "debugEcho".to_string(),
+                endpoint: "/tmp/debugEcho.sock".to_string(),
+                alive: false,
+                message: "backend unreachable".to_string(),
+            }))
+            .await
+            .is_ok());
+        let status = endpoint
+            .report_backend_liveness(Request::new(ReportBackendLivenessRequest {
+                name: "debugEcho".to_string(),
+                endpoint: "/tmp/other.sock".to_string(),
+                alive: true,
+                message: "".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn test_check_network_endpoint() {
+        let network_request = |endpoint: &str| RegisterDiscoveryHandlerRequest {
+            endpoint: endpoint.to_string(),
+            endpoint_type: EndpointType::Network as i32,
+            ..register_request("")
+        };
+        for endpoint in ["http://10.1.2.3:10000", "http://[fd00::1]:10000"] {
+            assert!(check_network_endpoint(&network_request(endpoint)).is_ok());
+        }
+        // Unbracketed IPv6 addresses cannot be told apart from their port
+        let status = check_network_endpoint(&network_request("http://fd00::1:10000")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        // Unix domain sockets are paths, not URIs
+        assert!(check_network_endpoint(&register_request("")).is_ok());
+    }
 
     #[tokio::test]
     async fn test_handle_stream_local() {
@@ -337,4 +818,287 @@ mod tests {
             .await
             .is_ok());
     }
+
+    #[tokio::test]
+    async fn test_register_metrics() {
+        let registered_endpoints = Arc::new(Mutex::new(Vec::new()));
+        let local_registered_endpoints = registered_endpoints.clone();
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry
+            .expect_register_endpoint()
+            .times(2)
+            .returning(move |endpoint| local_registered_endpoints.lock().unwrap().push(endpoint));
+        let endpoint = RegistrationEndpoint {
+            // Fail right away when querying the Discovery Handler, to close its endpoint
+            connect_retry: ConnectRetry {
+                timeout: Duration::ZERO,
+                retry_interval: DEFAULT_CONNECT_RETRY_INTERVAL,
+            },
+            ..make_registration_endpoint(registry)
+        };
+        let request = RegisterDiscoveryHandlerRequest {
+            name: "metricsHandler".to_string(),
+            endpoint: "/tmp/metricsHandler-absent.sock".to_string(),
+            ..register_request("")
+        };
+        let gauge =
+            REGISTERED_DISCOVERY_HANDLERS_METRIC.with_label_values(&["metricsHandler", "uds"]);
+        let reregistrations = DISCOVERY_HANDLER_REREGISTRATIONS_METRIC.get();
+
+        assert!(endpoint
+            .register_discovery_handler(Request::new(request.clone()))
+            .await
+            .is_ok());
+        assert_eq!(gauge.get(), 1);
+        assert_eq!(
+            DISCOVERY_HANDLER_REREGISTRATIONS_METRIC.get(),
+            reregistrations
+        );
+
+        // Registering the same endpoint again counts as a re-registration, replacing the registered endpoint
+        assert!(endpoint
+            .register_discovery_handler(Request::new(request))
+            .await
+            .is_ok());
+        assert_eq!(gauge.get(), 1);
+        assert!(DISCOVERY_HANDLER_REREGISTRATIONS_METRIC.get() > reregistrations);
+
+        // Endpoints that can no longer be reached are no longer counted
+        let endpoints = registered_endpoints.lock().unwrap().clone();
+        for registered in endpoints {
+            let (sender, _receiver) = watch::channel(Default::default());
+            assert!(registered
+                .query(sender, DiscoverRequest::default())
+                .await
+                .is_err());
+        }
+        assert!(tokio::time::timeout(Duration::from_millis(500), async {
+            while gauge.get() != 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .is_ok());
+        // The closed endpoint is remembered for the re-registration window
+        assert!(matches!(
+            endpoint
+                .registered_uids
+                .lock()
+                .unwrap()
+                .get("metricsHandler@/tmp/metricsHandler-absent.sock"),
+            Some(Some(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_registered_uids_pruned() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry
+            .expect_register_endpoint()
+            .times(2)
+            .returning(|_| ());
+        let endpoint = make_registration_endpoint(registry);
+        endpoint.registered_uids.lock().unwrap().extend([
+            (
+                "debugEcho@/tmp/closed.sock".to_string(),
+                Some(Instant::now()),
+            ),
+            ("debugEcho@/tmp/live.sock".to_string(), None),
+        ]);
+        tokio::time::advance(REREGISTRATION_WINDOW / 2).await;
+        assert!(endpoint
+            .register_discovery_handler(Request::new(register_request("")))
+            .await
+            .is_ok());
+        assert_eq!(endpoint.registered_uids.lock().unwrap().len(), 3);
+
+        // Closed endpoints are forgotten once the re-registration window elapsed, live ones are kept
+        tokio::time::advance(REREGISTRATION_WINDOW / 2).await;
+        assert!(endpoint
+            .register_discovery_handler(Request::new(register_request("")))
+            .await
+            .is_ok());
+        assert_eq!(
+            endpoint
+                .registered_uids
+                .lock()
+                .unwrap()
+                .keys()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec!["debugEcho@/tmp/debugEcho.sock", "debugEcho@/tmp/live.sock"]
+        );
+    }
+
+    #[test]
+    fn test_connect_retry_from_env() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_HANDLER_CONNECT_TIMEOUT_MS_LABEL))
+            .returning(|_| Ok("10000".to_string()));
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_HANDLER_CONNECT_RETRY_INTERVAL_MS_LABEL))
+            .returning(|_| Ok("invalid".to_string()));
+        assert_eq!(
+            ConnectRetry::from_env(&env),
+            ConnectRetry {
+                timeout: Duration::from_secs(10),
+                retry_interval: DEFAULT_CONNECT_RETRY_INTERVAL,
+            }
+        );
+    }
+
+    fn uds_endpoint(endpoint: &str, connect_retry: ConnectRetry) -> NetworkEndpoint {
+        NetworkEndpoint::new(
+            RegisterDiscoveryHandlerRequest {
+                endpoint: endpoint.to_string(),
+                ..register_request("")
+            },
+            "node-a".to_string(),
+            connect_retry,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_query_slow_starting_handler() {
+        let (dir, endpoint) = get_mock_discovery_handler_dir_and_endpoint("slow.sock");
+        let network_endpoint = uds_endpoint(
+            &endpoint,
+            ConnectRetry {
+                timeout: Duration::from_secs(5),
+                retry_interval: Duration::from_millis(100),
+            },
+        );
+        // The Discovery Handler only starts serving some time after the first connection attempt
+        let handler = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            run_mock_discovery_handler(
+                &dir,
+                &endpoint,
+                false,
+                vec![Device {
+                    id: "bar".to_string(),
+                    ..Default::default()
+                }],
+            )
+            .await
+        });
+
+        let (sender, mut receiver) = watch::channel(Default::default());
+        let start = Instant::now();
+        assert!(network_endpoint
+            .query(sender, DiscoverRequest::default())
+            .await
+            .is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert!(!network_endpoint.is_closed());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), receiver.changed())
+                .await
+                .is_ok()
+        );
+        assert_eq!(receiver.borrow().len(), 1);
+
+        network_endpoint.stopped.stop();
+        handler.await.unwrap().abort();
+    }
+
+    #[tokio::test]
+    async fn test_query_records_discovery_duration() {
+        let (dir, endpoint) = get_mock_discovery_handler_dir_and_endpoint("duration.sock");
+        let handler = run_mock_discovery_handler(
+            &dir,
+            &endpoint,
+            false,
+            vec![
+                Device {
+                    id: "foo".to_string(),
+                    ..Default::default()
+                },
+                Device {
+                    id: "bar".to_string(),
+                    ..Default::default()
+                },
+            ],
+        )
+        .await;
+        let network_endpoint = NetworkEndpoint::new(
+            RegisterDiscoveryHandlerRequest {
+                name: "durationHandler".to_string(),
+                endpoint: endpoint.to_string(),
+                ..register_request("")
+            },
+            "node-a".to_string(),
+            Default::default(),
+        );
+        let histogram = DISCOVERY_DURATION_METRIC.with_label_values(&["durationHandler"]);
+
+        let (sender, mut receiver) = watch::channel(Default::default());
+        assert!(network_endpoint
+            .query(sender, DiscoverRequest::default())
+            .await
+            .is_ok());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), receiver.changed())
+                .await
+                .is_ok()
+        );
+        assert_eq!(receiver.borrow().len(), 2);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert!(histogram.get_sample_sum() > 0.0);
+
+        network_endpoint.stopped.stop();
+        handler.abort();
+    }
+
+    #[tokio::test]
+    async fn test_query_handler_not_started_within_timeout() {
+        let (_, endpoint) = get_mock_discovery_handler_dir_and_endpoint("absent.sock");
+        let connect_retry = ConnectRetry {
+            timeout: Duration::from_millis(300),
+            retry_interval: Duration::from_millis(100),
+        };
+        let network_endpoint = uds_endpoint(&endpoint, connect_retry.clone());
+
+        let (sender, _receiver) = watch::channel(Default::default());
+        let start = Instant::now();
+        assert!(matches!(
+            network_endpoint
+                .query(sender, DiscoverRequest::default())
+                .await,
+            Err(DiscoveryError::UnavailableDiscoveryHandler(_))
+        ));
+        // Connection has been retried until the end of the window before giving up
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < connect_retry.timeout * 2);
+        assert!(network_endpoint.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_bind_registration_socket_creates_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("not-yet-mounted/agent-registration.sock");
+        let _uds = bind_registration_socket(socket_path.to_str().unwrap(), Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(socket_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_registration_socket_unusable_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        // The socket directory cannot be created where a file already is
+        std::fs::write(dir.path().join("file"), "").unwrap();
+        let socket_path = dir.path().join("file/agent-registration.sock");
+        let start = Instant::now();
+        let err =
+            bind_registration_socket(socket_path.to_str().unwrap(), Duration::from_millis(600))
+                .await
+                .unwrap_err();
+        // Binding has been retried until the end of the window before giving up
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert!(err
+            .to_string()
+            .contains("unable to bind to registration socket"));
+    }
 }
@@ -8,8 +8,11 @@ mod discovery_handler_manager;
 mod plugin_manager;
 mod util;
 
-use akri_shared::akri::{metrics::run_metrics_server, API_NAMESPACE};
-use log::{info, trace};
+use akri_shared::{
+    akri::{metrics::run_metrics_server, API_NAMESPACE},
+    os::env_var::ActualEnvVarQuery,
+};
+use log::{error, info, trace};
 use std::{
     collections::HashMap,
     env,
@@ -36,7 +39,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     let mut tasks = Vec::new();
     let node_name = env::var("AGENT_NODE_NAME")?;
 
-    {
+    if util::local_mode::is_enabled(&ActualEnvVarQuery {}) {
+        info!("{} Agent running in local mode", API_NAMESPACE);
+        return util::local_mode::run(node_name, &ActualEnvVarQuery {}).await;
+    }
+
+    let (config_controller_context, device_plugin_manager) = {
         let kube_client = Arc::new(kube::Client::try_default().await?);
 
         // Start server for Prometheus metrics
@@ -61,13 +69,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
             .unwrap()
         }));
 
+        if let Some(directory) = device_manager::cdi_spec::cdi_spec_directory(&ActualEnvVarQuery {})
+        {
+            tasks.push(tokio::spawn(device_manager::cdi_spec::run_cdi_spec_writer(
+                directory,
+                device_notifier.clone(),
+            )));
+        }
+
         let im_device_manager = Arc::new(device_manager::InMemoryManager::new(device_notifier));
 
+        let unknown_device_usage_policy =
+            plugin_manager::device_plugin_instance_controller::UnknownDeviceUsagePolicy::from_env(
+                &ActualEnvVarQuery {},
+            )?;
         let device_plugin_manager = Arc::new(
             plugin_manager::device_plugin_instance_controller::DevicePluginManager::new(
                 node_name.clone(),
                 kube_client.clone(),
                 im_device_manager.clone(),
+            )
+            .with_list_and_watch_initial_delay(
+                plugin_manager::device_plugin_instance_controller::get_list_and_watch_initial_delay(
+                    &ActualEnvVarQuery {},
+                ),
+            )
+            .with_unknown_device_usage_policy(unknown_device_usage_policy)
+            .with_kubelet_registration_max_attempts(
+                plugin_manager::device_plugin_runner::get_kubelet_registration_max_attempts(
+                    &ActualEnvVarQuery {},
+                ),
+            )
+            .with_managed_finalizers(
+                util::discovery_configuration_controller::get_agent_managed_finalizers(
+                    &ActualEnvVarQuery {},
+                ),
+            )
+            .with_device_manifest_directory(
+                device_manager::device_manifest::device_manifest_directory(&ActualEnvVarQuery {}),
+            )
+            .with_device_usage_coalescing_window(
+                plugin_manager::device_plugin_instance_controller::get_device_usage_coalescing_window(
+                    &ActualEnvVarQuery {},
+                ),
+            )
+            .with_allocation_audit_events(
+                plugin_manager::device_plugin_instance_controller::get_allocation_audit_events(
+                    &ActualEnvVarQuery {},
+                )
+                .then_some(kube_client.clone() as _),
             ),
         );
 
@@ -78,7 +128,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
         tasks.push(device_plugin_controller_task);
 
         tasks.push(tokio::spawn(
-            plugin_manager::device_plugin_slot_reclaimer::start_reclaimer(device_plugin_manager),
+            plugin_manager::device_plugin_slot_reclaimer::start_reclaimer(
+                device_plugin_manager.clone(),
+            ),
         ));
 
         let config_controller_context = Arc::new(
@@ -88,19 +140,91 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
                 client: kube_client.clone(),
                 agent_identifier: node_name.clone(),
                 error_backoffs: Mutex::new(HashMap::new()),
+                instance_batching:
+                    util::discovery_configuration_controller::InstanceBatching::from_env(
+                        &ActualEnvVarQuery {},
+                    ),
+                discovery_export:
+                    util::discovery_configuration_controller::DiscoveryExport::from_env(
+                        &ActualEnvVarQuery {},
+                    ),
+                configuration_guards: Default::default(),
+                discovery_jitter:
+                    util::discovery_configuration_controller::DiscoveryJitter::from_env(
+                        &ActualEnvVarQuery {},
+                    ),
+                rediscover_tracker: Default::default(),
+                offline_instances: Default::default(),
+                configuration_warnings: Default::default(),
+                managed_finalizers:
+                    util::discovery_configuration_controller::get_agent_managed_finalizers(
+                        &ActualEnvVarQuery {},
+                    ),
             },
         );
 
+        if let Some(address) = util::debug_dump::debug_dump_address(&ActualEnvVarQuery {}) {
+            tasks.push(tokio::spawn(util::debug_dump::run_debug_dump_server(
+                config_controller_context.clone(),
+                address,
+            )));
+        }
+
+        if let Some(address) =
+            util::devices_endpoint::devices_endpoint_address(&ActualEnvVarQuery {})
+        {
+            tasks.push(tokio::spawn(
+                util::devices_endpoint::run_devices_endpoint_server(
+                    config_controller_context.clone(),
+                    address,
+                ),
+            ));
+        }
+
+        if let Some(port) = util::health::health_port(&ActualEnvVarQuery {}) {
+            let heartbeat = Arc::new(util::health::Heartbeat::default());
+            tasks.push(tokio::spawn(
+                util::discovery_configuration_controller::run_heartbeat(
+                    config_controller_context.clone(),
+                    heartbeat.clone(),
+                ),
+            ));
+            tasks.push(tokio::spawn(util::health::run_health_server(
+                heartbeat,
+                port,
+                util::health::get_heartbeat_timeout(&ActualEnvVarQuery {}),
+            )));
+        }
+
+        let local_config_controller_context = config_controller_context.clone();
         tasks.push(tokio::spawn(async {
             util::discovery_configuration_controller::start_controller(
-                config_controller_context,
+                local_config_controller_context,
                 config_notifier,
             )
             .await;
         }));
+        (config_controller_context, device_plugin_manager)
+    };
+
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        res = futures::future::try_join_all(tasks) => {
+            res?;
+        }
+        _ = terminate.recv() => {
+            info!("{} Agent received SIGTERM, draining device plugins", API_NAMESPACE);
+            device_plugin_manager.drain().await;
+            info!("{} Agent releasing deleted Configurations", API_NAMESPACE);
+            if let Err(e) = util::discovery_configuration_controller::release_deleted_configurations(
+                &config_controller_context,
+            )
+            .await
+            {
+                error!("Failed to release deleted Configurations: {}", e);
+            }
+        }
     }
-
-    futures::future::try_join_all(tasks).await?;
     info!("{} Agent end", API_NAMESPACE);
     Ok(())
 }
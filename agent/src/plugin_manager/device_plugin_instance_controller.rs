@@ -1,13 +1,31 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Display;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use akri_shared::{akri::instance::Instance, k8s::api::IntoApi};
+use akri_shared::{
+    akri::{
+        instance::{
+            device_usage::{DeviceUsageKind, NodeUsage},
+            is_quarantined, Instance,
+        },
+        AKRI_OVER_COMMITTED_ANNOTATION_NAME, AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME,
+        AKRI_RESOURCE_NAME_ANNOTATION_NAME,
+    },
+    k8s::{api::IntoApi, event},
+    os::env_var::{get_env_bool, EnvVarQuery},
+};
 use anyhow::Context;
 use async_trait::async_trait;
 use futures::StreamExt;
 use itertools::Itertools;
+use k8s_openapi::api::core::v1::Event;
 use kube::api::{Patch, PatchParams};
 use kube::core::{NotUsed, Object, ObjectMeta, TypeMeta};
 use kube::{Resource, ResourceExt};
@@ -15,21 +33,55 @@ use kube_runtime::controller::Action;
 use kube_runtime::reflector::Store;
 use kube_runtime::Controller;
 use thiserror::Error;
-use tokio::sync::{watch, Mutex, RwLock};
+use tokio::sync::{watch, Mutex, MutexGuard, RwLock, RwLockReadGuard};
 use tokio::task::JoinHandle;
 use tonic::Request;
 
-use crate::device_manager::{cdi, DeviceManager};
+use crate::device_manager::{cdi, device_manifest, DeviceManager};
 use crate::plugin_manager::v1beta1::ContainerAllocateResponse;
+use crate::util::metrics::{DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC, INSTANCE_SLOT_RESERVED_METRIC};
 use crate::util::stopper::Stopper;
 
 use super::device_plugin_runner::{
     serve_and_register_plugin, DeviceUsageStream, InternalDevicePlugin,
+    DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS,
 };
 use super::v1beta1::{AllocateRequest, AllocateResponse, ListAndWatchResponse};
 
 pub const DP_SLOT_PREFIX: &str = "akri.sh/";
 
+/// Waiting longer than this to acquire a device plugin slots lock gets logged as contention
+const LOCK_WAIT_WARNING_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Name of the environment variable that sets the delay (in milliseconds) before an Instance device plugin
+/// answers its first list_and_watch, to stagger the load of many plugins starting at once
+pub const LIST_AND_WATCH_INITIAL_DELAY_MS_LABEL: &str = "LIST_AND_WATCH_INITIAL_DELAY_MS";
+
+/// Gets the initial list_and_watch delay from the environment, no delay gets applied if unset or invalid
+pub fn get_list_and_watch_initial_delay(env_var_query: &impl EnvVarQuery) -> Duration {
+    env_var_query
+        .get_env_var(LIST_AND_WATCH_INITIAL_DELAY_MS_LABEL)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or_default()
+}
+
+/// Name of the environment variable that sets the window (in milliseconds) during which the device usage
+/// updates of an Instance are coalesced into a single write
+pub const DEVICE_USAGE_COALESCING_WINDOW_MS_LABEL: &str = "DEVICE_USAGE_COALESCING_WINDOW_MS";
+const DEFAULT_DEVICE_USAGE_COALESCING_WINDOW: Duration = Duration::from_millis(100);
+
+/// Gets the device usage coalescing window from the environment, defaults to 100ms if unset or invalid
+pub fn get_device_usage_coalescing_window(env_var_query: &impl EnvVarQuery) -> Duration {
+    env_var_query
+        .get_env_var(DEVICE_USAGE_COALESCING_WINDOW_MS_LABEL)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_DEVICE_USAGE_COALESCING_WINDOW)
+}
+
 #[derive(Error, Debug)]
 pub enum DevicePluginError {
     #[error("Slot already in use")]
@@ -55,7 +107,12 @@ pub enum DevicePluginError {
 enum DeviceUsage {
     Unused,
     Node(String),
-    Configuration { vdev: String, node: String },
+    Configuration {
+        vdev: String,
+        node: String,
+    },
+    /// Usage this agent doesn't understand (e.g. written by a newer agent), kept as is
+    Unknown(String),
 }
 
 impl DeviceUsage {
@@ -68,16 +125,20 @@ impl DeviceUsage {
     }
 }
 
+/// Written the same way as `NodeUsage`, in the legacy format, so that agents not reading the
+/// versioned format yet keep understanding them
 impl Display for DeviceUsage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DeviceUsage::Unused => write!(f, ""),
             DeviceUsage::Node(node) => write!(f, "{}", node),
             DeviceUsage::Configuration { vdev, node } => write!(f, "C:{}:{}", vdev, node),
+            DeviceUsage::Unknown(usage) => write!(f, "{}", usage),
         }
     }
 }
 
+/// Reads both the legacy and the versioned format of `NodeUsage`
 impl FromStr for DeviceUsage {
     type Err = DevicePluginError;
     fn from_str(val: &str) -> Result<Self, DevicePluginError> {
@@ -90,12 +151,258 @@ impl FromStr for DeviceUsage {
                     node: node.to_owned(),
                 }),
                 [node] => Ok(Self::Node(node.to_owned())),
+                // Later versions may add fields with colons in their values
+                [version, _, ..] if version.starts_with('v') => {
+                    let usage =
+                        NodeUsage::from_str(val).or(Err(DevicePluginError::UsageParseError))?;
+                    Ok(match usage.get_kind() {
+                        DeviceUsageKind::Free => Self::Unused,
+                        DeviceUsageKind::Instance => Self::Node(usage.get_node_name()),
+                        DeviceUsageKind::Configuration(vdev) => Self::Configuration {
+                            vdev,
+                            node: usage.get_node_name(),
+                        },
+                    })
+                }
                 _ => Err(DevicePluginError::UsageParseError),
             }
         }
     }
 }
 
+/// Target of the allocation audit log records, so that they can be filtered or routed apart
+const ALLOCATION_AUDIT_TARGET: &str = "akri::audit";
+
+/// Name of the environment variable that enables reporting every slot allocation and release as a
+/// Kubernetes Event on its Instance, in addition to the audit log record
+pub const ALLOCATION_AUDIT_EVENTS_LABEL: &str = "ALLOCATION_AUDIT_EVENTS";
+
+/// Gets whether allocations are reported as Kubernetes Events from the environment, they are not by default
+pub fn get_allocation_audit_events(env_var_query: &impl EnvVarQuery) -> bool {
+    get_env_bool(env_var_query, ALLOCATION_AUDIT_EVENTS_LABEL, false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum AllocationAuditAction {
+    Allocate,
+    Release,
+}
+
+/// Audit record of a slot allocation or release, logged as JSON under the `akri::audit` target.
+/// The kubelet doesn't tell device plugins which Pod an allocation is for, the Pod using a slot
+/// can be found from the slot's device id through the kubelet's pod resources API.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AllocationAuditRecord {
+    action: AllocationAuditAction,
+    node: String,
+    instance: String,
+    namespace: String,
+    // Device id of the slot, as known by the kubelet
+    slot: String,
+    // Virtual device of the Configuration the slot is allocated through, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    configuration_device: Option<String>,
+    timestamp: String,
+}
+
+impl AllocationAuditRecord {
+    fn new(
+        action: AllocationAuditAction,
+        instance: &str,
+        namespace: &str,
+        id: usize,
+        usage: &DeviceUsage,
+    ) -> Self {
+        let (node, configuration_device) = match usage {
+            DeviceUsage::Node(node) => (node.clone(), None),
+            DeviceUsage::Configuration { vdev, node } => (node.clone(), Some(vdev.clone())),
+            DeviceUsage::Unused | DeviceUsage::Unknown(_) => (String::new(), None),
+        };
+        AllocationAuditRecord {
+            action,
+            node,
+            instance: instance.to_owned(),
+            namespace: namespace.to_owned(),
+            slot: format!("{}-{}", instance, id),
+            configuration_device,
+            timestamp: k8s_openapi::chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    fn log(&self) {
+        match serde_json::to_string(self) {
+            Ok(record) => info!(target: ALLOCATION_AUDIT_TARGET, "{}", record),
+            Err(e) => error!("Failed to serialize allocation audit record: {}", e),
+        }
+    }
+
+    /// Normal Event on the Instance recording the allocation or release. It is named after the slot,
+    /// the action and the time so that every record gets its own Event.
+    fn event(&self) -> Event {
+        let (reason, verb) = match self.action {
+            AllocationAuditAction::Allocate => ("SlotAllocated", "allocated to"),
+            AllocationAuditAction::Release => ("SlotReleased", "released by"),
+        };
+        let message = match &self.configuration_device {
+            Some(vdev) => format!(
+                "Slot {} {} node {} through {}",
+                self.slot, verb, self.node, vdev
+            ),
+            None => format!("Slot {} {} node {}", self.slot, verb, self.node),
+        };
+        let mut event = event::create_component_instance_event(
+            event::AKRI_AGENT_EVENT_COMPONENT,
+            &self.instance,
+            &self.namespace,
+            event::EVENT_TYPE_NORMAL,
+            reason,
+            &message,
+        );
+        event.metadata.generate_name = None;
+        event.metadata.name = Some(format!(
+            "{}.{}.{}",
+            self.slot,
+            reason.to_lowercase(),
+            k8s_openapi::chrono::Utc::now().timestamp_millis()
+        ));
+        if let Some(source) = event.source.as_mut() {
+            source.host = Some(self.node.clone()).filter(|node| !node.is_empty());
+        }
+        event
+    }
+}
+
+/// Maximum number of reserved slots reported by the akri_instance_slot_reserved metric, to bound its cardinality
+const MAX_REPORTED_RESERVED_SLOTS: usize = 1000;
+
+/// Owning node of the slots reported by the akri_instance_slot_reserved metric, by Instance and slot.
+/// It is shared by the device plugins of the Instances, the slots of an Instance are no longer reported
+/// once its device plugin is stopped (e.g. the Instance got deleted).
+struct ReportedReservedSlots {
+    slots: std::sync::Mutex<HashMap<String, HashMap<String, String>>>,
+    max: usize,
+}
+
+impl Default for ReportedReservedSlots {
+    fn default() -> Self {
+        ReportedReservedSlots {
+            slots: Default::default(),
+            max: MAX_REPORTED_RESERVED_SLOTS,
+        }
+    }
+}
+
+impl ReportedReservedSlots {
+    /// Updates the akri_instance_slot_reserved metric with the reserved slots of the given Instance
+    fn report(&self, instance_name: &str, slots: &[DeviceUsage]) {
+        let reserved: HashMap<String, String> = slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, usage)| match usage {
+                DeviceUsage::Unused | DeviceUsage::Unknown(_) => None,
+                DeviceUsage::Node(node) | DeviceUsage::Configuration { node, .. } => {
+                    Some((slot.to_string(), node.to_owned()))
+                }
+            })
+            .collect();
+        let mut reported = self.slots.lock().unwrap();
+        let mut instance_reported = reported.remove(instance_name).unwrap_or_default();
+        instance_reported.retain(|slot, node| {
+            if reserved.get(slot) == Some(node) {
+                return true;
+            }
+            let _ = INSTANCE_SLOT_RESERVED_METRIC.remove_label_values(&[instance_name, slot, node]);
+            false
+        });
+        let mut count: usize =
+            reported.values().map(HashMap::len).sum::<usize>() + instance_reported.len();
+        let mut unreported = 0;
+        for (slot, node) in reserved {
+            if instance_reported.contains_key(&slot) {
+                continue;
+            }
+            if count >= self.max {
+                unreported += 1;
+                continue;
+            }
+            INSTANCE_SLOT_RESERVED_METRIC
+                .with_label_values(&[instance_name, &slot, &node])
+                .set(1);
+            instance_reported.insert(slot, node);
+            count += 1;
+        }
+        if !instance_reported.is_empty() {
+            reported.insert(instance_name.to_owned(), instance_reported);
+        }
+        if unreported > 0 {
+            warn!(
+                "{} reserved slots of Instance {} are not reported, akri_instance_slot_reserved is capped at {} slots",
+                unreported, instance_name, self.max
+            );
+        }
+    }
+
+    /// Stops reporting the reserved slots of the given Instance
+    fn remove(&self, instance_name: &str) {
+        if let Some(slots) = self.slots.lock().unwrap().remove(instance_name) {
+            for (slot, node) in slots {
+                let _ = INSTANCE_SLOT_RESERVED_METRIC.remove_label_values(&[
+                    instance_name,
+                    &slot,
+                    &node,
+                ]);
+            }
+        }
+    }
+}
+
+/// Name of the environment variable that sets how slots with an unknown device usage are handled,
+/// either `reserved` (default) or `free`
+pub const UNKNOWN_DEVICE_USAGE_POLICY_LABEL: &str = "UNKNOWN_DEVICE_USAGE_POLICY";
+
+/// How the slots with a device usage this agent doesn't understand are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UnknownDeviceUsagePolicy {
+    /// The slot is considered reserved by another node
+    #[default]
+    Reserved,
+    /// The slot is considered free, and can be claimed over the unknown usage
+    Free,
+}
+
+impl UnknownDeviceUsagePolicy {
+    /// Gets the policy from the environment, using the default when unset and failing on invalid values
+    pub fn from_env(env_var_query: &impl EnvVarQuery) -> anyhow::Result<Self> {
+        match env_var_query.get_env_var(UNKNOWN_DEVICE_USAGE_POLICY_LABEL) {
+            Err(std::env::VarError::NotPresent) => Ok(UnknownDeviceUsagePolicy::default()),
+            Ok(value) if value == "reserved" => Ok(UnknownDeviceUsagePolicy::Reserved),
+            Ok(value) if value == "free" => Ok(UnknownDeviceUsagePolicy::Free),
+            value => Err(anyhow::format_err!(
+                "invalid {} value {:?}, expected reserved or free",
+                UNKNOWN_DEVICE_USAGE_POLICY_LABEL,
+                value
+            )),
+        }
+    }
+
+    fn apply(self, usage: DeviceUsage) -> DeviceUsage {
+        match (self, usage) {
+            (UnknownDeviceUsagePolicy::Free, DeviceUsage::Unknown(_)) => DeviceUsage::Unused,
+            (_, usage) => usage,
+        }
+    }
+}
+
+/// Parses a device usage, keeping the usages this agent doesn't understand as Unknown
+fn parse_device_usage(val: &str) -> DeviceUsage {
+    DeviceUsage::from_str(val).unwrap_or_else(|_| {
+        trace!("Unknown device usage: {}", val);
+        DeviceUsage::Unknown(val.to_owned())
+    })
+}
+
 fn parse_slot_id(st: &str) -> Result<usize, DevicePluginError> {
     usize::from_str(
         st.rsplit_once('-')
@@ -110,7 +417,7 @@ fn construct_slots_map(
 ) -> Result<HashMap<usize, DeviceUsage>, DevicePluginError> {
     slots
         .iter()
-        .map(|(k, v)| Ok((parse_slot_id(k)?, DeviceUsage::from_str(v)?)))
+        .map(|(k, v)| Ok((parse_slot_id(k)?, parse_device_usage(v))))
         .try_collect()
 }
 
@@ -124,24 +431,154 @@ fn construct_slots_vec(
         if index >= capacity {
             return Err(DevicePluginError::UsageParseError);
         }
-        out_vec[index] = DeviceUsage::from_str(v)?;
+        out_vec[index] = parse_device_usage(v);
     }
     Ok(out_vec)
 }
 
+/// Warns about the device usage slots beyond the capacity, these never get advertised as free
+fn warn_slots_beyond_capacity(
+    instance_name: &str,
+    slots: &HashMap<usize, DeviceUsage>,
+    capacity: usize,
+) {
+    let beyond = slots.keys().filter(|k| **k >= capacity).count();
+    if beyond > 0 {
+        warn!(
+            "Instance {} device usage has {} slots beyond its capacity of {}",
+            instance_name, beyond, capacity
+        );
+    }
+}
+
+/// Resizes the slots to the given capacity without ever removing a reserved slot, slots beyond
+/// the capacity are kept until they get released.
+fn resize_slots(slots: &mut Vec<DeviceUsage>, capacity: usize) {
+    let len = slots
+        .iter()
+        .rposition(|u| *u != DeviceUsage::Unused)
+        .map_or(0, |i| i + 1)
+        .max(capacity);
+    slots.resize(len, DeviceUsage::Unused);
+}
+
+fn used_slots_count(slots: &[DeviceUsage]) -> usize {
+    slots.iter().filter(|u| **u != DeviceUsage::Unused).count()
+}
+
+/// An Instance is over-committed when its capacity got reduced below the number of reserved slots
+fn is_over_committed(slots: &[DeviceUsage], capacity: usize) -> bool {
+    used_slots_count(slots) > capacity
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 struct PartialInstanceSlotUsage {
     device_usage: HashMap<String, String>,
 }
 
+/// Outcome of a device usage write, shared by all the slot updates it carries
+type DeviceUsageWriteResult = Option<Result<(), Arc<DevicePluginError>>>;
+
+/// Coalesces the device usage updates of an Instance made within a short window into a single write, to
+/// lower the load on the API server when many slots get claimed or freed at once. Writes always carry the
+/// whole device usage of the node, read once the window elapsed, so an update is never lost as long as it
+/// is queued with the slots lock held, right after being made.
+#[derive(Default)]
+struct DeviceUsageWriter {
+    window: Duration,
+    // Write still open to additional updates, if any
+    pending: std::sync::Mutex<Option<watch::Receiver<DeviceUsageWriteResult>>>,
+    // Held while writing, so that writes reach the API server in the order their device usage got read
+    write_lock: Mutex<()>,
+}
+
+/// Write queued for a device usage update
+enum QueuedWrite {
+    /// The update opened a new write, and is in charge of performing it
+    Leader(watch::Sender<DeviceUsageWriteResult>),
+    /// The update joined an open write
+    Follower(watch::Receiver<DeviceUsageWriteResult>),
+}
+
+impl DeviceUsageWriter {
+    fn queue(&self) -> QueuedWrite {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_ref() {
+            // A write whose leader got cancelled cannot be joined
+            Some(receiver) if receiver.has_changed().is_ok() => {
+                QueuedWrite::Follower(receiver.clone())
+            }
+            _ => {
+                let (sender, receiver) = watch::channel(None);
+                *pending = Some(receiver);
+                QueuedWrite::Leader(sender)
+            }
+        }
+    }
+}
+
+/// Only conflicts need to be told apart by callers, other errors get carried as their message
+fn unshare_write_error(error: Arc<DevicePluginError>) -> DevicePluginError {
+    match error.as_ref() {
+        DevicePluginError::SlotInUse => DevicePluginError::SlotInUse,
+        e => anyhow::anyhow!("{}", e).into(),
+    }
+}
+
+/// Drain state shared by the device plugins, set when the Agent shuts down so that they stop offering
+/// and allocating slots while the allocations in progress complete
+#[derive(Default)]
+struct Drain {
+    draining: AtomicBool,
+    // Held (shared) by every allocation in progress
+    allocations: RwLock<()>,
+}
+
+impl Drain {
+    fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Registers an allocation in progress, rejected with a retriable error once draining
+    async fn start_allocation(&self) -> Result<RwLockReadGuard<'_, ()>, tonic::Status> {
+        let guard = self.allocations.read().await;
+        if self.is_draining() {
+            return Err(tonic::Status::unavailable(
+                "Agent is shutting down, device plugin is draining",
+            ));
+        }
+        Ok(guard)
+    }
+
+    /// Starts draining, then waits for the allocations in progress to complete
+    async fn drain(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+        drop(self.allocations.write().await);
+    }
+}
+
 struct InstanceDevicePlugin {
     device: cdi::Device,
     slots_status: Mutex<watch::Sender<Vec<DeviceUsage>>>,
+    // Shared with the list_and_watch stream, which never advertises more slots than the capacity
+    capacity: Arc<AtomicUsize>,
+    // A quarantined Instance has all its slots reported unhealthy and none can be claimed
+    quarantined: Arc<AtomicBool>,
+    // Delay to wait for before the first list_and_watch, taken by it
+    initial_delay: std::sync::Mutex<Option<Duration>>,
+    unknown_usage_policy: UnknownDeviceUsagePolicy,
+    // Directory the manifest of the device gets written to upon allocation, if any
+    device_manifest_directory: Option<PathBuf>,
+    device_usage_writer: DeviceUsageWriter,
+    drain: Arc<Drain>,
     node_name: String,
     instance_name: String,
     instance_namespace: String,
     kube_client: Arc<dyn IntoApi<Instance>>,
+    // Client the allocation audit Events are reported with, if enabled
+    audit_events: Option<Arc<dyn IntoApi<Event>>>,
+    reported_reserved_slots: Arc<ReportedReservedSlots>,
     stopper: Stopper,
 }
 
@@ -155,31 +592,220 @@ impl InstanceDevicePlugin {
         capacity: usize,
         client: Arc<dyn IntoApi<Instance>>,
     ) -> Result<Self, DevicePluginError> {
-        let (slots_status, _) = watch::channel(construct_slots_vec(slots, capacity)?);
+        // Reserved slots may be beyond the capacity if it got reduced
+        let slots_map = construct_slots_map(slots)?;
+        warn_slots_beyond_capacity(&plugin_name, &slots_map, capacity);
+        let len = slots_map
+            .keys()
+            .map(|k| k + 1)
+            .max()
+            .unwrap_or_default()
+            .max(capacity);
+        let mut slots_vec = construct_slots_vec(slots, len)?;
+        resize_slots(&mut slots_vec, capacity);
+        let reported_reserved_slots: Arc<ReportedReservedSlots> = Default::default();
+        reported_reserved_slots.report(&plugin_name, &slots_vec);
+        let (slots_status, _) = watch::channel(slots_vec);
         Ok(Self {
             device,
             slots_status: Mutex::new(slots_status),
+            capacity: Arc::new(AtomicUsize::new(capacity)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
             node_name,
             instance_name: plugin_name,
             kube_client: client,
+            audit_events: None,
+            reported_reserved_slots,
             stopper: Stopper::new(),
             instance_namespace: namespace,
         })
     }
 
+    /// Delays the first list_and_watch of the plugin by the given amount
+    fn with_initial_delay(self, delay: Duration) -> Self {
+        if !delay.is_zero() {
+            *self.initial_delay.lock().unwrap() = Some(delay);
+        }
+        self
+    }
+
+    /// Sets how the slots with an unknown device usage are handled
+    fn with_unknown_usage_policy(mut self, policy: UnknownDeviceUsagePolicy) -> Self {
+        self.unknown_usage_policy = policy;
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        self.slots_status.get_mut().send_modify(|slots| {
+            for slot in slots.iter_mut() {
+                *slot = policy.apply(std::mem::replace(slot, DeviceUsage::Unused));
+            }
+            resize_slots(slots, capacity);
+        });
+        self.reported_reserved_slots
+            .report(&self.instance_name, &self.slots_status.get_mut().borrow());
+        self
+    }
+
+    /// Sets the reserved slots reported by the akri_instance_slot_reserved metric, shared with the other
+    /// plugins of the Agent for its cardinality cap to apply to all the Instances
+    fn with_reported_reserved_slots(mut self, reported: Arc<ReportedReservedSlots>) -> Self {
+        self.reported_reserved_slots.remove(&self.instance_name);
+        self.reported_reserved_slots = reported;
+        self.reported_reserved_slots
+            .report(&self.instance_name, &self.slots_status.get_mut().borrow());
+        self
+    }
+
+    /// Sets the directory the manifest of the device gets written to upon allocation
+    fn with_device_manifest_directory(mut self, directory: Option<PathBuf>) -> Self {
+        self.device_manifest_directory = directory;
+        self
+    }
+
+    /// Sets the window during which the device usage updates are coalesced into a single write
+    fn with_device_usage_coalescing_window(mut self, window: Duration) -> Self {
+        self.device_usage_writer.window = window;
+        self
+    }
+
+    /// Sets the drain state of the plugin, shared with the other plugins of the Agent
+    fn with_drain(mut self, drain: Arc<Drain>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Sets the client the allocation audit records are reported as Events with, they are only
+    /// logged if unset
+    fn with_allocation_audit_events(mut self, client: Option<Arc<dyn IntoApi<Event>>>) -> Self {
+        self.audit_events = client;
+        self
+    }
+
+    /// Logs the allocation audit record, and reports it as an Event on the Instance if enabled.
+    /// Failing to report it does not fail the allocation or release.
+    async fn audit(&self, record: AllocationAuditRecord) {
+        record.log();
+        let Some(client) = &self.audit_events else {
+            return;
+        };
+        if let Err(e) = client
+            .namespaced(&self.instance_namespace)
+            .apply(record.event(), &self.node_name)
+            .await
+        {
+            warn!(
+                "Failed to report allocation audit Event for {}: {:?}",
+                record.slot, e
+            );
+        }
+    }
+
+    /// Builds the allocation response of a container using the device, along with its manifest if enabled
+    fn container_allocate_response(&self) -> Result<ContainerAllocateResponse, tonic::Status> {
+        let Some(directory) = &self.device_manifest_directory else {
+            return Ok(cdi_device_to_car(&self.device));
+        };
+        let device =
+            device_manifest::with_device_manifest(directory, &self.device).map_err(|e| {
+                error!("Unable to write device manifest: {:?}", e);
+                tonic::Status::unknown("Unable to write device manifest")
+            })?;
+        Ok(cdi_device_to_car(&device))
+    }
+
+    /// Acquires the slots lock, recording the time spent waiting for it
+    async fn lock_slots(&self) -> MutexGuard<'_, watch::Sender<Vec<DeviceUsage>>> {
+        let start = Instant::now();
+        let guard = self.slots_status.lock().await;
+        let wait = start.elapsed();
+        DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC.observe(wait.as_secs_f64());
+        if wait >= LOCK_WAIT_WARNING_THRESHOLD {
+            warn!(
+                "Waited {}ms to acquire slots lock of Instance {}",
+                wait.as_millis(),
+                self.instance_name
+            );
+        }
+        guard
+    }
+
     async fn update_slots(&self, slots: &HashMap<String, String>) -> Result<(), DevicePluginError> {
-        let my_slots = self.slots_status.lock().await;
-        let new_slots = construct_slots_map(slots)?;
+        let my_slots = self.lock_slots().await;
+        let new_slots: HashMap<usize, DeviceUsage> = construct_slots_map(slots)?
+            .into_iter()
+            .map(|(k, v)| (k, self.unknown_usage_policy.apply(v)))
+            .collect();
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        warn_slots_beyond_capacity(&self.instance_name, &new_slots, capacity);
         my_slots.send_if_modified(|current| {
             let mut modified = false;
             for (k, v) in new_slots.iter() {
+                if *k >= current.len() {
+                    if *v == DeviceUsage::Unused {
+                        continue;
+                    }
+                    current.resize(*k + 1, DeviceUsage::Unused);
+                }
                 if current[*k] != *v {
                     v.clone_into(&mut current[*k]);
                     modified = true;
                 }
             }
-            modified
+            let len = current.len();
+            resize_slots(current, capacity);
+            modified || len != current.len()
+        });
+        self.reported_reserved_slots
+            .report(&self.instance_name, &my_slots.borrow());
+        Ok(())
+    }
+
+    /// Handles the Instance entering or leaving quarantine, the kubelet is sent the slots again so
+    /// that it picks up their new health
+    async fn set_quarantined(&self, quarantined: bool) {
+        let slots_status = self.lock_slots().await;
+        if self.quarantined.swap(quarantined, Ordering::Relaxed) != quarantined {
+            info!(
+                "Instance {} {} quarantine",
+                self.instance_name,
+                if quarantined { "entered" } else { "left" }
+            );
+            slots_status.send_modify(|_| {});
+        }
+    }
+
+    /// Handles a change of the Instance's capacity, when shrinking below the number of reserved
+    /// slots, no slot gets removed, the Instance is rather flagged as over-committed and slots
+    /// are only removed once released.
+    async fn update_capacity(&self, capacity: usize) -> Result<(), DevicePluginError> {
+        let slots_status = self.lock_slots().await;
+        let previous_capacity = self.capacity.swap(capacity, Ordering::Relaxed);
+        if previous_capacity == capacity {
+            return Ok(());
+        }
+        slots_status.send_if_modified(|slots| {
+            let len = slots.len();
+            resize_slots(slots, capacity);
+            len != slots.len()
         });
+        let slots = slots_status.borrow().clone();
+        let over_committed = is_over_committed(&slots, capacity);
+        if over_committed {
+            warn!(
+                "Instance {} capacity reduced to {} below its {} reserved slots",
+                self.instance_name,
+                capacity,
+                used_slots_count(&slots)
+            );
+        }
+        if over_committed != is_over_committed(&slots, previous_capacity) {
+            let write = self.device_usage_writer.queue();
+            drop(slots_status);
+            self.write_device_usage(write).await?;
+        }
         Ok(())
     }
 
@@ -191,85 +817,119 @@ impl InstanceDevicePlugin {
         if wanted_state == DeviceUsage::Unused {
             return Err(anyhow::anyhow!("Should never happen").into());
         }
-        let slots_status = self.slots_status.lock().await;
+        let slots_status = self.lock_slots().await;
+        // No new slot can be reserved while the Instance is quarantined or at or over its capacity
+        let at_capacity = self.quarantined.load(Ordering::Relaxed)
+            || used_slots_count(&slots_status.borrow()) >= self.capacity.load(Ordering::Relaxed);
         let id = match id {
-            Some(id) => match &slots_status.borrow()[id] {
-                DeviceUsage::Unused => id,
+            Some(id) => match slots_status.borrow().get(id) {
+                Some(DeviceUsage::Unused) if !at_capacity => id,
                 // The kubelet asks for the same slot, it knows best
-                d if *d == wanted_state => id,
+                Some(d) if *d == wanted_state => id,
+                Some(DeviceUsage::Unused) | None => return Err(DevicePluginError::NoSlot),
                 _ => {
                     trace!("Trying to claim already used slot");
                     return Err(DevicePluginError::SlotInUse);
                 }
             },
+            None if at_capacity => return Err(DevicePluginError::NoSlot),
             None => slots_status
                 .borrow()
                 .iter()
                 .position(|v| *v == DeviceUsage::Unused)
                 .ok_or(DevicePluginError::NoSlot)?,
         };
+        let record = AllocationAuditRecord::new(
+            AllocationAuditAction::Allocate,
+            &self.instance_name,
+            &self.instance_namespace,
+            id,
+            &wanted_state,
+        );
         slots_status.send_modify(|slots| {
             slots[id] = wanted_state;
         });
-        let device_usage = slots_status
-            .borrow()
-            .iter()
-            .enumerate()
-            .filter_map(|(i, v)| match v {
-                v if v.is_owned_by(&self.node_name) => {
-                    Some((format!("{}-{}", self.instance_name, i), v.to_string()))
-                }
-                _ => None,
-            })
-            .collect();
-        let api = self.kube_client.namespaced(&self.instance_namespace);
-        let patch = Patch::Apply(
-            serde_json::to_value(Object {
-                types: Some(TypeMeta {
-                    api_version: Instance::api_version(&()).to_string(),
-                    kind: Instance::kind(&()).to_string(),
-                }),
-                status: None::<NotUsed>,
-                spec: PartialInstanceSlotUsage { device_usage },
-                metadata: ObjectMeta {
-                    name: Some(self.instance_name.to_owned()),
-                    ..Default::default()
-                },
-            })
-            .context("Could not create instance patch")?,
-        );
-        api.raw_patch(
-            &self.instance_name,
-            &patch,
-            &PatchParams::apply(&format!("dp-{}", &self.node_name)),
-        )
-        .await
-        .map_err(|e| match e {
-            kube::Error::Api(ae) => match ae.code {
-                409 => {
-                    trace!("Conflict on apply {:?}", ae);
-                    DevicePluginError::SlotInUse
-                }
-                _ => DevicePluginError::Other(ae.into()),
-            },
-            e => DevicePluginError::Other(e.into()),
-        })?;
+        self.reported_reserved_slots
+            .report(&self.instance_name, &slots_status.borrow());
+        let write = self.device_usage_writer.queue();
+        drop(slots_status);
+        self.write_device_usage(write).await?;
+        self.audit(record).await;
         Ok(id)
     }
 
     async fn free_slot(&self, id: usize) -> Result<(), DevicePluginError> {
-        let slots_status = self.slots_status.lock().await;
+        let slots_status = self.lock_slots().await;
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let mut released = None;
         slots_status.send_if_modified(|slots| {
             if id >= slots.len() {
                 // We try to free a slot that doesn't exists, probably already freed
                 false
             } else {
-                slots[id] = DeviceUsage::Unused;
+                released = Some(std::mem::replace(&mut slots[id], DeviceUsage::Unused));
+                // Released slots beyond the capacity get removed
+                resize_slots(slots, capacity);
                 true
             }
         });
-        let device_usage = slots_status
-            .borrow()
+        self.reported_reserved_slots
+            .report(&self.instance_name, &slots_status.borrow());
+        let write = self.device_usage_writer.queue();
+        drop(slots_status);
+        self.write_device_usage(write).await?;
+        if let Some(usage) = released.filter(|u| *u != DeviceUsage::Unused) {
+            self.audit(AllocationAuditRecord::new(
+                AllocationAuditAction::Release,
+                &self.instance_name,
+                &self.instance_namespace,
+                id,
+                &usage,
+            ))
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Waits for the queued write of a device usage update to be done, performing it if in charge of it
+    async fn write_device_usage(&self, write: QueuedWrite) -> Result<(), DevicePluginError> {
+        match write {
+            QueuedWrite::Leader(sender) => {
+                if !self.device_usage_writer.window.is_zero() {
+                    tokio::time::sleep(self.device_usage_writer.window).await;
+                }
+                let result = self.flush_device_usage().await.map_err(Arc::new);
+                sender.send_replace(Some(result.clone()));
+                result.map_err(unshare_write_error)
+            }
+            QueuedWrite::Follower(mut receiver) => {
+                let result = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .map(|result| result.clone());
+                match result {
+                    Ok(Some(result)) => result.map_err(unshare_write_error),
+                    // The leader got cancelled before writing, write the device usage on its behalf
+                    _ => self.flush_device_usage().await,
+                }
+            }
+        }
+    }
+
+    /// Writes the current device usage, closing the open write so that later updates get carried by a new one
+    async fn flush_device_usage(&self) -> Result<(), DevicePluginError> {
+        let _writing = self.device_usage_writer.write_lock.lock().await;
+        let slots_status = self.lock_slots().await;
+        self.device_usage_writer.pending.lock().unwrap().take();
+        let slots = slots_status.borrow().clone();
+        drop(slots_status);
+        self.patch_device_usage(&slots).await
+    }
+
+    /// Updates the Instance's device usage with the slots owned by this node, flagging the
+    /// Instance as over-committed if needed
+    async fn patch_device_usage(&self, slots: &[DeviceUsage]) -> Result<(), DevicePluginError> {
+        let device_usage = slots
             .iter()
             .enumerate()
             .filter_map(|(i, v)| match v {
@@ -279,6 +939,13 @@ impl InstanceDevicePlugin {
                 _ => None,
             })
             .collect();
+        let annotations =
+            is_over_committed(slots, self.capacity.load(Ordering::Relaxed)).then(|| {
+                BTreeMap::from([(
+                    AKRI_OVER_COMMITTED_ANNOTATION_NAME.to_string(),
+                    "true".to_string(),
+                )])
+            });
         let api = self.kube_client.namespaced(&self.instance_namespace);
         let patch = Patch::Apply(
             serde_json::to_value(Object {
@@ -290,6 +957,7 @@ impl InstanceDevicePlugin {
                 spec: PartialInstanceSlotUsage { device_usage },
                 metadata: ObjectMeta {
                     name: Some(self.instance_name.to_owned()),
+                    annotations,
                     ..Default::default()
                 },
             })
@@ -303,7 +971,10 @@ impl InstanceDevicePlugin {
         .await
         .map_err(|e| match e {
             kube::Error::Api(ae) => match ae.code {
-                409 => DevicePluginError::SlotInUse,
+                409 => {
+                    trace!("Conflict on apply {:?}", ae);
+                    DevicePluginError::SlotInUse
+                }
                 _ => DevicePluginError::Other(ae.into()),
             },
             e => DevicePluginError::Other(e.into()),
@@ -312,19 +983,30 @@ impl InstanceDevicePlugin {
     }
 }
 
+/// Free slots beyond the capacity are not advertised, and only as many free slots as the capacity
+/// still allows are reported healthy, so that the kubelet never gets offered more than `capacity` slots
 fn instance_device_usage_to_device(
     device_name: &str,
     node_name: &str,
     devices: Vec<DeviceUsage>,
+    capacity: usize,
+    unavailable: bool,
 ) -> Result<ListAndWatchResponse, tonic::Status> {
+    let mut free_slots = capacity.saturating_sub(used_slots_count(&devices));
     let devices = devices
         .into_iter()
         .enumerate()
+        .filter(|(id, dev)| *id < capacity || *dev != DeviceUsage::Unused)
         .map(|(id, dev)| super::v1beta1::Device {
             id: format!("{}-{}", device_name, id),
             health: match dev {
-                DeviceUsage::Unused => "Healthy",
-                DeviceUsage::Configuration { .. } => "Unhealthy",
+                _ if unavailable => "Unhealthy",
+                DeviceUsage::Unused if free_slots > 0 => {
+                    free_slots -= 1;
+                    "Healthy"
+                }
+                DeviceUsage::Unused => "Unhealthy",
+                DeviceUsage::Configuration { .. } | DeviceUsage::Unknown(_) => "Unhealthy",
                 DeviceUsage::Node(n) => match n == node_name {
                     true => "Healthy",
                     false => "Unhealthy",
@@ -348,6 +1030,10 @@ impl InternalDevicePlugin for InstanceDevicePlugin {
 
     fn stop(&self) {
         trace!("stopping device plugin");
+        self.reported_reserved_slots.remove(&self.instance_name);
+        if let Some(directory) = &self.device_manifest_directory {
+            device_manifest::remove_device_manifest(directory, &self.device.name);
+        }
         self.stopper.stop()
     }
 
@@ -363,13 +1049,39 @@ impl InternalDevicePlugin for InstanceDevicePlugin {
             "list_and_watch - kubelet called list_and_watch for instance {}",
             self.instance_name
         );
+        let initial_delay = self.initial_delay.lock().unwrap().take();
+        if let Some(delay) = initial_delay {
+            trace!(
+                "list_and_watch - delaying first list_and_watch of instance {} by {:?}",
+                self.instance_name,
+                delay
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {},
+                _ = self.stopper.stopped() => {
+                    return Err(tonic::Status::unavailable("device plugin stopped"));
+                },
+            }
+        }
         let device_name = self.instance_name.clone();
         let node_name = self.node_name.clone();
-        let receiver = self.slots_status.lock().await.subscribe();
+        let receiver = self.lock_slots().await.subscribe();
         let receiver_stream = tokio_stream::wrappers::WatchStream::new(receiver);
+        let quarantined = self.quarantined.clone();
+        let capacity = self.capacity.clone();
+        let drain = self.drain.clone();
 
         Ok(tonic::Response::new(DeviceUsageStream {
-            device_usage_to_device: instance_device_usage_to_device,
+            device_usage_to_device: Box::new(move |device_name, node_name, devices| {
+                // Neither a quarantined nor a draining Instance offers any slot
+                instance_device_usage_to_device(
+                    device_name,
+                    node_name,
+                    devices,
+                    capacity.load(Ordering::Relaxed),
+                    quarantined.load(Ordering::Relaxed) || drain.is_draining(),
+                )
+            }),
             input_stream: self.stopper.make_abortable(receiver_stream),
             device_name,
             node_name,
@@ -387,6 +1099,7 @@ impl InternalDevicePlugin for InstanceDevicePlugin {
             "allocate - kubelet called allocate for Instance {}",
             self.instance_name
         );
+        let _allocation = self.drain.start_allocation().await?;
         let mut container_responses: Vec<super::v1beta1::ContainerAllocateResponse> = Vec::new();
         let reqs = requests.into_inner().container_requests;
         for allocate_request in reqs {
@@ -405,7 +1118,7 @@ impl InternalDevicePlugin for InstanceDevicePlugin {
                         tonic::Status::unknown("Unable to claim slot")
                     })?;
             }
-            container_responses.push(cdi_device_to_car(&self.device));
+            container_responses.push(self.container_allocate_response()?);
         }
         Ok(tonic::Response::new(AllocateResponse {
             container_responses,
@@ -474,25 +1187,66 @@ enum ConfigurationSlot {
     DeviceUsed { device: String, slot_id: usize },
 }
 
+/// Separates the Configuration name from the alias in the name of an alias plugin, it can't be
+/// part of a Configuration name
+const ALIAS_SEPARATOR: char = '_';
+
+/// Name of the plugin advertising the resource of a Configuration under an alias, its slots are
+/// named after it
+fn alias_plugin_name(config_name: &str, alias: &str) -> String {
+    format!("{}{}{}", config_name, ALIAS_SEPARATOR, alias)
+}
+
+/// Gets the alias a Configuration slot was claimed through, if any
+fn slot_alias(vdev: &str) -> Option<&str> {
+    let (plugin_name, _) = vdev.rsplit_once('-')?;
+    plugin_name
+        .split_once(ALIAS_SEPARATOR)
+        .map(|(_, alias)| alias)
+}
+
 struct ConfigurationDevicePlugin {
     instances: RwLock<HashMap<String, Arc<InstanceDevicePlugin>>>,
     slots: Arc<RwLock<watch::Sender<HashMap<String, ConfigurationSlot>>>>,
     config_name: String,
+    // Name of the resource advertised to the kubelet (without the akri.sh/ prefix)
+    resource_name: String,
+    // Alias of the Configuration resource this plugin advertises, if not the resource itself
+    alias: Option<String>,
     node_name: String,
+    drain: Arc<Drain>,
     stopper: Stopper,
 }
 
 impl ConfigurationDevicePlugin {
-    fn new(config_name: String, node_name: String) -> Self {
+    fn new(config_name: String, resource_name: String, node_name: String) -> Self {
         let (slots, _) = watch::channel(Default::default());
         Self {
             instances: Default::default(),
             slots: Arc::new(RwLock::new(slots)),
             config_name,
+            resource_name,
+            alias: None,
             node_name,
+            drain: Default::default(),
             stopper: Stopper::new(),
         }
     }
+
+    /// Creates a plugin advertising the devices of the Configuration under an alias resource name,
+    /// they are claimable through either resource
+    fn new_alias(config_name: &str, alias: String, node_name: String) -> Self {
+        Self {
+            alias: Some(alias.clone()),
+            ..Self::new(alias_plugin_name(config_name, &alias), alias, node_name)
+        }
+    }
+
+    /// Sets the drain state of the plugin, shared with the other plugins of the Agent
+    fn with_drain(mut self, drain: Arc<Drain>) -> Self {
+        self.drain = drain;
+        self
+    }
     async fn add_plugin(&self, name: String, plugin: Arc<InstanceDevicePlugin>) {
         self.instances
             .write()
@@ -501,19 +1255,27 @@ impl ConfigurationDevicePlugin {
         let node_name = self.node_name.clone();
         let slots_ref = self.slots.clone();
         let config_name = self.config_name.clone();
+        let alias = self.alias.clone();
         let instance_name = plugin.instance_name.clone();
-        let mut receiver = plugin.slots_status.lock().await.subscribe();
+        let mut receiver = plugin.lock_slots().await.subscribe();
         tokio::spawn(async move {
             loop {
                 {
                     let (has_free, used_config_slots) = {
                         let values = receiver.borrow_and_update();
-                        let has_free = values.contains(&DeviceUsage::Unused);
+                        // A quarantined Instance offers no slot to the Configuration
+                        let has_free = !plugin.quarantined.load(Ordering::Relaxed)
+                            && values.contains(&DeviceUsage::Unused);
                         let used_config_slots: HashMap<String, ConfigurationSlot> = values
                             .iter()
                             .enumerate()
                             .filter_map(|(slot, du)| match du {
-                                DeviceUsage::Configuration { vdev, node } if *node == node_name => {
+                                // Slots claimed through the other resources of the Configuration
+                                // belong to their plugins
+                                DeviceUsage::Configuration { vdev, node }
+                                    if *node == node_name
+                                        && slot_alias(vdev) == alias.as_deref() =>
+                                {
                                     Some((
                                         vdev.clone(),
                                         ConfigurationSlot::DeviceUsed {
@@ -622,7 +1384,7 @@ impl InternalDevicePlugin for ConfigurationDevicePlugin {
     type DeviceStore = HashMap<String, ConfigurationSlot>;
 
     fn get_name(&self) -> String {
-        self.config_name.clone()
+        self.resource_name.clone()
     }
 
     async fn stopped(&self) {
@@ -645,9 +1407,12 @@ impl InternalDevicePlugin for ConfigurationDevicePlugin {
         let node_name = self.node_name.clone();
         let receiver = self.slots.read().await.subscribe();
         let receiver_stream = tokio_stream::wrappers::WatchStream::new(receiver);
+        let drain = self.drain.clone();
 
         Ok(tonic::Response::new(DeviceUsageStream {
-            device_usage_to_device: config_device_usage_to_device,
+            device_usage_to_device: Box::new(move |device_name, node_name, devices| {
+                config_device_usage_to_device(device_name, node_name, devices, drain.is_draining())
+            }),
             input_stream: self.stopper.make_abortable(receiver_stream),
             device_name,
             node_name,
@@ -665,6 +1430,7 @@ impl InternalDevicePlugin for ConfigurationDevicePlugin {
             "allocate - kubelet called allocate for Configuration {}",
             self.config_name
         );
+        let _allocation = self.drain.start_allocation().await?;
         let mut container_responses: Vec<super::v1beta1::ContainerAllocateResponse> = Vec::new();
         let reqs = requests.into_inner().container_requests;
         for allocate_request in reqs {
@@ -686,7 +1452,7 @@ impl InternalDevicePlugin for ConfigurationDevicePlugin {
                         .get(&dev)
                         .ok_or(tonic::Status::unknown("Invalid slot"))?
                         .clone();
-                    container_responses.push(cdi_device_to_car(&dp.device));
+                    container_responses.push(dp.container_allocate_response()?);
                     dp.claim_slot(
                         None,
                         DeviceUsage::Configuration {
@@ -711,19 +1477,49 @@ fn config_device_usage_to_device(
     _device_name: &str,
     _node_name: &str,
     devices: HashMap<String, ConfigurationSlot>,
+    draining: bool,
 ) -> Result<ListAndWatchResponse, tonic::Status> {
     Ok(ListAndWatchResponse {
         devices: devices
             .into_keys()
             .map(|id| super::v1beta1::Device {
                 id,
-                health: "Healthy".to_string(),
+                health: match draining {
+                    true => "Unhealthy",
+                    false => "Healthy",
+                }
+                .to_string(),
                 topology: None,
             })
             .collect(),
     })
 }
 
+/// Gets the name of the resource advertised for the Configuration of an Instance, which is
+/// the Configuration name unless overridden in the Configuration
+fn get_configuration_resource_name(instance: &Instance) -> String {
+    instance
+        .annotations()
+        .get(AKRI_RESOURCE_NAME_ANNOTATION_NAME)
+        .cloned()
+        .unwrap_or_else(|| instance.spec.configuration_name.to_owned())
+}
+
+/// Gets the aliases of the resource advertised for the Configuration of an Instance
+fn get_configuration_resource_name_aliases(instance: &Instance) -> Vec<String> {
+    instance
+        .annotations()
+        .get(AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME)
+        .map(|aliases| {
+            aliases
+                .split(',')
+                .filter(|a| !a.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// This module implements a controller for Instance resources that will ensure device plugins are correctly created with the correct health status
 
 pub struct DevicePluginManager {
@@ -733,6 +1529,15 @@ pub struct DevicePluginManager {
     kube_client: Arc<dyn IntoApi<Instance>>,
     device_manager: Arc<dyn DeviceManager>,
     error_backoffs: std::sync::Mutex<HashMap<String, Duration>>,
+    list_and_watch_initial_delay: Duration,
+    unknown_usage_policy: UnknownDeviceUsagePolicy,
+    registration_max_attempts: u8,
+    managed_finalizers: bool,
+    device_manifest_directory: Option<PathBuf>,
+    device_usage_coalescing_window: Duration,
+    audit_events: Option<Arc<dyn IntoApi<Event>>>,
+    reported_reserved_slots: Arc<ReportedReservedSlots>,
+    drain: Arc<Drain>,
 }
 
 const SUCCESS_REQUEUE: Duration = Duration::from_secs(600);
@@ -750,12 +1555,66 @@ impl DevicePluginManager {
             kube_client,
             device_manager,
             error_backoffs: std::sync::Mutex::new(HashMap::default()),
+            list_and_watch_initial_delay: Duration::ZERO,
+            unknown_usage_policy: Default::default(),
+            registration_max_attempts: DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS,
+            managed_finalizers: true,
+            device_manifest_directory: None,
+            device_usage_coalescing_window: DEFAULT_DEVICE_USAGE_COALESCING_WINDOW,
+            audit_events: None,
+            reported_reserved_slots: Default::default(),
+            drain: Default::default(),
         }
     }
 
-    pub async fn free_slot(&self, device_id: String) -> Result<(), DevicePluginError> {
-        let (plugin_name, slot_id) = device_id
-            .rsplit_once('-')
+    /// Sets the window during which the device usage updates of an Instance are coalesced into a single write
+    pub fn with_device_usage_coalescing_window(mut self, window: Duration) -> Self {
+        self.device_usage_coalescing_window = window;
+        self
+    }
+
+    /// Sets the client the allocation audit records of the Instance device plugins are reported as
+    /// Events with, they are only logged if unset
+    pub fn with_allocation_audit_events(mut self, client: Option<Arc<dyn IntoApi<Event>>>) -> Self {
+        self.audit_events = client;
+        self
+    }
+
+    /// Sets the directory the manifests of the devices allocated to brokers are written to, no manifest
+    /// is generated if unset
+    pub fn with_device_manifest_directory(mut self, directory: Option<PathBuf>) -> Self {
+        self.device_manifest_directory = directory;
+        self
+    }
+
+    /// Sets whether a finalizer gets added to the Instances exposed on this node, the ones added
+    /// before are removed in any case once the Instance is torn down
+    pub fn with_managed_finalizers(mut self, managed_finalizers: bool) -> Self {
+        self.managed_finalizers = managed_finalizers;
+        self
+    }
+
+    /// Sets how many times registering a device plugin with kubelet is attempted before giving up
+    pub fn with_kubelet_registration_max_attempts(mut self, max_attempts: u8) -> Self {
+        self.registration_max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets how the slots of the Instances with an unknown device usage are handled
+    pub fn with_unknown_device_usage_policy(mut self, policy: UnknownDeviceUsagePolicy) -> Self {
+        self.unknown_usage_policy = policy;
+        self
+    }
+
+    /// Delays the first list_and_watch of every Instance device plugin by the given amount
+    pub fn with_list_and_watch_initial_delay(mut self, delay: Duration) -> Self {
+        self.list_and_watch_initial_delay = delay;
+        self
+    }
+
+    pub async fn free_slot(&self, device_id: String) -> Result<(), DevicePluginError> {
+        let (plugin_name, slot_id) = device_id
+            .rsplit_once('-')
             .ok_or(DevicePluginError::UsageParseError)?;
         let slot_id = slot_id
             .parse::<usize>()
@@ -780,13 +1639,26 @@ impl DevicePluginManager {
         Err(DevicePluginError::NoSlot)
     }
 
+    /// Drains the device plugins before the Agent shuts down: new allocations are rejected with a
+    /// retriable error and the ones in progress are waited for, so that they don't leave slots
+    /// orphaned, then all the slots are reported unhealthy to the kubelet
+    pub async fn drain(&self) {
+        info!("Draining device plugins");
+        self.drain.drain().await;
+        for plugin in self.instance_plugins.lock().await.values() {
+            plugin.lock_slots().await.send_modify(|_| {});
+        }
+        for plugin in self.configuration_plugins.lock().await.values() {
+            plugin.slots.write().await.send_modify(|_| {});
+        }
+    }
+
     pub async fn get_used_slots(&self) -> HashSet<String> {
         let mut slots: HashSet<String> = Default::default();
         for (instance, plugin) in self.instance_plugins.lock().await.iter() {
             slots.extend(
                 plugin
-                    .slots_status
-                    .lock()
+                    .lock_slots()
                     .await
                     .borrow()
                     .iter()
@@ -835,6 +1707,18 @@ pub async fn reconcile(
                 cps.remove(&instance.spec.configuration_name);
             }
         }
+        // The Instance is also withdrawn from every alias of its Configuration
+        let alias_prefix = alias_plugin_name(&instance.spec.configuration_name, "");
+        let alias_plugins = cps
+            .keys()
+            .filter(|k| k.starts_with(&alias_prefix))
+            .cloned()
+            .collect_vec();
+        for name in alias_plugins {
+            if cps[&name].remove_plugin(&instance.name_any()).await {
+                cps.remove(&name).unwrap().stop();
+            }
+        }
         if let Some(plugin) = ctx
             .instance_plugins
             .lock()
@@ -850,29 +1734,47 @@ pub async fn reconcile(
         let device = ctx.device_manager.get(&instance.spec.cdi_name).ok_or(
             DevicePluginError::UnknownDevice(instance.spec.cdi_name.to_owned()),
         )?;
-        api.add_finalizer(&instance, &ctx.node_name)
-            .await
-            .map_err(|e| DevicePluginError::Other(e.into()))?;
+        if ctx.managed_finalizers {
+            api.add_finalizer(&instance, &ctx.node_name)
+                .await
+                .map_err(|e| DevicePluginError::Other(e.into()))?;
+        } else if instance.finalizers().contains(&ctx.node_name) {
+            api.remove_finalizer(&instance, &ctx.node_name)
+                .await
+                .map_err(|e| DevicePluginError::Other(e.into()))?;
+        }
 
         let instance_plugin = {
             let mut instance_plugins = ctx.instance_plugins.lock().await;
             match instance_plugins.get(&instance.name_any()) {
                 None => {
-                    let plugin = Arc::new(InstanceDevicePlugin::new(
-                        ctx.node_name.to_owned(),
-                        instance.name_any(),
-                        instance.namespace().unwrap_or("default".to_string()),
-                        device,
-                        &instance.spec.device_usage,
-                        instance.spec.capacity,
-                        ctx.kube_client.clone(),
-                    )?);
-                    serve_and_register_plugin(plugin.clone()).await?;
+                    let plugin = Arc::new(
+                        InstanceDevicePlugin::new(
+                            ctx.node_name.to_owned(),
+                            instance.name_any(),
+                            instance.namespace().unwrap_or("default".to_string()),
+                            device,
+                            &instance.spec.device_usage,
+                            instance.spec.capacity,
+                            ctx.kube_client.clone(),
+                        )?
+                        .with_reported_reserved_slots(ctx.reported_reserved_slots.clone())
+                        .with_initial_delay(ctx.list_and_watch_initial_delay)
+                        .with_unknown_usage_policy(ctx.unknown_usage_policy)
+                        .with_device_manifest_directory(ctx.device_manifest_directory.clone())
+                        .with_device_usage_coalescing_window(ctx.device_usage_coalescing_window)
+                        .with_allocation_audit_events(ctx.audit_events.clone())
+                        .with_drain(ctx.drain.clone()),
+                    );
+                    plugin.set_quarantined(is_quarantined(&instance)).await;
+                    serve_and_register_plugin(plugin.clone(), ctx.registration_max_attempts)
+                        .await?;
                     instance_plugins.insert(instance.name_any(), plugin.clone());
                     plugin
                 }
                 Some(plugin) => {
-                    // TODO: Add a way to handle a change in the instance's capacity.
+                    plugin.set_quarantined(is_quarantined(&instance)).await;
+                    plugin.update_capacity(instance.spec.capacity).await?;
                     plugin.update_slots(&instance.spec.device_usage).await?;
                     plugin.clone()
                 }
@@ -882,21 +1784,66 @@ pub async fn reconcile(
             let mut configuration_plugins = ctx.configuration_plugins.lock().await;
             match configuration_plugins.get(&instance.spec.configuration_name) {
                 None => {
-                    let plugin = Arc::new(ConfigurationDevicePlugin::new(
-                        instance.spec.configuration_name.to_owned(),
-                        ctx.node_name.to_owned(),
-                    ));
-                    serve_and_register_plugin(plugin.clone()).await?;
+                    let plugin = Arc::new(
+                        ConfigurationDevicePlugin::new(
+                            instance.spec.configuration_name.to_owned(),
+                            get_configuration_resource_name(&instance),
+                            ctx.node_name.to_owned(),
+                        )
+                        .with_drain(ctx.drain.clone()),
+                    );
+                    serve_and_register_plugin(plugin.clone(), ctx.registration_max_attempts)
+                        .await?;
                     configuration_plugins
                         .insert(instance.spec.configuration_name.to_owned(), plugin.clone());
                     plugin
                 }
-                Some(plugin) => plugin.clone(),
+                Some(plugin) => {
+                    let resource_name = get_configuration_resource_name(&instance);
+                    if plugin.resource_name != resource_name {
+                        warn!(
+                            "Configuration {} resource name changed to {}, it is still advertised as {}{} until all its Instances are gone",
+                            instance.spec.configuration_name,
+                            resource_name,
+                            DP_SLOT_PREFIX,
+                            plugin.resource_name
+                        );
+                    }
+                    plugin.clone()
+                }
             }
         };
         configuration_plugin
-            .add_plugin(instance.name_any(), instance_plugin)
+            .add_plugin(instance.name_any(), instance_plugin.clone())
             .await;
+
+        // Aliases removed from the Configuration are still advertised until all its Instances are gone
+        for alias in get_configuration_resource_name_aliases(&instance) {
+            let alias_plugin = {
+                let mut configuration_plugins = ctx.configuration_plugins.lock().await;
+                let name = alias_plugin_name(&instance.spec.configuration_name, &alias);
+                match configuration_plugins.get(&name) {
+                    None => {
+                        let plugin = Arc::new(
+                            ConfigurationDevicePlugin::new_alias(
+                                &instance.spec.configuration_name,
+                                alias,
+                                ctx.node_name.to_owned(),
+                            )
+                            .with_drain(ctx.drain.clone()),
+                        );
+                        serve_and_register_plugin(plugin.clone(), ctx.registration_max_attempts)
+                            .await?;
+                        configuration_plugins.insert(name, plugin.clone());
+                        plugin
+                    }
+                    Some(plugin) => plugin.clone(),
+                }
+            };
+            alias_plugin
+                .add_plugin(instance.name_any(), instance_plugin.clone())
+                .await;
+        }
     }
     ctx.error_backoffs
         .lock()
@@ -958,6 +1905,18 @@ mod tests {
         );
         assert_eq!(DeviceUsage::from_str("")?, DeviceUsage::Unused,);
         assert!(DeviceUsage::from_str("C:node-a").is_err());
+        assert_eq!(
+            DeviceUsage::from_str("v1:kind=instance;node=node-a")?,
+            DeviceUsage::Node("node-a".to_string())
+        );
+        assert_eq!(
+            DeviceUsage::from_str("v1:kind=configuration;vdev=vdev1;node=node-a")?,
+            DeviceUsage::Configuration {
+                vdev: "vdev1".to_string(),
+                node: "node-a".to_string()
+            },
+        );
+        assert!(DeviceUsage::from_str("v1:kind=instance").is_err());
 
         Ok(())
     }
@@ -1014,6 +1973,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_allocation_audit_record() {
+        let record = AllocationAuditRecord::new(
+            AllocationAuditAction::Allocate,
+            "config-a-359973",
+            "namespace-a",
+            2,
+            &DeviceUsage::Node("node-a".to_owned()),
+        );
+        let mut value = serde_json::to_value(&record).unwrap();
+        let timestamp = value.as_object_mut().unwrap().remove("timestamp").unwrap();
+        assert!(
+            k8s_openapi::chrono::DateTime::parse_from_rfc3339(timestamp.as_str().unwrap()).is_ok()
+        );
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "action": "allocate",
+                "node": "node-a",
+                "instance": "config-a-359973",
+                "namespace": "namespace-a",
+                "slot": "config-a-359973-2",
+            })
+        );
+
+        let record = AllocationAuditRecord::new(
+            AllocationAuditAction::Release,
+            "config-a-359973",
+            "namespace-a",
+            0,
+            &DeviceUsage::Configuration {
+                vdev: "config-a-1".to_owned(),
+                node: "node-b".to_owned(),
+            },
+        );
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["action"], "release");
+        assert_eq!(value["node"], "node-b");
+        assert_eq!(value["slot"], "config-a-359973-0");
+        assert_eq!(value["configurationDevice"], "config-a-1");
+    }
+
     #[tokio::test]
     async fn test_instance_plugin_update_slots() {
         let plugin = InstanceDevicePlugin::new(
@@ -1045,165 +2046,852 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_free_slot() {
-        let dm = crate::device_manager::MockDeviceManager::new();
-        let mut kube_client = MockIntoApi::new();
-        kube_client.expect_namespaced().returning(|_| {
-            let mut api = MockApi::new();
-            api.expect_raw_patch()
-                .with(
-                    mockall::predicate::eq("instance-a"),
-                    mockall::predicate::function(|a: &Patch<serde_json::Value>| match a {
-                        Patch::Apply(v) => {
-                            let su: Object<PartialInstanceSlotUsage, NotUsed> =
-                                serde_json::from_value(v.clone()).unwrap();
-                            error!("{:?}", su.spec.device_usage);
-                            su.spec.device_usage.is_empty()
-                        }
-                        _ => false,
-                    }),
-                    mockall::predicate::always(),
-                )
-                .returning(|_, _, _| {
-                    Ok(Instance {
-                        metadata: Default::default(),
-                        spec: InstanceSpec {
-                            configuration_name: "config-a".to_owned(),
-                            cdi_name: Default::default(),
-                            capacity: 1,
-                            broker_properties: Default::default(),
-                            shared: false,
-                            nodes: Default::default(),
-                            device_usage: Default::default(),
-                        },
-                    })
-                });
-            Box::new(api)
-        });
-        let kube_client = Arc::new(kube_client);
-        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
-
-        let stopper = Stopper::new();
-
-        let (s, _) = watch::channel(vec![
-            DeviceUsage::Configuration {
-                vdev: "config-a-1".to_owned(),
-                node: "node-a".to_owned(),
-            },
-            DeviceUsage::Node("node-b".to_owned()),
-        ]);
-
-        let instance_plugin = Arc::new(InstanceDevicePlugin {
-            device: Device {
+    async fn test_report_reserved_slots() {
+        let reported_reserved_slots = Arc::new(ReportedReservedSlots::default());
+        let reported = |slot: &str| {
+            reported_reserved_slots
+                .slots
+                .lock()
+                .unwrap()
+                .get("instance-reserved")
+                .and_then(|slots| slots.get(slot))
+                .cloned()
+        };
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-reserved".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
                 name: "my-device".to_owned(),
                 annotations: Default::default(),
-                container_edits: ContainerEdit {
-                    ..Default::default()
-                },
+                container_edits: Default::default(),
             },
-            slots_status: Mutex::new(s),
-            node_name: "node-a".to_owned(),
-            instance_name: "instance-a".to_owned(),
-            instance_namespace: "namespace-a".to_owned(),
-            kube_client,
-            stopper: stopper.clone(),
-        });
-        dpm.instance_plugins
-            .lock()
+            &HashMap::from([
+                ("instance-reserved-0".to_owned(), "node-a".to_owned()),
+                (
+                    "instance-reserved-2".to_owned(),
+                    "C:config-a-0:node-b".to_owned(),
+                ),
+            ]),
+            3,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap()
+        .with_reported_reserved_slots(reported_reserved_slots.clone());
+
+        assert_eq!(reported("0"), Some("node-a".to_owned()));
+        assert_eq!(reported("1"), None);
+        assert_eq!(reported("2"), Some("node-b".to_owned()));
+        assert_eq!(
+            INSTANCE_SLOT_RESERVED_METRIC
+                .with_label_values(&["instance-reserved", "0", "node-a"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            INSTANCE_SLOT_RESERVED_METRIC
+                .with_label_values(&["instance-reserved", "2", "node-b"])
+                .get(),
+            1
+        );
+
+        // Released slots are no longer reported
+        plugin
+            .update_slots(&HashMap::from([(
+                "instance-reserved-0".to_owned(),
+                "".to_owned(),
+            )]))
             .await
-            .insert("instance-a".to_owned(), instance_plugin.clone());
+            .unwrap();
+        assert_eq!(reported("0"), None);
+        assert_eq!(reported("2"), Some("node-b".to_owned()));
 
-        let (s, _) = watch::channel(HashMap::from([(
-            "config-a-1".to_owned(),
-            ConfigurationSlot::DeviceUsed {
-                device: "instance-a".to_owned(),
-                slot_id: 0,
-            },
-        )]));
+        // Nor are the slots of stopped plugins
+        plugin.stop();
+        assert_eq!(reported("2"), None);
+        assert!(reported_reserved_slots.slots.lock().unwrap().is_empty());
+    }
 
-        dpm.configuration_plugins.lock().await.insert(
-            "config-a".to_owned(),
-            Arc::new(ConfigurationDevicePlugin {
-                instances: RwLock::new(HashMap::from([("instance-a".to_owned(), instance_plugin)])),
-                slots: Arc::new(RwLock::new(s)),
-                config_name: "config-a".to_owned(),
-                node_name: "node-a".to_string(),
-                stopper,
-            }),
+    #[test]
+    fn test_reported_reserved_slots_cap() {
+        let reported_reserved_slots = ReportedReservedSlots {
+            slots: Default::default(),
+            max: 2,
+        };
+        let count = || {
+            reported_reserved_slots
+                .slots
+                .lock()
+                .unwrap()
+                .values()
+                .map(HashMap::len)
+                .sum::<usize>()
+        };
+        reported_reserved_slots.report(
+            "instance-cap-a",
+            &[
+                DeviceUsage::Node("node-a".to_owned()),
+                DeviceUsage::Unused,
+                DeviceUsage::Node("node-a".to_owned()),
+            ],
         );
+        assert_eq!(count(), 2);
 
-        assert!(dpm.free_slot("config-b-2".to_owned()).await.is_err());
-        assert!(dpm.free_slot("config-a-1".to_owned()).await.is_ok());
+        // The cap applies across Instances
+        reported_reserved_slots.report("instance-cap-b", &[DeviceUsage::Node("node-a".to_owned())]);
+        assert_eq!(count(), 2);
+        assert!(!reported_reserved_slots
+            .slots
+            .lock()
+            .unwrap()
+            .contains_key("instance-cap-b"));
+
+        // Removed Instances make room for the others
+        reported_reserved_slots.remove("instance-cap-a");
+        reported_reserved_slots.report("instance-cap-b", &[DeviceUsage::Node("node-a".to_owned())]);
+        assert_eq!(count(), 1);
+        assert_eq!(
+            INSTANCE_SLOT_RESERVED_METRIC
+                .with_label_values(&["instance-cap-b", "0", "node-a"])
+                .get(),
+            1
+        );
     }
 
-    #[tokio::test]
-    async fn test_get_used_slots() {
-        let dm = crate::device_manager::MockDeviceManager::new();
-        let kube_client = Arc::new(MockIntoApi::new());
-        let stopper = Stopper::new();
-        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
+    #[test]
+    fn test_unknown_device_usage_policy_from_env() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(
+            UnknownDeviceUsagePolicy::from_env(&env).unwrap(),
+            UnknownDeviceUsagePolicy::Reserved
+        );
 
-        assert!(dpm.get_used_slots().await.is_empty());
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == UNKNOWN_DEVICE_USAGE_POLICY_LABEL)
+            .returning(|_| Ok("free".to_string()));
+        assert_eq!(
+            UnknownDeviceUsagePolicy::from_env(&env).unwrap(),
+            UnknownDeviceUsagePolicy::Free
+        );
 
-        let (s, _) = watch::channel(vec![
-            DeviceUsage::Configuration {
-                vdev: "akri.sh/config-a-1".to_owned(),
-                node: "node-a".to_owned(),
-            },
-            DeviceUsage::Node("node-a".to_owned()),
-            DeviceUsage::Node("node-b".to_owned()),
-            DeviceUsage::Unused,
-        ]);
-        let instance_plugin = Arc::new(InstanceDevicePlugin {
-            device: Device {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == UNKNOWN_DEVICE_USAGE_POLICY_LABEL)
+            .returning(|_| Ok("reserved".to_string()));
+        assert_eq!(
+            UnknownDeviceUsagePolicy::from_env(&env).unwrap(),
+            UnknownDeviceUsagePolicy::Reserved
+        );
+
+        // Invalid values are rejected rather than silently replaced by the default
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == UNKNOWN_DEVICE_USAGE_POLICY_LABEL)
+            .returning(|_| Ok("Free".to_string()));
+        assert!(UnknownDeviceUsagePolicy::from_env(&env).is_err());
+    }
+
+    fn make_plugin_with_unknown_usage(policy: UnknownDeviceUsagePolicy) -> InstanceDevicePlugin {
+        InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
                 name: "my-device".to_owned(),
                 annotations: Default::default(),
-                container_edits: ContainerEdit {
-                    ..Default::default()
-                },
+                container_edits: Default::default(),
             },
-            slots_status: Mutex::new(s),
-            node_name: "node-a".to_owned(),
-            instance_name: "instance-a".to_owned(),
-            instance_namespace: "namespace-a".to_owned(),
-            kube_client,
-            stopper: stopper.clone(),
-        });
-        dpm.instance_plugins
-            .lock()
+            &HashMap::from([("instance-a-0".to_owned(), "X:future:node-b".to_owned())]),
+            2,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap()
+        .with_unknown_usage_policy(policy)
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_unknown_usage_reserved() {
+        let plugin = make_plugin_with_unknown_usage(UnknownDeviceUsagePolicy::Reserved);
+        let slots = plugin.lock_slots().await.borrow().clone();
+        assert_eq!(
+            slots,
+            vec![
+                DeviceUsage::Unknown("X:future:node-b".to_owned()),
+                DeviceUsage::Unused
+            ]
+        );
+        assert_eq!(
+            instance_device_usage_to_device("instance-a", "node-a", slots, 2, false)
+                .unwrap()
+                .devices[0]
+                .health,
+            "Unhealthy"
+        );
+        assert!(matches!(
+            plugin
+                .claim_slot(Some(0), DeviceUsage::Node("node-a".to_owned()))
+                .await,
+            Err(DevicePluginError::SlotInUse)
+        ));
+
+        // Unknown usages are kept on updates
+        plugin
+            .update_slots(&HashMap::from([(
+                "instance-a-1".to_owned(),
+                "X:future:node-c".to_owned(),
+            )]))
             .await
-            .insert("instance-a".to_owned(), instance_plugin);
+            .unwrap();
         assert_eq!(
-            dpm.get_used_slots().await,
-            HashSet::from([
-                "akri.sh/config-a-1".to_owned(),
-                "akri.sh/instance-a-1".to_owned()
-            ])
+            plugin.lock_slots().await.borrow()[1],
+            DeviceUsage::Unknown("X:future:node-c".to_owned())
         );
     }
 
     #[tokio::test]
-    async fn test_config_plugin_add_remove_plugin() {
-        let kube_client = Arc::new(MockIntoApi::new());
-        let stopper = Stopper::new();
-        let (s, mut r) = watch::channel(vec![
-            DeviceUsage::Configuration {
-                vdev: "akri.sh/config-a-1".to_owned(),
-                node: "node-a".to_owned(),
-            },
-            DeviceUsage::Node("node-a".to_owned()),
-            DeviceUsage::Node("node-b".to_owned()),
-            DeviceUsage::Unused,
-        ]);
-        let instance_plugin = Arc::new(InstanceDevicePlugin {
-            device: Device {
-                name: "my-device".to_owned(),
-                annotations: Default::default(),
-                container_edits: ContainerEdit {
-                    ..Default::default()
-                },
-            },
+    async fn test_instance_plugin_unknown_usage_free() {
+        let plugin = make_plugin_with_unknown_usage(UnknownDeviceUsagePolicy::Free);
+        let slots = plugin.lock_slots().await.borrow().clone();
+        assert_eq!(slots, vec![DeviceUsage::Unused, DeviceUsage::Unused]);
+        assert_eq!(
+            instance_device_usage_to_device("instance-a", "node-a", slots, 2, false)
+                .unwrap()
+                .devices[0]
+                .health,
+            "Healthy"
+        );
+
+        // Unknown usages are considered free on updates
+        plugin
+            .update_slots(&HashMap::from([(
+                "instance-a-1".to_owned(),
+                "X:future:node-c".to_owned(),
+            )]))
+            .await
+            .unwrap();
+        assert_eq!(plugin.lock_slots().await.borrow()[1], DeviceUsage::Unused);
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_shrink_capacity() {
+        let patches: Arc<std::sync::Mutex<Vec<Object<PartialInstanceSlotUsage, NotUsed>>>> =
+            Default::default();
+        let local_patches = patches.clone();
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(move |_| {
+            let mut api = MockApi::new();
+            let local_patches = local_patches.clone();
+            api.expect_raw_patch().returning(move |_, patch, _| {
+                if let Patch::Apply(v) = patch {
+                    local_patches
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::from_value(v.clone()).unwrap());
+                }
+                Ok(Instance {
+                    metadata: Default::default(),
+                    spec: InstanceSpec {
+                        configuration_name: "config-a".to_owned(),
+                        cdi_name: Default::default(),
+                        capacity: 2,
+                        broker_properties: Default::default(),
+                        shared: false,
+                        nodes: Default::default(),
+                        device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
+                    },
+                })
+            });
+            Box::new(api)
+        });
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "my-device".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            &HashMap::from([
+                ("my-device-1".to_owned(), "node-a".to_owned()),
+                ("my-device-2".to_owned(), "node-a".to_owned()),
+                ("my-device-3".to_owned(), "node-a".to_owned()),
+            ]),
+            4,
+            Arc::new(kube_client),
+        )
+        .unwrap();
+        let is_flagged = |p: &Object<PartialInstanceSlotUsage, NotUsed>| {
+            p.metadata
+                .annotations
+                .as_ref()
+                .is_some_and(|a| a.contains_key(AKRI_OVER_COMMITTED_ANNOTATION_NAME))
+        };
+
+        // Shrinking below the reserved count keeps all reserved slots and flags the Instance
+        plugin.update_capacity(2).await.unwrap();
+        assert_eq!(
+            *plugin.slots_status.lock().await.borrow(),
+            vec![
+                DeviceUsage::Unused,
+                DeviceUsage::Node("node-a".to_owned()),
+                DeviceUsage::Node("node-a".to_owned()),
+                DeviceUsage::Node("node-a".to_owned()),
+            ]
+        );
+        {
+            let patches = patches.lock().unwrap();
+            assert_eq!(patches.len(), 1);
+            assert!(is_flagged(&patches[0]));
+            assert_eq!(patches[0].spec.device_usage.len(), 3);
+        }
+
+        // No new slot can be claimed while over-committed
+        assert!(matches!(
+            plugin
+                .claim_slot(None, DeviceUsage::Node("node-a".to_owned()))
+                .await,
+            Err(DevicePluginError::NoSlot)
+        ));
+        assert!(matches!(
+            plugin
+                .claim_slot(Some(0), DeviceUsage::Node("node-a".to_owned()))
+                .await,
+            Err(DevicePluginError::NoSlot)
+        ));
+
+        // Released slots beyond the capacity are removed, and the flag cleared
+        plugin.free_slot(3).await.unwrap();
+        assert_eq!(
+            *plugin.slots_status.lock().await.borrow(),
+            vec![
+                DeviceUsage::Unused,
+                DeviceUsage::Node("node-a".to_owned()),
+                DeviceUsage::Node("node-a".to_owned()),
+            ]
+        );
+        plugin.free_slot(2).await.unwrap();
+        assert_eq!(
+            *plugin.slots_status.lock().await.borrow(),
+            vec![DeviceUsage::Unused, DeviceUsage::Node("node-a".to_owned()),]
+        );
+        {
+            let patches = patches.lock().unwrap();
+            assert_eq!(patches.len(), 3);
+            assert!(!is_flagged(&patches[1]));
+            assert_eq!(
+                patches[2].spec.device_usage,
+                HashMap::from([("my-device-1".to_owned(), "node-a".to_owned())])
+            );
+        }
+
+        // Once back within capacity, free slots can be claimed again
+        assert_eq!(
+            plugin
+                .claim_slot(None, DeviceUsage::Node("node-a".to_owned()))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_coalesces_device_usage_writes() {
+        let patches: Arc<std::sync::Mutex<Vec<Object<PartialInstanceSlotUsage, NotUsed>>>> =
+            Default::default();
+        let local_patches = patches.clone();
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(move |_| {
+            let mut api = MockApi::new();
+            let local_patches = local_patches.clone();
+            api.expect_raw_patch().returning(move |_, patch, _| {
+                if let Patch::Apply(v) = patch {
+                    local_patches
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::from_value(v.clone()).unwrap());
+                }
+                Ok(Instance {
+                    metadata: Default::default(),
+                    spec: InstanceSpec {
+                        configuration_name: "config-a".to_owned(),
+                        cdi_name: Default::default(),
+                        capacity: 2,
+                        broker_properties: Default::default(),
+                        shared: false,
+                        nodes: Default::default(),
+                        device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
+                    },
+                })
+            });
+            Box::new(api)
+        });
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "my-device".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            &HashMap::new(),
+            2,
+            Arc::new(kube_client),
+        )
+        .unwrap()
+        .with_device_usage_coalescing_window(Duration::from_millis(100));
+
+        // Two rapid claims of different slots get written at once
+        let (first, second) = tokio::join!(
+            plugin.claim_slot(Some(0), DeviceUsage::Node("node-a".to_owned())),
+            plugin.claim_slot(Some(1), DeviceUsage::Node("node-a".to_owned())),
+        );
+        assert_eq!(first.unwrap(), 0);
+        assert_eq!(second.unwrap(), 1);
+        {
+            let patches = patches.lock().unwrap();
+            assert_eq!(patches.len(), 1);
+            assert_eq!(
+                patches[0].spec.device_usage,
+                HashMap::from([
+                    ("my-device-0".to_owned(), "node-a".to_owned()),
+                    ("my-device-1".to_owned(), "node-a".to_owned()),
+                ])
+            );
+        }
+
+        // Updates made after a write got sent get written by a new one
+        plugin.free_slot(1).await.unwrap();
+        let patches = patches.lock().unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(
+            patches[1].spec.device_usage,
+            HashMap::from([("my-device-0".to_owned(), "node-a".to_owned())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_allocation_audit_events() {
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(|_| {
+            let mut api = MockApi::new();
+            api.expect_raw_patch().returning(|_, _, _| {
+                Ok(Instance {
+                    metadata: Default::default(),
+                    spec: InstanceSpec {
+                        configuration_name: "config-a".to_owned(),
+                        cdi_name: Default::default(),
+                        capacity: 2,
+                        broker_properties: Default::default(),
+                        shared: false,
+                        nodes: Default::default(),
+                        device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
+                    },
+                })
+            });
+            Box::new(api)
+        });
+        let events: Arc<std::sync::Mutex<Vec<Event>>> = Default::default();
+        let local_events = events.clone();
+        let mut event_client = MockIntoApi::<Event>::new();
+        event_client
+            .expect_namespaced()
+            .with(mockall::predicate::eq("namespace-a"))
+            .returning(move |_| {
+                let mut api = MockApi::new();
+                let local_events = local_events.clone();
+                api.expect_apply()
+                    .withf(|_, field_manager| field_manager == "node-a")
+                    .returning(move |event, _| {
+                        local_events.lock().unwrap().push(event.clone());
+                        Ok(event)
+                    });
+                Box::new(api)
+            });
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "my-device".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            &HashMap::new(),
+            2,
+            Arc::new(kube_client),
+        )
+        .unwrap()
+        .with_allocation_audit_events(Some(Arc::new(event_client)));
+
+        plugin
+            .claim_slot(Some(1), DeviceUsage::Node("node-a".to_owned()))
+            .await
+            .unwrap();
+        plugin.free_slot(1).await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        for (event, reason) in events.iter().zip(["SlotAllocated", "SlotReleased"]) {
+            assert_eq!(event.reason.as_deref(), Some(reason));
+            assert_eq!(event.type_.as_deref(), Some(event::EVENT_TYPE_NORMAL));
+            assert_eq!(event.involved_object.name.as_deref(), Some("my-device"));
+            assert_eq!(
+                event.involved_object.namespace.as_deref(),
+                Some("namespace-a")
+            );
+            assert!(event
+                .metadata
+                .name
+                .as_ref()
+                .is_some_and(|name| name.starts_with("my-device-1.")));
+            assert_eq!(
+                event.source.as_ref().and_then(|s| s.host.as_deref()),
+                Some("node-a")
+            );
+        }
+        assert_eq!(
+            events[0].message.as_deref(),
+            Some("Slot my-device-1 allocated to node node-a")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_slots_records_wait() {
+        let plugin = Arc::new(
+            InstanceDevicePlugin::new(
+                "node-a".to_owned(),
+                "my-device".to_owned(),
+                "namespace-a".to_owned(),
+                Device {
+                    name: "my-device".to_owned(),
+                    annotations: Default::default(),
+                    container_edits: ContainerEdit {
+                        ..Default::default()
+                    },
+                },
+                &HashMap::new(),
+                1,
+                Arc::new(MockIntoApi::new()),
+            )
+            .unwrap(),
+        );
+        let previous_count = DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC.get_sample_count();
+        let previous_sum = DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC.get_sample_sum();
+
+        // Hold the lock while another task tries to acquire it
+        let guard = plugin.lock_slots().await;
+        let local_plugin = plugin.clone();
+        let contender = tokio::spawn(async move {
+            let _guard = local_plugin.lock_slots().await;
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(guard);
+        contender.await.unwrap();
+
+        assert!(DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC.get_sample_count() >= previous_count + 2);
+        assert!(DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC.get_sample_sum() >= previous_sum + 0.05);
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_new_over_committed() {
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "my-device".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            &HashMap::from([
+                ("my-device-0".to_owned(), "node-b".to_owned()),
+                ("my-device-2".to_owned(), "node-a".to_owned()),
+                ("my-device-3".to_owned(), "".to_owned()),
+            ]),
+            1,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap();
+
+        let slots = plugin.slots_status.lock().await.borrow().clone();
+        assert_eq!(
+            slots,
+            vec![
+                DeviceUsage::Node("node-b".to_owned()),
+                DeviceUsage::Unused,
+                DeviceUsage::Node("node-a".to_owned()),
+            ]
+        );
+        assert!(is_over_committed(&slots, 1));
+    }
+
+    #[tokio::test]
+    async fn test_free_slot() {
+        let dm = crate::device_manager::MockDeviceManager::new();
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(|_| {
+            let mut api = MockApi::new();
+            api.expect_raw_patch()
+                .with(
+                    mockall::predicate::eq("instance-a"),
+                    mockall::predicate::function(|a: &Patch<serde_json::Value>| match a {
+                        Patch::Apply(v) => {
+                            let su: Object<PartialInstanceSlotUsage, NotUsed> =
+                                serde_json::from_value(v.clone()).unwrap();
+                            error!("{:?}", su.spec.device_usage);
+                            su.spec.device_usage.is_empty()
+                        }
+                        _ => false,
+                    }),
+                    mockall::predicate::always(),
+                )
+                .returning(|_, _, _| {
+                    Ok(Instance {
+                        metadata: Default::default(),
+                        spec: InstanceSpec {
+                            configuration_name: "config-a".to_owned(),
+                            cdi_name: Default::default(),
+                            capacity: 1,
+                            broker_properties: Default::default(),
+                            shared: false,
+                            nodes: Default::default(),
+                            device_usage: Default::default(),
+                            first_discovered: None,
+                            last_seen: Default::default(),
+                        },
+                    })
+                });
+            Box::new(api)
+        });
+        let kube_client = Arc::new(kube_client);
+        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
+
+        let stopper = Stopper::new();
+
+        let (s, _) = watch::channel(vec![
+            DeviceUsage::Configuration {
+                vdev: "config-a-1".to_owned(),
+                node: "node-a".to_owned(),
+            },
+            DeviceUsage::Node("node-b".to_owned()),
+        ]);
+
+        let instance_plugin = Arc::new(InstanceDevicePlugin {
+            device: Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            slots_status: Mutex::new(s),
+            capacity: Arc::new(AtomicUsize::new(2)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
+            node_name: "node-a".to_owned(),
+            instance_name: "instance-a".to_owned(),
+            instance_namespace: "namespace-a".to_owned(),
+            kube_client,
+            stopper: stopper.clone(),
+        });
+        dpm.instance_plugins
+            .lock()
+            .await
+            .insert("instance-a".to_owned(), instance_plugin.clone());
+
+        let (s, _) = watch::channel(HashMap::from([(
+            "config-a-1".to_owned(),
+            ConfigurationSlot::DeviceUsed {
+                device: "instance-a".to_owned(),
+                slot_id: 0,
+            },
+        )]));
+
+        dpm.configuration_plugins.lock().await.insert(
+            "config-a".to_owned(),
+            Arc::new(ConfigurationDevicePlugin {
+                instances: RwLock::new(HashMap::from([("instance-a".to_owned(), instance_plugin)])),
+                slots: Arc::new(RwLock::new(s)),
+                config_name: "config-a".to_owned(),
+                resource_name: "config-a".to_owned(),
+                node_name: "node-a".to_string(),
+                drain: Default::default(),
+                stopper,
+            }),
+        );
+
+        assert!(dpm.free_slot("config-b-2".to_owned()).await.is_err());
+        assert!(dpm.free_slot("config-a-1".to_owned()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_used_slots() {
+        let dm = crate::device_manager::MockDeviceManager::new();
+        let kube_client = Arc::new(MockIntoApi::new());
+        let stopper = Stopper::new();
+        let dpm = DevicePluginManager::new("node-a".to_owned(), kube_client.clone(), Arc::new(dm));
+
+        assert!(dpm.get_used_slots().await.is_empty());
+
+        let (s, _) = watch::channel(vec![
+            DeviceUsage::Configuration {
+                vdev: "akri.sh/config-a-1".to_owned(),
+                node: "node-a".to_owned(),
+            },
+            DeviceUsage::Node("node-a".to_owned()),
+            DeviceUsage::Node("node-b".to_owned()),
+            DeviceUsage::Unused,
+        ]);
+        let instance_plugin = Arc::new(InstanceDevicePlugin {
+            device: Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
+            slots_status: Mutex::new(s),
+            capacity: Arc::new(AtomicUsize::new(4)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
+            node_name: "node-a".to_owned(),
+            instance_name: "instance-a".to_owned(),
+            instance_namespace: "namespace-a".to_owned(),
+            kube_client,
+            stopper: stopper.clone(),
+        });
+        dpm.instance_plugins
+            .lock()
+            .await
+            .insert("instance-a".to_owned(), instance_plugin);
+        assert_eq!(
+            dpm.get_used_slots().await,
+            HashSet::from([
+                "akri.sh/config-a-1".to_owned(),
+                "akri.sh/instance-a-1".to_owned()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_get_configuration_resource_name() {
+        let mut instance = Instance {
+            metadata: ObjectMeta {
+                name: Some("instance-a".to_owned()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-a".to_owned(),
+                cdi_name: "akri.sh/config-a=abcdef".to_owned(),
+                capacity: 1,
+                broker_properties: Default::default(),
+                shared: false,
+                nodes: vec!["node-a".to_owned()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        };
+        assert_eq!(get_configuration_resource_name(&instance), "config-a");
+
+        instance.annotations_mut().insert(
+            AKRI_RESOURCE_NAME_ANNOTATION_NAME.to_owned(),
+            "gpu-camera".to_owned(),
+        );
+        let resource_name = get_configuration_resource_name(&instance);
+        assert_eq!(resource_name, "gpu-camera");
+
+        // The overridden name is the one registered to the kubelet
+        let plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            resource_name,
+            "node-a".to_owned(),
+        );
+        assert_eq!(plugin.get_name(), "gpu-camera");
+
+        assert!(get_configuration_resource_name_aliases(&instance).is_empty());
+        instance.annotations_mut().insert(
+            AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME.to_owned(),
+            "camera,usb-camera".to_owned(),
+        );
+        assert_eq!(
+            get_configuration_resource_name_aliases(&instance),
+            vec!["camera", "usb-camera"]
+        );
+        // Aliases are registered to the kubelet under their own name
+        let plugin = ConfigurationDevicePlugin::new_alias(
+            "config-a",
+            "camera".to_owned(),
+            "node-a".to_owned(),
+        );
+        assert_eq!(plugin.get_name(), "camera");
+        assert_eq!(plugin.config_name, "config-a_camera");
+    }
+
+    #[test]
+    fn test_slot_alias() {
+        assert_eq!(slot_alias("config-a-0"), None);
+        assert_eq!(slot_alias("config-a_camera-0"), Some("camera"));
+        assert_eq!(slot_alias("config-a_usb_camera-12"), Some("usb_camera"));
+        assert_eq!(slot_alias("config-a"), None);
+    }
+
+    #[tokio::test]
+    async fn test_config_plugin_add_remove_plugin() {
+        let kube_client = Arc::new(MockIntoApi::new());
+        let stopper = Stopper::new();
+        let (s, mut r) = watch::channel(vec![
+            DeviceUsage::Configuration {
+                vdev: "akri.sh/config-a-1".to_owned(),
+                node: "node-a".to_owned(),
+            },
+            DeviceUsage::Node("node-a".to_owned()),
+            DeviceUsage::Node("node-b".to_owned()),
+            DeviceUsage::Unused,
+        ]);
+        let instance_plugin = Arc::new(InstanceDevicePlugin {
+            device: Device {
+                name: "my-device".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    ..Default::default()
+                },
+            },
             slots_status: Mutex::new(s),
+            capacity: Arc::new(AtomicUsize::new(4)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
@@ -1211,8 +2899,11 @@ mod tests {
             stopper: stopper.clone(),
         });
 
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "config-a".to_owned(),
+            "node-a".to_owned(),
+        );
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin.clone())
             .await;
@@ -1287,6 +2978,8 @@ mod tests {
                         shared: false,
                         nodes: Default::default(),
                         device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
                     },
                 })
             });
@@ -1312,6 +3005,13 @@ mod tests {
                 },
             },
             slots_status: Mutex::new(s),
+            capacity: Arc::new(AtomicUsize::new(4)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
@@ -1319,8 +3019,11 @@ mod tests {
             stopper: stopper.clone(),
         });
 
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "config-a".to_owned(),
+            "node-a".to_owned(),
+        );
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin)
             .await;
@@ -1343,6 +3046,145 @@ mod tests {
         );
     }
 
+    /// Waits for the slots advertised by a Configuration plugin to be the expected ones
+    async fn wait_for_config_slots(
+        plugin: &ConfigurationDevicePlugin,
+        expected: HashMap<String, ConfigurationSlot>,
+    ) {
+        let mut slots = plugin.slots.read().await.subscribe();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            slots.wait_for(|slots| *slots == expected),
+        )
+        .await
+        .expect("Configuration slots not updated in time")
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_config_plugin_allocate_through_alias() {
+        let mut kube_client = MockIntoApi::new();
+        kube_client.expect_namespaced().returning(|_| {
+            let mut api = MockApi::new();
+            api.expect_raw_patch().returning(|_, _, _| {
+                Ok(Instance {
+                    metadata: Default::default(),
+                    spec: InstanceSpec {
+                        configuration_name: "config-a".to_owned(),
+                        cdi_name: Default::default(),
+                        capacity: 2,
+                        broker_properties: Default::default(),
+                        shared: false,
+                        nodes: Default::default(),
+                        device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
+                    },
+                })
+            });
+            Box::new(api)
+        });
+        let instance_plugin = Arc::new(
+            InstanceDevicePlugin::new(
+                "node-a".to_owned(),
+                "instance-a".to_owned(),
+                "namespace-a".to_owned(),
+                Device {
+                    name: "my-device".to_owned(),
+                    annotations: Default::default(),
+                    container_edits: Default::default(),
+                },
+                &HashMap::new(),
+                2,
+                Arc::new(kube_client),
+            )
+            .unwrap(),
+        );
+        let config_plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "config-a".to_owned(),
+            "node-a".to_owned(),
+        );
+        let alias_plugin = ConfigurationDevicePlugin::new_alias(
+            "config-a",
+            "camera".to_owned(),
+            "node-a".to_owned(),
+        );
+        config_plugin
+            .add_plugin("instance-a".to_owned(), instance_plugin.clone())
+            .await;
+        alias_plugin
+            .add_plugin("instance-a".to_owned(), instance_plugin.clone())
+            .await;
+        let allocate_request = |device: &str| {
+            Request::new(AllocateRequest {
+                container_requests: vec![ContainerAllocateRequest {
+                    devices_i_ds: vec![device.to_owned()],
+                }],
+            })
+        };
+
+        // The same device is offered by the resource and its alias
+        wait_for_config_slots(
+            &alias_plugin,
+            HashMap::from([(
+                "config-a_camera-0".to_owned(),
+                ConfigurationSlot::DeviceFree("instance-a".to_owned()),
+            )]),
+        )
+        .await;
+        assert!(alias_plugin
+            .allocate(allocate_request("config-a_camera-0"))
+            .await
+            .is_ok());
+
+        // Its remaining slot is still claimable through the resource, which doesn't list the slot
+        // claimed through the alias
+        wait_for_config_slots(
+            &config_plugin,
+            HashMap::from([(
+                "config-a-0".to_owned(),
+                ConfigurationSlot::DeviceFree("instance-a".to_owned()),
+            )]),
+        )
+        .await;
+        assert!(config_plugin
+            .allocate(allocate_request("config-a-0"))
+            .await
+            .is_ok());
+        assert_eq!(
+            instance_plugin.lock_slots().await.borrow().clone(),
+            vec![
+                DeviceUsage::Configuration {
+                    vdev: "config-a_camera-0".to_owned(),
+                    node: "node-a".to_owned(),
+                },
+                DeviceUsage::Configuration {
+                    vdev: "config-a-0".to_owned(),
+                    node: "node-a".to_owned(),
+                },
+            ]
+        );
+
+        // The slot claimed through the alias gets freed through it
+        wait_for_config_slots(
+            &alias_plugin,
+            HashMap::from([(
+                "config-a_camera-0".to_owned(),
+                ConfigurationSlot::DeviceUsed {
+                    device: "instance-a".to_owned(),
+                    slot_id: 0,
+                },
+            )]),
+        )
+        .await;
+        alias_plugin.free_slot(0).await.unwrap();
+        assert_eq!(
+            instance_plugin.lock_slots().await.borrow()[0],
+            DeviceUsage::Unused
+        );
+    }
+
     #[tokio::test]
     async fn test_instance_plugin_allocate() {
         let mut kube_client = MockIntoApi::new();
@@ -1381,6 +3223,8 @@ mod tests {
                             shared: false,
                             nodes: Default::default(),
                             device_usage: Default::default(),
+                            first_discovered: None,
+                            last_seen: Default::default(),
                         },
                     })
                 });
@@ -1406,6 +3250,13 @@ mod tests {
                 },
             },
             slots_status: Mutex::new(s),
+            capacity: Arc::new(AtomicUsize::new(4)),
+            quarantined: Default::default(),
+            initial_delay: Default::default(),
+            unknown_usage_policy: Default::default(),
+            device_manifest_directory: None,
+            device_usage_writer: Default::default(),
+            drain: Default::default(),
             node_name: "node-a".to_owned(),
             instance_name: "instance-a".to_owned(),
             instance_namespace: "namespace-a".to_owned(),
@@ -1431,6 +3282,280 @@ mod tests {
             .is_ok());
     }
 
+    #[tokio::test]
+    async fn test_instance_plugin_allocate_device_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "akri.sh/config-a-a1b2c3".to_owned(),
+                annotations: Default::default(),
+                container_edits: ContainerEdit {
+                    env: vec!["DEVICE_ID=a".to_owned()],
+                    ..Default::default()
+                },
+            },
+            &HashMap::new(),
+            1,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap()
+        .with_device_manifest_directory(Some(dir.path().to_path_buf()));
+
+        let response = plugin.container_allocate_response().unwrap();
+        let host_path = dir.path().join("akri.sh-config-a-a1b2c3.json");
+        let container_path = "/etc/akri/devices/akri.sh-config-a-a1b2c3.json";
+        let manifest: device_manifest::DeviceManifest =
+            serde_json::from_str(&std::fs::read_to_string(&host_path).unwrap()).unwrap();
+        assert_eq!(
+            manifest.properties,
+            BTreeMap::from([("DEVICE_ID".to_owned(), "a".to_owned())])
+        );
+        assert_eq!(
+            response.mounts,
+            vec![super::super::v1beta1::Mount {
+                container_path: container_path.to_owned(),
+                host_path: host_path.to_string_lossy().into_owned(),
+                read_only: true,
+            }]
+        );
+        assert_eq!(response.envs["AKRI_DEVICE_MANIFEST"], container_path);
+        assert_eq!(response.envs["AKRI_DEVICE_MANIFEST_A1B2C3"], container_path);
+        assert_eq!(response.envs["DEVICE_ID"], "a");
+
+        // The manifest goes away along with the plugin
+        plugin.stop();
+        assert!(!host_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_quarantined() {
+        let instance_plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_string(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            &HashMap::new(),
+            2,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap();
+        instance_plugin.set_quarantined(true).await;
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+
+        // All slots are reported unhealthy and none can be claimed
+        assert_eq!(
+            instance_stream.next().await.unwrap().unwrap(),
+            ListAndWatchResponse {
+                devices: vec![
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-0".to_owned(),
+                        health: "Unhealthy".to_owned(),
+                        topology: None,
+                    },
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-1".to_owned(),
+                        health: "Unhealthy".to_owned(),
+                        topology: None,
+                    },
+                ]
+            }
+        );
+        assert!(matches!(
+            instance_plugin
+                .claim_slot(Some(0), DeviceUsage::Node("node-a".to_owned()))
+                .await,
+            Err(DevicePluginError::NoSlot)
+        ));
+
+        // Slots are healthy again once the quarantine is lifted
+        instance_plugin.set_quarantined(false).await;
+        assert_eq!(
+            instance_stream.next().await.unwrap().unwrap(),
+            ListAndWatchResponse {
+                devices: vec![
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-0".to_owned(),
+                        health: "Healthy".to_owned(),
+                        topology: None,
+                    },
+                    crate::plugin_manager::v1beta1::Device {
+                        id: "instance-a-1".to_owned(),
+                        health: "Healthy".to_owned(),
+                        topology: None,
+                    },
+                ]
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plugins_drain() {
+        let drain = Arc::new(Drain::default());
+        let instance_plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_string(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            &HashMap::new(),
+            2,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap()
+        .with_drain(drain.clone());
+        let config_plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "config-a".to_owned(),
+            "node-a".to_owned(),
+        )
+        .with_drain(drain.clone());
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+        assert!(instance_stream
+            .next()
+            .await
+            .unwrap()
+            .unwrap()
+            .devices
+            .iter()
+            .all(|d| d.health == "Healthy"));
+
+        // Allocations in progress are waited for
+        let allocation = drain.start_allocation().await.unwrap();
+        let local_drain = drain.clone();
+        let draining = tokio::spawn(async move { local_drain.drain().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!draining.is_finished());
+        drop(allocation);
+        draining.await.unwrap();
+
+        // New allocations are rejected with a retriable error
+        let allocate_request = |device: &str| {
+            Request::new(AllocateRequest {
+                container_requests: vec![ContainerAllocateRequest {
+                    devices_i_ds: vec![device.to_owned()],
+                }],
+            })
+        };
+        let status = instance_plugin
+            .allocate(allocate_request("instance-a-0"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        let status = config_plugin
+            .allocate(allocate_request("config-a-0"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        // All slots get reported unhealthy once refreshed
+        instance_plugin.lock_slots().await.send_modify(|_| {});
+        assert!(instance_stream
+            .next()
+            .await
+            .unwrap()
+            .unwrap()
+            .devices
+            .iter()
+            .all(|d| d.health == "Unhealthy"));
+        config_plugin.slots.write().await.send_modify(|slots| {
+            slots.insert(
+                "config-a-0".to_owned(),
+                ConfigurationSlot::DeviceFree("instance-a".to_owned()),
+            );
+        });
+        let mut config_stream = config_plugin.list_and_watch().await.unwrap().into_inner();
+        assert_eq!(
+            config_stream.next().await.unwrap().unwrap(),
+            ListAndWatchResponse {
+                devices: vec![crate::plugin_manager::v1beta1::Device {
+                    id: "config-a-0".to_owned(),
+                    health: "Unhealthy".to_owned(),
+                    topology: None,
+                }]
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_list_and_watch_initial_delay() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_list_and_watch_initial_delay(&env), Duration::ZERO);
+
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Ok("not-a-number".to_string()));
+        assert_eq!(get_list_and_watch_initial_delay(&env), Duration::ZERO);
+
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == LIST_AND_WATCH_INITIAL_DELAY_MS_LABEL)
+            .returning(|_| Ok("1500".to_string()));
+        assert_eq!(
+            get_list_and_watch_initial_delay(&env),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn test_get_device_usage_coalescing_window() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(
+            get_device_usage_coalescing_window(&env),
+            DEFAULT_DEVICE_USAGE_COALESCING_WINDOW
+        );
+
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == DEVICE_USAGE_COALESCING_WINDOW_MS_LABEL)
+            .returning(|_| Ok("0".to_string()));
+        assert_eq!(get_device_usage_coalescing_window(&env), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_instance_plugin_list_and_watch_initial_delay() {
+        let delay = Duration::from_millis(200);
+        let instance_plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_string(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            &HashMap::new(),
+            1,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap()
+        .with_initial_delay(delay);
+
+        let start = Instant::now();
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+        assert!(instance_stream.next().await.unwrap().is_ok());
+        assert!(start.elapsed() >= delay);
+
+        // Only the first list_and_watch is delayed
+        let start = Instant::now();
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+        assert!(instance_stream.next().await.unwrap().is_ok());
+        assert!(start.elapsed() < delay);
+    }
+
     #[tokio::test]
     async fn test_list_and_watch() {
         let kube_client = Arc::new(MockIntoApi::new());
@@ -1450,8 +3575,11 @@ mod tests {
             )
             .unwrap(),
         );
-        let config_plugin =
-            ConfigurationDevicePlugin::new("config-a".to_owned(), "node-a".to_owned());
+        let config_plugin = ConfigurationDevicePlugin::new(
+            "config-a".to_owned(),
+            "config-a".to_owned(),
+            "node-a".to_owned(),
+        );
         config_plugin
             .add_plugin("instance-a".to_owned(), instance_plugin.clone())
             .await;
@@ -1540,4 +3668,102 @@ mod tests {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_list_and_watch_device_usage_beyond_capacity() {
+        let instance_plugin = InstanceDevicePlugin::new(
+            "node-a".to_owned(),
+            "instance-a".to_owned(),
+            "namespace-a".to_owned(),
+            Device {
+                name: "my-device".to_string(),
+                annotations: Default::default(),
+                container_edits: Default::default(),
+            },
+            &HashMap::from([
+                ("instance-a-0".to_owned(), "".to_owned()),
+                ("instance-a-1".to_owned(), "".to_owned()),
+                ("instance-a-2".to_owned(), "".to_owned()),
+                ("instance-a-3".to_owned(), "node-a".to_owned()),
+            ]),
+            2,
+            Arc::new(MockIntoApi::new()),
+        )
+        .unwrap();
+        let mut instance_stream = instance_plugin.list_and_watch().await.unwrap().into_inner();
+
+        // The free slot beyond the capacity is not advertised, and the reserved one leaves room
+        // for a single free slot
+        let devices = instance_stream.next().await.unwrap().unwrap().devices;
+        assert_eq!(
+            devices,
+            vec![
+                crate::plugin_manager::v1beta1::Device {
+                    id: "instance-a-0".to_owned(),
+                    health: "Healthy".to_owned(),
+                    topology: None,
+                },
+                crate::plugin_manager::v1beta1::Device {
+                    id: "instance-a-1".to_owned(),
+                    health: "Unhealthy".to_owned(),
+                    topology: None,
+                },
+                crate::plugin_manager::v1beta1::Device {
+                    id: "instance-a-3".to_owned(),
+                    health: "Healthy".to_owned(),
+                    topology: None,
+                },
+            ]
+        );
+        assert_eq!(devices.iter().filter(|d| d.health == "Healthy").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_removes_finalizer_of_torn_down_instance() {
+        for managed_finalizers in [true, false] {
+            let mut kube_client = MockIntoApi::new();
+            kube_client
+                .expect_namespaced()
+                .with(mockall::predicate::eq("namespace-a"))
+                .returning(|_| {
+                    let mut api = MockApi::new();
+                    api.expect_remove_finalizer()
+                        .withf(|instance: &Instance, finalizer| {
+                            instance.name_any() == "instance-a" && finalizer == "node-a"
+                        })
+                        .times(1)
+                        .returning(|_, _| Ok(()));
+                    Box::new(api)
+                });
+            let dpm = Arc::new(
+                DevicePluginManager::new(
+                    "node-a".to_owned(),
+                    Arc::new(kube_client),
+                    Arc::new(crate::device_manager::MockDeviceManager::new()),
+                )
+                .with_managed_finalizers(managed_finalizers),
+            );
+            // This node no longer exposes the Instance
+            let instance = Arc::new(Instance {
+                metadata: ObjectMeta {
+                    name: Some("instance-a".to_string()),
+                    namespace: Some("namespace-a".to_string()),
+                    finalizers: Some(vec!["node-a".to_string()]),
+                    ..Default::default()
+                },
+                spec: InstanceSpec {
+                    configuration_name: "config-a".to_owned(),
+                    cdi_name: "akri.sh/config-a=instance-a".to_owned(),
+                    capacity: 1,
+                    broker_properties: Default::default(),
+                    shared: true,
+                    nodes: vec!["node-b".to_string()],
+                    device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
+                },
+            });
+            assert!(reconcile(instance, dpm).await.is_ok());
+        }
+    }
 }
@@ -1,6 +1,6 @@
-use std::{convert::TryFrom, path::Path, sync::Arc, time::SystemTime};
+use std::{convert::TryFrom, future::Future, path::Path, sync::Arc, time::SystemTime};
 
-use akri_shared::uds::unix_stream;
+use akri_shared::{akri::retry::backoff, os::env_var::EnvVarQuery, uds::unix_stream};
 use async_trait::async_trait;
 use futures::{StreamExt, TryFutureExt};
 use thiserror::Error;
@@ -21,6 +21,25 @@ pub const DEVICE_PLUGIN_PATH: &str = "/var/lib/kubelet/device-plugins";
 /// Path of the Kubelet registry socket
 pub const KUBELET_SOCKET: &str = "/var/lib/kubelet/device-plugins/kubelet.sock";
 
+/// Name of the environment variable that sets how many times registering a device plugin with kubelet is
+/// attempted before giving up (e.g. while kubelet restarts)
+pub const KUBELET_REGISTRATION_MAX_ATTEMPTS_LABEL: &str = "KUBELET_REGISTRATION_MAX_ATTEMPTS";
+/// Number of kubelet registration attempts when unset
+pub const DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS: u8 = 5;
+
+/// Gets the maximum number of kubelet registration attempts from the environment, using the default for
+/// unset or invalid values
+pub fn get_kubelet_registration_max_attempts(env_var_query: &impl EnvVarQuery) -> u8 {
+    env_var_query
+        .get_env_var(KUBELET_REGISTRATION_MAX_ATTEMPTS_LABEL)
+        .ok()
+        .and_then(|v| v.parse::<u8>().ok())
+        .filter(|attempts| *attempts > 0)
+        .unwrap_or(DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS)
+}
+
+use crate::util::metrics::KUBELET_REGISTRATION_ATTEMPTS_METRIC;
+
 use super::v1beta1::{
     device_plugin_server::{DevicePlugin, DevicePluginServer},
     registration_client, AllocateRequest, AllocateResponse, DevicePluginOptions, Empty,
@@ -45,7 +64,9 @@ pub(super) trait InternalDevicePlugin: Sync + Send {
 }
 
 pub(super) struct DeviceUsageStream<T: Clone + 'static + Send + Sync> {
-    pub device_usage_to_device: fn(&str, &str, T) -> Result<ListAndWatchResponse, tonic::Status>,
+    #[allow(clippy::type_complexity)]
+    pub device_usage_to_device:
+        Box<dyn Fn(&str, &str, T) -> Result<ListAndWatchResponse, tonic::Status> + Send + Sync>,
     pub input_stream: futures::stream::Abortable<WatchStream<T>>,
     pub device_name: String,
     pub node_name: String,
@@ -139,6 +160,7 @@ pub enum RunnerError {
 
 pub(super) async fn serve_and_register_plugin<T: Clone + 'static + Send + Sync>(
     plugin: Arc<dyn InternalDevicePlugin<DeviceStore = T>>,
+    registration_max_attempts: u8,
 ) -> Result<(), RunnerError> {
     let device_plugin_name = plugin.get_name();
     let plugin_impl = DevicePluginImpl {
@@ -190,13 +212,62 @@ pub(super) async fn serve_and_register_plugin<T: Clone + 'static + Send + Sync>(
         std::fs::remove_file(socket_to_delete).unwrap_or(());
     });
 
-    if let Err(e) = register_plugin(device_plugin_name, device_endpoint, socket_path).await {
+    if let Err(e) = register_with_retry(&device_plugin_name, registration_max_attempts, || {
+        register_plugin(
+            device_plugin_name.clone(),
+            device_endpoint.clone(),
+            socket_path.clone(),
+        )
+    })
+    .await
+    {
         plugin.stop();
         return Err(e);
     }
     Ok(())
 }
 
+/// Calls `register` until it succeeds, at most `max_attempts` times, backing off between attempts
+async fn register_with_retry<F, Fut>(
+    device_plugin_name: &str,
+    max_attempts: u8,
+    mut register: F,
+) -> Result<(), RunnerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), RunnerError>>,
+{
+    let mut attempt: u8 = 0;
+    loop {
+        match register().await {
+            Ok(()) => {
+                KUBELET_REGISTRATION_ATTEMPTS_METRIC
+                    .with_label_values(&["success"])
+                    .inc();
+                return Ok(());
+            }
+            Err(e) => {
+                KUBELET_REGISTRATION_ATTEMPTS_METRIC
+                    .with_label_values(&["failure"])
+                    .inc();
+                attempt += 1;
+                if attempt >= max_attempts {
+                    error!(
+                        "register - giving up registering {} with kubelet after {} attempts",
+                        device_plugin_name, attempt
+                    );
+                    return Err(e);
+                }
+                warn!(
+                    "register - failed to register {} with kubelet (attempt {}/{}), retrying",
+                    device_plugin_name, attempt, max_attempts
+                );
+                backoff(attempt - 1).await;
+            }
+        }
+    }
+}
+
 async fn register_plugin(
     device_plugin_name: String,
     device_endpoint: String,
@@ -249,3 +320,77 @@ async fn register_plugin(
         .map_err(|_| RunnerError::RegistrationError)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use akri_shared::os::env_var::MockEnvVarQuery;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_with_retry_transient_failure() {
+        let failures_before = KUBELET_REGISTRATION_ATTEMPTS_METRIC
+            .with_label_values(&["failure"])
+            .get();
+        let calls = AtomicUsize::new(0);
+        // kubelet is unreachable for the first attempt, e.g. while it restarts
+        let result = register_with_retry("plugin-a", 3, || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call == 0 {
+                    Err(RunnerError::RegistrationError)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(
+            KUBELET_REGISTRATION_ATTEMPTS_METRIC
+                .with_label_values(&["failure"])
+                .get()
+                > failures_before
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_with_retry_gives_up() {
+        let calls = AtomicUsize::new(0);
+        let result = register_with_retry("plugin-a", 2, || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(RunnerError::RegistrationError) }
+        })
+        .await;
+        assert!(matches!(result, Err(RunnerError::RegistrationError)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_get_kubelet_registration_max_attempts() {
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(
+            get_kubelet_registration_max_attempts(&env),
+            DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS
+        );
+
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .withf(|label| label == KUBELET_REGISTRATION_MAX_ATTEMPTS_LABEL)
+            .returning(|_| Ok("10".to_string()));
+        assert_eq!(get_kubelet_registration_max_attempts(&env), 10);
+
+        // At least one attempt is always made
+        let mut env = MockEnvVarQuery::new();
+        env.expect_get_env_var().returning(|_| Ok("0".to_string()));
+        assert_eq!(
+            get_kubelet_registration_max_attempts(&env),
+            DEFAULT_KUBELET_REGISTRATION_MAX_ATTEMPTS
+        );
+    }
+}
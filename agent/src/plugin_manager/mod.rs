@@ -2,5 +2,5 @@ pub mod v1; // Prost generated podresources module
 pub mod v1beta1; // Prost generated pluginapi module
 
 pub mod device_plugin_instance_controller;
-mod device_plugin_runner;
+pub mod device_plugin_runner;
 pub mod device_plugin_slot_reclaimer;
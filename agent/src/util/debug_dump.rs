@@ -0,0 +1,256 @@
+//! Debug endpoint dumping a snapshot of the agent's discovery state as JSON, to help diagnose why
+//! some Instances don't show up. It is only served when the `ENABLE_DEBUG_DUMP` environment variable is
+//! set to `true`, and only on the loopback interface unless `DEBUG_DUMP_ADDRESS` says otherwise, as the
+//! endpoint is not authenticated and the agent runs on the host network.
+
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use akri_shared::{akri::instance::Instance, os::env_var::EnvVarQuery};
+use kube::ResourceExt;
+use kube_runtime::reflector::Store;
+use warp::Filter;
+
+use super::discovery_configuration_controller::ControllerContext;
+use crate::discovery_handler_manager::discovery_handler_registry::{
+    DiscoveryHandlerRegistry, RegistryState,
+};
+
+/// Name of the environment variable that enables the debug dump endpoint
+pub const ENABLE_DEBUG_DUMP_LABEL: &str = "ENABLE_DEBUG_DUMP";
+/// Name of the environment variable that sets the port of the debug dump endpoint
+pub const DEBUG_DUMP_PORT_LABEL: &str = "DEBUG_DUMP_PORT";
+/// Name of the environment variable that sets the address the debug dump endpoint listens on
+pub const DEBUG_DUMP_ADDRESS_LABEL: &str = "DEBUG_DUMP_ADDRESS";
+const DEFAULT_DEBUG_DUMP_PORT: u16 = 8082;
+const DEFAULT_DEBUG_DUMP_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Snapshot of the agent's discovery state
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentState {
+    pub node_name: String,
+    /// Configurations processed by the agent
+    pub configurations: Vec<String>,
+    /// Registered Discovery Handlers and in-flight discovery requests
+    pub discovery: RegistryState,
+    /// Nodes of the Instances known to the agent, by `namespace/name`
+    pub instances: BTreeMap<String, Vec<String>>,
+}
+
+/// Returns the address to serve the debug dump endpoint on, if it is enabled
+pub fn debug_dump_address(env_var_query: &impl EnvVarQuery) -> Option<SocketAddr> {
+    opt_in_endpoint_address(
+        env_var_query,
+        ENABLE_DEBUG_DUMP_LABEL,
+        DEBUG_DUMP_ADDRESS_LABEL,
+        DEBUG_DUMP_PORT_LABEL,
+        DEFAULT_DEBUG_DUMP_PORT,
+    )
+}
+
+/// Returns the address to serve an unauthenticated endpoint on, if `enable_label` is set to `true`.
+/// It listens on the loopback interface and `default_port` unless `address_label` and `port_label`
+/// say otherwise.
+pub(crate) fn opt_in_endpoint_address(
+    env_var_query: &impl EnvVarQuery,
+    enable_label: &str,
+    address_label: &str,
+    port_label: &str,
+    default_port: u16,
+) -> Option<SocketAddr> {
+    let enabled = env_var_query.get_env_var(enable_label).ok()?;
+    match enabled.parse::<bool>() {
+        Ok(true) => {}
+        Ok(false) => return None,
+        Err(e) => {
+            error!(
+                "opt_in_endpoint_address - invalid {} value {:?}: {}",
+                enable_label, enabled, e
+            );
+            return None;
+        }
+    }
+    let ip = env_var_query
+        .get_env_var(address_label)
+        .ok()
+        .and_then(|address| address.parse().ok())
+        .unwrap_or(DEFAULT_DEBUG_DUMP_ADDRESS);
+    let port = env_var_query
+        .get_env_var(port_label)
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(default_port);
+    Some(SocketAddr::new(ip, port))
+}
+
+async fn collect_agent_state(
+    node_name: &str,
+    configurations: Vec<String>,
+    dh_registry: &dyn DiscoveryHandlerRegistry,
+    instances_cache: &Store<Instance>,
+) -> AgentState {
+    AgentState {
+        node_name: node_name.to_string(),
+        configurations,
+        discovery: dh_registry.state().await,
+        instances: instances_cache
+            .state()
+            .iter()
+            .map(|instance| {
+                (
+                    format!(
+                        "{}/{}",
+                        instance.namespace().unwrap_or_default(),
+                        instance.name_any()
+                    ),
+                    instance.spec.nodes.clone(),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Take a snapshot of the agent's discovery state
+pub async fn agent_state(ctx: &ControllerContext) -> AgentState {
+    collect_agent_state(
+        &ctx.agent_identifier,
+        ctx.configuration_guards.configurations(),
+        ctx.dh_registry.as_ref(),
+        &ctx.instances_cache,
+    )
+    .await
+}
+
+/// Serves the agent's discovery state as JSON over a web service at /debug/state
+pub async fn run_debug_dump_server(ctx: Arc<ControllerContext>, address: SocketAddr) {
+    info!("starting debug dump server on {} at /debug/state", address);
+    let state_route = warp::path!("debug" / "state")
+        .and(warp::get())
+        .then(move || {
+            let ctx = ctx.clone();
+            async move { warp::reply::json(&agent_state(&ctx).await) }
+        });
+    warp::serve(state_route).run(address).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery_handler_manager::discovery_handler_registry::{
+        MockDiscoveryHandlerRegistry, RequestState,
+    };
+    use akri_shared::{akri::instance::InstanceSpec, os::env_var::MockEnvVarQuery};
+    use kube::core::ObjectMeta;
+    use std::env::VarError;
+
+    fn debug_dump_env(
+        enabled: Option<&'static str>,
+        address: Option<&'static str>,
+        port: Option<&'static str>,
+    ) -> MockEnvVarQuery {
+        let mut env = MockEnvVarQuery::new();
+        for (label, value) in [
+            (ENABLE_DEBUG_DUMP_LABEL, enabled),
+            (DEBUG_DUMP_ADDRESS_LABEL, address),
+            (DEBUG_DUMP_PORT_LABEL, port),
+        ] {
+            env.expect_get_env_var()
+                .with(mockall::predicate::eq(label))
+                .returning(move |_| value.map(String::from).ok_or(VarError::NotPresent));
+        }
+        env
+    }
+
+    #[test]
+    fn test_debug_dump_address() {
+        for enabled in [None, Some("false"), Some("1"), Some("yes")] {
+            assert_eq!(
+                debug_dump_address(&debug_dump_env(enabled, None, None)),
+                None
+            );
+        }
+        assert_eq!(
+            debug_dump_address(&debug_dump_env(Some("true"), None, None)),
+            Some(SocketAddr::from(([127, 0, 0, 1], DEFAULT_DEBUG_DUMP_PORT)))
+        );
+        assert_eq!(
+            debug_dump_address(&debug_dump_env(Some("true"), Some("0.0.0.0"), Some("9000"))),
+            Some(SocketAddr::from(([0, 0, 0, 0], 9000)))
+        );
+        // An invalid address falls back to the loopback interface
+        assert_eq!(
+            debug_dump_address(&debug_dump_env(Some("true"), Some("anywhere"), None)),
+            Some(SocketAddr::from(([127, 0, 0, 1], DEFAULT_DEBUG_DUMP_PORT)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect_agent_state() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_state().returning(|| RegistryState {
+            handlers: BTreeMap::from([
+                ("debugEcho".to_string(), vec!["debugEcho-local".to_string()]),
+                (
+                    "onvif".to_string(),
+                    vec!["onvif-local".to_string(), "onvif-remote".to_string()],
+                ),
+            ]),
+            requests: BTreeMap::from([(
+                "config-1".to_string(),
+                RequestState {
+                    handler_name: "debugEcho".to_string(),
+                    endpoints: 1,
+                    devices: 2,
+                },
+            )]),
+        });
+        let (store, mut writer) = kube_runtime::reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![Instance {
+            metadata: ObjectMeta {
+                name: Some("config-1-a".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-1".to_string(),
+                cdi_name: "akri.sh/config-1=a".to_string(),
+                capacity: 1,
+                broker_properties: Default::default(),
+                shared: false,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        }]));
+
+        let state = collect_agent_state(
+            "node-a",
+            vec!["config-1".to_string(), "config-2".to_string()],
+            &registry,
+            &store,
+        )
+        .await;
+        assert_eq!(
+            serde_json::to_value(state).unwrap(),
+            serde_json::json!({
+                "nodeName": "node-a",
+                "configurations": ["config-1", "config-2"],
+                "discovery": {
+                    "handlers": {
+                        "debugEcho": ["debugEcho-local"],
+                        "onvif": ["onvif-local", "onvif-remote"],
+                    },
+                    "requests": {
+                        "config-1": {"handlerName": "debugEcho", "endpoints": 1, "devices": 2},
+                    },
+                },
+                "instances": {"namespace-a/config-1-a": ["node-a"]},
+            })
+        );
+    }
+}
@@ -1,22 +1,41 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    path::PathBuf,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use akri_shared::{
     akri::{
-        configuration::{Configuration, DiscoveryProperty},
+        configuration::{Configuration, DiscoveryHandlerInfo, DiscoveryProperty},
         instance::Instance,
+        node_annotation_name, AKRI_CONFIGURATION_GENERATION_ANNOTATION_NAME,
+        AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME, AKRI_LAST_WARNING_ANNOTATION_NAME,
+        AKRI_REDISCOVER_ANNOTATION_NAME, AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME,
+        AKRI_RESOURCE_NAME_ANNOTATION_NAME, AKRI_SHARED_SOURCE_ANNOTATION_NAME,
+        AKRI_SHARED_SOURCE_CONFIGURATION, AKRI_SHARED_SOURCE_DISCOVERY_HANDLER,
+    },
+    k8s::{
+        api::{Api, IntoApi},
+        event, ERROR_CONFLICT, ERROR_NOT_FOUND,
+    },
+    os::{
+        env_var::{get_env_bool, EnvVarQuery},
+        file,
     },
-    k8s::api::IntoApi,
 };
 use futures::StreamExt;
-use tokio::sync::mpsc;
+use k8s_openapi::{
+    api::core::v1::{Event, Node},
+    apimachinery::pkg::apis::meta::v1::Time,
+};
+use tokio::{sync::mpsc, time::Instant};
 
 use crate::discovery_handler_manager::{
     discovery_handler_registry::DiscoveryHandlerRegistry, DiscoveryError,
 };
+use crate::util::{health::Heartbeat, metrics::INSTANCE_COUNT_METRIC};
 
 use kube::{Resource, ResourceExt};
 use kube_runtime::{
@@ -34,18 +53,484 @@ pub enum Error {
     Other(#[from] anyhow::Error),
 }
 
+/// Delay before the next discovery pass of Configurations that don't set a discoveryPollIntervalSecs
 const SUCCESS_REQUEUE: Duration = Duration::from_secs(600);
 
-pub trait DiscoveryConfigurationKubeClient: IntoApi<Configuration> + IntoApi<Instance> {}
+/// Minimum age of the last seen time of a node on an Instance before the node refreshes it
+const LAST_SEEN_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+/// Delay before checking again whether the Instances of a deleted Configuration are gone
+const DELETION_REQUEUE: Duration = Duration::from_secs(5);
+/// Delay between two updates of the heartbeat checked by the liveness endpoint
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Name of the environment variable that sets whether the agent manages its finalizers on Configurations and Instances
+pub const AGENT_MANAGED_FINALIZERS_LABEL: &str = "AGENT_MANAGED_FINALIZERS";
+
+/// Get whether the agent manages its finalizers, defaults to true.
+///
+/// The agent is the only component managing finalizers: it adds one named after its node to the
+/// Configurations it discovers for and to the Instances it exposes a device plugin for, and removes
+/// them once this node's Instances are torn down. The controller never adds or removes finalizers.
+/// When disabled, the agent only removes the finalizers it previously added, and deleted
+/// Configurations and Instances go away right away.
+pub fn get_agent_managed_finalizers(env_var_query: &impl EnvVarQuery) -> bool {
+    get_env_bool(env_var_query, AGENT_MANAGED_FINALIZERS_LABEL, true)
+}
+
+/// Reason of the Warning Event reported when a Configuration discovers more devices than its maximum number of Instances
+pub const MAX_INSTANCES_REACHED_EVENT_REASON: &str = "MaxInstancesReached";
+/// Reason of the Warning Event reported when discovery of a Configuration is skipped as its Discovery Handler
+/// reported its backend down
+pub const DISCOVERY_DEGRADED_EVENT_REASON: &str = "DiscoveryDegraded";
+/// Kind of warning reported when a Configuration discovers more devices than its maximum number of Instances
+const MAX_INSTANCES_WARNING: &str = "max-instances";
+/// Kind of warning reported when discovery of a Configuration is skipped as its Discovery Handler reported
+/// its backend down
+const DISCOVERY_DEGRADED_WARNING: &str = "discovery-degraded";
+
+/// Name of the environment variable that sets the maximum number of Instances written concurrently,
+/// Instances are all written at once if unset
+pub const INSTANCE_BATCH_SIZE_LABEL: &str = "INSTANCE_BATCH_SIZE";
+/// Name of the environment variable that sets the delay (in milliseconds) between batches of Instance writes
+pub const INSTANCE_BATCH_DELAY_MS_LABEL: &str = "INSTANCE_BATCH_DELAY_MS";
+const DEFAULT_INSTANCE_BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// Bounds the number of Instances written concurrently during a reconciliation and the delay
+/// between batches, to smooth the write load on the API server when many devices get discovered at once.
+/// Batching is opt-in, by default all Instances are written at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceBatching {
+    pub batch_size: usize,
+    pub batch_delay: Duration,
+}
+
+impl Default for InstanceBatching {
+    fn default() -> Self {
+        InstanceBatching {
+            batch_size: usize::MAX,
+            batch_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl InstanceBatching {
+    /// Gets batching settings from the environment, Instances are not batched unless a valid batch
+    /// size is set, the delay between batches then defaults to 100ms
+    pub fn from_env(env_var_query: &impl EnvVarQuery) -> Self {
+        let Some(batch_size) = env_var_query
+            .get_env_var(INSTANCE_BATCH_SIZE_LABEL)
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|size| *size > 0)
+        else {
+            return InstanceBatching::default();
+        };
+        InstanceBatching {
+            batch_size,
+            batch_delay: env_var_query
+                .get_env_var(INSTANCE_BATCH_DELAY_MS_LABEL)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_INSTANCE_BATCH_DELAY),
+        }
+    }
+}
+
+/// Name of the environment variable that sets the maximum random delay (in milliseconds) before the first discovery of each Configuration
+pub const DISCOVERY_JITTER_MAX_MS_LABEL: &str = "DISCOVERY_JITTER_MAX_MS";
+
+/// Delays the first discovery of each Configuration by a random amount, up to `max`, so that agents
+/// starting at the same time (e.g. upon a DaemonSet rollout) don't all write their Instances at once.
+#[derive(Debug, Default)]
+pub struct DiscoveryJitter {
+    pub max: Duration,
+    /// Time before which the first discovery of each Configuration doesn't start, keyed by the
+    /// namespace and name of the Configuration
+    deadlines: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl DiscoveryJitter {
+    pub fn new(max: Duration) -> Self {
+        DiscoveryJitter {
+            max,
+            deadlines: Default::default(),
+        }
+    }
+
+    /// Gets the maximum delay from the environment, no delay gets applied if unset or invalid
+    pub fn from_env(env_var_query: &impl EnvVarQuery) -> Self {
+        DiscoveryJitter::new(
+            env_var_query
+                .get_env_var(DISCOVERY_JITTER_MAX_MS_LABEL)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns the delay left to wait for before the first discovery of the given Configuration,
+    /// or None if there is no delay or it has elapsed. The delay is drawn upon the first call for
+    /// a Configuration, later calls (e.g. upon other reconciliation triggers) wait for the same deadline.
+    fn first_discovery_delay(
+        &self,
+        namespace: &str,
+        configuration: &str,
+        now: Instant,
+    ) -> Option<Duration> {
+        if self.max.is_zero() {
+            return None;
+        }
+        let deadline = *self
+            .deadlines
+            .lock()
+            .unwrap()
+            .entry((namespace.to_string(), configuration.to_string()))
+            .or_insert_with(|| now + self.max.mul_f64(rand::random::<f64>()));
+        Some(deadline.saturating_duration_since(now)).filter(|delay| !delay.is_zero())
+    }
+}
+
+/// Name of the environment variable that enables exporting discovery results, to a file per Configuration in the given directory
+pub const DISCOVERY_EXPORT_DIRECTORY_LABEL: &str = "DISCOVERY_EXPORT_DIRECTORY";
+/// Name of the environment variable that sets the size (in bytes) at which an export file gets rotated.
+/// If unset (or zero), export files only contain the latest discovery cycle.
+pub const DISCOVERY_EXPORT_MAX_BYTES_LABEL: &str = "DISCOVERY_EXPORT_MAX_BYTES";
+
+/// Writes the devices discovered during each discovery cycle to a JSON file on the node,
+/// for consumption by local tooling without querying the API server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveryExport {
+    pub directory: PathBuf,
+    pub max_bytes: Option<u64>,
+}
+
+impl DiscoveryExport {
+    /// Gets export settings from the environment, returns None if exporting is not enabled
+    pub fn from_env(env_var_query: &impl EnvVarQuery) -> Option<Self> {
+        let directory = env_var_query
+            .get_env_var(DISCOVERY_EXPORT_DIRECTORY_LABEL)
+            .ok()
+            .filter(|d| !d.is_empty())?;
+        Some(DiscoveryExport {
+            directory: PathBuf::from(directory),
+            max_bytes: env_var_query
+                .get_env_var(DISCOVERY_EXPORT_MAX_BYTES_LABEL)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|max| *max > 0),
+        })
+    }
+
+    /// Path of the export file of a Configuration, Configurations of the same name in different
+    /// namespaces get different files as namespaces cannot contain dots
+    fn path(&self, namespace: &str, configuration: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}.json", namespace, configuration))
+    }
+
+    /// Writes one JSON line describing the discovery cycle, rotating or truncating the file as configured
+    fn export(
+        &self,
+        namespace: &str,
+        configuration: &str,
+        node: &str,
+        instances: &[Instance],
+    ) -> std::io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let devices: Vec<serde_json::Value> = instances
+            .iter()
+            .map(|instance| {
+                serde_json::json!({
+                    "instance": instance.name_any(),
+                    "cdiName": instance.spec.cdi_name,
+                    "properties": instance.spec.broker_properties,
+                })
+            })
+            .collect();
+        let record = serde_json::json!({
+            "configuration": configuration,
+            "namespace": namespace,
+            "node": node,
+            "timestamp": timestamp,
+            "devices": devices,
+        });
+        file::write_string_to_file(
+            &self.path(namespace, configuration),
+            &format!("{}\n", record),
+            self.max_bytes,
+        )
+    }
+}
+
+pub trait DiscoveryConfigurationKubeClient:
+    IntoApi<Configuration> + IntoApi<Instance> + IntoApi<Node> + IntoApi<Event>
+{
+}
+
+impl<T: IntoApi<Configuration> + IntoApi<Instance> + IntoApi<Node> + IntoApi<Event>>
+    DiscoveryConfigurationKubeClient for T
+{
+}
+
+/// Summary of a discovery cycle (i.e a reconciliation) for a Configuration, logged once per cycle
+#[derive(Debug, PartialEq)]
+struct DiscoverySummary {
+    namespace: String,
+    configuration: String,
+    handler: String,
+    devices: usize,
+    added: usize,
+    removed: usize,
+    duration: Duration,
+}
+
+impl DiscoverySummary {
+    fn new(
+        namespace: &str,
+        configuration: &str,
+        handler: &str,
+        previous: &HashSet<String>,
+        current: &HashSet<String>,
+        duration: Duration,
+    ) -> Self {
+        DiscoverySummary {
+            namespace: namespace.to_string(),
+            configuration: configuration.to_string(),
+            handler: handler.to_string(),
+            devices: current.len(),
+            added: current.difference(previous).count(),
+            removed: previous.difference(current).count(),
+            duration,
+        }
+    }
+}
+
+impl Display for DiscoverySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Discovery summary for Configuration {}::{} (handler {}): {} device(s), {} added, {} removed, took {}ms",
+            self.namespace,
+            self.configuration,
+            self.handler,
+            self.devices,
+            self.added,
+            self.removed,
+            self.duration.as_millis()
+        )
+    }
+}
+
+/// Serializes the processing of each Configuration, so that the handling of its deletion cannot
+/// interleave with a reconciliation still writing its Instances (e.g. upon a quick apply-then-delete).
+/// Guards are keyed by the namespace and name of the Configuration.
+#[derive(Default)]
+pub struct ConfigurationGuards(Mutex<HashMap<(String, String), Arc<tokio::sync::Mutex<()>>>>);
+
+impl ConfigurationGuards {
+    /// Wait for any ongoing processing of the given Configuration to finish, the returned guard
+    /// must be held for the whole processing
+    async fn lock(&self, namespace: &str, name: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let guard = self
+            .0
+            .lock()
+            .unwrap()
+            .entry((namespace.to_string(), name.to_string()))
+            .or_default()
+            .clone();
+        guard.lock_owned().await
+    }
+
+    /// Forget about a Configuration that is gone, releasing the guard held while processing its
+    /// deletion. The guard is kept if another processing of the Configuration is waiting for it.
+    fn remove(&self, namespace: &str, name: &str, guard: tokio::sync::OwnedMutexGuard<()>) {
+        let mut guards = self.0.lock().unwrap();
+        drop(guard);
+        // Guards are only cloned under the map lock, so nobody else holds or waits for it if
+        // the map has the only reference
+        let key = (namespace.to_string(), name.to_string());
+        if guards.get(&key).is_some_and(|g| Arc::strong_count(g) == 1) {
+            guards.remove(&key);
+        }
+    }
+
+    /// Namespaced names (`<namespace>/<name>`) of the Configurations processed so far and not gone since
+    pub(crate) fn configurations(&self) -> Vec<String> {
+        let mut configurations: Vec<String> = self
+            .0
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|(namespace, name)| format!("{}/{}", namespace, name))
+            .collect();
+        configurations.sort();
+        configurations
+    }
+}
+
+/// Tracks the value of the rediscover Annotation of each Configuration, so that its discovery gets
+/// restarted when the value changes, but not when the Configuration is reconciled for another reason.
+/// Values are keyed by the namespace and name of the Configuration.
+#[derive(Default)]
+pub struct RediscoverTracker(Mutex<HashMap<(String, String), Option<String>>>);
+
+impl RediscoverTracker {
+    /// Records the rediscover Annotation value of the Configuration and returns whether it changed
+    /// to a new value. The first value seen for a Configuration (e.g. upon agent start) is only
+    /// recorded, as its discovery is starting anyway.
+    fn changed(&self, dc: &Configuration) -> bool {
+        let value = dc
+            .annotations()
+            .get(AKRI_REDISCOVER_ANNOTATION_NAME)
+            .cloned();
+        let key = (dc.namespace().unwrap_or_default(), dc.name_any());
+        match self.0.lock().unwrap().entry(key) {
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                false
+            }
+            std::collections::hash_map::Entry::Occupied(mut entry) => {
+                if *entry.get() == value {
+                    return false;
+                }
+                let changed = value.is_some();
+                entry.insert(value);
+                changed
+            }
+        }
+    }
+
+    /// Forget about a Configuration that is gone
+    fn remove(&self, namespace: &str, configuration: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(namespace.to_string(), configuration.to_string()));
+    }
+}
+
+/// Tracks since when the Instances of each Configuration are no longer discovered, so that they
+/// are only removed once the Configuration's offline grace period elapsed. Instances are grouped by
+/// the namespace and name of their Configuration.
+#[derive(Default)]
+pub struct OfflineInstances(Mutex<HashMap<(String, String), HashMap<String, Instant>>>);
+
+impl OfflineInstances {
+    /// Records the Instance as offline as of `now` (if not already) and returns the time left before
+    /// it should be removed, or `None` if its grace period elapsed.
+    fn remaining_grace(
+        &self,
+        namespace: &str,
+        configuration: &str,
+        instance: &str,
+        grace: Duration,
+        now: Instant,
+    ) -> Option<Duration> {
+        if grace.is_zero() {
+            return None;
+        }
+        let mut offline_instances = self.0.lock().unwrap();
+        let instances = offline_instances
+            .entry((namespace.to_string(), configuration.to_string()))
+            .or_default();
+        let offline_for =
+            now.saturating_duration_since(*instances.entry(instance.to_string()).or_insert(now));
+        if offline_for >= grace {
+            instances.remove(instance);
+            return None;
+        }
+        Some(grace - offline_for)
+    }
+
+    /// Forget about the Instances of the Configuration that are discovered again
+    fn online(&self, namespace: &str, configuration: &str, discovered_instances: &HashSet<String>) {
+        if let Some(instances) = self
+            .0
+            .lock()
+            .unwrap()
+            .get_mut(&(namespace.to_string(), configuration.to_string()))
+        {
+            instances.retain(|instance, _| !discovered_instances.contains(instance));
+        }
+    }
+
+    /// Forget about a Configuration that is gone
+    fn remove(&self, namespace: &str, configuration: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(namespace.to_string(), configuration.to_string()));
+    }
+
+    /// Returns whether the Instance is no longer discovered, and only kept during its grace period
+    pub(crate) fn is_offline(&self, namespace: &str, configuration: &str, instance: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&(namespace.to_string(), configuration.to_string()))
+            .is_some_and(|instances| instances.contains_key(instance))
+    }
+}
 
-impl<T: IntoApi<Configuration> + IntoApi<Instance>> DiscoveryConfigurationKubeClient for T {}
+/// Tracks the warnings currently raised for each Configuration, so that their Event is only reported
+/// when the warning gets raised rather than on every reconciliation it still holds for. Warnings are
+/// keyed by the namespace and name of the Configuration.
+#[derive(Default)]
+pub struct ConfigurationWarnings(Mutex<HashMap<(String, String), HashSet<&'static str>>>);
+
+impl ConfigurationWarnings {
+    /// Records the warning as raised and returns whether it was not already
+    fn raise(&self, namespace: &str, configuration: &str, warning: &'static str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .entry((namespace.to_string(), configuration.to_string()))
+            .or_default()
+            .insert(warning)
+    }
+
+    /// Records the warning as no longer holding, it gets reported again when raised next
+    fn clear(&self, namespace: &str, configuration: &str, warning: &'static str) {
+        let mut warnings = self.0.lock().unwrap();
+        let key = (namespace.to_string(), configuration.to_string());
+        if let Some(raised) = warnings.get_mut(&key) {
+            raised.remove(warning);
+            if raised.is_empty() {
+                warnings.remove(&key);
+            }
+        }
+    }
+
+    /// Forget about a Configuration that is gone
+    fn remove(&self, namespace: &str, configuration: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(&(namespace.to_string(), configuration.to_string()));
+    }
+}
 
 pub struct ControllerContext {
     pub instances_cache: Store<Instance>,
     pub dh_registry: Arc<dyn DiscoveryHandlerRegistry>,
     pub client: Arc<dyn DiscoveryConfigurationKubeClient>,
     pub agent_identifier: String,
-    pub error_backoffs: Mutex<HashMap<String, Duration>>,
+    /// Backoff of the next retry of each failing Configuration, keyed by its namespace and name
+    pub error_backoffs: Mutex<HashMap<(String, String), Duration>>,
+    pub instance_batching: InstanceBatching,
+    pub discovery_export: Option<DiscoveryExport>,
+    pub configuration_guards: ConfigurationGuards,
+    pub discovery_jitter: DiscoveryJitter,
+    pub rediscover_tracker: RediscoverTracker,
+    pub offline_instances: OfflineInstances,
+    pub configuration_warnings: ConfigurationWarnings,
+    /// Whether the agent adds its finalizer to Configurations, see [get_agent_managed_finalizers]
+    pub managed_finalizers: bool,
 }
 
 /// This function starts the reconciling loop for the Configuration controller.
@@ -65,37 +550,85 @@ pub async fn start_controller(
         .await;
 }
 
+/// Checks that the state shared by the reconciliations is not stuck, by briefly taking each of its
+/// locks and querying the discovery handler registry. The per Configuration guards are not taken,
+/// as they are legitimately held for the whole (possibly long) processing of a Configuration.
+async fn heartbeat_probe(ctx: &ControllerContext) {
+    drop(ctx.error_backoffs.lock().unwrap());
+    drop(ctx.configuration_guards.0.lock().unwrap());
+    drop(ctx.rediscover_tracker.0.lock().unwrap());
+    drop(ctx.offline_instances.0.lock().unwrap());
+    drop(ctx.configuration_warnings.0.lock().unwrap());
+    ctx.dh_registry.state().await;
+}
+
+/// Regularly updates the heartbeat checked by the liveness endpoint, as long as the controller is
+/// not stuck. It is expected to run this as a task.
+pub async fn run_heartbeat(ctx: Arc<ControllerContext>, heartbeat: Arc<Heartbeat>) {
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        interval.tick().await;
+        heartbeat_probe(&ctx).await;
+        heartbeat.beat();
+    }
+}
+
 /// This function is the main Reconcile function for Configurations resources
 /// This will get called every time a Configuration gets added or is changed, it will also be called
 /// for every existing configuration on startup.
 /// We also set-up discovery manager to trigger reconciliation upon discovery state change
 ///
 /// Here the function will (in order):
-///  - Check if Configuration awaits deletion, and if so terminate pending discovery, remove finalizer and return early
-///  - Add finalizer if not here already
-///  - Start discovery if not already started
+///  - Check if Configuration awaits deletion, and if so terminate pending discovery, delete its Instances,
+///    remove finalizer once they are gone and return early
+///  - Add finalizer if not here already (or remove it if the agent doesn't manage finalizers)
+///  - Start discovery if not already started, or restart it if its rediscover Annotation changed
 ///  - Get discovery results (empty list if just started)
-///  - Create/Delete Instances according to discovery results
+///  - Create/Delete Instances according to discovery results, Instances no longer discovered are
+///    only deleted once the Configuration's offline grace period elapsed
 pub async fn reconcile(
     dc: Arc<Configuration>,
     ctx: Arc<ControllerContext>,
 ) -> Result<Action, Error> {
     trace!("Reconciling {:?}::{}", dc.namespace(), dc.name_any());
     let namespace = dc.namespace().unwrap();
+    let guard = ctx
+        .configuration_guards
+        .lock(&namespace, &dc.name_any())
+        .await;
+    let start = Instant::now();
     let owner_ref = dc.controller_owner_ref(&()).unwrap();
     if dc.metadata.deletion_timestamp.is_some() {
         ctx.dh_registry.terminate_request(&dc.name_any()).await;
 
-        ctx.client
-            .namespaced(&namespace)
-            .remove_finalizer(dc.as_ref(), &ctx.agent_identifier)
-            .await
-            .map_err(|e| Error::Other(e.into()))?;
+        // Keep the finalizer until the device plugins of this node's Instances are torn down, which
+        // happens before the Instances actually go away
+        if delete_owned_instances(&dc, &ctx).await? {
+            trace!(
+                "Waiting for Instances of {}::{} to be deleted before removing finalizer",
+                namespace,
+                dc.name_any()
+            );
+            return Ok(Action::requeue(DELETION_REQUEUE));
+        }
+        remove_configuration_finalizer(&dc, &ctx).await?;
+        ctx.configuration_guards
+            .remove(&namespace, &dc.name_any(), guard);
+        ctx.rediscover_tracker.remove(&namespace, &dc.name_any());
+        ctx.offline_instances.remove(&namespace, &dc.name_any());
+        ctx.configuration_warnings
+            .remove(&namespace, &dc.name_any());
+        clear_instance_count(&namespace, &dc.name_any());
 
         return Ok(Action::await_change());
     }
 
-    if !dc.finalizers().contains(&ctx.agent_identifier) {
+    if !ctx.managed_finalizers {
+        // Release the finalizer added while the agent was managing them
+        if dc.finalizers().contains(&ctx.agent_identifier) {
+            remove_configuration_finalizer(&dc, &ctx).await?;
+        }
+    } else if !dc.finalizers().contains(&ctx.agent_identifier) {
         ctx.client
             .namespaced(&namespace)
             .add_finalizer(dc.as_ref(), &ctx.agent_identifier)
@@ -103,54 +636,197 @@ pub async fn reconcile(
             .map_err(|e| Error::Other(e.into()))?
     }
 
-    let dh_name = &dc.spec.discovery_handler.name;
-    let dh_details = &dc.spec.discovery_handler.discovery_details;
-    let dh_properties: &[DiscoveryProperty] = dc
+    let dh_name = dc
         .spec
-        .discovery_handler
-        .discovery_properties
-        .as_deref()
-        .unwrap_or_default();
-    let dh_extra_device_properties = dc.spec.broker_properties.clone();
+        .discovery_handlers()
+        .map(|dh| dh.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
 
-    let discovered_instances: Vec<Instance> =
+    let node_selected = is_node_selected(&dc, &ctx).await?;
+    if node_selected {
+        if let Some((dh_name, reason)) = backend_down(&dc, &ctx).await {
+            // Devices cannot be told apart from gone ones while the backend is down, so the Instances
+            // are kept as they are until it is back
+            warn!(
+                "Backend of Discovery Handler {} is down, skipping discovery of {}::{}: {}",
+                dh_name,
+                namespace,
+                dc.name_any(),
+                reason
+            );
+            report_discovery_degraded(&dc, &ctx, &dh_name, &reason).await;
+            return Ok(Action::requeue(discovery_poll_interval(&dc)));
+        }
+    }
+    ctx.configuration_warnings
+        .clear(&namespace, &dc.name_any(), DISCOVERY_DEGRADED_WARNING);
+    let discovered_instances: Vec<Instance> = if !node_selected {
+        trace!(
+            "Node {} does not match the discovery node selector of {:?}::{}, skipping discovery",
+            ctx.agent_identifier,
+            dc.namespace(),
+            dc.name_any()
+        );
+        // Stop any discovery started before the node stopped matching, its Instances get removed below
+        ctx.dh_registry.terminate_request(&dc.name_any()).await;
+        vec![]
+    } else {
         match ctx.dh_registry.get_request(&dc.name_any()).await {
+            Some(_) if ctx.rediscover_tracker.changed(&dc) => {
+                info!(
+                    "Rediscover Annotation of {}::{} changed, restarting discovery",
+                    namespace,
+                    dc.name_any()
+                );
+                ctx.dh_registry.terminate_request(&dc.name_any()).await;
+                new_discovery_request(&dc, &ctx).await?;
+                // Instances get updated once the new discovery reports its results
+                ctx.error_backoffs
+                    .lock()
+                    .unwrap()
+                    .remove(&(namespace.clone(), dc.name_any()));
+                return Ok(Action::requeue(discovery_poll_interval(&dc)));
+            }
             Some(req) => {
                 req.set_extra_device_properties(dc.spec.broker_properties.clone())
                     .await;
+                req.set_broker_property_templates(
+                    dc.spec
+                        .broker_property_templates
+                        .clone()
+                        .unwrap_or_default(),
+                )
+                .await;
+                let now = Time(k8s_openapi::chrono::Utc::now());
                 req.get_instances()
                     .await?
                     .into_iter()
                     .map(|mut instance| {
                         // Add
+                        let existing = ctx
+                            .instances_cache
+                            .get(&ObjectRef::new(&instance.name_any()).within(&namespace));
+                        set_discovery_timestamps(
+                            &mut instance,
+                            existing.as_deref(),
+                            &ctx.agent_identifier,
+                            &now,
+                        );
                         instance.spec.nodes = vec![ctx.agent_identifier.to_owned()];
                         instance.owner_references_mut().push(owner_ref.clone());
                         instance.spec.capacity = dc.spec.capacity;
+                        set_shared(&mut instance, dc.spec.shared, &ctx.agent_identifier);
+                        set_configuration_generation(
+                            &mut instance,
+                            dc.metadata.generation,
+                            &ctx.agent_identifier,
+                        );
+                        set_last_warning_node(&mut instance, &ctx.agent_identifier);
+                        set_resource_name(&mut instance, dc.spec.resource_name.as_deref());
+                        set_resource_name_aliases(
+                            &mut instance,
+                            dc.spec.resource_name_aliases.as_deref(),
+                        );
                         instance
                     })
                     .collect()
             }
             None => {
-                ctx.dh_registry
-                    .new_request(
-                        &dc.name_any(),
-                        dh_name,
-                        dh_details,
-                        dh_properties,
-                        dh_extra_device_properties,
-                        &dc.namespace().unwrap_or("default".to_string()),
-                    )
-                    .await?;
+                if let Some(delay) = ctx.discovery_jitter.first_discovery_delay(
+                    &namespace,
+                    &dc.name_any(),
+                    Instant::now(),
+                ) {
+                    trace!(
+                        "Delaying first discovery of {}::{} by {}ms",
+                        namespace,
+                        dc.name_any(),
+                        delay.as_millis()
+                    );
+                    return Ok(Action::requeue(delay));
+                }
+                // Record the current rediscover Annotation value, discovery is starting anyway
+                ctx.rediscover_tracker.changed(&dc);
+                new_discovery_request(&dc, &ctx).await?;
                 vec![]
             }
-        };
+        }
+    };
+
+    if let Some(discovery_export) = &ctx.discovery_export {
+        if let Err(e) = discovery_export.export(
+            &namespace,
+            &dc.name_any(),
+            &ctx.agent_identifier,
+            &discovered_instances,
+        ) {
+            warn!(
+                "Failed to export discovery results for {}::{}: {}",
+                namespace,
+                dc.name_any(),
+                e
+            );
+        }
+    }
 
+    let previous_instances: HashSet<String> = ctx
+        .instances_cache
+        .state()
+        .iter()
+        .filter(|instance| instance.owner_references().contains(&owner_ref))
+        .map(|instance| instance.name_any())
+        .collect();
+
+    let discovered_instances = match dc.spec.max_instances {
+        Some(max_instances) if discovered_instances.len() > max_instances => {
+            warn!(
+                "{} device(s) discovered for Configuration {}::{}, above its maximum of {} Instances, ignoring the additional devices",
+                discovered_instances.len(),
+                namespace,
+                dc.name_any(),
+                max_instances
+            );
+            report_max_instances_reached(&dc, &ctx, discovered_instances.len(), max_instances)
+                .await;
+            cap_instances(discovered_instances, max_instances, &previous_instances)
+        }
+        _ => {
+            ctx.configuration_warnings
+                .clear(&namespace, &dc.name_any(), MAX_INSTANCES_WARNING);
+            discovered_instances
+        }
+    };
+
+    // The grace period only applies to devices no longer discovered, not to a node that stopped
+    // matching the discovery node selector
+    let offline_grace = match dc.spec.instance_offline_grace_secs {
+        Some(secs) if node_selected => Duration::from_secs(secs),
+        _ => Duration::ZERO,
+    };
+    let mut requeue = discovery_poll_interval(&dc);
+    let now = Instant::now();
     for instance in ctx.instances_cache.state() {
         if instance.owner_references().contains(&owner_ref)
             && !discovered_instances
                 .iter()
                 .any(|di| di.name_any() == instance.name_any())
         {
+            if let Some(remaining) = ctx.offline_instances.remaining_grace(
+                &namespace,
+                &dc.name_any(),
+                &instance.name_any(),
+                offline_grace,
+                now,
+            ) {
+                trace!(
+                    "Instance {} is no longer discovered, keeping it for another {}s",
+                    instance.name_any(),
+                    remaining.as_secs()
+                );
+                requeue = requeue.min(remaining);
+                continue;
+            }
             delete_instance(
                 ctx.client.as_ref(),
                 instance.as_ref(),
@@ -160,22 +836,191 @@ pub async fn reconcile(
         }
     }
 
-    for instance in discovered_instances {
-        ctx.client
-            .namespaced(&namespace)
-            .apply(instance, &ctx.agent_identifier)
-            .await
-            .map_err(|e| Error::Other(e.into()))?;
+    let current_instances: HashSet<String> =
+        discovered_instances.iter().map(|i| i.name_any()).collect();
+    ctx.offline_instances
+        .online(&namespace, &dc.name_any(), &current_instances);
+    let shared_instances = discovered_instances
+        .iter()
+        .filter(|i| i.spec.shared)
+        .count();
+    if !discovered_instances.is_empty() {
+        apply_instances(
+            ctx.client.namespaced(&namespace).as_ref(),
+            discovered_instances,
+            &ctx.agent_identifier,
+            &ctx.instance_batching,
+        )
+        .await?;
+    }
+    report_instance_count(
+        &namespace,
+        &dc.name_any(),
+        shared_instances,
+        current_instances.len() - shared_instances,
+    );
+
+    info!(
+        "{}",
+        DiscoverySummary::new(
+            &namespace,
+            &dc.name_any(),
+            &dh_name,
+            &previous_instances,
+            &current_instances,
+            start.elapsed(),
+        )
+    );
+    ctx.error_backoffs
+        .lock()
+        .unwrap()
+        .remove(&(namespace, dc.name_any()));
+    Ok(Action::requeue(requeue))
+}
+
+/// Get the delay before the next discovery pass of the Configuration
+fn discovery_poll_interval(dc: &Configuration) -> Duration {
+    dc.spec
+        .discovery_poll_interval_secs
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(SUCCESS_REQUEUE)
+}
+
+/// Get the name of the first Discovery Handler of the Configuration whose backend is down, along with
+/// the reason it is down
+async fn backend_down(dc: &Configuration, ctx: &ControllerContext) -> Option<(String, String)> {
+    for dh in dc.spec.discovery_handlers() {
+        if let Some(reason) = ctx.dh_registry.backend_down(&dh.name).await {
+            return Some((dh.name.clone(), reason));
+        }
+    }
+    None
+}
+
+/// Sends a new discovery request for the Configuration to its Discovery Handlers. The Configuration's
+/// discovery poll interval (if any) is passed along as a reserved discovery property.
+async fn new_discovery_request(dc: &Configuration, ctx: &ControllerContext) -> Result<(), Error> {
+    let dh_infos: Vec<DiscoveryHandlerInfo> = dc
+        .spec
+        .discovery_handlers()
+        .cloned()
+        .map(|mut dh_info| {
+            if let Some(secs) = dc
+                .spec
+                .discovery_poll_interval_secs
+                .filter(|secs| *secs > 0)
+            {
+                dh_info
+                    .discovery_properties
+                    .get_or_insert_with(Vec::new)
+                    .push(DiscoveryProperty {
+                        name: AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME.to_string(),
+                        value: Some(secs.to_string()),
+                        value_from: None,
+                    });
+            }
+            dh_info
+        })
+        .collect();
+    ctx.dh_registry
+        .new_request(
+            &dc.name_any(),
+            &dh_infos,
+            dc.spec.broker_properties.clone(),
+            dc.spec.instance_naming_strategy.unwrap_or_default(),
+            &dc.namespace().unwrap_or("default".to_string()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Release the Configurations awaiting deletion that still carry this agent's finalizer.
+/// This is called when the agent shuts down, so that they don't stay stuck if it never comes back
+/// (e.g. upon uninstall). The device plugins stop along with the agent, so this node's Instances
+/// of these Configurations are deleted and released as well.
+pub async fn release_deleted_configurations(ctx: &ControllerContext) -> Result<(), Error> {
+    let configurations = IntoApi::<Configuration>::all(ctx.client.as_ref())
+        .list()
+        .await
+        .map_err(|e| Error::Other(e.into()))?;
+    for dc in configurations.items.iter().filter(|dc| {
+        dc.metadata.deletion_timestamp.is_some() && dc.finalizers().contains(&ctx.agent_identifier)
+    }) {
+        let _guard = ctx
+            .configuration_guards
+            .lock(&dc.namespace().unwrap(), &dc.name_any())
+            .await;
+        delete_owned_instances(dc, ctx).await?;
+        for instance in owned_instances(dc, ctx) {
+            if let Err(e) =
+                IntoApi::<Instance>::namespaced(ctx.client.as_ref(), &instance.namespace().unwrap())
+                    .remove_finalizer(instance.as_ref(), &ctx.agent_identifier)
+                    .await
+            {
+                warn!(
+                    "Failed to remove finalizer of Instance {}: {}",
+                    instance.name_any(),
+                    e
+                );
+            }
+        }
+        remove_configuration_finalizer(dc, ctx).await?;
+        clear_instance_count(&dc.namespace().unwrap(), &dc.name_any());
+    }
+    Ok(())
+}
+
+/// Instances of the given Configuration this node takes part in
+fn owned_instances(dc: &Configuration, ctx: &ControllerContext) -> Vec<Arc<Instance>> {
+    let owner_ref = dc.controller_owner_ref(&()).unwrap();
+    ctx.instances_cache
+        .state()
+        .into_iter()
+        .filter(|instance| {
+            instance.owner_references().contains(&owner_ref)
+                && instance.spec.nodes.contains(&ctx.agent_identifier)
+        })
+        .collect()
+}
+
+/// Delete (or remove this node from) the Instances of the given Configuration, returns whether
+/// some of them were still around
+async fn delete_owned_instances(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+) -> Result<bool, Error> {
+    let instances = owned_instances(dc, ctx);
+    for instance in instances.iter() {
+        delete_instance(
+            ctx.client.as_ref(),
+            instance.as_ref(),
+            &ctx.agent_identifier,
+        )
+        .await?;
     }
+    Ok(!instances.is_empty())
+}
 
-    ctx.error_backoffs.lock().unwrap().remove(&dc.name_any());
-    Ok(Action::requeue(SUCCESS_REQUEUE))
+/// Remove this agent's finalizer from the given Configuration, it being already gone is not an error
+async fn remove_configuration_finalizer(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+) -> Result<(), Error> {
+    match IntoApi::<Configuration>::namespaced(ctx.client.as_ref(), &dc.namespace().unwrap())
+        .remove_finalizer(dc, &ctx.agent_identifier)
+        .await
+    {
+        Err(kube::Error::Api(ae)) if ae.code == ERROR_NOT_FOUND => Ok(()),
+        res => res.map_err(|e| Error::Other(e.into())),
+    }
 }
 
 pub fn error_policy(dc: Arc<Configuration>, error: &Error, ctx: Arc<ControllerContext>) -> Action {
+    let key = (dc.namespace().unwrap_or_default(), dc.name_any());
     let mut error_backoffs = ctx.error_backoffs.lock().unwrap();
     let previous_duration = error_backoffs
-        .get(&dc.name_any())
+        .get(&key)
         .cloned()
         .unwrap_or(Duration::from_millis(500));
     let next_duration = previous_duration * 2;
@@ -186,10 +1031,303 @@ pub fn error_policy(dc: Arc<Configuration>, error: &Error, ctx: Arc<ControllerCo
         next_duration.as_secs_f32(),
         error
     );
-    error_backoffs.insert(dc.name_any(), next_duration);
+    error_backoffs.insert(key, next_duration);
     Action::requeue(next_duration)
 }
 
+/// Checks whether the agent's node matches the discovery node selector of the Configuration (if any)
+async fn is_node_selected(dc: &Configuration, ctx: &ControllerContext) -> Result<bool, Error> {
+    let selector = match &dc.spec.discovery_node_selector {
+        Some(selector) if !selector.is_empty() => selector,
+        _ => return Ok(true),
+    };
+    let node = IntoApi::<Node>::all(ctx.client.as_ref())
+        .get(&ctx.agent_identifier)
+        .await
+        .map_err(|e| Error::Other(e.into()))?
+        .ok_or_else(|| Error::Other(anyhow::anyhow!("Node {} not found", ctx.agent_identifier)))?;
+    let labels = node.labels();
+    Ok(selector
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value)))
+}
+
+/// Applies the Configuration override of the shared flag (if any) to the Instance and
+/// records the source of the shared determination as an annotation of this node
+fn set_shared(instance: &mut Instance, shared_override: Option<bool>, node_name: &str) {
+    let source = match shared_override {
+        Some(shared) => {
+            instance.spec.shared = shared;
+            AKRI_SHARED_SOURCE_CONFIGURATION
+        }
+        None => AKRI_SHARED_SOURCE_DISCOVERY_HANDLER,
+    };
+    instance.annotations_mut().insert(
+        node_annotation_name(AKRI_SHARED_SOURCE_ANNOTATION_NAME, node_name),
+        source.to_string(),
+    );
+}
+
+/// Moves the last warning reported by the Discovery Handler (if any) to an annotation of this node,
+/// other nodes sharing the Instance may have different warnings for the same device
+fn set_last_warning_node(instance: &mut Instance, node_name: &str) {
+    let annotations = instance.annotations_mut();
+    if let Some(warning) = annotations.remove(AKRI_LAST_WARNING_ANNOTATION_NAME) {
+        annotations.insert(
+            node_annotation_name(AKRI_LAST_WARNING_ANNOTATION_NAME, node_name),
+            warning,
+        );
+    }
+}
+
+/// Records when the Instance was first discovered and when this node last discovered it. Each node
+/// only applies its own last seen time, and only refreshes it once the stored one is older than
+/// LAST_SEEN_REFRESH_INTERVAL so that periodic rediscoveries don't rewrite the Instance every time.
+/// The first discovery time is the one stored on the Instance, or its creation time; all nodes apply
+/// the same value so they don't conflict on it. It is thus left unset until the Instance exists.
+fn set_discovery_timestamps(
+    instance: &mut Instance,
+    existing: Option<&Instance>,
+    node_name: &str,
+    now: &Time,
+) {
+    instance.spec.first_discovered = existing.and_then(|i| {
+        i.spec
+            .first_discovered
+            .clone()
+            .or_else(|| i.metadata.creation_timestamp.clone())
+    });
+    let last_seen = existing
+        .and_then(|i| i.spec.last_seen.get(node_name))
+        .filter(|last_seen| {
+            (now.0 - last_seen.0)
+                .to_std()
+                .map_or(true, |age| age < LAST_SEEN_REFRESH_INTERVAL)
+        })
+        .unwrap_or(now);
+    instance.spec.last_seen = BTreeMap::from([(node_name.to_owned(), last_seen.clone())]);
+}
+
+/// Records the generation of the Configuration that produced the Instance as an annotation of this node,
+/// so nodes applying Instances from a stale version of the Configuration can be detected
+fn set_configuration_generation(instance: &mut Instance, generation: Option<i64>, node_name: &str) {
+    if let Some(generation) = generation {
+        instance.annotations_mut().insert(
+            node_annotation_name(AKRI_CONFIGURATION_GENERATION_ANNOTATION_NAME, node_name),
+            generation.to_string(),
+        );
+    }
+}
+
+/// Updates the akri_instance_count metric with the number of shared and unshared Instances of the Configuration
+fn report_instance_count(namespace: &str, configuration: &str, shared: usize, unshared: usize) {
+    INSTANCE_COUNT_METRIC
+        .with_label_values(&[namespace, configuration, "true"])
+        .set(shared as i64);
+    INSTANCE_COUNT_METRIC
+        .with_label_values(&[namespace, configuration, "false"])
+        .set(unshared as i64);
+}
+
+/// Removes the akri_instance_count series of a Configuration that is gone
+fn clear_instance_count(namespace: &str, configuration: &str) {
+    for shared in ["true", "false"] {
+        let _ = INSTANCE_COUNT_METRIC.remove_label_values(&[namespace, configuration, shared]);
+    }
+}
+
+/// Keeps at most `max_instances` Instances, preferring the ones that already exist so that newly
+/// discovered devices do not replace existing Instances once the cap is reached
+fn cap_instances(
+    mut instances: Vec<Instance>,
+    max_instances: usize,
+    existing: &HashSet<String>,
+) -> Vec<Instance> {
+    instances.sort_by_cached_key(|instance| {
+        let name = instance.name_any();
+        (!existing.contains(&name), name)
+    });
+    instances.truncate(max_instances);
+    instances
+}
+
+/// Reports a Warning Event on the Configuration stating that its maximum number of Instances is reached
+async fn report_max_instances_reached(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+    discovered: usize,
+    max_instances: usize,
+) {
+    report_configuration_warning(
+        dc,
+        ctx,
+        MAX_INSTANCES_REACHED_EVENT_REASON,
+        MAX_INSTANCES_WARNING,
+        &format!(
+            "{} device(s) discovered on node {}, only {} Instances are created",
+            discovered, ctx.agent_identifier, max_instances
+        ),
+    )
+    .await
+}
+
+/// Reports a Warning Event on the Configuration stating that its discovery is degraded, as the backend
+/// of one of its Discovery Handlers is down
+async fn report_discovery_degraded(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+    dh_name: &str,
+    reason: &str,
+) {
+    report_configuration_warning(
+        dc,
+        ctx,
+        DISCOVERY_DEGRADED_EVENT_REASON,
+        DISCOVERY_DEGRADED_WARNING,
+        &format!(
+            "Discovery skipped on node {}, the backend of Discovery Handler {} is down: {}",
+            ctx.agent_identifier, dh_name, reason
+        ),
+    )
+    .await
+}
+
+/// Reports a Warning Event on the Configuration, unless the warning is already raised. The Event is
+/// named after the Configuration, the kind of warning and the node so that it gets updated, rather than
+/// duplicated, each time the warning gets raised again. Failing to report it does not fail the
+/// reconciliation, it is reported again on the next one.
+async fn report_configuration_warning(
+    dc: &Configuration,
+    ctx: &ControllerContext,
+    reason: &str,
+    kind: &'static str,
+    message: &str,
+) {
+    let namespace = dc.namespace().unwrap_or_default();
+    if !ctx
+        .configuration_warnings
+        .raise(&namespace, &dc.name_any(), kind)
+    {
+        return;
+    }
+    let mut warning = event::create_component_configuration_warning_event(
+        event::AKRI_AGENT_EVENT_COMPONENT,
+        dc,
+        reason,
+        message,
+    );
+    warning.metadata.generate_name = None;
+    warning.metadata.name = Some(format!(
+        "{}.{}.{}",
+        dc.name_any(),
+        kind,
+        ctx.agent_identifier
+    ));
+    if let Some(source) = warning.source.as_mut() {
+        source.host = Some(ctx.agent_identifier.clone());
+    }
+    if let Err(e) = IntoApi::<Event>::namespaced(ctx.client.as_ref(), &dc.namespace().unwrap())
+        .apply(warning, &ctx.agent_identifier)
+        .await
+    {
+        warn!(
+            "Failed to report {} for {:?}::{}: {:?}",
+            reason,
+            dc.namespace(),
+            dc.name_any(),
+            e
+        );
+        ctx.configuration_warnings
+            .clear(&namespace, &dc.name_any(), kind);
+    }
+}
+
+/// Records the overridden resource name of the Configuration for the device plugin manager
+fn set_resource_name(instance: &mut Instance, resource_name: Option<&str>) {
+    if let Some(resource_name) = resource_name {
+        instance.annotations_mut().insert(
+            AKRI_RESOURCE_NAME_ANNOTATION_NAME.to_string(),
+            resource_name.to_string(),
+        );
+    }
+}
+
+/// Records the resource name aliases of the Configuration for the device plugin manager
+fn set_resource_name_aliases(instance: &mut Instance, aliases: Option<&[String]>) {
+    if let Some(aliases) = aliases.filter(|a| !a.is_empty()) {
+        instance.annotations_mut().insert(
+            AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME.to_string(),
+            aliases.join(","),
+        );
+    }
+}
+
+/// Applies Instances in batches of bounded size, waiting between batches
+async fn apply_instances(
+    api: &dyn Api<Instance>,
+    instances: Vec<Instance>,
+    field_manager: &str,
+    batching: &InstanceBatching,
+) -> Result<(), Error> {
+    let mut batches = instances.chunks(batching.batch_size.max(1)).peekable();
+    while let Some(batch) = batches.next() {
+        futures::future::try_join_all(
+            batch
+                .iter()
+                .map(|instance| apply_instance(api, instance.clone(), field_manager)),
+        )
+        .await
+        .map_err(|e| Error::Other(e.into()))?;
+        if batches.peek().is_some() && !batching.batch_delay.is_zero() {
+            tokio::time::sleep(batching.batch_delay).await;
+        }
+    }
+    Ok(())
+}
+
+/// Field manager used by a node that only adds itself to the nodes of a conflicting shared Instance.
+/// It is kept apart from the node's own field manager so that this partial apply doesn't release the
+/// other fields the node owns on the Instance.
+fn membership_field_manager(field_manager: &str) -> String {
+    format!("{}-membership", field_manager)
+}
+
+/// Applies an Instance. Another node may have created the same shared Instance with conflicting values
+/// (e.g. while a Configuration update rolls out), in which case this node only merges itself into the
+/// Instance's nodes rather than failing until the conflict goes away.
+async fn apply_instance(
+    api: &dyn Api<Instance>,
+    instance: Instance,
+    field_manager: &str,
+) -> Result<(), kube::Error> {
+    let name = instance.name_any();
+    let nodes = instance.spec.nodes.clone();
+    let shared = instance.spec.shared;
+    match api.apply(instance, field_manager).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(ae)) if ae.code == ERROR_CONFLICT && shared => {
+            info!(
+                "Instance {} conflicts with the one created by another node, only adding node {} to it: {}",
+                name, field_manager, ae.message
+            );
+            let patch = kube::api::Patch::Apply(serde_json::json!({
+                "apiVersion": Instance::api_version(&()),
+                "kind": Instance::kind(&()),
+                "metadata": { "name": name },
+                "spec": { "nodes": nodes },
+            }));
+            api.raw_patch(
+                &name,
+                &patch,
+                &kube::api::PatchParams::apply(&membership_field_manager(field_manager)),
+            )
+            .await?;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
 async fn delete_instance(
     client: &dyn DiscoveryConfigurationKubeClient,
     instance: &Instance,
@@ -208,6 +1346,26 @@ async fn delete_instance(
         api.apply(new_instance, agent_instance_name)
             .await
             .map_err(|e| Error::Other(e.into()))?;
+        // The node may also have merged itself into the Instance upon a conflict, release that as well
+        let membership_manager = membership_field_manager(agent_instance_name);
+        if instance
+            .managed_fields()
+            .iter()
+            .any(|mf| mf.manager.as_deref() == Some(membership_manager.as_str()))
+        {
+            let patch = kube::api::Patch::Apply(serde_json::json!({
+                "apiVersion": Instance::api_version(&()),
+                "kind": Instance::kind(&()),
+                "metadata": { "name": instance.name_any() },
+            }));
+            api.raw_patch(
+                &instance.name_any(),
+                &patch,
+                &kube::api::PatchParams::apply(&membership_manager),
+            )
+            .await
+            .map_err(|e| Error::Other(e.into()))?;
+        }
     }
     Ok(())
 }
@@ -222,8 +1380,9 @@ mod tests {
         k8s::api::{Api, MockApi, MockIntoApi},
     };
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
-    use kube::core::{ObjectMeta, Status};
+    use kube::core::{ObjectList, ObjectMeta, Status};
     use mockall::predicate::eq;
+    use std::collections::BTreeMap;
 
     use crate::discovery_handler_manager::discovery_handler_registry::{
         MockDiscoveryHandlerRegistry, MockDiscoveryHandlerRequest,
@@ -235,6 +1394,22 @@ mod tests {
     pub struct MockDiscoveryConfigurationKubeClient {
         instance: MockIntoApi<Instance>,
         config: MockIntoApi<Configuration>,
+        node: MockIntoApi<Node>,
+        event: MockIntoApi<Event>,
+    }
+
+    impl IntoApi<Event> for MockDiscoveryConfigurationKubeClient {
+        fn all(&self) -> Box<dyn Api<Event>> {
+            self.event.all()
+        }
+
+        fn namespaced(&self, namespace: &str) -> Box<dyn Api<Event>> {
+            self.event.namespaced(namespace)
+        }
+
+        fn default_namespaced(&self) -> Box<dyn Api<Event>> {
+            self.event.default_namespaced()
+        }
     }
 
     impl IntoApi<Instance> for MockDiscoveryConfigurationKubeClient {
@@ -251,6 +1426,20 @@ mod tests {
         }
     }
 
+    impl IntoApi<Node> for MockDiscoveryConfigurationKubeClient {
+        fn all(&self) -> Box<dyn Api<Node>> {
+            self.node.all()
+        }
+
+        fn namespaced(&self, _namespace: &str) -> Box<dyn Api<Node>> {
+            unreachable!("Node is not a namespaced resource")
+        }
+
+        fn default_namespaced(&self) -> Box<dyn Api<Node>> {
+            unreachable!("Node is not a namespaced resource")
+        }
+    }
+
     impl IntoApi<Configuration> for MockDiscoveryConfigurationKubeClient {
         fn all(&self) -> Box<dyn Api<Configuration>> {
             self.config.all()
@@ -265,25 +1454,103 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_error_policy() {
-        let _ = env_logger::builder().is_test(true).try_init();
-        let config_1 = Arc::new(Configuration {
+    fn make_test_context(
+        instances_cache: Store<Instance>,
+        dh_registry: MockDiscoveryHandlerRegistry,
+        client: MockDiscoveryConfigurationKubeClient,
+    ) -> ControllerContext {
+        ControllerContext {
+            instances_cache,
+            dh_registry: Arc::new(dh_registry),
+            client: Arc::new(client),
+            agent_identifier: "node-a".to_string(),
+            error_backoffs: Default::default(),
+            instance_batching: Default::default(),
+            discovery_export: None,
+            configuration_guards: Default::default(),
+            discovery_jitter: Default::default(),
+            rediscover_tracker: Default::default(),
+            offline_instances: Default::default(),
+            configuration_warnings: Default::default(),
+            managed_finalizers: true,
+        }
+    }
+
+    fn make_test_instance(name: &str) -> Instance {
+        Instance {
             metadata: ObjectMeta {
-                name: Some("config-1".to_string()),
+                name: Some(name.to_string()),
                 ..Default::default()
             },
-            spec: ConfigurationSpec {
-                discovery_handler: DiscoveryHandlerInfo {
-                    name: "debugEcho".to_string(),
-                    discovery_details: String::default(),
-                    discovery_properties: None,
-                },
+            spec: InstanceSpec {
+                configuration_name: "config-1".to_string(),
+                cdi_name: format!("akri.sh/{}", name),
                 capacity: 1,
-                broker_spec: None,
-                instance_service_spec: None,
-                configuration_service_spec: None,
-                broker_properties: Default::default(),
+                broker_properties: HashMap::new(),
+                shared: true,
+                nodes: vec![],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        }
+    }
+
+    fn make_test_configuration() -> Arc<Configuration> {
+        Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                finalizers: Some(vec!["node-a".to_string()]),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handlers: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn make_test_registry(discovered: &'static [&'static str]) -> MockDiscoveryHandlerRegistry {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        let mut request = MockDiscoveryHandlerRequest::new();
+        request
+            .expect_set_extra_device_properties()
+            .returning(|_| {});
+        request
+            .expect_set_broker_property_templates()
+            .returning(|_| {});
+        request
+            .expect_get_instances()
+            .returning(move || Ok(discovered.iter().copied().map(make_test_instance).collect()));
+        registry
+            .expect_get_request()
+            .return_once(|_| Some(Arc::new(request)));
+        registry
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let config_1 = Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handlers: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
             },
         });
         let config_2 = Arc::new(Configuration {
@@ -292,28 +1559,22 @@ mod tests {
                 ..Default::default()
             },
             spec: ConfigurationSpec {
-                discovery_handler: DiscoveryHandlerInfo {
+                discovery_handlers: DiscoveryHandlerInfo {
                     name: "debugEcho".to_string(),
-                    discovery_details: String::default(),
-                    discovery_properties: None,
-                },
-                capacity: 1,
-                broker_spec: None,
-                instance_service_spec: None,
-                configuration_service_spec: None,
-                broker_properties: Default::default(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
             },
         });
 
         let (store, _) = kube_runtime::reflector::store();
 
-        let ctx = Arc::new(ControllerContext {
-            instances_cache: store,
-            dh_registry: Arc::new(MockDiscoveryHandlerRegistry::new()),
-            client: Arc::new(MockDiscoveryConfigurationKubeClient::default()),
-            agent_identifier: "node-a".to_string(),
-            error_backoffs: Default::default(),
-        });
+        let ctx = Arc::new(make_test_context(
+            store,
+            MockDiscoveryHandlerRegistry::new(),
+            MockDiscoveryConfigurationKubeClient::default(),
+        ));
 
         assert_eq!(
             error_policy(
@@ -349,6 +1610,18 @@ mod tests {
             Action::requeue(Duration::from_secs(1))
         );
 
+        // A Configuration of the same name in another namespace has its own backoff
+        let mut config_1_other_namespace = config_1.as_ref().clone();
+        config_1_other_namespace.metadata.namespace = Some("namespace-b".to_string());
+        assert_eq!(
+            error_policy(
+                Arc::new(config_1_other_namespace),
+                &Error::Other(anyhow::anyhow!("Error")),
+                ctx.clone()
+            ),
+            Action::requeue(Duration::from_secs(1))
+        );
+
         assert_eq!(
             error_policy(config_1, &Error::Other(anyhow::anyhow!("Error")), ctx),
             Action::requeue(Duration::from_secs(8))
@@ -371,6 +1644,8 @@ mod tests {
                 shared: false,
                 nodes: vec!["node-a".to_string()],
                 device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
             },
         };
 
@@ -410,6 +1685,8 @@ mod tests {
                 shared: false,
                 nodes: vec!["node-a".to_string(), "node-b".to_string()],
                 device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
             },
         };
 
@@ -432,6 +1709,58 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_delete_instance_remove_membership() {
+        let mut instance = make_test_instance("instance-1");
+        instance.metadata.namespace = Some("namespace-a".to_string());
+        instance.metadata.managed_fields = Some(vec![
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry {
+                manager: Some("node-b".to_string()),
+                ..Default::default()
+            },
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry {
+                manager: Some("node-a-membership".to_string()),
+                ..Default::default()
+            },
+        ]);
+        instance.spec.nodes = vec!["node-a".to_string(), "node-b".to_string()];
+
+        let mut mock_client = MockDiscoveryConfigurationKubeClient::default();
+        let mut mock_api = MockApi::new();
+        let local_instance = instance.clone();
+        mock_api
+            .expect_apply()
+            .times(1)
+            .withf(|instance, field_manager| {
+                instance.spec.nodes.is_empty() && field_manager == "node-a"
+            })
+            .returning(move |_, _| Ok(local_instance.clone()));
+        let local_instance = instance.clone();
+        mock_api
+            .expect_raw_patch()
+            .times(1)
+            .withf(|name, patch, params| {
+                let kube::api::Patch::Apply(value) = patch else {
+                    return false;
+                };
+                name == "instance-1"
+                    && params.field_manager.as_deref() == Some("node-a-membership")
+                    && value.get("spec").is_none()
+            })
+            .returning(move |_, _, _| Ok(local_instance.clone()));
+        mock_client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(mock_api));
+
+        assert!(
+            delete_instance(&mock_client, &instance, &"node-a".to_string())
+                .await
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn test_delete_instance_other_node() {
         let instance = Instance {
@@ -448,6 +1777,8 @@ mod tests {
                 shared: false,
                 nodes: vec!["node-b".to_string()],
                 device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
             },
         };
 
@@ -477,46 +1808,157 @@ mod tests {
             .return_once(|_| Box::new(api));
 
         let mut registry = MockDiscoveryHandlerRegistry::new();
+
+        registry.expect_backend_down().returning(|_| None);
         let mut request = MockDiscoveryHandlerRequest::new();
         request
             .expect_set_extra_device_properties()
             .returning(|_| {});
+        request
+            .expect_set_broker_property_templates()
+            .returning(|_| {});
         request.expect_get_instances().returning(|| Ok(vec![]));
         registry
             .expect_get_request()
             .return_once(|_| Some(Arc::new(request)));
 
-        let ctx = Arc::new(ControllerContext {
-            instances_cache: store,
-            dh_registry: Arc::new(registry),
-            client: Arc::new(client),
-            agent_identifier: "node-a".to_string(),
-            error_backoffs: Default::default(),
-        });
+        let ctx = Arc::new(make_test_context(store, registry, client));
 
-        let dc = Arc::new(Configuration {
-            metadata: ObjectMeta {
-                name: Some("config-1".to_string()),
-                namespace: Some("namespace-a".to_string()),
-                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
-                finalizers: Some(vec!["node-a".to_string()]),
-                ..Default::default()
-            },
-            spec: ConfigurationSpec {
-                discovery_handler: DiscoveryHandlerInfo {
-                    name: "debugEcho".to_string(),
-                    discovery_details: String::new(),
-                    discovery_properties: None,
+        assert!(reconcile(make_test_configuration(), ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_discovery_poll_interval() {
+        for (poll_interval_secs, requeue) in [
+            (None, SUCCESS_REQUEUE),
+            (Some(0), SUCCESS_REQUEUE),
+            (Some(5), Duration::from_secs(5)),
+            (Some(300), Duration::from_secs(300)),
+        ] {
+            let (store, _) = kube_runtime::reflector::store();
+            let mut client = MockDiscoveryConfigurationKubeClient::default();
+            client
+                .config
+                .expect_namespaced()
+                .returning(|_| Box::new(MockApi::new()));
+
+            let mut registry = MockDiscoveryHandlerRegistry::new();
+            registry.expect_backend_down().returning(|_| None);
+            registry.expect_get_request().returning(|_| {
+                let mut request = MockDiscoveryHandlerRequest::new();
+                request
+                    .expect_set_extra_device_properties()
+                    .returning(|_| {});
+                request
+                    .expect_set_broker_property_templates()
+                    .returning(|_| {});
+                request.expect_get_instances().returning(|| Ok(vec![]));
+                Some(Arc::new(request))
+            });
+
+            let ctx = Arc::new(make_test_context(store, registry, client));
+
+            let dc = Arc::new(Configuration {
+                metadata: ObjectMeta {
+                    name: Some("config-poll".to_string()),
+                    namespace: Some("namespace-a".to_string()),
+                    uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                    finalizers: Some(vec!["node-a".to_string()]),
+                    ..Default::default()
                 },
-                capacity: 1,
-                broker_spec: None,
-                instance_service_spec: None,
-                configuration_service_spec: None,
-                broker_properties: Default::default(),
-            },
-        });
+                spec: ConfigurationSpec {
+                    discovery_handlers: DiscoveryHandlerInfo {
+                        name: "opcua".to_string(),
+                        ..Default::default()
+                    }
+                    .into(),
+                    discovery_poll_interval_secs: poll_interval_secs,
+                    ..Default::default()
+                },
+            });
 
-        assert!(reconcile(dc, ctx).await.is_ok());
+            // The next discovery pass is scheduled after the configured interval
+            assert_eq!(reconcile(dc, ctx).await.unwrap(), Action::requeue(requeue));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_passes_discovery_poll_interval() {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_get_request().returning(|_| None);
+        // The Discovery Handlers get the interval to poll their devices at
+        registry
+            .expect_new_request()
+            .times(1)
+            .withf(|_, dh_infos, _, _, _| {
+                dh_infos[0].discovery_properties
+                    == Some(vec![DiscoveryProperty {
+                        name: AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME.to_string(),
+                        value: Some("300".to_string()),
+                        value_from: None,
+                    }])
+            })
+            .returning(|_, _, _, _, _| Ok(()));
+        let ctx = Arc::new(make_test_context(
+            store,
+            registry,
+            MockDiscoveryConfigurationKubeClient::default(),
+        ));
+        let mut dc = make_test_configuration().as_ref().clone();
+        dc.spec.discovery_poll_interval_secs = Some(300);
+
+        assert!(reconcile(Arc::new(dc), ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_unmanaged_finalizers() {
+        for has_finalizer in [true, false] {
+            let (store, _) = kube_runtime::reflector::store();
+            let mut client = MockDiscoveryConfigurationKubeClient::default();
+            // The finalizer added while the agent was managing them is removed, none is ever added
+            client
+                .config
+                .expect_namespaced()
+                .times(has_finalizer as usize)
+                .returning(|_| {
+                    let mut api = MockApi::new();
+                    api.expect_add_finalizer().never();
+                    api.expect_remove_finalizer()
+                        .withf(|dc: &Configuration, finalizer| {
+                            dc.name_any() == "config-1" && finalizer == "node-a"
+                        })
+                        .times(1)
+                        .returning(|_, _| Ok(()));
+                    Box::new(api)
+                });
+
+            let mut registry = MockDiscoveryHandlerRegistry::new();
+
+            registry.expect_backend_down().returning(|_| None);
+            let mut request = MockDiscoveryHandlerRequest::new();
+            request
+                .expect_set_extra_device_properties()
+                .returning(|_| {});
+            request
+                .expect_set_broker_property_templates()
+                .returning(|_| {});
+            request.expect_get_instances().returning(|| Ok(vec![]));
+            registry
+                .expect_get_request()
+                .return_once(|_| Some(Arc::new(request)));
+
+            let ctx = Arc::new(ControllerContext {
+                managed_finalizers: false,
+                ..make_test_context(store, registry, client)
+            });
+
+            let mut dc = make_test_configuration().as_ref().clone();
+            dc.metadata.finalizers = has_finalizer.then(|| vec!["node-a".to_string()]);
+
+            assert!(reconcile(Arc::new(dc), ctx).await.is_ok());
+        }
     }
 
     #[tokio::test]
@@ -545,6 +1987,8 @@ mod tests {
                     shared: true,
                     nodes: vec!["node-a".to_string()],
                     device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
                 },
             },
             Instance {
@@ -569,6 +2013,8 @@ mod tests {
                     shared: true,
                     nodes: vec!["node-b".to_string()],
                     device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
                 },
             },
             Instance {
@@ -593,6 +2039,8 @@ mod tests {
                     shared: true,
                     nodes: vec!["node-a".to_string()],
                     device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
                 },
             },
         ]));
@@ -615,19 +2063,15 @@ mod tests {
             .return_once(|_| Box::new(instance_api));
 
         let mut registry = MockDiscoveryHandlerRegistry::new();
+
+        registry.expect_backend_down().returning(|_| None);
         registry.expect_get_request().return_once(|_| None);
         //TODO: check arguments here
         registry
             .expect_new_request()
-            .returning(|_, _, _, _, _, _| Ok(()));
+            .returning(|_, _, _, _, _| Ok(()));
 
-        let ctx = Arc::new(ControllerContext {
-            instances_cache: store,
-            dh_registry: Arc::new(registry),
-            client: Arc::new(client),
-            agent_identifier: "node-a".to_string(),
-            error_backoffs: Default::default(),
-        });
+        let ctx = Arc::new(make_test_context(store, registry, client));
 
         let dc = Arc::new(Configuration {
             metadata: ObjectMeta {
@@ -637,19 +2081,1910 @@ mod tests {
                 ..Default::default()
             },
             spec: ConfigurationSpec {
-                discovery_handler: DiscoveryHandlerInfo {
+                discovery_handlers: DiscoveryHandlerInfo {
                     name: "debugEcho".to_string(),
-                    discovery_details: String::new(),
-                    discovery_properties: None,
-                },
-                capacity: 1,
-                broker_spec: None,
-                instance_service_spec: None,
-                configuration_service_spec: None,
-                broker_properties: Default::default(),
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
             },
         });
 
         assert!(reconcile(dc, ctx).await.is_ok());
     }
+
+    fn make_deleted_configuration_test_context(
+        instances: Vec<Instance>,
+        client: MockDiscoveryConfigurationKubeClient,
+    ) -> Arc<ControllerContext> {
+        let (store, mut writer) = kube_runtime::reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(instances));
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry
+            .expect_terminate_request()
+            .with(eq("config-1"))
+            .returning(|_| ());
+        registry.expect_get_request().never();
+        registry.expect_new_request().never();
+        Arc::new(make_test_context(store, registry, client))
+    }
+
+    fn make_deleted_configuration_test_instance() -> Instance {
+        let mut instance = make_test_instance("config-1-a");
+        instance.metadata.namespace = Some("namespace-a".to_string());
+        instance.metadata.owner_references = Some(vec![OwnerReference {
+            api_version: Instance::api_version(&()).to_string(),
+            block_owner_deletion: None,
+            controller: Some(true),
+            kind: "Configuration".to_string(),
+            name: "config-1".to_string(),
+            uid: "00112233-4455-6677-8899-aabbccddeeff".to_string(),
+        }]);
+        instance.spec.shared = false;
+        instance.spec.nodes = vec!["node-a".to_string()];
+        instance
+    }
+
+    fn make_deleted_test_configuration() -> Arc<Configuration> {
+        let mut dc = make_test_configuration().as_ref().clone();
+        dc.metadata.deletion_timestamp = Some(
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(k8s_openapi::chrono::Utc::now()),
+        );
+        Arc::new(dc)
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_deleted_configuration() {
+        // While this node's Instances exist, they get deleted and the finalizer is kept
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut instance_api = MockApi::new();
+        instance_api
+            .expect_delete()
+            .with(eq("config-1-a"))
+            .times(1)
+            .returning(|_| Ok(itertools::Either::Right(Status::default())));
+        client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(instance_api));
+        client.config.expect_namespaced().never();
+        let ctx = make_deleted_configuration_test_context(
+            vec![make_deleted_configuration_test_instance()],
+            client,
+        );
+
+        assert_eq!(
+            reconcile(make_deleted_test_configuration(), ctx)
+                .await
+                .unwrap(),
+            Action::requeue(DELETION_REQUEUE)
+        );
+
+        // Once the Instances are gone, the finalizer is removed
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client.instance.expect_namespaced().never();
+        let mut config_api = MockApi::new();
+        config_api
+            .expect_remove_finalizer()
+            .withf(|dc, finalizer| dc.name_any() == "config-1" && finalizer == "node-a")
+            .times(1)
+            .returning(|_, _| Ok(()));
+        client
+            .config
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(config_api));
+        let ctx = make_deleted_configuration_test_context(vec![], client);
+
+        assert_eq!(
+            reconcile(make_deleted_test_configuration(), ctx)
+                .await
+                .unwrap(),
+            Action::await_change()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_deleted_configuration_already_gone() {
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut config_api = MockApi::new();
+        config_api
+            .expect_remove_finalizer()
+            .times(1)
+            .returning(|_, _| {
+                Err(kube::Error::Api(kube::error::ErrorResponse {
+                    status: "Failure".to_string(),
+                    message: "not found".to_string(),
+                    reason: "NotFound".to_string(),
+                    code: ERROR_NOT_FOUND,
+                }))
+            });
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(config_api));
+        let ctx = make_deleted_configuration_test_context(vec![], client);
+
+        assert!(reconcile(make_deleted_test_configuration(), ctx)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_delete_leaves_no_finalizers() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        async fn stored_configuration(client: &dyn IntoApi<Configuration>) -> Configuration {
+            client
+                .namespaced("namespace-a")
+                .get("config-1")
+                .await
+                .unwrap()
+                .unwrap()
+        }
+        for managed_finalizers in [true, false] {
+            let client = Arc::new(crate::util::local_mode::LocalKubeClient::default());
+            let mut dc = make_test_configuration().as_ref().clone();
+            dc.metadata.finalizers = None;
+            let config_api = IntoApi::<Configuration>::namespaced(client.as_ref(), "namespace-a");
+            config_api.apply(dc, "test").await.unwrap();
+            let (store, mut writer) = kube_runtime::reflector::store();
+            let mut registry = make_test_registry(&["config-1-a"]);
+            registry.expect_terminate_request().returning(|_| ());
+            let ctx = Arc::new(ControllerContext {
+                instances_cache: store,
+                dh_registry: Arc::new(registry),
+                client: client.clone(),
+                managed_finalizers,
+                ..make_test_context(
+                    kube_runtime::reflector::store().0,
+                    MockDiscoveryHandlerRegistry::new(),
+                    MockDiscoveryConfigurationKubeClient::default(),
+                )
+            });
+
+            // The finalizer is only added when the agent manages them
+            assert!(reconcile(
+                Arc::new(stored_configuration(client.as_ref()).await),
+                ctx.clone()
+            )
+            .await
+            .is_ok());
+            assert_eq!(client.instances().len(), 1);
+            assert_eq!(
+                stored_configuration(client.as_ref())
+                    .await
+                    .metadata
+                    .finalizers
+                    .is_some(),
+                managed_finalizers
+            );
+
+            // Deleting the Configuration removes its Instances, then its finalizer
+            let mut deleted = stored_configuration(client.as_ref()).await;
+            deleted.metadata.deletion_timestamp =
+                Some(k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(
+                    k8s_openapi::chrono::Utc::now(),
+                ));
+            let deleted = Arc::new(deleted);
+            for _ in 0..2 {
+                writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(
+                    client.instances(),
+                ));
+                assert!(reconcile(deleted.clone(), ctx.clone()).await.is_ok());
+            }
+            assert!(client.instances().is_empty());
+            assert!(stored_configuration(client.as_ref())
+                .await
+                .metadata
+                .finalizers
+                .unwrap_or_default()
+                .is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_release_deleted_configurations() {
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut all_config_api = MockApi::new();
+        all_config_api.expect_list().times(1).returning(|| {
+            Ok(ObjectList {
+                metadata: Default::default(),
+                items: vec![
+                    make_deleted_test_configuration().as_ref().clone(),
+                    // Configurations that are not being deleted are left untouched
+                    make_test_configuration().as_ref().clone(),
+                ],
+            })
+        });
+        client
+            .config
+            .expect_all()
+            .return_once(|| Box::new(all_config_api));
+        let mut config_api = MockApi::new();
+        config_api
+            .expect_remove_finalizer()
+            .withf(|dc, finalizer| {
+                dc.metadata.deletion_timestamp.is_some() && finalizer == "node-a"
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        client
+            .config
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(config_api));
+        let mut delete_instance_api = MockApi::new();
+        delete_instance_api
+            .expect_delete()
+            .with(eq("config-1-a"))
+            .times(1)
+            .returning(|_| Ok(itertools::Either::Right(Status::default())));
+        let mut finalizer_instance_api = MockApi::new();
+        finalizer_instance_api
+            .expect_remove_finalizer()
+            .withf(|instance, finalizer| {
+                instance.name_any() == "config-1-a" && finalizer == "node-a"
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut seq = mockall::Sequence::new();
+        client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .times(1)
+            .return_once(|_| Box::new(delete_instance_api))
+            .in_sequence(&mut seq);
+        client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .times(1)
+            .return_once(|_| Box::new(finalizer_instance_api))
+            .in_sequence(&mut seq);
+        let ctx = make_deleted_configuration_test_context(
+            vec![make_deleted_configuration_test_instance()],
+            client,
+        );
+
+        assert!(release_deleted_configurations(&ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_waits_for_configuration_guard() {
+        let finalizer_removed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut config_api = MockApi::new();
+        let local_finalizer_removed = finalizer_removed.clone();
+        config_api
+            .expect_remove_finalizer()
+            .times(1)
+            .returning(move |_, _| {
+                local_finalizer_removed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            });
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(config_api));
+        let ctx = make_deleted_configuration_test_context(vec![], client);
+
+        // Another processing of the Configuration is ongoing
+        let guard = ctx
+            .configuration_guards
+            .lock("namespace-a", "config-1")
+            .await;
+        let task = tokio::spawn(reconcile(make_deleted_test_configuration(), ctx.clone()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!finalizer_removed.load(std::sync::atomic::Ordering::SeqCst));
+
+        drop(guard);
+        assert!(task.await.unwrap().is_ok());
+        assert!(finalizer_removed.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_configuration_guards() {
+        let guards = Arc::new(ConfigurationGuards::default());
+        let guard = guards.lock("namespace-a", "config-1").await;
+        // Same-named Configurations of other namespaces are processed independently
+        assert!(tokio::time::timeout(
+            Duration::from_millis(100),
+            guards.lock("namespace-b", "config-1")
+        )
+        .await
+        .is_ok());
+
+        // The guard is kept while another processing waits for it
+        let local_guards = guards.clone();
+        let waiter = tokio::spawn(async move {
+            tokio::time::timeout(
+                Duration::from_secs(1),
+                local_guards.lock("namespace-a", "config-1"),
+            )
+            .await
+            .is_ok()
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        guards.remove("namespace-a", "config-1", guard);
+        assert_eq!(
+            guards.configurations(),
+            vec!["namespace-a/config-1", "namespace-b/config-1"]
+        );
+        assert!(waiter.await.unwrap());
+
+        // and removed once nobody uses it anymore
+        let guard = guards.lock("namespace-a", "config-1").await;
+        guards.remove("namespace-a", "config-1", guard);
+        assert_eq!(guards.configurations(), vec!["namespace-b/config-1"]);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_create_then_delete() {
+        let (store, mut writer) = kube_runtime::reflector::store();
+        let applied: Arc<Mutex<Vec<Instance>>> = Default::default();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut seq = mockall::Sequence::new();
+        let mut apply_instance_api = MockApi::new();
+        let local_applied = applied.clone();
+        apply_instance_api
+            .expect_apply()
+            .times(1)
+            .returning(move |instance, _| {
+                local_applied.lock().unwrap().push(instance.clone());
+                Ok(instance)
+            });
+        client
+            .instance
+            .expect_namespaced()
+            .times(1)
+            .return_once(|_| Box::new(apply_instance_api))
+            .in_sequence(&mut seq);
+        let mut delete_instance_api = MockApi::new();
+        delete_instance_api
+            .expect_delete()
+            .with(eq("config-1-a"))
+            .times(1)
+            .returning(|_| Ok(itertools::Either::Right(Status::default())));
+        client
+            .instance
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .times(1)
+            .return_once(|_| Box::new(delete_instance_api))
+            .in_sequence(&mut seq);
+        let mut config_api = MockApi::new();
+        config_api
+            .expect_remove_finalizer()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        client
+            .config
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(config_api));
+
+        let mut registry = make_test_registry(&["config-1-a"]);
+        registry
+            .expect_terminate_request()
+            .with(eq("config-1"))
+            .returning(|_| ());
+        let ctx = Arc::new(make_test_context(store, registry, client));
+
+        // The Configuration gets deleted right after its Instance got created
+        assert!(reconcile(make_test_configuration(), ctx.clone())
+            .await
+            .is_ok());
+        let mut instance = applied.lock().unwrap().pop().unwrap();
+        assert!(instance.spec.first_discovered.is_none());
+        assert_eq!(
+            instance.spec.last_seen.keys().collect::<Vec<_>>(),
+            vec!["node-a"]
+        );
+        instance.metadata.namespace = Some("namespace-a".to_string());
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Applied(instance.clone()));
+
+        // The Instance gets deleted and the Configuration stays around until it is gone
+        assert_eq!(
+            reconcile(make_deleted_test_configuration(), ctx.clone())
+                .await
+                .unwrap(),
+            Action::requeue(DELETION_REQUEUE)
+        );
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Deleted(instance));
+        assert_eq!(
+            reconcile(make_deleted_test_configuration(), ctx.clone())
+                .await
+                .unwrap(),
+            Action::await_change()
+        );
+        assert!(ctx.instances_cache.state().is_empty());
+    }
+
+    #[test]
+    fn test_discovery_summary() {
+        let previous = HashSet::from(["config-a-1".to_string(), "config-a-2".to_string()]);
+        let current = HashSet::from([
+            "config-a-2".to_string(),
+            "config-a-3".to_string(),
+            "config-a-4".to_string(),
+        ]);
+        let summary = DiscoverySummary::new(
+            "namespace-a",
+            "config-a",
+            "debugEcho",
+            &previous,
+            &current,
+            Duration::from_millis(42),
+        );
+        assert_eq!(
+            summary,
+            DiscoverySummary {
+                namespace: "namespace-a".to_string(),
+                configuration: "config-a".to_string(),
+                handler: "debugEcho".to_string(),
+                devices: 3,
+                added: 2,
+                removed: 1,
+                duration: Duration::from_millis(42),
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "Discovery summary for Configuration namespace-a::config-a (handler debugEcho): 3 device(s), 2 added, 1 removed, took 42ms"
+        );
+    }
+
+    #[test]
+    fn test_set_shared() {
+        let instance = Instance {
+            metadata: ObjectMeta {
+                name: Some("instance-1".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-1".to_string(),
+                cdi_name: "akri.sh/config-1=abcdef".to_string(),
+                capacity: 1,
+                broker_properties: HashMap::new(),
+                shared: true,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        };
+
+        // No override, the Discovery Handler value is kept
+        let mut default_instance = instance.clone();
+        set_shared(&mut default_instance, None, "node-a");
+        assert!(default_instance.spec.shared);
+        assert_eq!(
+            default_instance
+                .annotations()
+                .get("shared-source.akri.sh/node-a")
+                .unwrap(),
+            AKRI_SHARED_SOURCE_DISCOVERY_HANDLER
+        );
+
+        // Configuration override takes precedence
+        let mut override_instance = instance;
+        set_shared(&mut override_instance, Some(false), "node-a");
+        assert!(!override_instance.spec.shared);
+        assert_eq!(
+            override_instance
+                .annotations()
+                .get("shared-source.akri.sh/node-a")
+                .unwrap(),
+            AKRI_SHARED_SOURCE_CONFIGURATION
+        );
+    }
+
+    #[test]
+    fn test_set_last_warning_node() {
+        let mut instance = make_test_instance("config-1-a");
+        set_last_warning_node(&mut instance, "node-a");
+        assert!(instance.annotations().is_empty());
+
+        instance.annotations_mut().insert(
+            AKRI_LAST_WARNING_ANNOTATION_NAME.to_string(),
+            "authentication failed".to_string(),
+        );
+        set_last_warning_node(&mut instance, "node-a");
+        assert_eq!(
+            instance.annotations(),
+            &BTreeMap::from([(
+                "last-warning.akri.sh/node-a".to_string(),
+                "authentication failed".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_set_resource_name() {
+        let mut instance = Instance {
+            metadata: ObjectMeta {
+                name: Some("instance-1".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-1".to_string(),
+                cdi_name: "akri.sh/config-1=abcdef".to_string(),
+                capacity: 1,
+                broker_properties: HashMap::new(),
+                shared: true,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        };
+
+        set_resource_name(&mut instance, None);
+        assert!(!instance
+            .annotations()
+            .contains_key(AKRI_RESOURCE_NAME_ANNOTATION_NAME));
+
+        set_resource_name(&mut instance, Some("gpu-camera"));
+        assert_eq!(
+            instance
+                .annotations()
+                .get(AKRI_RESOURCE_NAME_ANNOTATION_NAME)
+                .unwrap(),
+            "gpu-camera"
+        );
+    }
+
+    #[test]
+    fn test_set_resource_name_aliases() {
+        let mut instance = make_test_instance("config-1-a");
+        set_resource_name_aliases(&mut instance, Some(&[]));
+        assert!(!instance
+            .annotations()
+            .contains_key(AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME));
+
+        set_resource_name_aliases(
+            &mut instance,
+            Some(&["usb-camera".to_string(), "camera".to_string()]),
+        );
+        assert_eq!(
+            instance
+                .annotations()
+                .get(AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME)
+                .unwrap(),
+            "usb-camera,camera"
+        );
+    }
+
+    #[test]
+    fn test_set_discovery_timestamps() {
+        let time = |secs| Time(k8s_openapi::chrono::DateTime::from_timestamp(secs, 0).unwrap());
+
+        // A newly discovered Instance gets its first discovery time once created
+        let mut instance = make_test_instance("config-1-a");
+        set_discovery_timestamps(&mut instance, None, "node-a", &time(100));
+        assert_eq!(instance.spec.first_discovered, None);
+        assert_eq!(
+            instance.spec.last_seen,
+            BTreeMap::from([("node-a".to_string(), time(100))])
+        );
+
+        // Instances created before the timestamps were recorded were first discovered when created
+        let mut existing = instance.clone();
+        existing.metadata.creation_timestamp = Some(time(101));
+        let mut instance = make_test_instance("config-1-a");
+        set_discovery_timestamps(&mut instance, Some(&existing), "node-a", &time(200));
+        assert_eq!(instance.spec.first_discovered, Some(time(101)));
+
+        // A recent last seen time of this node is kept, other nodes' ones are left to them
+        let mut existing = instance.clone();
+        existing.spec.first_discovered = Some(time(100));
+        existing
+            .spec
+            .last_seen
+            .insert("node-b".to_string(), time(150));
+        let mut instance = make_test_instance("config-1-a");
+        set_discovery_timestamps(&mut instance, Some(&existing), "node-a", &time(400));
+        assert_eq!(instance.spec.first_discovered, Some(time(100)));
+        assert_eq!(
+            instance.spec.last_seen,
+            BTreeMap::from([("node-a".to_string(), time(100))])
+        );
+
+        // An outdated last seen time gets refreshed
+        let mut instance = make_test_instance("config-1-a");
+        set_discovery_timestamps(&mut instance, Some(&existing), "node-a", &time(500));
+        assert_eq!(instance.spec.first_discovered, Some(time(100)));
+        assert_eq!(
+            instance.spec.last_seen,
+            BTreeMap::from([("node-a".to_string(), time(500))])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_keeps_recent_discovery_timestamps() {
+        let recent =
+            Time(k8s_openapi::chrono::Utc::now() - k8s_openapi::chrono::Duration::seconds(60));
+        let first_discovered =
+            Time(k8s_openapi::chrono::Utc::now() - k8s_openapi::chrono::Duration::seconds(3600));
+        let (store, mut writer) = kube_runtime::reflector::store();
+        let mut existing = make_test_instance("config-1-a");
+        existing.metadata.namespace = Some("namespace-a".to_string());
+        existing.spec.nodes = vec!["node-a".to_string(), "node-b".to_string()];
+        existing.spec.first_discovered = Some(first_discovered.clone());
+        existing.spec.last_seen = BTreeMap::from([
+            ("node-a".to_string(), recent.clone()),
+            ("node-b".to_string(), recent.clone()),
+        ]);
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Applied(existing));
+
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        let mut instance_api = MockApi::new();
+        // The applied Instance is the same as upon the previous discovery, the apply is a no-op
+        instance_api
+            .expect_apply()
+            .times(1)
+            .withf(move |instance, field_manager| {
+                field_manager == "node-a"
+                    && instance.spec.first_discovered == Some(first_discovered.clone())
+                    && instance.spec.last_seen
+                        == BTreeMap::from([("node-a".to_string(), recent.clone())])
+            })
+            .returning(|instance, _| Ok(instance));
+        client
+            .instance
+            .expect_namespaced()
+            .return_once(|_| Box::new(instance_api));
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        let mut request = MockDiscoveryHandlerRequest::new();
+        request
+            .expect_set_extra_device_properties()
+            .returning(|_| {});
+        request
+            .expect_set_broker_property_templates()
+            .returning(|_| {});
+        request
+            .expect_get_instances()
+            .returning(|| Ok(vec![make_test_instance("config-1-a")]));
+        registry
+            .expect_get_request()
+            .return_once(|_| Some(Arc::new(request)));
+
+        let ctx = Arc::new(make_test_context(store, registry, client));
+        assert!(reconcile(make_test_configuration(), ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_sets_configuration_generation() {
+        for generation in [1, 2] {
+            let (store, _) = kube_runtime::reflector::store();
+            let mut client = MockDiscoveryConfigurationKubeClient::default();
+            client
+                .config
+                .expect_namespaced()
+                .return_once(|_| Box::new(MockApi::new()));
+            let mut instance_api = MockApi::new();
+            instance_api
+                .expect_apply()
+                .times(1)
+                .withf(move |instance, _| {
+                    instance
+                        .annotations()
+                        .get("configuration-generation.akri.sh/node-a")
+                        == Some(&generation.to_string())
+                })
+                .returning(|instance, _| Ok(instance));
+            client
+                .instance
+                .expect_namespaced()
+                .return_once(|_| Box::new(instance_api));
+
+            let mut registry = MockDiscoveryHandlerRegistry::new();
+
+            registry.expect_backend_down().returning(|_| None);
+            let mut request = MockDiscoveryHandlerRequest::new();
+            request
+                .expect_set_extra_device_properties()
+                .returning(|_| {});
+            request
+                .expect_set_broker_property_templates()
+                .returning(|_| {});
+            request.expect_get_instances().returning(|| {
+                Ok(vec![Instance {
+                    metadata: ObjectMeta {
+                        name: Some("config-1-abcdef".to_string()),
+                        ..Default::default()
+                    },
+                    spec: InstanceSpec {
+                        configuration_name: "config-1".to_string(),
+                        cdi_name: "akri.sh/config-1=abcdef".to_string(),
+                        capacity: 1,
+                        broker_properties: HashMap::new(),
+                        shared: true,
+                        nodes: vec![],
+                        device_usage: Default::default(),
+                        first_discovered: None,
+                        last_seen: Default::default(),
+                    },
+                }])
+            });
+            registry
+                .expect_get_request()
+                .return_once(|_| Some(Arc::new(request)));
+
+            let ctx = Arc::new(make_test_context(store, registry, client));
+
+            let dc = Arc::new(Configuration {
+                metadata: ObjectMeta {
+                    name: Some("config-1".to_string()),
+                    namespace: Some("namespace-a".to_string()),
+                    uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                    finalizers: Some(vec!["node-a".to_string()]),
+                    generation: Some(generation),
+                    ..Default::default()
+                },
+                spec: ConfigurationSpec {
+                    discovery_handlers: DiscoveryHandlerInfo {
+                        name: "debugEcho".to_string(),
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                },
+            });
+
+            assert!(reconcile(dc, ctx).await.is_ok());
+        }
+    }
+
+    #[test]
+    fn test_cap_instances() {
+        let instances: Vec<Instance> = ["config-1-a", "config-1-b", "config-1-c"]
+            .into_iter()
+            .map(make_test_instance)
+            .collect();
+
+        let capped = cap_instances(instances.clone(), 2, &HashSet::new());
+        assert_eq!(
+            capped.iter().map(|i| i.name_any()).collect::<Vec<_>>(),
+            vec!["config-1-a", "config-1-b"]
+        );
+
+        // Already existing Instances are kept first
+        let capped = cap_instances(
+            instances.clone(),
+            2,
+            &HashSet::from(["config-1-c".to_string()]),
+        );
+        assert_eq!(
+            capped.iter().map(|i| i.name_any()).collect::<Vec<_>>(),
+            vec!["config-1-c", "config-1-a"]
+        );
+
+        assert_eq!(cap_instances(instances, 5, &HashSet::new()).len(), 3);
+    }
+
+    fn make_max_instances_test_configuration() -> Arc<Configuration> {
+        let mut dc = make_test_configuration().as_ref().clone();
+        dc.spec.max_instances = Some(2);
+        Arc::new(dc)
+    }
+
+    fn expect_max_instances_reached_event(client: &mut MockDiscoveryConfigurationKubeClient) {
+        let mut event_api = MockApi::new();
+        event_api
+            .expect_apply()
+            .times(1)
+            .withf(|event, _| {
+                event.metadata.name == Some("config-1.max-instances.node-a".to_string())
+                    && event.reason == Some(MAX_INSTANCES_REACHED_EVENT_REASON.to_string())
+                    && event.involved_object.name == Some("config-1".to_string())
+            })
+            .returning(|event, _| Ok(event));
+        client
+            .event
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(event_api));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_max_instances() {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        // The debugEcho handler reports more devices than the cap, only the first ones get an Instance
+        let mut instance_api = MockApi::new();
+        instance_api
+            .expect_apply()
+            .times(2)
+            .withf(|instance, _| instance.name_any() != "config-1-c")
+            .returning(|instance, _| Ok(instance));
+        client
+            .instance
+            .expect_namespaced()
+            .return_once(|_| Box::new(instance_api));
+        expect_max_instances_reached_event(&mut client);
+
+        let ctx = Arc::new(make_test_context(
+            store,
+            make_test_registry(&["config-1-c", "config-1-b", "config-1-a"]),
+            client,
+        ));
+
+        assert!(reconcile(make_max_instances_test_configuration(), ctx)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_max_instances_reported_once() {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .returning(|_| Box::new(MockApi::new()));
+        client.instance.expect_namespaced().times(2).returning(|_| {
+            let mut instance_api = MockApi::new();
+            instance_api
+                .expect_apply()
+                .times(2)
+                .returning(|instance, _| Ok(instance));
+            Box::new(instance_api)
+        });
+        // The cap is still reached on the second reconciliation, its Event is not reported again
+        expect_max_instances_reached_event(&mut client);
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_get_request().returning(|_| {
+            let mut request = MockDiscoveryHandlerRequest::new();
+            request
+                .expect_set_extra_device_properties()
+                .returning(|_| {});
+            request
+                .expect_set_broker_property_templates()
+                .returning(|_| {});
+            request.expect_get_instances().returning(|| {
+                Ok(["config-1-c", "config-1-b", "config-1-a"]
+                    .into_iter()
+                    .map(make_test_instance)
+                    .collect())
+            });
+            Some(Arc::new(request))
+        });
+        let ctx = Arc::new(make_test_context(store, registry, client));
+
+        for _ in 0..2 {
+            assert!(
+                reconcile(make_max_instances_test_configuration(), ctx.clone())
+                    .await
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_configuration_warnings() {
+        let warnings = ConfigurationWarnings::default();
+        assert!(warnings.raise("ns-a", "config-a", MAX_INSTANCES_WARNING));
+        assert!(!warnings.raise("ns-a", "config-a", MAX_INSTANCES_WARNING));
+        // A Configuration of the same name in another namespace has its own warnings
+        assert!(warnings.raise("ns-b", "config-a", MAX_INSTANCES_WARNING));
+        // A warning that stopped holding gets reported again once raised
+        warnings.clear("ns-a", "config-a", MAX_INSTANCES_WARNING);
+        assert!(warnings.raise("ns-a", "config-a", MAX_INSTANCES_WARNING));
+        warnings.remove("ns-b", "config-a");
+        assert!(warnings.raise("ns-b", "config-a", MAX_INSTANCES_WARNING));
+        warnings.clear("ns-a", "config-a", MAX_INSTANCES_WARNING);
+        warnings.clear("ns-b", "config-a", MAX_INSTANCES_WARNING);
+        assert!(warnings.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_max_instances_resumes_after_removal() {
+        // config-1-a and config-1-b already exist, config-1-a is not discovered anymore
+        let (store, mut writer) = kube_runtime::reflector::store();
+        let dc = make_max_instances_test_configuration();
+        let owner_ref = dc.controller_owner_ref(&()).unwrap();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(
+            ["config-1-a", "config-1-b"]
+                .into_iter()
+                .map(|name| {
+                    let mut instance = make_test_instance(name);
+                    instance.metadata.namespace = Some("namespace-a".to_string());
+                    instance.metadata.owner_references = Some(vec![owner_ref.clone()]);
+                    instance.spec.nodes = vec!["node-a".to_string()];
+                    instance
+                })
+                .collect(),
+        ));
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        let mut seq = mockall::Sequence::new();
+        let mut delete_api = MockApi::new();
+        delete_api
+            .expect_delete()
+            .times(1)
+            .with(eq("config-1-a"))
+            .returning(|_| Ok(itertools::Either::Right(Status::default())));
+        client
+            .instance
+            .expect_namespaced()
+            .times(1)
+            .return_once(|_| Box::new(delete_api))
+            .in_sequence(&mut seq);
+        // The room left by config-1-a is taken by the next device while the existing Instance is kept
+        let mut apply_api = MockApi::new();
+        apply_api
+            .expect_apply()
+            .times(2)
+            .withf(|instance, _| {
+                ["config-1-b", "config-1-c"].contains(&instance.name_any().as_str())
+            })
+            .returning(|instance, _| Ok(instance));
+        client
+            .instance
+            .expect_namespaced()
+            .times(1)
+            .return_once(|_| Box::new(apply_api))
+            .in_sequence(&mut seq);
+        expect_max_instances_reached_event(&mut client);
+
+        let ctx = Arc::new(make_test_context(
+            store,
+            make_test_registry(&["config-1-d", "config-1-c", "config-1-b"]),
+            client,
+        ));
+
+        assert!(reconcile(dc, ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_backend_down() {
+        // config-1-a exists, it is kept while the backend is down
+        let (store, mut writer) = kube_runtime::reflector::store();
+        let dc = make_test_configuration();
+        let mut instance = make_test_instance("config-1-a");
+        instance.metadata.namespace = Some("namespace-a".to_string());
+        instance.metadata.owner_references = Some(vec![dc.controller_owner_ref(&()).unwrap()]);
+        instance.spec.nodes = vec!["node-a".to_string()];
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![instance]));
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry
+            .expect_backend_down()
+            .with(eq("debugEcho"))
+            .returning(|_| Some("broker unreachable".to_string()));
+        registry.expect_get_request().never();
+        registry.expect_new_request().never();
+        registry.expect_terminate_request().never();
+
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .returning(|_| Box::new(MockApi::new()));
+        client.instance.expect_namespaced().never();
+        client.instance.expect_all().never();
+        let mut event_api = MockApi::new();
+        event_api
+            .expect_apply()
+            .times(1)
+            .withf(|event, _| {
+                event.metadata.name == Some("config-1.discovery-degraded.node-a".to_string())
+                    && event.reason == Some(DISCOVERY_DEGRADED_EVENT_REASON.to_string())
+                    && event
+                        .message
+                        .as_ref()
+                        .is_some_and(|m| m.contains("broker unreachable"))
+            })
+            .returning(|event, _| Ok(event));
+        client
+            .event
+            .expect_namespaced()
+            .with(eq("namespace-a"))
+            .return_once(|_| Box::new(event_api));
+
+        let ctx = Arc::new(make_test_context(store, registry, client));
+
+        // The backend is still down on the second reconciliation, its Event is not reported again
+        for _ in 0..2 {
+            assert_eq!(
+                reconcile(dc.clone(), ctx.clone()).await.unwrap(),
+                Action::requeue(SUCCESS_REQUEUE)
+            );
+        }
+    }
+
+    fn make_node_selector_test_context(
+        node_labels: BTreeMap<String, String>,
+        registry: MockDiscoveryHandlerRegistry,
+    ) -> Arc<ControllerContext> {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        let mut node_api = MockApi::new();
+        node_api
+            .expect_get()
+            .with(eq("node-a"))
+            .return_once(move |_| {
+                Ok(Some(Node {
+                    metadata: ObjectMeta {
+                        name: Some("node-a".to_string()),
+                        labels: Some(node_labels),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }))
+            });
+        client.node.expect_all().return_once(|| Box::new(node_api));
+        Arc::new(make_test_context(store, registry, client))
+    }
+
+    fn make_node_selector_test_configuration() -> Arc<Configuration> {
+        Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-1".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                finalizers: Some(vec!["node-a".to_string()]),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handlers: DiscoveryHandlerInfo {
+                    name: "opcua".to_string(),
+                    ..Default::default()
+                }
+                .into(),
+                discovery_node_selector: Some(BTreeMap::from([(
+                    "akri.sh/opcua".to_string(),
+                    "enabled".to_string(),
+                )])),
+                ..Default::default()
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_node_not_selected() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        // Discovery is neither queried nor started
+        registry.expect_get_request().never();
+        registry.expect_new_request().never();
+        registry
+            .expect_terminate_request()
+            .with(eq("config-1"))
+            .times(1)
+            .returning(|_| ());
+        let ctx = make_node_selector_test_context(
+            BTreeMap::from([("akri.sh/opcua".to_string(), "disabled".to_string())]),
+            registry,
+        );
+
+        assert!(reconcile(make_node_selector_test_configuration(), ctx)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_node_selected() {
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_terminate_request().never();
+        registry.expect_get_request().times(1).return_once(|_| None);
+        registry
+            .expect_new_request()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        let ctx = make_node_selector_test_context(
+            BTreeMap::from([
+                ("akri.sh/opcua".to_string(), "enabled".to_string()),
+                ("kubernetes.io/os".to_string(), "linux".to_string()),
+            ]),
+            registry,
+        );
+
+        assert!(reconcile(make_node_selector_test_configuration(), ctx)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_rediscover_annotation() {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .returning(|_| Box::new(MockApi::new()));
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_get_request().returning(|_| {
+            let mut request = MockDiscoveryHandlerRequest::new();
+            request
+                .expect_set_extra_device_properties()
+                .returning(|_| {});
+            request
+                .expect_set_broker_property_templates()
+                .returning(|_| {});
+            request.expect_get_instances().returning(|| Ok(vec![]));
+            Some(Arc::new(request))
+        });
+        // Only the change from "1" to "2" restarts the discovery
+        registry
+            .expect_terminate_request()
+            .with(eq("config-1"))
+            .times(1)
+            .returning(|_| ());
+        registry
+            .expect_new_request()
+            .withf(|name, _, _, _, _| name == "config-1")
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+
+        let ctx = Arc::new(make_test_context(store, registry, client));
+
+        let make_configuration = |rediscover: &str| {
+            Arc::new(Configuration {
+                metadata: ObjectMeta {
+                    name: Some("config-1".to_string()),
+                    namespace: Some("namespace-a".to_string()),
+                    uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                    finalizers: Some(vec!["node-a".to_string()]),
+                    annotations: Some(BTreeMap::from([(
+                        AKRI_REDISCOVER_ANNOTATION_NAME.to_string(),
+                        rediscover.to_string(),
+                    )])),
+                    ..Default::default()
+                },
+                spec: ConfigurationSpec {
+                    discovery_handlers: DiscoveryHandlerInfo {
+                        name: "debugEcho".to_string(),
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                },
+            })
+        };
+
+        for rediscover in ["1", "1", "2", "2"] {
+            assert!(reconcile(make_configuration(rediscover), ctx.clone())
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconcile_instance_offline_grace_period() {
+        let (store, mut writer) = kube_runtime::reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![Instance {
+            metadata: ObjectMeta {
+                namespace: Some("namespace-a".to_string()),
+                name: Some("instance-1".to_string()),
+                owner_references: Some(vec![OwnerReference {
+                    api_version: Instance::api_version(&()).to_string(),
+                    block_owner_deletion: None,
+                    controller: Some(true),
+                    kind: "Configuration".to_string(),
+                    name: "config-grace".to_string(),
+                    uid: "00112233-4455-6677-8899-aabbccddeeff".to_string(),
+                }]),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-grace".to_string(),
+                cdi_name: "akri.sh/config-grace=abcdef".to_string(),
+                capacity: 1,
+                broker_properties: HashMap::new(),
+                shared: false,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        }]));
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .returning(|_| Box::new(MockApi::new()));
+        // The Instance only gets deleted once the grace period elapsed
+        client.instance.expect_namespaced().times(1).returning(|_| {
+            let mut instance_api = MockApi::new();
+            instance_api
+                .expect_delete()
+                .with(eq("instance-1"))
+                .times(1)
+                .returning(|_| Ok(itertools::Either::Right(Status::default())));
+            Box::new(instance_api)
+        });
+
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_get_request().returning(|_| {
+            let mut request = MockDiscoveryHandlerRequest::new();
+            request
+                .expect_set_extra_device_properties()
+                .returning(|_| {});
+            request
+                .expect_set_broker_property_templates()
+                .returning(|_| {});
+            request.expect_get_instances().returning(|| Ok(vec![]));
+            Some(Arc::new(request))
+        });
+
+        let ctx = Arc::new(make_test_context(store, registry, client));
+
+        let dc = Arc::new(Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-grace".to_string()),
+                namespace: Some("namespace-a".to_string()),
+                uid: Some("00112233-4455-6677-8899-aabbccddeeff".to_string()),
+                finalizers: Some(vec!["node-a".to_string()]),
+                ..Default::default()
+            },
+            spec: ConfigurationSpec {
+                discovery_handlers: DiscoveryHandlerInfo {
+                    name: "debugEcho".to_string(),
+                    ..Default::default()
+                }
+                .into(),
+                instance_offline_grace_secs: Some(60),
+                ..Default::default()
+            },
+        });
+
+        // Kept and requeued for when the grace period elapses
+        assert_eq!(
+            reconcile(dc.clone(), ctx.clone()).await.unwrap(),
+            Action::requeue(Duration::from_secs(60))
+        );
+        tokio::time::advance(Duration::from_secs(45)).await;
+        assert_eq!(
+            reconcile(dc.clone(), ctx.clone()).await.unwrap(),
+            Action::requeue(Duration::from_secs(15))
+        );
+        // Deleted once the grace period elapsed
+        tokio::time::advance(Duration::from_secs(15)).await;
+        assert_eq!(
+            reconcile(dc, ctx).await.unwrap(),
+            Action::requeue(SUCCESS_REQUEUE)
+        );
+    }
+
+    #[test]
+    fn test_offline_instances_back_online() {
+        let offline_instances = OfflineInstances::default();
+        let grace = Duration::from_secs(10);
+        let start = Instant::now();
+        assert_eq!(
+            offline_instances.remaining_grace("ns-a", "config-a", "instance-1", grace, start),
+            Some(grace)
+        );
+        assert!(offline_instances.is_offline("ns-a", "config-a", "instance-1"));
+        assert_eq!(
+            offline_instances.remaining_grace(
+                "ns-a",
+                "config-a",
+                "instance-1",
+                grace,
+                start + Duration::from_secs(5)
+            ),
+            Some(Duration::from_secs(5))
+        );
+        // Discovered again, the grace period starts over the next time it goes offline
+        offline_instances.online(
+            "ns-a",
+            "config-a",
+            &HashSet::from(["instance-1".to_string()]),
+        );
+        assert!(!offline_instances.is_offline("ns-a", "config-a", "instance-1"));
+        assert_eq!(
+            offline_instances.remaining_grace(
+                "ns-a",
+                "config-a",
+                "instance-1",
+                grace,
+                start + Duration::from_secs(6)
+            ),
+            Some(grace)
+        );
+        // Without a grace period, Instances are removed right away
+        assert_eq!(
+            offline_instances.remaining_grace(
+                "ns-a",
+                "config-a",
+                "instance-2",
+                Duration::ZERO,
+                start
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_offline_instances_per_namespace() {
+        let offline_instances = OfflineInstances::default();
+        let grace = Duration::from_secs(10);
+        let start = Instant::now();
+        offline_instances.remaining_grace("ns-a", "config-a", "instance-1", grace, start);
+        // A Configuration of the same name in another namespace has its own offline Instances
+        assert!(!offline_instances.is_offline("ns-b", "config-a", "instance-1"));
+        offline_instances.online(
+            "ns-b",
+            "config-a",
+            &HashSet::from(["instance-1".to_string()]),
+        );
+        offline_instances.remove("ns-b", "config-a");
+        assert!(offline_instances.is_offline("ns-a", "config-a", "instance-1"));
+    }
+
+    #[test]
+    fn test_rediscover_tracker_per_namespace() {
+        let make_configuration = |namespace: &str, rediscover: &str| Configuration {
+            metadata: ObjectMeta {
+                name: Some("config-a".to_string()),
+                namespace: Some(namespace.to_string()),
+                annotations: Some(BTreeMap::from([(
+                    AKRI_REDISCOVER_ANNOTATION_NAME.to_string(),
+                    rediscover.to_string(),
+                )])),
+                ..Default::default()
+            },
+            spec: Default::default(),
+        };
+        let tracker = RediscoverTracker::default();
+        assert!(!tracker.changed(&make_configuration("ns-a", "1")));
+        // The value of a Configuration of the same name in another namespace is tracked apart
+        assert!(!tracker.changed(&make_configuration("ns-b", "2")));
+        assert!(!tracker.changed(&make_configuration("ns-a", "1")));
+        assert!(tracker.changed(&make_configuration("ns-b", "3")));
+        tracker.remove("ns-b", "config-a");
+        assert!(tracker.changed(&make_configuration("ns-a", "2")));
+    }
+
+    #[test]
+    fn test_discovery_jitter_first_discovery_delay() {
+        let jitter = DiscoveryJitter::new(Duration::from_millis(500));
+        let now = Instant::now();
+        for i in 0..100 {
+            let delay = jitter
+                .first_discovery_delay("ns-a", &format!("config-{}", i), now)
+                .unwrap_or_default();
+            assert!(delay <= Duration::from_millis(500));
+        }
+        // Later calls wait for the same deadline, until it passes
+        let delay = jitter
+            .first_discovery_delay("ns-a", "config-1", now)
+            .unwrap();
+        assert_eq!(
+            jitter.first_discovery_delay("ns-a", "config-1", now + delay / 2),
+            Some(delay - delay / 2)
+        );
+        assert_eq!(
+            jitter.first_discovery_delay("ns-a", "config-1", now + delay),
+            None
+        );
+        assert_eq!(
+            jitter.first_discovery_delay("ns-a", "config-1", now + Duration::from_secs(1)),
+            None
+        );
+        // A Configuration of the same name in another namespace gets its own deadline
+        jitter.first_discovery_delay("ns-b", "config-1", now + Duration::from_secs(1));
+        assert_eq!(101, jitter.deadlines.lock().unwrap().len());
+
+        let jitter = DiscoveryJitter::default();
+        assert_eq!(jitter.first_discovery_delay("ns-a", "config-1", now), None);
+    }
+
+    #[test]
+    fn test_discovery_jitter_from_env() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_JITTER_MAX_MS_LABEL))
+            .returning(|_| Ok("2000".to_string()));
+        assert_eq!(
+            DiscoveryJitter::from_env(&env).max,
+            Duration::from_millis(2000)
+        );
+
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_JITTER_MAX_MS_LABEL))
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert!(DiscoveryJitter::from_env(&env).max.is_zero());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconcile_discovery_jitter() {
+        let (store, _) = kube_runtime::reflector::store();
+        let new_request_called = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let local_new_request_called = new_request_called.clone();
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_get_request().returning(|_| None);
+        registry
+            .expect_new_request()
+            .times(1)
+            .returning(move |_, _, _, _, _| {
+                local_new_request_called.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            });
+        let ctx = Arc::new(ControllerContext {
+            discovery_jitter: DiscoveryJitter::new(Duration::from_secs(30)),
+            ..make_test_context(
+                store,
+                registry,
+                MockDiscoveryConfigurationKubeClient::default(),
+            )
+        });
+        // Make sure the drawn delay is not zero, for the discovery to be actually postponed
+        ctx.discovery_jitter.deadlines.lock().unwrap().insert(
+            "config-1".to_string(),
+            Instant::now() + Duration::from_secs(20),
+        );
+
+        // The first discovery is postponed until the deadline
+        assert_eq!(
+            reconcile(make_test_configuration(), ctx.clone())
+                .await
+                .unwrap(),
+            Action::requeue(Duration::from_secs(20))
+        );
+        // Reconciliations triggered before the deadline wait for it as well
+        tokio::time::advance(Duration::from_secs(15)).await;
+        assert_eq!(
+            reconcile(make_test_configuration(), ctx.clone())
+                .await
+                .unwrap(),
+            Action::requeue(Duration::from_secs(5))
+        );
+        assert!(!new_request_called.load(std::sync::atomic::Ordering::SeqCst));
+
+        // The discovery starts once the deadline passed
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(
+            reconcile(make_test_configuration(), ctx).await.unwrap(),
+            Action::requeue(SUCCESS_REQUEUE)
+        );
+        assert!(new_request_called.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_get_agent_managed_finalizers() {
+        for (value, expected) in [
+            (None, true),
+            (Some("true"), true),
+            (Some("false"), false),
+            (Some("no"), true),
+        ] {
+            let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+            env.expect_get_env_var()
+                .with(eq(AGENT_MANAGED_FINALIZERS_LABEL))
+                .returning(move |_| {
+                    value
+                        .map(String::from)
+                        .ok_or(std::env::VarError::NotPresent)
+                });
+            assert_eq!(get_agent_managed_finalizers(&env), expected);
+        }
+    }
+
+    #[test]
+    fn test_instance_batching_from_env() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(INSTANCE_BATCH_SIZE_LABEL))
+            .returning(|_| Ok("3".to_string()));
+        env.expect_get_env_var()
+            .with(eq(INSTANCE_BATCH_DELAY_MS_LABEL))
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(
+            InstanceBatching::from_env(&env),
+            InstanceBatching {
+                batch_size: 3,
+                batch_delay: DEFAULT_INSTANCE_BATCH_DELAY,
+            }
+        );
+
+        // Instances are not batched by default
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(INSTANCE_BATCH_SIZE_LABEL))
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        env.expect_get_env_var()
+            .with(eq(INSTANCE_BATCH_DELAY_MS_LABEL))
+            .returning(|_| Ok("500".to_string()));
+        assert_eq!(
+            InstanceBatching::from_env(&env),
+            InstanceBatching::default()
+        );
+    }
+
+    #[test]
+    fn test_discovery_export_from_env() {
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_EXPORT_DIRECTORY_LABEL))
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(DiscoveryExport::from_env(&env), None);
+
+        let mut env = akri_shared::os::env_var::MockEnvVarQuery::new();
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_EXPORT_DIRECTORY_LABEL))
+            .returning(|_| Ok("/var/lib/akri/discovery".to_string()));
+        env.expect_get_env_var()
+            .with(eq(DISCOVERY_EXPORT_MAX_BYTES_LABEL))
+            .returning(|_| Ok("1024".to_string()));
+        assert_eq!(
+            DiscoveryExport::from_env(&env),
+            Some(DiscoveryExport {
+                directory: PathBuf::from("/var/lib/akri/discovery"),
+                max_bytes: Some(1024),
+            })
+        );
+    }
+
+    #[test]
+    fn test_discovery_export_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let discovery_export = DiscoveryExport {
+            directory: dir.path().to_path_buf(),
+            max_bytes: None,
+        };
+        let instance = Instance {
+            metadata: ObjectMeta {
+                name: Some("config-a-b494b6".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-a".to_string(),
+                cdi_name: "akri.sh/config-a=b494b6".to_string(),
+                capacity: 1,
+                broker_properties: HashMap::from([(
+                    "DEBUG_ECHO_DESCRIPTION".to_string(),
+                    "foo".to_string(),
+                )]),
+                shared: false,
+                nodes: vec!["node-a".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        };
+
+        // Without max bytes, each cycle replaces the previous one
+        for _ in 0..2 {
+            discovery_export
+                .export(
+                    "namespace-a",
+                    "config-a",
+                    "node-a",
+                    std::slice::from_ref(&instance),
+                )
+                .unwrap();
+        }
+
+        let content =
+            std::fs::read_to_string(dir.path().join("namespace-a.config-a.json")).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        let record: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(record["configuration"], "config-a");
+        assert_eq!(record["namespace"], "namespace-a");
+        assert_eq!(record["node"], "node-a");
+        assert_eq!(
+            record["devices"],
+            serde_json::json!([{
+                "instance": "config-a-b494b6",
+                "cdiName": "akri.sh/config-a=b494b6",
+                "properties": {"DEBUG_ECHO_DESCRIPTION": "foo"},
+            }])
+        );
+
+        // A Configuration of the same name in another namespace is exported to its own file
+        discovery_export
+            .export("namespace-b", "config-a", "node-a", &[])
+            .unwrap();
+        let content =
+            std::fs::read_to_string(dir.path().join("namespace-a.config-a.json")).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        let record: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("namespace-b.config-a.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(record["namespace"], "namespace-b");
+        assert_eq!(record["devices"], serde_json::json!([]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_apply_instances_batched() {
+        let instances: Vec<Instance> = (0..25)
+            .map(|i| Instance {
+                metadata: ObjectMeta {
+                    name: Some(format!("instance-{}", i)),
+                    ..Default::default()
+                },
+                spec: InstanceSpec {
+                    configuration_name: "config-1".to_string(),
+                    cdi_name: format!("akri.sh/config-1={}", i),
+                    capacity: 1,
+                    broker_properties: Default::default(),
+                    shared: false,
+                    nodes: vec!["node-a".to_string()],
+                    device_usage: Default::default(),
+                    first_discovered: None,
+                    last_seen: Default::default(),
+                },
+            })
+            .collect();
+        let applied_at = Arc::new(Mutex::new(Vec::new()));
+        let local_applied_at = applied_at.clone();
+        let mut api = MockApi::new();
+        api.expect_apply().times(25).returning(move |instance, _| {
+            local_applied_at.lock().unwrap().push(Instant::now());
+            Ok(instance)
+        });
+        let batching = InstanceBatching {
+            batch_size: 10,
+            batch_delay: Duration::from_millis(50),
+        };
+
+        let start = Instant::now();
+        assert!(apply_instances(&api, instances, "node-a", &batching)
+            .await
+            .is_ok());
+
+        // 3 batches (10, 10, 5) separated by the batch delay
+        let applied_at = applied_at.lock().unwrap();
+        for (i, at) in applied_at.iter().enumerate() {
+            assert_eq!(
+                at.duration_since(start),
+                batching.batch_delay * (i / 10) as u32
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_apply_instances_unbatched() {
+        let instances: Vec<Instance> = (0..25)
+            .map(|i| make_test_instance(&format!("instance-{}", i)))
+            .collect();
+        let mut api = MockApi::new();
+        api.expect_apply()
+            .times(25)
+            .returning(|instance, _| Ok(instance));
+
+        let start = Instant::now();
+        assert!(
+            apply_instances(&api, instances, "node-a", &Default::default())
+                .await
+                .is_ok()
+        );
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_apply_instance_conflict() {
+        let instance = |shared| Instance {
+            metadata: ObjectMeta {
+                name: Some("config-1-a".to_string()),
+                ..Default::default()
+            },
+            spec: InstanceSpec {
+                configuration_name: "config-1".to_string(),
+                cdi_name: "akri.sh/config-1=a".to_string(),
+                capacity: 1,
+                broker_properties: Default::default(),
+                shared,
+                nodes: vec!["node-b".to_string()],
+                device_usage: Default::default(),
+                first_discovered: None,
+                last_seen: Default::default(),
+            },
+        };
+        let conflict = || {
+            Err(kube::Error::Api(kube::error::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "Apply failed with 1 conflict".to_string(),
+                reason: "Conflict".to_string(),
+                code: ERROR_CONFLICT,
+            }))
+        };
+
+        // The shared Instance created by another node gets this node merged in
+        let mut api = MockApi::new();
+        api.expect_apply()
+            .times(1)
+            .returning(move |_, _| conflict());
+        api.expect_raw_patch()
+            .times(1)
+            .withf(|name, patch, params| {
+                let kube::api::Patch::Apply(value) = patch else {
+                    return false;
+                };
+                name == "config-1-a"
+                    && params.field_manager.as_deref() == Some("node-b-membership")
+                    && value["metadata"]["name"] == "config-1-a"
+                    && value["spec"] == serde_json::json!({"nodes": ["node-b"]})
+            })
+            .returning(move |_, _, _| Ok(instance(true)));
+        assert!(apply_instance(&api, instance(true), "node-b").await.is_ok());
+
+        // Conflicts on local Instances are not expected, they are reported
+        let mut api = MockApi::new();
+        api.expect_apply()
+            .times(1)
+            .returning(move |_, _| conflict());
+        api.expect_raw_patch().never();
+        assert!(apply_instance(&api, instance(false), "node-b")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_reports_instance_count() {
+        // A dedicated Configuration keeps other tests from updating the same metric series
+        let mut dc = make_test_configuration().as_ref().clone();
+        dc.metadata.name = Some("config-count".to_string());
+        let owner_ref = dc.controller_owner_ref(&()).unwrap();
+        let make_instance = |name: &str| {
+            let mut instance = make_test_instance(name);
+            instance.metadata.namespace = Some("namespace-a".to_string());
+            instance.metadata.owner_references = Some(vec![owner_ref.clone()]);
+            instance.spec.nodes = vec!["node-a".to_string()];
+            instance
+        };
+        let make_ctx = |cached: Vec<Instance>,
+                        registry: MockDiscoveryHandlerRegistry,
+                        client: MockDiscoveryConfigurationKubeClient| {
+            let (store, mut writer) = kube_runtime::reflector::store();
+            writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(cached));
+            Arc::new(make_test_context(store, registry, client))
+        };
+        let instance_count = |shared: &str| {
+            INSTANCE_COUNT_METRIC
+                .with_label_values(&["namespace-a", "config-count", shared])
+                .get()
+        };
+
+        // Two devices get discovered
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        let mut apply_api = MockApi::new();
+        apply_api
+            .expect_apply()
+            .times(2)
+            .returning(|instance, _| Ok(instance));
+        client
+            .instance
+            .expect_namespaced()
+            .return_once(|_| Box::new(apply_api));
+        let ctx = make_ctx(
+            vec![],
+            make_test_registry(&["config-count-a", "config-count-b"]),
+            client,
+        );
+        assert!(reconcile(Arc::new(dc.clone()), ctx).await.is_ok());
+        assert_eq!(instance_count("true"), 2);
+        assert_eq!(instance_count("false"), 0);
+
+        // One of them goes away
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(MockApi::new()));
+        let mut seq = mockall::Sequence::new();
+        let mut delete_api = MockApi::new();
+        delete_api
+            .expect_delete()
+            .times(1)
+            .with(eq("config-count-b"))
+            .returning(|_| Ok(itertools::Either::Right(Status::default())));
+        client
+            .instance
+            .expect_namespaced()
+            .times(1)
+            .return_once(|_| Box::new(delete_api))
+            .in_sequence(&mut seq);
+        let mut apply_api = MockApi::new();
+        apply_api
+            .expect_apply()
+            .times(1)
+            .returning(|instance, _| Ok(instance));
+        client
+            .instance
+            .expect_namespaced()
+            .times(1)
+            .return_once(|_| Box::new(apply_api))
+            .in_sequence(&mut seq);
+        let ctx = make_ctx(
+            vec![
+                make_instance("config-count-a"),
+                make_instance("config-count-b"),
+            ],
+            make_test_registry(&["config-count-a"]),
+            client,
+        );
+        assert!(reconcile(Arc::new(dc.clone()), ctx).await.is_ok());
+        assert_eq!(instance_count("true"), 1);
+
+        // The series of the Configuration are removed along with it
+        let mut client = MockDiscoveryConfigurationKubeClient::default();
+        let mut config_api = MockApi::new();
+        config_api
+            .expect_remove_finalizer()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        client
+            .config
+            .expect_namespaced()
+            .return_once(|_| Box::new(config_api));
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_terminate_request().returning(|_| ());
+        dc.metadata.deletion_timestamp = Some(
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(k8s_openapi::chrono::Utc::now()),
+        );
+        let ctx = make_ctx(vec![], registry, client);
+        assert!(reconcile(Arc::new(dc), ctx).await.is_ok());
+        for shared in ["true", "false"] {
+            assert!(INSTANCE_COUNT_METRIC
+                .remove_label_values(&["namespace-a", "config-count", shared])
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_probe_ignores_busy_configuration() {
+        let (store, _) = kube_runtime::reflector::store();
+        let mut registry = MockDiscoveryHandlerRegistry::new();
+        registry.expect_backend_down().returning(|_| None);
+        registry.expect_state().returning(Default::default);
+        let ctx = make_test_context(
+            store,
+            registry,
+            MockDiscoveryConfigurationKubeClient::default(),
+        );
+        let probe_timeout = Duration::from_millis(100);
+        assert!(tokio::time::timeout(probe_timeout, heartbeat_probe(&ctx))
+            .await
+            .is_ok());
+
+        // A long reconciliation holding the guard of its Configuration does not fail the probe
+        let _guard = ctx
+            .configuration_guards
+            .lock("namespace-a", "config-1")
+            .await;
+        assert!(tokio::time::timeout(probe_timeout, heartbeat_probe(&ctx))
+            .await
+            .is_ok());
+    }
 }
@@ -0,0 +1,125 @@
+//! Liveness endpoint of the agent, served at /healthz when the `AGENT_HEALTH_PORT` environment variable
+//! is set. The Configuration controller regularly updates a [Heartbeat], so that a stalled controller
+//! (e.g. deadlocked on a lock) fails the liveness probe and gets the agent restarted instead of silently
+//! not discovering anymore.
+
+use std::{sync::Mutex, time::Duration};
+
+#[cfg(test)]
+use mock_instant::Instant;
+#[cfg(not(test))]
+use std::time::Instant;
+
+use akri_shared::os::env_var::EnvVarQuery;
+use warp::{http::StatusCode, Filter};
+
+/// Name of the environment variable that sets the port of the liveness endpoint
+pub const AGENT_HEALTH_PORT_LABEL: &str = "AGENT_HEALTH_PORT";
+/// Name of the environment variable that sets how long (in seconds) the heartbeat can go without an
+/// update before the agent is reported unhealthy
+pub const HEARTBEAT_TIMEOUT_SECS_LABEL: &str = "HEARTBEAT_TIMEOUT_SECS";
+const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Returns the port to serve the liveness endpoint on, if it is enabled
+pub fn health_port(env_var_query: &impl EnvVarQuery) -> Option<u16> {
+    env_var_query
+        .get_env_var(AGENT_HEALTH_PORT_LABEL)
+        .ok()
+        .and_then(|port| port.parse().ok())
+}
+
+/// Gets the heartbeat timeout from the environment, using the default for unset or invalid values
+pub fn get_heartbeat_timeout(env_var_query: &impl EnvVarQuery) -> Duration {
+    env_var_query
+        .get_env_var(HEARTBEAT_TIMEOUT_SECS_LABEL)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_HEARTBEAT_TIMEOUT)
+}
+
+/// Timestamp of the last time the Configuration controller showed it was making progress
+pub struct Heartbeat(Mutex<Instant>);
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Heartbeat(Mutex::new(Instant::now()))
+    }
+}
+
+impl Heartbeat {
+    /// Records that the controller is alive
+    pub fn beat(&self) {
+        *self.0.lock().unwrap() = Instant::now();
+    }
+
+    /// Returns whether the controller updated the heartbeat within the timeout
+    pub fn is_healthy(&self, timeout: Duration) -> bool {
+        self.0.lock().unwrap().elapsed() <= timeout
+    }
+}
+
+fn health_route(
+    heartbeat: std::sync::Arc<Heartbeat>,
+    timeout: Duration,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("healthz").and(warp::get()).map(move || {
+        if heartbeat.is_healthy(timeout) {
+            warp::reply::with_status("ok", StatusCode::OK)
+        } else {
+            warp::reply::with_status("stalled", StatusCode::SERVICE_UNAVAILABLE)
+        }
+    })
+}
+
+/// Serves the liveness endpoint, reporting unhealthy once the heartbeat is older than the timeout
+pub async fn run_health_server(heartbeat: std::sync::Arc<Heartbeat>, port: u16, timeout: Duration) {
+    info!("starting health server on port {} at /healthz", port);
+    warp::serve(health_route(heartbeat, timeout))
+        .run(([0, 0, 0, 0], port))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akri_shared::os::env_var::MockEnvVarQuery;
+    use mock_instant::MockClock;
+    use std::{env::VarError, sync::Arc};
+
+    #[test]
+    fn test_get_heartbeat_timeout() {
+        for (value, expected) in [
+            (None, DEFAULT_HEARTBEAT_TIMEOUT),
+            (Some("0"), DEFAULT_HEARTBEAT_TIMEOUT),
+            (Some("abc"), DEFAULT_HEARTBEAT_TIMEOUT),
+            (Some("30"), Duration::from_secs(30)),
+        ] {
+            let mut env = MockEnvVarQuery::new();
+            env.expect_get_env_var()
+                .with(mockall::predicate::eq(HEARTBEAT_TIMEOUT_SECS_LABEL))
+                .returning(move |_| value.map(String::from).ok_or(VarError::NotPresent));
+            assert_eq!(get_heartbeat_timeout(&env), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_heartbeat_is_unhealthy() {
+        let heartbeat = Arc::new(Heartbeat::default());
+        let route = health_route(heartbeat.clone(), Duration::from_secs(60));
+
+        MockClock::advance(Duration::from_secs(45));
+        let res = warp::test::request().path("/healthz").reply(&route).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // The controller stopped updating the heartbeat
+        MockClock::advance(Duration::from_secs(30));
+        let res = warp::test::request().path("/healthz").reply(&route).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        heartbeat.beat();
+        let res = warp::test::request().path("/healthz").reply(&route).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
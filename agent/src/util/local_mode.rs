@@ -0,0 +1,558 @@
+//! Local mode, letting the agent run discovery without a Kubernetes cluster, to ease the development of
+//! Discovery Handlers. It is enabled by setting the `AKRI_AGENT_LOCAL_MODE` environment variable.
+//!
+//! In local mode, the agent reads its Configurations from the JSON file pointed to by
+//! `AKRI_AGENT_LOCAL_CONFIGURATIONS` (either a single Configuration or a `List` of Configurations, as
+//! output by `kubectl create --dry-run=client -o json -f <file>`). It serves the registration socket as
+//! usual, but does not register any device plugin to the kubelet: discovered Instances are kept in
+//! memory and logged instead of being written to the cluster. The Configurations are reconciled by the
+//! same discovery Configuration controller as in a cluster, driven by a regular discovery pass rather
+//! than by watching the API server.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use akri_shared::{
+    akri::{configuration::Configuration, instance::Instance},
+    k8s::api::{Api, IntoApi},
+    os::env_var::EnvVarQuery,
+};
+use async_trait::async_trait;
+use itertools::Either;
+use k8s_openapi::api::core::v1::{ConfigMap, Event, Node, Secret};
+use kube::{
+    api::{Patch, PatchParams},
+    core::{ObjectList, Status},
+    Resource, ResourceExt,
+};
+use kube_runtime::{
+    reflector::{self, store::Writer},
+    watcher,
+};
+use serde_json::Value;
+
+use crate::discovery_handler_manager::{
+    self, discovery_handler_registry::DiscoveryHandlerRegistry, DiscoveryError,
+};
+use crate::util::discovery_configuration_controller::{self, ControllerContext};
+
+/// Name of the environment variable that enables local mode
+pub const AKRI_AGENT_LOCAL_MODE_LABEL: &str = "AKRI_AGENT_LOCAL_MODE";
+/// Name of the environment variable that sets the path of the Configurations to use in local mode
+pub const AKRI_AGENT_LOCAL_CONFIGURATIONS_LABEL: &str = "AKRI_AGENT_LOCAL_CONFIGURATIONS";
+/// Delay between two discovery passes, when no discovery result triggers one earlier
+const LOCAL_DISCOVERY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Returns whether the agent should run in local mode
+pub fn is_enabled(env_var_query: &impl EnvVarQuery) -> bool {
+    env_var_query
+        .get_env_var(AKRI_AGENT_LOCAL_MODE_LABEL)
+        .is_ok_and(|v| !matches!(v.to_lowercase().as_str(), "" | "0" | "false"))
+}
+
+/// Parses the Configurations to use in local mode, accepting either a single Configuration or a `List`
+pub fn parse_configurations(content: &str) -> Result<Vec<Configuration>, serde_json::Error> {
+    let mut value: Value = serde_json::from_str(content)?;
+    match value.get_mut("items").map(Value::take) {
+        Some(items) => serde_json::from_value(items),
+        None => Ok(vec![serde_json::from_value(value)?]),
+    }
+}
+
+/// In-memory stand-in for the Kubernetes API, used in local mode by both the discovery properties
+/// solver and the discovery Configuration controller. Secrets and ConfigMaps start empty, so discovery
+/// properties referring to one only resolve if optional.
+#[derive(Default)]
+pub struct LocalKubeClient {
+    secrets: LocalObjects<Secret>,
+    config_maps: LocalObjects<ConfigMap>,
+    configurations: LocalObjects<Configuration>,
+    instances: LocalObjects<Instance>,
+    nodes: LocalObjects<Node>,
+    events: LocalObjects<Event>,
+}
+
+impl LocalKubeClient {
+    /// Returns the Instances currently stored
+    pub fn instances(&self) -> Vec<Instance> {
+        self.instances.lock().unwrap().values().cloned().collect()
+    }
+}
+
+type LocalObjects<T> = Arc<Mutex<BTreeMap<(Option<String>, String), T>>>;
+
+struct InMemoryApi<T> {
+    namespace: Option<String>,
+    objects: LocalObjects<T>,
+}
+
+impl<T> InMemoryApi<T> {
+    fn new(namespace: Option<String>, objects: &LocalObjects<T>) -> Self {
+        InMemoryApi {
+            namespace,
+            objects: objects.clone(),
+        }
+    }
+
+    fn key(&self, name: &str) -> (Option<String>, String) {
+        (self.namespace.clone(), name.to_string())
+    }
+}
+
+fn unsupported(reason: &str, message: String) -> kube::Error {
+    kube::Error::Api(kube::error::ErrorResponse {
+        status: "Failure".to_string(),
+        message,
+        reason: reason.to_string(),
+        code: if reason == "NotFound" { 404 } else { 405 },
+    })
+}
+
+/// Service standing for the API server behind the `kube::Api` handed out in local mode, every
+/// request gets a "service unavailable" error, as there is no API server to send it to
+#[derive(Clone)]
+struct OfflineApiServer;
+
+impl tower::Service<hyper::Request<hyper::Body>> for OfflineApiServer {
+    type Response = hyper::Response<hyper::Body>;
+    type Error = std::convert::Infallible;
+    type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: hyper::Request<hyper::Body>) -> Self::Future {
+        let status = serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Status",
+            "status": "Failure",
+            "message": format!("{} {} is not available in local mode", req.method(), req.uri()),
+            "reason": "ServiceUnavailable",
+            "code": 503,
+        });
+        std::future::ready(Ok(hyper::Response::builder()
+            .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(status.to_string()))
+            .unwrap()))
+    }
+}
+
+#[async_trait]
+impl<T> Api<T> for InMemoryApi<T>
+where
+    T: Resource<DynamicType = ()> + Clone + Send + Sync + 'static,
+{
+    /// Objects are only kept in memory, the returned `kube::Api` fails all its requests
+    fn as_inner(&self) -> kube::Api<T> {
+        kube::Api::all(kube::Client::new(OfflineApiServer, "default"))
+    }
+    async fn apply(&self, mut obj: T, _field_manager: &str) -> Result<T, kube::Error> {
+        let name = obj.name_any();
+        // As the API server would, namespaced objects get the namespace they are applied to
+        if self.namespace.is_some() {
+            obj.meta_mut().namespace = self.namespace.clone();
+        }
+        if self
+            .objects
+            .lock()
+            .unwrap()
+            .insert(self.key(&name), obj.clone())
+            .is_none()
+        {
+            info!("local mode: created {} {}", T::kind(&()), name);
+        }
+        Ok(obj)
+    }
+    async fn raw_patch(
+        &self,
+        name: &str,
+        _patch: &Patch<Value>,
+        _pp: &PatchParams,
+    ) -> Result<T, kube::Error> {
+        Err(unsupported(
+            "MethodNotAllowed",
+            format!("cannot patch {} in local mode", name),
+        ))
+    }
+    async fn delete(&self, name: &str) -> Result<Either<T, Status>, kube::Error> {
+        match self.objects.lock().unwrap().remove(&self.key(name)) {
+            Some(obj) => {
+                info!("local mode: deleted {} {}", T::kind(&()), name);
+                Ok(Either::Left(obj))
+            }
+            None => Err(unsupported("NotFound", format!("{} not found", name))),
+        }
+    }
+    async fn get(&self, name: &str) -> Result<Option<T>, kube::Error> {
+        Ok(self.objects.lock().unwrap().get(&self.key(name)).cloned())
+    }
+    async fn list(&self) -> Result<ObjectList<T>, kube::Error> {
+        let items = self
+            .objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((namespace, _), _)| self.namespace.is_none() || *namespace == self.namespace)
+            .map(|(_, obj)| obj.clone())
+            .collect();
+        Ok(ObjectList {
+            metadata: Default::default(),
+            items,
+        })
+    }
+    async fn set_finalizers(
+        &self,
+        name: &str,
+        finalizers: Option<Vec<String>>,
+        _field_manager: &str,
+    ) -> Result<(), kube::Error> {
+        match self.objects.lock().unwrap().get_mut(&self.key(name)) {
+            Some(obj) => {
+                obj.meta_mut().finalizers = finalizers;
+                Ok(())
+            }
+            None => Err(unsupported("NotFound", format!("{} not found", name))),
+        }
+    }
+}
+
+macro_rules! impl_local_into_api {
+    ($kind:ty, $objects:ident) => {
+        impl IntoApi<$kind> for LocalKubeClient {
+            fn all(&self) -> Box<dyn Api<$kind>> {
+                Box::new(InMemoryApi::new(None, &self.$objects))
+            }
+
+            fn namespaced(&self, namespace: &str) -> Box<dyn Api<$kind>> {
+                Box::new(InMemoryApi::new(
+                    Some(namespace.to_string()),
+                    &self.$objects,
+                ))
+            }
+
+            fn default_namespaced(&self) -> Box<dyn Api<$kind>> {
+                IntoApi::<$kind>::namespaced(self, "default")
+            }
+        }
+    };
+}
+
+impl_local_into_api!(Secret, secrets);
+impl_local_into_api!(ConfigMap, config_maps);
+impl_local_into_api!(Configuration, configurations);
+impl_local_into_api!(Instance, instances);
+impl_local_into_api!(Node, nodes);
+impl_local_into_api!(Event, events);
+
+/// Agent running in local mode: the Configurations are reconciled by the actual discovery
+/// Configuration controller, against the in-memory client rather than a cluster
+pub struct LocalAgent {
+    client: Arc<LocalKubeClient>,
+    ctx: Arc<ControllerContext>,
+    instances_writer: Writer<Instance>,
+    configurations: Vec<Arc<Configuration>>,
+}
+
+impl LocalAgent {
+    /// Stores the Configurations and the agent's Node in the in-memory client. Configurations missing
+    /// a namespace or uid get one, as it would have been set by the API server.
+    pub async fn new(
+        node_name: String,
+        configurations: Vec<Configuration>,
+        client: Arc<LocalKubeClient>,
+        dh_registry: Arc<dyn DiscoveryHandlerRegistry>,
+    ) -> Result<Self, kube::Error> {
+        let mut node = Node::default();
+        node.metadata.name = Some(node_name.clone());
+        IntoApi::<Node>::all(client.as_ref())
+            .apply(node, &node_name)
+            .await?;
+        let mut stored = Vec::new();
+        for mut dc in configurations {
+            let namespace = dc.namespace().unwrap_or("default".to_string());
+            dc.metadata
+                .uid
+                .get_or_insert(format!("local-{}-{}", namespace, dc.name_any()));
+            dc.metadata.namespace = Some(namespace.clone());
+            stored.push(Arc::new(
+                IntoApi::<Configuration>::namespaced(client.as_ref(), &namespace)
+                    .apply(dc, &node_name)
+                    .await?,
+            ));
+        }
+        let (instances_cache, instances_writer) = reflector::store();
+        let ctx = Arc::new(ControllerContext {
+            instances_cache,
+            dh_registry,
+            client: client.clone(),
+            agent_identifier: node_name,
+            error_backoffs: Default::default(),
+            instance_batching: Default::default(),
+            discovery_export: None,
+            configuration_guards: Default::default(),
+            discovery_jitter: Default::default(),
+            rediscover_tracker: Default::default(),
+            offline_instances: Default::default(),
+            configuration_warnings: Default::default(),
+            // Finalizers only matter for Configurations deleted from a cluster
+            managed_finalizers: false,
+        });
+        Ok(LocalAgent {
+            client,
+            ctx,
+            instances_writer,
+            configurations: stored,
+        })
+    }
+
+    /// Reconciles every Configuration once, then refreshes the Instances cache from the stored
+    /// Instances, as the Instances watcher would
+    pub async fn discovery_pass(&mut self) {
+        for dc in self.configurations.iter() {
+            match discovery_configuration_controller::reconcile(dc.clone(), self.ctx.clone()).await
+            {
+                Ok(_) => {}
+                // The Discovery Handler may register later on, discovery is retried on next pass
+                Err(discovery_configuration_controller::Error::DiscoveryError(
+                    DiscoveryError::NoHandler(dh),
+                )) => debug!(
+                    "local mode: no Discovery Handler {} registered yet for Configuration {}",
+                    dh,
+                    dc.name_any()
+                ),
+                Err(e) => warn!(
+                    "local mode: failed to reconcile Configuration {}: {}",
+                    dc.name_any(),
+                    e
+                ),
+            }
+        }
+        self.instances_writer
+            .apply_watcher_event(&watcher::Event::Restarted(self.client.instances()));
+    }
+}
+
+/// Runs the agent in local mode, until the registration server stops
+pub async fn run(
+    node_name: String,
+    env_var_query: &impl EnvVarQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let path = env_var_query.get_env_var(AKRI_AGENT_LOCAL_CONFIGURATIONS_LABEL)?;
+    let configurations = parse_configurations(&tokio::fs::read_to_string(&path).await?)?;
+    info!(
+        "local mode: loaded {} Configuration(s) from {}",
+        configurations.len(),
+        path
+    );
+
+    let client = Arc::new(LocalKubeClient::default());
+    let (_device_notifier, dh_registry, mut config_notifier) =
+        discovery_handler_manager::new_registry(client.clone());
+    let dh_registry = Arc::new(dh_registry);
+    let local_dh_reg = dh_registry.clone();
+    let local_node_name = node_name.clone();
+    let registration = tokio::spawn(async {
+        discovery_handler_manager::run_registration_server(
+            local_dh_reg,
+            &akri_discovery_utils::get_registration_socket(),
+            local_node_name,
+        )
+        .await
+        .unwrap()
+    });
+
+    let mut agent = LocalAgent::new(node_name, configurations, client, dh_registry).await?;
+    let mut interval = tokio::time::interval(LOCAL_DISCOVERY_INTERVAL);
+    tokio::pin!(registration);
+    loop {
+        tokio::select! {
+            res = &mut registration => {
+                res?;
+                return Ok(());
+            }
+            _ = interval.tick() => {}
+            Some(_) = config_notifier.recv() => {}
+        }
+        agent.discovery_pass().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery_handler_manager::discovery_handler_registry::{
+        DiscoveredDevice, MockDiscoveryHandlerEndpoint,
+    };
+    use akri_discovery_utils::discovery::v0::Device;
+    use akri_shared::os::env_var::MockEnvVarQuery;
+    use futures::FutureExt;
+
+    const CONFIGURATION: &str = r#"{
+        "apiVersion": "akri.sh/v0",
+        "kind": "Configuration",
+        "metadata": { "name": "config-a", "namespace": "default" },
+        "spec": { "discoveryHandler": { "name": "mock_handler", "discoveryDetails": "" } }
+    }"#;
+
+    #[test]
+    fn test_is_enabled() {
+        for (value, expected) in [
+            (None, false),
+            (Some("false"), false),
+            (Some("0"), false),
+            (Some("true"), true),
+            (Some("1"), true),
+        ] {
+            let mut mock_env_var = MockEnvVarQuery::new();
+            mock_env_var.expect_get_env_var().returning(move |_| {
+                value
+                    .map(String::from)
+                    .ok_or(std::env::VarError::NotPresent)
+            });
+            assert_eq!(is_enabled(&mock_env_var), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_configurations() {
+        assert_eq!(parse_configurations(CONFIGURATION).unwrap().len(), 1);
+        let list = format!(
+            r#"{{ "apiVersion": "v1", "kind": "List", "items": [{}, {}] }}"#,
+            CONFIGURATION, CONFIGURATION
+        );
+        assert_eq!(parse_configurations(&list).unwrap().len(), 2);
+        assert!(parse_configurations("{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_kube_client() {
+        let client = LocalKubeClient::default();
+        let secrets = IntoApi::<Secret>::namespaced(&client, "ns");
+        assert_eq!(secrets.get("secret").await.unwrap(), None);
+        let mut secret = Secret::default();
+        secret.metadata.name = Some("secret".to_string());
+        secrets.apply(secret.clone(), "test").await.unwrap();
+        // The object gets the namespace it is applied to
+        secret.metadata.namespace = Some("ns".to_string());
+        assert_eq!(secrets.get("secret").await.unwrap(), Some(secret));
+        assert_eq!(
+            IntoApi::<Secret>::all(&client)
+                .list()
+                .await
+                .unwrap()
+                .items
+                .len(),
+            1
+        );
+        assert_eq!(
+            IntoApi::<Secret>::default_namespaced(&client)
+                .get("secret")
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(secrets.delete("secret").await.is_ok());
+        assert!(secrets.delete("secret").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_discovery_pass() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let configurations = parse_configurations(CONFIGURATION).unwrap();
+        let client = Arc::new(LocalKubeClient::default());
+        let (_device_notifier, dh_registry, mut config_notifier) =
+            discovery_handler_manager::new_registry(client.clone());
+        let dh_registry: Arc<dyn DiscoveryHandlerRegistry> = Arc::new(dh_registry);
+        let mut agent = LocalAgent::new(
+            "node-a".to_string(),
+            configurations,
+            client.clone(),
+            dh_registry.clone(),
+        )
+        .await
+        .unwrap();
+
+        // No Discovery Handler registered yet, the request gets retried on next pass
+        agent.discovery_pass().await;
+        assert!(dh_registry.get_request("config-a").await.is_none());
+
+        let (_close, closed) = tokio::sync::oneshot::channel::<()>();
+        let mut endpoint = MockDiscoveryHandlerEndpoint::new();
+        endpoint.expect_get_name().return_const("mock_handler");
+        endpoint.expect_get_uid().return_const("mock_handler_local");
+        endpoint.expect_closed().return_once(|| {
+            Box::pin(async {
+                let _ = closed.await;
+            })
+        });
+        endpoint.expect_is_closed().return_const(false);
+        let senders = Arc::new(Mutex::new(vec![]));
+        let local_senders = senders.clone();
+        endpoint.expect_query().returning(move |sender, _| {
+            local_senders.lock().unwrap().push(sender);
+            async { Ok(()) }.boxed()
+        });
+        dh_registry.register_endpoint(Arc::new(endpoint)).await;
+
+        agent.discovery_pass().await;
+        assert!(dh_registry.get_request("config-a").await.is_some());
+        assert!(client.instances().is_empty());
+        senders
+            .lock()
+            .unwrap()
+            .first()
+            .unwrap()
+            .send(vec![Arc::new(DiscoveredDevice::SharedDevice(Device {
+                id: "dev_1".to_owned(),
+                properties: Default::default(),
+                mounts: Default::default(),
+                device_specs: Default::default(),
+                last_warning: None,
+                error: None,
+            }))])
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), config_notifier.recv())
+            .await
+            .unwrap();
+        agent.discovery_pass().await;
+        let instances = client.instances();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].spec.configuration_name, "config-a");
+        assert_eq!(instances[0].spec.nodes, vec!["node-a".to_string()]);
+        assert_eq!(
+            instances[0].owner_references()[0].uid,
+            "local-default-config-a"
+        );
+
+        // The device going away gets its Instance deleted, the Instances cache being up to date
+        senders
+            .lock()
+            .unwrap()
+            .first()
+            .unwrap()
+            .send(vec![])
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), config_notifier.recv())
+            .await
+            .unwrap();
+        agent.discovery_pass().await;
+        assert!(client.instances().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_kube_client_as_inner() {
+        let client = LocalKubeClient::default();
+        // There is no API server to reach in local mode, requests fail instead of panicking
+        assert!(IntoApi::<Instance>::all(&client)
+            .as_inner()
+            .list(&Default::default())
+            .await
+            .is_err());
+    }
+}
@@ -1,16 +1,27 @@
 use lazy_static::lazy_static;
-use prometheus::{opts, register_int_counter_vec, HistogramVec, IntCounterVec, IntGaugeVec};
+use prometheus::{
+    opts, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge_vec, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, IntGaugeVec,
+};
 
 // Discovery request response time bucket (in seconds)
 const DISCOVERY_RESPONSE_TIME_BUCKETS: &[f64; 9] =
     &[0.25, 0.5, 1.0, 1.5, 2.0, 3.0, 5.0, 10.0, 60.0];
 
+// Device plugin slots lock wait time bucket (in seconds)
+const DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_BUCKETS: &[f64; 8] =
+    &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+// Device probe latency bucket (in seconds)
+const DEVICE_PROBE_LATENCY_BUCKETS: &[f64; 9] = &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
 lazy_static! {
-    // Reports the number of Instances visible to this node, grouped by Configuration and whether it is shared
+    // Reports the number of Instances visible to this node, grouped by Configuration namespace and name and whether it is shared
     pub static ref INSTANCE_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!(
         "akri_instance_count",
         "Akri Instance Count",
-        &["configuration", "is_shared"])
+        &["namespace", "configuration", "is_shared"])
         .expect("akri_instance_count metric can be created");
     // Reports the time to get discovery results, grouped by Configuration
     pub static ref DISCOVERY_RESPONSE_TIME_METRIC: HistogramVec = prometheus::register_histogram_vec!(
@@ -25,4 +36,55 @@ lazy_static! {
         opts!("akri_discovery_response_result", "Akri Discovery Response Result"),
         &["discovery_handler_name", "result"])
         .expect("akri_discovery_response_result metric can be created");
+    // Reports the time each discovery pass of a Discovery Handler took to get discovery results, grouped by Discovery Handler name
+    pub static ref DISCOVERY_DURATION_METRIC: HistogramVec = register_histogram_vec!(
+        "akri_discovery_duration_seconds",
+        "Akri Discovery Duration",
+        &["discovery_handler_name"],
+        DISCOVERY_RESPONSE_TIME_BUCKETS.to_vec()
+        )
+        .expect("akri_discovery_duration_seconds metric can be created");
+    // Reports the latency Discovery Handlers measured when probing the devices they discovered, grouped by Discovery Handler name
+    pub static ref DEVICE_PROBE_LATENCY_METRIC: HistogramVec = register_histogram_vec!(
+        "akri_device_probe_latency_seconds",
+        "Akri Device Probe Latency",
+        &["discovery_handler_name"],
+        DEVICE_PROBE_LATENCY_BUCKETS.to_vec()
+        )
+        .expect("akri_device_probe_latency_seconds metric can be created");
+    // Reports the number of devices currently discovered, grouped by Configuration
+    pub static ref DISCOVERY_DEVICES_FOUND_METRIC: IntGaugeVec = register_int_gauge_vec!(
+        "akri_discovery_devices_found",
+        "Akri Discovery Devices Found",
+        &["configuration"])
+        .expect("akri_discovery_devices_found metric can be created");
+    // Reports the time spent waiting to acquire the slots lock of an Instance device plugin
+    pub static ref DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_METRIC: Histogram = register_histogram!(
+        "akri_device_plugin_context_lock_wait_seconds",
+        "Akri Device Plugin Context Lock Wait Time",
+        DEVICE_PLUGIN_CONTEXT_LOCK_WAIT_BUCKETS.to_vec()
+        )
+        .expect("akri_device_plugin_context_lock_wait_seconds metric can be created");
+    // Reports the number of Discovery Handlers registered with this Agent, grouped by Discovery Handler name and endpoint kind (uds/network)
+    pub static ref REGISTERED_DISCOVERY_HANDLERS_METRIC: IntGaugeVec = register_int_gauge_vec!(
+        "akri_registered_discovery_handlers",
+        "Akri Registered Discovery Handlers",
+        &["discovery_handler_name", "endpoint_kind"])
+        .expect("akri_registered_discovery_handlers metric can be created");
+    // Reports the number of times a Discovery Handler registered again with an endpoint it had already registered
+    pub static ref DISCOVERY_HANDLER_REREGISTRATIONS_METRIC: IntCounter = register_int_counter!(
+        "akri_discovery_handler_reregistrations_total",
+        "Akri Discovery Handler Re-registrations")
+        .expect("akri_discovery_handler_reregistrations_total metric can be created");
+    // Reports the slots of the Instances that are reserved, grouped by Instance, slot and owning node (1 when reserved)
+    pub static ref INSTANCE_SLOT_RESERVED_METRIC: IntGaugeVec = register_int_gauge_vec!(
+        "akri_instance_slot_reserved",
+        "Akri Instance Slot Reserved",
+        &["instance", "slot", "node"])
+        .expect("akri_instance_slot_reserved metric can be created");
+    // Reports the attempts to register a device plugin with kubelet, grouped by result (success/failure)
+    pub static ref KUBELET_REGISTRATION_ATTEMPTS_METRIC: IntCounterVec = register_int_counter_vec!(
+        opts!("akri_kubelet_registration_attempts_total", "Akri Kubelet Registration Attempts"),
+        &["result"])
+        .expect("akri_kubelet_registration_attempts_total metric can be created");
 }
@@ -1,5 +1,9 @@
+pub mod debug_dump;
+pub mod devices_endpoint;
 pub mod discovery_configuration_controller;
+pub mod health;
+pub mod local_mode;
 
-mod metrics;
+pub(crate) mod metrics;
 
 pub mod stopper;
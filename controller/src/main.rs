@@ -2,18 +2,29 @@
 extern crate lazy_static;
 mod util;
 
-use akri_shared::akri::{metrics::run_metrics_server, API_NAMESPACE};
+use akri_shared::{
+    akri::{metrics::run_metrics_server, retry, API_NAMESPACE},
+    os::env_var::ActualEnvVarQuery,
+};
 use async_std::sync::Mutex;
-use prometheus::IntGaugeVec;
+use prometheus::{IntCounterVec, IntGaugeVec};
 use std::sync::Arc;
-use util::{instance_action, node_watcher, pod_watcher};
+use util::{instance_action, instance_cache, node_watcher, pod_watcher};
 
-/// Length of time to sleep between controller system validation checks
+/// Default length of time (in seconds) to sleep between controller system validation checks
 pub const SYSTEM_CHECK_DELAY_SECS: u64 = 30;
 
 lazy_static! {
     // Reports the number of Broker pods running, grouped by Configuration and Node
     pub static ref BROKER_POD_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_broker_pod_count", "Akri Broker Pod Count", &["configuration", "node"]).unwrap();
+    // Reports the number of times a Broker pod got stuck pulling its image, grouped by Configuration
+    pub static ref BROKER_POD_IMAGE_PULL_BACK_OFF_METRIC: IntCounterVec = prometheus::register_int_counter_vec!("akri_broker_pod_image_pull_back_off_count", "Akri Broker Pod Image Pull Back Off Count", &["configuration"]).unwrap();
+    // Reports the number of Instances each Node participates in (i.e. is listed in the `nodes` of), grouped by Node
+    pub static ref NODE_INSTANCE_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_node_instance_count", "Akri Node Instance Count", &["node"]).unwrap();
+    // Reports the number of broker Pods the Instances of a Configuration should have (one per Node of each Instance), grouped by namespace and Configuration
+    pub static ref EXPECTED_BROKER_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_expected_broker_count", "Akri Expected Broker Count", &["namespace", "configuration"]).unwrap();
+    // Reports the number of Running broker Pods of the Instances of a Configuration, grouped by namespace and Configuration
+    pub static ref ACTUAL_BROKER_COUNT_METRIC: IntGaugeVec = prometheus::register_int_gauge_vec!("akri_actual_broker_count", "Akri Actual Broker Count", &["namespace", "configuration"]).unwrap();
 }
 
 /// This is the entry point for the controller.
@@ -35,6 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
 
     let synchronization = Arc::new(Mutex::new(()));
     let instance_watch_synchronization = synchronization.clone();
+    let (instance_cache, instance_cache_writer) = kube_runtime::reflector::store();
     let mut tasks = Vec::new();
 
     // Start server for prometheus metrics
@@ -42,6 +54,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
         run_metrics_server().await.unwrap();
     }));
 
+    // Keep the Instance cache in sync
+    tasks.push(tokio::spawn({
+        async move {
+            instance_cache::run_instance_cache(instance_cache_writer)
+                .await
+                .unwrap();
+        }
+    }));
     // Handle existing instances
     tasks.push(tokio::spawn({
         async move {
@@ -59,14 +79,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
     // Watch for node disappearance
     tasks.push(tokio::spawn({
         async move {
-            let mut node_watcher = node_watcher::NodeWatcher::new();
+            let mut node_watcher = node_watcher::NodeWatcher::new()
+                .with_system_check_delay(node_watcher::get_system_check_delay(
+                    &ActualEnvVarQuery {},
+                ))
+                .with_max_instance_update_tries(retry::get_max_instance_update_tries(
+                    &ActualEnvVarQuery {},
+                ));
             node_watcher.watch().await.unwrap();
         }
     }));
     // Watch for broker Pod state changes
     tasks.push(tokio::spawn({
         async move {
-            let mut broker_pod_watcher = pod_watcher::BrokerPodWatcher::new();
+            let mut broker_pod_watcher =
+                pod_watcher::BrokerPodWatcher::with_instance_cache(instance_cache);
             broker_pod_watcher.watch().await.unwrap();
         }
     }));
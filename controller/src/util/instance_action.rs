@@ -1,27 +1,66 @@
-use super::super::BROKER_POD_COUNT_METRIC;
+use super::super::{
+    ACTUAL_BROKER_COUNT_METRIC, BROKER_POD_COUNT_METRIC, EXPECTED_BROKER_COUNT_METRIC,
+    NODE_INSTANCE_COUNT_METRIC,
+};
 use super::{pod_action::PodAction, pod_action::PodActionInfo};
 use akri_shared::{
-    akri::{configuration::BrokerSpec, instance::Instance, AKRI_PREFIX},
+    akri::{
+        configuration::BrokerSpec,
+        instance::{is_quarantined, Instance},
+        AKRI_PREFIX,
+    },
     k8s::{
-        self, job, pod,
-        pod::{AKRI_INSTANCE_LABEL_NAME, AKRI_TARGET_NODE_LABEL_NAME},
+        self, deployment, job, pod,
+        pod::{
+            AKRI_BROKER_SPEC_HASH_LABEL_NAME, AKRI_INSTANCE_LABEL_NAME, AKRI_TARGET_NODE_LABEL_NAME,
+        },
         KubeInterface, OwnershipInfo, OwnershipType,
     },
+    os::env_var::{get_env_bool, ActualEnvVarQuery, EnvVarQuery},
 };
 use async_std::sync::Mutex;
 use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::apps::v1::DeploymentSpec;
 use k8s_openapi::api::batch::v1::JobSpec;
 use k8s_openapi::api::core::v1::{Pod, PodSpec};
 use kube::api::Api;
 use kube_runtime::watcher::{watcher, Config, Event};
 use kube_runtime::WatchStreamExt;
 use log::{error, info, trace};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 /// Length of time a Pod can be pending before we give up and retry
 pub const PENDING_POD_GRACE_PERIOD_MINUTES: i64 = 5;
 /// Length of time a Pod can be in an error state before we retry
 pub const FAILED_POD_GRACE_PERIOD_MINUTES: i64 = 0;
+/// Environment variable name for setting the grace period (in seconds) given to broker Pods
+/// to drain before being deleted on Instance removal
+pub const BROKER_DRAIN_GRACE_PERIOD_SECS_LABEL: &str = "BROKER_DRAIN_GRACE_PERIOD_SECS";
+/// Environment variable name for setting whether broker Pods get recreated when the broker PodSpec
+/// of their Configuration changes
+pub const ROLL_BROKERS_ON_SPEC_CHANGE_LABEL: &str = "ROLL_BROKERS_ON_SPEC_CHANGE";
+
+/// Get the grace period broker Pods are given to release their device on Instance removal, if set
+fn get_broker_drain_grace_period(env_var_query: &impl EnvVarQuery) -> Option<u32> {
+    let value = env_var_query
+        .get_env_var(BROKER_DRAIN_GRACE_PERIOD_SECS_LABEL)
+        .ok()?;
+    match value.parse::<u32>() {
+        Ok(grace_period) => Some(grace_period),
+        Err(e) => {
+            error!(
+                "get_broker_drain_grace_period - invalid {} value {:?}: {}",
+                BROKER_DRAIN_GRACE_PERIOD_SECS_LABEL, value, e
+            );
+            None
+        }
+    }
+}
+
+/// Get whether broker Pods created from an outdated PodSpec should be recreated, defaults to true
+fn get_roll_brokers_on_spec_change(env_var_query: &impl EnvVarQuery) -> bool {
+    get_env_bool(env_var_query, ROLL_BROKERS_ON_SPEC_CHANGE_LABEL, true)
+}
 
 /// Instance action types
 ///
@@ -33,14 +72,17 @@ pub const FAILED_POD_GRACE_PERIOD_MINUTES: i64 = 0;
 ///   | --> InstanceAction::Add
 ///                 | --> No broker => Do nothing
 ///                 | --> <BrokerSpec::BrokerJobSpec> => Deploy a Job
+///                 | --> <BrokerSpec::BrokerDeploymentSpec> => Deploy a Deployment
 ///                 | --> <BrokerSpec::BrokerPodSpec> => Deploy Pod to each Node on Instance's `nodes` list (up to `capacity` total)
 ///   | --> InstanceAction::Remove
 ///                 | --> No broker => Do nothing
 ///                 | --> <BrokerSpec::BrokerJobSpec> => Delete all Jobs labeled with the Instance name
+///                 | --> <BrokerSpec::BrokerDeploymentSpec> => Delete all Deployments labeled with the Instance name
 ///                 | --> <BrokerSpec::BrokerPodSpec> => Delete all Pods labeled with the Instance name
 ///   | --> InstanceAction::Update
 ///                 | --> No broker => Do nothing
 ///                 | --> <BrokerSpec::BrokerJobSpec> => No nothing
+///                 | --> <BrokerSpec::BrokerDeploymentSpec> => Apply DeploymentSpec to the Deployment
 ///                 | --> <BrokerSpec::BrokerPodSpec> => Ensure that each Node on Instance's `nodes` list (up to `capacity` total) have a Pod
 ///
 #[derive(Clone, Debug, PartialEq)]
@@ -96,6 +138,7 @@ async fn internal_do_instance_watch(
     let watcher = watcher(resource, Config::default()).default_backoff();
     let mut informer = watcher.boxed();
     let mut first_event = true;
+    let mut node_instance_counts = NodeInstanceCounts::default();
     // Currently, this does not handle None except to break the loop.
     loop {
         let event = match informer.try_next().await {
@@ -111,20 +154,193 @@ async fn internal_do_instance_watch(
         // cannot execute at the same time.
         let _lock = synchronization.lock().await;
         trace!("internal_do_instance_watch - aquired sync lock");
-        handle_instance(event, kube_interface, &mut first_event).await?;
+        handle_instance(
+            event,
+            kube_interface,
+            &mut first_event,
+            &mut node_instance_counts,
+        )
+        .await?;
     }
     Ok(())
 }
 
+/// Tracks the Nodes of every Instance, to report how many Instances each Node participates in
+/// through the NODE_INSTANCE_COUNT_METRIC.
+#[derive(Default)]
+pub(crate) struct NodeInstanceCounts {
+    /// Nodes of each Instance, keyed by namespace and name
+    instance_nodes: HashMap<(String, String), Vec<String>>,
+    /// Nodes currently reported by the metric
+    reported_nodes: HashSet<String>,
+}
+
+impl NodeInstanceCounts {
+    fn key(instance: &Instance) -> (String, String) {
+        (
+            instance.metadata.namespace.clone().unwrap_or_default(),
+            instance.metadata.name.clone().unwrap_or_default(),
+        )
+    }
+
+    /// Records the current Nodes of an Instance
+    pub(crate) fn apply(&mut self, instance: &Instance) {
+        self.instance_nodes
+            .insert(Self::key(instance), instance.spec.nodes.clone());
+        self.report();
+    }
+
+    /// Forgets an Instance that is deleted or awaiting deletion
+    pub(crate) fn remove(&mut self, instance: &Instance) {
+        self.instance_nodes.remove(&Self::key(instance));
+        self.report();
+    }
+
+    /// Replaces the tracked Instances, e.g. when the watcher (re)starts with the full list of Instances
+    pub(crate) fn reset(&mut self, instances: &[Instance]) {
+        self.instance_nodes = instances
+            .iter()
+            .map(|instance| (Self::key(instance), instance.spec.nodes.clone()))
+            .collect();
+        self.report();
+    }
+
+    /// Number of Instances each Node participates in, Nodes without Instance are omitted
+    fn counts(&self) -> HashMap<String, i64> {
+        let mut counts = HashMap::new();
+        for nodes in self.instance_nodes.values() {
+            // A Node listed twice in an Instance still only participates in it once
+            for node in nodes.iter().collect::<HashSet<&String>>() {
+                *counts.entry(node.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn report(&mut self) {
+        let counts = self.counts();
+        for node in self.reported_nodes.iter() {
+            if !counts.contains_key(node) {
+                // The Node no longer participates in any Instance, stop reporting it
+                let _ = NODE_INSTANCE_COUNT_METRIC.remove_label_values(&[node.as_str()]);
+            }
+        }
+        for (node, count) in counts.iter() {
+            NODE_INSTANCE_COUNT_METRIC
+                .with_label_values(&[node.as_str()])
+                .set(*count);
+        }
+        self.reported_nodes = counts.into_keys().collect();
+    }
+}
+
+/// Expected and actual broker Pod counts of an Instance, as found during its last reconcile. The actual
+/// count is then kept up to date by the broker Pod watcher as broker Pods start or stop running.
+#[derive(Clone, Debug, PartialEq)]
+struct InstanceBrokerCounts {
+    configuration: String,
+    expected: i64,
+    actual: i64,
+}
+
+lazy_static! {
+    // Broker Pod counts of every Instance with Pod brokers, keyed by namespace and name
+    static ref INSTANCE_BROKER_COUNTS: std::sync::Mutex<HashMap<(String, String), InstanceBrokerCounts>> =
+        Default::default();
+}
+
+/// Records the expected and actual broker Pod counts of an Instance, `None` forgetting a removed Instance, and
+/// reports the totals of its Configuration through the EXPECTED_BROKER_COUNT_METRIC and ACTUAL_BROKER_COUNT_METRIC.
+/// A lasting gap between the two hints at brokers failing to start.
+fn report_broker_counts(instance: &Instance, counts: Option<(i64, i64)>) {
+    let key = NodeInstanceCounts::key(instance);
+    let configuration = &instance.spec.configuration_name;
+    let mut instance_broker_counts = INSTANCE_BROKER_COUNTS.lock().unwrap();
+    match counts {
+        Some((expected, actual)) => {
+            instance_broker_counts.insert(
+                key.clone(),
+                InstanceBrokerCounts {
+                    configuration: configuration.clone(),
+                    expected,
+                    actual,
+                },
+            );
+        }
+        None => {
+            instance_broker_counts.remove(&key);
+        }
+    }
+    report_configuration_broker_counts(&instance_broker_counts, &key.0, configuration);
+}
+
+/// Updates the actual broker Pod count of an Instance as its broker Pods start or stop running, which doesn't
+/// trigger a reconcile of the Instance. Instances that were not reconciled yet are left to their reconcile.
+pub(crate) fn report_running_brokers(namespace: &str, instance_name: &str, running: i64) {
+    let mut instance_broker_counts = INSTANCE_BROKER_COUNTS.lock().unwrap();
+    let Some(counts) =
+        instance_broker_counts.get_mut(&(namespace.to_string(), instance_name.to_string()))
+    else {
+        return;
+    };
+    counts.actual = running;
+    let configuration = counts.configuration.clone();
+    report_configuration_broker_counts(&instance_broker_counts, namespace, &configuration);
+}
+
+fn report_configuration_broker_counts(
+    instance_broker_counts: &HashMap<(String, String), InstanceBrokerCounts>,
+    namespace: &str,
+    configuration: &str,
+) {
+    let configuration_counts = instance_broker_counts
+        .iter()
+        .filter(|((instance_namespace, _), counts)| {
+            instance_namespace == namespace && counts.configuration == configuration
+        })
+        .map(|(_, counts)| counts)
+        .collect::<Vec<_>>();
+    let labels = [namespace, configuration];
+    if configuration_counts.is_empty() {
+        // The Configuration has no Instance with Pod brokers left, stop reporting it
+        let _ = EXPECTED_BROKER_COUNT_METRIC.remove_label_values(&labels);
+        let _ = ACTUAL_BROKER_COUNT_METRIC.remove_label_values(&labels);
+        return;
+    }
+    EXPECTED_BROKER_COUNT_METRIC.with_label_values(&labels).set(
+        configuration_counts
+            .iter()
+            .map(|counts| counts.expected)
+            .sum(),
+    );
+    ACTUAL_BROKER_COUNT_METRIC.with_label_values(&labels).set(
+        configuration_counts
+            .iter()
+            .map(|counts| counts.actual)
+            .sum(),
+    );
+}
+
 /// This takes an event off the Instance stream and delegates it to the
 /// correct function based on the event type.
 async fn handle_instance(
     event: Event<Instance>,
     kube_interface: &impl KubeInterface,
     first_event: &mut bool,
+    node_instance_counts: &mut NodeInstanceCounts,
 ) -> anyhow::Result<()> {
     trace!("handle_instance - enter");
     match event {
+        Event::Applied(instance) if instance.metadata.deletion_timestamp.is_some() => {
+            info!(
+                "handle_instance - Akri Instance {:?} awaits deletion",
+                instance.metadata.name
+            );
+            node_instance_counts.remove(&instance);
+            // Remove the brokers as soon as the Instance is marked for deletion, so that they
+            // are gone by the time the Agent releases the Instance's finalizer
+            handle_instance_change(&instance, &InstanceAction::Remove, kube_interface).await?;
+        }
         Event::Applied(instance) => {
             info!(
                 "handle_instance - added or modified Akri Instance {:?}: {:?}",
@@ -133,6 +349,7 @@ async fn handle_instance(
             // TODO: consider renaming `InstanceAction::Add` to `InstanceAction::AddOrUpdate`
             // to reflect that this could also be an Update event. Or as we do more specific
             // inspection in future, delineation may be useful.
+            node_instance_counts.apply(&instance);
             handle_instance_change(&instance, &InstanceAction::Add, kube_interface).await?;
         }
         Event::Deleted(instance) => {
@@ -140,11 +357,13 @@ async fn handle_instance(
                 "handle_instance - deleted Akri Instance {:?}: {:?}",
                 instance.metadata.name, instance.spec
             );
+            node_instance_counts.remove(&instance);
             handle_instance_change(&instance, &InstanceAction::Remove, kube_interface).await?;
         }
-        Event::Restarted(_instances) => {
+        Event::Restarted(instances) => {
             if *first_event {
                 info!("handle_instance - watcher started");
+                node_instance_counts.reset(&instances);
             } else {
                 return Err(anyhow::anyhow!(
                     "Instance watcher restarted - throwing error to restart controller"
@@ -199,10 +418,14 @@ pub(crate) fn create_pod_context(k8s_pod: &Pod, action: PodAction) -> anyhow::Re
 
 /// This finds what to do with a given broker Pod based on its current state and
 /// the Instance event action.  If this method has enough information,
-/// it will update the nodes_to_act_on map with the required action.
+/// it will update the nodes_to_act_on map with the required action. When given the
+/// hash of the current broker PodSpec, a Pod that would otherwise be kept is recreated
+/// if it was created from another PodSpec.
 fn determine_action_for_pod(
     k8s_pod: &Pod,
     action: &InstanceAction,
+    spec_hash: Option<&str>,
+    rolled: &mut bool,
     nodes_to_act_on: &mut HashMap<String, PodContext>,
 ) -> anyhow::Result<()> {
     let pod_name = k8s_pod.metadata.name.as_ref().unwrap();
@@ -230,6 +453,25 @@ fn determine_action_for_pod(
         trace_node_name: k8s_pod.metadata.name.clone().unwrap(),
     };
     update_pod_context.action = pod_action_info.select_pod_action()?;
+    // Pods created before their spec hash was tracked are left alone, and at most one outdated Pod
+    // is recreated per reconcile
+    let pod_spec_hash = k8s_pod
+        .metadata
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(AKRI_BROKER_SPEC_HASH_LABEL_NAME));
+    if let (PodAction::NoAction, Some(current), Some(previous)) =
+        (update_pod_context.action, spec_hash, pod_spec_hash)
+    {
+        if current != previous && !*rolled {
+            info!(
+                "determine_action_for_pod - broker spec of Pod {} changed, recreating it",
+                pod_name
+            );
+            update_pod_context.action = PodAction::RemoveAndAdd;
+            *rolled = true;
+        }
+    }
     nodes_to_act_on.insert(node_to_run_pod_on.to_string(), update_pod_context);
     Ok(())
 }
@@ -243,6 +485,7 @@ async fn handle_deletion_work(
     instance_shared: bool,
     node_to_delete_pod: &str,
     context: &PodContext,
+    drain_grace_period: Option<u32>,
     kube_interface: &impl KubeInterface,
 ) -> anyhow::Result<()> {
     let context_node_name = context.node_name.as_ref().ok_or_else(|| {
@@ -274,13 +517,23 @@ async fn handle_deletion_work(
         "pod",
     );
     trace!(
-        "handle_deletion_work - pod::remove_pod name={:?}, namespace={:?}",
+        "handle_deletion_work - pod::remove_pod name={:?}, namespace={:?}, grace period={:?}",
         &pod_app_name,
-        &context_namespace
+        &context_namespace,
+        drain_grace_period
     );
-    kube_interface
-        .remove_pod(&pod_app_name, context_namespace)
-        .await?;
+    match drain_grace_period {
+        Some(grace_period) => {
+            kube_interface
+                .remove_pod_with_grace_period(&pod_app_name, context_namespace, grace_period)
+                .await?
+        }
+        None => {
+            kube_interface
+                .remove_pod(&pod_app_name, context_namespace)
+                .await?
+        }
+    }
     trace!("handle_deletion_work - pod::remove_pod succeeded",);
     BROKER_POD_COUNT_METRIC
         .with_label_values(&[configuration_name, context_node_name])
@@ -291,7 +544,7 @@ async fn handle_deletion_work(
 #[cfg(test)]
 mod handle_deletion_work_tests {
     use super::*;
-    use akri_shared::k8s::MockKubeInterface;
+    use akri_shared::{k8s::MockKubeInterface, os::env_var::MockEnvVarQuery};
 
     #[tokio::test]
     async fn test_handle_deletion_work_with_no_node_name() {
@@ -309,6 +562,7 @@ mod handle_deletion_work_tests {
             true,
             "node_to_delete_pod",
             &context,
+            None,
             &MockKubeInterface::new(),
         )
         .await
@@ -331,11 +585,115 @@ mod handle_deletion_work_tests {
             true,
             "node_to_delete_pod",
             &context,
+            None,
             &MockKubeInterface::new(),
         )
         .await
         .is_err());
     }
+
+    #[tokio::test]
+    async fn test_handle_deletion_work_honors_drain_grace_period() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let context = PodContext {
+            node_name: Some("node-a".into()),
+            namespace: Some("namespace".into()),
+            action: PodAction::Remove,
+        };
+        let mut mock = MockKubeInterface::new();
+        mock.expect_remove_pod().never();
+        mock.expect_remove_pod_with_grace_period()
+            .times(1)
+            .withf(|pod_to_remove, namespace, grace_period_seconds| {
+                pod_to_remove == "instance_name-pod"
+                    && namespace == "namespace"
+                    && *grace_period_seconds == 30
+            })
+            .returning(|_, _, _| Ok(()));
+
+        assert!(handle_deletion_work(
+            "instance_name",
+            "configuration_name",
+            false,
+            "node-a",
+            &context,
+            Some(30),
+            &mock,
+        )
+        .await
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handle_deletion_work_without_drain_grace_period() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let context = PodContext {
+            node_name: Some("node-a".into()),
+            namespace: Some("namespace".into()),
+            action: PodAction::Remove,
+        };
+        let mut mock = MockKubeInterface::new();
+        mock.expect_remove_pod_with_grace_period().never();
+        mock.expect_remove_pod().times(1).returning(|_, _| Ok(()));
+
+        assert!(handle_deletion_work(
+            "instance_name",
+            "configuration_name",
+            false,
+            "node-a",
+            &context,
+            None,
+            &mock,
+        )
+        .await
+        .is_ok());
+    }
+
+    #[test]
+    fn test_get_broker_drain_grace_period() {
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .with(mockall::predicate::eq(BROKER_DRAIN_GRACE_PERIOD_SECS_LABEL))
+            .returning(|_| Ok("30".to_string()));
+        assert_eq!(get_broker_drain_grace_period(&mock_query), Some(30));
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Ok("not-a-number".to_string()));
+        assert_eq!(get_broker_drain_grace_period(&mock_query), None);
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_broker_drain_grace_period(&mock_query), None);
+    }
+
+    #[test]
+    fn test_get_roll_brokers_on_spec_change() {
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .with(mockall::predicate::eq(ROLL_BROKERS_ON_SPEC_CHANGE_LABEL))
+            .returning(|_| Ok("false".to_string()));
+        assert!(!get_roll_brokers_on_spec_change(&mock_query));
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Ok("not-a-bool".to_string()));
+        assert!(get_roll_brokers_on_spec_change(&mock_query));
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert!(get_roll_brokers_on_spec_change(&mock_query));
+    }
 }
 
 /// This handles Instance addition event by creating the
@@ -351,6 +709,7 @@ async fn handle_addition_work(
     instance_shared: bool,
     new_node: &str,
     podspec: &PodSpec,
+    broker_spec_hash: Option<&str>,
     kube_interface: &impl KubeInterface,
 ) -> anyhow::Result<()> {
     trace!(
@@ -358,7 +717,7 @@ async fn handle_addition_work(
         new_node
     );
     let capability_id = format!("{}/{}", AKRI_PREFIX, instance_name);
-    let new_pod = pod::create_new_pod_from_spec(
+    let mut new_pod = pod::create_new_pod_from_spec(
         instance_namespace,
         instance_name,
         instance_class_name,
@@ -372,6 +731,16 @@ async fn handle_addition_work(
         instance_shared,
         podspec,
     )?;
+    if let Some(broker_spec_hash) = broker_spec_hash {
+        new_pod
+            .metadata
+            .labels
+            .get_or_insert_with(Default::default)
+            .insert(
+                AKRI_BROKER_SPEC_HASH_LABEL_NAME.to_string(),
+                broker_spec_hash.to_string(),
+            );
+    }
 
     trace!("handle_addition_work - New pod spec={:?}", new_pod);
 
@@ -396,6 +765,16 @@ pub async fn handle_instance_change(
 ) -> anyhow::Result<()> {
     trace!("handle_instance_change - enter {:?}", action);
     let instance_name = instance.metadata.name.clone().unwrap();
+    // No broker runs for a quarantined Instance, the brokers of a newly quarantined one get removed
+    let action = if *action != InstanceAction::Remove && is_quarantined(instance) {
+        info!(
+            "handle_instance_change - Instance {} is quarantined, removing its brokers",
+            instance_name
+        );
+        &InstanceAction::Remove
+    } else {
+        action
+    };
     let instance_namespace =
         instance.metadata.namespace.as_ref().ok_or_else(|| {
             anyhow::anyhow!("Namespace not found for instance: {}", &instance_name)
@@ -406,7 +785,21 @@ pub async fn handle_instance_change(
     {
         Ok(config) => config,
         _ => {
-            if action != &InstanceAction::Remove {
+            if action == &InstanceAction::Remove {
+                // The Configuration may already be gone when its Instances get deleted (e.g. on
+                // uninstall), broker Pods are found by their Instance label so still remove them
+                if let Err(e) = handle_instance_change_pod(
+                    instance,
+                    &PodSpec::default(),
+                    None,
+                    action,
+                    kube_interface,
+                )
+                .await
+                {
+                    error!("Unable to remove broker Pods: {:?}", e);
+                }
+            } else {
                 // In this scenario, a configuration has been deleted without the Akri Agent deleting the associated Instances.
                 // Furthermore, Akri Agent is still modifying the Instances. This should not happen beacuse Agent
                 // is designed to shutdown when it's Configuration watcher fails.
@@ -419,21 +812,92 @@ pub async fn handle_instance_change(
         }
     };
     if let Some(broker_spec) = &configuration.spec.broker_spec {
-        let instance_change_result = match broker_spec {
-            BrokerSpec::BrokerPodSpec(p) => {
-                handle_instance_change_pod(instance, p, action, kube_interface).await
-            }
-            BrokerSpec::BrokerJobSpec(j) => {
-                handle_instance_change_job(
-                    instance,
-                    *configuration.metadata.generation.as_ref().unwrap(),
-                    j,
-                    action,
-                    kube_interface,
-                )
-                .await
-            }
+        let scheduler_name = configuration.spec.broker_scheduler_name.as_deref();
+        let image_pull_secrets = configuration
+            .spec
+            .broker_image_pull_secrets
+            .as_deref()
+            .unwrap_or_default();
+        let add_readiness_probe = |podspec: &mut PodSpec| match action {
+            // Brokers are removed regardless of their readiness probe
+            InstanceAction::Remove => Ok(()),
+            _ => pod::add_broker_readiness_probe(
+                podspec,
+                configuration.spec.broker_readiness_probe.as_ref(),
+                &instance.spec.broker_properties,
+            ),
         };
+        let instance_change_result: anyhow::Result<()> = async {
+            match broker_spec {
+                BrokerSpec::BrokerPodSpec(p) => {
+                    let mut podspec = p.as_ref().clone();
+                    pod::add_node_affinity_preferences(
+                        &mut podspec,
+                        configuration
+                            .spec
+                            .node_affinity_preferences
+                            .as_deref()
+                            .unwrap_or_default(),
+                    );
+                    pod::set_scheduler_name(&mut podspec, scheduler_name)?;
+                    pod::add_image_pull_secrets(&mut podspec, image_pull_secrets)?;
+                    pod::resolve_instance_placeholders(&mut podspec, instance.spec.capacity);
+                    add_readiness_probe(&mut podspec)?;
+                    // The effective spec is hashed, so that changing what the controller injects
+                    // in it (e.g. brokerSchedulerName or brokerImagePullSecrets) rolls the brokers
+                    let broker_spec_hash = pod::broker_spec_hash(&podspec);
+                    handle_instance_change_pod(
+                        instance,
+                        &podspec,
+                        Some(&broker_spec_hash),
+                        action,
+                        kube_interface,
+                    )
+                    .await
+                }
+                BrokerSpec::BrokerJobSpec(j) => {
+                    let mut jobspec = j.as_ref().clone();
+                    if let Some(podspec) = jobspec.template.spec.as_mut() {
+                        pod::set_scheduler_name(podspec, scheduler_name)?;
+                        pod::add_image_pull_secrets(podspec, image_pull_secrets)?;
+                        add_readiness_probe(podspec)?;
+                    }
+                    handle_instance_change_job(
+                        instance,
+                        *configuration.metadata.generation.as_ref().unwrap(),
+                        &jobspec,
+                        action,
+                        kube_interface,
+                    )
+                    .await
+                }
+                BrokerSpec::BrokerDeploymentSpec(d) => {
+                    let mut deploymentspec = d.as_ref().clone();
+                    if let Some(podspec) = deploymentspec.template.spec.as_mut() {
+                        pod::add_node_affinity_preferences(
+                            podspec,
+                            configuration
+                                .spec
+                                .node_affinity_preferences
+                                .as_deref()
+                                .unwrap_or_default(),
+                        );
+                        pod::set_scheduler_name(podspec, scheduler_name)?;
+                        pod::add_image_pull_secrets(podspec, image_pull_secrets)?;
+                        pod::resolve_instance_placeholders(podspec, instance.spec.capacity);
+                        add_readiness_probe(podspec)?;
+                    }
+                    handle_instance_change_deployment(
+                        instance,
+                        &deploymentspec,
+                        action,
+                        kube_interface,
+                    )
+                    .await
+                }
+            }
+        }
+        .await;
         if let Err(e) = instance_change_result {
             error!("Unable to handle Broker action: {:?}", e);
         }
@@ -513,6 +977,74 @@ pub async fn handle_instance_change_job(
     Ok(())
 }
 
+/// Called when an Instance has changed that requires a Deployment broker. Action determined by InstanceAction.
+/// InstanceAction::Add =>  Deploy a Deployment with DeploymentSpec from Configuration. Label with Instance name.
+/// InstanceAction::Remove => Delete all Deployments labeled with the Instance name
+/// InstanceAction::Update => Apply the DeploymentSpec from Configuration to the existing Deployment
+pub async fn handle_instance_change_deployment(
+    instance: &Instance,
+    deployment_spec: &DeploymentSpec,
+    action: &InstanceAction,
+    kube_interface: &impl KubeInterface,
+) -> anyhow::Result<()> {
+    trace!("handle_instance_change_deployment - enter {:?}", action);
+    let instance_name = instance.metadata.name.as_ref().unwrap();
+    let instance_namespace = instance.metadata.namespace.as_ref().unwrap();
+    match action {
+        // Applying the Deployment on updates too reconciles the replicas, brokerSpec and
+        // capacity of an existing Deployment with its Configuration and Instance
+        InstanceAction::Add | InstanceAction::Update => {
+            trace!("handle_instance_change_deployment - instance added or updated");
+            let instance_uid =
+                instance.metadata.uid.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!("UID not found for instance: {}", &instance_name)
+                })?;
+            let deployment_name = pod::create_broker_app_name(
+                instance_name,
+                None,
+                instance.spec.shared,
+                "deployment",
+            );
+            let capability_id = format!("{}/{}", AKRI_PREFIX, instance_name);
+            let new_deployment = deployment::create_new_deployment_from_spec(
+                instance,
+                OwnershipInfo::new(
+                    OwnershipType::Instance,
+                    instance_name.to_string(),
+                    instance_uid.to_string(),
+                ),
+                &capability_id,
+                deployment_spec,
+                &deployment_name,
+            )?;
+            kube_interface
+                .apply_deployment(&new_deployment, instance_namespace)
+                .await?;
+        }
+        InstanceAction::Remove => {
+            trace!("handle_instance_change_deployment - instance removed");
+            // Find all deployments with the label
+            let instance_deployments = kube_interface
+                .find_deployments_with_label(&format!(
+                    "{}={}",
+                    AKRI_INSTANCE_LABEL_NAME, instance_name
+                ))
+                .await?;
+            let delete_tasks = instance_deployments.into_iter().map(|d| async move {
+                kube_interface
+                    .remove_deployment(
+                        d.metadata.name.as_ref().unwrap(),
+                        d.metadata.namespace.as_ref().unwrap(),
+                    )
+                    .await
+            });
+
+            futures::future::try_join_all(delete_tasks).await?;
+        }
+    }
+    Ok(())
+}
+
 /// Called when an Instance has changed that requires a Pod broker.
 /// Action determined by InstanceAction and changes to the Instance's `nodes` list.
 /// Starts broker Pods that are missing and stops Pods that are no longer needed.
@@ -522,6 +1054,7 @@ pub async fn handle_instance_change_job(
 pub async fn handle_instance_change_pod(
     instance: &Instance,
     podspec: &PodSpec,
+    broker_spec_hash: Option<&str>,
     action: &InstanceAction,
     kube_interface: &impl KubeInterface,
 ) -> anyhow::Result<()> {
@@ -554,6 +1087,10 @@ pub async fn handle_instance_change_pod(
         "handle_instance_change - nodes tracked from instance={:?}",
         nodes_to_act_on
     );
+    let expected_brokers = match action {
+        InstanceAction::Remove => None,
+        _ => Some(nodes_to_act_on.len() as i64),
+    };
 
     trace!(
         "handle_instance_change - find all pods that have {}={}",
@@ -567,21 +1104,56 @@ pub async fn handle_instance_change_pod(
         "handle_instance_change - found {} pods",
         instance_pods.items.len()
     );
+    let running_brokers = instance_pods
+        .items
+        .iter()
+        .filter(|pod| pod.metadata.namespace == instance.metadata.namespace)
+        .filter(|pod| {
+            pod.status
+                .as_ref()
+                .and_then(|status| status.phase.as_deref())
+                == Some("Running")
+        })
+        .count() as i64;
+    report_broker_counts(
+        instance,
+        expected_brokers.map(|expected| (expected, running_brokers)),
+    );
 
     trace!("handle_instance_change - update actions based on the existing pods");
+    // Running brokers created from an outdated PodSpec get recreated one at a time, without touching
+    // the Instance, the deletion of the recreated broker triggers the reconcile rolling the next one
+    let spec_hash = match action {
+        InstanceAction::Remove => None,
+        _ if !get_roll_brokers_on_spec_change(&ActualEnvVarQuery {}) => None,
+        _ => broker_spec_hash,
+    };
+    let mut rolled = false;
     // By default, assume any pod tracked by the instance need to be added.
     // Query the existing pods to see if some of these are already added, or
     // need to be removed
-    instance_pods
-        .items
-        .iter()
-        .try_for_each(|x| determine_action_for_pod(x, action, &mut nodes_to_act_on))?;
+    instance_pods.items.iter().try_for_each(|x| {
+        determine_action_for_pod(x, action, spec_hash, &mut rolled, &mut nodes_to_act_on)
+    })?;
 
     trace!(
         "handle_instance_change - nodes tracked after querying existing pods={:?}",
         nodes_to_act_on
     );
-    do_pod_action_for_nodes(nodes_to_act_on, instance, podspec, kube_interface).await?;
+    // Brokers are only given time to drain when their Instance goes away
+    let drain_grace_period = match action {
+        InstanceAction::Remove => get_broker_drain_grace_period(&ActualEnvVarQuery {}),
+        _ => None,
+    };
+    do_pod_action_for_nodes(
+        nodes_to_act_on,
+        instance,
+        podspec,
+        broker_spec_hash,
+        drain_grace_period,
+        kube_interface,
+    )
+    .await?;
     trace!("handle_instance_change - exit");
 
     Ok(())
@@ -591,6 +1163,8 @@ pub(crate) async fn do_pod_action_for_nodes(
     nodes_to_act_on: HashMap<String, PodContext>,
     instance: &Instance,
     podspec: &PodSpec,
+    broker_spec_hash: Option<&str>,
+    drain_grace_period: Option<u32>,
     kube_interface: &impl KubeInterface,
 ) -> anyhow::Result<()> {
     trace!("do_pod_action_for_nodes - enter");
@@ -604,6 +1178,7 @@ pub(crate) async fn do_pod_action_for_nodes(
             instance.spec.shared,
             node_to_delete_pod,
             context,
+            drain_grace_period,
             kube_interface,
         )
         .await?
@@ -631,6 +1206,7 @@ pub(crate) async fn do_pod_action_for_nodes(
             instance.spec.shared,
             &new_node,
             podspec,
+            broker_spec_hash,
             kube_interface,
         )
         .await?;
@@ -644,12 +1220,19 @@ mod handle_instance_tests {
     use super::super::shared_test_utils::config_for_tests::PodList;
     use super::*;
     use akri_shared::{
-        akri::instance::Instance,
-        k8s::{pod::AKRI_INSTANCE_LABEL_NAME, MockKubeInterface},
+        akri::{
+            configuration::{
+                BrokerReadinessProbe, BrokerReadinessProbeType, Configuration,
+                NodeAffinityPreference,
+            },
+            instance::Instance,
+        },
+        k8s::{pod::AKRI_INSTANCE_LABEL_NAME, MockKubeInterface, OBJECT_NAME_FIELD},
         os::file,
     };
     use chrono::prelude::*;
     use chrono::Utc;
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
     use mockall::predicate::*;
 
     fn configure_find_pods_with_phase(
@@ -898,12 +1481,112 @@ mod handle_instance_tests {
             },
             mock,
             &mut false,
+            &mut Default::default(),
         )
         .await
         .unwrap();
         trace!("run_handle_instance_change_test exit");
     }
 
+    #[tokio::test]
+    async fn test_handle_instance_change_for_remove_instance_without_configuration() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_config(
+            &mut mock,
+            "config-a",
+            "config-a-namespace",
+            "",
+            true,
+        );
+        config_for_tests::configure_find_pods(
+            &mut mock,
+            "akri.sh/instance=config-a-b494b6",
+            "../test/json/running-pod-list-for-config-a-local.json",
+            false,
+        );
+        configure_for_handle_deletion_work(
+            &mut mock,
+            &configure_deletion_work_for_config_a_b494b6(),
+        );
+        run_handle_instance_change_test(
+            &mut mock,
+            "../test/json/local-instance.json",
+            &InstanceAction::Remove,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_awaiting_deletion() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_instance_change(
+            &mut mock,
+            &HandleInstanceWork {
+                find_pods_selector: "akri.sh/instance=config-a-b494b6",
+                find_pods_result: "../test/json/running-pod-list-for-config-a-local.json",
+                find_pods_phase: None,
+                find_pods_start_time: None,
+                find_pods_delete_start_time: false,
+                config_work: get_config_work(),
+                deletion_work: Some(configure_deletion_work_for_config_a_b494b6()),
+                addition_work: None,
+            },
+        );
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        instance.metadata.deletion_timestamp = Some(
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(k8s_openapi::chrono::Utc::now()),
+        );
+        handle_instance(
+            Event::Applied(instance),
+            &mut mock,
+            &mut false,
+            &mut Default::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_quarantined() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // The brokers of a quarantined Instance are removed and none gets added
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_instance_change(
+            &mut mock,
+            &HandleInstanceWork {
+                find_pods_selector: "akri.sh/instance=config-a-b494b6",
+                find_pods_result: "../test/json/running-pod-list-for-config-a-local.json",
+                find_pods_phase: None,
+                find_pods_start_time: None,
+                find_pods_delete_start_time: false,
+                config_work: get_config_work(),
+                deletion_work: Some(configure_deletion_work_for_config_a_b494b6()),
+                addition_work: None,
+            },
+        );
+        mock.expect_create_pod().never();
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        instance.metadata.annotations = Some(std::collections::BTreeMap::from([(
+            akri_shared::akri::AKRI_QUARANTINED_ANNOTATION_NAME.to_string(),
+            "broker crashed".to_string(),
+        )]));
+        handle_instance(
+            Event::Applied(instance),
+            &mut mock,
+            &mut false,
+            &mut Default::default(),
+        )
+        .await
+        .unwrap();
+    }
+
     // Test that watcher errors on restarts unless it is the first restart (aka initial startup)
     #[tokio::test]
     async fn test_handle_watcher_restart() {
@@ -912,7 +1595,8 @@ mod handle_instance_tests {
         assert!(handle_instance(
             Event::Restarted(Vec::new()),
             &MockKubeInterface::new(),
-            &mut first_event
+            &mut first_event,
+            &mut Default::default(),
         )
         .await
         .is_ok());
@@ -920,12 +1604,127 @@ mod handle_instance_tests {
         assert!(handle_instance(
             Event::Restarted(Vec::new()),
             &MockKubeInterface::new(),
-            &mut first_event
+            &mut first_event,
+            &mut Default::default(),
         )
         .await
         .is_err());
     }
 
+    fn make_instance_on_nodes(name: &str, nodes: &[&str]) -> Instance {
+        let instance_json = file::read_file_to_string("../test/json/shared-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        instance.metadata.name = Some(name.to_string());
+        instance.spec.nodes = nodes.iter().map(|node| node.to_string()).collect();
+        instance
+    }
+
+    fn get_node_instance_count(node: &str) -> i64 {
+        NODE_INSTANCE_COUNT_METRIC.with_label_values(&[node]).get()
+    }
+
+    fn get_broker_counts(configuration: &str) -> (i64, i64) {
+        let labels = ["config-a-namespace", configuration];
+        (
+            EXPECTED_BROKER_COUNT_METRIC
+                .with_label_values(&labels)
+                .get(),
+            ACTUAL_BROKER_COUNT_METRIC.with_label_values(&labels).get(),
+        )
+    }
+
+    // Configuration names are unique to this test, as the metrics are global
+    #[test]
+    fn test_broker_count_metrics() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut instance_1 = make_instance_on_nodes("instance-1", &["node-a", "node-b"]);
+        instance_1.spec.configuration_name = "count-config-a".to_string();
+        let mut instance_2 = make_instance_on_nodes("instance-2", &["node-a"]);
+        instance_2.spec.configuration_name = "count-config-a".to_string();
+        let mut instance_3 = make_instance_on_nodes("instance-3", &["node-c"]);
+        instance_3.spec.configuration_name = "count-config-b".to_string();
+        // Same-named Configurations of other namespaces are reported apart
+        let mut other_namespace_instance = make_instance_on_nodes("instance-4", &["node-a"]);
+        other_namespace_instance.spec.configuration_name = "count-config-a".to_string();
+        other_namespace_instance.metadata.namespace = Some("other-namespace".to_string());
+
+        // Only one of the two brokers of instance-1 is running
+        report_broker_counts(&instance_1, Some((2, 1)));
+        report_broker_counts(&instance_2, Some((1, 1)));
+        report_broker_counts(&instance_3, Some((1, 1)));
+        report_broker_counts(&other_namespace_instance, Some((1, 0)));
+        assert_eq!(get_broker_counts("count-config-a"), (3, 2));
+        assert_eq!(get_broker_counts("count-config-b"), (1, 1));
+        assert_eq!(
+            (
+                EXPECTED_BROKER_COUNT_METRIC
+                    .with_label_values(&["other-namespace", "count-config-a"])
+                    .get(),
+                ACTUAL_BROKER_COUNT_METRIC
+                    .with_label_values(&["other-namespace", "count-config-a"])
+                    .get()
+            ),
+            (1, 0)
+        );
+
+        // A new reconcile replaces the previous counts of the Instance
+        report_broker_counts(&instance_1, Some((2, 2)));
+        assert_eq!(get_broker_counts("count-config-a"), (3, 3));
+
+        // Brokers that stop running get reported without waiting for a reconcile
+        report_running_brokers("config-a-namespace", "instance-1", 0);
+        assert_eq!(get_broker_counts("count-config-a"), (3, 1));
+        // Instances not reconciled yet are left to their reconcile
+        report_running_brokers("config-a-namespace", "instance-5", 1);
+        assert_eq!(get_broker_counts("count-config-a"), (3, 1));
+
+        report_broker_counts(&instance_1, None);
+        assert_eq!(get_broker_counts("count-config-a"), (1, 1));
+        report_broker_counts(&instance_2, None);
+        // The Configuration is no longer reported once it has no Instance left
+        assert!(EXPECTED_BROKER_COUNT_METRIC
+            .remove_label_values(&["config-a-namespace", "count-config-a"])
+            .is_err());
+        assert!(ACTUAL_BROKER_COUNT_METRIC
+            .remove_label_values(&["config-a-namespace", "count-config-a"])
+            .is_err());
+    }
+
+    // Node names are unique to this test, as the metric is global
+    #[tokio::test]
+    async fn test_node_instance_count_metric() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut node_instance_counts = NodeInstanceCounts::default();
+        assert!(handle_instance(
+            Event::Restarted(vec![
+                make_instance_on_nodes("instance-1", &["count-node-a", "count-node-b"]),
+                make_instance_on_nodes("instance-2", &["count-node-a"]),
+                make_instance_on_nodes("instance-3", &["count-node-a", "count-node-c"]),
+            ]),
+            &MockKubeInterface::new(),
+            &mut true,
+            &mut node_instance_counts,
+        )
+        .await
+        .is_ok());
+        assert_eq!(get_node_instance_count("count-node-a"), 3);
+        assert_eq!(get_node_instance_count("count-node-b"), 1);
+        assert_eq!(get_node_instance_count("count-node-c"), 1);
+
+        // count-node-c leaves instance-3 and count-node-b joins it
+        node_instance_counts.apply(&make_instance_on_nodes(
+            "instance-3",
+            &["count-node-a", "count-node-b"],
+        ));
+        assert_eq!(get_node_instance_count("count-node-a"), 3);
+        assert_eq!(get_node_instance_count("count-node-b"), 2);
+        assert!(!node_instance_counts.reported_nodes.contains("count-node-c"));
+
+        node_instance_counts.remove(&make_instance_on_nodes("instance-1", &[]));
+        assert_eq!(get_node_instance_count("count-node-a"), 2);
+        assert_eq!(get_node_instance_count("count-node-b"), 1);
+    }
+
     #[tokio::test]
     async fn test_internal_handle_existing_instances_no_instances() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -961,6 +1760,277 @@ mod handle_instance_tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_handle_instance_change_recreates_outdated_local_broker() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_config(
+            &mut mock,
+            "config-a",
+            "config-a-namespace",
+            "../test/json/config-a.json",
+            false,
+        );
+        // The running broker was created from a previous broker spec of the Configuration
+        mock.expect_find_pods_with_label()
+            .times(1)
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                let pods_json = file::read_file_to_string(
+                    "../test/json/running-pod-list-for-config-a-local.json",
+                )
+                .replace(
+                    r#""akri.sh/instance": "config-a-b494b6","#,
+                    r#""akri.sh/instance": "config-a-b494b6",
+                            "akri.sh/broker-spec-hash": "0000000000000000","#,
+                );
+                let pods: PodList = serde_json::from_str(&pods_json).unwrap();
+                Ok(pods)
+            });
+        configure_for_handle_deletion_work(
+            &mut mock,
+            &configure_deletion_work_for_config_a_b494b6(),
+        );
+        mock.expect_create_pod()
+            .times(1)
+            .withf(|pod, namespace| {
+                let labels = pod.metadata.labels.as_ref().unwrap();
+                pod.metadata.name.as_deref() == Some("config-a-b494b6-pod")
+                    && labels.get(AKRI_BROKER_SPEC_HASH_LABEL_NAME).unwrap() != "0000000000000000"
+                    && namespace == "config-a-namespace"
+            })
+            .returning(|_, _| Ok(()));
+        // The Instance (and its device usage) is left untouched, no Instance update is expected
+        run_handle_instance_change_test(
+            &mut mock,
+            "../test/json/local-instance.json",
+            &InstanceAction::Update,
+        )
+        .await;
+    }
+
+    async fn get_created_broker_spec_hash(broker_scheduler_name: Option<&'static str>) -> String {
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_configuration()
+            .times(1)
+            .returning(move |_, _| {
+                let config_json = file::read_file_to_string("../test/json/config-a.json");
+                let mut config: Configuration = serde_json::from_str(&config_json).unwrap();
+                config.spec.broker_scheduler_name = broker_scheduler_name.map(str::to_string);
+                Ok(config)
+            });
+        mock.expect_find_pods_with_label().times(1).returning(|_| {
+            let pods_json = file::read_file_to_string("../test/json/empty-list.json");
+            let pods: PodList = serde_json::from_str(&pods_json).unwrap();
+            Ok(pods)
+        });
+        let created_hash = Arc::new(std::sync::Mutex::new(String::new()));
+        let created_hash_clone = created_hash.clone();
+        mock.expect_create_pod().times(1).returning(move |pod, _| {
+            *created_hash_clone.lock().unwrap() =
+                pod.metadata.labels.as_ref().unwrap()[AKRI_BROKER_SPEC_HASH_LABEL_NAME].clone();
+            Ok(())
+        });
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        handle_instance_change(&instance, &InstanceAction::Add, &mock)
+            .await
+            .unwrap();
+        drop(mock);
+        Arc::try_unwrap(created_hash).unwrap().into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_hashes_effective_broker_spec() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        // What the controller injects in the broker PodSpec is part of its hash, so that changing
+        // it rolls the brokers
+        assert_ne!(
+            get_created_broker_spec_hash(None).await,
+            get_created_broker_spec_hash(Some("custom-scheduler")).await
+        );
+        assert_eq!(
+            get_created_broker_spec_hash(Some("custom-scheduler")).await,
+            get_created_broker_spec_hash(Some("custom-scheduler")).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_recreates_one_outdated_broker_per_reconcile() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_config(
+            &mut mock,
+            "config-a",
+            "config-a-namespace",
+            "../test/json/config-a.json",
+            false,
+        );
+        // The running brokers of both nodes were created from a previous broker spec
+        mock.expect_find_pods_with_label()
+            .times(1)
+            .withf(|selector| selector == "akri.sh/instance=config-a-359973")
+            .returning(|_| {
+                let pods_json = file::read_file_to_string(
+                    "../test/json/running-pod-list-for-config-a-shared.json",
+                )
+                .replace(
+                    r#""akri.sh/instance": "config-a-359973","#,
+                    r#""akri.sh/instance": "config-a-359973",
+                            "akri.sh/broker-spec-hash": "0000000000000000","#,
+                );
+                let mut pods: PodList = serde_json::from_str(&pods_json).unwrap();
+                let mut node_b_pod = pods.items[0].clone();
+                node_b_pod.metadata.name = Some("node-b-config-a-359973-pod".to_string());
+                node_b_pod.metadata.labels.as_mut().unwrap().insert(
+                    AKRI_TARGET_NODE_LABEL_NAME.to_string(),
+                    "node-b".to_string(),
+                );
+                pods.items.push(node_b_pod);
+                Ok(pods)
+            });
+        // Only the broker of the first node is recreated
+        configure_for_handle_deletion_work(
+            &mut mock,
+            &configure_deletion_work_for_config_a_359973(),
+        );
+        mock.expect_create_pod()
+            .times(1)
+            .withf(|pod, namespace| {
+                pod.metadata.name.as_deref() == Some("node-a-config-a-359973-pod")
+                    && namespace == "config-a-namespace"
+            })
+            .returning(|_, _| Ok(()));
+
+        let instance_json = file::read_file_to_string("../test/json/shared-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        instance.spec.nodes = vec!["node-a".to_string(), "node-b".to_string()];
+        handle_instance_change(&instance, &InstanceAction::Update, &mock)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_adds_broker_readiness_probe() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_configuration()
+            .times(1)
+            .withf(|name, namespace| name == "config-a" && namespace == "config-a-namespace")
+            .returning(|_, _| {
+                let config_json = file::read_file_to_string("../test/json/config-a.json");
+                let mut config: Configuration = serde_json::from_str(&config_json).unwrap();
+                config.spec.broker_readiness_probe = Some(BrokerReadinessProbe {
+                    probe_type: BrokerReadinessProbeType::Tcp,
+                    target: "${DEVICE_IP}:554".to_string(),
+                    image: None,
+                    period_seconds: None,
+                });
+                Ok(config)
+            });
+        mock.expect_find_pods_with_label()
+            .times(1)
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                let pods_json = file::read_file_to_string("../test/json/empty-list.json");
+                let pods: PodList = serde_json::from_str(&pods_json).unwrap();
+                Ok(pods)
+            });
+        // The broker Pod waits for the device of its Instance
+        mock.expect_create_pod()
+            .times(1)
+            .withf(|pod, namespace| {
+                let init_container =
+                    &pod.spec.as_ref().unwrap().init_containers.as_ref().unwrap()[0];
+                init_container.name == pod::BROKER_READINESS_CONTAINER_NAME
+                    && init_container.command == Some(vec!["sh".to_string(), "-c".to_string()])
+                    && init_container.args.as_ref().unwrap()[1..3] == ["10.0.0.1", "554"]
+                    && namespace == "config-a-namespace"
+            })
+            .returning(|_, _| Ok(()));
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        instance
+            .spec
+            .broker_properties
+            .insert("DEVICE_IP".to_string(), "10.0.0.1".to_string());
+        handle_instance_change(&instance, &InstanceAction::Add, &mock)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_adds_node_affinity_preferences() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_configuration()
+            .times(1)
+            .withf(|name, namespace| name == "config-a" && namespace == "config-a-namespace")
+            .returning(|_, _| {
+                let config_json = file::read_file_to_string("../test/json/config-a.json");
+                let mut config: Configuration = serde_json::from_str(&config_json).unwrap();
+                config.spec.node_affinity_preferences = Some(vec![NodeAffinityPreference {
+                    weight: 80,
+                    match_labels: [("gpu".to_string(), "true".to_string())].into(),
+                }]);
+                Ok(config)
+            });
+        mock.expect_find_pods_with_label()
+            .times(1)
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                let pods_json = file::read_file_to_string("../test/json/empty-list.json");
+                let pods: PodList = serde_json::from_str(&pods_json).unwrap();
+                Ok(pods)
+            });
+        // The broker Pod prefers the labelled nodes while still being required on its node
+        mock.expect_create_pod()
+            .times(1)
+            .withf(|pod, namespace| {
+                let node_affinity = pod
+                    .spec
+                    .as_ref()
+                    .unwrap()
+                    .affinity
+                    .as_ref()
+                    .unwrap()
+                    .node_affinity
+                    .as_ref()
+                    .unwrap();
+                let preferred = node_affinity
+                    .preferred_during_scheduling_ignored_during_execution
+                    .as_ref()
+                    .unwrap();
+                let required = &node_affinity
+                    .required_during_scheduling_ignored_during_execution
+                    .as_ref()
+                    .unwrap()
+                    .node_selector_terms[0]
+                    .match_fields
+                    .as_ref()
+                    .unwrap()[0];
+                preferred.len() == 1
+                    && preferred[0].weight == 80
+                    && preferred[0].preference.match_expressions.as_ref().unwrap()[0].key == "gpu"
+                    && required.key == OBJECT_NAME_FIELD
+                    && required.values == Some(vec!["node-a".to_string()])
+                    && namespace == "config-a-namespace"
+            })
+            .returning(|_, _| Ok(()));
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        handle_instance_change(&instance, &InstanceAction::Add, &mock)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_handle_instance_change_for_add_new_local_instance_error() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1217,4 +2287,157 @@ mod handle_instance_tests {
             0
         );
     }
+
+    fn get_deployment_spec_for_tests() -> DeploymentSpec {
+        serde_json::from_str(
+            r#"{"replicas": 2, "selector": {}, "template": {"spec": {"containers": [{"image": "nginx:latest", "name": "broker"}]}}}"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_deployment_add() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let mut mock = MockKubeInterface::new();
+        mock.expect_apply_deployment()
+            .times(1)
+            .withf(|deployment, namespace| {
+                namespace == "config-a-namespace"
+                    && deployment.metadata.name.as_deref() == Some("config-a-b494b6-deployment")
+                    && deployment.metadata.owner_references.as_ref().unwrap()[0].name
+                        == "config-a-b494b6"
+                    && deployment.spec.as_ref().unwrap().replicas == Some(2)
+                    && deployment
+                        .spec
+                        .as_ref()
+                        .unwrap()
+                        .selector
+                        .match_labels
+                        .as_ref()
+                        .unwrap()
+                        .get(AKRI_INSTANCE_LABEL_NAME)
+                        .map(String::as_str)
+                        == Some("config-a-b494b6")
+            })
+            .returning(|_, _| Ok(()));
+        handle_instance_change_deployment(
+            &instance,
+            &get_deployment_spec_for_tests(),
+            &InstanceAction::Add,
+            &mock,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_deployment_remove() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_deployments_with_label()
+            .times(1)
+            .withf(|selector| selector == "akri.sh/instance=config-a-b494b6")
+            .returning(|_| {
+                Ok(serde_json::from_str(
+                    r#"{"apiVersion": "apps/v1", "kind": "DeploymentList", "metadata": {}, "items": [{"metadata": {"name": "config-a-b494b6-deployment", "namespace": "config-a-namespace"}}]}"#,
+                )
+                .unwrap())
+            });
+        mock.expect_remove_deployment()
+            .times(1)
+            .withf(|name, namespace| {
+                name == "config-a-b494b6-deployment" && namespace == "config-a-namespace"
+            })
+            .returning(|_, _| Ok(()));
+        handle_instance_change_deployment(
+            &instance,
+            &get_deployment_spec_for_tests(),
+            &InstanceAction::Remove,
+            &mock,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_deployment_update() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        // The existing Deployment is updated to the new number of replicas
+        let mut mock = MockKubeInterface::new();
+        mock.expect_apply_deployment()
+            .times(1)
+            .withf(|deployment, namespace| {
+                namespace == "config-a-namespace"
+                    && deployment.metadata.name.as_deref() == Some("config-a-b494b6-deployment")
+                    && deployment.spec.as_ref().unwrap().replicas == Some(5)
+            })
+            .returning(|_, _| Ok(()));
+        let mut deployment_spec = get_deployment_spec_for_tests();
+        deployment_spec.replicas = Some(5);
+        handle_instance_change_deployment(
+            &instance,
+            &deployment_spec,
+            &InstanceAction::Update,
+            &mock,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_instance_change_applies_updated_deployment() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_configuration()
+            .times(1)
+            .withf(|name, namespace| name == "config-a" && namespace == "config-a-namespace")
+            .returning(|_, _| {
+                let config_json = file::read_file_to_string("../test/json/config-a.json");
+                let mut config: Configuration = serde_json::from_str(&config_json).unwrap();
+                let mut deployment_spec: DeploymentSpec = serde_json::from_str(
+                    r#"{"replicas": 3, "selector": {}, "template": {"spec": {"containers": [{"image": "nginx:stable", "name": "broker", "resources": {"requests": {"cpu": "{{AKRI_INSTANCE_CAPACITY}}00m"}}}]}}}"#,
+                )
+                .unwrap();
+                deployment_spec.min_ready_seconds = Some(10);
+                config.spec.broker_spec =
+                    Some(BrokerSpec::BrokerDeploymentSpec(Box::new(deployment_spec)));
+                Ok(config)
+            });
+        // The Deployment follows the Configuration's brokerSpec and the Instance capacity
+        mock.expect_apply_deployment()
+            .times(1)
+            .withf(|deployment, namespace| {
+                let spec = deployment.spec.as_ref().unwrap();
+                let container = &spec.template.spec.as_ref().unwrap().containers[0];
+                namespace == "config-a-namespace"
+                    && spec.replicas == Some(3)
+                    && spec.min_ready_seconds == Some(10)
+                    && container.image.as_deref() == Some("nginx:stable")
+                    && container
+                        .resources
+                        .as_ref()
+                        .unwrap()
+                        .requests
+                        .as_ref()
+                        .unwrap()["cpu"]
+                        == Quantity("500m".to_string())
+            })
+            .returning(|_, _| Ok(()));
+
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        handle_instance_change(&instance, &InstanceAction::Update, &mock)
+            .await
+            .unwrap();
+    }
 }
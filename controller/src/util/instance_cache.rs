@@ -0,0 +1,106 @@
+use akri_shared::{
+    akri::instance::Instance,
+    k8s::{self, KubeInterface},
+};
+use futures::StreamExt;
+use kube::api::Api;
+use kube_runtime::reflector::{self, store::Writer, ObjectRef, Store};
+use kube_runtime::watcher::{watcher, Config};
+use kube_runtime::WatchStreamExt;
+use log::{error, trace};
+
+/// This keeps the given cache in sync with the Instances of the cluster.
+///
+/// The cache is a read-only replica of the Instances, consulted instead of
+/// the API server wherever a slightly stale Instance is acceptable.
+pub async fn run_instance_cache(
+    writer: Writer<Instance>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    trace!("run_instance_cache - enter");
+    let kube_interface = k8s::KubeImpl::new().await?;
+    let resource = Api::<Instance>::all(kube_interface.get_kube_client());
+    let mut informer = reflector::reflector(
+        writer,
+        watcher(resource, Config::default()).default_backoff(),
+    )
+    .boxed();
+    while let Some(event) = informer.next().await {
+        if let Err(e) = event {
+            error!("run_instance_cache - error during watch: {}", e);
+        }
+    }
+    Err(anyhow::anyhow!("Instance cache watch stream ended").into())
+}
+
+/// This looks for an Instance in the cache, falling back to the API server
+/// on cache miss (e.g. the Instance was just created and the cache has
+/// not caught up yet).
+pub async fn find_instance(
+    instance_cache: &Store<Instance>,
+    name: &str,
+    namespace: &str,
+    kube_interface: &impl KubeInterface,
+) -> anyhow::Result<Instance> {
+    match instance_cache.get(&ObjectRef::new(name).within(namespace)) {
+        Some(instance) => {
+            trace!("find_instance - cache hit for {}/{}", namespace, name);
+            Ok(instance.as_ref().clone())
+        }
+        None => {
+            trace!("find_instance - cache miss for {}/{}", namespace, name);
+            kube_interface.find_instance(name, namespace).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::shared_test_utils::config_for_tests;
+    use super::*;
+    use akri_shared::{k8s::MockKubeInterface, os::file};
+    use kube::ResourceExt;
+
+    fn make_instance() -> Instance {
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        serde_json::from_str(&instance_json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_find_instance_cache_hit() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (store, mut writer) = reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![
+            make_instance(),
+        ]));
+        // No expectation is set, any call to the API server would fail the test
+        let mock = MockKubeInterface::new();
+
+        let instance = find_instance(&store, "config-a-b494b6", "config-a-namespace", &mock)
+            .await
+            .unwrap();
+        assert_eq!(instance.name_any(), "config-a-b494b6");
+    }
+
+    #[tokio::test]
+    async fn test_find_instance_cache_miss() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let (store, mut writer) = reflector::store();
+        writer.apply_watcher_event(&kube_runtime::watcher::Event::Restarted(vec![
+            make_instance(),
+        ]));
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_instance(
+            &mut mock,
+            "config-a-b494b6",
+            "other-namespace",
+            "../test/json/local-instance.json",
+            false,
+        );
+
+        assert!(
+            find_instance(&store, "config-a-b494b6", "other-namespace", &mock)
+                .await
+                .is_ok()
+        );
+    }
+}
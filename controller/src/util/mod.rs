@@ -1,4 +1,5 @@
 pub mod instance_action;
+pub mod instance_cache;
 pub mod node_watcher;
 mod pod_action;
 pub mod pod_watcher;
@@ -2,11 +2,13 @@ use akri_shared::{
     akri::{
         instance::device_usage::NodeUsage,
         instance::{Instance, InstanceSpec},
-        retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
+        retry::{backoff, MAX_INSTANCE_UPDATE_TRIES},
     },
     k8s,
     k8s::KubeInterface,
+    os::env_var::EnvVarQuery,
 };
+use chrono::{DateTime, Utc};
 use futures::{StreamExt, TryStreamExt};
 use k8s_openapi::api::core::v1::{Node, NodeStatus};
 use kube::api::Api;
@@ -16,6 +18,40 @@ use log::{error, info, trace};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Time (in seconds) a previously Running Node may stay NotReady before the Instances
+/// are cleaned of references to it
+pub const NODE_NOT_READY_GRACE_PERIOD_SECS: i64 = 300;
+
+/// Environment variable name for setting the interval (in seconds) of the controller's periodic
+/// system checks, [`crate::SYSTEM_CHECK_DELAY_SECS`] is used if unset or invalid
+pub const SYSTEM_CHECK_DELAY_SECS_LABEL: &str = "SYSTEM_CHECK_DELAY_SECS";
+
+/// Get the interval of the controller's periodic system checks, such as checking NotReady Nodes for
+/// grace period expiry, as a NotReady Node may not get updated anymore
+pub fn get_system_check_delay(env_var_query: &impl EnvVarQuery) -> std::time::Duration {
+    let default = std::time::Duration::from_secs(crate::SYSTEM_CHECK_DELAY_SECS);
+    let Ok(value) = env_var_query.get_env_var(SYSTEM_CHECK_DELAY_SECS_LABEL) else {
+        return default;
+    };
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => std::time::Duration::from_secs(secs),
+        Ok(_) => {
+            error!(
+                "get_system_check_delay - {} must be greater than 0",
+                SYSTEM_CHECK_DELAY_SECS_LABEL
+            );
+            default
+        }
+        Err(e) => {
+            error!(
+                "get_system_check_delay - invalid {} value {:?}: {}",
+                SYSTEM_CHECK_DELAY_SECS_LABEL, value, e
+            );
+            default
+        }
+    }
+}
+
 /// Node states that NodeWatcher is interested in
 ///
 /// NodeState describes the various states that the controller can
@@ -26,6 +62,9 @@ enum NodeState {
     Known,
     /// Node has been seen Running
     Running,
+    /// A previously Running Node has been seen as not Running since
+    /// the given time, and is still within the grace period
+    NotReady(DateTime<Utc>),
     /// A previously Running Node has been seen as not Running
     /// and the Instances have been cleaned of references to that
     /// vanished Node
@@ -39,8 +78,14 @@ enum NodeState {
 /// Instance.nodes property no longer contains the node and
 /// that the Instance.deviceUsage property no longer contains
 /// slots that are occupied by the node.
+///
+/// A Node that stops being Ready is given NODE_NOT_READY_GRACE_PERIOD_SECS
+/// to recover before the Instances get cleaned.
 pub struct NodeWatcher {
     known_nodes: HashMap<String, NodeState>,
+    not_ready_grace_period: chrono::Duration,
+    system_check_delay: std::time::Duration,
+    max_instance_update_tries: u8,
 }
 
 impl NodeWatcher {
@@ -48,9 +93,24 @@ impl NodeWatcher {
     pub fn new() -> Self {
         NodeWatcher {
             known_nodes: HashMap::new(),
+            not_ready_grace_period: chrono::Duration::seconds(NODE_NOT_READY_GRACE_PERIOD_SECS),
+            system_check_delay: std::time::Duration::from_secs(crate::SYSTEM_CHECK_DELAY_SECS),
+            max_instance_update_tries: MAX_INSTANCE_UPDATE_TRIES,
         }
     }
 
+    /// Sets the interval at which NotReady Nodes are checked for grace period expiry
+    pub fn with_system_check_delay(mut self, delay: std::time::Duration) -> Self {
+        self.system_check_delay = delay;
+        self
+    }
+
+    /// Sets the maximum amount of tries to update an Instance of a vanished Node
+    pub fn with_max_instance_update_tries(mut self, tries: u8) -> Self {
+        self.max_instance_update_tries = tries;
+        self
+    }
+
     /// This watches for Node events
     pub async fn watch(
         &mut self,
@@ -61,10 +121,19 @@ impl NodeWatcher {
         let watcher = watcher(resource, Config::default()).default_backoff();
         let mut informer = watcher.boxed();
         let mut first_event = true;
+        let mut not_ready_check = tokio::time::interval(self.system_check_delay);
 
         // Currently, this does not handle None except to break the loop.
         loop {
-            let event = match informer.try_next().await {
+            let event = tokio::select! {
+                event = informer.try_next() => event,
+                _ = not_ready_check.tick() => {
+                    self.handle_not_ready_timeouts(Utc::now(), &kube_interface)
+                        .await?;
+                    continue;
+                }
+            };
+            let event = match event {
                 Err(e) => {
                     error!("Error during watch: {}", e);
                     continue;
@@ -96,7 +165,9 @@ impl NodeWatcher {
     ///
     /// Once a Node moves through the Running state into a non Running
     /// state, it becomes important to clean Instances referencing the
-    /// non-Running Node.
+    /// non-Running Node.  Unless the Node is deleted, this only happens
+    /// once it has been non Running for longer than the grace period, in
+    /// the meantime it is stored as NotReady.
     async fn handle_node(
         &mut self,
         event: Event<Node>,
@@ -116,7 +187,7 @@ impl NodeWatcher {
                     e.insert(NodeState::Known);
                 } else {
                     // Node Modified
-                    self.call_handle_node_disappearance_if_needed(&node, kube_interface)
+                    self.handle_not_ready_node(&node, Utc::now(), kube_interface)
                         .await?;
                 }
             }
@@ -139,6 +210,70 @@ impl NodeWatcher {
         Ok(())
     }
 
+    /// This should be called for previously seen Nodes that are !Ready.
+    /// The Instances are only cleaned once the Node has been !Ready for
+    /// longer than the grace period, the Node is stored as NotReady until then.
+    async fn handle_not_ready_node(
+        &mut self,
+        node: &Node,
+        now: DateTime<Utc>,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        let node_name = node.metadata.name.clone().unwrap();
+        let since = match self.known_nodes.get(&node_name) {
+            Some(NodeState::Running) => self.not_ready_since(node).unwrap_or(now),
+            Some(NodeState::NotReady(since)) => *since,
+            _ => return Ok(()),
+        };
+        if now - since >= self.not_ready_grace_period {
+            info!(
+                "handle_not_ready_node - {} has not been ready since {}",
+                node_name, since
+            );
+            self.call_handle_node_disappearance_if_needed(node, kube_interface)
+                .await
+        } else {
+            trace!(
+                "handle_not_ready_node - {} not ready since {}, within grace period",
+                node_name,
+                since
+            );
+            self.known_nodes
+                .insert(node_name, NodeState::NotReady(since));
+            Ok(())
+        }
+    }
+
+    /// This cleans the Instances of references to Nodes that have been
+    /// NotReady for longer than the grace period.
+    async fn handle_not_ready_timeouts(
+        &mut self,
+        now: DateTime<Utc>,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        let timed_out_nodes = self
+            .known_nodes
+            .iter()
+            .filter_map(|(node_name, state)| match state {
+                NodeState::NotReady(since) if now - *since >= self.not_ready_grace_period => {
+                    Some(node_name.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        for node_name in timed_out_nodes {
+            info!(
+                "handle_not_ready_timeouts - {} not ready beyond grace period",
+                node_name
+            );
+            self.handle_node_disappearance(&node_name, kube_interface)
+                .await?;
+            self.known_nodes
+                .insert(node_name, NodeState::InstancesCleaned);
+        }
+        Ok(())
+    }
+
     /// This should be called for Nodes that are either !Ready or Deleted.
     /// This function ensures that handle_node_disappearance is called
     /// only once for any Node as it disappears.
@@ -165,7 +300,10 @@ impl NodeWatcher {
         //
         // Also, there is no need to call handle_node_disappearance if a
         // Node has never been in the Running state.
-        if last_known_state == &NodeState::Running {
+        if matches!(
+            last_known_state,
+            NodeState::Running | NodeState::NotReady(_)
+        ) {
             trace!(
                 "call_handle_node_disappearance_if_needed - call handle_node_disappearance: {:?}",
                 &node.metadata.name
@@ -202,6 +340,22 @@ impl NodeWatcher {
             == &true
     }
 
+    /// This returns the time a !Ready Node stopped being Ready, if known.
+    fn not_ready_since(&self, k8s_node: &Node) -> Option<DateTime<Utc>> {
+        k8s_node
+            .status
+            .as_ref()?
+            .conditions
+            .as_ref()?
+            .iter()
+            .rev()
+            .find(|condition| condition.type_ == "Ready")
+            .filter(|condition| condition.status != "True")?
+            .last_transition_time
+            .as_ref()
+            .map(|time| time.0)
+    }
+
     /// This handles when a node disappears by clearing nodes from
     /// the nodes list and deviceUsage map and then trying 5 times to
     /// update the Instance.
@@ -231,8 +385,8 @@ impl NodeWatcher {
                 &instance_name
             );
 
-            // Try up to MAX_INSTANCE_UPDATE_TRIES times to update/create/get instance
-            for x in 0..MAX_INSTANCE_UPDATE_TRIES {
+            // Try up to max_instance_update_tries times to update/create/get instance
+            for x in 0..self.max_instance_update_tries {
                 match if x == 0 {
                     self.try_remove_nodes_from_instance(
                         vanished_node_name,
@@ -257,10 +411,10 @@ impl NodeWatcher {
                 } {
                     Ok(_) => break,
                     Err(e) => {
-                        if x == (MAX_INSTANCE_UPDATE_TRIES - 1) {
+                        if x == (self.max_instance_update_tries - 1) {
                             return Err(e);
                         }
-                        random_delay().await;
+                        backoff(x).await;
                     }
                 }
             }
@@ -323,6 +477,9 @@ impl NodeWatcher {
             shared: instance.spec.shared,
             device_usage: modified_device_usage,
             nodes: modified_nodes,
+            // Left out of the merge patch, the discovery timestamps are only written by the Agents
+            first_discovered: None,
+            last_seen: Default::default(),
         };
 
         trace!(
@@ -342,7 +499,10 @@ impl NodeWatcher {
 mod tests {
     use super::super::shared_test_utils::config_for_tests;
     use super::*;
-    use akri_shared::{akri::instance::InstanceList, k8s::MockKubeInterface, os::file};
+    use akri_shared::{
+        akri::instance::InstanceList, k8s::MockKubeInterface, os::env_var::MockEnvVarQuery,
+        os::file,
+    };
 
     #[derive(Clone)]
     struct UpdateInstance {
@@ -511,6 +671,143 @@ mod tests {
         )
     }
 
+    fn make_node_not_ready_since(since: DateTime<Utc>) -> Node {
+        let node_json = file::read_file_to_string("../test/json/node-b-not-ready.json");
+        let mut node: Node = serde_json::from_str(&node_json).unwrap();
+        for condition in node
+            .status
+            .as_mut()
+            .unwrap()
+            .conditions
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .filter(|condition| condition.type_ == "Ready")
+        {
+            condition.last_transition_time =
+                Some(k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(since));
+        }
+        node
+    }
+
+    #[tokio::test]
+    async fn test_handle_node_not_ready_then_recovered() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let since = Utc::now();
+        let mut node_watcher = NodeWatcher::new();
+        node_watcher
+            .known_nodes
+            .insert("node-b".to_string(), NodeState::Running);
+        // No expectation is set, any Instance update would fail the test
+        let mock = MockKubeInterface::new();
+        node_watcher
+            .handle_node(
+                Event::Applied(make_node_not_ready_since(since)),
+                &mock,
+                &mut false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::NotReady(since),
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+
+        let node_json = file::read_file_to_string("../test/json/node-b.json");
+        let node: Node = serde_json::from_str(&node_json).unwrap();
+        node_watcher
+            .handle_node(Event::Applied(node), &mock, &mut false)
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::Running,
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+
+        // Recovered Nodes are not cleaned once the grace period would have expired
+        node_watcher
+            .handle_not_ready_timeouts(
+                since + chrono::Duration::seconds(NODE_NOT_READY_GRACE_PERIOD_SECS),
+                &mock,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::Running,
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_node_not_ready_then_timeout() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let since = Utc::now();
+        let mut node_watcher = NodeWatcher::new();
+        node_watcher
+            .known_nodes
+            .insert("node-b".to_string(), NodeState::Running);
+        node_watcher
+            .handle_node(
+                Event::Applied(make_node_not_ready_since(since)),
+                &MockKubeInterface::new(),
+                &mut false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::NotReady(since),
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+
+        // Still within the grace period
+        node_watcher
+            .handle_not_ready_timeouts(
+                since + chrono::Duration::seconds(10),
+                &MockKubeInterface::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::NotReady(since),
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+
+        let instance_file = "../test/json/shared-instance-update.json";
+        let instance_json = file::read_file_to_string(instance_file);
+        let kube_object_instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let mut instance = kube_object_instance.spec;
+        instance.nodes.clear();
+        instance
+            .device_usage
+            .insert("config-a-359973-2".to_string(), "".to_string());
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_node_disappearance(
+            &mut mock,
+            &HandleNodeDisappearance {
+                get_instances_result_file: "../test/json/shared-instance-update.json",
+                get_instances_result_listify: true,
+                update_instance: Some(UpdateInstance {
+                    instance_to_update: instance,
+                    instance_name: "config-a-359973",
+                    instance_namespace: "config-a-namespace",
+                }),
+            },
+        );
+        node_watcher
+            .handle_not_ready_timeouts(
+                since + chrono::Duration::seconds(NODE_NOT_READY_GRACE_PERIOD_SECS),
+                &mock,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            &NodeState::InstancesCleaned,
+            node_watcher.known_nodes.get("node-b").unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn test_handle_node_deleted_unready_unknown() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -676,4 +973,37 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_get_system_check_delay() {
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .with(mockall::predicate::eq(SYSTEM_CHECK_DELAY_SECS_LABEL))
+            .returning(|_| Ok("120".to_string()));
+        let delay = get_system_check_delay(&mock_query);
+        assert_eq!(delay, std::time::Duration::from_secs(120));
+        assert_eq!(
+            NodeWatcher::new()
+                .with_system_check_delay(delay)
+                .system_check_delay,
+            delay
+        );
+
+        let default = std::time::Duration::from_secs(crate::SYSTEM_CHECK_DELAY_SECS);
+        assert_eq!(NodeWatcher::new().system_check_delay, default);
+        for value in ["not-a-number", "-5", "0", ""] {
+            let mut mock_query = MockEnvVarQuery::new();
+            mock_query
+                .expect_get_env_var()
+                .returning(move |_| Ok(value.to_string()));
+            assert_eq!(get_system_check_delay(&mock_query), default);
+        }
+
+        let mut mock_query = MockEnvVarQuery::new();
+        mock_query
+            .expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_system_check_delay(&mock_query), default);
+    }
 }
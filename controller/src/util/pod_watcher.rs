@@ -1,25 +1,106 @@
+use super::super::BROKER_POD_IMAGE_PULL_BACK_OFF_METRIC;
+use super::{instance_action, instance_cache};
 use akri_shared::{
     akri::{
         configuration::Configuration,
-        retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
+        instance::{is_quarantined, Instance},
+        retry::{backoff, get_max_instance_update_tries},
+        AKRI_QUARANTINED_ANNOTATION_NAME,
     },
     k8s,
     k8s::{
+        event,
         pod::{AKRI_CONFIGURATION_LABEL_NAME, AKRI_INSTANCE_LABEL_NAME},
         service, KubeInterface, OwnershipInfo, OwnershipType,
     },
+    os::env_var::{ActualEnvVarQuery, EnvVarQuery},
 };
 use async_std::sync::Mutex;
 use futures::{StreamExt, TryStreamExt};
 use k8s_openapi::api::core::v1::{Pod, ServiceSpec};
 use kube::api::Api;
+use kube_runtime::reflector::Store;
 use kube_runtime::watcher::{watcher, Config, Event};
 use kube_runtime::WatchStreamExt;
-use log::{error, info, trace};
-use std::{collections::HashMap, sync::Arc};
+use log::{error, info, trace, warn};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::Instant;
 
 type PodSlice = [Pod];
 
+/// Container waiting reason set by the kubelet once it repeatedly failed to pull an image
+const IMAGE_PULL_BACK_OFF_REASON: &str = "ImagePullBackOff";
+/// Reason of the Event raised on a Configuration when one of its broker Pods cannot pull its image
+pub const BROKER_IMAGE_PULL_BACK_OFF_EVENT_REASON: &str = "BrokerImagePullBackOff";
+/// Reason of the Event raised on a Configuration when one of its Instances gets quarantined
+pub const BROKER_QUARANTINED_EVENT_REASON: &str = "BrokerCrashLoopQuarantine";
+/// Environment variable name for setting how many times a broker Pod may restart before its
+/// Instance gets quarantined, Instances are never quarantined if unset
+pub const BROKER_QUARANTINE_RESTART_THRESHOLD_LABEL: &str = "BROKER_QUARANTINE_RESTART_THRESHOLD";
+/// Environment variable name for setting how long (in seconds) the broker Pods of an Instance are
+/// not recreated once they repeatedly ended, [`DEFAULT_BROKER_RECREATION_COOLDOWN_SECS`] is used if
+/// unset or invalid and 0 disables the cooldown
+pub const BROKER_RECREATION_COOLDOWN_SECS_LABEL: &str = "BROKER_RECREATION_COOLDOWN_SECS";
+/// Default cooldown (in seconds) before recreating the broker Pods of an Instance that repeatedly ended
+pub const DEFAULT_BROKER_RECREATION_COOLDOWN_SECS: u64 = 30;
+/// Window in which the broker Pods of an Instance ending count towards its recreation cooldown
+pub const BROKER_RECREATION_WINDOW: Duration = Duration::from_secs(300);
+/// Number of broker Pods of an Instance ending within [`BROKER_RECREATION_WINDOW`] after which
+/// their recreation waits for the cooldown
+pub const BROKER_RECREATION_COOLDOWN_THRESHOLD: usize = 2;
+
+/// Get the number of restarts after which the Instance of a crashing broker gets quarantined, if set
+fn get_quarantine_restart_threshold(env_var_query: &impl EnvVarQuery) -> Option<i32> {
+    let value = env_var_query
+        .get_env_var(BROKER_QUARANTINE_RESTART_THRESHOLD_LABEL)
+        .ok()?;
+    match value.parse::<i32>() {
+        Ok(threshold) if threshold > 0 => Some(threshold),
+        _ => {
+            error!(
+                "get_quarantine_restart_threshold - invalid {} value {:?}",
+                BROKER_QUARANTINE_RESTART_THRESHOLD_LABEL, value
+            );
+            None
+        }
+    }
+}
+
+/// Get how long the broker Pods of an Instance that repeatedly ended are not recreated, a zero
+/// duration disables the cooldown
+fn get_broker_recreation_cooldown(env_var_query: &impl EnvVarQuery) -> Duration {
+    let default = Duration::from_secs(DEFAULT_BROKER_RECREATION_COOLDOWN_SECS);
+    let Ok(value) = env_var_query.get_env_var(BROKER_RECREATION_COOLDOWN_SECS_LABEL) else {
+        return default;
+    };
+    match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(e) => {
+            error!(
+                "get_broker_recreation_cooldown - invalid {} value {:?}: {}",
+                BROKER_RECREATION_COOLDOWN_SECS_LABEL, value, e
+            );
+            default
+        }
+    }
+}
+
+/// Returns the highest restart count among the containers of the Pod
+fn get_restart_count(pod: &Pod) -> i32 {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.container_statuses.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|container_status| container_status.restart_count)
+        .max()
+        .unwrap_or_default()
+}
+
 /// Pod states that BrokerPodWatcher is interested in
 ///
 /// PodState describes the various states that the controller can
@@ -28,6 +109,10 @@ type PodSlice = [Pod];
 enum PodState {
     /// Pod is in Pending state and no action is needed.
     Pending,
+    /// Pod is in Pending state because one of its containers
+    /// repeatedly failed to pull its image. The failure is
+    /// reported once per transition into this state.
+    ImagePullBackOff,
     /// Pod is in Running state and needs to ensure that
     /// instance and configuration services are running
     Running,
@@ -97,6 +182,28 @@ fn get_broker_pod_owner_kind(pod: &Pod) -> BrokerPodOwnerKind {
     }
 }
 
+/// Returns a description of the first container of the Pod that is backing off
+/// pulling its image, if any
+fn get_image_pull_back_off(pod: &Pod) -> Option<String> {
+    let status = pod.status.as_ref()?;
+    status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten())
+        .find_map(|container_status| {
+            let waiting = container_status.state.as_ref()?.waiting.as_ref()?;
+            (waiting.reason.as_deref() == Some(IMAGE_PULL_BACK_OFF_REASON)).then(|| {
+                format!(
+                    "container {} cannot pull image {}: {}",
+                    container_status.name,
+                    container_status.image,
+                    waiting.message.as_deref().unwrap_or_default()
+                )
+            })
+        })
+}
+
 /// This is used to handle broker Pods entering and leaving
 /// the Running state.
 ///
@@ -112,13 +219,37 @@ fn get_broker_pod_owner_kind(pod: &Pod) -> BrokerPodOwnerKind {
 #[derive(Debug)]
 pub struct BrokerPodWatcher {
     known_pods: HashMap<String, PodState>,
+    instance_cache: Store<Instance>,
+    quarantine_restart_threshold: Option<i32>,
+    broker_recreation_cooldown: Duration,
+    /// Broker Pods (by UID) that recently ended, per Instance namespace and name
+    ended_broker_pods: HashMap<(String, String), Vec<(String, Instant)>>,
+    /// When the broker Pods of Instances in cooldown get recreated, per Instance namespace and name
+    pending_recreations: HashMap<(String, String), Instant>,
+    /// Running broker Pods (by name), per Instance namespace and name
+    running_broker_pods: HashMap<(String, String), HashSet<String>>,
+    max_instance_update_tries: u8,
 }
 
 impl BrokerPodWatcher {
     /// Create new instance of BrokerPodWatcher
     pub fn new() -> Self {
+        let (instance_cache, _) = kube_runtime::reflector::store();
+        Self::with_instance_cache(instance_cache)
+    }
+
+    /// Create new instance of BrokerPodWatcher that looks for Instances in
+    /// the given cache before querying the API server
+    pub fn with_instance_cache(instance_cache: Store<Instance>) -> Self {
         BrokerPodWatcher {
             known_pods: HashMap::new(),
+            instance_cache,
+            quarantine_restart_threshold: get_quarantine_restart_threshold(&ActualEnvVarQuery {}),
+            broker_recreation_cooldown: get_broker_recreation_cooldown(&ActualEnvVarQuery {}),
+            ended_broker_pods: HashMap::new(),
+            pending_recreations: HashMap::new(),
+            running_broker_pods: HashMap::new(),
+            max_instance_update_tries: get_max_instance_update_tries(&ActualEnvVarQuery {}),
         }
     }
 
@@ -137,7 +268,22 @@ impl BrokerPodWatcher {
         let mut first_event = true;
 
         loop {
-            let event = match informer.try_next().await {
+            let next_recreation = self.next_broker_recreation();
+            let event = tokio::select! {
+                event = informer.try_next() => event,
+                _ = async {
+                    match next_recreation {
+                        Some(at) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let _lock = synchronization.lock().await;
+                    self.recreate_due_broker_pods(Instant::now(), &kube_interface)
+                        .await?;
+                    continue;
+                }
+            };
+            let event = match event {
                 Err(e) => {
                     error!("Error during watch: {}", e);
                     continue;
@@ -182,16 +328,28 @@ impl BrokerPodWatcher {
                     "handle_pod - pod {:?} added or modified",
                     &pod.metadata.name
                 );
+                self.quarantine_instance_if_needed(&pod, kube_interface)
+                    .await?;
                 let phase = self.get_pod_phase(&pod);
                 trace!("handle_pod - pod phase {:?}", &phase);
+                self.record_running_broker_pod(&pod, phase == "Running");
                 match phase.as_str() {
                     "Unknown" | "Pending" => {
-                        self.known_pods.insert(
-                            pod.metadata.name.clone().ok_or_else(|| {
-                                anyhow::format_err!("Pod {:?} does not have name", pod)
-                            })?,
-                            PodState::Pending,
-                        );
+                        if let Some(failure) = get_image_pull_back_off(&pod) {
+                            self.handle_image_pull_back_off_pod_if_needed(
+                                &pod,
+                                &failure,
+                                kube_interface,
+                            )
+                            .await?;
+                        } else {
+                            self.known_pods.insert(
+                                pod.metadata.name.clone().ok_or_else(|| {
+                                    anyhow::format_err!("Pod {:?} does not have name", pod)
+                                })?,
+                                PodState::Pending,
+                            );
+                        }
                     }
                     "Running" => {
                         self.handle_running_pod_if_needed(&pod, kube_interface)
@@ -208,6 +366,7 @@ impl BrokerPodWatcher {
             }
             Event::Deleted(pod) => {
                 info!("handle_pod - Deleted: {:?}", &pod.metadata.name);
+                self.record_running_broker_pod(&pod, false);
                 self.handle_deleted_pod_if_needed(&pod, kube_interface)
                     .await?;
             }
@@ -217,6 +376,10 @@ impl BrokerPodWatcher {
                         "handle_pod - pod watcher [re]started. Pods are : {:?}",
                         pods
                     );
+                    for pod in pods.iter() {
+                        let running = self.get_pod_phase(pod) == "Running";
+                        self.record_running_broker_pod(pod, running);
+                    }
                 } else {
                     return Err(anyhow::anyhow!(
                         "Pod watcher restarted - throwing error to restart controller"
@@ -228,6 +391,181 @@ impl BrokerPodWatcher {
         Ok(())
     }
 
+    /// This ensures that a broker Pod that cannot pull its image is reported
+    /// only once per transition into the ImagePullBackOff state, by incrementing
+    /// the image pull back off metric and raising a Warning Event on its Configuration.
+    async fn handle_image_pull_back_off_pod_if_needed(
+        &mut self,
+        pod: &Pod,
+        failure: &str,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        trace!("handle_image_pull_back_off_pod_if_needed - enter");
+        let pod_name = pod
+            .metadata
+            .name
+            .clone()
+            .ok_or_else(|| anyhow::format_err!("Pod {:?} does not have name", pod))?;
+        if self.known_pods.get(&pod_name) == Some(&PodState::ImagePullBackOff) {
+            return Ok(());
+        }
+        self.known_pods
+            .insert(pod_name.clone(), PodState::ImagePullBackOff);
+        let namespace = pod.metadata.namespace.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Namespace not found for pod: {:?}", &pod.metadata.name)
+        })?;
+        let (_, config_name) = self.get_instance_and_configuration_from_pod(pod)?;
+        warn!(
+            "handle_image_pull_back_off_pod_if_needed - broker Pod {} of Configuration {} is stuck: {}",
+            pod_name, config_name, failure
+        );
+        BROKER_POD_IMAGE_PULL_BACK_OFF_METRIC
+            .with_label_values(&[&config_name])
+            .inc();
+
+        // Failing to report the Event should not stop the watcher
+        match kube_interface
+            .find_configuration(&config_name, namespace)
+            .await
+        {
+            Ok(configuration) => {
+                let warning = event::create_configuration_warning_event(
+                    &configuration,
+                    BROKER_IMAGE_PULL_BACK_OFF_EVENT_REASON,
+                    &format!("Broker Pod {} {}", pod_name, failure),
+                );
+                if let Err(e) = kube_interface.create_event(&warning, namespace).await {
+                    error!(
+                        "handle_image_pull_back_off_pod_if_needed - failed to create Event for Configuration {}: {:?}",
+                        config_name, e
+                    );
+                }
+            }
+            Err(e) => error!(
+                "handle_image_pull_back_off_pod_if_needed - failed to find Configuration {}: {:?}",
+                config_name, e
+            ),
+        }
+        Ok(())
+    }
+
+    /// This quarantines the Instance of a broker Pod that restarted at least
+    /// as many times as the quarantine restart threshold, and raises a Warning
+    /// Event on its Configuration. No broker gets scheduled for a quarantined
+    /// Instance until its quarantine Annotation is removed.
+    async fn quarantine_instance_if_needed(
+        &self,
+        pod: &Pod,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        trace!("quarantine_instance_if_needed - enter");
+        let restart_count = get_restart_count(pod);
+        match self.quarantine_restart_threshold {
+            Some(threshold) if restart_count >= threshold => {}
+            _ => return Ok(()),
+        }
+        // Only Pods managed by the Akri Controller get rescheduled
+        if get_broker_pod_owner_kind(pod) != BrokerPodOwnerKind::Instance {
+            return Ok(());
+        }
+        let pod_name = pod
+            .metadata
+            .name
+            .clone()
+            .ok_or_else(|| anyhow::format_err!("Pod {:?} does not have name", pod))?;
+        let namespace = pod.metadata.namespace.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Namespace not found for pod: {:?}", &pod.metadata.name)
+        })?;
+        let (instance_name, config_name) = self.get_instance_and_configuration_from_pod(pod)?;
+        // The cache may lag behind a quarantine that was just applied, read the Instance live
+        let instance = match kube_interface
+            .find_instance(&instance_name, namespace)
+            .await
+        {
+            Ok(instance) => instance,
+            _ => {
+                trace!(
+                    "quarantine_instance_if_needed - no instance found for {}",
+                    &instance_name
+                );
+                return Ok(());
+            }
+        };
+        if is_quarantined(&instance) {
+            return Ok(());
+        }
+        let reason = format!("broker Pod {} restarted {} times", pod_name, restart_count);
+        warn!(
+            "quarantine_instance_if_needed - quarantining Instance {}: {}",
+            instance_name, reason
+        );
+        // Failing to quarantine the Instance should not stop the watcher, it is retried on the next Pod update
+        if let Err(e) = kube_interface
+            .annotate_instance(
+                &BTreeMap::from([(AKRI_QUARANTINED_ANNOTATION_NAME.to_string(), reason.clone())]),
+                &instance_name,
+                namespace,
+            )
+            .await
+        {
+            error!(
+                "quarantine_instance_if_needed - failed to quarantine Instance {}: {:?}",
+                instance_name, e
+            );
+            return Ok(());
+        }
+        match kube_interface
+            .find_configuration(&config_name, namespace)
+            .await
+        {
+            Ok(configuration) => {
+                let warning = event::create_configuration_warning_event(
+                    &configuration,
+                    BROKER_QUARANTINED_EVENT_REASON,
+                    &format!(
+                        "Instance {} quarantined, {}. Remove its {} Annotation to schedule brokers again",
+                        instance_name, reason, AKRI_QUARANTINED_ANNOTATION_NAME
+                    ),
+                );
+                if let Err(e) = kube_interface.create_event(&warning, namespace).await {
+                    error!(
+                        "quarantine_instance_if_needed - failed to create Event for Configuration {}: {:?}",
+                        config_name, e
+                    );
+                }
+            }
+            Err(e) => error!(
+                "quarantine_instance_if_needed - failed to find Configuration {}: {:?}",
+                config_name, e
+            ),
+        }
+        Ok(())
+    }
+
+    /// This records whether a broker Pod is Running and reports the resulting number of Running
+    /// broker Pods of its Instance, as these phase transitions don't trigger a reconcile of the Instance.
+    fn record_running_broker_pod(&mut self, pod: &Pod, running: bool) {
+        let (Some(namespace), Some(pod_name), Ok((instance_name, _))) = (
+            pod.metadata.namespace.clone(),
+            pod.metadata.name.clone(),
+            self.get_instance_and_configuration_from_pod(pod),
+        ) else {
+            return;
+        };
+        let key = (namespace, instance_name);
+        let running_pods = self.running_broker_pods.entry(key.clone()).or_default();
+        if running {
+            running_pods.insert(pod_name);
+        } else {
+            running_pods.remove(&pod_name);
+        }
+        let count = running_pods.len() as i64;
+        if count == 0 {
+            self.running_broker_pods.remove(&key);
+        }
+        instance_action::report_running_brokers(&key.0, &key.1, count);
+    }
+
     /// This ensures that handle_running_pod is called only once for
     /// any Pod as it exits the Running phase.
     async fn handle_running_pod_if_needed(
@@ -341,7 +679,7 @@ impl BrokerPodWatcher {
     /// that instance and configuration services are only running when
     /// supported by Running broker Pods.
     async fn handle_non_running_pod(
-        &self,
+        &mut self,
         pod: &Pod,
         kube_interface: &impl KubeInterface,
     ) -> anyhow::Result<()> {
@@ -369,15 +707,106 @@ impl BrokerPodWatcher {
 
         // Only redeploy Pods that are managed by the Akri Controller (controlled by an Instance OwnerReference)
         if get_broker_pod_owner_kind(pod) == BrokerPodOwnerKind::Instance {
-            // Make sure instance has required Pods
-            if let Ok(instance) = kube_interface.find_instance(&instance_id, namespace).await {
-                super::instance_action::handle_instance_change(
-                    &instance,
-                    &super::instance_action::InstanceAction::Update,
-                    kube_interface,
-                )
-                .await?;
+            if let Some(at) =
+                self.record_ended_broker_pod(pod, &instance_id, namespace, Instant::now())
+            {
+                info!(
+                    "handle_non_running_pod - broker Pods of Instance {} repeatedly ended, recreating them in {:?}",
+                    instance_id,
+                    at.saturating_duration_since(Instant::now())
+                );
+                return Ok(());
             }
+            self.recreate_broker_pods(&instance_id, namespace, kube_interface)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// This records that a broker Pod of an Instance ended and, if its broker Pods repeatedly
+    /// ended within the recreation window, defers their recreation until the cooldown elapses.
+    /// Returns when they get recreated if deferred.
+    fn record_ended_broker_pod(
+        &mut self,
+        pod: &Pod,
+        instance_name: &str,
+        namespace: &str,
+        now: Instant,
+    ) -> Option<Instant> {
+        let key = (namespace.to_string(), instance_name.to_string());
+        // A Pod ending then getting deleted only counts once
+        let pod_id = pod
+            .metadata
+            .uid
+            .clone()
+            .or_else(|| pod.metadata.name.clone())
+            .unwrap_or_default();
+        let ended = self.ended_broker_pods.entry(key.clone()).or_default();
+        ended.retain(|(_, at)| now.saturating_duration_since(*at) < BROKER_RECREATION_WINDOW);
+        if !ended.iter().any(|(id, _)| *id == pod_id) {
+            ended.push((pod_id, now));
+        }
+        if let Some(at) = self.pending_recreations.get(&key) {
+            return Some(*at);
+        }
+        if self.broker_recreation_cooldown.is_zero()
+            || ended.len() < BROKER_RECREATION_COOLDOWN_THRESHOLD
+        {
+            return None;
+        }
+        let at = now + self.broker_recreation_cooldown;
+        self.pending_recreations.insert(key, at);
+        Some(at)
+    }
+
+    /// Returns when the next broker Pods in cooldown get recreated, if any
+    fn next_broker_recreation(&self) -> Option<Instant> {
+        self.pending_recreations.values().min().copied()
+    }
+
+    /// This recreates the broker Pods of the Instances whose cooldown elapsed
+    async fn recreate_due_broker_pods(
+        &mut self,
+        now: Instant,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        trace!("recreate_due_broker_pods - enter");
+        let due: Vec<(String, String)> = self
+            .pending_recreations
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in due {
+            self.pending_recreations.remove(&key);
+            let (namespace, instance_name) = key;
+            self.recreate_broker_pods(&instance_name, &namespace, kube_interface)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// This ensures that an Instance, if it still exists, has its required broker Pods
+    async fn recreate_broker_pods(
+        &self,
+        instance_name: &str,
+        namespace: &str,
+        kube_interface: &impl KubeInterface,
+    ) -> anyhow::Result<()> {
+        if let Ok(instance) = instance_cache::find_instance(
+            &self.instance_cache,
+            instance_name,
+            namespace,
+            kube_interface,
+        )
+        .await
+        {
+            super::instance_action::handle_instance_change(
+                &instance,
+                &super::instance_action::InstanceAction::Update,
+                kube_interface,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -493,9 +922,13 @@ impl BrokerPodWatcher {
                 return Ok(());
             }
         };
-        let instance = match kube_interface
-            .find_instance(&instance_name, namespace)
-            .await
+        let instance = match instance_cache::find_instance(
+            &self.instance_cache,
+            &instance_name,
+            namespace,
+            kube_interface,
+        )
+        .await
         {
             Ok(instance) => instance,
             _ => {
@@ -613,8 +1046,8 @@ impl BrokerPodWatcher {
                 instance_name.to_string(),
                 instance_uid.to_string(),
             );
-            // Try up to MAX_INSTANCE_UPDATE_TRIES times to update/create/get instance
-            for x in 0..MAX_INSTANCE_UPDATE_TRIES {
+            // Try up to max_instance_update_tries times to update/create/get instance
+            for x in 0..self.max_instance_update_tries {
                 match self
                     .create_or_update_service(
                         instance_name,
@@ -631,10 +1064,10 @@ impl BrokerPodWatcher {
                 {
                     Ok(_) => break,
                     Err(e) => {
-                        if x == (MAX_INSTANCE_UPDATE_TRIES - 1) {
+                        if x == (self.max_instance_update_tries - 1) {
                             return Err(e);
                         }
-                        random_delay().await;
+                        backoff(x).await;
                     }
                 }
             }
@@ -649,8 +1082,8 @@ impl BrokerPodWatcher {
                 configuration_name.to_string(),
                 configuration_uid.clone(),
             );
-            // Try up to MAX_INSTANCE_UPDATE_TRIES times to update/create/get instance
-            for x in 0..MAX_INSTANCE_UPDATE_TRIES {
+            // Try up to max_instance_update_tries times to update/create/get instance
+            for x in 0..self.max_instance_update_tries {
                 match self
                     .create_or_update_service(
                         instance_name,
@@ -667,10 +1100,10 @@ impl BrokerPodWatcher {
                 {
                     Ok(_) => break,
                     Err(e) => {
-                        if x == (MAX_INSTANCE_UPDATE_TRIES - 1) {
+                        if x == (self.max_instance_update_tries - 1) {
                             return Err(e);
                         }
-                        random_delay().await;
+                        backoff(x).await;
                     }
                 }
             }
@@ -685,7 +1118,9 @@ mod tests {
     use super::super::shared_test_utils::config_for_tests::PodList;
     use super::*;
     use akri_shared::{k8s::MockKubeInterface, os::file};
-    use k8s_openapi::api::core::v1::PodSpec;
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateWaiting, ContainerStatus, PodSpec,
+    };
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
 
     fn create_pods_with_phase(result_file: &'static str, specified_phase: &'static str) -> PodList {
@@ -783,6 +1218,206 @@ mod tests {
         );
     }
 
+    fn make_pending_pod_with_waiting_reason(reason: &str) -> Pod {
+        let pod_list = create_pods_with_phase(
+            "../test/json/running-pod-list-for-config-a-local.json",
+            "Pending",
+        );
+        let mut pod = pod_list.items.first().unwrap().clone();
+        pod.status.as_mut().unwrap().container_statuses = Some(vec![ContainerStatus {
+            name: "config-a-broker".to_string(),
+            image: "nginx:missing".to_string(),
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: Some(reason.to_string()),
+                    message: Some("Back-off pulling image".to_string()),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]);
+        pod
+    }
+
+    #[test]
+    fn test_get_image_pull_back_off() {
+        assert_eq!(
+            get_image_pull_back_off(&make_pending_pod_with_waiting_reason("ImagePullBackOff")),
+            Some(
+                "container config-a-broker cannot pull image nginx:missing: Back-off pulling image"
+                    .to_string()
+            )
+        );
+        // A single failed pull is not yet a persistent failure
+        assert_eq!(
+            get_image_pull_back_off(&make_pending_pod_with_waiting_reason("ErrImagePull")),
+            None
+        );
+        assert_eq!(
+            get_image_pull_back_off(&make_pod_with_owner_references(Vec::new())),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_pod_image_pull_back_off() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let pod = make_pending_pod_with_waiting_reason("ImagePullBackOff");
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_config(
+            &mut mock,
+            "config-a",
+            "config-a-namespace",
+            "../test/json/config-a.json",
+            false,
+        );
+        mock.expect_create_event()
+            .times(1)
+            .withf(|event, namespace| {
+                namespace == "config-a-namespace"
+                    && event.reason.as_deref() == Some(BROKER_IMAGE_PULL_BACK_OFF_EVENT_REASON)
+                    && event.involved_object.name.as_deref() == Some("config-a")
+            })
+            .returning(|_, _| Ok(()));
+        let metric = BROKER_POD_IMAGE_PULL_BACK_OFF_METRIC.with_label_values(&["config-a"]);
+        let previous_count = metric.get();
+
+        // The failure is only reported once while the Pod keeps backing off
+        let mut pod_watcher = BrokerPodWatcher::new();
+        for _ in 0..2 {
+            pod_watcher
+                .handle_pod(Event::Applied(pod.clone()), &mock, &mut false)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            pod_watcher.known_pods.get("config-a-b494b6-pod"),
+            Some(&PodState::ImagePullBackOff)
+        );
+        assert_eq!(metric.get(), previous_count + 1);
+    }
+
+    fn make_running_pod_with_restart_count(restart_count: i32) -> Pod {
+        let pod_list = create_pods_with_phase(
+            "../test/json/running-pod-list-for-config-a-local.json",
+            "Running",
+        );
+        let mut pod = pod_list.items.first().unwrap().clone();
+        pod.status.as_mut().unwrap().container_statuses = Some(vec![ContainerStatus {
+            name: "config-a-broker".to_string(),
+            image: "nginx:latest".to_string(),
+            restart_count,
+            state: Some(ContainerState {
+                waiting: Some(ContainerStateWaiting {
+                    reason: Some("CrashLoopBackOff".to_string()),
+                    message: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]);
+        pod
+    }
+
+    #[test]
+    fn test_get_quarantine_restart_threshold() {
+        let mut mock_env_var = akri_shared::os::env_var::MockEnvVarQuery::new();
+        mock_env_var
+            .expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(get_quarantine_restart_threshold(&mock_env_var), None);
+
+        let mut mock_env_var = akri_shared::os::env_var::MockEnvVarQuery::new();
+        mock_env_var
+            .expect_get_env_var()
+            .returning(|_| Ok("5".to_string()));
+        assert_eq!(get_quarantine_restart_threshold(&mock_env_var), Some(5));
+
+        for invalid in ["0", "-1", "five"] {
+            let mut mock_env_var = akri_shared::os::env_var::MockEnvVarQuery::new();
+            mock_env_var
+                .expect_get_env_var()
+                .returning(move |_| Ok(invalid.to_string()));
+            assert_eq!(get_quarantine_restart_threshold(&mock_env_var), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_instance_if_needed() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut pod_watcher = BrokerPodWatcher::new();
+        pod_watcher.quarantine_restart_threshold = Some(5);
+
+        // Below the threshold nothing happens
+        let mock = MockKubeInterface::new();
+        pod_watcher
+            .quarantine_instance_if_needed(&make_running_pod_with_restart_count(4), &mock)
+            .await
+            .unwrap();
+
+        // Once over the threshold, the Instance is quarantined and a Warning Event raised
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_instance(
+            &mut mock,
+            "config-a-b494b6",
+            "config-a-namespace",
+            "../test/json/local-instance.json",
+            false,
+        );
+        mock.expect_annotate_instance()
+            .times(1)
+            .withf(|annotations, name, namespace| {
+                name == "config-a-b494b6"
+                    && namespace == "config-a-namespace"
+                    && annotations
+                        .get(AKRI_QUARANTINED_ANNOTATION_NAME)
+                        .map(|r| r.as_str())
+                        == Some("broker Pod config-a-b494b6-pod restarted 5 times")
+            })
+            .returning(|_, _, _| Ok(()));
+        config_for_tests::configure_find_config(
+            &mut mock,
+            "config-a",
+            "config-a-namespace",
+            "../test/json/config-a.json",
+            false,
+        );
+        mock.expect_create_event()
+            .times(1)
+            .withf(|event, namespace| {
+                namespace == "config-a-namespace"
+                    && event.reason.as_deref() == Some(BROKER_QUARANTINED_EVENT_REASON)
+            })
+            .returning(|_, _| Ok(()));
+        pod_watcher
+            .quarantine_instance_if_needed(&make_running_pod_with_restart_count(5), &mock)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_instance_if_needed_already_quarantined() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut pod_watcher = BrokerPodWatcher::new();
+        pod_watcher.quarantine_restart_threshold = Some(5);
+        let mut mock = MockKubeInterface::new();
+        mock.expect_find_instance().times(1).returning(|_, _| {
+            let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+            let mut instance: Instance = serde_json::from_str(&instance_json).unwrap();
+            instance.metadata.annotations = Some(BTreeMap::from([(
+                AKRI_QUARANTINED_ANNOTATION_NAME.to_string(),
+                "broker crashed".to_string(),
+            )]));
+            Ok(instance)
+        });
+        mock.expect_annotate_instance().never();
+        mock.expect_create_event().never();
+        pod_watcher
+            .quarantine_instance_if_needed(&make_running_pod_with_restart_count(7), &mock)
+            .await
+            .unwrap();
+    }
+
     // Test that watcher errors on restarts unless it is the first restart (aka initial startup)
     #[tokio::test]
     async fn test_handle_watcher_restart() {
@@ -1098,6 +1733,259 @@ mod tests {
         )
     }
 
+    fn make_broker_pod_with_uid(uid: &str, phase: &'static str) -> Pod {
+        let pod_list = create_pods_with_phase(
+            "../test/json/running-pod-list-for-config-a-local.json",
+            phase,
+        );
+        let mut pod = pod_list.items.first().unwrap().clone();
+        pod.metadata.uid = Some(uid.to_string());
+        pod
+    }
+
+    fn make_broker_svcs_cleanup() -> CleanupServices {
+        CleanupServices {
+            find_svc_selector: "controller=akri.sh",
+            find_svc_result: "../test/json/running-svc-list-for-config-a-local.json",
+            cleanup_services: vec![
+                CleanupService {
+                    find_pod_selector: "akri.sh/configuration=config-a",
+                    find_pod_result: "../test/json/empty-list.json",
+                    remove_service: Some(RemoveService {
+                        remove_service_name: "config-a-svc",
+                        remove_service_namespace: "config-a-namespace",
+                    }),
+                },
+                CleanupService {
+                    find_pod_selector: "akri.sh/instance=config-a-b494b6",
+                    find_pod_result: "../test/json/empty-list.json",
+                    remove_service: Some(RemoveService {
+                        remove_service_name: "config-a-b494b6-svc",
+                        remove_service_namespace: "config-a-namespace",
+                    }),
+                },
+            ],
+            find_instance_id: "config-a-b494b6",
+            find_instance_namespace: "config-a-namespace",
+            find_instance_result: "",
+            find_instance_result_error: true,
+        }
+    }
+
+    #[test]
+    fn test_get_broker_recreation_cooldown() {
+        let mut mock_env_var = akri_shared::os::env_var::MockEnvVarQuery::new();
+        mock_env_var
+            .expect_get_env_var()
+            .returning(|_| Err(std::env::VarError::NotPresent));
+        assert_eq!(
+            get_broker_recreation_cooldown(&mock_env_var),
+            Duration::from_secs(DEFAULT_BROKER_RECREATION_COOLDOWN_SECS)
+        );
+
+        for (value, expected) in [("90", 90), ("0", 0), ("-1", 30), ("soon", 30)] {
+            let mut mock_env_var = akri_shared::os::env_var::MockEnvVarQuery::new();
+            mock_env_var
+                .expect_get_env_var()
+                .with(mockall::predicate::eq(
+                    BROKER_RECREATION_COOLDOWN_SECS_LABEL,
+                ))
+                .returning(move |_| Ok(value.to_string()));
+            assert_eq!(
+                get_broker_recreation_cooldown(&mock_env_var),
+                Duration::from_secs(expected)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_pod_deleted_twice_recreation_cooldown() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut pod_watcher = BrokerPodWatcher::new();
+        pod_watcher.quarantine_restart_threshold = None;
+        pod_watcher.broker_recreation_cooldown = Duration::from_secs(30);
+
+        // The first deleted broker Pod gets recreated right away
+        let mut mock = MockKubeInterface::new();
+        configure_for_handle_pod(
+            &mut mock,
+            &HandlePod {
+                running: None,
+                ended: Some(make_broker_svcs_cleanup()),
+            },
+        );
+        pod_watcher
+            .handle_pod(
+                Event::Deleted(make_broker_pod_with_uid("pod-uid-1", "Running")),
+                &mock,
+                &mut false,
+            )
+            .await
+            .unwrap();
+        assert!(pod_watcher.pending_recreations.is_empty());
+
+        // Its replacement shows up then gets deleted too, recreating it waits for the cooldown
+        let mock = MockKubeInterface::new();
+        pod_watcher
+            .handle_pod(
+                Event::Applied(make_broker_pod_with_uid("pod-uid-2", "Pending")),
+                &mock,
+                &mut false,
+            )
+            .await
+            .unwrap();
+        let mut mock = MockKubeInterface::new();
+        for cleanup_service in make_broker_svcs_cleanup().cleanup_services {
+            config_for_tests::configure_find_pods(
+                &mut mock,
+                cleanup_service.find_pod_selector,
+                cleanup_service.find_pod_result,
+                false,
+            );
+            let remove_service = cleanup_service.remove_service.unwrap();
+            config_for_tests::configure_remove_service(
+                &mut mock,
+                remove_service.remove_service_name,
+                remove_service.remove_service_namespace,
+            );
+        }
+        let deleted_at = Instant::now();
+        pod_watcher
+            .handle_pod(
+                Event::Deleted(make_broker_pod_with_uid("pod-uid-2", "Running")),
+                &mock,
+                &mut false,
+            )
+            .await
+            .unwrap();
+        let next_recreation = pod_watcher.next_broker_recreation().unwrap();
+        assert!(next_recreation >= deleted_at + Duration::from_secs(30));
+
+        // Nothing gets recreated before the cooldown elapses
+        let mock = MockKubeInterface::new();
+        pod_watcher
+            .recreate_due_broker_pods(deleted_at, &mock)
+            .await
+            .unwrap();
+        assert_eq!(pod_watcher.next_broker_recreation(), Some(next_recreation));
+
+        let mut mock = MockKubeInterface::new();
+        config_for_tests::configure_find_instance(
+            &mut mock,
+            "config-a-b494b6",
+            "config-a-namespace",
+            "",
+            true,
+        );
+        pod_watcher
+            .recreate_due_broker_pods(next_recreation, &mock)
+            .await
+            .unwrap();
+        assert_eq!(pod_watcher.next_broker_recreation(), None);
+    }
+
+    #[test]
+    fn test_record_running_broker_pod() {
+        let mut pod_watcher = BrokerPodWatcher::new();
+        let key = (
+            "config-a-namespace".to_string(),
+            "config-a-b494b6".to_string(),
+        );
+        let mut running = make_broker_pod_with_uid("pod-uid-1", "Running");
+        running.metadata.name = Some("pod-1".to_string());
+        let mut other_running = make_broker_pod_with_uid("pod-uid-2", "Running");
+        other_running.metadata.name = Some("pod-2".to_string());
+
+        pod_watcher.record_running_broker_pod(&running, true);
+        pod_watcher.record_running_broker_pod(&running, true);
+        pod_watcher.record_running_broker_pod(&other_running, true);
+        assert_eq!(pod_watcher.running_broker_pods[&key].len(), 2);
+
+        // Broker Pods that stop running are no longer counted
+        pod_watcher.record_running_broker_pod(&running, false);
+        assert_eq!(
+            pod_watcher.running_broker_pods[&key],
+            HashSet::from(["pod-2".to_string()])
+        );
+        pod_watcher.record_running_broker_pod(&other_running, false);
+        assert!(pod_watcher.running_broker_pods.is_empty());
+    }
+
+    #[test]
+    fn test_record_ended_broker_pod() {
+        let mut pod_watcher = BrokerPodWatcher::new();
+        pod_watcher.broker_recreation_cooldown = Duration::from_secs(30);
+        let now = Instant::now();
+        let ended = make_broker_pod_with_uid("pod-uid-1", "Failed");
+        assert_eq!(
+            pod_watcher.record_ended_broker_pod(
+                &ended,
+                "config-a-b494b6",
+                "config-a-namespace",
+                now
+            ),
+            None
+        );
+        // The same Pod getting deleted once ended doesn't count twice
+        assert_eq!(
+            pod_watcher.record_ended_broker_pod(
+                &ended,
+                "config-a-b494b6",
+                "config-a-namespace",
+                now
+            ),
+            None
+        );
+        // Pods ending outside of the window don't count either
+        let later = now + BROKER_RECREATION_WINDOW;
+        let replacement = make_broker_pod_with_uid("pod-uid-2", "Failed");
+        assert_eq!(
+            pod_watcher.record_ended_broker_pod(
+                &replacement,
+                "config-a-b494b6",
+                "config-a-namespace",
+                later
+            ),
+            None
+        );
+        let replacement = make_broker_pod_with_uid("pod-uid-3", "Failed");
+        assert_eq!(
+            pod_watcher.record_ended_broker_pod(
+                &replacement,
+                "config-a-b494b6",
+                "config-a-namespace",
+                later
+            ),
+            Some(later + Duration::from_secs(30))
+        );
+        // Other Instances are not affected
+        assert_eq!(
+            pod_watcher.record_ended_broker_pod(
+                &ended,
+                "config-a-359973",
+                "config-a-namespace",
+                later
+            ),
+            None
+        );
+
+        // The cooldown can be disabled
+        pod_watcher = BrokerPodWatcher::new();
+        pod_watcher.broker_recreation_cooldown = Duration::ZERO;
+        for uid in ["pod-uid-1", "pod-uid-2", "pod-uid-3"] {
+            assert_eq!(
+                pod_watcher.record_ended_broker_pod(
+                    &make_broker_pod_with_uid(uid, "Failed"),
+                    "config-a-b494b6",
+                    "config-a-namespace",
+                    now
+                ),
+                None
+            );
+        }
+    }
+
     #[tokio::test]
     async fn test_handle_pod_succeeded() {
         let _ = env_logger::builder().is_test(true).try_init();
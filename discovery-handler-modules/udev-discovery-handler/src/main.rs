@@ -1,11 +1,26 @@
 use akri_discovery_utils::discovery::discovery_handler::{
-    run_discovery_handler, REGISTER_AGAIN_CHANNEL_CAPACITY,
+    discover_once_if_requested, run_discovery_handler, validate_if_requested,
+    REGISTER_AGAIN_CHANNEL_CAPACITY,
+};
+use akri_udev::{
+    discovery_handler::{DiscoveryHandlerImpl, UdevDiscoveryDetails},
+    DISCOVERY_HANDLER_NAME, SHARED,
 };
-use akri_udev::{discovery_handler::DiscoveryHandlerImpl, DISCOVERY_HANDLER_NAME, SHARED};
 use log::info;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     env_logger::try_init()?;
+    // Check the discovery details of a Configuration without running discovery
+    if let Some(exit_code) = validate_if_requested(std::env::args(), UdevDiscoveryDetails::validate)
+    {
+        std::process::exit(exit_code);
+    }
+    // Run discovery a single time and print the discovered devices, without registering with an Agent
+    if let Some(exit_code) =
+        discover_once_if_requested(std::env::args(), &DiscoveryHandlerImpl::new(None)).await
+    {
+        std::process::exit(exit_code);
+    }
     info!("main - udev discovery handler started");
     let (register_sender, register_receiver) =
         tokio::sync::mpsc::channel(REGISTER_AGAIN_CHANNEL_CAPACITY);
@@ -1,5 +1,7 @@
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+    },
     v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse},
     DiscoverStream,
 };
@@ -11,7 +13,7 @@ use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default interval between two discovery passes, overridden by the Configuration's discoveryPollIntervalSecs
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
 /// File acting as an environment variable for testing discovery.
@@ -25,11 +27,58 @@ pub const OFFLINE: &str = "OFFLINE";
 
 /// DebugEchoDiscoveryDetails describes the necessary information needed to discover and filter debug echo devices.
 /// Specifically, it contains a list (`descriptions`) of fake devices to be discovered.
+/// Additional descriptions can be read from a file (`descriptionsFile`), one per line, the file is
+/// re-read on each discovery cycle so that the discovered devices can be changed at runtime.
+/// To simulate slow hardware, each discovery response can be delayed by `discoveryDelaySecs` seconds.
 /// This information is expected to be serialized in the discovery details map sent during Discover requests.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct DebugEchoDiscoveryDetails {
     pub descriptions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptions_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_delay_secs: Option<u64>,
+}
+
+impl DebugEchoDiscoveryDetails {
+    /// Returns the inline descriptions followed by the ones read from the descriptions file, if any.
+    /// An unreadable file is treated as empty.
+    fn get_descriptions(&self) -> Vec<String> {
+        let mut descriptions = self.descriptions.clone();
+        if let Some(path) = &self.descriptions_file {
+            match fs::read_to_string(path) {
+                Ok(content) => descriptions.extend(
+                    content
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty())
+                        .map(String::from),
+                ),
+                Err(e) => trace!("get_descriptions - could not read file {}: {}", path, e),
+            }
+        }
+        descriptions
+    }
+
+    /// Checks that some devices can be discovered, to be used with
+    /// [validate_discovery_details](akri_discovery_utils::discovery::discovery_handler::validate_discovery_details)
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.descriptions.is_empty() && self.descriptions_file.is_none() {
+            return Err(anyhow::format_err!(
+                "either descriptions or descriptionsFile must be set for any device to be discovered"
+            ));
+        }
+        if self.descriptions.iter().any(|d| d.trim().is_empty()) {
+            return Err(anyhow::format_err!("descriptions must not be empty"));
+        }
+        Ok(())
+    }
+
+    /// Returns how long to wait before emitting each discovery response
+    fn get_discovery_delay(&self) -> Duration {
+        Duration::from_secs(self.discovery_delay_secs.unwrap_or_default())
+    }
 }
 
 /// The DiscoveryHandlerImpl discovers a list of devices, named in its `descriptions`.
@@ -60,11 +109,16 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         let discovery_handler_config: DebugEchoDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let descriptions = discovery_handler_config.descriptions;
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let mut descriptions = Vec::new();
         let mut offline = fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH)
             .unwrap_or_default()
             .contains(OFFLINE);
         let mut first_loop = true;
+        let discovery_delay = discovery_handler_config.get_discovery_delay();
         tokio::spawn(async move {
             loop {
                 // Before each iteration, check if receiver has dropped
@@ -78,53 +132,64 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
 
                 let availability =
                     fs::read_to_string(DEBUG_ECHO_AVAILABILITY_CHECK_PATH).unwrap_or_default();
+                let current_descriptions = discovery_handler_config.get_descriptions();
                 trace!(
                     "discover -- debugEcho devices are online? {}",
                     !availability.contains(OFFLINE)
                 );
-                if (availability.contains(OFFLINE) && !offline) || offline && first_loop {
-                    if first_loop {
-                        first_loop = false;
-                    }
-                    // If the device is now offline, return an empty list of instance info
-                    offline = true;
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse {
+                let response =
+                    if (availability.contains(OFFLINE) && !offline) || offline && first_loop {
+                        if first_loop {
+                            first_loop = false;
+                        }
+                        // If the device is now offline, return an empty list of instance info
+                        offline = true;
+                        Some(DiscoverResponse {
                             devices: Vec::new(),
-                        }))
-                        .await
+                        })
+                    } else if (!availability.contains(OFFLINE) && offline)
+                        || !offline && (first_loop || current_descriptions != descriptions)
                     {
-                        error!("discover - for debugEcho failed to send discovery response with error {}", e);
-                        if let Some(sender) = register_sender {
-                            sender.send(()).await.unwrap();
+                        if first_loop {
+                            first_loop = false;
                         }
-                        break;
-                    }
-                } else if (!availability.contains(OFFLINE) && offline) || !offline && first_loop {
-                    if first_loop {
-                        first_loop = false;
-                    }
-                    offline = false;
-                    let devices = descriptions
-                        .iter()
-                        .map(|description| {
-                            let mut properties = HashMap::new();
-                            properties.insert(
-                                super::DEBUG_ECHO_DESCRIPTION_LABEL.to_string(),
-                                description.clone(),
-                            );
-                            Device {
-                                id: description.clone(),
-                                properties,
-                                mounts: Vec::default(),
-                                device_specs: Vec::default(),
+                        offline = false;
+                        descriptions = current_descriptions;
+                        let devices = descriptions
+                            .iter()
+                            .map(|description| {
+                                let mut properties = HashMap::new();
+                                properties.insert(
+                                    super::DEBUG_ECHO_DESCRIPTION_LABEL.to_string(),
+                                    description.clone(),
+                                );
+                                Device {
+                                    id: description.clone(),
+                                    properties,
+                                    mounts: Vec::default(),
+                                    device_specs: Vec::default(),
+                                    last_warning: None,
+                                    error: None,
+                                }
+                            })
+                            .collect::<Vec<Device>>();
+                        Some(DiscoverResponse { devices })
+                    } else {
+                        None
+                    };
+                if let Some(response) = response {
+                    // Simulate slow hardware, stopping early if the Agent stops listening
+                    tokio::select! {
+                        _ = sleep(discovery_delay) => {},
+                        _ = discovered_devices_sender.closed() => {
+                            error!("discover - channel closed during discovery delay ... attempting to re-register with Agent");
+                            if let Some(sender) = register_sender {
+                                sender.send(()).await.unwrap();
                             }
-                        })
-                        .collect::<Vec<Device>>();
-                    if let Err(e) = discovered_devices_sender
-                        .send(Ok(DiscoverResponse { devices }))
-                        .await
-                    {
+                            break;
+                        }
+                    }
+                    if let Err(e) = discovered_devices_sender.send(Ok(response)).await {
                         // TODO: consider re-registering here
                         error!("discover - for debugEcho failed to send discovery response with error {}", e);
                         if let Some(sender) = register_sender {
@@ -133,7 +198,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                sleep(discovery_interval).await;
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -145,7 +210,9 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use akri_discovery_utils::discovery::v0::DiscoverRequest;
+    use akri_discovery_utils::discovery::{
+        discovery_handler::validate_discovery_details, v0::DiscoverRequest,
+    };
     use akri_shared::akri::configuration::DiscoveryHandlerInfo;
 
     #[test]
@@ -173,6 +240,70 @@ mod tests {
         assert_eq!(&dh_config.descriptions[0], "foo1");
     }
 
+    #[test]
+    fn test_validate_discovery_details() {
+        let parsed = validate_discovery_details(
+            "descriptions: [foo0, foo1]",
+            DebugEchoDiscoveryDetails::validate,
+        )
+        .unwrap();
+        let dh_config: DebugEchoDiscoveryDetails = deserialize_discovery_details(&parsed).unwrap();
+        assert_eq!(dh_config.descriptions, vec!["foo0", "foo1"]);
+        assert!(validate_discovery_details(
+            "descriptionsFile: /tmp/descriptions.txt",
+            DebugEchoDiscoveryDetails::validate
+        )
+        .is_ok());
+
+        // Malformed YAML
+        assert!(validate_discovery_details(
+            "descriptions: [foo0",
+            DebugEchoDiscoveryDetails::validate
+        )
+        .is_err());
+        // Misspelled field, leaving no description
+        assert!(validate_discovery_details(
+            "description: [foo0]",
+            DebugEchoDiscoveryDetails::validate
+        )
+        .is_err());
+        // Nothing to discover
+        assert!(validate_discovery_details(
+            "descriptions: []",
+            DebugEchoDiscoveryDetails::validate
+        )
+        .is_err());
+        assert!(validate_discovery_details(
+            "descriptions: ['']",
+            DebugEchoDiscoveryDetails::validate
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_descriptions_from_file() {
+        let path = std::env::temp_dir().join("debug-echo-test-descriptions.txt");
+        fs::write(&path, "foo2\n\n  foo3  \n").unwrap();
+        let yaml = format!(
+            r#"
+            descriptions:
+              - "foo1"
+            descriptionsFile: "{}"
+        "#,
+            path.display()
+        );
+        let dh_config: DebugEchoDiscoveryDetails = deserialize_discovery_details(&yaml).unwrap();
+        assert_eq!(dh_config.get_descriptions(), vec!["foo1", "foo2", "foo3"]);
+
+        // Changes to the file are reflected on re-read
+        fs::write(&path, "foo4\n").unwrap();
+        assert_eq!(dh_config.get_descriptions(), vec!["foo1", "foo4"]);
+
+        // A missing file only yields the inline descriptions
+        fs::remove_file(&path).unwrap();
+        assert_eq!(dh_config.get_descriptions(), vec!["foo1"]);
+    }
+
     #[tokio::test]
     async fn test_discover_online_devices() {
         // Make devices "online"
@@ -197,6 +328,8 @@ mod tests {
             properties,
             mounts: Vec::default(),
             device_specs: Vec::default(),
+            last_warning: None,
+            error: None,
         };
         let discover_request = tonic::Request::new(DiscoverRequest {
             discovery_details: deserialized.discovery_details.clone(),
@@ -212,4 +345,65 @@ mod tests {
         assert_eq!(1, devices.len());
         assert_eq!(devices[0], device);
     }
+
+    #[tokio::test]
+    async fn test_discover_with_discovery_delay() {
+        // Make devices "online"
+        fs::write(DEBUG_ECHO_AVAILABILITY_CHECK_PATH, "").unwrap();
+        let debug_echo_yaml = r#"
+          name: debugEcho
+          discoveryDetails: |+
+              descriptions:
+              - "foo1"
+              discoveryDelaySecs: 1
+        "#;
+        let deserialized: DiscoveryHandlerInfo = serde_yaml::from_str(debug_echo_yaml).unwrap();
+        let discovery_handler = DiscoveryHandlerImpl::new(None);
+        let discover_request = tonic::Request::new(DiscoverRequest {
+            discovery_details: deserialized.discovery_details.clone(),
+            discovery_properties: HashMap::new(),
+        });
+        let start = std::time::Instant::now();
+        let mut stream = discovery_handler
+            .discover(discover_request)
+            .await
+            .unwrap()
+            .into_inner()
+            .into_inner();
+        let devices = stream.recv().await.unwrap().unwrap().devices;
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(1, devices.len());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_discover_poll_interval() {
+        // Make devices "online"
+        fs::write(DEBUG_ECHO_AVAILABILITY_CHECK_PATH, "").unwrap();
+        let path = std::env::temp_dir().join("debug-echo-test-poll-interval.txt");
+        fs::write(&path, "foo1\n").unwrap();
+        let discovery_handler = DiscoveryHandlerImpl::new(None);
+        let discover_request = tonic::Request::new(DiscoverRequest {
+            discovery_details: format!("descriptions: []\ndescriptionsFile: {}", path.display()),
+            discovery_properties: HashMap::from([(
+                akri_shared::akri::AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME.to_string(),
+                akri_discovery_utils::discovery::v0::ByteData {
+                    vec: Some(b"300".to_vec()),
+                },
+            )]),
+        });
+        let mut stream = discovery_handler
+            .discover(discover_request)
+            .await
+            .unwrap()
+            .into_inner()
+            .into_inner();
+        assert_eq!(stream.recv().await.unwrap().unwrap().devices.len(), 1);
+
+        // The change is only picked up upon the next pass, after the configured interval
+        fs::write(&path, "foo1\nfoo2\n").unwrap();
+        let start = tokio::time::Instant::now();
+        assert_eq!(stream.recv().await.unwrap().unwrap().devices.len(), 2);
+        assert_eq!(start.elapsed(), Duration::from_secs(300));
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -1,5 +1,9 @@
 use akri_discovery_utils::discovery::v0::ByteData;
-use std::collections::HashMap;
+use log::trace;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 /// Key name of device credential list in discoveryProperties
 pub const DEVICE_CREDENTIAL_LIST: &str = "device_credential_list";
@@ -51,6 +55,7 @@ struct CredentialRefData {
 #[derive(Default)]
 pub struct CredentialStore {
     credentials: HashMap<String, (String, Option<String>)>,
+    credentials_directory: Option<PathBuf>,
 }
 
 impl CredentialStore {
@@ -63,6 +68,30 @@ impl CredentialStore {
         store
     }
 
+    /// Also looks up credentials in the files of the given directory, see `get_for_device`
+    pub fn with_credentials_directory(mut self, credentials_directory: Option<PathBuf>) -> Self {
+        self.credentials_directory = credentials_directory;
+        self
+    }
+
+    /// Gets the credential of a camera from the file named after its uuid (or else its ip address, if known)
+    /// in the credentials directory, falling back to the credentials of the discovery properties.
+    /// Files are read on every call so that rotated credentials get picked up.
+    pub fn get_for_device(
+        &self,
+        uuid: &str,
+        ip_address: Option<&str>,
+    ) -> Option<(String, Option<String>)> {
+        self.credentials_directory
+            .as_deref()
+            .and_then(|directory| {
+                std::iter::once(uuid)
+                    .chain(ip_address)
+                    .find_map(|id| read_credential_file(directory, id))
+            })
+            .or_else(|| self.get(uuid))
+    }
+
     pub fn get(&self, uuid: &str) -> Option<(String, Option<String>)> {
         self.credentials
             .get(uuid)
@@ -213,6 +242,25 @@ fn parse_credential_ref_list(
         .collect()
 }
 
+/// Reads the `username:password` (or only `username`) credential file of the given camera id
+fn read_credential_file(directory: &Path, id: &str) -> Option<(String, Option<String>)> {
+    // The ids come from the network, do not let them point outside of the directory
+    if id.is_empty() || Path::new(id).file_name() != Some(std::ffi::OsStr::new(id)) {
+        return None;
+    }
+    let content = std::fs::read_to_string(directory.join(id)).ok()?;
+    trace!("read_credential_file - found credential file for {}", id);
+    let content = content.trim_end_matches(['\r', '\n']);
+    let (username, password) = match content.split_once(':') {
+        Some((username, password)) => (username, Some(password.to_string())),
+        None => (content, None),
+    };
+    if username.is_empty() {
+        return None;
+    }
+    Some((username.to_string(), password))
+}
+
 fn parse_list_data(key: &str, credential_data: &HashMap<String, ByteData>) -> Option<Vec<String>> {
     credential_data
         .get(key)
@@ -711,4 +759,59 @@ mod tests {
         let result = credential_store.get("not-exist-uuid");
         assert_eq!(result, Some(default_credential.1));
     }
+
+    #[test]
+    fn test_get_for_device_credentials_directory() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let directory =
+            std::env::temp_dir().join(format!("akri-onvif-credentials-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let credential_data =
+            generate_username_password_credential_data(vec![DeviceCredentialData {
+                id: "default".to_string(),
+                username: Some("secret_user".as_bytes()),
+                password: Some("secret_password".as_bytes()),
+            }]);
+        let credential_store = CredentialStore::new(&credential_data)
+            .with_credentials_directory(Some(directory.clone()));
+
+        // Without a file, the Secret-based credentials are used
+        assert_eq!(
+            credential_store.get_for_device("device-uuid", Some("10.0.0.1")),
+            Some((
+                "secret_user".to_string(),
+                Some("secret_password".to_string())
+            ))
+        );
+
+        // Files named after the ip address are used when there is none for the uuid
+        std::fs::write(directory.join("10.0.0.1"), "ip_user:ip_password\n").unwrap();
+        assert_eq!(
+            credential_store.get_for_device("device-uuid", Some("10.0.0.1")),
+            Some(("ip_user".to_string(), Some("ip_password".to_string())))
+        );
+        std::fs::write(directory.join("device-uuid"), "admin:password1").unwrap();
+        assert_eq!(
+            credential_store.get_for_device("device-uuid", Some("10.0.0.1")),
+            Some(("admin".to_string(), Some("password1".to_string())))
+        );
+
+        // Rotated credentials are used on the next query
+        std::fs::write(directory.join("device-uuid"), "admin:pass:word2").unwrap();
+        assert_eq!(
+            credential_store.get_for_device("device-uuid", Some("10.0.0.1")),
+            Some(("admin".to_string(), Some("pass:word2".to_string())))
+        );
+
+        // Ids can't point outside of the directory
+        assert_eq!(
+            credential_store.get_for_device("../device-uuid", None),
+            Some((
+                "secret_user".to_string(),
+                Some("secret_password".to_string())
+            ))
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
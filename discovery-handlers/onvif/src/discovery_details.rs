@@ -0,0 +1,57 @@
+use akri_discovery_utils::filtering::FilterList;
+
+/// This defines the ONVIF data stored in the Configuration
+/// CRD
+///
+/// The ONVIF discovery handler is structured to store a filter list for
+/// ip addresses, mac addresses, and ONVIF scopes.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OnvifDiscoveryDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_addresses: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_addresses: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<FilterList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuids: Option<FilterList>,
+    #[serde(default = "default_discovery_timeout_seconds")]
+    pub discovery_timeout_seconds: i32,
+    /// Whether to report how long querying each camera's ip and mac address took, as the
+    /// `AKRI_DEVICE_PROBE_LATENCY_MS` device property the Agent records as a metric
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub report_probe_latency: bool,
+    /// Directory of credential files, each named after a camera's uuid or ip address and containing
+    /// `username:password`. They take precedence over the credentials of the discovery properties and
+    /// are read on every query, so that rotated credentials are used without restarting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_directory: Option<String>,
+    /// Maximum number of connections the cameras are probed over at once, they are kept open to be
+    /// reused by the following probes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_pool_size: Option<usize>,
+    /// Time after which a request to a camera is terminated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_request_timeout_ms: Option<u64>,
+}
+
+fn default_discovery_timeout_seconds() -> i32 {
+    1
+}
+
+impl Default for OnvifDiscoveryDetails {
+    fn default() -> Self {
+        OnvifDiscoveryDetails {
+            ip_addresses: None,
+            mac_addresses: None,
+            scopes: None,
+            uuids: None,
+            discovery_timeout_seconds: default_discovery_timeout_seconds(),
+            report_probe_latency: false,
+            credentials_directory: None,
+            http_pool_size: None,
+            http_request_timeout_ms: None,
+        }
+    }
+}
@@ -1,53 +1,32 @@
 use super::credential_store::CredentialStore;
+pub use super::discovery_details::OnvifDiscoveryDetails;
 use super::discovery_impl::util;
 use super::discovery_utils::{
-    OnvifQuery, OnvifQueryImpl, ONVIF_DEVICE_IP_ADDRESS_LABEL_ID,
-    ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID, ONVIF_DEVICE_SERVICE_URL_LABEL_ID,
-    ONVIF_DEVICE_UUID_LABEL_ID,
+    OnvifQuery, OnvifQueryImpl, DEFAULT_HTTP_POOL_SIZE, DEFAULT_HTTP_REQUEST_TIMEOUT_MS,
+    ONVIF_DEVICE_IP_ADDRESS_LABEL_ID, ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID,
+    ONVIF_DEVICE_SERVICE_URL_LABEL_ID, ONVIF_DEVICE_UUID_LABEL_ID,
 };
-use akri_discovery_utils::{
-    discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-        v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
-        },
-        DiscoverStream,
+use akri_discovery_utils::discovery::{
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, set_probe_latency,
+        DISCOVERED_DEVICES_CHANNEL_CAPACITY,
     },
-    filtering::FilterList,
+    v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse},
+    DiscoverStream,
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use tokio::{sync::mpsc, time::sleep};
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default interval between two discovery passes, overridden by the Configuration's discoveryPollIntervalSecs
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
-/// This defines the ONVIF data stored in the Configuration
-/// CRD
-///
-/// The ONVIF discovery handler is structured to store a filter list for
-/// ip addresses, mac addresses, and ONVIF scopes.
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct OnvifDiscoveryDetails {
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub ip_addresses: Option<FilterList>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub mac_addresses: Option<FilterList>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub scopes: Option<FilterList>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub uuids: Option<FilterList>,
-    #[serde(default = "default_discovery_timeout_seconds")]
-    pub discovery_timeout_seconds: i32,
-}
-
-fn default_discovery_timeout_seconds() -> i32 {
-    1
-}
-
 /// `DiscoveryHandlerImpl` discovers the onvif instances as described by the `OnvifDiscoveryDetails` filters `ip_addresses`,
 /// `mac_addresses`, and `scopes`.
 /// The instances it discovers are always shared.
@@ -76,8 +55,27 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         let discovery_handler_config: OnvifDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
-        let credential_store = CredentialStore::new(&discover_request.discovery_properties);
-        let onvif_query = OnvifQueryImpl::new(credential_store);
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let credential_store = CredentialStore::new(&discover_request.discovery_properties)
+            .with_credentials_directory(
+                discovery_handler_config
+                    .credentials_directory
+                    .as_ref()
+                    .map(PathBuf::from),
+            );
+        let onvif_query = OnvifQueryImpl::new(credential_store).with_http_pool(
+            discovery_handler_config
+                .http_pool_size
+                .unwrap_or(DEFAULT_HTTP_POOL_SIZE),
+            Duration::from_millis(
+                discovery_handler_config
+                    .http_request_timeout_ms
+                    .unwrap_or(DEFAULT_HTTP_REQUEST_TIMEOUT_MS),
+            ),
+        );
         tokio::spawn(async move {
             let mut previous_cameras = HashMap::new();
             let mut filtered_camera_devices = HashMap::new();
@@ -145,7 +143,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                sleep(discovery_interval).await;
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -173,14 +171,21 @@ async fn apply_filters(
         return None;
     }
 
-    let ip_and_mac = onvif_query
+    let probe_start = Instant::now();
+    let ip_and_mac_result = onvif_query
         .get_device_ip_and_mac_address(device_service_uri, device_uuid)
-        .await
-        .map_err(|e| {
+        .await;
+    let probe_latency = probe_start.elapsed();
+    let (ip_and_mac, error) = match ip_and_mac_result {
+        Ok(ip_and_mac) => (Some(ip_and_mac), None),
+        Err(e) => {
             error!("apply_filters - error getting ip and mac address: {}", e);
-            e
-        })
-        .ok();
+            (
+                None,
+                Some(format!("failed to get ip and mac address: {}", e)),
+            )
+        }
+    };
     // Evaluate camera ip address against ip filter if provided
     // use case-insensitive comparison in case of IPv6 is used
     let ip_address_as_vec = ip_and_mac.as_ref().map(|(ip, _)| vec![ip.clone()]);
@@ -214,22 +219,25 @@ async fn apply_filters(
         properties.insert(ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID.into(), mac_address);
     }
 
-    Some((
-        device_service_uri.to_string(),
-        Device {
-            id: service_uri_and_uuid_joined,
-            properties,
-            mounts: Vec::default(),
-            device_specs: Vec::default(),
-        },
-    ))
+    let mut device = Device {
+        id: service_uri_and_uuid_joined,
+        properties,
+        mounts: Vec::default(),
+        device_specs: Vec::default(),
+        last_warning: None,
+        error,
+    };
+    if discovery_handler_config.report_probe_latency {
+        set_probe_latency(&mut device, probe_latency);
+    }
+    Some((device_service_uri.to_string(), device))
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::discovery_utils::MockOnvifQuery;
     use super::*;
-    use akri_discovery_utils::filtering::FilterType;
+    use akri_discovery_utils::filtering::{FilterList, FilterType, MatchType};
 
     #[derive(Clone)]
     struct IpAndMac {
@@ -285,6 +293,8 @@ mod tests {
                 properties,
                 mounts: Vec::default(),
                 device_specs: Vec::default(),
+                last_warning: None,
+                error: None,
             },
         )
     }
@@ -297,6 +307,19 @@ mod tests {
         let serialized = serde_json::to_string(&dh_config).unwrap();
         let expected_deserialized = r#"{"discoveryTimeoutSeconds":1}"#;
         assert_eq!(expected_deserialized, serialized);
+        // Default values match the ones of empty discovery details
+        assert_eq!(
+            expected_deserialized,
+            serde_json::to_string(&OnvifDiscoveryDetails::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_http_pool() {
+        let dh_config: OnvifDiscoveryDetails =
+            deserialize_discovery_details("httpPoolSize: 4\nhttpRequestTimeoutMs: 500").unwrap();
+        assert_eq!(dh_config.http_pool_size, Some(4));
+        assert_eq!(dh_config.http_request_timeout_ms, Some(500));
     }
 
     #[tokio::test]
@@ -313,13 +336,7 @@ mod tests {
         let mut mock = MockOnvifQuery::new();
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
-        let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
-        };
+        let onvif_config = OnvifDiscoveryDetails::default();
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
             .unwrap();
@@ -330,6 +347,40 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_apply_filters_report_probe_latency() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mock_uri = "device_uri";
+        let mock_uuid = "device_uuid";
+        let mock_ip_and_mac = IpAndMac {
+            ip: "mock.ip",
+            mac: "mock:mac",
+        };
+
+        let mut mock = MockOnvifQuery::new();
+        configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
+
+        let onvif_config = OnvifDiscoveryDetails {
+            report_probe_latency: true,
+            ..Default::default()
+        };
+        let (uri, mut device) = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
+            .await
+            .unwrap();
+
+        let latency = device
+            .properties
+            .remove("AKRI_DEVICE_PROBE_LATENCY_MS")
+            .expect("probe latency should be reported");
+        assert!(latency.parse::<u128>().is_ok());
+        // Apart from the latency, the device is unchanged
+        assert_eq!(
+            expected_device(mock_uri, mock_uuid, Some(mock_ip_and_mac)),
+            (uri, device)
+        );
+    }
+
     #[tokio::test]
     async fn test_apply_filters_no_filters_get_ip_mac_address_fail() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -344,18 +395,18 @@ mod tests {
             Err(String::from("mock get_device_ip_and_mac_address failure")),
         );
 
-        let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
-        };
+        let onvif_config = OnvifDiscoveryDetails::default();
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
             .unwrap();
 
-        assert_eq!(expected_device(mock_uri, mock_uuid, None), instance);
+        // The device is still discovered, reporting why its ip and mac address are missing
+        let mut expected = expected_device(mock_uri, mock_uuid, None);
+        expected.1.error = Some(
+            "failed to get ip and mac address: mock get_device_ip_and_mac_address failure"
+                .to_string(),
+        );
+        assert_eq!(expected, instance);
     }
 
     #[tokio::test]
@@ -377,11 +428,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_ip.to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -407,11 +456,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_ip.to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -441,11 +488,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist.ip".to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -470,11 +515,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["mock.i".to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -499,11 +542,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist.ip".to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -534,11 +575,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_ip.to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -563,11 +602,9 @@ mod tests {
             ip_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["mock.i".to_string()],
+                match_type: MatchType::Exact,
             }),
-            mac_addresses: None,
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -595,14 +632,12 @@ mod tests {
         );
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_string()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -625,14 +660,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_string()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -659,14 +692,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist:mac".to_string()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -688,14 +719,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist:mac".to_string()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -723,14 +752,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_mac.to_string()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -753,14 +780,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_mac.to_uppercase()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -788,14 +813,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
             mac_addresses: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_mac.to_uppercase()],
+                match_type: MatchType::Exact,
             }),
-            scopes: None,
-            uuids: None,
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -817,14 +840,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_uuid.to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -845,14 +866,12 @@ mod tests {
 
         let mock = MockOnvifQuery::new();
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["nonexist-uuid".to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -868,14 +887,12 @@ mod tests {
 
         let mock = MockOnvifQuery::new();
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["device_uui".to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -891,14 +908,12 @@ mod tests {
 
         let mock = MockOnvifQuery::new();
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_uuid.to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -920,14 +935,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["nonexist-uuid".to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -954,14 +967,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["device_uui".to_string()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -988,14 +999,12 @@ mod tests {
         configure_scenario(&mut mock, mock_uri, Ok(mock_ip_and_mac.clone()));
 
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Include,
                 items: vec![mock_uuid.to_uppercase()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         let instance = apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
@@ -1016,14 +1025,12 @@ mod tests {
 
         let mock = MockOnvifQuery::new();
         let onvif_config = OnvifDiscoveryDetails {
-            ip_addresses: None,
-            mac_addresses: None,
-            scopes: None,
             uuids: Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![mock_uuid.to_uppercase()],
+                match_type: MatchType::Exact,
             }),
-            discovery_timeout_seconds: 1,
+            ..Default::default()
         };
         assert!(apply_filters(&onvif_config, mock_uri, mock_uuid, &mock)
             .await
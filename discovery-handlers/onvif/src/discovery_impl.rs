@@ -142,7 +142,7 @@ pub mod common {
 pub mod util {
     use super::super::discovery_utils::{OnvifQuery, OnvifQueryImpl};
     use super::{common, probe_types, to_deserialize, to_serialize};
-    use akri_discovery_utils::filtering::{FilterList, FilterType};
+    use akri_discovery_utils::filtering::{FilterList, FilterType, MatchType};
     use log::{error, info, trace};
     use std::collections::HashMap;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -193,6 +193,29 @@ pub mod util {
         }
     }
 
+    /// ONVIF scope prefix advertising the hardware of a device
+    const ONVIF_HARDWARE_SCOPE_PREFIX: &str = "onvif://www.onvif.org/hardware/";
+
+    /// Scopes must match the pattern exactly, except for hardware scopes where the pattern's
+    /// hardware string only needs to be a case-insensitive substring of the advertised one
+    /// (e.g. `onvif://www.onvif.org/hardware/axis` matches `onvif://www.onvif.org/hardware/AXIS-P1375`).
+    fn scope_matches(scope: &str, pattern: &str) -> bool {
+        let hardware = scope
+            .get(..ONVIF_HARDWARE_SCOPE_PREFIX.len())
+            .filter(|p| p.eq_ignore_ascii_case(ONVIF_HARDWARE_SCOPE_PREFIX))
+            .map(|_| &scope[ONVIF_HARDWARE_SCOPE_PREFIX.len()..]);
+        let hardware_pattern = pattern
+            .get(..ONVIF_HARDWARE_SCOPE_PREFIX.len())
+            .filter(|p| p.eq_ignore_ascii_case(ONVIF_HARDWARE_SCOPE_PREFIX))
+            .map(|_| &pattern[ONVIF_HARDWARE_SCOPE_PREFIX.len()..]);
+        match (hardware, hardware_pattern) {
+            (Some(hardware), Some(hardware_pattern)) => hardware
+                .to_lowercase()
+                .contains(&hardware_pattern.to_lowercase()),
+            _ => scope == pattern,
+        }
+    }
+
     fn get_scope_filtered_uris_from_discovery_response(
         discovery_response: &str,
         scopes: Option<&FilterList>,
@@ -219,7 +242,7 @@ pub mod util {
             .iter()
             .filter(|probe_match| {
                 !execute_filter(scopes, Some(&probe_match.scopes), |scope, pattern| {
-                    scope.split_whitespace().any(|s| s == pattern)
+                    scope.split_whitespace().any(|s| scope_matches(s, pattern))
                 })
             })
             .flat_map(|probe_match| {
@@ -400,6 +423,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name/NVT".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -424,6 +448,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name/NVT".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -442,6 +467,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name/NVT123".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -460,6 +486,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name/NVT123".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -484,6 +511,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Include,
                 items: vec!["onvif://www.onvif.org/name".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -502,6 +530,7 @@ pub mod util {
             let filter_list = FilterList {
                 action: FilterType::Exclude,
                 items: vec!["onvif://www.onvif.org/name".to_string()],
+                match_type: MatchType::Exact,
             };
             let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
             let device_uuid = "device_uuid";
@@ -519,6 +548,76 @@ pub mod util {
             );
         }
 
+        #[test]
+        fn test_get_scope_filtered_uris_from_discovery_response_exclude_hardware_scope() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            // The device advertises multiple scopes, only the hardware one matches the Exclude rule
+            let filter_list = FilterList {
+                action: FilterType::Exclude,
+                items: vec!["onvif://www.onvif.org/hardware/ipc".to_string()],
+                match_type: MatchType::Exact,
+            };
+            let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
+            let device_uuid = "device_uuid";
+            let response = get_expected_probe_match_message(device_uuid, &uris);
+            assert!(get_scope_filtered_uris_from_discovery_response(
+                &response,
+                Some(filter_list).as_ref()
+            )
+            .is_empty());
+        }
+
+        #[test]
+        fn test_get_scope_filtered_uris_from_discovery_response_include_hardware_scope() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let filter_list = FilterList {
+                action: FilterType::Include,
+                items: vec!["onvif://www.onvif.org/hardware/ipc-MODEL".to_string()],
+                match_type: MatchType::Exact,
+            };
+            let uris = vec!["uri_one".to_string(), "uri_two".to_string()];
+            let device_uuid = "device_uuid";
+            let expected_uris = uris
+                .iter()
+                .map(|u| (u.to_string(), device_uuid.to_string()))
+                .collect::<Vec<_>>();
+            let response = get_expected_probe_match_message(device_uuid, &uris);
+            assert_eq!(
+                expected_uris,
+                get_scope_filtered_uris_from_discovery_response(
+                    &response,
+                    Some(filter_list).as_ref()
+                )
+            );
+        }
+
+        #[test]
+        fn test_scope_matches() {
+            assert!(scope_matches(
+                "onvif://www.onvif.org/hardware/AXIS-P1375",
+                "onvif://www.onvif.org/hardware/axis"
+            ));
+            assert!(!scope_matches(
+                "onvif://www.onvif.org/hardware/AXIS-P1375",
+                "onvif://www.onvif.org/hardware/hikvision"
+            ));
+            // Non hardware scopes are matched exactly
+            assert!(scope_matches(
+                "onvif://www.onvif.org/name/NVT",
+                "onvif://www.onvif.org/name/NVT"
+            ));
+            assert!(!scope_matches(
+                "onvif://www.onvif.org/name/NVT",
+                "onvif://www.onvif.org/name/nvt"
+            ));
+            assert!(!scope_matches(
+                "onvif://www.onvif.org/name/NVT",
+                "onvif://www.onvif.org/name"
+            ));
+        }
+
         #[test]
         fn test_get_onvif_device_id() {
             // expect either no prefix or the prefix is
@@ -578,6 +677,7 @@ pub mod util {
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec![],
+                match_type: MatchType::Exact,
             });
 
             assert!(!execute_filter(filter_list.as_ref(), None, predicate_match,));
@@ -609,6 +709,7 @@ pub mod util {
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                match_type: MatchType::Exact,
             });
 
             assert!(execute_filter(filter_list.as_ref(), None, predicate_match,));
@@ -621,6 +722,7 @@ pub mod util {
             let filter_list = Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                match_type: MatchType::Exact,
             });
             assert!(execute_filter(filter_list.as_ref(), None, predicate_match,));
             assert!(execute_filter(
@@ -638,6 +740,7 @@ pub mod util {
             let filter_list = Some(FilterList {
                 action: FilterType::Exclude,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                match_type: MatchType::Exact,
             });
 
             assert!(execute_filter(
@@ -659,6 +762,7 @@ pub mod util {
             let filter_list = Some(FilterList {
                 action: FilterType::Include,
                 items: vec!["foo".to_string(), "bar".to_string()],
+                match_type: MatchType::Exact,
             });
             assert!(!execute_filter(
                 filter_list.as_ref(),
@@ -6,9 +6,14 @@ use hyper::Request;
 use log::trace;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use std::io::{Error, ErrorKind};
+use std::{
+    io::{Error, ErrorKind},
+    sync::Arc,
+    time::Duration,
+};
 use sxd_document::{parser, Package};
 use sxd_xpath::Value;
+use tokio::sync::Semaphore;
 
 pub const ONVIF_DEVICE_SERVICE_URL_LABEL_ID: &str = "ONVIF_DEVICE_SERVICE_URL";
 pub const ONVIF_DEVICE_IP_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_IP_ADDRESS";
@@ -16,6 +21,10 @@ pub const ONVIF_DEVICE_MAC_ADDRESS_LABEL_ID: &str = "ONVIF_DEVICE_MAC_ADDRESS";
 pub const ONVIF_DEVICE_UUID_LABEL_ID: &str = "ONVIF_DEVICE_UUID";
 pub const MEDIA_WSDL: &str = "http://www.onvif.org/ver10/media/wsdl";
 pub const DEVICE_WSDL: &str = "http://www.onvif.org/ver10/device/wsdl";
+/// Default number of connections the cameras are probed over at once
+pub const DEFAULT_HTTP_POOL_SIZE: usize = 16;
+/// Default time after which a request to a camera is terminated
+pub const DEFAULT_HTTP_REQUEST_TIMEOUT_MS: u64 = 1000;
 
 /// OnvifQuery can access ONVIF properties given an ONVIF camera's device service url.
 ///
@@ -48,11 +57,21 @@ pub trait OnvifQuery {
 #[derive(Default)]
 pub struct OnvifQueryImpl {
     credential_store: CredentialStore,
+    http: HttpRequest,
 }
 
 impl OnvifQueryImpl {
     pub fn new(credential_store: CredentialStore) -> Self {
-        Self { credential_store }
+        Self {
+            credential_store,
+            http: Default::default(),
+        }
+    }
+
+    /// Sets the size of the connection pool shared by all the queries, and the timeout of each request
+    pub fn with_http_pool(mut self, pool_size: usize, request_timeout: Duration) -> Self {
+        self.http = HttpRequest::new(pool_size, request_timeout);
+        self
     }
 }
 
@@ -64,9 +83,14 @@ impl OnvifQuery for OnvifQueryImpl {
         service_url: &str,
         device_uuid: &str,
     ) -> Result<(String, String), anyhow::Error> {
-        let credential = self.credential_store.get(device_uuid);
-        let http = HttpRequest {};
-        inner_get_device_ip_and_mac_address(service_url, credential, &http).await
+        let ip_address = service_url.parse::<hyper::Uri>().ok().and_then(|uri| {
+            uri.host()
+                .map(|host| host.trim_matches(['[', ']']).to_string())
+        });
+        let credential = self
+            .credential_store
+            .get_for_device(device_uuid, ip_address.as_deref());
+        inner_get_device_ip_and_mac_address(service_url, credential, &self.http).await
     }
 
     /// Gets specific service, like media, from a given ONVIF camera
@@ -75,14 +99,12 @@ impl OnvifQuery for OnvifQueryImpl {
         url: &str,
         service: &str,
     ) -> Result<String, anyhow::Error> {
-        let http = HttpRequest {};
-        inner_get_device_service_uri(url, service, &http).await
+        inner_get_device_service_uri(url, service, &self.http).await
     }
 
     /// Gets the list of streaming profiles for a given ONVIF camera
     async fn get_device_profiles(&self, url: &str) -> Result<Vec<String>, anyhow::Error> {
-        let http = HttpRequest {};
-        inner_get_device_profiles(url, &http).await
+        inner_get_device_profiles(url, &self.http).await
     }
 
     /// Gets the streaming uri for a given ONVIF camera's profile
@@ -91,15 +113,13 @@ impl OnvifQuery for OnvifQueryImpl {
         url: &str,
         profile_token: &str,
     ) -> Result<String, anyhow::Error> {
-        let http = HttpRequest {};
-        inner_get_device_profile_streaming_uri(url, profile_token, &http).await
+        inner_get_device_profile_streaming_uri(url, profile_token, &self.http).await
     }
 
     /// Calls the publically accessible GetSystemDateAndTime endpoint to determine
     /// that the camera is responsive. If responsive, returns the responding url.
     async fn is_device_responding(&self, url: &str) -> Result<String, anyhow::Error> {
-        let http = HttpRequest {};
-        inner_is_device_responding(url, &http).await
+        inner_is_device_responding(url, &self.http).await
     }
 }
 
@@ -113,9 +133,38 @@ trait Http {
         -> Result<Package, anyhow::Error>;
 }
 
-struct HttpRequest {}
+/// Sends the requests over a pool of at most `pool_size` connections, shared by its clones
+///
+/// Akri has no HTTP discovery handler, so the bounded connection pool lives here: the ONVIF discovery
+/// handler is the one probing many endpoints over HTTP.
+#[derive(Clone)]
+struct HttpRequest {
+    client: hyper::Client<hyper::client::HttpConnector>,
+    permits: Arc<Semaphore>,
+    request_timeout: Duration,
+}
+
+impl Default for HttpRequest {
+    fn default() -> Self {
+        HttpRequest::new(
+            DEFAULT_HTTP_POOL_SIZE,
+            Duration::from_millis(DEFAULT_HTTP_REQUEST_TIMEOUT_MS),
+        )
+    }
+}
 
 impl HttpRequest {
+    fn new(pool_size: usize, request_timeout: Duration) -> Self {
+        let pool_size = pool_size.max(1);
+        HttpRequest {
+            client: hyper::Client::builder()
+                .pool_max_idle_per_host(pool_size)
+                .build_http(),
+            permits: Arc::new(Semaphore::new(pool_size)),
+            request_timeout,
+        }
+    }
+
     /// This converts an http response body into an sxd_document::Package
     fn handle_request_body(body: &str) -> Result<Package, anyhow::Error> {
         let xml_as_tree = match parser::parse(body) {
@@ -154,12 +203,10 @@ impl Http for HttpRequest {
             .header("CONTENT-TYPE", full_mime)
             .body(msg.to_string().into())
             .expect("infallible");
-        // terminate a request if it takes over a second
-        let response = tokio::time::timeout(
-            std::time::Duration::from_secs(1),
-            hyper::Client::new().request(request),
-        )
-        .await??;
+        // never open more connections than the pool size, waiting for one to be released
+        let _permit = self.permits.acquire().await?;
+        let response =
+            tokio::time::timeout(self.request_timeout, self.client.request(request)).await??;
         if response.status() != 200 {
             return Err(anyhow::format_err!(
                 "Received a response status of {}, expected 200",
@@ -665,6 +712,37 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_http_request_pool() {
+        let query = OnvifQueryImpl::new(CredentialStore::default())
+            .with_http_pool(2, Duration::from_millis(50));
+        assert_eq!(query.http.permits.available_permits(), 2);
+        assert_eq!(query.http.request_timeout, Duration::from_millis(50));
+        // A pool holds at least one connection
+        assert_eq!(
+            HttpRequest::new(0, Duration::from_secs(1))
+                .permits
+                .available_permits(),
+            1
+        );
+
+        // Clones share the pool, so a request holds a connection of every clone
+        let http = query.http.clone();
+        assert!(Arc::ptr_eq(&http.permits, &query.http.permits));
+        let permit = http.permits.acquire().await.unwrap();
+        assert_eq!(query.http.permits.available_permits(), 1);
+        drop(permit);
+
+        // A camera that never responds is given up on after the request timeout, releasing
+        // the connection
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/onvif", listener.local_addr().unwrap());
+        let start = std::time::Instant::now();
+        assert!(query.is_device_responding(&url).await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(query.http.permits.available_permits(), 2);
+    }
+
     #[test]
     fn test_http_handle_request_body_no_panic() {
         assert!(HttpRequest::handle_request_body("\r\n").is_err());
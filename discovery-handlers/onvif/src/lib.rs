@@ -1,11 +1,18 @@
+#[cfg(feature = "discovery-handler")]
 mod credential_store;
+pub mod discovery_details;
+#[cfg(feature = "discovery-handler")]
 pub mod discovery_handler;
+#[cfg(feature = "discovery-handler")]
 mod discovery_impl;
+#[cfg(feature = "discovery-handler")]
 mod discovery_utils;
+#[cfg(feature = "discovery-handler")]
 mod username_token;
 
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "discovery-handler")]
 #[macro_use]
 extern crate yaserde_derive;
 
@@ -0,0 +1,95 @@
+use akri_discovery_utils::filtering::FilterList;
+
+/// Methods for discovering OPC UA Servers
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum OpcuaDiscoveryMethod {
+    Standard(StandardOpcuaDiscovery),
+    DnsSd(DnsSdOpcuaDiscovery),
+    // TODO: add scan
+}
+
+/// Discovers OPC UA Servers and/or LocalDiscoveryServers at specified DiscoveryURLs.
+/// If the DiscoveryURL is for a LocalDiscoveryServer, it will discover all Servers
+/// that have registered with that LocalDiscoveryServer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StandardOpcuaDiscovery {
+    #[serde(default = "lds_discovery_url", skip_serializing_if = "Vec::is_empty")]
+    pub discovery_urls: Vec<String>,
+}
+
+/// Discovers OPC UA Servers and/or LocalDiscoveryServers advertised through DNS-SD as services of the
+/// specified type. Each resolved service is verified and, for LocalDiscoveryServers, expanded the same
+/// way as the DiscoveryURLs of standard discovery.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsSdOpcuaDiscovery {
+    #[serde(default = "dns_sd_service_type")]
+    pub service_type: String,
+}
+
+/// If no service type is specified, uses the one OPC UA applications supporting
+/// the opc.tcp protocol are advertised with
+fn dns_sd_service_type() -> String {
+    "_opcua-tcp._tcp".to_string()
+}
+
+/// If no DiscoveryURLs are specified, uses the OPC UA default DiscoveryURL
+/// for the LocalDiscoveryServer running on the host
+fn lds_discovery_url() -> Vec<String> {
+    vec!["opc.tcp://localhost:4840/".to_string()]
+}
+
+/// Security policies the discovery client can secure its channel to DiscoveryEndpoints with.
+/// Only the non-deprecated policies of the OPC UA specification are supported.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OpcuaSecurityPolicy {
+    #[default]
+    None,
+    Basic256Sha256,
+    Aes128Sha256RsaOaep,
+    Aes256Sha256RsaPss,
+}
+
+/// This defines the OPC UA data stored in the Configuration
+/// CRD
+///
+/// The OPC UA discovery handler is designed to support multiple methods
+/// for discovering OPC UA servers and stores a filter list for
+/// application names.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpcuaDiscoveryDetails {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub opcua_discovery_method: OpcuaDiscoveryMethod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_names: Option<FilterList>,
+    /// Security policy of the channel used to call FindServers, no security is used if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_policy: Option<OpcuaSecurityPolicy>,
+    /// Path to the DER certificate the discovery client presents to servers,
+    /// a self-signed one is generated if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate_path: Option<String>,
+    /// Path to the PEM private key of `certificate_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key_path: Option<String>,
+    /// Directory of the discovery client's PKI, server certificates are only trusted if they are in its
+    /// `trusted/certs` subdirectory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pki_dir: Option<String>,
+}
+
+impl OpcuaDiscoveryDetails {
+    /// Checks that the client certificate and its private key are set together, to be used with
+    /// [validate_discovery_details](akri_discovery_utils::discovery::discovery_handler::validate_discovery_details)
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.certificate_path.is_some() != self.private_key_path.is_some() {
+            return Err(anyhow::format_err!(
+                "certificatePath and privateKeyPath must be set together"
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,61 +1,39 @@
-use super::{discovery_impl::do_standard_discovery, OPCUA_DISCOVERY_URL_LABEL};
-use akri_discovery_utils::{
-    discovery::{
-        discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
-        v0::{
-            discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse,
-        },
-        DiscoverStream,
+pub use super::discovery_details::{
+    DnsSdOpcuaDiscovery, OpcuaDiscoveryDetails, OpcuaDiscoveryMethod, OpcuaSecurityPolicy,
+    StandardOpcuaDiscovery,
+};
+use super::{
+    discovery_impl::{do_dns_sd_discovery, do_standard_discovery},
+    wrappers::opcua_client_wrapper::OpcuaClientSecurity,
+    OPCUA_DISCOVERY_URL_LABEL,
+};
+use akri_discovery_utils::discovery::{
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
     },
-    filtering::FilterList,
+    v0::{discovery_handler_server::DiscoveryHandler, Device, DiscoverRequest, DiscoverResponse},
+    DiscoverStream,
 };
 use async_trait::async_trait;
 use log::{error, info, trace};
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default interval between two discovery passes, overridden by the Configuration's discoveryPollIntervalSecs
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
-/// Methods for discovering OPC UA Servers
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub enum OpcuaDiscoveryMethod {
-    Standard(StandardOpcuaDiscovery),
-    // TODO: add scan
-}
-
-/// Discovers OPC UA Servers and/or LocalDiscoveryServers at specified DiscoveryURLs.
-/// If the DiscoveryURL is for a LocalDiscoveryServer, it will discover all Servers
-/// that have registered with that LocalDiscoveryServer.
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct StandardOpcuaDiscovery {
-    #[serde(default = "lds_discovery_url", skip_serializing_if = "Vec::is_empty")]
-    pub discovery_urls: Vec<String>,
-}
-
-/// If no DiscoveryURLs are specified, uses the OPC UA default DiscoveryURL
-/// for the LocalDiscoveryServer running on the host
-fn lds_discovery_url() -> Vec<String> {
-    vec!["opc.tcp://localhost:4840/".to_string()]
-}
-
-/// This defines the OPC UA data stored in the Configuration
-/// CRD
-///
-/// The OPC UA discovery handler is designed to support multiple methods
-/// for discovering OPC UA servers and stores a filter list for
-/// application names.
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct OpcuaDiscoveryDetails {
-    #[serde(with = "serde_yaml::with::singleton_map")]
-    pub opcua_discovery_method: OpcuaDiscoveryMethod,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub application_names: Option<FilterList>,
+impl OpcuaDiscoveryDetails {
+    /// Returns the security settings of the discovery client
+    fn client_security(&self) -> OpcuaClientSecurity {
+        OpcuaClientSecurity {
+            security_policy: self.security_policy.unwrap_or_default(),
+            certificate_path: self.certificate_path.as_ref().map(PathBuf::from),
+            private_key_path: self.private_key_path.as_ref().map(PathBuf::from),
+            pki_dir: self.pki_dir.as_ref().map(PathBuf::from),
+        }
+    }
 }
 
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
@@ -85,10 +63,18 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         let discovery_handler_config: OpcuaDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        discovery_handler_config
+            .validate()
+            .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
         let mut previously_discovered_devices: Vec<Device> = Vec::new();
         tokio::spawn(async move {
             let discovery_method = discovery_handler_config.opcua_discovery_method.clone();
             let application_names = discovery_handler_config.application_names.clone();
+            let client_security = discovery_handler_config.client_security();
             loop {
                 // Before each iteration, check if receiver has dropped
                 if discovered_devices_sender.is_closed() {
@@ -103,12 +89,27 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     OpcuaDiscoveryMethod::Standard(standard_opcua_discovery) => {
                         let discovery_urls = standard_opcua_discovery.discovery_urls.clone();
                         let application_names = application_names.clone();
+                        let client_security = client_security.clone();
+                        tokio::task::spawn_blocking(move || {
+                            do_standard_discovery(
+                                discovery_urls,
+                                application_names,
+                                client_security,
+                            )
+                        })
+                        .await
+                        .unwrap()
+                    }
+                    OpcuaDiscoveryMethod::DnsSd(dns_sd_opcua_discovery) => {
+                        let service_type = dns_sd_opcua_discovery.service_type.clone();
+                        let application_names = application_names.clone();
+                        let client_security = client_security.clone();
                         tokio::task::spawn_blocking(move || {
-                            do_standard_discovery(discovery_urls, application_names)
+                            do_dns_sd_discovery(service_type, application_names, client_security)
                         })
                         .await
                         .unwrap()
-                    } // No other discovery methods implemented yet
+                    }
                 };
 
                 // Build DiscoveryResult for each server discovered
@@ -127,6 +128,8 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                             properties,
                             mounts: Vec::default(),
                             device_specs: Vec::default(),
+                            last_warning: None,
+                            error: None,
                         }
                     })
                     .collect::<Vec<Device>>();
@@ -160,7 +163,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                sleep(discovery_interval).await;
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -204,4 +207,84 @@ mod tests {
         let expected_serialized = r#"{"opcuaDiscoveryMethod":{"standard":{"discoveryUrls":["opc.tcp://127.0.0.1:4855/"]}},"applicationNames":{"items":["Some application name"],"action":"Include"}}"#;
         assert_eq!(expected_serialized, serialized);
     }
+
+    #[test]
+    fn test_deserialize_discovery_details_dns_sd() {
+        // Check that if no service type is provided, the opc.tcp one is used.
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              dnsSd: {}
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        let serialized = serde_json::to_string(&dh_config).unwrap();
+        let expected_serialized =
+            r#"{"opcuaDiscoveryMethod":{"dnsSd":{"serviceType":"_opcua-tcp._tcp"}}}"#;
+        assert_eq!(expected_serialized, serialized);
+
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              dnsSd:
+                serviceType: _opcua-https._tcp
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        match dh_config.opcua_discovery_method {
+            OpcuaDiscoveryMethod::DnsSd(dns_sd) => {
+                assert_eq!(dns_sd.service_type, "_opcua-https._tcp")
+            }
+            _ => panic!("expected dnsSd discovery method"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_security() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard:
+                discoveryUrls:
+                - opc.tcp://127.0.0.1:4855/
+            securityPolicy: Basic256Sha256
+            certificatePath: /etc/opcua/cert.der
+            privateKeyPath: /etc/opcua/private.pem
+            pkiDir: /etc/opcua/pki
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert!(dh_config.validate().is_ok());
+        assert_eq!(
+            dh_config.client_security(),
+            OpcuaClientSecurity {
+                security_policy: OpcuaSecurityPolicy::Basic256Sha256,
+                certificate_path: Some(PathBuf::from("/etc/opcua/cert.der")),
+                private_key_path: Some(PathBuf::from("/etc/opcua/private.pem")),
+                pki_dir: Some(PathBuf::from("/etc/opcua/pki")),
+            }
+        );
+
+        // No security when omitted
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert_eq!(dh_config.client_security(), OpcuaClientSecurity::default());
+
+        // Unsupported security policies are rejected
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            securityPolicy: Basic128Rsa15
+        "#;
+        assert!(deserialize_discovery_details::<OpcuaDiscoveryDetails>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_validate_discovery_details_security() {
+        let yaml = r#"
+            opcuaDiscoveryMethod:
+              standard: {}
+            securityPolicy: Basic256Sha256
+            certificatePath: /etc/opcua/cert.der
+        "#;
+        let dh_config: OpcuaDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert!(dh_config.validate().is_err());
+    }
 }
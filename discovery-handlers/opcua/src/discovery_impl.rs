@@ -1,5 +1,6 @@
 use super::wrappers::{
-    opcua_client_wrapper::{create_opcua_discovery_client, OpcuaClient},
+    dns_sd_wrapper::{DnsSdResolver, DnsSdResolverImpl},
+    opcua_client_wrapper::{create_opcua_discovery_client, OpcuaClient, OpcuaClientSecurity},
     tcp_stream_wrapper::{TcpStream, TcpStreamImpl},
 };
 use ::url::Url;
@@ -17,7 +18,10 @@ use std::{
 /// Used when testing TCP connection before calling FindServers on the endpoint
 const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 
-/// `standard` is the only `OpcuaDiscoveryMethod` currently implemented, which takes in a set of DiscoveryURLs and discovers all the servers at those DiscoveryURLs.
+/// Time spent browsing DNS-SD for the services of Servers and LocalDiscoveryServers
+const DNS_SD_BROWSE_TIMEOUT_SECS: u64 = 3;
+
+/// The `standard` `OpcuaDiscoveryMethod` takes in a set of DiscoveryURLs and discovers all the servers at those DiscoveryURLs.
 ///
 /// Every OPC UA server/application has a DiscoveryEndpoint that Clients can access without establishing a session.
 /// The address for this endpoint is defined by a DiscoveryURL.
@@ -26,16 +30,17 @@ const TCP_CONNECTION_TEST_TIMEOUT_SECS: u64 = 3;
 /// provides mechanisms for Clients to obtain this list" (OPC UA Specification 12). A LocalDiscoveryServer is an implementation
 /// of an OPC UA DiscoveryServer.
 /// `do_standard_discovery` creates an OPC UA Discovery Client and calls get_discovery_urls, passing in the DiscoveryURLs provided
-/// in the OPC UA Configuration.
+/// in the OPC UA Configuration. The client secures its channel to DiscoveryEndpoints according to `security`.
 pub fn do_standard_discovery(
     discovery_urls: Vec<String>,
     filter_list: Option<FilterList>,
+    security: OpcuaClientSecurity,
 ) -> Vec<String> {
     info!(
         "do_standard_discovery - for DiscoveryUrls {:?}",
         discovery_urls
     );
-    let mut discovery_handler_client = create_opcua_discovery_client();
+    let mut discovery_handler_client = create_opcua_discovery_client(&security);
     let tcp_stream = TcpStreamImpl {};
     get_discovery_urls(
         &mut discovery_handler_client,
@@ -45,16 +50,90 @@ pub fn do_standard_discovery(
     )
 }
 
+/// The `dnsSd` `OpcuaDiscoveryMethod` browses DNS-SD for the services of the given type, e.g. `_opcua-tcp._tcp`,
+/// which Servers and LocalDiscoveryServers advertise over multicast DNS (OPC UA Specification 12).
+/// Each resolved service is turned into a DiscoveryURL, which is then verified with FindServers the same way
+/// as the DiscoveryURLs of `standard` discovery.
+pub fn do_dns_sd_discovery(
+    service_type: String,
+    filter_list: Option<FilterList>,
+    security: OpcuaClientSecurity,
+) -> Vec<String> {
+    info!("do_dns_sd_discovery - for service type {}", service_type);
+    let resolver = DnsSdResolverImpl {
+        browse_timeout: Duration::from_secs(DNS_SD_BROWSE_TIMEOUT_SECS),
+    };
+    let mut discovery_handler_client = create_opcua_discovery_client(&security);
+    let tcp_stream = TcpStreamImpl {};
+    get_dns_sd_discovery_urls(
+        &resolver,
+        &service_type,
+        &mut discovery_handler_client,
+        filter_list,
+        tcp_stream,
+    )
+}
+
+/// Browses DNS-SD for the services of `service_type` and calls FindServers on the DiscoveryURL of each of them
+fn get_dns_sd_discovery_urls(
+    resolver: &impl DnsSdResolver,
+    service_type: &str,
+    discovery_handler_client: &mut impl OpcuaClient,
+    filter_list: Option<FilterList>,
+    tcp_stream: impl TcpStream,
+) -> Vec<String> {
+    let services = match resolver.browse(&get_dns_sd_service_domain(service_type)) {
+        Ok(services) => services,
+        Err(e) => {
+            error!(
+                "get_dns_sd_discovery_urls - failed to browse services of type {} with error {:?}",
+                service_type, e
+            );
+            return Vec::new();
+        }
+    };
+    let mut lds_urls: Vec<String> = services
+        .iter()
+        .map(|service| {
+            format!(
+                "{}://{}:{}/{}",
+                OPC_TCP_SCHEME,
+                service.host,
+                service.port,
+                service.path.trim_start_matches('/')
+            )
+        })
+        .collect();
+    // Browsing returns services in no particular order
+    lds_urls.sort();
+    trace!(
+        "get_dns_sd_discovery_urls - resolved DiscoveryURLs {:?}",
+        lds_urls
+    );
+    get_discovery_urls(discovery_handler_client, lds_urls, filter_list, tcp_stream)
+}
+
+/// Services are browsed in the `local` domain of multicast DNS, e.g. `_opcua-tcp._tcp` is browsed as
+/// `_opcua-tcp._tcp.local.`
+fn get_dns_sd_service_domain(service_type: &str) -> String {
+    let service_type = service_type.trim_end_matches('.');
+    let service_type = service_type.strip_suffix(".local").unwrap_or(service_type);
+    format!("{}.local.", service_type)
+}
+
 /// This calls FindServers on each DiscoveryURL provided in order to
 /// (1) verify the DiscoveryURL
 /// (2) discover other servers registered with a Local Discovery Server in the case that the DiscoveryURL is for an LDS
 /// (3) determine whether the application at that URL should be included according to `ApplicationType` and the `application_names` filter
+/// Applications are filtered by name as soon as they are returned from FindServers, so excluded servers are never
+/// resolved or connected to. An absent or empty `application_names` filter includes every Server.
 fn get_discovery_urls(
     discovery_handler_client: &mut impl OpcuaClient,
     lds_urls: Vec<String>,
     filter_list: Option<FilterList>,
     tcp_stream: impl TcpStream,
 ) -> Vec<String> {
+    let filter_list = filter_list.filter(|filter_list| !filter_list.items.is_empty());
     let mut discovery_urls: Vec<String> = Vec::new();
     lds_urls.iter().for_each(|url| {
         if let Err(e) = test_tcp_connection(url, &tcp_stream) {
@@ -237,9 +316,12 @@ fn get_discovery_url_ip(
 #[cfg(test)]
 mod tests {
     use super::super::wrappers::{
-        opcua_client_wrapper::MockOpcuaClient, tcp_stream_wrapper::MockTcpStream,
+        dns_sd_wrapper::{DnsSdService, MockDnsSdResolver},
+        opcua_client_wrapper::MockOpcuaClient,
+        tcp_stream_wrapper::MockTcpStream,
     };
     use super::*;
+    use akri_discovery_utils::filtering::{FilterType, MatchType};
     use mockall::Sequence;
 
     pub fn create_application_description(
@@ -421,6 +503,249 @@ mod tests {
         assert_eq!(discovery_urls.len(), 1);
     }
 
+    fn set_up_mock_client_with_mixed_servers(
+        lds_url: &'static str,
+        discovery_urls: [&'static str; 3],
+    ) -> MockOpcuaClient {
+        let mut mock_client = MockOpcuaClient::new();
+        let applications = vec![
+            create_application_description(
+                "urn:Mock OPC UA Server",
+                "Mock OPC UA Server",
+                ApplicationType::Server,
+                discovery_urls[0],
+            ),
+            create_application_description(
+                "urn:Mock OPC UA Server2",
+                "Mock OPC UA Server2",
+                ApplicationType::Server,
+                discovery_urls[1],
+            ),
+            create_application_description(
+                "urn:Mock OPC UA Server3",
+                "Mock OPC UA Server3",
+                ApplicationType::Server,
+                discovery_urls[2],
+            ),
+        ];
+        mock_client
+            .expect_find_servers()
+            .times(1)
+            .withf(move |url: &str| url == lds_url)
+            .return_once(move |_| Ok(applications));
+        mock_client
+    }
+
+    fn set_up_mock_tcp_stream_for_lds(lds_url: &'static str) -> MockTcpStream {
+        let mut mock_tcp_stream = MockTcpStream::new();
+        let tcp_timeout_duration = Duration::from_secs(TCP_CONNECTION_TEST_TIMEOUT_SECS);
+        let lds_url_socket_addr = get_socket_addr(lds_url).unwrap();
+        // Only the LDS should be connected to, never the servers it returns
+        mock_tcp_stream
+            .expect_connect_timeout()
+            .times(1)
+            .withf(move |addr: &SocketAddr, timeout: &Duration| {
+                addr == &lds_url_socket_addr && timeout == &tcp_timeout_duration
+            })
+            .return_once(move |_, _| Ok(()));
+        mock_tcp_stream
+    }
+
+    #[test]
+    fn test_get_discovery_urls_application_names_exclude() {
+        let lds_url = "opc.tcp://127.0.0.1:4840/";
+        let discovery_urls = [
+            "opc.tcp://127.0.0.1:4855/",
+            "opc.tcp://127.0.0.1:4866/",
+            "opc.tcp://127.0.0.1:4877/",
+        ];
+        let mut mock_client = set_up_mock_client_with_mixed_servers(lds_url, discovery_urls);
+        let mock_tcp_stream = set_up_mock_tcp_stream_for_lds(lds_url);
+        let filter_list = FilterList {
+            items: vec![
+                "Mock OPC UA Server".to_string(),
+                "Mock OPC UA Server3".to_string(),
+            ],
+            action: FilterType::Exclude,
+            match_type: MatchType::Exact,
+        };
+
+        let found_urls = get_discovery_urls(
+            &mut mock_client,
+            vec![lds_url.to_string()],
+            Some(filter_list),
+            mock_tcp_stream,
+        );
+        assert_eq!(found_urls, vec![discovery_urls[1].to_string()]);
+    }
+
+    #[test]
+    fn test_get_discovery_urls_application_names_include() {
+        let lds_url = "opc.tcp://127.0.0.1:4840/";
+        let discovery_urls = [
+            "opc.tcp://127.0.0.1:4855/",
+            "opc.tcp://127.0.0.1:4866/",
+            "opc.tcp://127.0.0.1:4877/",
+        ];
+        let mut mock_client = set_up_mock_client_with_mixed_servers(lds_url, discovery_urls);
+        let mock_tcp_stream = set_up_mock_tcp_stream_for_lds(lds_url);
+        let filter_list = FilterList {
+            items: vec![
+                "Mock OPC UA Server".to_string(),
+                "Unknown Server".to_string(),
+            ],
+            action: FilterType::Include,
+            match_type: MatchType::Exact,
+        };
+
+        let found_urls = get_discovery_urls(
+            &mut mock_client,
+            vec![lds_url.to_string()],
+            Some(filter_list),
+            mock_tcp_stream,
+        );
+        assert_eq!(found_urls, vec![discovery_urls[0].to_string()]);
+    }
+
+    #[test]
+    fn test_get_discovery_urls_empty_application_names() {
+        let lds_url = "opc.tcp://127.0.0.1:4840/";
+        let discovery_urls = [
+            "opc.tcp://127.0.0.1:4855/",
+            "opc.tcp://127.0.0.1:4866/",
+            "opc.tcp://127.0.0.1:4877/",
+        ];
+        let expected_urls: Vec<String> = discovery_urls.iter().map(|u| u.to_string()).collect();
+
+        // An absent filter includes all servers
+        let mut mock_client = set_up_mock_client_with_mixed_servers(lds_url, discovery_urls);
+        let mock_tcp_stream = set_up_mock_tcp_stream_for_lds(lds_url);
+        let found_urls = get_discovery_urls(
+            &mut mock_client,
+            vec![lds_url.to_string()],
+            None,
+            mock_tcp_stream,
+        );
+        assert_eq!(found_urls, expected_urls);
+
+        // An empty filter, regardless of action, includes all servers
+        for action in [FilterType::Include, FilterType::Exclude] {
+            let mut mock_client = set_up_mock_client_with_mixed_servers(lds_url, discovery_urls);
+            let mock_tcp_stream = set_up_mock_tcp_stream_for_lds(lds_url);
+            let found_urls = get_discovery_urls(
+                &mut mock_client,
+                vec![lds_url.to_string()],
+                Some(FilterList {
+                    items: Vec::new(),
+                    action,
+                    match_type: MatchType::Exact,
+                }),
+                mock_tcp_stream,
+            );
+            assert_eq!(found_urls, expected_urls);
+        }
+    }
+
+    #[test]
+    fn test_get_dns_sd_discovery_urls() {
+        let lds_url = "opc.tcp://10.0.0.1:4840/";
+        let server_url = "opc.tcp://10.0.0.2:4855/OPCUA/Server";
+        let discovery_url = "opc.tcp://127.0.0.1:4855/";
+        let discovery_url2 = "opc.tcp://10.0.0.2:4855/OPCUA/Server";
+        let mut mock_resolver = MockDnsSdResolver::new();
+        mock_resolver
+            .expect_browse()
+            .times(1)
+            .withf(|service_type: &str| service_type == "_opcua-tcp._tcp.local.")
+            .returning(|_| {
+                Ok(vec![
+                    DnsSdService {
+                        host: "10.0.0.2".to_string(),
+                        port: 4855,
+                        path: "/OPCUA/Server".to_string(),
+                    },
+                    DnsSdService {
+                        host: "10.0.0.1".to_string(),
+                        port: 4840,
+                        path: String::new(),
+                    },
+                ])
+            });
+        // The TCP connection to each resolved service is tested before calling FindServers on it
+        let mock_tcp_stream = set_up_mock_tcp_stream(lds_url, server_url);
+
+        let mut mock_client = MockOpcuaClient::new();
+        let mut find_servers_seq = Sequence::new();
+        let server_application_description = create_application_description(
+            "urn:Mock OPC UA Server",
+            "Mock OPC UA Server",
+            ApplicationType::Server,
+            discovery_url,
+        );
+        let server_application_description2 = create_application_description(
+            "urn:Mock OPC UA Server2",
+            "Mock OPC UA Server2",
+            ApplicationType::Server,
+            discovery_url2,
+        );
+        mock_client
+            .expect_find_servers()
+            .times(1)
+            .withf(move |url: &str| url == lds_url)
+            .return_once(move |_| Ok(vec![server_application_description]))
+            .in_sequence(&mut find_servers_seq);
+        mock_client
+            .expect_find_servers()
+            .times(1)
+            .withf(move |url: &str| url == server_url)
+            .return_once(move |_| Ok(vec![server_application_description2]))
+            .in_sequence(&mut find_servers_seq);
+
+        let discovery_urls = get_dns_sd_discovery_urls(
+            &mock_resolver,
+            "_opcua-tcp._tcp",
+            &mut mock_client,
+            None,
+            mock_tcp_stream,
+        );
+        assert_eq!(discovery_urls, vec![discovery_url, discovery_url2]);
+    }
+
+    #[test]
+    fn test_get_dns_sd_discovery_urls_browse_failure() {
+        let mut mock_resolver = MockDnsSdResolver::new();
+        mock_resolver
+            .expect_browse()
+            .times(1)
+            .returning(|_| Err(anyhow::format_err!("no multicast interface")));
+        let mut mock_client = MockOpcuaClient::new();
+        let mock_tcp_stream = MockTcpStream::new();
+        assert!(get_dns_sd_discovery_urls(
+            &mock_resolver,
+            "_opcua-tcp._tcp",
+            &mut mock_client,
+            None,
+            mock_tcp_stream
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_get_dns_sd_service_domain() {
+        assert_eq!(
+            get_dns_sd_service_domain("_opcua-tcp._tcp"),
+            "_opcua-tcp._tcp.local."
+        );
+        assert_eq!(
+            get_dns_sd_service_domain("_opcua-tcp._tcp.local."),
+            "_opcua-tcp._tcp.local."
+        );
+        assert_eq!(
+            get_dns_sd_service_domain("_opcua-tcp._tcp.local"),
+            "_opcua-tcp._tcp.local."
+        );
+    }
+
     #[test]
     // Test that find servers isn't called on invalid DiscoveryURL (missing opc)
     fn test_get_server_endpoints_invalid_url() {
@@ -1,8 +1,12 @@
 #[macro_use]
 extern crate serde_derive;
 
+pub mod discovery_details;
+#[cfg(feature = "discovery-handler")]
 pub mod discovery_handler;
+#[cfg(feature = "discovery-handler")]
 mod discovery_impl;
+#[cfg(feature = "discovery-handler")]
 mod wrappers;
 
 /// Name of the environment variable that will be mounted into the OPC UA broker pods.
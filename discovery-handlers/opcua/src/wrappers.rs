@@ -1,8 +1,11 @@
 /// Wrapper to enable mocking of OPC UA Client
 pub mod opcua_client_wrapper {
+    use crate::discovery_handler::OpcuaSecurityPolicy;
+    use log::error;
     #[cfg(test)]
     use mockall::{automock, predicate::*};
     use opcua::client::prelude::*;
+    use std::path::PathBuf;
 
     #[cfg_attr(test, automock)]
     pub trait OpcuaClient {
@@ -12,8 +15,29 @@ pub mod opcua_client_wrapper {
         ) -> Result<Vec<ApplicationDescription>, StatusCode>;
     }
 
+    /// Security settings of the discovery client
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct OpcuaClientSecurity {
+        pub security_policy: OpcuaSecurityPolicy,
+        pub certificate_path: Option<PathBuf>,
+        pub private_key_path: Option<PathBuf>,
+        pub pki_dir: Option<PathBuf>,
+    }
+
+    impl OpcuaSecurityPolicy {
+        fn to_security_policy(self) -> SecurityPolicy {
+            match self {
+                OpcuaSecurityPolicy::None => SecurityPolicy::None,
+                OpcuaSecurityPolicy::Basic256Sha256 => SecurityPolicy::Basic256Sha256,
+                OpcuaSecurityPolicy::Aes128Sha256RsaOaep => SecurityPolicy::Aes128Sha256RsaOaep,
+                OpcuaSecurityPolicy::Aes256Sha256RsaPss => SecurityPolicy::Aes256Sha256RsaPss,
+            }
+        }
+    }
+
     pub struct OpcuaClientImpl {
         inner_opcua_client: Client,
+        security_policy: SecurityPolicy,
     }
 
     impl OpcuaClientImpl {
@@ -22,32 +46,84 @@ pub mod opcua_client_wrapper {
             application_uri: &str,
             create_sample_keypair: bool,
             session_retry_limit: i32,
+            security: &OpcuaClientSecurity,
         ) -> Self {
             OpcuaClientImpl {
-                inner_opcua_client: ClientBuilder::new()
-                    .application_name(application_name)
-                    .application_uri(application_uri)
-                    .create_sample_keypair(create_sample_keypair)
-                    .session_retry_limit(session_retry_limit)
-                    .client()
-                    .unwrap(),
+                inner_opcua_client: client_builder(
+                    application_name,
+                    application_uri,
+                    create_sample_keypair,
+                    session_retry_limit,
+                    security,
+                )
+                .client()
+                .unwrap(),
+                security_policy: security.security_policy.to_security_policy(),
             }
         }
     }
 
+    fn client_builder(
+        application_name: &str,
+        application_uri: &str,
+        create_sample_keypair: bool,
+        session_retry_limit: i32,
+        security: &OpcuaClientSecurity,
+    ) -> ClientBuilder {
+        let mut builder = ClientBuilder::new()
+            .application_name(application_name)
+            .application_uri(application_uri)
+            .create_sample_keypair(create_sample_keypair)
+            .session_retry_limit(session_retry_limit);
+        if let Some(certificate_path) = &security.certificate_path {
+            builder = builder.certificate_path(certificate_path.clone());
+        }
+        if let Some(private_key_path) = &security.private_key_path {
+            builder = builder.private_key_path(private_key_path.clone());
+        }
+        if let Some(pki_dir) = &security.pki_dir {
+            builder = builder.pki_dir(pki_dir.clone());
+        }
+        builder
+    }
+
     impl OpcuaClient for OpcuaClientImpl {
         fn find_servers(
             &mut self,
             discovery_endpoint_url: &str,
         ) -> Result<Vec<ApplicationDescription>, StatusCode> {
-            self.inner_opcua_client.find_servers(discovery_endpoint_url)
+            if self.security_policy == SecurityPolicy::None {
+                return self.inner_opcua_client.find_servers(discovery_endpoint_url);
+            }
+            // Open a secure channel to the DiscoveryEndpoint, no session is needed to call FindServers
+            let endpoint: EndpointDescription = (
+                discovery_endpoint_url,
+                self.security_policy.to_uri(),
+                MessageSecurityMode::SignAndEncrypt,
+            )
+                .into();
+            let session = self
+                .inner_opcua_client
+                .new_session_from_info(endpoint)
+                .map_err(|e| {
+                    error!(
+                        "find_servers - failed to create secure channel to {}: {}",
+                        discovery_endpoint_url, e
+                    );
+                    StatusCode::BadSecurityChecksFailed
+                })?;
+            let session = session.read();
+            session.connect()?;
+            let servers = session.find_servers(discovery_endpoint_url);
+            session.disconnect();
+            servers
         }
     }
     /// Returns an OPC UA Client that will only be used to connect to OPC UA Server and Local Discovery Servers' DiscoveryEndpoints
-    pub fn create_opcua_discovery_client() -> impl OpcuaClient {
-        // Automatically create a self-signed private key and public cert.
+    pub fn create_opcua_discovery_client(security: &OpcuaClientSecurity) -> impl OpcuaClient {
+        // Unless a certificate is provided, automatically create a self-signed private key and public cert.
         // This will not be used to authenticate as only public endpoints will be accessed by the discovery client.
-        let create_sample_keypair = true;
+        let create_sample_keypair = security.certificate_path.is_none();
         // Do not try to create a session again
         let session_retry_limit = 0;
         OpcuaClientImpl::new(
@@ -55,8 +131,54 @@ pub mod opcua_client_wrapper {
             "urn:DiscoveryHandlerClient",
             create_sample_keypair,
             session_retry_limit,
+            security,
         )
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_client_builder_security() {
+            let security = OpcuaClientSecurity {
+                security_policy: OpcuaSecurityPolicy::Basic256Sha256,
+                certificate_path: Some(PathBuf::from("/etc/opcua/cert.der")),
+                private_key_path: Some(PathBuf::from("/etc/opcua/private.pem")),
+                pki_dir: Some(PathBuf::from("/etc/opcua/pki")),
+            };
+            let config = client_builder("app", "urn:app", false, 0, &security).config();
+            assert!(!config.create_sample_keypair);
+            assert_eq!(
+                config.certificate_path,
+                Some(PathBuf::from("/etc/opcua/cert.der"))
+            );
+            assert_eq!(
+                config.private_key_path,
+                Some(PathBuf::from("/etc/opcua/private.pem"))
+            );
+            assert_eq!(config.pki_dir, PathBuf::from("/etc/opcua/pki"));
+            assert_eq!(
+                security.security_policy.to_security_policy(),
+                SecurityPolicy::Basic256Sha256
+            );
+        }
+
+        #[test]
+        fn test_client_builder_no_security() {
+            let security = OpcuaClientSecurity::default();
+            let default_config = ClientBuilder::new().config();
+            let config = client_builder("app", "urn:app", true, 0, &security).config();
+            assert!(config.create_sample_keypair);
+            assert_eq!(config.certificate_path, default_config.certificate_path);
+            assert_eq!(config.private_key_path, default_config.private_key_path);
+            assert_eq!(config.pki_dir, default_config.pki_dir);
+            assert_eq!(
+                security.security_policy.to_security_policy(),
+                SecurityPolicy::None
+            );
+        }
+    }
 }
 pub mod tcp_stream_wrapper {
     #[cfg(test)]
@@ -81,3 +203,67 @@ pub mod tcp_stream_wrapper {
         }
     }
 }
+/// Wrapper to enable mocking of DNS-SD browsing
+pub mod dns_sd_wrapper {
+    use mdns_sd::{ServiceDaemon, ServiceEvent};
+    #[cfg(test)]
+    use mockall::{automock, predicate::*};
+    use std::{
+        collections::HashMap,
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    /// A service instance resolved through DNS-SD
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DnsSdService {
+        /// IP address or hostname of the service, IPv6 addresses are enclosed in brackets
+        pub host: String,
+        pub port: u16,
+        /// Path of the DiscoveryURL, from the `path` TXT record of the service
+        pub path: String,
+    }
+
+    #[cfg_attr(test, automock)]
+    pub trait DnsSdResolver {
+        fn browse(&self, service_type: &str) -> Result<Vec<DnsSdService>, anyhow::Error>;
+    }
+
+    /// Browses services over multicast DNS for `browse_timeout`
+    pub struct DnsSdResolverImpl {
+        pub browse_timeout: Duration,
+    }
+
+    impl DnsSdResolver for DnsSdResolverImpl {
+        fn browse(&self, service_type: &str) -> Result<Vec<DnsSdService>, anyhow::Error> {
+            let daemon = ServiceDaemon::new()?;
+            let receiver = daemon.browse(service_type)?;
+            let deadline = Instant::now() + self.browse_timeout;
+            // A service can be resolved more than once while browsing, only keep its latest resolution
+            let mut services = HashMap::new();
+            while let Ok(event) = receiver.recv_deadline(deadline) {
+                if let ServiceEvent::ServiceResolved(info) = event {
+                    // Prefer an address, as the system resolver may not resolve mDNS hostnames
+                    let host = match info.get_addresses().iter().next() {
+                        Some(IpAddr::V6(addr)) => format!("[{}]", addr),
+                        Some(IpAddr::V4(addr)) => addr.to_string(),
+                        None => info.get_hostname().trim_end_matches('.').to_string(),
+                    };
+                    services.insert(
+                        info.get_fullname().to_string(),
+                        DnsSdService {
+                            host,
+                            port: info.get_port(),
+                            path: info
+                                .get_property_val_str("path")
+                                .unwrap_or_default()
+                                .to_string(),
+                        },
+                    );
+                }
+            }
+            let _ = daemon.shutdown();
+            Ok(services.into_values().collect())
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use super::udev_rule::check_udev_rule;
+
+/// This defines the udev data stored in the Configuration
+/// CRD DiscoveryDetails
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UdevDiscoveryDetails {
+    pub udev_rules: Vec<String>,
+
+    #[serde(default)]
+    pub group_recursive: bool,
+
+    /// Optional list of subsystems (e.g. `video4linux`) to limit device enumeration to.
+    /// All subsystems are enumerated if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subsystems: Vec<String>,
+
+    /// Whether to pass the serial/GPIO metadata (serial number, vendor/model ids, bus, UART type and
+    /// clock, GPIO chip label and lines...) of discovered tty and gpio devices as `UDEV_*` properties
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub serial_metadata: bool,
+}
+
+impl UdevDiscoveryDetails {
+    /// Checks that every udev rule can be parsed, to be used with
+    /// [validate_discovery_details](akri_discovery_utils::discovery::discovery_handler::validate_discovery_details)
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.udev_rules.is_empty() {
+            return Err(anyhow::format_err!("udevRules must not be empty"));
+        }
+        for udev_rule in &self.udev_rules {
+            check_udev_rule(udev_rule)
+                .map_err(|e| anyhow::format_err!("invalid udev rule {}: {}", udev_rule, e))?;
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,15 @@
+pub use super::discovery_details::UdevDiscoveryDetails;
 use super::{
-    discovery_impl::{do_parse_and_find, insert_device_with_relatives, DeviceProperties},
+    discovery_impl::{
+        get_device_env_vars, get_serial_metadata_env_vars, insert_device_with_relatives,
+        DeviceProperties, UdevRuleCache,
+    },
     wrappers::udev_enumerator,
 };
 use akri_discovery_utils::discovery::{
-    discovery_handler::{deserialize_discovery_details, DISCOVERED_DEVICES_CHANNEL_CAPACITY},
+    discovery_handler::{
+        deserialize_discovery_details, get_discovery_interval, DISCOVERED_DEVICES_CHANNEL_CAPACITY,
+    },
     v0::{
         discovery_handler_server::DiscoveryHandler, Device, DeviceSpec, DiscoverRequest,
         DiscoverResponse,
@@ -18,20 +24,9 @@ use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tonic::{Response, Status};
 
-// TODO: make this configurable
+/// Default interval between two discovery passes, overridden by the Configuration's discoveryPollIntervalSecs
 pub const DISCOVERY_INTERVAL_SECS: u64 = 10;
 
-/// This defines the udev data stored in the Configuration
-/// CRD DiscoveryDetails
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct UdevDiscoveryDetails {
-    pub udev_rules: Vec<String>,
-
-    #[serde(default)]
-    pub group_recursive: bool,
-}
-
 /// `DiscoveryHandlerImpl` discovers udev instances by parsing the udev rules in `discovery_handler_config.udev_rules`.
 pub struct DiscoveryHandlerImpl {
     register_sender: Option<mpsc::Sender<()>>,
@@ -58,11 +53,19 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
         let discovery_handler_config: UdevDiscoveryDetails =
             deserialize_discovery_details(&discover_request.discovery_details)
                 .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
+        let discovery_interval = get_discovery_interval(
+            discover_request,
+            Duration::from_secs(DISCOVERY_INTERVAL_SECS),
+        );
+        let udev_rule_cache = UdevRuleCache::new(&discovery_handler_config.udev_rules)
+            .map_err(|e| tonic::Status::new(tonic::Code::InvalidArgument, format!("{}", e)))?;
         let mut previously_discovered_devices: Vec<Device> = Vec::new();
         tokio::spawn(async move {
-            let udev_rules = discovery_handler_config.udev_rules.clone();
             loop {
-                trace!("discover - for udev rules {:?}", udev_rules);
+                trace!(
+                    "discover - for udev rules {:?}",
+                    discovery_handler_config.udev_rules
+                );
                 // Before each iteration, check if receiver has dropped
                 if discovered_devices_sender.is_closed() {
                     error!("discover - channel closed ... attempting to re-register with Agent");
@@ -72,17 +75,19 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     break;
                 }
                 let mut devpaths: HashMap<String, HashSet<DeviceProperties>> = HashMap::new();
-                udev_rules.iter().for_each(|rule| {
-                    let enumerator = udev_enumerator::create_enumerator();
-                    let paths = do_parse_and_find(enumerator, rule).unwrap();
-                    for path in paths.into_iter() {
-                        if !discovery_handler_config.group_recursive {
-                            devpaths.insert(path.0.clone(), HashSet::from([path]));
-                        } else {
-                            insert_device_with_relatives(&mut devpaths, path);
-                        }
+                let paths = udev_rule_cache
+                    .find(
+                        udev_enumerator::create_enumerator,
+                        &discovery_handler_config.subsystems,
+                    )
+                    .unwrap();
+                for path in paths.into_iter() {
+                    if !discovery_handler_config.group_recursive {
+                        devpaths.insert(path.0.clone(), HashSet::from([path]));
+                    } else {
+                        insert_device_with_relatives(&mut devpaths, path);
                     }
-                });
+                }
                 trace!(
                     "discover - mapping and returning devices at devpaths {:?}",
                     devpaths
@@ -92,16 +97,17 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                     .map(|(id, paths)| {
                         let mut properties = HashMap::new();
                         let mut device_specs = Vec::new();
-                        for (i, (_, node)) in paths.into_iter().enumerate() {
+                        for (i, path) in paths.into_iter().enumerate() {
                             let property_suffix = discovery_handler_config
                                 .group_recursive
                                 .then(|| format!("_{}", i))
                                 .unwrap_or_default();
-                            if let Some(devnode) = node {
-                                properties.insert(
-                                    super::UDEV_DEVNODE_LABEL_ID.to_string() + &property_suffix,
-                                    devnode.clone(),
-                                );
+                            properties.extend(get_device_env_vars(&path, &property_suffix));
+                            if discovery_handler_config.serial_metadata {
+                                properties
+                                    .extend(get_serial_metadata_env_vars(&path, &property_suffix));
+                            }
+                            if let Some(devnode) = path.1 {
                                 device_specs.push(DeviceSpec {
                                     container_path: devnode.clone(),
                                     host_path: devnode,
@@ -119,6 +125,8 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                             properties,
                             mounts: Vec::default(),
                             device_specs,
+                            last_warning: None,
+                            error: None,
                         }
                     })
                     .collect::<Vec<Device>>();
@@ -152,7 +160,7 @@ impl DiscoveryHandler for DiscoveryHandlerImpl {
                         break;
                     }
                 }
-                sleep(Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+                sleep(discovery_interval).await;
             }
         });
         Ok(Response::new(tokio_stream::wrappers::ReceiverStream::new(
@@ -191,5 +199,32 @@ mod tests {
         let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
         assert_eq!(udev_dh_config.udev_rules.len(), 1);
         assert_eq!(&udev_dh_config.udev_rules[0], "KERNEL==\"video[0-9]*\"");
+        assert!(udev_dh_config.subsystems.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_subsystems() {
+        let yaml = r#"
+          udevRules:
+          - 'KERNEL=="video[0-9]*"'
+          subsystems:
+          - video4linux
+        "#;
+        let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert_eq!(udev_dh_config.subsystems, vec!["video4linux".to_string()]);
+        assert!(!udev_dh_config.serial_metadata);
+    }
+
+    #[test]
+    fn test_deserialize_discovery_details_serial_metadata() {
+        let yaml = r#"
+          udevRules:
+          - 'SUBSYSTEM=="tty", KERNEL=="ttyUSB[0-9]*"'
+          serialMetadata: true
+        "#;
+        let udev_dh_config: UdevDiscoveryDetails = deserialize_discovery_details(yaml).unwrap();
+        assert!(udev_dh_config.serial_metadata);
+        let serialized = serde_json::to_string(&udev_dh_config).unwrap();
+        assert!(serialized.contains(r#""serialMetadata":true"#));
     }
 }
@@ -1,5 +1,8 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+#[cfg(test)]
+use super::udev_rule::PARSE_COUNT;
+use super::udev_rule::{parse_udev_rule, Rule, UdevFilter};
 use super::wrappers::{
     udev_device::{
         get_attribute_value, get_devnode, get_devpath, get_driver, get_parent, get_property_value,
@@ -7,107 +10,81 @@ use super::wrappers::{
     },
     udev_enumerator::Enumerator,
 };
-use log::{error, info, trace};
-use pest::iterators::Pair;
-use pest::Parser;
+use log::{error, trace};
 use regex::Regex;
 
 const TAGS: &str = "TAGS";
 
-#[derive(Parser)]
-#[grammar = "udev_rule_grammar.pest"]
-pub struct UdevRuleParser;
-
-#[derive(Debug, PartialEq)]
-pub struct UdevFilter<'a> {
-    field: Pair<'a, Rule>,
-    operation: Rule,
-    value: String,
-}
-
-/// A udev device is defined by its devpath and devnode (if exists)
-pub(crate) type DeviceProperties = (String, Option<String>);
-
-/// This parses the udev rule into UdevFilters and finds all devices that match those filters
-pub fn do_parse_and_find(
-    enumerator: impl Enumerator,
-    udev_rule_string: &str,
-) -> Result<Vec<DeviceProperties>, anyhow::Error> {
-    let udev_filters = parse_udev_rule(udev_rule_string)?;
-    let devices = find_devices(enumerator, udev_filters)?;
-    trace!(
-        "do_parse_and_find - returning discovered devices with devpaths: {:?}",
-        devices
-    );
-    Ok(devices)
-}
-
-/// This parses a udev rule and returns a list of UdevFilter objects that specify which devices to search for.
-/// This returns an error if the udev rule parameter does not fit the format specified in udev
-/// man pages/wiki and therefore does not match the grammar specified in udev_rule_grammar.pest
-/// A udev rule is made of a list of field-value pairs which have format field<operation>"value"
-/// This function will only create UdevFilter objects for field-value pairs with supported fields and operations.
-/// Udev discovery is only interested in match operations ("==",  "!="), so all action ("=" , "+=" , "-=" , ":=") operations
-/// will be ignored.
-/// Udev discovery is only interested in match fields, so all action fields, such as TEST, are ignored
-fn parse_udev_rule(udev_rule_string: &str) -> Result<Vec<UdevFilter>, anyhow::Error> {
-    info!(
-        "parse_udev_rule - enter for udev rule string {}",
-        udev_rule_string
-    );
-    let mut udev_filters: Vec<UdevFilter> = Vec::new();
-
-    // So long as parse succeeds, subsequent unwraps will not fails, since they are following the
-    // format specified in the grammar
-    let udev_rule = UdevRuleParser::parse(Rule::udev_rule, udev_rule_string)?
-        .next() // move to first rule within udev_rule aka inner_rule
-        .unwrap() // does not panic because udev_rule always has inner_rule
-        .into_inner() // go into inner_rule which has format { udev_filter ~ ("," ~ udev_filter)* }
-        .next() // move to first rule in inner_rule aka udev_filter
-        .unwrap(); // does not panic because inner_rule always has udev_filter
-
-    trace!(
-        "parse_udev_rule - parsing udev_rule {:?}",
-        udev_rule.as_str()
-    );
-    for udev_filter in udev_rule.into_inner() {
-        let mut inner_rules = udev_filter.into_inner();
-        let field_pair = inner_rules.next().unwrap();
-        let inner_field = field_pair.into_inner().next().unwrap();
-        if inner_field.as_rule() == Rule::unsupported_field {
-            return Err(anyhow::format_err!(
-                "parse_udev_rule - unsupported field {}",
-                inner_field.into_inner().next().unwrap().as_str()
-            ));
+/// Subsystems of the devices that get their serial/GPIO metadata collected
+const SERIAL_METADATA_SUBSYSTEMS: [&str; 2] = ["tty", "gpio"];
+/// udev properties collected as serial/GPIO metadata
+const SERIAL_METADATA_PROPERTIES: [&str; 10] = [
+    "ID_BUS",
+    "ID_PATH",
+    "ID_SERIAL",
+    "ID_SERIAL_SHORT",
+    "ID_VENDOR",
+    "ID_VENDOR_ID",
+    "ID_MODEL",
+    "ID_MODEL_ID",
+    "ID_USB_DRIVER",
+    "ID_USB_INTERFACE_NUM",
+];
+/// sysfs attributes collected as serial/GPIO metadata, i.e the UART type, clock (from which the
+/// supported baud rates derive) and flags of serial ports, and the label and lines of GPIO chips
+const SERIAL_METADATA_ATTRIBUTES: [&str; 6] =
+    ["type", "uartclk", "flags", "label", "base", "ngpio"];
+
+/// A udev device is defined by its devpath, devnode (if exists), subsystem (if exists), driver (if bound)
+/// and serial/GPIO metadata (only for tty/gpio devices)
+pub(crate) type DeviceProperties = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    BTreeMap<String, String>,
+);
+
+/// The udev rules of a discovery request, parsed once into UdevFilters and reused across discovery
+/// cycles. Identical rules are only parsed once. A change of the Configuration starts a new discovery
+/// request, so its rules get parsed again.
+#[derive(Debug)]
+pub struct UdevRuleCache(Vec<(String, Vec<UdevFilter>)>);
+
+impl UdevRuleCache {
+    /// Parses the udev rules, returning an error if any of them can't be parsed
+    pub fn new(udev_rules: &[String]) -> Result<Self, anyhow::Error> {
+        let mut parsed_rules: Vec<(String, Vec<UdevFilter>)> = Vec::new();
+        for udev_rule in udev_rules {
+            if !parsed_rules.iter().any(|(rule, _)| rule == udev_rule) {
+                parsed_rules.push((udev_rule.clone(), parse_udev_rule(udev_rule)?));
+            }
         }
+        Ok(UdevRuleCache(parsed_rules))
+    }
 
-        let operation_rule = inner_rules
-            .next()
-            .unwrap()
-            .into_inner()
-            .next()
-            .unwrap()
-            .as_rule();
-        let mut quoted_value = inner_rules.next().unwrap().into_inner();
-        let value = quoted_value.next().unwrap().as_str();
-        if operation_rule != Rule::action_operation {
-            udev_filters.push(UdevFilter {
-                field: inner_field,
-                operation: operation_rule,
-                value: value.to_string(),
-            });
-        } else {
-            return Err(anyhow::format_err!("parse_udev_rule - unsupported action operation for rule with field [{}], operation [{:?}], and value[{}]",
-            inner_field.into_inner().as_str(), operation_rule, value));
+    /// Finds all devices matching any of the rules, using a new Enumerator for each rule.
+    /// If `subsystems` is not empty, only devices of those subsystems are considered.
+    pub fn find<E: Enumerator>(
+        &self,
+        mut create_enumerator: impl FnMut() -> E,
+        subsystems: &[String],
+    ) -> Result<Vec<DeviceProperties>, anyhow::Error> {
+        let mut devices = Vec::new();
+        for (udev_rule, udev_filters) in self.0.iter() {
+            trace!("find - for udev rule {}", udev_rule);
+            devices.extend(find_devices(create_enumerator(), udev_filters, subsystems)?);
         }
+        Ok(devices)
     }
-    Ok(udev_filters)
 }
 
-/// This searches for devices that match the UdevFilters and returns their devpaths
+/// This searches for devices that match the UdevFilters and belong to one of the allowed subsystems (if any)
+/// and returns their devpaths
 fn find_devices(
     enumerator: impl Enumerator,
-    udev_filters: Vec<UdevFilter>,
+    udev_filters: &[UdevFilter],
+    subsystems: &[String],
 ) -> std::io::Result<Vec<DeviceProperties>> {
     let mut enumerator = enumerator;
     trace!("find_devices - enter with udev_filters {:?}", udev_filters);
@@ -133,12 +110,10 @@ fn find_devices(
 
     // Sort UdevFilters based off of which group they belong to
     udev_filters.iter().for_each(|udev_filter| {
-        if udev_filter.operation == Rule::equality
-            && match_fields.contains(&udev_filter.field.as_rule())
-        {
+        if udev_filter.operation == Rule::equality && match_fields.contains(&udev_filter.field) {
             match_udev_filters.push(udev_filter);
         } else if udev_filter.operation == Rule::inequality
-            && nomatch_fields.contains(&udev_filter.field.as_rule())
+            && nomatch_fields.contains(&udev_filter.field)
         {
             nomatch_udev_filters.push(udev_filter);
         } else {
@@ -146,25 +121,108 @@ fn find_devices(
         }
     });
 
+    // Scope the enumeration to the allowed subsystems. Subsystem matches are OR'ed by the Enumerator,
+    // so if the rule already matches on subsystem, the allowed subsystems are checked after the scan instead.
+    let rule_matches_subsystem = match_udev_filters
+        .iter()
+        .any(|udev_filter| udev_filter.field == Rule::subsystem);
+    if !rule_matches_subsystem {
+        for subsystem in subsystems {
+            enumerator.match_subsystem(subsystem)?;
+        }
+    }
+
     // Apply UdevFilters of groups in 1,2,3 order
     filter_by_match_udev_filters(&mut enumerator, match_udev_filters);
     filter_by_nomatch_udev_filters(&mut enumerator, nomatch_udev_filters);
     let devices: Vec<udev::Device> = enumerator.scan_devices()?.collect();
-    let final_devices = filter_by_remaining_udev_filters(devices, remaining_udev_filters);
+    let mut final_devices = filter_by_remaining_udev_filters(devices, remaining_udev_filters);
+    if rule_matches_subsystem && !subsystems.is_empty() {
+        final_devices.retain(|device| {
+            get_subsystem(device)
+                .and_then(|subsystem| subsystem.to_str())
+                .map(|subsystem| subsystems.iter().any(|s| s == subsystem))
+                .unwrap_or(false)
+        });
+    }
 
-    let device_devpaths: Vec<DeviceProperties> = final_devices
-        .into_iter()
-        .map(|device| {
-            (
-                get_devpath(&device).to_str().unwrap().to_string(),
-                get_devnode(&device).map(|devnode| devnode.to_str().unwrap().to_string()),
-            )
-        })
-        .collect();
+    let device_devpaths: Vec<DeviceProperties> =
+        final_devices.iter().map(get_device_properties).collect();
 
     Ok(device_devpaths)
 }
 
+/// This gets the properties of a device that are passed on to brokers
+fn get_device_properties(device: &impl DeviceExt) -> DeviceProperties {
+    (
+        get_devpath(device).to_str().unwrap().to_string(),
+        get_devnode(device).map(|devnode| devnode.to_str().unwrap().to_string()),
+        get_subsystem(device).map(|subsystem| subsystem.to_string_lossy().to_string()),
+        get_driver(device).map(|driver| driver.to_string_lossy().to_string()),
+        get_serial_metadata(device),
+    )
+}
+
+/// This gets the serial/GPIO metadata of tty and gpio devices, keyed by udev property name, `ATTR_`
+/// followed by the upper-cased attribute name for sysfs attributes, and `SYSNAME` for the kernel name
+/// (e.g. `ttyUSB0`). It is empty for devices of other subsystems.
+fn get_serial_metadata(device: &impl DeviceExt) -> BTreeMap<String, String> {
+    let mut metadata = BTreeMap::new();
+    match get_subsystem(device).and_then(|subsystem| subsystem.to_str()) {
+        Some(subsystem) if SERIAL_METADATA_SUBSYSTEMS.contains(&subsystem) => (),
+        _ => return metadata,
+    }
+    metadata.insert(
+        "SYSNAME".to_string(),
+        get_sysname(device).to_string_lossy().to_string(),
+    );
+    for property in SERIAL_METADATA_PROPERTIES {
+        if let Some(value) = get_property_value(device, property) {
+            metadata.insert(property.to_string(), value.to_string_lossy().to_string());
+        }
+    }
+    for attribute in SERIAL_METADATA_ATTRIBUTES {
+        if let Some(value) = get_attribute_value(device, attribute) {
+            metadata.insert(
+                format!("ATTR_{}", attribute.to_uppercase()),
+                value.to_string_lossy().trim().to_string(),
+            );
+        }
+    }
+    metadata
+}
+
+/// This creates the broker environment variables for a device, appending `suffix` to their names.
+/// Variables are only set for the properties the device has.
+pub fn get_device_env_vars(device: &DeviceProperties, suffix: &str) -> HashMap<String, String> {
+    let (_, devnode, subsystem, driver, _) = device;
+    [
+        (super::UDEV_DEVNODE_LABEL_ID, devnode),
+        (super::UDEV_SUBSYSTEM_LABEL_ID, subsystem),
+        (super::UDEV_DRIVER_LABEL_ID, driver),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| {
+        value
+            .as_ref()
+            .map(|value| (label.to_string() + suffix, value.clone()))
+    })
+    .collect()
+}
+
+/// This creates the broker environment variables for the serial/GPIO metadata of a device, named after
+/// the metadata keys prefixed with `UDEV_` and followed by `suffix` (e.g. `UDEV_ID_SERIAL`)
+pub fn get_serial_metadata_env_vars(
+    device: &DeviceProperties,
+    suffix: &str,
+) -> HashMap<String, String> {
+    device
+        .4
+        .iter()
+        .map(|(key, value)| (format!("UDEV_{}{}", key, suffix), value.clone()))
+        .collect()
+}
+
 /// This adds equality filters to the Enumerator
 fn filter_by_match_udev_filters(enumerator: &mut impl Enumerator, udev_filters: Vec<&UdevFilter>) {
     trace!(
@@ -172,7 +230,7 @@ fn filter_by_match_udev_filters(enumerator: &mut impl Enumerator, udev_filters:
         udev_filters
     );
     for udev_filter in udev_filters {
-        match udev_filter.field.as_rule() {
+        match udev_filter.field {
             Rule::devpath => {
                 let mut syspath: String = "/sys".to_owned();
                 syspath.push_str(&udev_filter.value);
@@ -188,29 +246,11 @@ fn filter_by_match_udev_filters(enumerator: &mut impl Enumerator, udev_filters:
                 enumerator.match_subsystem(&udev_filter.value).unwrap();
             }
             Rule::attribute => {
-                let key = udev_filter
-                    .field
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str();
+                let key = udev_filter.key();
                 enumerator.match_attribute(key, &udev_filter.value).unwrap();
             }
             Rule::property => {
-                let key = udev_filter
-                    .field
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str();
+                let key = udev_filter.key();
                 enumerator.match_property(key, &udev_filter.value).unwrap();
             }
             _ => {
@@ -230,18 +270,9 @@ fn filter_by_nomatch_udev_filters(
         udev_filters
     );
     for udev_filter in udev_filters {
-        match udev_filter.field.as_rule() {
+        match udev_filter.field {
             Rule::attribute => {
-                let key = udev_filter
-                    .field
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str();
+                let key = udev_filter.key();
                 enumerator
                     .nomatch_attribute(key, &udev_filter.value)
                     .unwrap();
@@ -270,7 +301,7 @@ fn filter_by_remaining_udev_filters(
     for udev_filter in udev_filters {
         let value_regex = Regex::new(&udev_filter.value).unwrap();
         let is_equality = udev_filter.operation == Rule::equality;
-        match udev_filter.field.as_rule() {
+        match udev_filter.field {
             Rule::devpath => {
                 // Filter for inequality. Equality already accounted for in filter_by_match_udev_filters
                 mutable_devices.retain(|device| {
@@ -305,16 +336,7 @@ fn filter_by_remaining_udev_filters(
                 });
             }
             Rule::property => {
-                let key = udev_filter
-                    .field
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str();
+                let key = udev_filter.key();
                 // Filter for inequality. Equality already accounted for in filter_by_match_udev_filters
                 mutable_devices.retain(|device| {
                     if let Some(property_value) = get_property_value(device, key) {
@@ -343,16 +365,7 @@ fn filter_by_remaining_udev_filters(
                 });
             }
             Rule::attributes => {
-                let key = udev_filter
-                    .field
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .into_inner()
-                    .next()
-                    .unwrap()
-                    .as_str();
+                let key = udev_filter.key();
                 mutable_devices.retain(|device| {
                     filter_equality_check(
                         is_equality,
@@ -640,15 +653,16 @@ mod discovery_tests {
         let rule = "KERNEL==\"video[0-9]*\",SUBSYSTEM==\"video4linux\", ATTR{idVendor}==\"05a9\"";
         let udev_filters = parse_udev_rule(rule).unwrap();
         assert_eq!(udev_filters.len(), 3);
-        assert_eq!(udev_filters[0].field.as_str(), "KERNEL");
+        assert_eq!(udev_filters[0].field, Rule::kernel);
         assert_eq!(udev_filters[0].operation, Rule::equality);
         assert_eq!(&udev_filters[0].value, "video[0-9]*");
 
-        assert_eq!(udev_filters[1].field.as_str(), "SUBSYSTEM");
+        assert_eq!(udev_filters[1].field, Rule::subsystem);
         assert_eq!(udev_filters[1].operation, Rule::equality);
         assert_eq!(&udev_filters[1].value, "video4linux");
 
-        assert_eq!(udev_filters[2].field.as_str(), "ATTR{idVendor}");
+        assert_eq!(udev_filters[2].field, Rule::attribute);
+        assert_eq!(udev_filters[2].key(), "idVendor");
         assert_eq!(udev_filters[2].operation, Rule::equality);
         assert_eq!(&udev_filters[2].value, "05a9");
     }
@@ -1296,6 +1310,16 @@ mod discovery_tests {
         );
     }
 
+    fn parse_and_find(
+        enumerator: impl Enumerator,
+        udev_rule: &str,
+        subsystems: &[String],
+    ) -> Result<Vec<DeviceProperties>, anyhow::Error> {
+        let mut enumerator = Some(enumerator);
+        UdevRuleCache::new(&[udev_rule.to_string()])?
+            .find(|| enumerator.take().unwrap(), subsystems)
+    }
+
     // Only tests that proper match calls were made
     #[test]
     fn test_do_parse_and_find() {
@@ -1320,7 +1344,265 @@ mod discovery_tests {
                 .unwrap();
             enumerator.scan_devices()
         });
-        assert_eq!(do_parse_and_find(mock, rule).unwrap().len(), 0);
+        assert_eq!(parse_and_find(mock, rule, &[]).unwrap().len(), 0);
+    }
+
+    // Only tests that enumeration is scoped to the allowed subsystems
+    #[test]
+    fn test_do_parse_and_find_with_subsystems() {
+        let rule = "KERNEL==\"video[0-9]*\"";
+        let subsystems = vec!["video4linux".to_string(), "tty".to_string()];
+        let mut mock = MockEnumerator::new();
+        mock.expect_match_subsystem()
+            .times(1)
+            .withf(move |value: &str| value == "video4linux")
+            .returning(|_| Ok(()));
+        mock.expect_match_subsystem()
+            .times(1)
+            .withf(move |value: &str| value == "tty")
+            .returning(|_| Ok(()));
+        mock.expect_match_sysname()
+            .times(1)
+            .withf(move |value: &str| value == "video[0-9]*")
+            .returning(|_| Ok(()));
+        mock.expect_scan_devices().times(1).returning(|| {
+            let mut enumerator = create_enumerator();
+            enumerator
+                .match_attribute("random", "attribute_that_should_not_be_found")
+                .unwrap();
+            enumerator.scan_devices()
+        });
+        assert_eq!(parse_and_find(mock, rule, &subsystems).unwrap().len(), 0);
+    }
+
+    // Subsystem matches are OR'ed by the Enumerator, so allowed subsystems must not be added
+    // to the Enumerator when the rule already matches on subsystem
+    #[test]
+    fn test_do_parse_and_find_with_subsystems_and_subsystem_rule() {
+        let rule = "SUBSYSTEM==\"video4linux\"";
+        let subsystems = vec!["tty".to_string()];
+        let mut mock = MockEnumerator::new();
+        mock.expect_match_subsystem()
+            .times(1)
+            .withf(move |value: &str| value == "video4linux")
+            .returning(|_| Ok(()));
+        mock.expect_scan_devices().times(1).returning(|| {
+            let mut enumerator = create_enumerator();
+            enumerator
+                .match_attribute("random", "attribute_that_should_not_be_found")
+                .unwrap();
+            enumerator.scan_devices()
+        });
+        assert_eq!(parse_and_find(mock, rule, &subsystems).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_udev_rule_cache_parses_rules_once() {
+        let udev_rules = vec![
+            "KERNEL==\"video[0-9]*\"".to_string(),
+            "KERNEL==\"ttyUSB[0-9]*\"".to_string(),
+            "KERNEL==\"video[0-9]*\"".to_string(),
+        ];
+        let udev_rule_cache = UdevRuleCache::new(&udev_rules).unwrap();
+        let mut enumerator_count = 0;
+        for _ in 0..100 {
+            let devices = udev_rule_cache
+                .find(
+                    || {
+                        enumerator_count += 1;
+                        let mut mock = MockEnumerator::new();
+                        mock.expect_match_sysname().returning(|_| Ok(()));
+                        mock.expect_scan_devices().returning(|| {
+                            let mut enumerator = create_enumerator();
+                            enumerator
+                                .match_attribute("random", "attribute_that_should_not_be_found")
+                                .unwrap();
+                            enumerator.scan_devices()
+                        });
+                        mock
+                    },
+                    &[],
+                )
+                .unwrap();
+            assert!(devices.is_empty());
+        }
+        // Each discovery cycle enumerates devices for both distinct rules without parsing them again
+        assert_eq!(enumerator_count, 200);
+        assert_eq!(PARSE_COUNT.with(|count| count.get()), 2);
+
+        assert!(UdevRuleCache::new(&["TYPO==\"blah\"".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_get_device_properties() {
+        let device_with_driver = create_mock_device(
+            "/sys/devices/path",
+            "/dev/ttyUSB0",
+            "ttyUSB0",
+            HashMap::new(),
+            HashMap::new(),
+            Some(OsStr::new("ftdi_sio")),
+            Some(OsStr::new("tty")),
+            None,
+        );
+        assert_eq!(
+            get_device_properties(&device_with_driver),
+            (
+                "/sys/devices/path".to_string(),
+                Some("/dev/ttyUSB0".to_string()),
+                Some("tty".to_string()),
+                Some("ftdi_sio".to_string()),
+                BTreeMap::from([("SYSNAME".to_string(), "ttyUSB0".to_string())])
+            )
+        );
+
+        let device_without_driver = create_mock_device(
+            "/sys/devices/path",
+            "/dev/gpiochip0",
+            "gpiochip0",
+            HashMap::new(),
+            HashMap::new(),
+            None,
+            Some(OsStr::new("gpio")),
+            None,
+        );
+        assert_eq!(
+            get_device_properties(&device_without_driver),
+            (
+                "/sys/devices/path".to_string(),
+                Some("/dev/gpiochip0".to_string()),
+                Some("gpio".to_string()),
+                None,
+                BTreeMap::from([("SYSNAME".to_string(), "gpiochip0".to_string())])
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_device_env_vars() {
+        let device = (
+            "/sys/devices/path".to_string(),
+            Some("/dev/ttyUSB0".to_string()),
+            Some("tty".to_string()),
+            Some("ftdi_sio".to_string()),
+            BTreeMap::new(),
+        );
+        assert_eq!(
+            get_device_env_vars(&device, ""),
+            HashMap::from([
+                ("UDEV_DEVNODE".to_string(), "/dev/ttyUSB0".to_string()),
+                ("UDEV_SUBSYSTEM".to_string(), "tty".to_string()),
+                ("UDEV_DRIVER".to_string(), "ftdi_sio".to_string()),
+            ])
+        );
+
+        // No driver bound and no devnode
+        let device = (
+            "/sys/devices/path".to_string(),
+            None,
+            Some("gpio".to_string()),
+            None,
+            BTreeMap::new(),
+        );
+        assert_eq!(
+            get_device_env_vars(&device, "_1"),
+            HashMap::from([("UDEV_SUBSYSTEM_1".to_string(), "gpio".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_get_serial_metadata() {
+        let serial_device = create_mock_device(
+            "/sys/devices/pci0/usb1/1-1/1-1:1.0/ttyUSB0/tty/ttyUSB0",
+            "/dev/ttyUSB0",
+            "ttyUSB0",
+            HashMap::from([
+                ("ID_BUS".to_string(), "usb".to_string()),
+                ("ID_SERIAL".to_string(), "FTDI_FT232R_A1B2C3".to_string()),
+                ("ID_VENDOR_ID".to_string(), "0403".to_string()),
+                ("ID_MODEL_ID".to_string(), "6001".to_string()),
+                // Not part of the collected properties
+                ("MAJOR".to_string(), "188".to_string()),
+            ]),
+            HashMap::from([
+                ("type".to_string(), "4\n".to_string()),
+                ("uartclk".to_string(), "1843200".to_string()),
+                // Not part of the collected attributes
+                ("dev".to_string(), "188:0".to_string()),
+            ]),
+            Some(OsStr::new("ftdi_sio")),
+            Some(OsStr::new("tty")),
+            None,
+        );
+        let expected = BTreeMap::from([
+            ("SYSNAME".to_string(), "ttyUSB0".to_string()),
+            ("ID_BUS".to_string(), "usb".to_string()),
+            ("ID_SERIAL".to_string(), "FTDI_FT232R_A1B2C3".to_string()),
+            ("ID_VENDOR_ID".to_string(), "0403".to_string()),
+            ("ID_MODEL_ID".to_string(), "6001".to_string()),
+            ("ATTR_TYPE".to_string(), "4".to_string()),
+            ("ATTR_UARTCLK".to_string(), "1843200".to_string()),
+        ]);
+        assert_eq!(get_serial_metadata(&serial_device), expected);
+        assert_eq!(get_device_properties(&serial_device).4, expected);
+
+        let gpio_device = create_mock_device(
+            "/sys/devices/platform/soc/gpio/gpiochip0",
+            "/dev/gpiochip0",
+            "gpiochip0",
+            HashMap::new(),
+            HashMap::from([
+                ("label".to_string(), "pinctrl-bcm2835".to_string()),
+                ("ngpio".to_string(), "54".to_string()),
+            ]),
+            None,
+            Some(OsStr::new("gpio")),
+            None,
+        );
+        assert_eq!(
+            get_serial_metadata(&gpio_device),
+            BTreeMap::from([
+                ("SYSNAME".to_string(), "gpiochip0".to_string()),
+                ("ATTR_LABEL".to_string(), "pinctrl-bcm2835".to_string()),
+                ("ATTR_NGPIO".to_string(), "54".to_string()),
+            ])
+        );
+
+        // Devices of other subsystems have no serial/GPIO metadata
+        let video_device = create_mock_device(
+            "/sys/devices/pci0/usb1/1-2/1-2:1.0/video4linux/video0",
+            "/dev/video0",
+            "video0",
+            HashMap::from([("ID_BUS".to_string(), "usb".to_string())]),
+            HashMap::new(),
+            None,
+            Some(OsStr::new("video4linux")),
+            None,
+        );
+        assert!(get_serial_metadata(&video_device).is_empty());
+    }
+
+    #[test]
+    fn test_get_serial_metadata_env_vars() {
+        let device = (
+            "/sys/devices/path".to_string(),
+            Some("/dev/ttyUSB0".to_string()),
+            Some("tty".to_string()),
+            Some("ftdi_sio".to_string()),
+            BTreeMap::from([
+                ("SYSNAME".to_string(), "ttyUSB0".to_string()),
+                ("ATTR_UARTCLK".to_string(), "1843200".to_string()),
+            ]),
+        );
+        assert_eq!(
+            get_serial_metadata_env_vars(&device, "_1"),
+            HashMap::from([
+                ("UDEV_SYSNAME_1".to_string(), "ttyUSB0".to_string()),
+                ("UDEV_ATTR_UARTCLK_1".to_string(), "1843200".to_string()),
+            ])
+        );
+        // Serial/GPIO metadata is not part of the default environment variables
+        assert_eq!(get_device_env_vars(&device, "").len(), 3);
     }
 
     #[test]
@@ -1367,19 +1649,34 @@ mod discovery_tests {
     fn test_insert_device_with_relatives() {
         let mut devpaths: HashMap<String, HashSet<DeviceProperties>> = HashMap::default();
         let related_devices = [
-            ("/sys/device/parent".to_string(), None),
+            (
+                "/sys/device/parent".to_string(),
+                None,
+                None,
+                None,
+                BTreeMap::new(),
+            ),
             (
                 "/sys/device/parent/child1".to_string(),
                 Some("/dev/dev1".to_string()),
+                None,
+                None,
+                BTreeMap::new(),
             ),
             (
                 "/sys/device/parent/child1/child2".to_string(),
                 Some("/dev/dev2".to_string()),
+                None,
+                None,
+                BTreeMap::new(),
             ),
         ];
         let unrelated_device = (
             "/sys/device/other".to_string(),
             Some("/dev/other".to_string()),
+            None,
+            None,
+            BTreeMap::new(),
         );
 
         // Add first device
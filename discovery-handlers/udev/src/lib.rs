@@ -1,12 +1,20 @@
 extern crate pest;
 #[macro_use]
 extern crate pest_derive;
+#[cfg(feature = "discovery-handler")]
 extern crate udev;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod discovery_details;
+#[cfg(feature = "discovery-handler")]
 pub mod discovery_handler;
+#[cfg(feature = "discovery-handler")]
 mod discovery_impl;
+// The parsed rules are only used by the discovery handler, the discovery details only check them
+#[cfg_attr(not(feature = "discovery-handler"), allow(dead_code))]
+mod udev_rule;
+#[cfg(feature = "discovery-handler")]
 mod wrappers;
 
 /// Name of environment variable that is set in udev brokers. Contains devnode for udev device
@@ -15,6 +23,12 @@ pub const UDEV_DEVNODE_LABEL_ID: &str = "UDEV_DEVNODE";
 /// Name of environment variable that is set in udev brokers. Contains devpath for udev device
 /// the broker should connect to.
 pub const UDEV_DEVPATH_LABEL_ID: &str = "UDEV_DEVPATH";
+/// Name of environment variable that is set in udev brokers. Contains subsystem of the udev device
+/// the broker should use.
+pub const UDEV_SUBSYSTEM_LABEL_ID: &str = "UDEV_SUBSYSTEM";
+/// Name of environment variable that is set in udev brokers. Contains driver bound to the udev device
+/// the broker should use. Not set if no driver is bound to the device.
+pub const UDEV_DRIVER_LABEL_ID: &str = "UDEV_DRIVER";
 /// Name that udev discovery handlers use when registering with the Agent
 pub const DISCOVERY_HANDLER_NAME: &str = "udev";
 /// Defines whether this discovery handler discovers local devices on nodes rather than ones visible to multiple nodes
@@ -0,0 +1,103 @@
+use log::{info, trace};
+use pest::Parser;
+
+#[derive(Parser)]
+#[grammar = "udev_rule_grammar.pest"]
+pub struct UdevRuleParser;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UdevFilter {
+    pub(crate) field: Rule,
+    /// Key of the fields that take one, e.g `idVendor` for `ATTR{idVendor}`
+    pub(crate) key: Option<String>,
+    pub(crate) operation: Rule,
+    pub(crate) value: String,
+}
+
+impl UdevFilter {
+    pub(crate) fn key(&self) -> &str {
+        self.key.as_deref().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of udev rules parsed by the current test
+    pub(crate) static PARSE_COUNT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// This parses a udev rule and returns a list of UdevFilter objects that specify which devices to search for.
+/// This returns an error if the udev rule parameter does not fit the format specified in udev
+/// man pages/wiki and therefore does not match the grammar specified in udev_rule_grammar.pest
+/// A udev rule is made of a list of field-value pairs which have format field<operation>"value"
+/// This function will only create UdevFilter objects for field-value pairs with supported fields and operations.
+/// Udev discovery is only interested in match operations ("==",  "!="), so all action ("=" , "+=" , "-=" , ":=") operations
+/// will be ignored.
+/// Udev discovery is only interested in match fields, so all action fields, such as TEST, are ignored
+/// Checks that a udev rule can be parsed without running any discovery
+pub fn check_udev_rule(udev_rule_string: &str) -> Result<(), anyhow::Error> {
+    parse_udev_rule(udev_rule_string).map(|_| ())
+}
+
+pub(crate) fn parse_udev_rule(udev_rule_string: &str) -> Result<Vec<UdevFilter>, anyhow::Error> {
+    #[cfg(test)]
+    PARSE_COUNT.with(|count| count.set(count.get() + 1));
+    info!(
+        "parse_udev_rule - enter for udev rule string {}",
+        udev_rule_string
+    );
+    let mut udev_filters: Vec<UdevFilter> = Vec::new();
+
+    // So long as parse succeeds, subsequent unwraps will not fails, since they are following the
+    // format specified in the grammar
+    let udev_rule = UdevRuleParser::parse(Rule::udev_rule, udev_rule_string)?
+        .next() // move to first rule within udev_rule aka inner_rule
+        .unwrap() // does not panic because udev_rule always has inner_rule
+        .into_inner() // go into inner_rule which has format { udev_filter ~ ("," ~ udev_filter)* }
+        .next() // move to first rule in inner_rule aka udev_filter
+        .unwrap(); // does not panic because inner_rule always has udev_filter
+
+    trace!(
+        "parse_udev_rule - parsing udev_rule {:?}",
+        udev_rule.as_str()
+    );
+    for udev_filter in udev_rule.into_inner() {
+        let mut inner_rules = udev_filter.into_inner();
+        let field_pair = inner_rules.next().unwrap();
+        let inner_field = field_pair.into_inner().next().unwrap();
+        if inner_field.as_rule() == Rule::unsupported_field {
+            return Err(anyhow::format_err!(
+                "parse_udev_rule - unsupported field {}",
+                inner_field.into_inner().next().unwrap().as_str()
+            ));
+        }
+
+        let operation_rule = inner_rules
+            .next()
+            .unwrap()
+            .into_inner()
+            .next()
+            .unwrap()
+            .as_rule();
+        let mut quoted_value = inner_rules.next().unwrap().into_inner();
+        let value = quoted_value.next().unwrap().as_str();
+        if operation_rule != Rule::action_operation {
+            let key = inner_field
+                .clone()
+                .into_inner()
+                .next()
+                .and_then(|bounded_key| bounded_key.into_inner().next())
+                .map(|key| key.as_str().to_string());
+            udev_filters.push(UdevFilter {
+                field: inner_field.as_rule(),
+                key,
+                operation: operation_rule,
+                value: value.to_string(),
+            });
+        } else {
+            return Err(anyhow::format_err!("parse_udev_rule - unsupported action operation for rule with field [{}], operation [{:?}], and value[{}]",
+            inner_field.into_inner().as_str(), operation_rule, value));
+        }
+    }
+    Ok(udev_filters)
+}
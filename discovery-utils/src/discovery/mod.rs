@@ -13,13 +13,34 @@ pub mod discovery_handler {
         server::run_discovery_server,
         v0::{
             discovery_handler_server::DiscoveryHandler,
-            register_discovery_handler_request::EndpointType, RegisterDiscoveryHandlerRequest,
+            register_discovery_handler_request::EndpointType, Device, DiscoverRequest,
+            RegisterDiscoveryHandlerRequest,
         },
     };
-    use log::trace;
+    use akri_shared::{
+        akri::{
+            AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME, AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME,
+        },
+        os::env_var::{ActualEnvVarQuery, EnvVarQuery},
+    };
+    use futures::StreamExt;
+    use log::{info, trace};
+    use std::net::{IpAddr, SocketAddr};
+    use std::time::Duration;
     use tokio::sync::mpsc;
 
-    const DISCOVERY_PORT: i16 = 10000;
+    /// Name of the environment variable that overrides the port the discovery server listens on when registering
+    /// over the network
+    pub const DISCOVERY_PORT_LABEL: &str = "DISCOVERY_PORT";
+    const DEFAULT_DISCOVERY_PORT: u16 = 10000;
+
+    /// Command line flag making a Discovery Handler validate the discovery details in the given file and exit,
+    /// instead of serving discovery requests
+    pub const VALIDATE_FLAG: &str = "--validate";
+
+    /// Command line flag making a Discovery Handler run discovery a single time with the discovery details in the
+    /// given file, print the discovered devices and exit, instead of serving discovery requests
+    pub const ONCE_FLAG: &str = "--once";
 
     /// Capacity of channel over which a message is sent by `DiscoveryHandler::discover` that its `DiscoveryHandler`
     /// should re-register due to the Agent dropping its end of the current connection.
@@ -41,7 +62,7 @@ pub mod discovery_handler {
             Ok(pod_ip) => {
                 trace!("run_discovery_handler - registering with Agent with IP endpoint");
                 use_uds = false;
-                format!("{}:{}", pod_ip, DISCOVERY_PORT)
+                get_network_endpoint(&pod_ip, get_discovery_port(&ActualEnvVarQuery {})?)
             }
             Err(_) => {
                 trace!("run_discovery_handler - registering with Agent with uds endpoint");
@@ -69,15 +90,68 @@ pub mod discovery_handler {
             endpoint,
             endpoint_type: endpoint_type as i32,
             shared,
+            api_version: super::super::DISCOVERY_API_VERSION.to_string(),
         };
         register_discovery_handler(&register_request).await?;
         let registration_handle = tokio::spawn(async move {
             register_discovery_handler_again(register_receiver, &register_request).await;
         });
-        tokio::try_join!(discovery_handle, registration_handle)?;
+        // The discovery server only returns once the Discovery Handler has been asked to terminate, at which point
+        // there is no point in registering again
+        discovery_handle.await?;
+        registration_handle.abort();
+        info!("run_discovery_handler - discovery server shut down, exiting");
         Ok(())
     }
 
+    /// Builds the network endpoint of the discovery server from the Pod's IP, IPv6 addresses are enclosed in
+    /// brackets (e.g. `[fd00::1]:10000`) so that the port can be told apart from the address.
+    fn get_network_endpoint(pod_ip: &str, port: u16) -> String {
+        let pod_ip = pod_ip.trim_start_matches('[').trim_end_matches(']');
+        match pod_ip.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port).to_string(),
+            Err(_) => format!("{}:{}", pod_ip, port),
+        }
+    }
+
+    /// Gets the port the discovery server listens on from the `DISCOVERY_PORT` environment variable, defaulting to
+    /// 10000 if unset.
+    fn get_discovery_port(env_var_query: &impl EnvVarQuery) -> Result<u16, anyhow::Error> {
+        match env_var_query.get_env_var(DISCOVERY_PORT_LABEL) {
+            Ok(port) => match port.parse::<u16>() {
+                Ok(port) if port != 0 => Ok(port),
+                _ => Err(anyhow::format_err!(
+                    "{} must be a port number between 1 and 65535, got {:?}",
+                    DISCOVERY_PORT_LABEL,
+                    port
+                )),
+            },
+            Err(_) => Ok(DEFAULT_DISCOVERY_PORT),
+        }
+    }
+
+    /// Records how long probing the device took as a device property, which the Agent reports as a metric
+    pub fn set_probe_latency(device: &mut Device, latency: Duration) {
+        device.properties.insert(
+            AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME.to_string(),
+            latency.as_millis().to_string(),
+        );
+    }
+
+    /// Returns the interval between two discovery passes requested by the Agent for the Configuration through the
+    /// reserved AKRI_DISCOVERY_POLL_INTERVAL_SECS discovery property, or `default` if none (or an invalid one) is given
+    pub fn get_discovery_interval(request: &DiscoverRequest, default: Duration) -> Duration {
+        request
+            .discovery_properties
+            .get(AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME)
+            .and_then(|property| property.vec.as_deref())
+            .and_then(|value| std::str::from_utf8(value).ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(default)
+    }
+
     /// This obtains the expected type `T` from a discovery details String by running it through function `f` which will
     /// attempt to deserialize the String.
     pub fn deserialize_discovery_details<T>(discovery_details: &str) -> Result<T, anyhow::Error>
@@ -92,6 +166,354 @@ pub mod discovery_handler {
         })?;
         Ok(discovery_handler_config)
     }
+
+    /// This deserializes discovery details as `T` and runs the Discovery Handler specific `check` on them, returning
+    /// the parsed details serialized back to YAML so that users can see how they were understood.
+    pub fn validate_discovery_details<T>(
+        discovery_details: &str,
+        check: impl FnOnce(&T) -> Result<(), anyhow::Error>,
+    ) -> Result<String, anyhow::Error>
+    where
+        T: serde::de::DeserializeOwned + serde::Serialize,
+    {
+        let discovery_handler_config: T = deserialize_discovery_details(discovery_details)?;
+        check(&discovery_handler_config)?;
+        Ok(serde_yaml::to_string(&discovery_handler_config)?)
+    }
+
+    /// If `--validate <file>` is among the given command line arguments, this validates the discovery details in the
+    /// file, prints the parsed details or the error, and returns the code the Discovery Handler should exit with.
+    /// Returns `None` when the flag is absent, in which case the Discovery Handler should run as usual.
+    pub fn validate_if_requested<T>(
+        mut args: impl Iterator<Item = String>,
+        check: impl FnOnce(&T) -> Result<(), anyhow::Error>,
+    ) -> Option<i32>
+    where
+        T: serde::de::DeserializeOwned + serde::Serialize,
+    {
+        args.find(|arg| arg == VALIDATE_FLAG)?;
+        let result = args
+            .next()
+            .ok_or_else(|| {
+                anyhow::format_err!(
+                    "{} expects the path of a file holding discovery details",
+                    VALIDATE_FLAG
+                )
+            })
+            .and_then(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::format_err!("Cannot read {}: {}", path, e))
+            })
+            .and_then(|discovery_details| validate_discovery_details(&discovery_details, check));
+        match result {
+            Ok(parsed) => {
+                println!("{}", parsed);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Invalid discovery details: {}", e);
+                Some(1)
+            }
+        }
+    }
+
+    /// Runs discovery with the given discovery details, without any discovery properties, and returns the devices
+    /// of the first response of the Discovery Handler.
+    pub async fn run_discovery_once(
+        discovery_handler: &impl DiscoveryHandler,
+        discovery_details: &str,
+    ) -> Result<Vec<Device>, tonic::Status> {
+        let stream = discovery_handler
+            .discover(tonic::Request::new(DiscoverRequest {
+                discovery_details: discovery_details.to_string(),
+                discovery_properties: Default::default(),
+            }))
+            .await?
+            .into_inner();
+        match Box::pin(stream).next().await {
+            Some(response) => Ok(response?.devices),
+            None => Err(tonic::Status::aborted(
+                "discovery ended before reporting any device",
+            )),
+        }
+    }
+
+    /// If `--once <file>` is among the given command line arguments, this runs discovery a single time with the
+    /// discovery details in the file, prints the discovered devices or the error, and returns the code the Discovery
+    /// Handler should exit with. Returns `None` when the flag is absent, in which case the Discovery Handler should
+    /// run as usual.
+    pub async fn discover_once_if_requested(
+        mut args: impl Iterator<Item = String>,
+        discovery_handler: &impl DiscoveryHandler,
+    ) -> Option<i32> {
+        args.find(|arg| arg == ONCE_FLAG)?;
+        let discovery_details = args
+            .next()
+            .ok_or_else(|| {
+                anyhow::format_err!(
+                    "{} expects the path of a file holding discovery details",
+                    ONCE_FLAG
+                )
+            })
+            .and_then(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::format_err!("Cannot read {}: {}", path, e))
+            });
+        let result = match discovery_details {
+            Ok(discovery_details) => run_discovery_once(discovery_handler, &discovery_details)
+                .await
+                .map_err(|e| anyhow::format_err!("{}", e.message())),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(devices) => {
+                println!("{} device(s) discovered", devices.len());
+                for device in devices {
+                    println!("{:#?}", device);
+                }
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Discovery failed: {}", e);
+                Some(1)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::{
+            mock_discovery_handler::MockDiscoveryHandler, server::internal_run_discovery_server,
+        };
+        use super::*;
+        use akri_shared::os::env_var::MockEnvVarQuery;
+        use std::collections::HashMap;
+
+        #[test]
+        fn test_get_network_endpoint() {
+            assert_eq!(get_network_endpoint("10.1.2.3", 10000), "10.1.2.3:10000");
+            assert_eq!(get_network_endpoint("fd00::1", 10000), "[fd00::1]:10000");
+            assert_eq!(get_network_endpoint("[fd00::1]", 10000), "[fd00::1]:10000");
+            for pod_ip in ["10.1.2.3", "fd00::1", "::1"] {
+                let endpoint = get_network_endpoint(pod_ip, 10000);
+                // The discovery server binds the endpoint and the Agent connects to it over http
+                assert!(endpoint.parse::<SocketAddr>().is_ok());
+                assert!(format!("http://{}", endpoint)
+                    .parse::<tonic::transport::Uri>()
+                    .is_ok());
+            }
+        }
+
+        #[test]
+        fn test_validate_if_requested() {
+            let args = |args: &[&str]| {
+                args.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .into_iter()
+            };
+            let no_check = |_: &HashMap<String, String>| Ok(());
+            assert_eq!(
+                validate_if_requested(args(&["discovery-handler"]), no_check),
+                None
+            );
+            assert_eq!(
+                validate_if_requested(args(&["discovery-handler", VALIDATE_FLAG]), no_check),
+                Some(1)
+            );
+
+            let mut details = tempfile::NamedTempFile::new().unwrap();
+            std::io::Write::write_all(&mut details, b"key: value").unwrap();
+            let path = details.path().to_str().unwrap();
+            assert_eq!(
+                validate_if_requested(args(&["discovery-handler", VALIDATE_FLAG, path]), no_check),
+                Some(0)
+            );
+            assert_eq!(
+                validate_if_requested(
+                    args(&["discovery-handler", VALIDATE_FLAG, path]),
+                    |_: &HashMap<String, String>| Err(anyhow::format_err!("rejected"))
+                ),
+                Some(1)
+            );
+        }
+
+        #[tokio::test]
+        async fn test_run_discovery_once() {
+            let device = Device {
+                id: "device-1".to_string(),
+                properties: HashMap::from([("KEY".to_string(), "value".to_string())]),
+                ..Default::default()
+            };
+            let discovery_handler = MockDiscoveryHandler {
+                return_error: false,
+                devices: vec![device.clone()],
+            };
+            assert_eq!(
+                run_discovery_once(&discovery_handler, "").await.unwrap(),
+                vec![device]
+            );
+
+            let discovery_handler = MockDiscoveryHandler {
+                return_error: true,
+                devices: Vec::new(),
+            };
+            assert_eq!(
+                run_discovery_once(&discovery_handler, "")
+                    .await
+                    .unwrap_err()
+                    .code(),
+                tonic::Code::InvalidArgument
+            );
+        }
+
+        #[tokio::test]
+        async fn test_discover_once_if_requested() {
+            let args = |args: &[&str]| {
+                args.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .into_iter()
+            };
+            let discovery_handler = MockDiscoveryHandler {
+                return_error: false,
+                devices: vec![Device {
+                    id: "device-1".to_string(),
+                    ..Default::default()
+                }],
+            };
+            assert_eq!(
+                discover_once_if_requested(args(&["discovery-handler"]), &discovery_handler).await,
+                None
+            );
+            assert_eq!(
+                discover_once_if_requested(
+                    args(&["discovery-handler", ONCE_FLAG]),
+                    &discovery_handler
+                )
+                .await,
+                Some(1)
+            );
+
+            let mut details = tempfile::NamedTempFile::new().unwrap();
+            std::io::Write::write_all(&mut details, b"key: value").unwrap();
+            let path = details.path().to_str().unwrap();
+            assert_eq!(
+                discover_once_if_requested(
+                    args(&["discovery-handler", ONCE_FLAG, path]),
+                    &discovery_handler
+                )
+                .await,
+                Some(0)
+            );
+            let failing_discovery_handler = MockDiscoveryHandler {
+                return_error: true,
+                devices: Vec::new(),
+            };
+            assert_eq!(
+                discover_once_if_requested(
+                    args(&["discovery-handler", ONCE_FLAG, path]),
+                    &failing_discovery_handler
+                )
+                .await,
+                Some(1)
+            );
+        }
+
+        #[test]
+        fn test_get_discovery_interval() {
+            let default = Duration::from_secs(10);
+            let request = |value: Option<&str>| DiscoverRequest {
+                discovery_details: String::new(),
+                discovery_properties: value
+                    .map(|v| {
+                        HashMap::from([(
+                            AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME.to_string(),
+                            super::super::v0::ByteData {
+                                vec: Some(v.as_bytes().to_vec()),
+                            },
+                        )])
+                    })
+                    .unwrap_or_default(),
+            };
+            assert_eq!(get_discovery_interval(&request(None), default), default);
+            assert_eq!(
+                get_discovery_interval(&request(Some("300")), default),
+                Duration::from_secs(300)
+            );
+            for invalid in ["0", "-1", "5m", ""] {
+                assert_eq!(
+                    get_discovery_interval(&request(Some(invalid)), default),
+                    default
+                );
+            }
+        }
+
+        #[test]
+        fn test_get_discovery_port() {
+            let mut env = MockEnvVarQuery::new();
+            env.expect_get_env_var()
+                .returning(|_| Err(std::env::VarError::NotPresent));
+            assert_eq!(get_discovery_port(&env).unwrap(), DEFAULT_DISCOVERY_PORT);
+
+            for invalid in ["0", "65536", "-1", "port"] {
+                let mut env = MockEnvVarQuery::new();
+                env.expect_get_env_var()
+                    .returning(move |_| Ok(invalid.to_string()));
+                let e = get_discovery_port(&env).unwrap_err();
+                assert!(e.to_string().contains(DISCOVERY_PORT_LABEL));
+            }
+        }
+
+        #[tokio::test]
+        async fn test_discovery_port_override() {
+            let mut env = MockEnvVarQuery::new();
+            env.expect_get_env_var()
+                .withf(|name| name == DISCOVERY_PORT_LABEL)
+                .returning(|_| Ok("10042".to_string()));
+            let endpoint = get_network_endpoint("127.0.0.1", get_discovery_port(&env).unwrap());
+            assert_eq!(endpoint, "127.0.0.1:10042");
+
+            let discovery_handler_temp_dir = tempfile::Builder::new()
+                .prefix("discovery-handlers")
+                .tempdir()
+                .unwrap();
+            let server_endpoint = endpoint.clone();
+            let server_dir = discovery_handler_temp_dir
+                .path()
+                .to_str()
+                .unwrap()
+                .to_string();
+            tokio::spawn(async move {
+                internal_run_discovery_server(
+                    MockDiscoveryHandler {
+                        return_error: false,
+                        devices: Vec::new(),
+                    },
+                    &server_endpoint,
+                    &server_dir,
+                )
+                .await
+                .unwrap();
+            });
+            // The Agent connects to the endpoint it is registered with
+            let uri = format!("http://{}", endpoint);
+            let mut connected = false;
+            for _ in 0..50 {
+                if tonic::transport::Endpoint::from_shared(uri.clone())
+                    .unwrap()
+                    .connect()
+                    .await
+                    .is_ok()
+                {
+                    connected = true;
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert!(connected);
+        }
+    }
 }
 
 #[cfg(any(feature = "mock-discovery-handler", test))]
@@ -189,14 +611,275 @@ pub mod mock_discovery_handler {
     }
 }
 
+/// Client side of the Discovery API, to query a Discovery Handler the way the Agent does.
+pub mod client {
+    use super::v0::{
+        discovery_handler_client::DiscoveryHandlerClient,
+        register_discovery_handler_request::EndpointType, ByteData, Device, DiscoverRequest,
+        RegisterDiscoveryHandlerRequest,
+    };
+    use futures::{Stream, StreamExt};
+    use log::error;
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+    use tonic::transport::{Channel, Endpoint, Uri};
+
+    /// Endpoint a Discovery Handler serves its discovery service on
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum DiscoveryEndpoint {
+        /// Path of a unix domain socket
+        Uds(String),
+        /// URL of a network endpoint (e.g. `http://10.1.2.3:10000`)
+        Network(String),
+    }
+
+    impl DiscoveryEndpoint {
+        /// Endpoint a Discovery Handler advertised in its registration request
+        pub fn from_registration(request: &RegisterDiscoveryHandlerRequest) -> Self {
+            match EndpointType::try_from(request.endpoint_type) {
+                Ok(EndpointType::Network) => DiscoveryEndpoint::Network(request.endpoint.clone()),
+                _ => DiscoveryEndpoint::Uds(request.endpoint.clone()),
+            }
+        }
+    }
+
+    /// Client of a Discovery Handler's discovery service
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_discovery_utils::discovery::client::{DiscoveryClient, DiscoveryEndpoint};
+    /// use futures::StreamExt;
+    ///
+    /// # async fn print_discovered_devices() {
+    /// let endpoint = DiscoveryEndpoint::Uds("/var/lib/akri/onvif.sock".to_string());
+    /// let mut client = DiscoveryClient::connect(&endpoint).await.unwrap();
+    /// let devices = client
+    ///     .discover([("discoveryTimeoutSeconds".to_string(), "2".to_string())].into())
+    ///     .await;
+    /// futures::pin_mut!(devices);
+    /// while let Some(device) = devices.next().await {
+    ///     println!("discovered device {}", device.id);
+    /// }
+    /// # }
+    /// ```
+    #[derive(Clone, Debug)]
+    pub struct DiscoveryClient {
+        inner: DiscoveryHandlerClient<Channel>,
+    }
+
+    impl DiscoveryClient {
+        /// Connects to the Discovery Handler serving on the given endpoint
+        pub async fn connect(
+            endpoint: &DiscoveryEndpoint,
+        ) -> Result<Self, tonic::transport::Error> {
+            let channel = match endpoint {
+                DiscoveryEndpoint::Uds(socket) => {
+                    let socket = socket.clone();
+                    // We will ignore this dummy uri because UDS does not use it.
+                    Endpoint::try_from("http://[::1]:50051")?
+                        .connect_with_connector(tower::service_fn(move |_: Uri| {
+                            tokio::net::UnixStream::connect(socket.clone())
+                        }))
+                        .await?
+                }
+                DiscoveryEndpoint::Network(url) => {
+                    Endpoint::from_shared(url.clone())?.connect().await?
+                }
+            };
+            Ok(DiscoveryClient {
+                inner: DiscoveryHandlerClient::new(channel),
+            })
+        }
+
+        /// Starts a discovery with the given details, passed to the Discovery Handler as a YAML
+        /// mapping, and streams the discovered devices. Every time the Discovery Handler reports a
+        /// change, all the devices it currently discovers are streamed again. The stream ends when
+        /// the discovery does, a failure to start or continue it is logged.
+        pub async fn discover(
+            &mut self,
+            details: HashMap<String, String>,
+        ) -> impl Stream<Item = Device> {
+            let devices = match serde_yaml::to_string(&details) {
+                Ok(discovery_details) => self
+                    .discover_device_lists(&discovery_details, HashMap::new())
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            let devices = match devices {
+                Ok(devices) => devices.left_stream(),
+                Err(e) => {
+                    error!("discover - could not start discovery: {}", e);
+                    futures::stream::empty().right_stream()
+                }
+            };
+            devices
+                .take_while(|devices| {
+                    if let Err(e) = devices {
+                        error!("discover - discovery ended with error: {}", e);
+                    }
+                    futures::future::ready(devices.is_ok())
+                })
+                .flat_map(|devices| futures::stream::iter(devices.unwrap_or_default()))
+        }
+
+        /// Starts a discovery with the given details and properties. Each item of the returned
+        /// stream is the full list of devices currently discovered, sent every time it changes.
+        pub async fn discover_device_lists(
+            &mut self,
+            discovery_details: &str,
+            discovery_properties: HashMap<String, ByteData>,
+        ) -> Result<impl Stream<Item = Result<Vec<Device>, tonic::Status>>, tonic::Status> {
+            let stream = self
+                .inner
+                .discover(tonic::Request::new(DiscoverRequest {
+                    discovery_details: discovery_details.to_string(),
+                    discovery_properties,
+                }))
+                .await?
+                .into_inner();
+            Ok(stream.map(|response| response.map(|response| response.devices)))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::mock_discovery_handler::{
+            get_mock_discovery_handler_dir_and_endpoint, run_mock_discovery_handler,
+        };
+        use super::*;
+
+        #[test]
+        fn test_discovery_endpoint_from_registration() {
+            let mut request = RegisterDiscoveryHandlerRequest {
+                name: "protocol".to_string(),
+                endpoint: "/var/lib/akri/protocol.sock".to_string(),
+                endpoint_type: EndpointType::Uds as i32,
+                shared: false,
+                api_version: String::new(),
+            };
+            assert_eq!(
+                DiscoveryEndpoint::from_registration(&request),
+                DiscoveryEndpoint::Uds("/var/lib/akri/protocol.sock".to_string())
+            );
+            request.endpoint = "http://10.1.2.3:10000".to_string();
+            request.endpoint_type = EndpointType::Network as i32;
+            assert_eq!(
+                DiscoveryEndpoint::from_registration(&request),
+                DiscoveryEndpoint::Network("http://10.1.2.3:10000".to_string())
+            );
+        }
+
+        #[tokio::test]
+        async fn test_discover_uds() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("client.sock");
+            let device = Device {
+                id: "device-1".to_string(),
+                ..Default::default()
+            };
+            let _handle = run_mock_discovery_handler(
+                &discovery_handler_dir,
+                &discovery_handler_socket,
+                false,
+                vec![device.clone()],
+            )
+            .await;
+            let mut client =
+                DiscoveryClient::connect(&DiscoveryEndpoint::Uds(discovery_handler_socket))
+                    .await
+                    .unwrap();
+            let mut devices = Box::pin(
+                client
+                    .discover_device_lists("", HashMap::new())
+                    .await
+                    .unwrap(),
+            );
+            assert_eq!(devices.next().await.unwrap().unwrap(), vec![device]);
+        }
+
+        #[tokio::test]
+        async fn test_discover_devices() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("client-devices.sock");
+            let devices = vec![
+                Device {
+                    id: "device-1".to_string(),
+                    ..Default::default()
+                },
+                Device {
+                    id: "device-2".to_string(),
+                    ..Default::default()
+                },
+            ];
+            let _handle = run_mock_discovery_handler(
+                &discovery_handler_dir,
+                &discovery_handler_socket,
+                false,
+                devices.clone(),
+            )
+            .await;
+            let mut client =
+                DiscoveryClient::connect(&DiscoveryEndpoint::Uds(discovery_handler_socket))
+                    .await
+                    .unwrap();
+            let discovered = client
+                .discover(HashMap::from([("key".to_string(), "value".to_string())]))
+                .await
+                .take(2)
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(discovered, devices);
+        }
+
+        #[tokio::test]
+        async fn test_discover_error() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("client-error.sock");
+            let _handle = run_mock_discovery_handler(
+                &discovery_handler_dir,
+                &discovery_handler_socket,
+                true,
+                Vec::new(),
+            )
+            .await;
+            let mut client =
+                DiscoveryClient::connect(&DiscoveryEndpoint::Uds(discovery_handler_socket))
+                    .await
+                    .unwrap();
+            assert_eq!(
+                client
+                    .discover_device_lists("", HashMap::new())
+                    .await
+                    .err()
+                    .unwrap()
+                    .code(),
+                tonic::Code::InvalidArgument
+            );
+
+            // The devices stream of a discovery that failed to start is empty
+            assert_eq!(client.discover(HashMap::new()).await.count().await, 0);
+        }
+    }
+}
+
 pub mod server {
     use super::v0::discovery_handler_server::{DiscoveryHandler, DiscoveryHandlerServer};
     use akri_shared::uds::unix_stream;
+    use futures::Future;
     use futures::TryFutureExt;
-    use log::info;
+    use log::{error, info};
     use std::path::Path;
+    use std::time::Duration;
     use tokio::net::UnixListener;
-    use tonic::transport::Server;
+    use tokio::signal::unix::{signal, SignalKind};
+    use tonic::{server::NamedService, transport::Server};
+    use tonic_health::{server::HealthReporter, ServingStatus};
+
+    /// Time given to in-flight discover streams to finish once the Discovery Handler is asked to terminate, after
+    /// which they are cancelled. Discover streams usually never end on their own, so this bounds the shutdown time.
+    pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
 
     pub async fn run_discovery_server(
         discovery_handler: impl DiscoveryHandler,
@@ -210,15 +893,101 @@ pub mod server {
         .await
     }
 
+    /// Sets the serving status of the DiscoveryHandler service and of the server as a whole (empty service name)
+    async fn set_health_status<T: DiscoveryHandler>(
+        health_reporter: &mut HealthReporter,
+        status: ServingStatus,
+    ) {
+        for service in ["", <DiscoveryHandlerServer<T> as NamedService>::NAME] {
+            health_reporter.set_service_status(service, status).await;
+        }
+    }
+
+    /// Resolves once the process is asked to terminate (SIGTERM)
+    async fn terminate_signal() {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!(
+                    "terminate_signal - cannot listen for termination signal: {}",
+                    e
+                );
+                futures::future::pending::<()>().await;
+            }
+        }
+    }
+
+    /// Resolves once `shutdown` resolves, after reporting the server as not serving anymore and notifying `stopping`
+    async fn shutdown_signal<T: DiscoveryHandler>(
+        shutdown: impl Future<Output = ()>,
+        mut health_reporter: HealthReporter,
+        stopping: tokio::sync::oneshot::Sender<()>,
+    ) {
+        shutdown.await;
+        info!("shutdown_signal - terminating, reporting NOT_SERVING");
+        set_health_status::<T>(&mut health_reporter, ServingStatus::NotServing).await;
+        stopping.send(()).unwrap_or(());
+    }
+
+    /// Drives the server until it has drained its connections after shutting down or until `grace_period` has
+    /// elapsed since it started shutting down, in which case the server, along with any in-flight stream, is dropped.
+    async fn serve_with_grace_period(
+        server: impl Future<Output = Result<(), tonic::transport::Error>>,
+        stopping: tokio::sync::oneshot::Receiver<()>,
+        grace_period: Duration,
+    ) -> Result<(), tonic::transport::Error> {
+        let cancel = async move {
+            match stopping.await {
+                Ok(_) => tokio::time::sleep(grace_period).await,
+                // The server returned before shutting down
+                Err(_) => futures::future::pending::<()>().await,
+            }
+        };
+        tokio::select! {
+            result = server => result,
+            _ = cancel => {
+                info!("serve_with_grace_period - grace period elapsed, cancelling in-flight streams");
+                Ok(())
+            }
+        }
+    }
+
     /// Creates a DiscoveryHandlerServer for the given Discovery Handler at the specified endpoint Verifies the endpoint
     /// by checking that it is in the discovery handler directory if it is UDS or that it is a valid IP address and
     /// port.
-    pub async fn internal_run_discovery_server(
-        discovery_handler: impl DiscoveryHandler,
+    /// The standard gRPC Health Checking service (`grpc.health.v1.Health`) is served alongside it, reporting `SERVING`
+    /// once the endpoint is bound and `NOT_SERVING` while shutting down.
+    /// On SIGTERM, in-flight discover streams are given `SHUTDOWN_GRACE_PERIOD` to finish before being cancelled, the
+    /// socket (if UDS) is removed and `Ok` is returned.
+    pub async fn internal_run_discovery_server<T: DiscoveryHandler>(
+        discovery_handler: T,
+        discovery_endpoint: &str,
+        discovery_handler_directory: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        run_discovery_server_until(
+            discovery_handler,
+            discovery_endpoint,
+            discovery_handler_directory,
+            terminate_signal(),
+            SHUTDOWN_GRACE_PERIOD,
+        )
+        .await
+    }
+
+    /// Serves the Discovery Handler until `shutdown` resolves. See `internal_run_discovery_server`.
+    async fn run_discovery_server_until<T: DiscoveryHandler>(
+        discovery_handler: T,
         discovery_endpoint: &str,
         discovery_handler_directory: &str,
+        shutdown: impl Future<Output = ()>,
+        grace_period: Duration,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         info!("internal_run_discovery_server - entered");
+        let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+        let (stopping_sender, stopping_receiver) = tokio::sync::oneshot::channel();
+        set_health_status::<T>(&mut health_reporter, ServingStatus::NotServing).await;
 
         if discovery_endpoint.starts_with(discovery_handler_directory) {
             tokio::fs::create_dir_all(Path::new(discovery_endpoint).parent().unwrap()).await?;
@@ -226,6 +995,7 @@ pub mod server {
             std::fs::remove_file(discovery_endpoint).unwrap_or(());
             let incoming = {
                 let uds = UnixListener::bind(discovery_endpoint)?;
+                set_health_status::<T>(&mut health_reporter, ServingStatus::Serving).await;
 
                 async_stream::stream! {
                     loop {
@@ -234,17 +1004,28 @@ pub mod server {
                     }
                 }
             };
-            Server::builder()
+            let server = Server::builder()
+                .add_service(health_service)
                 .add_service(DiscoveryHandlerServer::new(discovery_handler))
-                .serve_with_incoming(incoming)
-                .await?;
+                .serve_with_incoming_shutdown(
+                    incoming,
+                    shutdown_signal::<T>(shutdown, health_reporter, stopping_sender),
+                );
+            let result = serve_with_grace_period(server, stopping_receiver, grace_period).await;
             std::fs::remove_file(discovery_endpoint).unwrap_or(());
+            result?;
         } else {
             let addr = discovery_endpoint.parse()?;
-            Server::builder()
+            // The server binds the address when it starts serving
+            set_health_status::<T>(&mut health_reporter, ServingStatus::Serving).await;
+            let server = Server::builder()
+                .add_service(health_service)
                 .add_service(DiscoveryHandlerServer::new(discovery_handler))
-                .serve(addr)
-                .await?;
+                .serve_with_shutdown(
+                    addr,
+                    shutdown_signal::<T>(shutdown, health_reporter, stopping_sender),
+                );
+            serve_with_grace_period(server, stopping_receiver, grace_period).await?;
         }
         info!("internal_run_discovery_server - finished");
         Ok(())
@@ -257,9 +1038,12 @@ pub mod server {
                 get_mock_discovery_handler_dir_and_endpoint, run_mock_discovery_handler,
                 MockDiscoveryHandler,
             },
-            v0::{discovery_handler_client::DiscoveryHandlerClient, DiscoverRequest},
+            v0::{
+                discovery_handler_client::DiscoveryHandlerClient, DiscoverRequest, DiscoverResponse,
+            },
         };
         use super::*;
+        use async_trait::async_trait;
         use std::collections::HashMap;
         use std::convert::TryFrom;
         use tempfile::Builder;
@@ -268,6 +1052,9 @@ pub mod server {
             transport::{Endpoint, Uri},
             Request,
         };
+        use tonic_health::pb::{
+            health_check_response, health_client::HealthClient, HealthCheckRequest,
+        };
 
         #[tokio::test]
         async fn test_run_discovery_server_uds() {
@@ -299,6 +1086,123 @@ pub mod server {
             assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
         }
 
+        #[tokio::test]
+        async fn test_run_discovery_server_health_check() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("protocol.sock");
+            let _handle: tokio::task::JoinHandle<()> = run_mock_discovery_handler(
+                &discovery_handler_dir,
+                &discovery_handler_socket,
+                false,
+                Vec::new(),
+            )
+            .await;
+            let channel = Endpoint::try_from("http://[::1]:50051")
+                .unwrap()
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    UnixStream::connect(discovery_handler_socket.clone())
+                }))
+                .await
+                .unwrap();
+            let mut health_client = HealthClient::new(channel);
+            for service in [
+                "",
+                <DiscoveryHandlerServer<MockDiscoveryHandler> as NamedService>::NAME,
+            ] {
+                let response = health_client
+                    .check(Request::new(HealthCheckRequest {
+                        service: service.to_string(),
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                assert_eq!(
+                    response.status(),
+                    health_check_response::ServingStatus::Serving
+                );
+            }
+        }
+
+        /// Discovery handler whose discover streams send a single (empty) response and then stay open
+        struct EndlessDiscoveryHandler;
+
+        #[async_trait]
+        impl DiscoveryHandler for EndlessDiscoveryHandler {
+            type DiscoverStream = super::super::DiscoverStream;
+            async fn discover(
+                &self,
+                _: tonic::Request<DiscoverRequest>,
+            ) -> Result<tonic::Response<Self::DiscoverStream>, tonic::Status> {
+                let (sender, receiver) = tokio::sync::mpsc::channel(1);
+                tokio::spawn(async move {
+                    sender
+                        .send(Ok(DiscoverResponse {
+                            devices: Vec::new(),
+                        }))
+                        .await
+                        .unwrap();
+                    // Hold on to the sender so the stream never ends
+                    sender.closed().await;
+                });
+                Ok(tonic::Response::new(
+                    tokio_stream::wrappers::ReceiverStream::new(receiver),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_run_discovery_server_shutdown_during_stream() {
+            let (discovery_handler_dir, discovery_handler_socket) =
+                get_mock_discovery_handler_dir_and_endpoint("protocol.sock");
+            let (terminate_sender, terminate_receiver) = tokio::sync::oneshot::channel::<()>();
+            let server_dir = discovery_handler_dir.clone();
+            let server_socket = discovery_handler_socket.clone();
+            let server_handle = tokio::spawn(async move {
+                run_discovery_server_until(
+                    EndlessDiscoveryHandler,
+                    &server_socket,
+                    &server_dir,
+                    async move {
+                        terminate_receiver.await.unwrap();
+                    },
+                    Duration::from_millis(100),
+                )
+                .await
+                .is_ok()
+            });
+            unix_stream::try_connect(&discovery_handler_socket)
+                .await
+                .unwrap();
+            let socket_clone = discovery_handler_socket.clone();
+            let channel = Endpoint::try_from("http://[::1]:50051")
+                .unwrap()
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    UnixStream::connect(socket_clone.clone())
+                }))
+                .await
+                .unwrap();
+            let mut discovery_handler_client = DiscoveryHandlerClient::new(channel);
+            let mut stream = discovery_handler_client
+                .discover(Request::new(DiscoverRequest {
+                    discovery_details: String::new(),
+                    discovery_properties: HashMap::new(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(stream.message().await.unwrap().unwrap().devices.is_empty());
+
+            terminate_sender.send(()).unwrap();
+            let server_result = tokio::time::timeout(Duration::from_secs(5), server_handle)
+                .await
+                .expect("server should shut down despite the in-flight stream")
+                .unwrap();
+            assert!(server_result);
+            assert!(!Path::new(&discovery_handler_socket).exists());
+            // The in-flight stream has been cancelled
+            assert!(!matches!(stream.message().await, Ok(Some(_))));
+        }
+
         // Test when improper socket path or IP address is given as an endpoint
         #[tokio::test]
         async fn test_run_discovery_server_error_invalid_ip_addr() {
@@ -18,6 +18,10 @@ pub struct RegisterDiscoveryHandlerRequest {
     /// or can only be ever be discovered by a single node (e.g. a local USB device)
     #[prost(bool, tag = "4")]
     pub shared: bool,
+    /// Version of the discovery API implemented by the registering `DiscoveryHandler` (e.g. `v0`).
+    /// An empty version is considered to be the initial `v0` version.
+    #[prost(string, tag = "5")]
+    pub api_version: ::prost::alloc::string::String,
 }
 /// Nested message and enum types in `RegisterDiscoveryHandlerRequest`.
 pub mod register_discovery_handler_request {
@@ -51,6 +55,22 @@ pub mod register_discovery_handler_request {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReportBackendLivenessRequest {
+    /// Name of the reporting `DiscoveryHandler`, as registered
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Endpoint of the reporting `DiscoveryHandler`, as registered
+    #[prost(string, tag = "2")]
+    pub endpoint: ::prost::alloc::string::String,
+    /// Whether the backend the `DiscoveryHandler` discovers devices through is reachable
+    #[prost(bool, tag = "3")]
+    pub alive: bool,
+    /// Reason the backend is down, reported to users
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Empty {}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -95,6 +115,17 @@ pub struct Device {
     /// Optionally specify device information to be mounted for Pods that request this device as a resource
     #[prost(message, repeated, tag = "4")]
     pub device_specs: ::prost::alloc::vec::Vec<DeviceSpec>,
+    /// Optional non-fatal warning about this device (e.g. authentication issue or slow response), its
+    /// properties are complete. The device is still discovered, the warning is surfaced as an annotation
+    /// on the device's Instance and as an environment variable in the device's broker Pods.
+    #[prost(string, optional, tag = "5")]
+    pub last_warning: ::core::option::Option<::prost::alloc::string::String>,
+    /// Optional non-fatal error hit while gathering information about this device (e.g. failing to fetch
+    /// its metadata), its properties are incomplete. The device is still discovered, with partial
+    /// properties, and the error is surfaced in the properties of the device's Instance. Unlike
+    /// `last_warning`, it is part of the device's properties, so the Instance changes once it clears.
+    #[prost(string, optional, tag = "6")]
+    pub error: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// From Device Plugin  API
 /// Mount specifies a host volume to mount into a container.
@@ -236,6 +267,27 @@ pub mod registration_client {
             ));
             self.inner.unary(req, path, codec).await
         }
+        /// Optionally called by a registered `DiscoveryHandler` whenever the liveness of the backend
+        /// it discovers devices through changes. The Akri Agent skips discovery for the Configurations
+        /// using that `DiscoveryHandler` while its backend is down.
+        pub async fn report_backend_liveness(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReportBackendLivenessRequest>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/v0.Registration/ReportBackendLiveness");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("v0.Registration", "ReportBackendLiveness"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated client implementations.
@@ -355,6 +407,13 @@ pub mod registration_server {
             &self,
             request: tonic::Request<super::RegisterDiscoveryHandlerRequest>,
         ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
+        /// Optionally called by a registered `DiscoveryHandler` whenever the liveness of the backend
+        /// it discovers devices through changes. The Akri Agent skips discovery for the Configurations
+        /// using that `DiscoveryHandler` while its backend is down.
+        async fn report_backend_liveness(
+            &self,
+            request: tonic::Request<super::ReportBackendLivenessRequest>,
+        ) -> std::result::Result<tonic::Response<super::Empty>, tonic::Status>;
     }
     /// Registration is the service advertised by the Akri Agent.
     /// Any `DiscoveryHandler` can register with the Akri Agent.
@@ -478,6 +537,49 @@ pub mod registration_server {
                     };
                     Box::pin(fut)
                 }
+                "/v0.Registration/ReportBackendLiveness" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReportBackendLivenessSvc<T: Registration>(pub Arc<T>);
+                    impl<T: Registration>
+                        tonic::server::UnaryService<super::ReportBackendLivenessRequest>
+                        for ReportBackendLivenessSvc<T>
+                    {
+                        type Response = super::Empty;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReportBackendLivenessRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Registration>::report_backend_liveness(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReportBackendLivenessSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
@@ -1,3 +1,8 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Mutex, OnceLock};
+
 /// This defines the types of supported filters
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum FilterType {
@@ -14,13 +19,29 @@ fn default_action() -> FilterType {
     FilterType::Include
 }
 
+/// This defines how the items of a filter list are matched
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum MatchType {
+    /// An item only matches an identical value
+    #[default]
+    Exact,
+    /// An item is a regular expression that must match the whole value
+    Regex,
+}
+
+impl MatchType {
+    fn is_exact(&self) -> bool {
+        *self == MatchType::Exact
+    }
+}
+
 /// This defines a filter list.
 ///
 /// The items list can either define the only acceptable
 /// items (Include) or can define the only unacceptable items
 /// (Exclude)
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", try_from = "UncheckedFilterList")]
 pub struct FilterList {
     /// This defines a list of items that will be evaluated as part
     /// of the filtering process
@@ -30,6 +51,79 @@ pub struct FilterList {
     /// is `Include`
     #[serde(default = "default_action")]
     pub action: FilterType,
+    /// This defines how items are matched.  The default is `Exact`
+    #[serde(default, skip_serializing_if = "MatchType::is_exact")]
+    pub match_type: MatchType,
+}
+
+/// Filter list as deserialized, before its regular expressions (if any) are checked
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UncheckedFilterList {
+    items: Vec<String>,
+    #[serde(default = "default_action")]
+    action: FilterType,
+    #[serde(default)]
+    match_type: MatchType,
+}
+
+impl TryFrom<UncheckedFilterList> for FilterList {
+    type Error = String;
+
+    fn try_from(filter_list: UncheckedFilterList) -> Result<Self, Self::Error> {
+        if filter_list.match_type == MatchType::Regex {
+            for pattern in &filter_list.items {
+                compile_pattern(pattern)
+                    .map_err(|e| format!("invalid regex ({:?}) in filter list: {}", pattern, e))?;
+            }
+        }
+        Ok(FilterList {
+            items: filter_list.items,
+            action: filter_list.action,
+            match_type: filter_list.match_type,
+        })
+    }
+}
+
+/// Maximum number of compiled filter list regular expressions kept around
+const MAX_CACHED_PATTERNS: usize = 256;
+
+/// Compiled filter list regular expressions, by pattern
+static PATTERNS: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+
+/// Compiles a filter list regular expression (anchored to match whole values), compiled patterns
+/// are reused for every evaluation. The cache is emptied once it holds `MAX_CACHED_PATTERNS` of
+/// them, so that patterns of filter lists no longer in use do not pile up.
+fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut patterns = PATTERNS.get_or_init(Default::default).lock().unwrap();
+    if let Some(regex) = patterns.get(pattern) {
+        return Ok(regex.clone());
+    }
+    let regex = Regex::new(&format!("^(?:{})$", pattern))?;
+    if patterns.len() >= MAX_CACHED_PATTERNS {
+        patterns.clear();
+    }
+    patterns.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// This tests whether an item matches one of the items of the `FilterList`
+fn matches_any(filter_list: &FilterList, item: &str) -> bool {
+    match filter_list.match_type {
+        MatchType::Exact => filter_list.items.contains(&item.to_string()),
+        MatchType::Regex => {
+            filter_list
+                .items
+                .iter()
+                .any(|pattern| match compile_pattern(pattern) {
+                    Ok(regex) => regex.is_match(item),
+                    Err(e) => {
+                        log::error!("matches_any - ignoring invalid regex {:?}: {}", pattern, e);
+                        false
+                    }
+                })
+        }
+    }
 }
 
 /// This tests whether an item should be included according to the `FilterList`
@@ -37,7 +131,7 @@ pub fn should_include(filter_list: Option<&FilterList>, item: &str) -> bool {
     if filter_list.is_none() {
         return true;
     }
-    let item_contained = filter_list.unwrap().items.contains(&item.to_string());
+    let item_contained = matches_any(filter_list.unwrap(), item);
     if filter_list.as_ref().unwrap().action == FilterType::Include {
         item_contained
     } else {
@@ -56,6 +150,7 @@ mod tests {
         let exclude_filter_list = Some(FilterList {
             items: exclude_items,
             action: FilterType::Exclude,
+            match_type: MatchType::Exact,
         });
         assert!(!should_include(exclude_filter_list.as_ref(), "beep"));
         assert!(!should_include(exclude_filter_list.as_ref(), "bop"));
@@ -66,6 +161,7 @@ mod tests {
         let empty_exclude_filter_list = Some(FilterList {
             items: empty_exclude_items,
             action: FilterType::Exclude,
+            match_type: MatchType::Exact,
         });
         assert!(should_include(empty_exclude_filter_list.as_ref(), "beep"));
 
@@ -74,6 +170,7 @@ mod tests {
         let include_filter_list = Some(FilterList {
             items: include_items,
             action: FilterType::Include,
+            match_type: MatchType::Exact,
         });
         assert!(should_include(include_filter_list.as_ref(), "beep"));
         assert!(should_include(include_filter_list.as_ref(), "bop"));
@@ -84,10 +181,59 @@ mod tests {
         let empty_include_filter_list = Some(FilterList {
             items: empty_include_items,
             action: FilterType::Include,
+            match_type: MatchType::Exact,
         });
         assert!(!should_include(empty_include_filter_list.as_ref(), "beep"));
 
         // Test when None
         assert!(should_include(None, "beep"));
     }
+
+    #[test]
+    fn test_should_include_regex() {
+        let include_filter_list: FilterList =
+            serde_yaml::from_str(r"{items: ['10\.0\..*', '00:11:22:.*'], matchType: Regex}")
+                .unwrap();
+        assert_eq!(include_filter_list.action, FilterType::Include);
+        assert!(should_include(Some(&include_filter_list), "10.0.1.2"));
+        assert!(should_include(
+            Some(&include_filter_list),
+            "00:11:22:33:44:55"
+        ));
+        // Patterns must match the whole item
+        assert!(!should_include(Some(&include_filter_list), "110.0.1.2"));
+        assert!(!should_include(Some(&include_filter_list), "10.1.1.2"));
+
+        let exclude_filter_list = FilterList {
+            items: vec!["mock-.*".to_string()],
+            action: FilterType::Exclude,
+            match_type: MatchType::Regex,
+        };
+        assert!(!should_include(Some(&exclude_filter_list), "mock-camera"));
+        assert!(should_include(Some(&exclude_filter_list), "camera"));
+
+        // Without a match type, items are matched exactly
+        let exact_filter_list: FilterList = serde_yaml::from_str("items: ['mock-.*']").unwrap();
+        assert_eq!(exact_filter_list.match_type, MatchType::Exact);
+        assert!(!should_include(Some(&exact_filter_list), "mock-camera"));
+        assert!(should_include(Some(&exact_filter_list), "mock-.*"));
+    }
+
+    #[test]
+    fn test_deserialize_invalid_regex() {
+        let err = serde_yaml::from_str::<FilterList>("{items: ['10.0.('], matchType: Regex}")
+            .unwrap_err();
+        assert!(err.to_string().contains(r#"invalid regex ("10.0.(")"#));
+        // Not a regular expression when matching exactly
+        assert!(serde_yaml::from_str::<FilterList>("items: ['10.0.(']").is_ok());
+    }
+
+    #[test]
+    fn test_compile_pattern_cache_is_bounded() {
+        for i in 0..=MAX_CACHED_PATTERNS {
+            assert!(compile_pattern(&format!("bounded-{}", i)).is_ok());
+        }
+        assert!(PATTERNS.get().unwrap().lock().unwrap().len() <= MAX_CACHED_PATTERNS);
+        assert!(compile_pattern("bounded-0").unwrap().is_match("bounded-0"));
+    }
 }
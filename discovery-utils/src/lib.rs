@@ -5,6 +5,9 @@ pub mod registration_client;
 #[macro_use]
 extern crate serde_derive;
 
+/// Version of the discovery API implemented by this crate, sent by Discovery Handlers upon registration
+pub const DISCOVERY_API_VERSION: &str = "v0";
+
 /// Path of the Agent registration socket
 pub const AGENT_REGISTRATION_SOCKET_NAME: &str = "agent-registration.sock";
 
@@ -1,5 +1,6 @@
 use super::discovery::v0::{
     registration_client::RegistrationClient, RegisterDiscoveryHandlerRequest,
+    ReportBackendLivenessRequest,
 };
 use log::{info, trace};
 use std::convert::TryFrom;
@@ -34,6 +35,35 @@ pub async fn register_discovery_handler(
     Ok(())
 }
 
+/// Reports to the Agent whether the backend a registered Discovery Handler discovers devices through is
+/// reachable. While it is reported down, the Agent skips discovery for the Configurations using this
+/// Discovery Handler. Reporting is optional, Discovery Handlers that never report are considered alive.
+pub async fn report_backend_liveness(
+    register_request: &RegisterDiscoveryHandlerRequest,
+    alive: bool,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    trace!(
+        "report_backend_liveness - reporting backend of {} as {}",
+        register_request.name,
+        if alive { "alive" } else { "down" }
+    );
+    let channel = Endpoint::try_from("http://[::1]:50051")?
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            tokio::net::UnixStream::connect(super::get_registration_socket())
+        }))
+        .await?;
+    let mut client = RegistrationClient::new(channel);
+    let request = Request::new(ReportBackendLivenessRequest {
+        name: register_request.name.clone(),
+        endpoint: register_request.endpoint.clone(),
+        alive,
+        message: message.to_string(),
+    });
+    client.report_backend_liveness(request).await?;
+    Ok(())
+}
+
 /// Continually waits for message to re-register with an Agent
 pub async fn register_discovery_handler_again(
     mut register_receiver: tokio::sync::mpsc::Receiver<()>,
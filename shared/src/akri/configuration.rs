@@ -4,7 +4,9 @@
 // in favor of camelCase)
 //
 #![allow(non_camel_case_types)]
+use k8s_openapi::api::apps::v1::DeploymentSpec;
 use k8s_openapi::api::batch::v1::JobSpec;
+use k8s_openapi::api::core::v1::LocalObjectReference;
 use k8s_openapi::api::core::v1::PodSpec;
 use k8s_openapi::api::core::v1::ServiceSpec;
 use kube::CustomResource;
@@ -14,7 +16,7 @@ use kube::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub type ConfigurationList = ObjectList<Configuration>;
 
@@ -68,7 +70,7 @@ pub struct DiscoveryProperty {
 
 /// This specifies which `DiscoveryHandler` should be used for discovery
 /// and any details that need to be sent to the `DiscoveryHandler`.
-#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct DiscoveryHandlerInfo {
     pub name: String,
@@ -80,6 +82,27 @@ pub struct DiscoveryHandlerInfo {
     pub discovery_properties: Option<Vec<DiscoveryProperty>>,
 }
 
+/// This specifies the `DiscoveryHandler`s of a Configuration, either a
+/// single one or a list of them.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, PartialEq)]
+#[serde(untagged)]
+pub enum DiscoveryHandlers {
+    Single(DiscoveryHandlerInfo),
+    List(Vec<DiscoveryHandlerInfo>),
+}
+
+impl Default for DiscoveryHandlers {
+    fn default() -> Self {
+        DiscoveryHandlers::Single(Default::default())
+    }
+}
+
+impl From<DiscoveryHandlerInfo> for DiscoveryHandlers {
+    fn from(discovery_handler: DiscoveryHandlerInfo) -> Self {
+        DiscoveryHandlers::Single(discovery_handler)
+    }
+}
+
 /// This defines a workload that should be scheduled to nodes
 /// that can access a capability described by this Configuration.
 /// The enum contents are boxed to use the heap instead of the stack.
@@ -93,6 +116,69 @@ pub enum BrokerSpec {
     BrokerPodSpec(Box<PodSpec>),
     // JobSpec for Job that should be deployed to each capability described by this Configuration
     BrokerJobSpec(Box<JobSpec>),
+    // DeploymentSpec for Deployment that should be deployed to each capability described by this Configuration
+    BrokerDeploymentSpec(Box<DeploymentSpec>),
+}
+
+/// This defines how the names of the Instances discovered in response
+/// to a Configuration are generated.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default, JsonSchema)]
+pub enum InstanceNamingStrategy {
+    /// Instance name is the Configuration name suffixed with a digest of the device id
+    #[default]
+    IdBased,
+    /// Instance name is the Configuration name suffixed with a digest of the device properties,
+    /// the device id is used instead if the device has no properties
+    PropertyBased,
+    /// Instance name is only a digest of the Configuration name and device id, so that
+    /// neither appear in the name
+    HashBased,
+}
+
+/// A weighted node preference for scheduling broker Pods
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeAffinityPreference {
+    /// Weight of the preference, in the range 1-100, nodes matching
+    /// preferences with a higher total weight are preferred
+    pub weight: i32,
+
+    /// Labels a node must all have to match the preference
+    pub match_labels: BTreeMap<String, String>,
+}
+
+/// Protocol of a broker readiness probe
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default, JsonSchema)]
+pub enum BrokerReadinessProbeType {
+    /// The device's `host:port` accepts TCP connections
+    #[default]
+    Tcp,
+    /// A GET request to the device's URL succeeds
+    Http,
+}
+
+/// A check of the device run by an init container of the broker Pods,
+/// so that the brokers only start once the device is reachable
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokerReadinessProbe {
+    /// Protocol of the check, defaults to `Tcp`
+    #[serde(default, rename = "type")]
+    pub probe_type: BrokerReadinessProbeType,
+
+    /// Endpoint of the device, `host:port` for `Tcp` and a URL for
+    /// `Http`, referencing the Instance's broker properties as
+    /// `${PROP_NAME}`, e.g. `${ONVIF_DEVICE_IP_ADDRESS}:80`
+    pub target: String,
+
+    /// Image of the init container, which must provide `sh`, `nc`
+    /// and `wget`, defaults to busybox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+
+    /// Seconds between two checks, defaults to 2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_seconds: Option<u32>,
 }
 
 /// Defines the information in the Akri Configuration CRD
@@ -108,8 +194,13 @@ pub enum BrokerSpec {
 pub struct ConfigurationSpec {
     /// This defines the `DiscoveryHandler` that should be used to
     /// discover the capability and any information needed by the `DiscoveryHandler`.
-    #[schemars(schema_with = "immutable_dh_info")]
-    pub discovery_handler: DiscoveryHandlerInfo,
+    /// A list of `DiscoveryHandler`s can be given instead, their discovered
+    /// devices are merged into a single set of Instances. Devices are
+    /// deduplicated by id, properties reported by several handlers take the
+    /// value of the first one, in order.
+    #[serde(rename = "discoveryHandler")]
+    #[schemars(schema_with = "immutable::<DiscoveryHandlers>")]
+    pub discovery_handlers: DiscoveryHandlers,
 
     /// This defines the number of nodes that can schedule workloads for
     /// any given capability that is found
@@ -149,11 +240,149 @@ pub struct ConfigurationSpec {
     /// that represent the discovered resources.
     #[serde(default)]
     pub broker_properties: HashMap<String, String>,
+
+    /// This overrides whether the Instances discovered in response
+    /// to this Configuration are shared across nodes, if unset the
+    /// value reported by the `DiscoveryHandler` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared: Option<bool>,
+
+    /// This defines how the names of the Instances discovered in response
+    /// to this Configuration are generated, defaults to `IdBased`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_naming_strategy: Option<InstanceNamingStrategy>,
+
+    /// This restricts discovery to the nodes whose labels match all the
+    /// given labels, agents on other nodes neither run discovery nor
+    /// create Instances for this Configuration. If unset, discovery runs
+    /// on every node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_node_selector: Option<BTreeMap<String, String>>,
+
+    /// This overrides the name of the resource advertised for the
+    /// Configuration (`akri.sh/<resourceName>`), which defaults to the
+    /// Configuration's name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_name: Option<String>,
+
+    /// This lists additional names the resource of the Configuration is
+    /// advertised as (`akri.sh/<alias>`), so that workloads still requesting
+    /// the devices by a former resource name keep being scheduled. The same
+    /// devices are claimable through the resource and any of its aliases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_name_aliases: Option<Vec<String>>,
+
+    /// This caps the number of Instances created for the Configuration by
+    /// each Agent, additional discovered devices are ignored. If unset, an
+    /// Instance is created for every discovered device. The cap applies per
+    /// Agent, not cluster-wide: nodes discovering different devices can
+    /// together create more Instances than the cap.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_instances: Option<usize>,
+
+    /// This lists node preferences added to the broker Pods as
+    /// `preferredDuringSchedulingIgnoredDuringExecution` node affinity
+    /// terms, alongside the node the Pod is required to run on. If unset
+    /// or empty, the broker PodSpec is left untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_affinity_preferences: Option<Vec<NodeAffinityPreference>>,
+
+    /// This defines broker properties resolved per Instance from the
+    /// discovered device's properties, values reference them as
+    /// `${PROP_NAME}`. Properties referencing a property the device lacks
+    /// are left out, with a warning set on the device's Instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker_property_templates: Option<HashMap<String, String>>,
+
+    /// This sets the `schedulerName` of the broker Pods, so that they
+    /// are scheduled by the named scheduler instead of the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker_scheduler_name: Option<String>,
+
+    /// This lists imagePullSecrets added to the broker Pods, whichever
+    /// the kind of broker. They are combined with the imagePullSecrets
+    /// of the broker spec, which come first, without duplicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker_image_pull_secrets: Option<Vec<LocalObjectReference>>,
+
+    /// This defines how long, in seconds, an Instance no longer reported
+    /// by the `DiscoveryHandler` is kept before being removed, so that
+    /// devices with a flaky connectivity don't get torn down right away.
+    /// If unset, such Instances are removed immediately.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_offline_grace_secs: Option<u64>,
+
+    /// This defines how often, in seconds, the `DiscoveryHandler` polls for
+    /// the devices of the Configuration, and the Agents go through its
+    /// discovery results to update its Instances (on top of doing so
+    /// whenever the `DiscoveryHandler` reports a change). If unset, the
+    /// `DiscoveryHandler` polls at its own default interval and the Agents
+    /// go through the results every 600 seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_poll_interval_secs: Option<u64>,
+
+    /// This adds an init container to the broker Pods that waits for
+    /// the device endpoint to be reachable before the brokers start.
+    /// If unset, the broker PodSpec is left untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broker_readiness_probe: Option<BrokerReadinessProbe>,
+}
+
+impl Default for ConfigurationSpec {
+    fn default() -> Self {
+        ConfigurationSpec {
+            discovery_handlers: Default::default(),
+            capacity: default_capacity(),
+            broker_spec: None,
+            instance_service_spec: None,
+            configuration_service_spec: None,
+            broker_properties: Default::default(),
+            shared: None,
+            instance_naming_strategy: None,
+            discovery_node_selector: None,
+            resource_name: None,
+            resource_name_aliases: None,
+            max_instances: None,
+            node_affinity_preferences: None,
+            broker_property_templates: None,
+            broker_scheduler_name: None,
+            broker_image_pull_secrets: None,
+            instance_offline_grace_secs: None,
+            discovery_poll_interval_secs: None,
+            broker_readiness_probe: None,
+        }
+    }
 }
 
-fn immutable_dh_info(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
-    let mut schema: schemars::schema::SchemaObject =
-        <DiscoveryHandlerInfo>::json_schema(gen).into();
+impl ConfigurationSpec {
+    /// Get all the `DiscoveryHandler`s of the Configuration, in order
+    pub fn discovery_handlers(&self) -> impl Iterator<Item = &DiscoveryHandlerInfo> {
+        match &self.discovery_handlers {
+            DiscoveryHandlers::Single(discovery_handler) => std::slice::from_ref(discovery_handler),
+            DiscoveryHandlers::List(discovery_handlers) => discovery_handlers.as_slice(),
+        }
+        .iter()
+    }
+
+    /// Get the first `DiscoveryHandler` of the Configuration
+    #[deprecated(
+        note = "a Configuration can have several Discovery Handlers, use discovery_handlers() instead"
+    )]
+    pub fn discovery_handler(&self) -> &DiscoveryHandlerInfo {
+        // A list of Discovery Handlers is rejected by the webhook when empty
+        static NO_DISCOVERY_HANDLER: DiscoveryHandlerInfo = DiscoveryHandlerInfo {
+            name: String::new(),
+            discovery_details: String::new(),
+            discovery_properties: None,
+        };
+        self.discovery_handlers()
+            .next()
+            .unwrap_or(&NO_DISCOVERY_HANDLER)
+    }
+}
+
+fn immutable<T: JsonSchema>(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut schema: schemars::schema::SchemaObject = T::json_schema(gen).into();
     schema.extensions.insert(
         "x-kubernetes-validations".to_owned(),
         serde_json::from_str(r#"[{"message": "Value is immutable", "rule": "self == oldSelf"}]"#)
@@ -162,6 +391,27 @@ fn immutable_dh_info(gen: &mut schemars::gen::SchemaGenerator) -> schemars::sche
     schema.into()
 }
 
+/// Resolve the `${PROP_NAME}` references of a broker property template against the given device
+/// (or Instance broker) properties, returns the name of the first undefined property referenced if any
+pub fn resolve_broker_property_template(
+    template: &str,
+    properties: &HashMap<String, String>,
+) -> Result<String, String> {
+    let mut resolved = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        resolved.push_str(&rest[..start]);
+        resolved.push_str(properties.get(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + 2 + len + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
 /// Get Configurations for a given namespace
 ///
 /// Example:
@@ -285,6 +535,182 @@ mod crd_serialization_tests {
         assert_eq!(None, deserialized.instance_service_spec);
         assert_eq!(None, deserialized.configuration_service_spec);
         assert_eq!(0, deserialized.broker_properties.len());
+        assert_eq!(None, deserialized.node_affinity_preferences);
+        assert_eq!(None, deserialized.broker_property_templates);
+        assert_eq!(None, deserialized.broker_scheduler_name);
+        assert_eq!(None, deserialized.broker_image_pull_secrets);
+        assert_eq!(None, deserialized.instance_offline_grace_secs);
+        assert_eq!(None, deserialized.discovery_poll_interval_secs);
+        assert_eq!(None, deserialized.broker_readiness_probe);
+        assert_eq!(None, deserialized.resource_name_aliases);
+    }
+
+    #[test]
+    fn test_config_serialization_discovery_handler_list() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":[{"name":"onvif"}, {"name":"custom", "discoveryDetails":"details"}]}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            vec!["onvif", "custom"],
+            deserialized
+                .discovery_handlers()
+                .map(|dh| dh.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "details",
+            deserialized
+                .discovery_handlers()
+                .nth(1)
+                .unwrap()
+                .discovery_details
+        );
+        #[allow(deprecated)]
+        let first = deserialized.discovery_handler();
+        assert_eq!("onvif", first.name);
+        let serialized = serde_json::to_value(&deserialized).unwrap();
+        assert!(serialized["discoveryHandler"].is_array());
+
+        let json = r#"{"discoveryHandler":{"name":"onvif"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(1, deserialized.discovery_handlers().count());
+        let serialized = serde_json::to_value(&deserialized).unwrap();
+        assert!(serialized["discoveryHandler"].is_object());
+    }
+
+    #[test]
+    fn test_config_serialization_broker_property_templates() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"onvif"}, "brokerPropertyTemplates":{"CAMERA_URL":"rtsp://${ONVIF_IP}/stream"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(HashMap::from([(
+                "CAMERA_URL".to_string(),
+                "rtsp://${ONVIF_IP}/stream".to_string()
+            )])),
+            deserialized.broker_property_templates
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_node_affinity_preferences() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"random"}, "nodeAffinityPreferences":[{"weight":80,"matchLabels":{"gpu":"true"}}]}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(vec![NodeAffinityPreference {
+                weight: 80,
+                match_labels: BTreeMap::from([("gpu".to_string(), "true".to_string())]),
+            }]),
+            deserialized.node_affinity_preferences
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_broker_scheduler_name() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json =
+            r#"{"discoveryHandler":{"name":"random"}, "brokerSchedulerName":"edge-scheduler"}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some("edge-scheduler".to_string()),
+            deserialized.broker_scheduler_name
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_broker_image_pull_secrets() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"random"}, "brokerImagePullSecrets":[{"name":"regcred"}]}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(vec![LocalObjectReference {
+                name: Some("regcred".to_string())
+            }]),
+            deserialized.broker_image_pull_secrets
+        );
+        let serialized = serde_json::to_value(&deserialized).unwrap();
+        assert_eq!(
+            serde_json::json!([{"name": "regcred"}]),
+            serialized["brokerImagePullSecrets"]
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_discovery_poll_interval_secs() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"opcua"}, "discoveryPollIntervalSecs":300}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(Some(300), deserialized.discovery_poll_interval_secs);
+    }
+
+    #[test]
+    fn test_config_serialization_resource_name_aliases() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"udev"}, "resourceName":"camera", "resourceNameAliases":["usb-camera"]}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(vec!["usb-camera".to_string()]),
+            deserialized.resource_name_aliases
+        );
+    }
+
+    #[test]
+    fn test_config_serialization_instance_offline_grace_secs() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"onvif"}, "instanceOfflineGraceSecs":300}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(Some(300), deserialized.instance_offline_grace_secs);
+    }
+
+    #[test]
+    fn test_config_serialization_broker_readiness_probe() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"onvif"}, "brokerReadinessProbe":{"target":"${ONVIF_DEVICE_IP_ADDRESS}:80"}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Some(BrokerReadinessProbe {
+                probe_type: BrokerReadinessProbeType::Tcp,
+                target: "${ONVIF_DEVICE_IP_ADDRESS}:80".to_string(),
+                image: None,
+                period_seconds: None,
+            }),
+            deserialized.broker_readiness_probe
+        );
+
+        let json = r#"{"discoveryHandler":{"name":"onvif"}, "brokerReadinessProbe":{"type":"Http","target":"${ONVIF_DEVICE_SERVICE_URL}","periodSeconds":5}}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        let probe = deserialized.broker_readiness_probe.unwrap();
+        assert_eq!(BrokerReadinessProbeType::Http, probe.probe_type);
+        assert_eq!(Some(5), probe.period_seconds);
+    }
+
+    #[test]
+    fn test_resolve_broker_property_template() {
+        let properties = HashMap::from([
+            ("ONVIF_IP".to_owned(), "10.0.0.1".to_owned()),
+            ("ONVIF_PORT".to_owned(), "554".to_owned()),
+        ]);
+        assert_eq!(
+            resolve_broker_property_template(
+                "rtsp://${ONVIF_IP}:${ONVIF_PORT}/stream",
+                &properties
+            ),
+            Ok("rtsp://10.0.0.1:554/stream".to_owned())
+        );
+        assert_eq!(
+            resolve_broker_property_template("no reference", &properties),
+            Ok("no reference".to_owned())
+        );
+        // An unterminated reference is kept as is
+        assert_eq!(
+            resolve_broker_property_template("${ONVIF_IP}/${ONVIF", &properties),
+            Ok("10.0.0.1/${ONVIF".to_owned())
+        );
+        assert_eq!(
+            resolve_broker_property_template("rtsp://${ONVIF_HOST}/stream", &properties),
+            Err("ONVIF_HOST".to_owned())
+        );
     }
 
     #[test]
@@ -329,6 +755,23 @@ mod crd_serialization_tests {
         assert_eq!(expected_deserialized, serialized);
     }
 
+    #[test]
+    fn test_config_serialization_deploymentspec() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let json = r#"{"discoveryHandler":{"name":"random", "discoveryDetails":""}, "brokerSpec":{"brokerDeploymentSpec":{"replicas": 2, "selector": {}, "template": {"spec": {"containers": [{"image": "nginx:latest","name": "broker"}]}}}}, "capacity":4}"#;
+        let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
+        if let BrokerSpec::BrokerDeploymentSpec(d_deployment_spec) =
+            deserialized.broker_spec.as_ref().unwrap()
+        {
+            assert_eq!(Some(2), d_deployment_spec.replicas);
+        } else {
+            panic!("Expected BrokerDeploymentSpec");
+        }
+        let serialized = serde_json::to_string(&deserialized).unwrap();
+        let expected_deserialized = r#"{"discoveryHandler":{"name":"random","discoveryDetails":""},"capacity":4,"brokerSpec":{"brokerDeploymentSpec":{"replicas":2,"selector":{},"template":{"spec":{"containers":[{"image":"nginx:latest","name":"broker"}]}}}},"brokerProperties":{}}"#;
+        assert_eq!(expected_deserialized, serialized);
+    }
+
     #[test]
     fn test_real_config() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -409,8 +852,9 @@ mod crd_serialization_tests {
             }
         "#;
         let deserialized: ConfigurationSpec = serde_json::from_str(json).unwrap();
-        assert_eq!(deserialized.discovery_handler.name, "random".to_string());
-        assert!(deserialized.discovery_handler.discovery_details.is_empty());
+        let discovery_handler = deserialized.discovery_handlers().next().unwrap();
+        assert_eq!(discovery_handler.name, "random".to_string());
+        assert!(discovery_handler.discovery_details.is_empty());
         assert_eq!(5, deserialized.capacity);
         if let BrokerSpec::BrokerJobSpec(_j) = deserialized.broker_spec.unwrap() {
             panic!("Expected BrokerPodSpec");
@@ -1,12 +1,12 @@
-use super::{API_NAMESPACE, API_VERSION};
+use super::{AKRI_QUARANTINED_ANNOTATION_NAME, API_NAMESPACE, API_VERSION};
 use kube::{
     api::{Api, DeleteParams, ListParams, ObjectList, ObjectMeta, Patch, PatchParams, PostParams},
     Client, CustomResource,
 };
 
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{OwnerReference, Time};
 use schemars::JsonSchema;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub type InstanceList = ObjectList<Instance>;
 
@@ -87,6 +87,16 @@ pub struct InstanceSpec {
     #[serde(default)]
     #[schemars(schema_with = "ssa_usage_granular")]
     pub device_usage: HashMap<String, String>,
+
+    /// This contains the time the Instance was first discovered, it is purely informational
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_discovered: Option<Time>,
+
+    /// This contains a map of node names to the last time the node discovered the Instance, it is
+    /// purely informational and helps debugging flapping devices
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(schema_with = "ssa_last_seen_granular")]
+    pub last_seen: BTreeMap<String, Time>,
 }
 
 fn ssa_nodes_set(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
@@ -108,6 +118,16 @@ fn ssa_usage_granular(gen: &mut schemars::gen::SchemaGenerator) -> schemars::sch
     schema.into()
 }
 
+fn ssa_last_seen_granular(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut schema: schemars::schema::SchemaObject =
+        <BTreeMap<String, Time>>::json_schema(gen).into();
+    schema.extensions.insert(
+        "x-kubernetes-map-type".to_owned(),
+        serde_json::Value::String("granular".to_owned()),
+    );
+    schema.into()
+}
+
 /// Get Instances for a given namespace
 ///
 /// Example:
@@ -217,6 +237,8 @@ pub async fn find_instance(
 ///         nodes: Vec::new(),
 ///         device_usage: std::collections::HashMap::new(),
 ///         broker_properties: std::collections::HashMap::new(),
+///         first_discovered: None,
+///         last_seen: Default::default(),
 ///     },
 ///     "instance-1",
 ///     "default",
@@ -339,6 +361,8 @@ pub async fn delete_instance(
 ///         nodes: Vec::new(),
 ///         device_usage: std::collections::HashMap::new(),
 ///         broker_properties: std::collections::HashMap::new(),
+///         first_discovered: None,
+///         last_seen: Default::default(),
 ///     },
 ///     "instance-1",
 ///     "default",
@@ -380,10 +404,80 @@ pub async fn update_instance(
     }
 }
 
+/// Add Annotations to an Instance, leaving its other Annotations untouched
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::akri::instance;
+/// use kube::client::Client;
+/// use std::collections::BTreeMap;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// instance::annotate_instance(
+///     &BTreeMap::from([("akri.sh/quarantined".to_string(), "broker crashed".to_string())]),
+///     "instance-1",
+///     "default",
+///     &api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn annotate_instance(
+    annotations: &BTreeMap<String, String>,
+    name: &str,
+    namespace: &str,
+    kube_client: &Client,
+) -> Result<(), anyhow::Error> {
+    log::trace!("annotate_instance enter");
+    let instances_client: Api<Instance> = Api::namespaced(kube_client.clone(), namespace);
+    let patch = serde_json::json!({ "metadata": { "annotations": annotations } });
+    match instances_client
+        .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await
+    {
+        Ok(_instance_modified) => {
+            log::trace!("annotate_instance return");
+            Ok(())
+        }
+        Err(e) => {
+            log::trace!("annotate_instance kube_client.request error: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Returns whether the Instance is quarantined, in which case no broker should be scheduled for it
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::akri::instance::{self, Instance};
+///
+/// let instance: Instance = serde_json::from_str(r#"{"apiVersion": "akri.sh/v0", "kind": "Instance", "metadata": {"name": "instance-1", "annotations": {"akri.sh/quarantined": "broker crashed"}}, "spec": {"configurationName": "config-1", "cdiName": "akri.sh/config-1=instance-1", "nodes": [], "deviceUsage": {}}}"#).unwrap();
+/// assert!(instance::is_quarantined(&instance));
+/// ```
+pub fn is_quarantined(instance: &Instance) -> bool {
+    instance
+        .metadata
+        .annotations
+        .as_ref()
+        .is_some_and(|a| a.contains_key(AKRI_QUARANTINED_ANNOTATION_NAME))
+}
+
 fn default_shared() -> bool {
     false
 }
 
+/// Usage of the slots of an Instance, as stored in its `deviceUsage`.
+///
+/// A free slot is an empty string. Reserved slots can be read in a versioned format,
+/// `v<version>:<key>=<value>;<key>=<value>...`, e.g. `v1:kind=instance;node=node-a` or
+/// `v1:kind=configuration;vdev=a1b2c3;node=node-a`. Unknown keys are ignored, so that later versions can
+/// add fields (e.g. when the slot got reserved) without breaking readers of earlier ones.
+///
+/// Usages are still written in the legacy formats, `<node>` and `C:<vdev>:<node>`, until all the
+/// agents and controllers of a cluster read the versioned one.
 pub mod device_usage {
     #[derive(PartialEq, Clone, Debug, Default)]
     pub enum DeviceUsageKind {
@@ -425,7 +519,12 @@ pub mod device_usage {
                 });
             }
 
-            // Format "C:<vdev_id>:<node_name>"
+            // Format "v<version>:<fields>"
+            if let Some(fields) = strip_version(s) {
+                return parse_fields(fields);
+            }
+
+            // Legacy format "C:<vdev_id>:<node_name>"
             if let Some((vdev_id, node_name)) = s.strip_prefix("C:").and_then(|s| s.split_once(':'))
             {
                 if node_name.is_empty() {
@@ -437,7 +536,7 @@ pub mod device_usage {
                 });
             }
 
-            // Format "<node_name>"
+            // Legacy format "<node_name>"
             Ok(NodeUsage {
                 kind: DeviceUsageKind::Instance,
                 node_name: s.to_string(),
@@ -445,6 +544,43 @@ pub mod device_usage {
         }
     }
 
+    /// Returns the fields of a usage written in the versioned format, node names can't contain a
+    /// colon so that it can't be mistaken for a legacy usage
+    fn strip_version(s: &str) -> Option<&str> {
+        let (version, fields) = s.strip_prefix('v')?.split_once(':')?;
+        version.parse::<u32>().ok()?;
+        Some(fields)
+    }
+
+    fn parse_fields(fields: &str) -> Result<NodeUsage, ParseNodeUsageError> {
+        let mut kind = None;
+        let mut vdev_id = None;
+        let mut node_name = None;
+        for field in fields.split(';') {
+            match field.split_once('=').ok_or(ParseNodeUsageError)? {
+                ("kind", value) => kind = Some(value),
+                ("vdev", value) => vdev_id = Some(value),
+                ("node", value) => node_name = Some(value),
+                // Fields added by later versions
+                _ => {}
+            }
+        }
+        let node_name = node_name
+            .filter(|n| !n.is_empty())
+            .ok_or(ParseNodeUsageError)?;
+        let kind = match (kind, vdev_id) {
+            (Some("instance"), _) => DeviceUsageKind::Instance,
+            (Some("configuration"), Some(vdev_id)) => {
+                DeviceUsageKind::Configuration(vdev_id.to_string())
+            }
+            _ => return Err(ParseNodeUsageError),
+        };
+        Ok(NodeUsage {
+            kind,
+            node_name: node_name.to_string(),
+        })
+    }
+
     impl NodeUsage {
         pub fn create(kind: &DeviceUsageKind, node_name: &str) -> Result<Self, anyhow::Error> {
             match kind {
@@ -553,6 +689,35 @@ mod crd_serializeation_tests {
         let _ = serde_json::to_string(&deserialized).unwrap();
     }
 
+    #[test]
+    fn test_instance_discovery_timestamps_serialization() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let json = r#"{"configurationName":"foo","cdiName":"akri.sh/foo=bar","capacity":1,"brokerProperties":{},"shared":true,"nodes":["n1","n2"],"deviceUsage":{},"firstDiscovered":"2024-01-01T00:00:00Z","lastSeen":{"n1":"2024-01-02T00:00:00Z","n2":"2024-01-03T00:00:00Z"}}"#;
+        let deserialized: InstanceSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            "2024-01-01T00:00:00+00:00",
+            deserialized
+                .first_discovered
+                .as_ref()
+                .unwrap()
+                .0
+                .to_rfc3339()
+        );
+        assert_eq!(2, deserialized.last_seen.len());
+        assert_eq!(
+            "2024-01-03T00:00:00+00:00",
+            deserialized.last_seen["n2"].0.to_rfc3339()
+        );
+        assert_eq!(json, serde_json::to_string(&deserialized).unwrap());
+
+        // Instances without the timestamps still deserialize
+        let json = r#"{"configurationName":"foo","cdiName":"akri.sh/foo=bar","capacity":1}"#;
+        let deserialized: InstanceSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(None, deserialized.first_discovered);
+        assert!(deserialized.last_seen.is_empty());
+    }
+
     #[test]
     fn test_real_instance() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -568,3 +733,105 @@ mod crd_serializeation_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod device_usage_tests {
+    use super::device_usage::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_node_usage_round_trip() {
+        for usage in [
+            NodeUsage::default(),
+            NodeUsage::create(&DeviceUsageKind::Instance, "node-a").unwrap(),
+            NodeUsage::create(
+                &DeviceUsageKind::Configuration("a1b2c3".to_string()),
+                "node-a",
+            )
+            .unwrap(),
+        ] {
+            assert_eq!(NodeUsage::from_str(&usage.to_string()), Ok(usage));
+        }
+    }
+
+    #[test]
+    fn test_node_usage_format() {
+        assert_eq!(NodeUsage::default().to_string(), "");
+        assert_eq!(
+            NodeUsage::create(&DeviceUsageKind::Instance, "node-a")
+                .unwrap()
+                .to_string(),
+            "node-a"
+        );
+        assert_eq!(
+            NodeUsage::create(
+                &DeviceUsageKind::Configuration("a1b2c3".to_string()),
+                "node-a"
+            )
+            .unwrap()
+            .to_string(),
+            "C:a1b2c3:node-a"
+        );
+    }
+
+    #[test]
+    fn test_node_usage_legacy_format() {
+        let usage = NodeUsage::from_str("node-a").unwrap();
+        assert_eq!(usage.get_kind(), DeviceUsageKind::Instance);
+        assert_eq!(usage.get_node_name(), "node-a");
+        assert_eq!(usage.to_string(), "node-a");
+
+        let usage = NodeUsage::from_str("C:a1b2c3:node-a").unwrap();
+        assert_eq!(
+            usage.get_kind(),
+            DeviceUsageKind::Configuration("a1b2c3".to_string())
+        );
+        assert_eq!(usage.get_node_name(), "node-a");
+        assert_eq!(
+            NodeUsage::from_str(&usage.to_string()),
+            NodeUsage::from_str("C:a1b2c3:node-a")
+        );
+
+        assert!(NodeUsage::from_str("C:a1b2c3:").is_err());
+    }
+
+    #[test]
+    fn test_node_usage_versioned_format() {
+        let usage = NodeUsage::from_str("v1:kind=instance;node=node-a").unwrap();
+        assert_eq!(usage.get_kind(), DeviceUsageKind::Instance);
+        assert_eq!(usage.get_node_name(), "node-a");
+        assert_eq!(usage.to_string(), "node-a");
+
+        let usage = NodeUsage::from_str("v1:kind=configuration;vdev=a1b2c3;node=node-a").unwrap();
+        assert_eq!(
+            usage.get_kind(),
+            DeviceUsageKind::Configuration("a1b2c3".to_string())
+        );
+        assert_eq!(usage.to_string(), "C:a1b2c3:node-a");
+    }
+
+    #[test]
+    fn test_node_usage_forward_compatible() {
+        // Fields unknown to this version are ignored, whatever their version
+        let usage =
+            NodeUsage::from_str("v2:kind=instance;node=node-a;reservedAt=2024-01-01T00:00:00Z")
+                .unwrap();
+        assert_eq!(
+            usage,
+            NodeUsage::create(&DeviceUsageKind::Instance, "node-a").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_node_usage_invalid_format() {
+        for usage in [
+            "v1:kind=instance",
+            "v1:kind=instance;node=",
+            "v1:kind=configuration;node=node-a",
+            "v1:kind=unknown;node=node-a",
+            "v1:node-a",
+        ] {
+            assert!(NodeUsage::from_str(usage).is_err(), "{}", usage);
+        }
+    }
+}
@@ -13,26 +13,223 @@ pub const API_INSTANCES: &str = "instances";
 pub const AKRI_PREFIX: &str = "akri.sh";
 /// Container Annotation name prefix used to store slot name
 pub const AKRI_SLOT_ANNOTATION_NAME_PREFIX: &str = "akri.agent.slot-";
+/// Instance Annotation name used to store the last non-fatal warning reported for a device, each node
+/// records it under its own key (see `node_annotation_name`)
+pub const AKRI_LAST_WARNING_ANNOTATION_NAME: &str = "akri.sh/last-warning";
+/// Broker environment variable name used to expose the last non-fatal warning reported for a device
+pub const AKRI_LAST_WARNING_ENV_NAME: &str = "AKRI_DEVICE_LAST_WARNING";
+/// Instance property (and broker environment variable) name used to expose a non-fatal error reported for a device,
+/// meaning its other properties are incomplete, unlike a warning
+pub const AKRI_DEVICE_ERROR_PROPERTY_NAME: &str = "AKRI_DEVICE_ERROR";
+/// Device property name used by Discovery Handlers to report how long probing the device took, in milliseconds.
+/// The Agent records it in the `akri_device_probe_latency_seconds` metric and does not pass it on to brokers
+pub const AKRI_DEVICE_PROBE_LATENCY_PROPERTY_NAME: &str = "AKRI_DEVICE_PROBE_LATENCY_MS";
+/// Reserved discovery property name used by the Agent to pass the Configuration's discoveryPollIntervalSecs
+/// to Discovery Handlers, as the number of seconds to wait between two discovery passes
+pub const AKRI_DISCOVERY_POLL_INTERVAL_PROPERTY_NAME: &str = "AKRI_DISCOVERY_POLL_INTERVAL_SECS";
+/// Instance Annotation name used to flag an Instance with more reserved slots than its capacity
+pub const AKRI_OVER_COMMITTED_ANNOTATION_NAME: &str = "akri.sh/over-committed";
+/// Instance Annotation name used to record where the shared flag of an Instance comes from, each node
+/// records it under its own key (see `node_annotation_name`)
+pub const AKRI_SHARED_SOURCE_ANNOTATION_NAME: &str = "akri.sh/shared-source";
+/// Shared source annotation value when the shared flag comes from the Discovery Handler
+pub const AKRI_SHARED_SOURCE_DISCOVERY_HANDLER: &str = "discoveryHandler";
+/// Shared source annotation value when the shared flag comes from the Configuration
+pub const AKRI_SHARED_SOURCE_CONFIGURATION: &str = "configuration";
+/// Instance Annotation name used to record the generation of the Configuration that produced the Instance,
+/// each node records it under its own key (see `node_annotation_name`)
+pub const AKRI_CONFIGURATION_GENERATION_ANNOTATION_NAME: &str = "akri.sh/configuration-generation";
+/// Instance Annotation name used to record the resource name advertised for the Instance's Configuration
+pub const AKRI_RESOURCE_NAME_ANNOTATION_NAME: &str = "akri.sh/resource-name";
+/// Instance Annotation name used to record the comma-separated aliases the resource of the Instance's
+/// Configuration is also advertised as
+pub const AKRI_RESOURCE_NAME_ALIASES_ANNOTATION_NAME: &str = "akri.sh/resource-name-aliases";
+/// Instance Annotation name used to quarantine an Instance whose brokers keep crashing, no broker gets
+/// scheduled for it until the Annotation is removed
+pub const AKRI_QUARANTINED_ANNOTATION_NAME: &str = "akri.sh/quarantined";
+/// Configuration Annotation name used to request a new discovery on demand, the Agents restart the
+/// discovery of the Configuration every time its value changes
+pub const AKRI_REDISCOVER_ANNOTATION_NAME: &str = "akri.sh/rediscover";
+
+/// Maximum length of the name part (after the prefix) of an Annotation name
+const ANNOTATION_NAME_MAX_LENGTH: usize = 63;
+
+/// Returns the per-node variant of an Instance Annotation name, e.g. `last-warning.akri.sh/<node>` for
+/// `akri.sh/last-warning`. Shared Instances are applied by all the nodes that discover them, writing
+/// per-node values under a key of their own keeps the nodes from conflicting over a single value.
+/// Node names too long for an Annotation name get truncated and suffixed with a hash of the full name.
+pub fn node_annotation_name(annotation_name: &str, node_name: &str) -> String {
+    let (prefix, name) = annotation_name
+        .split_once('/')
+        .unwrap_or((AKRI_PREFIX, annotation_name));
+    let node_key = if node_name.len() <= ANNOTATION_NAME_MAX_LENGTH {
+        node_name.to_string()
+    } else {
+        let hash = digest(node_name, 4);
+        format!(
+            "{}-{}",
+            &node_name[..ANNOTATION_NAME_MAX_LENGTH - hash.len() - 1],
+            hash
+        )
+    };
+    format!("{}.{}/{}", name, prefix, node_key)
+}
+
+fn digest(value: &str, size: usize) -> String {
+    use blake2::digest::{Update, VariableOutput};
+    let mut digest = String::new();
+    let mut hasher = blake2::VarBlake2b::new(size).unwrap();
+    hasher.update(value);
+    hasher.finalize_variable(|var| {
+        digest = var.iter().map(|num| format!("{:02x}", num)).collect();
+    });
+    digest
+}
 
 pub mod configuration;
 pub mod instance;
 pub mod metrics;
 
 pub mod retry {
+    use crate::os::env_var::EnvVarQuery;
     use rand::random;
     use std::time::Duration;
     use tokio::time;
 
-    /// Maximum amount of tries to update or create an instance
-    pub const MAX_INSTANCE_UPDATE_TRIES: i8 = 5;
+    /// Default maximum amount of tries to update or create an instance
+    pub const MAX_INSTANCE_UPDATE_TRIES: u8 = 5;
+    /// Name of the environment variable that sets the maximum amount of tries to update or create an instance
+    pub const MAX_INSTANCE_UPDATE_TRIES_LABEL: &str = "MAX_INSTANCE_UPDATE_TRIES";
+    /// Delay before the first retry, doubled with each following attempt
+    pub const BACKOFF_BASE_DELAY: Duration = Duration::from_millis(100);
+    /// Upper bound of the delay between two tries
+    pub const BACKOFF_MAX_DELAY: Duration = Duration::from_secs(5);
 
-    /// This method will delay a random percentage of up to 200ms
+    /// Returns the delay to wait after the given (0 based) failed attempt
+    ///
+    /// The delay doubles with each attempt, up to `BACKOFF_MAX_DELAY`, and only its lower half is
+    /// fixed: the upper half is random to stagger the update/create requests to etcd of nodes
+    /// contending for the same Instance.
     ///
-    /// Wait for random amount of time to stagger update/create requests to etcd from
-    /// plugins created at the same time from daemonset
-    pub async fn random_delay() {
-        let random_decimal: f32 = random::<f32>();
-        let random_delay_0_to_200: u64 = (200_f32 * random_decimal) as u64;
-        time::sleep(Duration::from_millis(random_delay_0_to_200)).await;
+    /// Example:
+    ///
+    /// ```
+    /// use akri_shared::akri::retry::{backoff_delay, BACKOFF_MAX_DELAY};
+    ///
+    /// assert!(backoff_delay(2) >= backoff_delay(1));
+    /// assert!(backoff_delay(u8::MAX) <= BACKOFF_MAX_DELAY);
+    /// ```
+    pub fn backoff_delay(attempt: u8) -> Duration {
+        let ceiling = BACKOFF_BASE_DELAY
+            .checked_mul(1u32.checked_shl(attempt.into()).unwrap_or(u32::MAX))
+            .map_or(BACKOFF_MAX_DELAY, |delay| delay.min(BACKOFF_MAX_DELAY));
+        ceiling / 2 + ceiling.mul_f64(random::<f64>() / 2.0)
+    }
+
+    /// Wait for the backoff delay of the given (0 based) failed attempt, see `backoff_delay`
+    pub async fn backoff(attempt: u8) {
+        time::sleep(backoff_delay(attempt)).await;
+    }
+
+    /// Get the maximum amount of tries to update or create an instance, defaults to
+    /// `MAX_INSTANCE_UPDATE_TRIES`
+    pub fn get_max_instance_update_tries(env_var_query: &impl EnvVarQuery) -> u8 {
+        let Ok(value) = env_var_query.get_env_var(MAX_INSTANCE_UPDATE_TRIES_LABEL) else {
+            return MAX_INSTANCE_UPDATE_TRIES;
+        };
+        match value.parse::<u8>() {
+            Ok(tries) if tries > 0 => tries,
+            _ => {
+                log::error!(
+                    "get_max_instance_update_tries - invalid {} value {:?}, expected a number between 1 and {}",
+                    MAX_INSTANCE_UPDATE_TRIES_LABEL,
+                    value,
+                    u8::MAX
+                );
+                MAX_INSTANCE_UPDATE_TRIES
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::os::env_var::MockEnvVarQuery;
+
+        #[test]
+        fn test_backoff_delay_grows_with_attempts() {
+            for attempt in 1..5 {
+                // The smallest possible delay of an attempt is the largest one of the previous attempt
+                for _ in 0..100 {
+                    assert!(backoff_delay(attempt) >= backoff_delay(attempt - 1));
+                }
+            }
+            assert!(backoff_delay(0) >= BACKOFF_BASE_DELAY / 2);
+            assert!(backoff_delay(0) <= BACKOFF_BASE_DELAY);
+            assert!(backoff_delay(3) >= BACKOFF_BASE_DELAY * 4);
+        }
+
+        #[test]
+        fn test_get_max_instance_update_tries() {
+            for (value, expected) in [
+                (None, MAX_INSTANCE_UPDATE_TRIES),
+                (Some("10"), 10),
+                (Some("0"), MAX_INSTANCE_UPDATE_TRIES),
+                (Some("-1"), MAX_INSTANCE_UPDATE_TRIES),
+                (Some("255"), 255),
+                (Some("256"), MAX_INSTANCE_UPDATE_TRIES),
+                (Some("many"), MAX_INSTANCE_UPDATE_TRIES),
+            ] {
+                let mut mock_env_var = MockEnvVarQuery::new();
+                mock_env_var
+                    .expect_get_env_var()
+                    .with(mockall::predicate::eq(MAX_INSTANCE_UPDATE_TRIES_LABEL))
+                    .returning(move |_| {
+                        value
+                            .map(String::from)
+                            .ok_or(std::env::VarError::NotPresent)
+                    });
+                assert_eq!(get_max_instance_update_tries(&mock_env_var), expected);
+            }
+        }
+
+        #[test]
+        fn test_backoff_delay_is_bounded() {
+            for attempt in [6, 10, 31, 32, 64, u8::MAX] {
+                let delay = backoff_delay(attempt);
+                assert!(delay <= BACKOFF_MAX_DELAY);
+                assert!(delay >= BACKOFF_MAX_DELAY / 2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_annotation_name() {
+        assert_eq!(
+            node_annotation_name(AKRI_LAST_WARNING_ANNOTATION_NAME, "node-a"),
+            "last-warning.akri.sh/node-a"
+        );
+        assert_eq!(
+            node_annotation_name("shared-source", "node-a"),
+            "shared-source.akri.sh/node-a"
+        );
+
+        // Long node names get truncated, still telling nodes apart
+        let long_a = format!("{}-a.example.com", "node".repeat(20));
+        let long_b = format!("{}-b.example.com", "node".repeat(20));
+        let name_a = node_annotation_name(AKRI_SHARED_SOURCE_ANNOTATION_NAME, &long_a);
+        let name_b = node_annotation_name(AKRI_SHARED_SOURCE_ANNOTATION_NAME, &long_b);
+        assert_ne!(name_a, name_b);
+        for name in [name_a, name_b] {
+            let (prefix, key) = name.split_once('/').unwrap();
+            assert_eq!(prefix, "shared-source.akri.sh");
+            assert_eq!(key.len(), ANNOTATION_NAME_MAX_LENGTH);
+            assert!(key.starts_with("nodenode"));
+        }
     }
 }
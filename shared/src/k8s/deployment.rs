@@ -0,0 +1,503 @@
+use super::super::akri::{instance::Instance, API_NAMESPACE};
+use super::{
+    pod::modify_pod_spec,
+    pod::{
+        AKRI_CONFIGURATION_LABEL_NAME, AKRI_INSTANCE_LABEL_NAME, APP_LABEL_ID, CONTROLLER_LABEL_ID,
+    },
+    OwnershipInfo, ERROR_CONFLICT, ERROR_NOT_FOUND,
+};
+use either::Either;
+use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use kube::{
+    api::{
+        Api, DeleteParams, ListParams, ObjectList, Patch, PatchParams, PostParams,
+        PropagationPolicy,
+    },
+    client::Client,
+};
+use log::{error, info, trace};
+use std::collections::BTreeMap;
+
+/// Field manager of the Deployments applied by the controller
+pub const DEPLOYMENT_FIELD_MANAGER: &str = "akri-controller";
+
+/// Find Kubernetes Deployments with a given label selector
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::deployment;
+/// use kube::client::Client;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let label_selector = Some("environment=production,app=nginx".to_string());
+/// let api_client = Client::try_default().await.unwrap();
+/// for deployment in deployment::find_deployments_with_selector(label_selector, api_client).await.unwrap() {
+///     println!("found deployment: {}", deployment.metadata.name.unwrap())
+/// }
+/// # }
+/// ```
+pub async fn find_deployments_with_selector(
+    label_selector: Option<String>,
+    kube_client: Client,
+) -> Result<ObjectList<Deployment>, anyhow::Error> {
+    trace!(
+        "find_deployments_with_selector with label_selector={:?}",
+        &label_selector
+    );
+    let deployments: Api<Deployment> = Api::all(kube_client);
+    let deployment_list_params = ListParams {
+        label_selector,
+        ..Default::default()
+    };
+    let result = deployments.list(&deployment_list_params).await;
+    trace!("find_deployments_with_selector return");
+    Ok(result?)
+}
+
+/// Create Kubernetes Deployment with given Instance and OwnershipInfo.
+/// The Configuration and Instance labels are added to the Pod template and to the Deployment's
+/// selector, so that the Pods of the Deployments of different Instances are told apart.
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::{
+///     OwnershipInfo,
+///     OwnershipType,
+///     deployment
+/// };
+/// use akri_shared::akri::instance::{Instance, InstanceSpec};
+/// use kube::client::Client;
+/// use kube::config;
+/// use k8s_openapi::api::apps::v1::DeploymentSpec;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// let instance_spec = InstanceSpec {
+///     configuration_name: "configuration_name".to_string(),
+///     cdi_name: "akri.sh/configuration_name=instance_name".to_string(),
+///     capacity: 1,
+///     shared: true,
+///     nodes: Vec::new(),
+///     device_usage: std::collections::HashMap::new(),
+///     broker_properties: std::collections::HashMap::new(),
+///     first_discovered: None,
+///     last_seen: Default::default(),
+/// };
+/// let instance = Instance::new("instance_name", instance_spec);
+/// let deployment = deployment::create_new_deployment_from_spec(
+///     &instance,
+///     OwnershipInfo::new(
+///         OwnershipType::Instance,
+///         "instance_name".to_string(),
+///         "instance_uid".to_string()
+///     ),
+///     "akri.sh/configuration_name",
+///     &DeploymentSpec::default(),"app_name").unwrap();
+/// # }
+/// ```
+pub fn create_new_deployment_from_spec(
+    instance: &Instance,
+    ownership: OwnershipInfo,
+    resource_limit_name: &str,
+    deployment_spec: &DeploymentSpec,
+    app_name: &str,
+) -> anyhow::Result<Deployment> {
+    trace!("create_new_deployment_from_spec enter");
+    let instance_name = instance.metadata.name.as_ref().unwrap();
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    labels.insert(
+        AKRI_CONFIGURATION_LABEL_NAME.to_string(),
+        instance.spec.configuration_name.to_string(),
+    );
+    labels.insert(
+        AKRI_INSTANCE_LABEL_NAME.to_string(),
+        instance_name.to_string(),
+    );
+    let pod_labels = labels.clone();
+    labels.insert(APP_LABEL_ID.to_string(), app_name.to_string());
+    labels.insert(CONTROLLER_LABEL_ID.to_string(), API_NAMESPACE.to_string());
+
+    let owner_references: Vec<OwnerReference> = vec![OwnerReference {
+        api_version: ownership.get_api_version(),
+        kind: ownership.get_kind(),
+        controller: ownership.get_controller(),
+        block_owner_deletion: ownership.get_block_owner_deletion(),
+        name: ownership.get_name(),
+        uid: ownership.get_uid(),
+    }];
+
+    let mut modified_deployment_spec = deployment_spec.clone();
+    let mut pod_spec = modified_deployment_spec
+        .template
+        .spec
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("brokerDeploymentSpec has no Pod template spec"))?;
+    modify_pod_spec(&mut pod_spec, resource_limit_name, None);
+    modified_deployment_spec
+        .template
+        .metadata
+        .get_or_insert(ObjectMeta {
+            ..Default::default()
+        })
+        .labels
+        .get_or_insert(BTreeMap::new())
+        .extend(pod_labels.clone());
+    modified_deployment_spec
+        .selector
+        .match_labels
+        .get_or_insert(BTreeMap::new())
+        .extend(pod_labels);
+    modified_deployment_spec.template.spec = Some(pod_spec);
+    let result = Deployment {
+        spec: Some(modified_deployment_spec),
+        metadata: ObjectMeta {
+            name: Some(app_name.to_string()),
+            namespace: Some(instance.metadata.namespace.as_ref().unwrap().to_string()),
+            labels: Some(labels),
+            owner_references: Some(owner_references),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    trace!("create_new_deployment_from_spec return");
+    Ok(result)
+}
+
+/// Create Kubernetes Deployment
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::deployment;
+/// use kube::client::Client;
+/// use kube::config;
+/// use k8s_openapi::api::apps::v1::Deployment;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// deployment::create_deployment(&Deployment::default(), "deployment_namespace", api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn create_deployment(
+    deployment_to_create: &Deployment,
+    namespace: &str,
+    kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    trace!("create_deployment enter");
+    let deployments: Api<Deployment> = Api::namespaced(kube_client, namespace);
+    match deployments
+        .create(&PostParams::default(), deployment_to_create)
+        .await
+    {
+        Ok(created_deployment) => {
+            info!(
+                "create_deployment deployments.create return: {:?}",
+                created_deployment.metadata.name
+            );
+            Ok(())
+        }
+        Err(kube::Error::Api(ae)) => {
+            if ae.code == ERROR_CONFLICT {
+                trace!("create_deployment - deployment already exists");
+                Ok(())
+            } else {
+                error!(
+                    "create_deployment deployments.create [{:?}] returned kube error: {:?}",
+                    serde_json::to_string(&deployment_to_create),
+                    ae
+                );
+                Err(anyhow::anyhow!(ae))
+            }
+        }
+        Err(e) => {
+            error!(
+                "create_deployment deployments.create [{:?}] error: {:?}",
+                serde_json::to_string(&deployment_to_create),
+                e
+            );
+            Err(anyhow::anyhow!(e))
+        }
+    }
+}
+
+/// Create or update Kubernetes Deployment with a server-side apply, so that changes to the
+/// brokerDeploymentSpec of a Configuration (e.g. its replicas) reach existing Deployments
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::deployment;
+/// use kube::client::Client;
+/// use kube::config;
+/// use k8s_openapi::api::apps::v1::Deployment;
+/// use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// let deployment_to_apply = Deployment {
+///     metadata: ObjectMeta {
+///         name: Some("deployment_name".to_string()),
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// deployment::apply_deployment(&deployment_to_apply, "deployment_namespace", api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn apply_deployment(
+    deployment_to_apply: &Deployment,
+    namespace: &str,
+    kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    trace!("apply_deployment enter");
+    let name = deployment_to_apply
+        .metadata
+        .name
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Deployment to apply has no name"))?;
+    let deployments: Api<Deployment> = Api::namespaced(kube_client, namespace);
+    match deployments
+        .patch(
+            name,
+            &PatchParams::apply(DEPLOYMENT_FIELD_MANAGER).force(),
+            &Patch::Apply(deployment_to_apply),
+        )
+        .await
+    {
+        Ok(applied_deployment) => {
+            info!(
+                "apply_deployment deployments.patch return: {:?}",
+                applied_deployment.metadata.name
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "apply_deployment deployments.patch [{:?}] error: {:?}",
+                serde_json::to_string(&deployment_to_apply),
+                e
+            );
+            Err(anyhow::anyhow!(e))
+        }
+    }
+}
+
+/// Remove Kubernetes Deployment
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::deployment;
+/// use kube::client::Client;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// deployment::remove_deployment("deployment_to_remove", "deployment_namespace", api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn remove_deployment(
+    deployment_to_remove: &str,
+    namespace: &str,
+    kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    trace!("remove_deployment enter");
+    let deployments: Api<Deployment> = Api::namespaced(kube_client, namespace);
+    let dps = DeleteParams {
+        dry_run: false,
+        propagation_policy: Some(PropagationPolicy::Background),
+        ..Default::default()
+    };
+    match deployments.delete(deployment_to_remove, &dps).await {
+        Ok(deleted_deployment) => match deleted_deployment {
+            Either::Left(spec) => {
+                info!(
+                    "remove_deployment deployments.delete return: {:?}",
+                    &spec.metadata.name
+                );
+                Ok(())
+            }
+            Either::Right(status) => {
+                info!(
+                    "remove_deployment deployments.delete return: {:?}",
+                    &status.status
+                );
+                Ok(())
+            }
+        },
+        Err(kube::Error::Api(ae)) => {
+            if ae.code == ERROR_NOT_FOUND {
+                trace!("remove_deployment - deployment already removed");
+                Ok(())
+            } else {
+                error!(
+                    "remove_deployment deployments.delete [{:?}] returned kube error: {:?}",
+                    &deployment_to_remove, ae
+                );
+                Err(anyhow::anyhow!(ae))
+            }
+        }
+        Err(e) => {
+            error!(
+                "remove_deployment deployments.delete [{:?}] error: {:?}",
+                &deployment_to_remove, e
+            );
+            Err(anyhow::anyhow!(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod broker_deploymentspec_tests {
+    use super::super::super::{akri::API_VERSION, os::file};
+    use super::super::{OwnershipType, RESOURCE_REQUIREMENTS_KEY};
+    use super::*;
+    use k8s_openapi::api::core::v1::{Container, PodSpec, PodTemplateSpec, ResourceRequirements};
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+
+    type ResourceQuantityType = BTreeMap<String, Quantity>;
+
+    fn get_test_deployment_spec() -> DeploymentSpec {
+        let mut placeholder_limits: ResourceQuantityType = BTreeMap::new();
+        placeholder_limits.insert(RESOURCE_REQUIREMENTS_KEY.to_string(), Default::default());
+        let c = Container {
+            image: Some("image1".to_string()),
+            resources: Some(ResourceRequirements {
+                limits: Some(placeholder_limits),
+                requests: None,
+            }),
+            ..Default::default()
+        };
+        let mut preexisting_labels = BTreeMap::new();
+        preexisting_labels.insert("app".to_string(), "management".to_string());
+        DeploymentSpec {
+            replicas: Some(3),
+            selector: LabelSelector {
+                match_labels: Some(preexisting_labels.clone()),
+                ..Default::default()
+            },
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(preexisting_labels),
+                    ..Default::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![c],
+                    ..Default::default()
+                }),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_create_new_deployment_from_spec() {
+        let app_name = "deployment-name";
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let instance_name = instance.metadata.name.as_ref().unwrap();
+        let instance_uid = instance.metadata.uid.as_ref().unwrap();
+        let deployment = create_new_deployment_from_spec(
+            &instance,
+            OwnershipInfo::new(
+                OwnershipType::Instance,
+                instance_name.to_string(),
+                instance_uid.to_string(),
+            ),
+            "akri.sh/resource-limit-name",
+            &get_test_deployment_spec(),
+            app_name,
+        )
+        .unwrap();
+
+        // Validate that uses instance namespace and Akri labels are added to Deployment
+        assert_eq!(instance.metadata.namespace, deployment.metadata.namespace);
+        let labels = deployment.metadata.labels.as_ref().unwrap();
+        assert_eq!(app_name, labels.get(APP_LABEL_ID).unwrap());
+        assert_eq!(&API_NAMESPACE, labels.get(CONTROLLER_LABEL_ID).unwrap());
+        assert_eq!(instance_name, labels.get(AKRI_INSTANCE_LABEL_NAME).unwrap());
+
+        // Validate that pre-existing fields persist and that the selector matches the Pod template
+        let spec = deployment.spec.as_ref().unwrap();
+        assert_eq!(Some(3), spec.replicas);
+        let pod_labels = spec
+            .template
+            .metadata
+            .as_ref()
+            .unwrap()
+            .labels
+            .as_ref()
+            .unwrap();
+        let selector_labels = spec.selector.match_labels.as_ref().unwrap();
+        assert_eq!(pod_labels, selector_labels);
+        assert_eq!("management", selector_labels.get("app").unwrap());
+        assert_eq!(
+            &instance.spec.configuration_name,
+            selector_labels.get(AKRI_CONFIGURATION_LABEL_NAME).unwrap()
+        );
+        assert_eq!(
+            instance_name,
+            selector_labels.get(AKRI_INSTANCE_LABEL_NAME).unwrap()
+        );
+
+        // Validate that the Akri resource placeholder is replaced
+        let limits = spec.template.spec.as_ref().unwrap().containers[0]
+            .resources
+            .as_ref()
+            .unwrap()
+            .limits
+            .as_ref()
+            .unwrap();
+        assert!(limits.contains_key("akri.sh/resource-limit-name"));
+        assert!(!limits.contains_key(RESOURCE_REQUIREMENTS_KEY));
+
+        // Validate OwnerReferences
+        let owner_reference = deployment
+            .metadata
+            .owner_references
+            .as_ref()
+            .unwrap()
+            .first()
+            .unwrap();
+        assert_eq!(instance_name, &owner_reference.name);
+        assert_eq!(instance_uid, &owner_reference.uid);
+        assert_eq!("Instance", owner_reference.kind);
+        assert_eq!(
+            format!("{}/{}", API_NAMESPACE, API_VERSION),
+            owner_reference.api_version
+        );
+        assert!(owner_reference.controller.unwrap());
+        assert!(owner_reference.block_owner_deletion.unwrap());
+    }
+
+    #[test]
+    fn test_create_new_deployment_from_spec_without_template_spec() {
+        let instance_json = file::read_file_to_string("../test/json/local-instance.json");
+        let instance: Instance = serde_json::from_str(&instance_json).unwrap();
+        let mut deployment_spec = get_test_deployment_spec();
+        deployment_spec.template.spec = None;
+        assert!(create_new_deployment_from_spec(
+            &instance,
+            OwnershipInfo::new(
+                OwnershipType::Instance,
+                "instance_name".to_string(),
+                "instance_uid".to_string(),
+            ),
+            "akri.sh/resource-limit-name",
+            &deployment_spec,
+            "deployment-name",
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use super::super::akri::{configuration::Configuration, API_NAMESPACE, API_VERSION};
+use k8s_openapi::api::core::v1::{Event, EventSource, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use k8s_openapi::chrono::Utc;
+use kube::{
+    api::{Api, PostParams},
+    client::Client,
+    ResourceExt,
+};
+use log::{error, info, trace};
+
+/// Component reported as the source of Akri Controller Events
+pub const AKRI_CONTROLLER_EVENT_COMPONENT: &str = "akri-controller";
+/// Component reported as the source of Akri Agent Events
+pub const AKRI_AGENT_EVENT_COMPONENT: &str = "akri-agent";
+/// Type of Events reporting a problem that needs attention
+pub const EVENT_TYPE_WARNING: &str = "Warning";
+/// Type of Events reporting a regular operation
+pub const EVENT_TYPE_NORMAL: &str = "Normal";
+
+/// Create a Warning Event whose involved object is the given Configuration
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::akri::configuration::Configuration;
+/// use akri_shared::k8s::event;
+///
+/// let configuration = Configuration::new("config-1", serde_json::from_str(r#"{"discoveryHandler": {"name": "debugEcho"}}"#).unwrap());
+/// let warning = event::create_configuration_warning_event(&configuration, "BrokerImagePullBackOff", "cannot pull image");
+/// assert_eq!(warning.reason, Some("BrokerImagePullBackOff".to_string()));
+/// ```
+pub fn create_configuration_warning_event(
+    configuration: &Configuration,
+    reason: &str,
+    message: &str,
+) -> Event {
+    create_component_configuration_warning_event(
+        AKRI_CONTROLLER_EVENT_COMPONENT,
+        configuration,
+        reason,
+        message,
+    )
+}
+
+/// Create a Warning Event whose involved object is the given Configuration, reported by the given component
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::akri::configuration::Configuration;
+/// use akri_shared::k8s::event;
+///
+/// let configuration = Configuration::new("config-1", serde_json::from_str(r#"{"discoveryHandler": {"name": "debugEcho"}}"#).unwrap());
+/// let warning = event::create_component_configuration_warning_event(event::AKRI_AGENT_EVENT_COMPONENT, &configuration, "MaxInstancesReached", "too many devices");
+/// assert_eq!(warning.source.unwrap().component, Some("akri-agent".to_string()));
+/// ```
+pub fn create_component_configuration_warning_event(
+    component: &str,
+    configuration: &Configuration,
+    reason: &str,
+    message: &str,
+) -> Event {
+    let now = Time(Utc::now());
+    Event {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-", configuration.name_any())),
+            namespace: configuration.namespace(),
+            ..Default::default()
+        },
+        involved_object: ObjectReference {
+            api_version: Some(format!("{}/{}", API_NAMESPACE, API_VERSION)),
+            kind: Some("Configuration".to_string()),
+            name: Some(configuration.name_any()),
+            namespace: configuration.namespace(),
+            uid: configuration.uid(),
+            ..Default::default()
+        },
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+        type_: Some(EVENT_TYPE_WARNING.to_string()),
+        count: Some(1),
+        first_timestamp: Some(now.clone()),
+        last_timestamp: Some(now),
+        source: Some(EventSource {
+            component: Some(component.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Create an Event of the given type whose involved object is the Instance of the given name and
+/// namespace, reported by the given component
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::event;
+///
+/// let allocated = event::create_component_instance_event(event::AKRI_AGENT_EVENT_COMPONENT, "config-1-b494b6", "default", event::EVENT_TYPE_NORMAL, "SlotAllocated", "slot allocated");
+/// assert_eq!(allocated.involved_object.kind, Some("Instance".to_string()));
+/// ```
+pub fn create_component_instance_event(
+    component: &str,
+    instance_name: &str,
+    instance_namespace: &str,
+    type_: &str,
+    reason: &str,
+    message: &str,
+) -> Event {
+    let now = Time(Utc::now());
+    Event {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-", instance_name)),
+            namespace: Some(instance_namespace.to_string()),
+            ..Default::default()
+        },
+        involved_object: ObjectReference {
+            api_version: Some(format!("{}/{}", API_NAMESPACE, API_VERSION)),
+            kind: Some("Instance".to_string()),
+            name: Some(instance_name.to_string()),
+            namespace: Some(instance_namespace.to_string()),
+            ..Default::default()
+        },
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+        type_: Some(type_.to_string()),
+        count: Some(1),
+        first_timestamp: Some(now.clone()),
+        last_timestamp: Some(now),
+        source: Some(EventSource {
+            component: Some(component.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Create Kubernetes Event
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::event;
+/// use kube::client::Client;
+/// use kube::config;
+/// use k8s_openapi::api::core::v1::Event;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// event::create_event(&Event::default(), "event_namespace", api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn create_event(
+    event_to_create: &Event,
+    namespace: &str,
+    kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    trace!("create_event enter");
+    let events: Api<Event> = Api::namespaced(kube_client, namespace);
+    match events.create(&PostParams::default(), event_to_create).await {
+        Ok(created_event) => {
+            info!(
+                "create_event events.create return: {:?}",
+                created_event.metadata.name
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "create_event events.create [{:?}] returned error: {:?}",
+                event_to_create.reason, e
+            );
+            Err(anyhow::anyhow!(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_configuration_warning_event() {
+        let mut configuration = Configuration::new(
+            "config-a",
+            serde_json::from_str(r#"{"discoveryHandler": {"name": "debugEcho"}}"#).unwrap(),
+        );
+        configuration.metadata.namespace = Some("config-a-namespace".to_string());
+        configuration.metadata.uid = Some("e9ab6fb4-dd52-4bd1-9e1b-8a2a5a2d4dd8".to_string());
+
+        let event = create_configuration_warning_event(&configuration, "Reason", "Message");
+        assert_eq!(event.metadata.generate_name, Some("config-a-".to_string()));
+        assert_eq!(
+            event.metadata.namespace,
+            Some("config-a-namespace".to_string())
+        );
+        assert_eq!(
+            event.involved_object.kind,
+            Some("Configuration".to_string())
+        );
+        assert_eq!(event.involved_object.name, Some("config-a".to_string()));
+        assert_eq!(event.involved_object.uid, configuration.metadata.uid);
+        assert_eq!(event.reason, Some("Reason".to_string()));
+        assert_eq!(event.message, Some("Message".to_string()));
+        assert_eq!(event.type_, Some(EVENT_TYPE_WARNING.to_string()));
+        assert_eq!(
+            event.source.unwrap().component,
+            Some(AKRI_CONTROLLER_EVENT_COMPONENT.to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_component_instance_event() {
+        let event = create_component_instance_event(
+            AKRI_AGENT_EVENT_COMPONENT,
+            "config-a-b494b6",
+            "config-a-namespace",
+            EVENT_TYPE_NORMAL,
+            "Reason",
+            "Message",
+        );
+        assert_eq!(
+            event.metadata.generate_name,
+            Some("config-a-b494b6-".to_string())
+        );
+        assert_eq!(
+            event.metadata.namespace,
+            Some("config-a-namespace".to_string())
+        );
+        assert_eq!(
+            event.involved_object.api_version,
+            Some(format!("{}/{}", API_NAMESPACE, API_VERSION))
+        );
+        assert_eq!(event.involved_object.kind, Some("Instance".to_string()));
+        assert_eq!(
+            event.involved_object.name,
+            Some("config-a-b494b6".to_string())
+        );
+        assert_eq!(
+            event.involved_object.namespace,
+            Some("config-a-namespace".to_string())
+        );
+        assert_eq!(event.reason, Some("Reason".to_string()));
+        assert_eq!(event.message, Some("Message".to_string()));
+        assert_eq!(event.type_, Some(EVENT_TYPE_NORMAL.to_string()));
+        assert_eq!(
+            event.source.unwrap().component,
+            Some(AKRI_AGENT_EVENT_COMPONENT.to_string())
+        );
+    }
+}
@@ -95,7 +95,9 @@ pub async fn find_jobs_with_selector(
 ///     shared: true,
 ///     nodes: Vec::new(),
 ///     device_usage: std::collections::HashMap::new(),
-///     broker_properties: std::collections::HashMap::new()
+///     broker_properties: std::collections::HashMap::new(),
+///     first_discovered: None,
+///     last_seen: Default::default(),
 /// };    
 /// let instance = Instance::new("instance_name", instance_spec);
 /// let job = job::create_new_job_from_spec(
@@ -3,16 +3,20 @@ use super::akri::{
     configuration::{Configuration, ConfigurationList},
     instance,
     instance::{Instance, InstanceList, InstanceSpec},
-    retry::{random_delay, MAX_INSTANCE_UPDATE_TRIES},
+    retry::{backoff, MAX_INSTANCE_UPDATE_TRIES},
     API_NAMESPACE, API_VERSION,
 };
 use async_trait::async_trait;
+use k8s_openapi::api::apps::v1::Deployment;
 use k8s_openapi::api::batch::v1::Job;
-use k8s_openapi::api::core::v1::{Node, Pod, Service};
+use k8s_openapi::api::core::v1::{Event, Node, Pod, Service};
 use kube::{api::ObjectList, client::Client};
 use mockall::{automock, predicate::*};
+use std::collections::BTreeMap;
 
 pub mod api;
+pub mod deployment;
+pub mod event;
 pub mod job;
 pub mod node;
 pub mod pod;
@@ -21,6 +25,9 @@ pub mod service;
 pub const NODE_SELECTOR_OP_IN: &str = "In";
 pub const OBJECT_NAME_FIELD: &str = "metadata.name";
 pub const RESOURCE_REQUIREMENTS_KEY: &str = "{{PLACEHOLDER}}";
+/// Token replaced by the Instance's capacity in the resource quantities of a broker PodSpec,
+/// e.g. `cpu: "{{AKRI_INSTANCE_CAPACITY}}00m"`
+pub const INSTANCE_CAPACITY_PLACEHOLDER: &str = "{{AKRI_INSTANCE_CAPACITY}}";
 pub const ERROR_NOT_FOUND: u16 = 404;
 pub const ERROR_CONFLICT: u16 = 409;
 
@@ -99,12 +106,44 @@ pub trait KubeInterface: Send + Sync {
     async fn find_pods_with_field(&self, selector: &str) -> Result<ObjectList<Pod>, anyhow::Error>;
     async fn create_pod(&self, pod_to_create: &Pod, namespace: &str) -> Result<(), anyhow::Error>;
     async fn remove_pod(&self, pod_to_remove: &str, namespace: &str) -> Result<(), anyhow::Error>;
+    async fn remove_pod_with_grace_period(
+        &self,
+        pod_to_remove: &str,
+        namespace: &str,
+        grace_period_seconds: u32,
+    ) -> Result<(), anyhow::Error>;
+
+    async fn create_event(
+        &self,
+        event_to_create: &Event,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
 
     async fn find_jobs_with_label(&self, selector: &str) -> Result<ObjectList<Job>, anyhow::Error>;
     async fn find_jobs_with_field(&self, selector: &str) -> Result<ObjectList<Job>, anyhow::Error>;
     async fn create_job(&self, job_to_create: &Job, namespace: &str) -> Result<(), anyhow::Error>;
     async fn remove_job(&self, job_to_remove: &str, namespace: &str) -> Result<(), anyhow::Error>;
 
+    async fn find_deployments_with_label(
+        &self,
+        selector: &str,
+    ) -> Result<ObjectList<Deployment>, anyhow::Error>;
+    async fn create_deployment(
+        &self,
+        deployment_to_create: &Deployment,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
+    async fn apply_deployment(
+        &self,
+        deployment_to_apply: &Deployment,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
+    async fn remove_deployment(
+        &self,
+        deployment_to_remove: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
+
     async fn find_services(&self, selector: &str) -> Result<ObjectList<Service>, anyhow::Error>;
     async fn create_service(
         &self,
@@ -147,6 +186,12 @@ pub trait KubeInterface: Send + Sync {
         name: &str,
         namespace: &str,
     ) -> Result<(), anyhow::Error>;
+    async fn annotate_instance(
+        &self,
+        annotations: &BTreeMap<String, String>,
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error>;
 }
 
 #[derive(Clone)]
@@ -257,6 +302,57 @@ impl KubeInterface for KubeImpl {
     async fn remove_pod(&self, pod_to_remove: &str, namespace: &str) -> Result<(), anyhow::Error> {
         pod::remove_pod(pod_to_remove, namespace, self.get_kube_client()).await
     }
+    /// Remove Kubernetes pod, giving it the specified grace period (in seconds) to terminate
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.remove_pod_with_grace_period("pod_to_remove", "pod_namespace", 30).await.unwrap();
+    /// # }
+    /// ```
+    async fn remove_pod_with_grace_period(
+        &self,
+        pod_to_remove: &str,
+        namespace: &str,
+        grace_period_seconds: u32,
+    ) -> Result<(), anyhow::Error> {
+        pod::remove_pod_with_grace_period(
+            pod_to_remove,
+            namespace,
+            grace_period_seconds,
+            self.get_kube_client(),
+        )
+        .await
+    }
+
+    /// Create Kubernetes Event
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    /// use k8s_openapi::api::core::v1::Event;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.create_event(&Event::default(), "event_namespace").await.unwrap();
+    /// # }
+    /// ```
+    async fn create_event(
+        &self,
+        event_to_create: &Event,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        event::create_event(event_to_create, namespace, self.get_kube_client()).await
+    }
 
     /// Find Kuberenetes Jobs with specified label selector
     ///
@@ -329,6 +425,97 @@ impl KubeInterface for KubeImpl {
         job::remove_job(job_to_remove, namespace, self.get_kube_client()).await
     }
 
+    /// Find Kuberenetes Deployments with specified label selector
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// let interesting_deployments = kube.find_deployments_with_label("label=interesting").await.unwrap();
+    /// # }
+    /// ```
+    async fn find_deployments_with_label(
+        &self,
+        selector: &str,
+    ) -> Result<ObjectList<Deployment>, anyhow::Error> {
+        deployment::find_deployments_with_selector(
+            Some(selector.to_string()),
+            self.get_kube_client(),
+        )
+        .await
+    }
+
+    /// Create Kuberenetes Deployment
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    /// use k8s_openapi::api::apps::v1::Deployment;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.create_deployment(&Deployment::default(), "deployment_namespace").await.unwrap();
+    /// # }
+    /// ```
+    async fn create_deployment(
+        &self,
+        deployment_to_create: &Deployment,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        deployment::create_deployment(deployment_to_create, namespace, self.get_kube_client()).await
+    }
+    /// Create or update Kuberenetes Deployment with a server-side apply
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    /// use k8s_openapi::api::apps::v1::Deployment;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.apply_deployment(&Deployment::default(), "deployment_namespace").await.unwrap();
+    /// # }
+    /// ```
+    async fn apply_deployment(
+        &self,
+        deployment_to_apply: &Deployment,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        deployment::apply_deployment(deployment_to_apply, namespace, self.get_kube_client()).await
+    }
+    /// Remove Kubernetes Deployment
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s;
+    /// use akri_shared::k8s::KubeInterface;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = k8s::KubeImpl::new().await.unwrap();
+    /// kube.remove_deployment("deployment_to_remove", "deployment_namespace").await.unwrap();
+    /// # }
+    /// ```
+    async fn remove_deployment(
+        &self,
+        deployment_to_remove: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        deployment::remove_deployment(deployment_to_remove, namespace, self.get_kube_client()).await
+    }
+
     /// Get Kuberenetes services with specified label selector
     ///
     /// Example:
@@ -598,6 +785,36 @@ impl KubeInterface for KubeImpl {
         instance::update_instance(instance_to_update, name, namespace, &self.get_kube_client())
             .await
     }
+
+    /// Add Annotations to an Instance
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use akri_shared::k8s::{
+    ///     KubeImpl,
+    ///     KubeInterface
+    /// };
+    /// use std::collections::BTreeMap;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let kube = KubeImpl::new().await.unwrap();
+    /// kube.annotate_instance(
+    ///     &BTreeMap::from([("akri.sh/quarantined".to_string(), "broker crashed".to_string())]),
+    ///     "instance-1",
+    ///     "instance-namespace"
+    /// ).await.unwrap();
+    /// # }
+    /// ```
+    async fn annotate_instance(
+        &self,
+        annotations: &BTreeMap<String, String>,
+        name: &str,
+        namespace: &str,
+    ) -> Result<(), anyhow::Error> {
+        instance::annotate_instance(annotations, name, namespace, &self.get_kube_client()).await
+    }
 }
 
 /// This deletes an Instance unless it has already been deleted by another node
@@ -635,7 +852,7 @@ pub async fn try_delete_instance(
                 }
             }
         }
-        random_delay().await;
+        backoff(x).await;
     }
     Ok(())
 }
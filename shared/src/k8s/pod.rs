@@ -1,11 +1,18 @@
 use super::{
-    super::akri::API_NAMESPACE, OwnershipInfo, ERROR_CONFLICT, ERROR_NOT_FOUND,
+    super::akri::{
+        configuration::{
+            resolve_broker_property_template, BrokerReadinessProbe, BrokerReadinessProbeType,
+            NodeAffinityPreference,
+        },
+        API_NAMESPACE,
+    },
+    OwnershipInfo, ERROR_CONFLICT, ERROR_NOT_FOUND, INSTANCE_CAPACITY_PLACEHOLDER,
     NODE_SELECTOR_OP_IN, OBJECT_NAME_FIELD, RESOURCE_REQUIREMENTS_KEY,
 };
 use either::Either;
 use k8s_openapi::api::core::v1::{
-    Affinity, NodeAffinity, NodeSelector, NodeSelectorRequirement, NodeSelectorTerm, Pod, PodSpec,
-    ResourceRequirements,
+    Affinity, Container, LocalObjectReference, NodeAffinity, NodeSelector, NodeSelectorRequirement,
+    NodeSelectorTerm, Pod, PodSpec, PreferredSchedulingTerm, ResourceRequirements,
 };
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
@@ -14,13 +21,38 @@ use kube::{
     client::Client,
 };
 use log::{error, info, trace};
-use std::collections::BTreeMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 
 pub const APP_LABEL_ID: &str = "app";
 pub const CONTROLLER_LABEL_ID: &str = "controller";
 pub const AKRI_CONFIGURATION_LABEL_NAME: &str = "akri.sh/configuration";
 pub const AKRI_INSTANCE_LABEL_NAME: &str = "akri.sh/instance";
 pub const AKRI_TARGET_NODE_LABEL_NAME: &str = "akri.sh/target-node";
+/// Label holding the hash of the PodSpec a broker Pod was created from
+pub const AKRI_BROKER_SPEC_HASH_LABEL_NAME: &str = "akri.sh/broker-spec-hash";
+/// Name of the init container added to broker Pods by `add_broker_readiness_probe`
+pub const BROKER_READINESS_CONTAINER_NAME: &str = "akri-broker-readiness";
+const DEFAULT_BROKER_READINESS_IMAGE: &str = "busybox:1.36";
+const DEFAULT_BROKER_READINESS_PERIOD_SECS: u32 = 2;
+/// Scripts of the readiness checks, the device endpoint is passed as arguments rather than
+/// in the script so that property values can't inject commands
+const TCP_READINESS_SCRIPT: &str = r#"until nc -z -w "$2" "$0" "$1"; do sleep "$2"; done"#;
+const HTTP_READINESS_SCRIPT: &str =
+    r#"until wget -q -T "$1" -O /dev/null "$0"; do sleep "$1"; done"#;
+
+/// Lists of a PodSpec (and of its containers) that are merged item by item by `merge_pod_spec`,
+/// along with the field identifying an item. All other lists are replaced as a whole.
+const POD_SPEC_MERGE_KEYS: [(&str, &str); 8] = [
+    ("containers", "name"),
+    ("initContainers", "name"),
+    ("ephemeralContainers", "name"),
+    ("volumes", "name"),
+    ("imagePullSecrets", "name"),
+    ("env", "name"),
+    ("volumeMounts", "mountPath"),
+    ("ports", "containerPort"),
+];
 
 /// Get Kubernetes Pods with a given label or field selector
 ///
@@ -280,6 +312,371 @@ pub fn modify_pod_spec(
     }
 }
 
+/// Resolve the Instance related tokens found in the resource requests and limits of the PodSpec's
+/// containers (and init containers), so that brokers can request resources that scale with their
+/// Instance. The available tokens are:
+///
+/// - `{{AKRI_INSTANCE_CAPACITY}}`: the capacity of the Instance, e.g. `cpu: "{{AKRI_INSTANCE_CAPACITY}}00m"`
+///   requests 500m of CPU for an Instance with a capacity of 5
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::{Container, PodSpec, ResourceRequirements};
+/// use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+/// use std::collections::BTreeMap;
+///
+/// let mut pod_spec = PodSpec {
+///     containers: vec![Container {
+///         resources: Some(ResourceRequirements {
+///             requests: Some(BTreeMap::from([(
+///                 "cpu".to_string(),
+///                 Quantity("{{AKRI_INSTANCE_CAPACITY}}00m".to_string()),
+///             )])),
+///             ..Default::default()
+///         }),
+///         ..Default::default()
+///     }],
+///     ..Default::default()
+/// };
+/// pod::resolve_instance_placeholders(&mut pod_spec, 5);
+/// assert_eq!(
+///     pod_spec.containers[0].resources.as_ref().unwrap().requests.as_ref().unwrap()["cpu"],
+///     Quantity("500m".to_string())
+/// );
+/// ```
+pub fn resolve_instance_placeholders(pod_spec: &mut PodSpec, capacity: i32) {
+    let capacity = capacity.to_string();
+    for container in pod_spec
+        .containers
+        .iter_mut()
+        .chain(pod_spec.init_containers.iter_mut().flatten())
+    {
+        if let Some(resources) = container.resources.as_mut() {
+            for quantity in resources
+                .limits
+                .iter_mut()
+                .chain(resources.requests.iter_mut())
+                .flat_map(|map| map.values_mut())
+            {
+                quantity.0 = quantity.0.replace(INSTANCE_CAPACITY_PLACEHOLDER, &capacity);
+            }
+        }
+    }
+}
+
+/// Add a `preferredDuringSchedulingIgnoredDuringExecution` node affinity term to the PodSpec for
+/// each of the preferences, matching nodes that have all the preference's labels. These coexist
+/// with the required node affinity added by `modify_pod_spec` and with any existing affinity.
+/// An empty list of preferences leaves the PodSpec untouched.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::akri::configuration::NodeAffinityPreference;
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::PodSpec;
+/// use std::collections::BTreeMap;
+///
+/// let mut pod_spec = PodSpec::default();
+/// pod::add_node_affinity_preferences(&mut pod_spec, &[NodeAffinityPreference {
+///     weight: 50,
+///     match_labels: BTreeMap::from([("gpu".to_string(), "true".to_string())]),
+/// }]);
+/// assert!(pod_spec.affinity.is_some());
+/// ```
+pub fn add_node_affinity_preferences(
+    pod_spec: &mut PodSpec,
+    preferences: &[NodeAffinityPreference],
+) {
+    if preferences.is_empty() {
+        return;
+    }
+    pod_spec
+        .affinity
+        .get_or_insert(Affinity::default())
+        .node_affinity
+        .get_or_insert(NodeAffinity::default())
+        .preferred_during_scheduling_ignored_during_execution
+        .get_or_insert(Vec::new())
+        .extend(preferences.iter().map(|preference| {
+            PreferredSchedulingTerm {
+                weight: preference.weight,
+                preference: NodeSelectorTerm {
+                    match_expressions: Some(
+                        preference
+                            .match_labels
+                            .iter()
+                            .map(|(key, value)| NodeSelectorRequirement {
+                                key: key.clone(),
+                                operator: NODE_SELECTOR_OP_IN.to_string(),
+                                values: Some(vec![value.clone()]),
+                            })
+                            .collect(),
+                    ),
+                    ..Default::default()
+                },
+            }
+        }));
+}
+
+/// Set the scheduler of the PodSpec, so that the Pod gets scheduled by the named scheduler
+/// instead of the default one, it takes precedence over the one of the PodSpec as
+/// `merge_pod_spec` gives it. `None` leaves the PodSpec untouched.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::PodSpec;
+///
+/// let mut pod_spec = PodSpec::default();
+/// pod::set_scheduler_name(&mut pod_spec, Some("edge-scheduler")).unwrap();
+/// assert_eq!(Some("edge-scheduler".to_string()), pod_spec.scheduler_name);
+/// ```
+pub fn set_scheduler_name(
+    pod_spec: &mut PodSpec,
+    scheduler_name: Option<&str>,
+) -> anyhow::Result<()> {
+    let Some(scheduler_name) = scheduler_name else {
+        return Ok(());
+    };
+    *pod_spec = merge_pod_spec(
+        pod_spec,
+        &PodSpec {
+            scheduler_name: Some(scheduler_name.to_string()),
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
+
+/// Add imagePullSecrets to the PodSpec, after the ones it already lists, as `merge_pod_spec` merges
+/// them. Secrets the PodSpec already lists are not added again. An empty list leaves the PodSpec
+/// untouched.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::{LocalObjectReference, PodSpec};
+///
+/// let mut pod_spec = PodSpec::default();
+/// pod::add_image_pull_secrets(&mut pod_spec, &[LocalObjectReference {
+///     name: Some("regcred".to_string()),
+/// }])
+/// .unwrap();
+/// assert_eq!(pod_spec.image_pull_secrets.unwrap().len(), 1);
+/// ```
+pub fn add_image_pull_secrets(
+    pod_spec: &mut PodSpec,
+    secrets: &[LocalObjectReference],
+) -> anyhow::Result<()> {
+    if secrets.is_empty() {
+        return Ok(());
+    }
+    *pod_spec = merge_pod_spec(
+        pod_spec,
+        &PodSpec {
+            image_pull_secrets: Some(secrets.to_vec()),
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
+
+/// Add an init container to the PodSpec that blocks until the device endpoint described by the
+/// probe is reachable, so that the broker containers only start then. The `${PROP_NAME}`
+/// references of the probe's target are resolved against the Instance's broker properties.
+/// `None` leaves the PodSpec untouched.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::akri::configuration::{BrokerReadinessProbe, BrokerReadinessProbeType};
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::PodSpec;
+/// use std::collections::HashMap;
+///
+/// let mut pod_spec = PodSpec::default();
+/// pod::add_broker_readiness_probe(
+///     &mut pod_spec,
+///     Some(&BrokerReadinessProbe {
+///         probe_type: BrokerReadinessProbeType::Tcp,
+///         target: "${DEVICE_IP}:554".to_string(),
+///         image: None,
+///         period_seconds: None,
+///     }),
+///     &HashMap::from([("DEVICE_IP".to_string(), "10.0.0.1".to_string())]),
+/// )
+/// .unwrap();
+/// assert_eq!(pod_spec.init_containers.unwrap().len(), 1);
+/// ```
+pub fn add_broker_readiness_probe(
+    pod_spec: &mut PodSpec,
+    probe: Option<&BrokerReadinessProbe>,
+    broker_properties: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let Some(probe) = probe else {
+        return Ok(());
+    };
+    let target =
+        resolve_broker_property_template(&probe.target, broker_properties).map_err(|name| {
+            anyhow::anyhow!(
+                "broker readiness probe references undefined property {}",
+                name
+            )
+        })?;
+    let period = probe
+        .period_seconds
+        .unwrap_or(DEFAULT_BROKER_READINESS_PERIOD_SECS)
+        .to_string();
+    let command = match probe.probe_type {
+        BrokerReadinessProbeType::Tcp => {
+            let (host, port) = target
+                .rsplit_once(':')
+                .filter(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "broker readiness probe target {} is not a host:port",
+                        target
+                    )
+                })?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            vec![
+                TCP_READINESS_SCRIPT.to_string(),
+                host.to_string(),
+                port.to_string(),
+                period,
+            ]
+        }
+        BrokerReadinessProbeType::Http => {
+            vec![HTTP_READINESS_SCRIPT.to_string(), target, period]
+        }
+    };
+    pod_spec
+        .init_containers
+        .get_or_insert_with(Vec::new)
+        .insert(
+            0,
+            Container {
+                name: BROKER_READINESS_CONTAINER_NAME.to_string(),
+                image: Some(
+                    probe
+                        .image
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_BROKER_READINESS_IMAGE.to_string()),
+                ),
+                command: Some(vec!["sh".to_string(), "-c".to_string()]),
+                args: Some(command),
+                ..Default::default()
+            },
+        );
+    Ok(())
+}
+
+/// Get a hash of a broker PodSpec, suitable as a label value, to tell whether a broker Pod was
+/// created from the current PodSpec of its Configuration. It is meant for the PodSpec the broker
+/// Pods are created from, once the controller has added the scheduler name, image pull secrets and
+/// readiness probe of the Configuration to it. The hash (64 bits FNV-1a of the PodSpec's JSON
+/// serialization) is stable across restarts of the controller.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::PodSpec;
+///
+/// let pod_spec = PodSpec::default();
+/// assert_eq!(pod::broker_spec_hash(&pod_spec), pod::broker_spec_hash(&pod_spec.clone()));
+/// ```
+pub fn broker_spec_hash(pod_spec: &PodSpec) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    let hash = serde_json::to_vec(pod_spec)
+        .unwrap_or_default()
+        .iter()
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Deep-merge `overrides` into `base`, returning the merged PodSpec. Typically `base` is the
+/// Configuration default and `overrides` is specific to a device, so that the more specific one wins.
+///
+/// Precedence rules:
+/// 1. A field set in `overrides` takes precedence over the same field in `base`, fields set in
+///    only one of them are kept.
+/// 2. Objects (affinity, security contexts, resources, node selector, ...) are merged recursively,
+///    field by field (or key by key for maps).
+/// 3. Lists of identifiable items (containers, init containers, volumes, image pull secrets, and
+///    the env, volume mounts and ports of a container, see `POD_SPEC_MERGE_KEYS`) are merged item
+///    by item, items only present in `overrides` are appended.
+/// 4. All other lists (tolerations, command, args, ...) from `overrides` replace the ones in `base`.
+///
+/// Pod level and container level fields are merged separately, so a container level setting
+/// (e.g. its security context) is never replaced by a pod level one and keeps the precedence
+/// Kubernetes gives it.
+///
+/// Example:
+///
+/// ```
+/// use akri_shared::k8s::pod;
+/// use k8s_openapi::api::core::v1::PodSpec;
+///
+/// let base = PodSpec {
+///     service_account_name: Some("default-sa".to_string()),
+///     ..Default::default()
+/// };
+/// let overrides = PodSpec {
+///     service_account_name: Some("device-sa".to_string()),
+///     ..Default::default()
+/// };
+/// let merged = pod::merge_pod_spec(&base, &overrides).unwrap();
+/// assert_eq!(merged.service_account_name, Some("device-sa".to_string()));
+/// ```
+pub fn merge_pod_spec(base: &PodSpec, overrides: &PodSpec) -> anyhow::Result<PodSpec> {
+    let mut merged = serde_json::to_value(base)?;
+    merge_value(&mut merged, serde_json::to_value(overrides)?, None);
+    Ok(serde_json::from_value(merged)?)
+}
+
+/// Recursively merges `overrides` into `base` following the rules of `merge_pod_spec`.
+/// `merge_key` is the field identifying items when `base` is a list merged item by item.
+fn merge_value(base: &mut Value, overrides: Value, merge_key: Option<&str>) {
+    match (base, overrides, merge_key) {
+        (Value::Object(base_fields), Value::Object(override_fields), _) => {
+            for (field, value) in override_fields {
+                let item_key = POD_SPEC_MERGE_KEYS
+                    .iter()
+                    .find(|(list, _)| *list == field)
+                    .map(|(_, key)| *key);
+                match base_fields.get_mut(&field) {
+                    Some(base_value) => merge_value(base_value, value, item_key),
+                    None => {
+                        base_fields.insert(field, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base_items), Value::Array(override_items), Some(merge_key)) => {
+            for item in override_items {
+                let id = item.get(merge_key).cloned();
+                match base_items
+                    .iter_mut()
+                    .find(|base_item| id.is_some() && base_item.get(merge_key) == id.as_ref())
+                {
+                    Some(base_item) => merge_value(base_item, item, None),
+                    None => base_items.push(item),
+                }
+            }
+        }
+        (base, overrides, _) => *base = overrides,
+    }
+}
+
 /// Create Kubernetes Pod
 ///
 /// Example:
@@ -355,11 +752,54 @@ pub async fn remove_pod(
     pod_to_remove: &str,
     namespace: &str,
     kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    remove_pod_with_params(
+        pod_to_remove,
+        namespace,
+        &DeleteParams::default(),
+        kube_client,
+    )
+    .await
+}
+
+/// Remove Kubernetes Pod, giving it the specified grace period (in seconds) to terminate
+///
+/// Example:
+///
+/// ```no_run
+/// use akri_shared::k8s::pod;
+/// use kube::client::Client;
+/// use kube::config;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let api_client = Client::try_default().await.unwrap();
+/// pod::remove_pod_with_grace_period("pod_to_remove", "pod_namespace", 30, api_client).await.unwrap();
+/// # }
+/// ```
+pub async fn remove_pod_with_grace_period(
+    pod_to_remove: &str,
+    namespace: &str,
+    grace_period_seconds: u32,
+    kube_client: Client,
+) -> Result<(), anyhow::Error> {
+    let params = DeleteParams {
+        grace_period_seconds: Some(grace_period_seconds),
+        ..Default::default()
+    };
+    remove_pod_with_params(pod_to_remove, namespace, &params, kube_client).await
+}
+
+async fn remove_pod_with_params(
+    pod_to_remove: &str,
+    namespace: &str,
+    params: &DeleteParams,
+    kube_client: Client,
 ) -> Result<(), anyhow::Error> {
     trace!("remove_pod enter");
     let pods: Api<Pod> = Api::namespaced(kube_client, namespace);
     info!("remove_pod pods.delete(...).await?:");
-    match pods.delete(pod_to_remove, &DeleteParams::default()).await {
+    match pods.delete(pod_to_remove, params).await {
         Ok(deleted_pod) => match deleted_pod {
             Either::Left(spec) => {
                 info!("remove_pod pods.delete return: {:?}", &spec.metadata.name);
@@ -399,6 +839,7 @@ mod broker_podspec_tests {
     use super::*;
     use env_logger;
     use k8s_openapi::api::core::v1::Container;
+    use serde_json::json;
 
     #[test]
     fn test_create_broker_app_name() {
@@ -537,6 +978,382 @@ mod broker_podspec_tests {
         );
     }
 
+    #[test]
+    fn test_add_node_affinity_preferences() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let existing_term = PreferredSchedulingTerm {
+            weight: 10,
+            preference: NodeSelectorTerm {
+                match_expressions: Some(vec![NodeSelectorRequirement {
+                    key: "do-not-change-this".to_string(),
+                    operator: NODE_SELECTOR_OP_IN.to_string(),
+                    values: Some(vec!["existing".to_string()]),
+                }]),
+                ..Default::default()
+            },
+        };
+        let mut pod_spec = PodSpec {
+            affinity: Some(Affinity {
+                node_affinity: Some(NodeAffinity {
+                    preferred_during_scheduling_ignored_during_execution: Some(vec![
+                        existing_term.clone()
+                    ]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        add_node_affinity_preferences(
+            &mut pod_spec,
+            &[
+                NodeAffinityPreference {
+                    weight: 80,
+                    match_labels: BTreeMap::from([("gpu".to_string(), "true".to_string())]),
+                },
+                NodeAffinityPreference {
+                    weight: 20,
+                    match_labels: BTreeMap::from([
+                        ("tier".to_string(), "edge".to_string()),
+                        ("zone".to_string(), "a".to_string()),
+                    ]),
+                },
+            ],
+        );
+        let pod = create_new_pod_from_spec(
+            "pod_namespace",
+            "instance_name",
+            "configuration_name",
+            OwnershipInfo::new(
+                OwnershipType::Instance,
+                "instance_name".to_string(),
+                "instance_uid".to_string(),
+            ),
+            "resource_limit_name",
+            "node-a",
+            true,
+            &pod_spec,
+        )
+        .unwrap();
+        let node_affinity = pod.spec.unwrap().affinity.unwrap().node_affinity.unwrap();
+        let requirement = |key: &str, value: &str| NodeSelectorRequirement {
+            key: key.to_string(),
+            operator: NODE_SELECTOR_OP_IN.to_string(),
+            values: Some(vec![value.to_string()]),
+        };
+        assert_eq!(
+            node_affinity.preferred_during_scheduling_ignored_during_execution,
+            Some(vec![
+                existing_term,
+                PreferredSchedulingTerm {
+                    weight: 80,
+                    preference: NodeSelectorTerm {
+                        match_expressions: Some(vec![requirement("gpu", "true")]),
+                        ..Default::default()
+                    },
+                },
+                PreferredSchedulingTerm {
+                    weight: 20,
+                    preference: NodeSelectorTerm {
+                        match_expressions: Some(vec![
+                            requirement("tier", "edge"),
+                            requirement("zone", "a"),
+                        ]),
+                        ..Default::default()
+                    },
+                },
+            ])
+        );
+        // The Pod is still required to run on its target node
+        assert_eq!(
+            node_affinity
+                .required_during_scheduling_ignored_during_execution
+                .unwrap()
+                .node_selector_terms[0]
+                .match_fields,
+            Some(vec![NodeSelectorRequirement {
+                key: OBJECT_NAME_FIELD.to_string(),
+                operator: NODE_SELECTOR_OP_IN.to_string(),
+                values: Some(vec!["node-a".to_string()]),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_resolve_instance_placeholders() {
+        let resources = ResourceRequirements {
+            limits: Some(BTreeMap::from([
+                (
+                    "cpu".to_string(),
+                    Quantity("{{AKRI_INSTANCE_CAPACITY}}00m".to_string()),
+                ),
+                (
+                    "memory".to_string(),
+                    Quantity("{{AKRI_INSTANCE_CAPACITY}}Gi".to_string()),
+                ),
+                (
+                    RESOURCE_REQUIREMENTS_KEY.to_string(),
+                    Quantity("1".to_string()),
+                ),
+            ])),
+            requests: Some(BTreeMap::from([
+                (
+                    "cpu".to_string(),
+                    Quantity("{{AKRI_INSTANCE_CAPACITY}}0m".to_string()),
+                ),
+                ("memory".to_string(), Quantity("11Mi".to_string())),
+            ])),
+        };
+        let mut pod_spec = PodSpec {
+            containers: vec![Container {
+                resources: Some(resources.clone()),
+                ..Default::default()
+            }],
+            init_containers: Some(vec![Container {
+                resources: Some(resources),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        resolve_instance_placeholders(&mut pod_spec, 3);
+
+        let expected = ResourceRequirements {
+            limits: Some(BTreeMap::from([
+                ("cpu".to_string(), Quantity("300m".to_string())),
+                ("memory".to_string(), Quantity("3Gi".to_string())),
+                (
+                    RESOURCE_REQUIREMENTS_KEY.to_string(),
+                    Quantity("1".to_string()),
+                ),
+            ])),
+            requests: Some(BTreeMap::from([
+                ("cpu".to_string(), Quantity("30m".to_string())),
+                ("memory".to_string(), Quantity("11Mi".to_string())),
+            ])),
+        };
+        assert_eq!(pod_spec.containers[0].resources, Some(expected.clone()));
+        assert_eq!(
+            pod_spec.init_containers.unwrap()[0].resources,
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn test_add_node_affinity_preferences_empty() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut pod_spec = PodSpec::default();
+        add_node_affinity_preferences(&mut pod_spec, &[]);
+        assert_eq!(PodSpec::default(), pod_spec);
+    }
+
+    #[test]
+    fn test_broker_spec_hash() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let pod_spec = |image: &str| PodSpec {
+            containers: vec![Container {
+                name: "broker".to_string(),
+                image: Some(image.to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let hash = broker_spec_hash(&pod_spec("nginx:1.0"));
+        assert_eq!(16, hash.len());
+        assert_eq!(hash, broker_spec_hash(&pod_spec("nginx:1.0")));
+        assert_ne!(hash, broker_spec_hash(&pod_spec("nginx:2.0")));
+    }
+
+    #[test]
+    fn test_add_broker_readiness_probe() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let properties = HashMap::from([
+            ("DEVICE_IP".to_string(), "10.0.0.1".to_string()),
+            (
+                "DEVICE_URL".to_string(),
+                "http://10.0.0.1/onvif/device_service".to_string(),
+            ),
+        ]);
+        let probe = |probe_type, target: &str| BrokerReadinessProbe {
+            probe_type,
+            target: target.to_string(),
+            image: None,
+            period_seconds: None,
+        };
+
+        let mut pod_spec = PodSpec::default();
+        add_broker_readiness_probe(&mut pod_spec, None, &properties).unwrap();
+        assert_eq!(PodSpec::default(), pod_spec);
+
+        // The readiness check runs before any other init container
+        let mut pod_spec = PodSpec {
+            init_containers: Some(vec![Container {
+                name: "init".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        add_broker_readiness_probe(
+            &mut pod_spec,
+            Some(&probe(BrokerReadinessProbeType::Tcp, "${DEVICE_IP}:554")),
+            &properties,
+        )
+        .unwrap();
+        let init_containers = pod_spec.init_containers.unwrap();
+        assert_eq!(init_containers.len(), 2);
+        assert_eq!(init_containers[0].name, BROKER_READINESS_CONTAINER_NAME);
+        assert_eq!(
+            init_containers[0].image.as_deref(),
+            Some(DEFAULT_BROKER_READINESS_IMAGE)
+        );
+        assert_eq!(
+            init_containers[0].command,
+            Some(vec!["sh".to_string(), "-c".to_string()])
+        );
+        assert_eq!(
+            init_containers[0].args,
+            Some(vec![
+                TCP_READINESS_SCRIPT.to_string(),
+                "10.0.0.1".to_string(),
+                "554".to_string(),
+                "2".to_string(),
+            ])
+        );
+        assert_eq!(init_containers[1].name, "init");
+
+        let mut pod_spec = PodSpec::default();
+        add_broker_readiness_probe(
+            &mut pod_spec,
+            Some(&BrokerReadinessProbe {
+                image: Some("curlimages/curl:8.5.0".to_string()),
+                period_seconds: Some(5),
+                ..probe(BrokerReadinessProbeType::Http, "${DEVICE_URL}")
+            }),
+            &properties,
+        )
+        .unwrap();
+        let container = &pod_spec.init_containers.unwrap()[0];
+        assert_eq!(container.image.as_deref(), Some("curlimages/curl:8.5.0"));
+        assert_eq!(
+            container.args,
+            Some(vec![
+                HTTP_READINESS_SCRIPT.to_string(),
+                "http://10.0.0.1/onvif/device_service".to_string(),
+                "5".to_string(),
+            ])
+        );
+
+        // IPv6 hosts are given without brackets
+        let mut pod_spec = PodSpec::default();
+        add_broker_readiness_probe(
+            &mut pod_spec,
+            Some(&probe(BrokerReadinessProbeType::Tcp, "[fe80::1]:80")),
+            &properties,
+        )
+        .unwrap();
+        assert_eq!(
+            pod_spec.init_containers.unwrap()[0].args.as_ref().unwrap()[1],
+            "fe80::1"
+        );
+    }
+
+    #[test]
+    fn test_add_broker_readiness_probe_invalid() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let properties = HashMap::from([("DEVICE_IP".to_string(), "10.0.0.1".to_string())]);
+        for target in [
+            "${DEVICE_HOST}:554",
+            "${DEVICE_IP}",
+            "${DEVICE_IP}:port",
+            ":554",
+        ] {
+            let mut pod_spec = PodSpec::default();
+            assert!(add_broker_readiness_probe(
+                &mut pod_spec,
+                Some(&BrokerReadinessProbe {
+                    probe_type: BrokerReadinessProbeType::Tcp,
+                    target: target.to_string(),
+                    image: None,
+                    period_seconds: None,
+                }),
+                &properties,
+            )
+            .is_err());
+            assert_eq!(PodSpec::default(), pod_spec);
+        }
+    }
+
+    #[test]
+    fn test_add_image_pull_secrets() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let secret = |name: &str| LocalObjectReference {
+            name: Some(name.to_string()),
+        };
+        let mut pod_spec = PodSpec::default();
+        add_image_pull_secrets(&mut pod_spec, &[]).unwrap();
+        assert_eq!(PodSpec::default(), pod_spec);
+
+        pod_spec.image_pull_secrets = Some(vec![secret("spec-regcred"), secret("regcred")]);
+        add_image_pull_secrets(
+            &mut pod_spec,
+            &[secret("regcred"), secret("config-regcred")],
+        )
+        .unwrap();
+        let pod = create_new_pod_from_spec(
+            "pod_namespace",
+            "instance_name",
+            "configuration_name",
+            OwnershipInfo::new(
+                OwnershipType::Instance,
+                "instance_name".to_string(),
+                "instance_uid".to_string(),
+            ),
+            "resource_limit_name",
+            "node-a",
+            true,
+            &pod_spec,
+        )
+        .unwrap();
+        // The PodSpec's own secrets come first, secrets listed twice are only kept once
+        assert_eq!(
+            Some(vec![
+                secret("spec-regcred"),
+                secret("regcred"),
+                secret("config-regcred")
+            ]),
+            pod.spec.unwrap().image_pull_secrets
+        );
+    }
+
+    #[test]
+    fn test_set_scheduler_name() {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut pod_spec = PodSpec::default();
+        set_scheduler_name(&mut pod_spec, None).unwrap();
+        assert_eq!(PodSpec::default(), pod_spec);
+
+        set_scheduler_name(&mut pod_spec, Some("edge-scheduler")).unwrap();
+        let pod = create_new_pod_from_spec(
+            "pod_namespace",
+            "instance_name",
+            "configuration_name",
+            OwnershipInfo::new(
+                OwnershipType::Instance,
+                "instance_name".to_string(),
+                "instance_uid".to_string(),
+            ),
+            "resource_limit_name",
+            "node-a",
+            true,
+            &pod_spec,
+        )
+        .unwrap();
+        assert_eq!(
+            Some("edge-scheduler".to_string()),
+            pod.spec.unwrap().scheduler_name
+        );
+    }
+
     fn do_pod_spec_creation_test(
         image_names: Vec<String>,
         container_specs: Vec<Container>,
@@ -1100,4 +1917,189 @@ mod broker_podspec_tests {
             }
         }
     }
+
+    fn pod_spec_from_json(value: serde_json::Value) -> PodSpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_merge_pod_spec_empty_overrides() {
+        let base = pod_spec_from_json(json!({
+            "containers": [{"name": "broker", "image": "broker:v1"}],
+            "serviceAccountName": "default-sa",
+        }));
+        assert_eq!(merge_pod_spec(&base, &PodSpec::default()).unwrap(), base);
+        assert_eq!(merge_pod_spec(&PodSpec::default(), &base).unwrap(), base);
+    }
+
+    #[test]
+    fn test_merge_pod_spec_pod_level_fields() {
+        let base = pod_spec_from_json(json!({
+            "containers": [],
+            "serviceAccountName": "default-sa",
+            "restartPolicy": "Always",
+            "nodeSelector": {"disk": "ssd", "zone": "a"},
+            "tolerations": [{"key": "a", "operator": "Exists"}, {"key": "b", "operator": "Exists"}],
+            "affinity": {
+                "podAntiAffinity": {
+                    "preferredDuringSchedulingIgnoredDuringExecution": [{
+                        "weight": 1,
+                        "podAffinityTerm": {"topologyKey": "kubernetes.io/hostname"}
+                    }]
+                }
+            },
+        }));
+        let overrides = pod_spec_from_json(json!({
+            "containers": [],
+            "serviceAccountName": "device-sa",
+            "nodeSelector": {"zone": "b"},
+            "tolerations": [{"key": "c", "operator": "Exists"}],
+            "affinity": {
+                "nodeAffinity": {
+                    "preferredDuringSchedulingIgnoredDuringExecution": [{
+                        "weight": 1,
+                        "preference": {"matchExpressions": [{"key": "gpu", "operator": "Exists"}]}
+                    }]
+                }
+            },
+        }));
+        let merged = merge_pod_spec(&base, &overrides).unwrap();
+
+        // Scalars are overridden, unset ones are kept
+        assert_eq!(merged.service_account_name, Some("device-sa".to_string()));
+        assert_eq!(merged.restart_policy, Some("Always".to_string()));
+        // Maps are merged key by key
+        assert_eq!(
+            merged.node_selector,
+            Some(BTreeMap::from([
+                ("disk".to_string(), "ssd".to_string()),
+                ("zone".to_string(), "b".to_string()),
+            ]))
+        );
+        // Lists without merge key are replaced
+        let tolerations = merged.tolerations.unwrap();
+        assert_eq!(tolerations.len(), 1);
+        assert_eq!(tolerations[0].key, Some("c".to_string()));
+        // Nested objects are merged field by field
+        let affinity = merged.affinity.unwrap();
+        assert!(affinity.pod_anti_affinity.is_some());
+        assert!(affinity.node_affinity.is_some());
+    }
+
+    #[test]
+    fn test_merge_pod_spec_containers() {
+        let base = pod_spec_from_json(json!({
+            "containers": [
+                {
+                    "name": "broker",
+                    "image": "broker:v1",
+                    "args": ["--verbose", "--debug"],
+                    "env": [{"name": "A", "value": "base"}, {"name": "B", "value": "base"}],
+                    "ports": [{"containerPort": 8080, "name": "http"}],
+                    "resources": {"limits": {"cpu": "100m", "memory": "64Mi"}},
+                    "volumeMounts": [{"name": "config", "mountPath": "/etc/config"}],
+                },
+                {"name": "sidecar", "image": "sidecar:v1"},
+            ],
+            "initContainers": [{"name": "init", "image": "init:v1"}],
+            "volumes": [{"name": "config", "configMap": {"name": "base-config"}}],
+        }));
+        let overrides = pod_spec_from_json(json!({
+            "containers": [
+                {
+                    "name": "broker",
+                    "image": "broker:v2",
+                    "args": ["--quiet"],
+                    "env": [{"name": "B", "value": "device"}, {"name": "C", "value": "device"}],
+                    "ports": [{"containerPort": 8080, "name": "web"}, {"containerPort": 9090}],
+                    "resources": {"limits": {"memory": "128Mi"}},
+                    "volumeMounts": [{"name": "data", "mountPath": "/data"}],
+                },
+                {"name": "exporter", "image": "exporter:v1"},
+            ],
+            "volumes": [
+                {"name": "config", "configMap": {"name": "device-config"}},
+                {"name": "data", "emptyDir": {}},
+            ],
+        }));
+        let merged = merge_pod_spec(&base, &overrides).unwrap();
+
+        // Containers are merged by name, containers only in overrides are appended
+        let names: Vec<&str> = merged.containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["broker", "sidecar", "exporter"]);
+        let broker = &merged.containers[0];
+        assert_eq!(broker.image, Some("broker:v2".to_string()));
+        assert_eq!(broker.args, Some(vec!["--quiet".to_string()]));
+        let env: Vec<(String, Option<String>)> = broker
+            .env
+            .clone()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.value))
+            .collect();
+        assert_eq!(
+            env,
+            vec![
+                ("A".to_string(), Some("base".to_string())),
+                ("B".to_string(), Some("device".to_string())),
+                ("C".to_string(), Some("device".to_string())),
+            ]
+        );
+        let ports = broker.ports.clone().unwrap();
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].name, Some("web".to_string()));
+        assert_eq!(ports[1].container_port, 9090);
+        let limits = broker.resources.clone().unwrap().limits.unwrap();
+        assert_eq!(limits.get("cpu"), Some(&Quantity("100m".to_string())));
+        assert_eq!(limits.get("memory"), Some(&Quantity("128Mi".to_string())));
+        let mount_paths: Vec<String> = broker
+            .volume_mounts
+            .clone()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.mount_path)
+            .collect();
+        assert_eq!(mount_paths, vec!["/etc/config", "/data"]);
+        assert_eq!(merged.containers[1].image, Some("sidecar:v1".to_string()));
+
+        // Init containers only in base are kept
+        assert_eq!(merged.init_containers.unwrap()[0].name, "init");
+
+        // Volumes are merged by name
+        let volumes = merged.volumes.unwrap();
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(
+            volumes[0].config_map.as_ref().unwrap().name,
+            Some("device-config".to_string())
+        );
+        assert!(volumes[1].empty_dir.is_some());
+    }
+
+    #[test]
+    fn test_merge_pod_spec_security_context_precedence() {
+        let base = pod_spec_from_json(json!({
+            "containers": [{
+                "name": "broker",
+                "securityContext": {"runAsUser": 1000, "privileged": false},
+            }],
+            "securityContext": {"runAsUser": 2000, "runAsGroup": 3000},
+        }));
+        let overrides = pod_spec_from_json(json!({
+            "containers": [{
+                "name": "broker",
+                "securityContext": {"privileged": true},
+            }],
+            "securityContext": {"runAsUser": 4000},
+        }));
+        let merged = merge_pod_spec(&base, &overrides).unwrap();
+
+        // Pod level security context is merged on its own
+        let pod_security_context = merged.security_context.unwrap();
+        assert_eq!(pod_security_context.run_as_user, Some(4000));
+        assert_eq!(pod_security_context.run_as_group, Some(3000));
+        // Container level security context is not replaced by the pod level one
+        let container_security_context = merged.containers[0].security_context.clone().unwrap();
+        assert_eq!(container_security_context.run_as_user, Some(1000));
+        assert_eq!(container_security_context.privileged, Some(true));
+    }
 }
@@ -30,3 +30,29 @@ impl EnvVarQuery for ActualEnvVarQuery {
         env::vars().collect::<Vec<(String, String)>>()
     }
 }
+
+/// Gets a boolean from an environment variable, `default` is returned when it is not set or
+/// does not hold a boolean
+///
+/// Example
+/// ```
+/// use akri_shared::os::env_var::{get_env_bool, MockEnvVarQuery};
+///
+/// let mut env_query = MockEnvVarQuery::new();
+/// env_query
+///     .expect_get_env_var()
+///     .returning(|_| Ok("false".to_string()));
+/// assert!(!get_env_bool(&env_query, "SOME_FLAG", true));
+/// ```
+pub fn get_env_bool(env_var_query: &impl EnvVarQuery, label: &'static str, default: bool) -> bool {
+    let Ok(value) = env_var_query.get_env_var(label) else {
+        return default;
+    };
+    match value.parse::<bool>() {
+        Ok(value) => value,
+        Err(e) => {
+            log::error!("get_env_bool - invalid {} value {:?}: {}", label, value, e);
+            default
+        }
+    }
+}
@@ -3,7 +3,8 @@ pub mod env_var;
 /// Provide file operations
 pub mod file {
     use std::fs;
-    use std::path::PathBuf;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
     /// This will convert a relative path into the canonical path using std::fs
     pub fn get_canonical_path(relative_path: &str) -> String {
         fs::canonicalize(PathBuf::from(&relative_path))
@@ -18,4 +19,58 @@ pub mod file {
         fs::read_to_string(&file_path)
             .unwrap_or_else(|_| panic!("unable to read file: {}", &file_path))
     }
+    /// This will write `content` to a file, truncating it if `max_bytes` is not set.
+    /// If `max_bytes` is set, `content` is appended to the file, which is first rotated to `<path>.1`
+    /// (replacing any previously rotated file) if appending would make it exceed `max_bytes`.
+    pub fn write_string_to_file(
+        path: &Path,
+        content: &str,
+        max_bytes: Option<u64>,
+    ) -> std::io::Result<()> {
+        let max_bytes = match max_bytes {
+            Some(max_bytes) => max_bytes,
+            None => return fs::write(path, content),
+        };
+        let current_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if current_size > 0 && current_size + content.len() as u64 > max_bytes {
+            let mut rotated_path = path.as_os_str().to_owned();
+            rotated_path.push(".1");
+            fs::rename(path, rotated_path)?;
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(content.as_bytes())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_write_string_to_file_truncate() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("export.json");
+            write_string_to_file(&path, "first", None).unwrap();
+            write_string_to_file(&path, "second", None).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        }
+
+        #[test]
+        fn test_write_string_to_file_rotate() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("export.json");
+            let rotated_path = dir.path().join("export.json.1");
+            write_string_to_file(&path, "aaaa\n", Some(10)).unwrap();
+            write_string_to_file(&path, "bbbb\n", Some(10)).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "aaaa\nbbbb\n");
+            assert!(!rotated_path.exists());
+
+            // Exceeding the maximum size rotates the file
+            write_string_to_file(&path, "cccc\n", Some(10)).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "cccc\n");
+            assert_eq!(fs::read_to_string(&rotated_path).unwrap(), "aaaa\nbbbb\n");
+        }
+    }
 }
@@ -0,0 +1,337 @@
+use actix_web::{post, web, App, HttpResponse, HttpServer, Responder};
+use clap::Arg;
+use openapi::models::{
+    V1AdmissionRequest as AdmissionRequest, V1AdmissionResponse as AdmissionResponse,
+    V1AdmissionReview as AdmissionReview,
+};
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+use serde_json::{json, Value};
+
+/// Capacity given to Configurations that don't set one, matches the Configuration's own default
+const DEFAULT_CAPACITY: usize = 1;
+/// Default port of the Instance Services given to Configurations that don't set an instanceServiceSpec
+const DEFAULT_INSTANCE_SERVICE_PORT: &str = "6052";
+
+/// Defaults applied to the Configurations that omit some fields
+#[derive(Clone, Copy, Debug)]
+struct Defaults {
+    instance_service_port: u16,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults {
+            instance_service_port: DEFAULT_INSTANCE_SERVICE_PORT.parse().unwrap(),
+        }
+    }
+}
+
+fn get_builder(key: &str, crt: &str) -> SslAcceptorBuilder {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key_file(key, SslFiletype::PEM).unwrap();
+    builder.set_certificate_chain_file(crt).unwrap();
+    builder
+}
+
+fn is_unset(spec: &serde_json::Map<String, Value>, field: &str) -> bool {
+    spec.get(field).map_or(true, Value::is_null)
+}
+
+/// Builds the JSONPatch operations adding the defaults of the fields a raw Configuration omits.
+/// An instanceServiceSpec is only defaulted along with a brokerSpec, as Instance Services expose
+/// the brokers.
+fn get_default_patch(raw: &Value, defaults: Defaults) -> Vec<Value> {
+    let Some(spec) = raw["spec"].as_object() else {
+        return vec![];
+    };
+    let mut patch = Vec::new();
+    if is_unset(spec, "capacity") {
+        patch.push(json!({
+            "op": "add",
+            "path": "/spec/capacity",
+            "value": DEFAULT_CAPACITY,
+        }));
+    }
+    if !is_unset(spec, "brokerSpec") && is_unset(spec, "instanceServiceSpec") {
+        patch.push(json!({
+            "op": "add",
+            "path": "/spec/instanceServiceSpec",
+            "value": {
+                "type": "ClusterIP",
+                "ports": [{
+                    "name": "grpc",
+                    "port": defaults.instance_service_port,
+                    "protocol": "TCP",
+                    "targetPort": defaults.instance_service_port,
+                }],
+            },
+        }));
+    }
+    patch
+}
+
+/// Allows the Configuration, patching in the defaults of the fields it omits. Validating the
+/// Configuration is left to the validating Webhook, which runs after this one.
+fn default_configuration(rqst: &AdmissionRequest, defaults: Defaults) -> AdmissionResponse {
+    println!("Defaulting Configuration");
+    let mut resp = AdmissionResponse::new(true, rqst.uid.to_owned());
+    let patch = match &rqst.object {
+        Some(raw) => get_default_patch(raw, defaults),
+        None => vec![],
+    };
+    if !patch.is_empty() {
+        println!("default_configuration - patch: {:?}", patch);
+        resp.patch = Some(base64::encode(Value::Array(patch).to_string()));
+        resp.patch_type = Some("JSONPatch".to_owned());
+    }
+    resp
+}
+
+#[post("/mutate")]
+async fn mutate(
+    rqst: web::Json<AdmissionReview>,
+    defaults: Option<web::Data<Defaults>>,
+) -> impl Responder {
+    println!("Handler invoked");
+    match &rqst.request {
+        Some(rqst) => {
+            println!("Handler received: AdmissionRequest");
+            let defaults = defaults.map(|d| *d.get_ref()).unwrap_or_default();
+            let resp: AdmissionReview = AdmissionReview {
+                api_version: Some("admission.k8s.io/v1".to_owned()),
+                kind: Some("AdmissionReview".to_owned()),
+                request: None,
+                response: Some(default_configuration(rqst, defaults)),
+            };
+            let body = serde_json::to_string(&resp).expect("Valid AdmissionReview");
+            HttpResponse::Ok().body(body)
+        }
+        None => {
+            println!("Handler received: Nothing");
+            HttpResponse::BadRequest().body("")
+        }
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let matches = clap::Command::new("Akri Mutating Webhook")
+        .arg(
+            Arg::new("crt_file")
+                .long("tls-crt-file")
+                .required(true)
+                .help("TLS certificate file"),
+        )
+        .arg(
+            Arg::new("key_file")
+                .long("tls-key-file")
+                .required(true)
+                .help("TLS private key file"),
+        )
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .value_parser(clap::value_parser!(u16))
+                .default_value("8444")
+                .required(true)
+                .help("port"),
+        )
+        .arg(
+            Arg::new("instance_service_port")
+                .long("instance-service-port")
+                .value_parser(clap::value_parser!(u16))
+                .default_value(DEFAULT_INSTANCE_SERVICE_PORT)
+                .help("Port of the Instance Services given to Configurations without instanceServiceSpec"),
+        )
+        .get_matches();
+
+    let crt_file = matches
+        .get_one::<String>("crt_file")
+        .map(|v| v.as_str())
+        .expect("TLS certificate file");
+    let key_file = matches
+        .get_one::<String>("key_file")
+        .map(|v| v.as_str())
+        .expect("TLS private key file");
+
+    let port = matches
+        .get_one::<u16>("port")
+        .expect("valid port [0-65535]");
+
+    let defaults = Defaults {
+        instance_service_port: *matches
+            .get_one::<u16>("instance_service_port")
+            .expect("valid port [0-65535]"),
+    };
+
+    let endpoint = format!("0.0.0.0:{}", port);
+    println!("Started Mutating Webhook server: {}", endpoint);
+
+    let builder = get_builder(key_file, crt_file);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(defaults))
+            .service(mutate)
+    })
+    .bind_openssl(endpoint, builder)?
+    .run()
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMISSION_REVIEW: &str = r#"
+    {
+        "kind": "AdmissionReview",
+        "apiVersion": "admission.k8s.io/v1",
+        "request": {
+            "uid": "00000000-0000-0000-0000-000000000000",
+            "kind": {
+                "group": "akri.sh",
+                "version": "v0",
+                "kind": "Configuration"
+            },
+            "resource": {
+                "group": "akri.sh",
+                "version": "v0",
+                "resource": "configurations"
+            },
+            "name": "name",
+            "namespace": "default",
+            "operation": "CREATE",
+            "userInfo": {
+                "username": "admin",
+                "uid": "admin",
+                "groups": []
+            },
+            "object": {
+                "apiVersion": "akri.sh/v0",
+                "kind": "Configuration",
+                "metadata": {
+                    "name": "name",
+                    "namespace": "default"
+                },
+                "spec": {
+                    "discoveryHandler": {
+                        "name": "debugEcho",
+                        "discoveryDetails": "descriptions:\n- \"foo0\"\n- \"foo1\"\n"
+                    }
+                    INSERT_SPEC_HERE
+                }
+            },
+            "oldObject": null,
+            "dryRun": false
+        }
+    }"#;
+
+    const BROKER_SPEC: &str = r#""brokerSpec": {
+        "brokerPodSpec": {
+            "containers": [{"image": "nginx:latest", "name": "broker"}]
+        }
+    }"#;
+
+    fn get_admission_request(spec: &str) -> AdmissionRequest {
+        let spec = if spec.is_empty() {
+            String::new()
+        } else {
+            format!(",{}", spec)
+        };
+        let review: AdmissionReview =
+            serde_json::from_str(&ADMISSION_REVIEW.replace("INSERT_SPEC_HERE", &spec))
+                .expect("v1.AdmissionReview JSON");
+        review.request.expect("v1.AdmissionRequest JSON")
+    }
+
+    /// Decodes the patch of a response, checking that it is a base64 encoded JSONPatch
+    fn get_patch(resp: &AdmissionResponse) -> Vec<Value> {
+        assert!(resp.allowed);
+        assert_eq!(resp.patch_type.as_deref(), Some("JSONPatch"));
+        let patch = base64::decode(resp.patch.as_ref().unwrap()).expect("base64 patch");
+        let patch: Vec<Value> = serde_json::from_slice(&patch).expect("JSON patch");
+        for operation in &patch {
+            assert_eq!(operation["op"], "add");
+            assert!(operation["path"].as_str().unwrap().starts_with("/spec/"));
+        }
+        patch
+    }
+
+    #[test]
+    fn test_default_configuration_capacity() {
+        let resp = default_configuration(&get_admission_request(""), Defaults::default());
+        assert_eq!(
+            get_patch(&resp),
+            vec![json!({"op": "add", "path": "/spec/capacity", "value": 1})]
+        );
+    }
+
+    #[test]
+    fn test_default_configuration_instance_service_spec() {
+        let resp = default_configuration(
+            &get_admission_request(BROKER_SPEC),
+            Defaults {
+                instance_service_port: 8083,
+            },
+        );
+        assert_eq!(
+            get_patch(&resp),
+            vec![
+                json!({"op": "add", "path": "/spec/capacity", "value": 1}),
+                json!({
+                    "op": "add",
+                    "path": "/spec/instanceServiceSpec",
+                    "value": {
+                        "type": "ClusterIP",
+                        "ports": [{"name": "grpc", "port": 8083, "protocol": "TCP", "targetPort": 8083}],
+                    },
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_configuration_null_instance_service_spec() {
+        let resp = default_configuration(
+            &get_admission_request(&format!(
+                r#"{}, "capacity": 3, "instanceServiceSpec": null"#,
+                BROKER_SPEC
+            )),
+            Defaults::default(),
+        );
+        let patch = get_patch(&resp);
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch[0]["path"], "/spec/instanceServiceSpec");
+        assert_eq!(patch[0]["value"]["ports"][0]["port"], 6052);
+    }
+
+    #[test]
+    fn test_default_configuration_nothing_to_default() {
+        let resp = default_configuration(
+            &get_admission_request(&format!(
+                r#"{}, "capacity": 3, "instanceServiceSpec": {{"ports": [{{"port": 80}}]}}"#,
+                BROKER_SPEC
+            )),
+            Defaults::default(),
+        );
+        assert!(resp.allowed);
+        assert!(resp.patch.is_none());
+        assert!(resp.patch_type.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_mutate() {
+        let app = actix_web::test::init_service(App::new().service(mutate)).await;
+        let review: AdmissionReview =
+            serde_json::from_str(&ADMISSION_REVIEW.replace("INSERT_SPEC_HERE", ""))
+                .expect("v1.AdmissionReview JSON");
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/mutate")
+            .set_json(&review)
+            .to_request();
+        let resp: AdmissionReview = actix_web::test::call_and_read_body_json(&app, rqst).await;
+        let resp = resp.response.expect("v1.AdmissionResponse");
+        assert_eq!(resp.uid, "00000000-0000-0000-0000-000000000000");
+        assert_eq!(get_patch(&resp).len(), 1);
+    }
+}
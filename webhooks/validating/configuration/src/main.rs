@@ -1,13 +1,58 @@
-use actix_web::{post, web, App, HttpResponse, HttpServer, Responder};
-use akri_shared::akri::configuration::Configuration;
+use actix_web::{
+    error::{InternalError, JsonPayloadError},
+    post, web, App, HttpResponse, HttpServer, Responder,
+};
+use akri_debug_echo::discovery_handler::DebugEchoDiscoveryDetails;
+use akri_discovery_utils::discovery::discovery_handler::validate_discovery_details;
+use akri_discovery_utils::filtering::FilterList;
+use akri_onvif::discovery_details::OnvifDiscoveryDetails;
+use akri_opcua::discovery_details::OpcuaDiscoveryDetails;
+use akri_shared::{
+    akri::configuration::{BrokerSpec, Configuration, DiscoveryHandlerInfo},
+    k8s::api::IntoApi,
+};
+use akri_udev::discovery_details::UdevDiscoveryDetails;
 use clap::Arg;
+use k8s_openapi::api::core::v1::Secret;
 use k8s_openapi::apimachinery::pkg::runtime::RawExtension;
+use kube::ResourceExt;
 use openapi::models::{
     V1AdmissionRequest as AdmissionRequest, V1AdmissionResponse as AdmissionResponse,
     V1AdmissionReview as AdmissionReview, V1Status as Status,
 };
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Default maximum size (in bytes) of an AdmissionReview body, matches actix's default JSON payload limit
+const DEFAULT_MAX_PAYLOAD_SIZE: &str = "2097152";
+/// Default time (in seconds) a client has to send a complete request, matches actix's default
+const DEFAULT_REQUEST_TIMEOUT_SECS: &str = "5";
+
+/// Creates the JSON extractor configuration, rejecting AdmissionReviews bigger than `limit` bytes
+/// with a clear error message
+fn get_json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            let response = match &err {
+                JsonPayloadError::OverflowKnownLength { length, limit } => {
+                    HttpResponse::PayloadTooLarge().body(format!(
+                        "AdmissionReview size ({} bytes) exceeds the configured limit ({} bytes)",
+                        length, limit
+                    ))
+                }
+                JsonPayloadError::Overflow { limit } => {
+                    HttpResponse::PayloadTooLarge().body(format!(
+                        "AdmissionReview size exceeds the configured limit ({} bytes)",
+                        limit
+                    ))
+                }
+                e => HttpResponse::BadRequest().body(e.to_string()),
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
 
 fn get_builder(key: &str, crt: &str) -> SslAcceptorBuilder {
     let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
@@ -108,10 +153,531 @@ fn filter_configuration(mut v: Value) -> Value {
     v
 }
 
-fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
+/// Validates the filter lists (maps with `items` and `action`) found in a Configuration's discoveryDetails.
+/// A field can only be filtered by a single filter list with either an `Include` or `Exclude` action,
+/// otherwise the set of discovered devices would be ambiguous.
+fn validate_filter_lists(
+    discovery_details: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if discovery_details.trim().is_empty() {
+        return Ok(());
+    }
+    // Specifying the same field more than once is the only way to have both an Include and an Exclude list for it
+    if let Ok(DuplicateKeys(duplicates)) = serde_yaml::from_str(discovery_details) {
+        if !duplicates.is_empty() {
+            return Err(None.ok_or(format!(
+                "conflicting filter lists in discoveryDetails: fields ({:?}) are specified more than once",
+                duplicates
+            ))?);
+        }
+    }
+    let details: serde_yaml::Value = match serde_yaml::from_str(discovery_details) {
+        Ok(details) => details,
+        // Errors are reported by validate_discovery_handler_details for the Discovery Handlers shipped
+        // with Akri, and left for external Discovery Handlers to report
+        Err(_) => return Ok(()),
+    };
+    check_filter_lists(&details, "discoveryDetails")
+}
+
+/// Keys mappings of a YAML document list more than once, that `serde_yaml::Value` cannot hold
+struct DuplicateKeys(Vec<String>);
+
+impl<'de> serde::Deserialize<'de> for DuplicateKeys {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DuplicateKeysVisitor)
+    }
+}
+
+struct DuplicateKeysVisitor;
+
+impl<'de> serde::de::Visitor<'de> for DuplicateKeysVisitor {
+    type Value = DuplicateKeys;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a YAML value")
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut keys = Vec::new();
+        let mut duplicates = Vec::new();
+        while let Some(key) = map.next_key::<serde_yaml::Value>()? {
+            let DuplicateKeys(nested) = map.next_value()?;
+            duplicates.extend(nested);
+            let name = serde_yaml::to_string(&key)
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if keys.contains(&key) {
+                duplicates.push(name);
+            } else {
+                keys.push(key);
+            }
+        }
+        Ok(DuplicateKeys(duplicates))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut duplicates = Vec::new();
+        while let Some(DuplicateKeys(nested)) = seq.next_element()? {
+            duplicates.extend(nested);
+        }
+        Ok(DuplicateKeys(duplicates))
+    }
+
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        // Tagged values, the tag is the variant
+        let (_, variant) = data.variant::<serde_yaml::Value>()?;
+        serde::de::VariantAccess::newtype_variant(variant)
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(DuplicateKeys(Vec::new()))
+    }
+}
+
+fn check_filter_lists(
+    v: &serde_yaml::Value,
+    field: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match v {
+        serde_yaml::Value::Mapping(m) => {
+            if m.contains_key("items") && m.contains_key("action") {
+                let no_items = m
+                    .get("items")
+                    .and_then(|items| items.as_sequence())
+                    .map_or(true, |items| items.is_empty());
+                match m.get("action").and_then(|a| a.as_str()) {
+                    // Whether an empty Include list matches every item or none differs between
+                    // Discovery Handlers, so it is not accepted
+                    Some("Include") if no_items => {
+                        return Err(None.ok_or(format!(
+                            "empty Include filter list for field ({:?}), remove it or use an Exclude filter list instead",
+                            field
+                        ))?);
+                    }
+                    Some("Include") | Some("Exclude") => {}
+                    action => {
+                        return Err(None.ok_or(format!(
+                            "invalid filter action ({:?}) for field ({:?}), expected Include or Exclude",
+                            action, field
+                        ))?);
+                    }
+                }
+            }
+            if m.contains_key("items") && m.contains_key("matchType") {
+                check_filter_list_match_type(m, field)?;
+            }
+            for (key, value) in m {
+                check_filter_lists(value, key.as_str().unwrap_or(field))?;
+            }
+            Ok(())
+        }
+        serde_yaml::Value::Sequence(s) => {
+            for value in s {
+                check_filter_lists(value, field)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validates the match type of a filter list, and that its items are valid regular expressions
+/// when matched as such
+fn check_filter_list_match_type(
+    filter_list: &serde_yaml::Mapping,
+    field: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // Parsed as Discovery Handlers parse it, so that both agree on the valid match types and patterns
+    match serde_yaml::from_value::<FilterList>(serde_yaml::Value::Mapping(filter_list.clone())) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(None.ok_or(format!(
+            "invalid filter list for field ({:?}): {}",
+            field, e
+        ))?),
+    }
+}
+
+/// Validates that the discoveryDetails of each Discovery Handler of a Configuration can be parsed by the
+/// Discovery Handler it names, so that they don't only fail once the Agent sends them. Only the Discovery
+/// Handlers shipped with Akri are known, the discoveryDetails of other (external) Discovery Handlers are
+/// accepted as is.
+fn validate_discovery_handler_details(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if config.spec.discovery_handlers().next().is_none() {
+        return Err(None.ok_or("discoveryHandler must not be an empty list")?);
+    }
+    config
+        .spec
+        .discovery_handlers()
+        .try_for_each(validate_handler_details)
+}
+
+fn validate_handler_details(
+    discovery_handler: &DiscoveryHandlerInfo,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let name = discovery_handler.name.as_str();
+    let discovery_details = &discovery_handler.discovery_details;
+    let result = match name {
+        akri_debug_echo::DISCOVERY_HANDLER_NAME => {
+            validate_discovery_details(discovery_details, DebugEchoDiscoveryDetails::validate)
+        }
+        akri_onvif::DISCOVERY_HANDLER_NAME => {
+            validate_discovery_details::<OnvifDiscoveryDetails>(discovery_details, |_| Ok(()))
+        }
+        akri_opcua::DISCOVERY_HANDLER_NAME => {
+            validate_discovery_details(discovery_details, OpcuaDiscoveryDetails::validate)
+        }
+        akri_udev::DISCOVERY_HANDLER_NAME => {
+            validate_discovery_details(discovery_details, UdevDiscoveryDetails::validate)
+        }
+        _ => return Ok(()),
+    };
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(None.ok_or(format!(
+            "invalid discoveryDetails for the {} Discovery Handler: {}",
+            name, e
+        ))?),
+    }
+}
+
+/// Maximum length of a Kubernetes DNS subdomain name, such as a Secret name
+const MAX_DNS_SUBDOMAIN_NAME_LENGTH: usize = 253;
+/// Maximum length of the name part of a Kubernetes qualified name, such as an extended resource name
+const MAX_QUALIFIED_NAME_LENGTH: usize = 63;
+
+/// Returns the names of the imagePullSecrets referenced by a Configuration's broker spec, followed
+/// by the ones of its brokerImagePullSecrets
+fn get_image_pull_secret_names(config: &Configuration) -> Vec<String> {
+    let pod_spec = match &config.spec.broker_spec {
+        Some(BrokerSpec::BrokerPodSpec(pod_spec)) => Some(pod_spec.as_ref()),
+        Some(BrokerSpec::BrokerJobSpec(job_spec)) => job_spec.template.spec.as_ref(),
+        Some(BrokerSpec::BrokerDeploymentSpec(deployment_spec)) => {
+            deployment_spec.template.spec.as_ref()
+        }
+        None => None,
+    };
+    // Secrets referenced by both are only checked once
+    let mut seen = std::collections::HashSet::new();
+    pod_spec
+        .and_then(|spec| spec.image_pull_secrets.as_ref())
+        .into_iter()
+        .chain(config.spec.broker_image_pull_secrets.as_ref())
+        .flatten()
+        .filter_map(|s| s.name.clone())
+        .filter(|name| seen.insert(name.clone()))
+        .collect()
+}
+
+/// Checks that a name is a valid DNS subdomain name (RFC 1123), as required for Secret names
+fn is_dns_subdomain_name(name: &str) -> bool {
+    let is_alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    !name.is_empty()
+        && name.len() <= MAX_DNS_SUBDOMAIN_NAME_LENGTH
+        && name.starts_with(is_alphanumeric)
+        && name.ends_with(is_alphanumeric)
+        && name
+            .chars()
+            .all(|c| is_alphanumeric(c) || c == '-' || c == '.')
+}
+
+/// Checks that a name is a valid name part of a qualified name, as required for the name
+/// of an extended resource (`akri.sh/<name>`)
+fn is_qualified_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_QUALIFIED_NAME_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Validates the resource name override of a Configuration, it is advertised by the Agent as
+/// the `akri.sh/<resourceName>` extended resource.
+fn validate_resource_name(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match &config.spec.resource_name {
+        Some(name) if !is_qualified_name(name) => Err(None.ok_or(format!(
+            "invalid resourceName ({:?}), expected at most {} alphanumeric characters, '-', '_' or '.', starting and ending with an alphanumeric character",
+            name, MAX_QUALIFIED_NAME_LENGTH
+        ))?),
+        _ => Ok(()),
+    }
+}
+
+/// Gets the name of the `akri.sh/<name>` extended resource advertised for a Configuration
+fn get_resource_name(config: &Configuration) -> String {
+    config
+        .spec
+        .resource_name
+        .clone()
+        .unwrap_or_else(|| config.name_any())
+}
+
+/// Validates the resource name aliases of a Configuration, each is advertised as another
+/// `akri.sh/<alias>` extended resource, so it can neither be the resource name itself nor repeated.
+fn validate_resource_name_aliases(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let resource_name = get_resource_name(config);
+    let mut names = std::collections::HashSet::from([resource_name.as_str()]);
+    for alias in config.spec.resource_name_aliases.iter().flatten() {
+        if !is_qualified_name(alias) {
+            return Err(None.ok_or(format!(
+                "invalid resourceNameAliases entry ({:?}), expected at most {} alphanumeric characters, '-', '_' or '.', starting and ending with an alphanumeric character",
+                alias, MAX_QUALIFIED_NAME_LENGTH
+            ))?);
+        }
+        if !names.insert(alias) {
+            return Err(None.ok_or(format!(
+                "resourceNameAliases entry {:?} is already advertised for the Configuration",
+                alias
+            ))?);
+        }
+    }
+    Ok(())
+}
+
+/// Validates that the maximum number of Instances of a Configuration, when set, allows at least one Instance
+fn validate_max_instances(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match config.spec.max_instances {
+        Some(0) => Err(None.ok_or("invalid maxInstances (0), expected a positive number")?),
+        _ => Ok(()),
+    }
+}
+
+/// Default upper bound of the capacity of a Configuration, each unit of capacity is advertised to the
+/// kubelet as a virtual device of every Instance
+const DEFAULT_MAX_CAPACITY: usize = 1024;
+
+/// Upper bound of the capacity of a Configuration, set with `--max-capacity`
+#[derive(Clone, Copy, Debug)]
+struct MaxCapacity(usize);
+
+impl Default for MaxCapacity {
+    fn default() -> Self {
+        MaxCapacity(DEFAULT_MAX_CAPACITY)
+    }
+}
+
+/// Validates that the capacity of a Configuration allows at least one broker per device while
+/// staying within a sane bound
+fn validate_capacity(
+    config: &Configuration,
+    max_capacity: MaxCapacity,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match config.spec.capacity {
+        0 => Err(None.ok_or("invalid capacity (0), expected a positive number")?),
+        capacity if capacity > max_capacity.0 => Err(None.ok_or(format!(
+            "invalid capacity ({}), expected at most {}",
+            capacity, max_capacity.0
+        ))?),
+        _ => Ok(()),
+    }
+}
+
+/// Validates that the capacity of a raw Configuration is not negative, as negative capacities can't be
+/// parsed as a Configuration to be validated further
+fn validate_capacity_sign(
+    raw: &Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match raw["spec"]["capacity"].as_i64() {
+        Some(capacity) if capacity < 0 => Err(None.ok_or(format!(
+            "invalid capacity ({}), expected a positive number",
+            capacity
+        ))?),
+        _ => Ok(()),
+    }
+}
+
+/// Validates that the discovery poll interval of a raw Configuration, when set, is a positive number of
+/// seconds, as negative intervals can't be parsed as a Configuration to be validated further
+fn validate_discovery_poll_interval(
+    raw: &Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match &raw["spec"]["discoveryPollIntervalSecs"] {
+        Value::Null => Ok(()),
+        interval if interval.as_u64().map_or(false, |secs| secs > 0) => Ok(()),
+        interval => Err(None.ok_or(format!(
+            "invalid discoveryPollIntervalSecs ({}), expected a positive number of seconds",
+            interval
+        ))?),
+    }
+}
+
+/// Validates that the broker scheduler name of a Configuration, when set, is not empty
+fn validate_broker_scheduler_name(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match config.spec.broker_scheduler_name.as_deref() {
+        Some(name) if name.trim().is_empty() => {
+            Err(None.ok_or("invalid brokerSchedulerName, expected a non-empty scheduler name")?)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validates that the weights of the node affinity preferences of a Configuration are in the range
+/// accepted by Kubernetes, 1-100
+fn validate_node_affinity_preferences(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match config
+        .spec
+        .node_affinity_preferences
+        .iter()
+        .flatten()
+        .find(|preference| !(1..=100).contains(&preference.weight))
+    {
+        Some(preference) => Err(None.ok_or(format!(
+            "invalid nodeAffinityPreferences weight ({}), expected a number between 1 and 100",
+            preference.weight
+        ))?),
+        None => Ok(()),
+    }
+}
+
+/// Validates the names of the imagePullSecrets referenced by a Configuration's broker spec,
+/// a malformed name would only surface later as brokers stuck in ImagePullBackOff.
+fn validate_image_pull_secret_names(
+    config: &Configuration,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match get_image_pull_secret_names(config)
+        .into_iter()
+        .find(|name| !is_dns_subdomain_name(name))
+    {
+        Some(name) => Err(None.ok_or(format!(
+            "invalid imagePullSecret name ({:?}), expected a lowercase RFC 1123 subdomain name",
+            name
+        ))?),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the imagePullSecrets referenced by a Configuration's broker spec exist in the given namespace
+async fn check_image_pull_secrets_exist(
+    config: &Configuration,
+    namespace: &str,
+    secrets: &dyn IntoApi<Secret>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let api = secrets.namespaced(namespace);
+    for name in get_image_pull_secret_names(config) {
+        if api.get(&name).await?.is_none() {
+            return Err(None.ok_or(format!(
+                "imagePullSecret ({:?}) not found in namespace ({:?})",
+                name, namespace
+            ))?);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the resource name aliases of a Configuration are neither the resource name nor an alias of
+/// another Configuration, and that its resource name is no other Configuration's alias. The kubelet keeps
+/// a single registration of each `akri.sh/<name>` resource, the last one would silently replace the other.
+async fn check_resource_name_clashes(
+    config: &Configuration,
+    namespace: &str,
+    configurations: &dyn IntoApi<Configuration>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let resource_name = get_resource_name(config);
+    let aliases = config
+        .spec
+        .resource_name_aliases
+        .as_deref()
+        .unwrap_or_default();
+    for other in configurations.all().list().await?.items {
+        let other_namespace = other.namespace().unwrap_or_default();
+        // An update of the Configuration lists the Configuration itself
+        if other.name_any() == config.name_any() && other_namespace == namespace {
+            continue;
+        }
+        let other_resource_name = get_resource_name(&other);
+        let other_aliases = other
+            .spec
+            .resource_name_aliases
+            .as_deref()
+            .unwrap_or_default();
+        if let Some(alias) = aliases
+            .iter()
+            .find(|alias| **alias == other_resource_name || other_aliases.contains(alias))
+        {
+            return Err(None.ok_or(format!(
+                "resourceNameAliases entry {:?} is already advertised for Configuration {}::{}",
+                alias,
+                other_namespace,
+                other.name_any()
+            ))?);
+        }
+        if other_aliases.contains(&resource_name) {
+            return Err(None.ok_or(format!(
+                "resource name {:?} is already advertised as an alias for Configuration {}::{}",
+                resource_name,
+                other_namespace,
+                other.name_any()
+            ))?);
+        }
+    }
+    Ok(())
+}
+
+fn denied_response(uid: &str, message: String) -> AdmissionResponse {
+    AdmissionResponse {
+        allowed: false,
+        audit_annotations: None,
+        patch: None,
+        patch_type: None,
+        status: Some(Status {
+            api_version: None,
+            code: None,
+            details: None,
+            kind: None,
+            message: Some(message),
+            metadata: None,
+            reason: None,
+            status: None,
+        }),
+        uid: uid.to_owned(),
+        warnings: None,
+    }
+}
+
+fn validate_configuration(rqst: &AdmissionRequest, max_capacity: MaxCapacity) -> AdmissionResponse {
     println!("Validating Configuration");
     match &rqst.object {
         Some(raw) => {
+            if let Err(e) =
+                validate_capacity_sign(raw).and_then(|_| validate_discovery_poll_interval(raw))
+            {
+                return denied_response(&rqst.uid, e.to_string());
+            }
             let x: RawExtension = serde_json::from_value(raw.clone())
                 .expect("Could not parse as Kubernetes RawExtension");
             let y = serde_json::to_string(&x).unwrap();
@@ -129,57 +695,100 @@ fn validate_configuration(rqst: &AdmissionRequest) -> AdmissionResponse {
                 val
             );
 
-            // Do they match?
-            match check(&val, &deserialized) {
+            // Do they match? Are the filter lists unambiguous? Can the Discovery Handler parse the
+            // discoveryDetails? Are the imagePullSecrets well formed?
+            match check(&val, &deserialized)
+                .and_then(|_| {
+                    config
+                        .spec
+                        .discovery_handlers()
+                        .try_for_each(|dh| validate_filter_lists(&dh.discovery_details))
+                })
+                .and_then(|_| validate_discovery_handler_details(&config))
+                .and_then(|_| validate_image_pull_secret_names(&config))
+                .and_then(|_| validate_resource_name(&config))
+                .and_then(|_| validate_resource_name_aliases(&config))
+                .and_then(|_| validate_max_instances(&config))
+                .and_then(|_| validate_capacity(&config, max_capacity))
+                .and_then(|_| validate_broker_scheduler_name(&config))
+                .and_then(|_| validate_node_affinity_preferences(&config))
+            {
                 Ok(_) => AdmissionResponse::new(true, rqst.uid.to_owned()),
-                Err(e) => AdmissionResponse {
-                    allowed: false,
-                    audit_annotations: None,
-                    patch: None,
-                    patch_type: None,
-                    status: Some(Status {
-                        api_version: None,
-                        code: None,
-                        details: None,
-                        kind: None,
-                        message: Some(e.to_string()),
-                        metadata: None,
-                        reason: None,
-                        status: None,
-                    }),
-                    uid: rqst.uid.to_owned(),
-                    warnings: None,
-                },
+                Err(e) => denied_response(&rqst.uid, e.to_string()),
             }
         }
-        None => AdmissionResponse {
-            allowed: false,
-            audit_annotations: None,
-            patch: None,
-            patch_type: None,
-            status: Some(Status {
-                api_version: None,
-                code: None,
-                details: None,
-                kind: None,
-                message: Some("AdmissionRequest object contains no data".to_owned()),
-                metadata: None,
-                reason: None,
-                status: None,
-            }),
-            uid: rqst.uid.to_owned(),
-            warnings: None,
-        },
+        None => denied_response(
+            &rqst.uid,
+            "AdmissionRequest object contains no data".to_owned(),
+        ),
+    }
+}
+
+/// Denies the request if any imagePullSecret referenced by the (otherwise valid) Configuration is missing,
+/// or if its resource names clash with the ones of another Configuration, for the checks that are enabled
+async fn validate_cluster_state(
+    rqst: &AdmissionRequest,
+    secrets: Option<&dyn IntoApi<Secret>>,
+    configurations: Option<&dyn IntoApi<Configuration>>,
+) -> AdmissionResponse {
+    let config: Option<Configuration> = rqst
+        .object
+        .as_ref()
+        .and_then(|raw| serde_json::from_value(raw.clone()).ok());
+    // The object may not carry its namespace yet, the one of the request is the one it is created in
+    let namespace = rqst
+        .namespace
+        .clone()
+        .or_else(|| config.as_ref().and_then(|config| config.namespace()));
+    match (config, namespace) {
+        (Some(config), Some(namespace)) => {
+            let mut result = Ok(());
+            if let Some(secrets) = secrets {
+                result = check_image_pull_secrets_exist(&config, &namespace, secrets).await;
+            }
+            if let (Ok(()), Some(configurations)) = (&result, configurations) {
+                result = check_resource_name_clashes(&config, &namespace, configurations).await;
+            }
+            match result {
+                Ok(_) => AdmissionResponse::new(true, rqst.uid.to_owned()),
+                Err(e) => denied_response(&rqst.uid, e.to_string()),
+            }
+        }
+        (Some(_), None) => {
+            denied_response(&rqst.uid, "AdmissionRequest has no namespace".to_owned())
+        }
+        (None, _) => denied_response(
+            &rqst.uid,
+            "AdmissionRequest object is not a Configuration".to_owned(),
+        ),
     }
 }
 
 #[post("/validate")]
-async fn validate(rqst: web::Json<AdmissionReview>) -> impl Responder {
+async fn validate(
+    rqst: web::Json<AdmissionReview>,
+    secrets: Option<web::Data<dyn IntoApi<Secret>>>,
+    configurations: Option<web::Data<dyn IntoApi<Configuration>>>,
+    max_capacity: Option<web::Data<MaxCapacity>>,
+) -> impl Responder {
     println!("Handler invoked");
     match &rqst.request {
         Some(rqst) => {
             println!("Handler received: AdmissionRequest");
-            let resp = validate_configuration(rqst);
+            let max_capacity = max_capacity.map(|m| *m.get_ref()).unwrap_or_default();
+            let mut resp = validate_configuration(rqst, max_capacity);
+            // Existence of imagePullSecrets and resource name clashes can only be checked when running
+            // with cluster access
+            if resp.allowed && (secrets.is_some() || configurations.is_some()) {
+                resp = validate_cluster_state(
+                    rqst,
+                    secrets.as_ref().map(|secrets| secrets.get_ref()),
+                    configurations
+                        .as_ref()
+                        .map(|configurations| configurations.get_ref()),
+                )
+                .await;
+            }
             let resp: AdmissionReview = AdmissionReview {
                 api_version: Some("admission.k8s.io/v1".to_owned()),
                 kind: Some("AdmissionReview".to_owned()),
@@ -219,6 +828,38 @@ async fn main() -> std::io::Result<()> {
                 .required(true)
                 .help("port"),
         )
+        .arg(
+            Arg::new("max_payload_size")
+                .long("max-payload-size")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(DEFAULT_MAX_PAYLOAD_SIZE)
+                .help("Maximum size (in bytes) of an AdmissionReview request body"),
+        )
+        .arg(
+            Arg::new("request_timeout_secs")
+                .long("request-timeout-secs")
+                .value_parser(clap::value_parser!(u64))
+                .default_value(DEFAULT_REQUEST_TIMEOUT_SECS)
+                .help("Time (in seconds) a client has to send a complete request"),
+        )
+        .arg(
+            Arg::new("max_capacity")
+                .long("max-capacity")
+                .value_parser(clap::value_parser!(usize))
+                .help("Maximum capacity of a Configuration, defaults to 1024"),
+        )
+        .arg(
+            Arg::new("check_image_pull_secrets")
+                .long("check-image-pull-secrets")
+                .action(clap::ArgAction::SetTrue)
+                .help("Deny Configurations referencing imagePullSecrets missing from their namespace (requires cluster access)"),
+        )
+        .arg(
+            Arg::new("check_resource_name_clashes")
+                .long("check-resource-name-clashes")
+                .action(clap::ArgAction::SetTrue)
+                .help("Deny Configurations whose resource name aliases clash with the ones of other Configurations (requires cluster access)"),
+        )
         .get_matches();
 
     let crt_file = matches
@@ -234,19 +875,66 @@ async fn main() -> std::io::Result<()> {
         .get_one::<u16>("port")
         .expect("valid port [0-65535]");
 
+    let max_payload_size = *matches
+        .get_one::<usize>("max_payload_size")
+        .expect("valid payload size");
+    let request_timeout_secs = *matches
+        .get_one::<u64>("request_timeout_secs")
+        .expect("valid request timeout");
+    let max_capacity = matches
+        .get_one::<usize>("max_capacity")
+        .map(|max_capacity| MaxCapacity(*max_capacity))
+        .unwrap_or_default();
+
+    let check_image_pull_secrets = matches.get_flag("check_image_pull_secrets");
+    let check_resource_name_clashes = matches.get_flag("check_resource_name_clashes");
+    let client = if check_image_pull_secrets || check_resource_name_clashes {
+        Some(
+            kube::Client::try_default()
+                .await
+                .expect("Kubernetes client for checking the cluster state"),
+        )
+    } else {
+        None
+    };
+    let secrets: Option<web::Data<dyn IntoApi<Secret>>> = client
+        .clone()
+        .filter(|_| check_image_pull_secrets)
+        .map(|client| web::Data::from(Arc::new(client) as Arc<dyn IntoApi<Secret>>));
+    let configurations: Option<web::Data<dyn IntoApi<Configuration>>> = client
+        .filter(|_| check_resource_name_clashes)
+        .map(|client| web::Data::from(Arc::new(client) as Arc<dyn IntoApi<Configuration>>));
+
     let endpoint = format!("0.0.0.0:{}", port);
     println!("Started Webhook server: {}", endpoint);
 
     let builder = get_builder(key_file, crt_file);
-    HttpServer::new(|| App::new().service(validate))
-        .bind_openssl(endpoint, builder)?
-        .run()
-        .await
+    HttpServer::new(move || {
+        let app = App::new()
+            .app_data(get_json_config(max_payload_size))
+            .app_data(web::Data::new(max_capacity));
+        let app = match &secrets {
+            Some(secrets) => app.app_data(secrets.clone()),
+            None => app,
+        };
+        match &configurations {
+            Some(configurations) => app.app_data(configurations.clone()),
+            None => app,
+        }
+        .service(validate)
+    })
+    .client_request_timeout(std::time::Duration::from_secs(request_timeout_secs))
+    .bind_openssl(endpoint, builder)?
+    .run()
+    .await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use akri_shared::akri::configuration::ConfigurationSpec;
+    use akri_shared::k8s::api::{MockApi, MockIntoApi};
+    use kube::core::ObjectList;
     const BROKER_SPEC_INSERTION_KEYWORD: &str = "INSERT_BROKER_SPEC_HERE";
     const DISCOVERY_PROPERTIES_INSERTION_KEYWORD: &str = "INSERT_DISCOVERY_PROPERTIES_HERE";
     const ADMISSION_REVIEW: &str = r#"
@@ -541,6 +1229,41 @@ mod tests {
     // Misplaced `resources`
     //   Valid: .request.object.spec.brokerSpec.brokerJobSpec.template.spec.containers[*].resources
     // Invalid: .request.object.spec.brokerSpec.brokerJobSpec.template.spec.resources
+    const VALID_BROKER_DEPLOYMENT_SPEC: &str = r#"
+    "brokerDeploymentSpec": {
+        "replicas": 2,
+        "selector": {
+            "matchLabels": {
+                "app": "broker"
+            }
+        },
+        "template": {
+            "metadata": {
+                "labels": {
+                    "app": "broker"
+                }
+            },
+            "spec": {
+                "containers": [
+                    {
+                        "image": "image",
+                        "name": "name",
+                        "resources": {
+                            "limits": {
+                                "{{PLACEHOLDER}}": "1"
+                            }
+                        }
+                    }
+                ],
+                "imagePullSecrets": [
+                    {
+                        "name": "name"
+                    }
+                ]
+            }
+        }
+    }"#;
+
     const INVALID_BROKER_JOB_SPEC: &str = r#"
     "brokerJobSpec": {
         "template": {
@@ -570,6 +1293,22 @@ mod tests {
         }
     }"#;
 
+    // Valid akri.sh/v0/Configuration with a malformed imagePullSecret name
+    const BROKER_POD_SPEC_WITH_INVALID_IMAGE_PULL_SECRET: &str = r#"
+    "brokerPodSpec": {
+        "containers": [
+            {
+                "image": "image",
+                "name": "name"
+            }
+        ],
+        "imagePullSecrets": [
+            {
+                "name": "My_Registry_Secret"
+            }
+        ]
+    }"#;
+
     const METADATA: &str = r#"
     {
         "apiVersion": "akri.sh/v0",
@@ -617,6 +1356,61 @@ mod tests {
         )
     }
 
+    fn get_valid_admission_review_with_broker_deployment_spec() -> String {
+        ADMISSION_REVIEW.replace(BROKER_SPEC_INSERTION_KEYWORD, VALID_BROKER_DEPLOYMENT_SPEC)
+    }
+
+    fn get_invalid_admission_review_with_broker_deployment_and_other_spec(
+        other_spec: &str,
+    ) -> String {
+        let invalid_setting_both_broker_deployment_and_other =
+            format!("{},\n{}", VALID_BROKER_DEPLOYMENT_SPEC, other_spec);
+        ADMISSION_REVIEW.replace(
+            BROKER_SPEC_INSERTION_KEYWORD,
+            &invalid_setting_both_broker_deployment_and_other,
+        )
+    }
+
+    fn get_admission_review_with_discovery_details(discovery_details: &str) -> String {
+        get_admission_review_with_discovery_handler("onvif", discovery_details)
+    }
+
+    fn get_admission_review_with_discovery_handler(name: &str, discovery_details: &str) -> String {
+        get_valid_admission_review_with_broker_pod_spec().replace(
+            r#""name": "debugEcho",
+                        "discoveryDetails": "descriptions:\n- \"foo0\"\n- \"foo1\"\n""#,
+            &format!(
+                r#""name": {},
+                        "discoveryDetails": {}"#,
+                serde_json::to_string(name).unwrap(),
+                serde_json::to_string(discovery_details).unwrap()
+            ),
+        )
+    }
+
+    fn get_admission_review_with_discovery_handler_list(
+        name: &str,
+        discovery_details: &str,
+    ) -> String {
+        get_valid_admission_review_with_broker_pod_spec().replace(
+            r#""discoveryHandler": {
+                        "name": "debugEcho",
+                        "discoveryDetails": "descriptions:\n- \"foo0\"\n- \"foo1\"\n"
+                    },"#,
+            &format!(
+                r#""discoveryHandler": [{{
+                        "name": "debugEcho",
+                        "discoveryDetails": "descriptions:\n- \"foo0\"\n- \"foo1\"\n"
+                    }}, {{
+                        "name": {},
+                        "discoveryDetails": {}
+                    }}],"#,
+                serde_json::to_string(name).unwrap(),
+                serde_json::to_string(discovery_details).unwrap()
+            ),
+        )
+    }
+
     fn get_admission_review_with_discovery_properties(discovery_properties: &str) -> String {
         ADMISSION_REVIEW_FOR_DISCOVERY_PROPERTIES
             .replace(DISCOVERY_PROPERTIES_INSERTION_KEYWORD, discovery_properties)
@@ -805,7 +1599,7 @@ mod tests {
             serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
         assert!(resp.allowed);
     }
 
@@ -815,7 +1609,7 @@ mod tests {
             serde_json::from_str(&get_valid_admission_review_with_broker_job_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
         assert!(resp.allowed);
     }
 
@@ -825,7 +1619,7 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
         assert!(!resp.allowed);
     }
 
@@ -835,7 +1629,7 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_job_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
         assert!(!resp.allowed);
     }
 
@@ -846,7 +1640,43 @@ mod tests {
             serde_json::from_str(&get_invalid_admission_review_with_broker_job_and_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
-        validate_configuration(&rqst);
+        validate_configuration(&rqst, MaxCapacity::default());
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_deploymentspec() {
+        let valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_deployment_spec())
+                .expect("v1.AdmissionReview JSON");
+        let rqst = valid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(resp.allowed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Could not parse as Akri Configuration")]
+    fn test_validate_configuration_invalid_deploymentspec_and_podspec() {
+        let invalid: AdmissionReview = serde_json::from_str(
+            &get_invalid_admission_review_with_broker_deployment_and_other_spec(
+                VALID_BROKER_POD_SPEC,
+            ),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "Could not parse as Akri Configuration")]
+    fn test_validate_configuration_invalid_deploymentspec_and_jobspec() {
+        let invalid: AdmissionReview = serde_json::from_str(
+            &get_invalid_admission_review_with_broker_deployment_and_other_spec(
+                VALID_BROKER_JOB_SPEC,
+            ),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default());
     }
 
     #[test]
@@ -855,10 +1685,215 @@ mod tests {
             serde_json::from_str(&get_extended_admission_review_with_broker_pod_spec())
                 .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        let resp = validate_configuration(&rqst);
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
         assert!(resp.allowed);
     }
 
+    #[test]
+    fn test_validate_configuration_valid_filter_list() {
+        let discovery_details = "ipAddresses:\n  action: Exclude\n  items:\n  - 10.0.0.1\nmacAddresses:\n  action: Include\n  items:\n  - 00:11:22:33:44:55\n";
+        let valid: AdmissionReview = serde_json::from_str(
+            &get_admission_review_with_discovery_details(discovery_details),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = valid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(resp.allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_conflicting_filter_lists() {
+        let discovery_details = "ipAddresses:\n  action: Include\n  items:\n  - 10.0.0.1\nipAddresses:\n  action: Exclude\n  items:\n  - 10.0.0.2\n";
+        let invalid: AdmissionReview = serde_json::from_str(
+            &get_admission_review_with_discovery_details(discovery_details),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains(r#"conflicting filter lists in discoveryDetails: fields (["ipAddresses"])"#));
+    }
+
+    #[test]
+    fn test_validate_filter_lists_empty_include() {
+        assert!(validate_filter_lists("uuids:\n  action: Exclude\n  items: []\n").is_ok());
+        assert!(
+            validate_filter_lists("uuids:\n  action: Include\n  items: []\n")
+                .unwrap_err()
+                .to_string()
+                .contains(r#"empty Include filter list for field ("uuids")"#)
+        );
+    }
+
+    #[test]
+    fn test_validate_filter_lists_nested_duplicate() {
+        // Duplicates are found at any depth, the same field under different parents is not one
+        assert!(validate_filter_lists("a:\n  x: 1\nb:\n  x: 2\n").is_ok());
+        assert!(validate_filter_lists("a:\n  x: 1\n  x: 2\n")
+            .unwrap_err()
+            .to_string()
+            .contains(r#"fields (["x"])"#));
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_filter_action() {
+        let discovery_details = "ipAddresses:\n  action: Both\n  items:\n  - 10.0.0.1\n";
+        let invalid: AdmissionReview = serde_json::from_str(
+            &get_admission_review_with_discovery_details(discovery_details),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(!resp.allowed);
+    }
+
+    fn run_validate_configuration_discovery_details(discovery_details: &str) -> AdmissionResponse {
+        let review: AdmissionReview = serde_json::from_str(
+            &get_admission_review_with_discovery_details(discovery_details),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_regex_filter_list() {
+        let discovery_details =
+            "ipAddresses:\n  action: Include\n  matchType: Regex\n  items:\n  - 10\\.0\\..*\n";
+        assert!(run_validate_configuration_discovery_details(discovery_details).allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_regex_filter_list() {
+        let discovery_details =
+            "ipAddresses:\n  action: Include\n  matchType: Regex\n  items:\n  - 10.0.(\n";
+        let resp = run_validate_configuration_discovery_details(discovery_details);
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid regex"));
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_filter_match_type() {
+        let discovery_details =
+            "ipAddresses:\n  action: Include\n  matchType: Glob\n  items:\n  - 10.0.*\n";
+        assert!(!run_validate_configuration_discovery_details(discovery_details).allowed);
+    }
+
+    fn run_validate_configuration_discovery_handler(
+        name: &str,
+        discovery_details: &str,
+    ) -> AdmissionResponse {
+        let review: AdmissionReview = serde_json::from_str(
+            &get_admission_review_with_discovery_handler(name, discovery_details),
+        )
+        .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_udev_rules() {
+        let discovery_details = "udevRules:\n- KERNEL==\"video[0-9]*\"\n";
+        assert!(run_validate_configuration_discovery_handler("udev", discovery_details).allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_malformed_udev_rules() {
+        for discovery_details in [
+            // Unknown udev field
+            "udevRules:\n- KERNEL==\"video[0-9]*\", TYPO==\"blah\"\n",
+            // Not a list of rules
+            "udevRules:\n  kernel: video0\n",
+            // Missing rules
+            "groupRecursive: true\n",
+        ] {
+            let resp = run_validate_configuration_discovery_handler("udev", discovery_details);
+            assert!(!resp.allowed, "{:?} should be rejected", discovery_details);
+            assert!(resp
+                .status
+                .unwrap()
+                .message
+                .unwrap()
+                .contains("invalid discoveryDetails for the udev Discovery Handler"));
+        }
+    }
+
+    #[test]
+    fn test_validate_configuration_malformed_opcua_discovery_details() {
+        let discovery_details =
+            "opcuaDiscoveryMethod:\n  standard:\n    discoveryUrls: opc.tcp://localhost:4840/\n";
+        assert!(!run_validate_configuration_discovery_handler("opcua", discovery_details).allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_malformed_debug_echo_discovery_details() {
+        assert!(
+            !run_validate_configuration_discovery_handler("debugEcho", "descriptions: []\n")
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_validate_configuration_unknown_discovery_handler() {
+        // External Discovery Handlers validate their own discoveryDetails
+        assert!(run_validate_configuration_discovery_handler("custom", "not: [valid").allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_discovery_handler_list() {
+        let run = |name: &str, discovery_details: &str| {
+            let review: AdmissionReview = serde_json::from_str(
+                &get_admission_review_with_discovery_handler_list(name, discovery_details),
+            )
+            .expect("v1.AdmissionReview JSON");
+            let rqst = review.request.expect("v1.AdmissionRequest JSON");
+            validate_configuration(&rqst, MaxCapacity::default())
+        };
+        assert!(run("udev", "udevRules:\n- KERNEL==\"video[0-9]*\"\n").allowed);
+        assert!(run("custom", "not: [valid").allowed);
+
+        // The discoveryDetails of every Discovery Handler of the list are validated
+        let resp = run("udev", "groupRecursive: true\n");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid discoveryDetails for the udev Discovery Handler"));
+
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {
+                        "name": "debugEcho",
+                        "discoveryDetails": "descriptions:\n- \"foo0\"\n- \"foo1\"\n"
+                    },"#,
+                r#""discoveryHandler": [],"#,
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let resp = validate_configuration(
+            &review.request.expect("v1.AdmissionRequest JSON"),
+            MaxCapacity::default(),
+        );
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("discoveryHandler must not be an empty list"));
+    }
+
     #[test]
     fn test_validate_configuration_discovery_properties_empty() {
         let discovery_properties = "";
@@ -1084,7 +2119,541 @@ mod tests {
         )
         .expect("v1.AdmissionReview JSON");
         let rqst = valid.request.expect("v1.AdmissionRequest JSON");
-        validate_configuration(&rqst)
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_is_dns_subdomain_name() {
+        assert!(is_dns_subdomain_name("regcred"));
+        assert!(is_dns_subdomain_name("my-registry.example-1"));
+        assert!(!is_dns_subdomain_name(""));
+        assert!(!is_dns_subdomain_name("My_Registry_Secret"));
+        assert!(!is_dns_subdomain_name("-regcred"));
+        assert!(!is_dns_subdomain_name("regcred."));
+        assert!(!is_dns_subdomain_name(&"a".repeat(254)));
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_image_pull_secret_name() {
+        let invalid: AdmissionReview = serde_json::from_str(&ADMISSION_REVIEW.replace(
+            BROKER_SPEC_INSERTION_KEYWORD,
+            BROKER_POD_SPEC_WITH_INVALID_IMAGE_PULL_SECRET,
+        ))
+        .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("My_Registry_Secret"));
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_broker_image_pull_secret_name() {
+        let invalid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                r#""brokerImagePullSecrets": [{"name": "Config_Registry_Secret"}],
+                "discoveryHandler": {"#,
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = invalid.request.expect("v1.AdmissionRequest JSON");
+        let resp = validate_configuration(&rqst, MaxCapacity::default());
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("Config_Registry_Secret"));
+    }
+
+    #[test]
+    fn test_get_image_pull_secret_names() {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                r#""brokerImagePullSecrets": [{"name": "config-regcred"}, {"name": "name"}],
+                "discoveryHandler": {"#,
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let config: Configuration =
+            serde_json::from_value(review.request.unwrap().object.unwrap()).unwrap();
+        assert_eq!(
+            get_image_pull_secret_names(&config),
+            vec!["name".to_string(), "config-regcred".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_is_qualified_name() {
+        assert!(is_qualified_name("gpu-camera"));
+        assert!(is_qualified_name("Camera_1.hd"));
+        assert!(!is_qualified_name(""));
+        assert!(!is_qualified_name("akri.sh/camera"));
+        assert!(!is_qualified_name("_camera"));
+        assert!(!is_qualified_name("camera-"));
+        assert!(!is_qualified_name(&"a".repeat(64)));
+    }
+
+    fn run_validate_configuration_resource_name(resource_name: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""resourceName": {},
+                    "discoveryHandler": {{"#,
+                    serde_json::to_string(resource_name).unwrap()
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_resource_name() {
+        assert!(run_validate_configuration_resource_name("gpu-camera").allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_invalid_resource_name() {
+        let resp = run_validate_configuration_resource_name("akri.sh/gpu-camera");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid resourceName"));
+    }
+
+    fn run_validate_configuration_resource_name_aliases(aliases: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""resourceNameAliases": {},
+                    "discoveryHandler": {{"#,
+                    aliases
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_resource_name_aliases() {
+        assert!(
+            run_validate_configuration_resource_name_aliases(r#"["usb-camera", "camera"]"#).allowed
+        );
+
+        for (aliases, message) in [
+            (r#"["akri.sh/camera"]"#, "invalid resourceNameAliases entry"),
+            (r#"["camera", "camera"]"#, "is already advertised"),
+        ] {
+            let resp = run_validate_configuration_resource_name_aliases(aliases);
+            assert!(!resp.allowed);
+            assert!(resp.status.unwrap().message.unwrap().contains(message));
+        }
+    }
+
+    fn make_resource_name_test_configuration(
+        namespace: &str,
+        name: &str,
+        resource_name: Option<&str>,
+        aliases: &[&str],
+    ) -> Configuration {
+        let mut config = Configuration::new(
+            name,
+            ConfigurationSpec {
+                resource_name: resource_name.map(String::from),
+                resource_name_aliases: Some(aliases.iter().map(|a| a.to_string()).collect()),
+                ..Default::default()
+            },
+        );
+        config.metadata.namespace = Some(namespace.to_string());
+        config
+    }
+
+    fn get_configurations_mock(
+        configurations: Vec<Configuration>,
+    ) -> Arc<dyn IntoApi<Configuration>> {
+        let mut mock_configuration_api = MockApi::new();
+        mock_configuration_api
+            .expect_list()
+            .times(1)
+            .return_once(|| {
+                Ok(ObjectList {
+                    metadata: Default::default(),
+                    items: configurations,
+                })
+            });
+        let mut mock_kube_client = MockIntoApi::<Configuration>::new();
+        mock_kube_client
+            .expect_all()
+            .return_once(|| Box::new(mock_configuration_api));
+        Arc::new(mock_kube_client)
+    }
+
+    #[actix_web::test]
+    async fn test_check_resource_name_clashes() {
+        let config = make_resource_name_test_configuration("default", "name", None, &["camera"]);
+        // The Configuration itself (upon update) and Configurations advertising other resources
+        let configurations = get_configurations_mock(vec![
+            config.clone(),
+            make_resource_name_test_configuration("other", "name", None, &["usb-camera"]),
+        ]);
+        assert!(
+            check_resource_name_clashes(&config, "default", configurations.as_ref())
+                .await
+                .is_ok()
+        );
+
+        for (other, message) in [
+            (
+                make_resource_name_test_configuration("other", "camera", None, &[]),
+                r#"resourceNameAliases entry "camera" is already advertised for Configuration other::camera"#,
+            ),
+            (
+                make_resource_name_test_configuration("other", "other", Some("camera"), &[]),
+                r#"resourceNameAliases entry "camera" is already advertised for Configuration other::other"#,
+            ),
+            (
+                make_resource_name_test_configuration("other", "other", None, &["camera"]),
+                r#"resourceNameAliases entry "camera" is already advertised for Configuration other::other"#,
+            ),
+            (
+                make_resource_name_test_configuration("other", "other", None, &["name"]),
+                r#"resource name "name" is already advertised as an alias for Configuration other::other"#,
+            ),
+        ] {
+            let configurations = get_configurations_mock(vec![other]);
+            let err = check_resource_name_clashes(&config, "default", configurations.as_ref())
+                .await
+                .unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_validate_resource_name_clash() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::from(get_configurations_mock(vec![
+                    make_resource_name_test_configuration("other", "other", None, &["name"]),
+                ])))
+                .service(validate),
+        )
+        .await;
+        let valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
+                .expect("v1.AdmissionReview JSON");
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/validate")
+            .set_json(&valid)
+            .to_request();
+        let resp: AdmissionReview = actix_web::test::call_and_read_body_json(&app, rqst).await;
+        let resp = resp.response.expect("v1.AdmissionResponse JSON");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("is already advertised as an alias"));
+    }
+
+    fn run_validate_configuration_max_instances(max_instances: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""maxInstances": {},
+                    "discoveryHandler": {{"#,
+                    max_instances
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_max_instances() {
+        assert!(run_validate_configuration_max_instances("10").allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_zero_max_instances() {
+        let resp = run_validate_configuration_max_instances("0");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid maxInstances"));
+    }
+
+    fn run_validate_configuration_capacity(capacity: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""capacity": {},
+                    "discoveryHandler": {{"#,
+                    capacity
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_capacity() {
+        assert!(run_validate_configuration_capacity("5").allowed);
+        assert!(run_validate_configuration_capacity(&DEFAULT_MAX_CAPACITY.to_string()).allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_zero_capacity() {
+        let resp = run_validate_configuration_capacity("0");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid capacity (0)"));
+    }
+
+    #[test]
+    fn test_validate_configuration_negative_capacity() {
+        let resp = run_validate_configuration_capacity("-1");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid capacity (-1)"));
+    }
+
+    #[test]
+    fn test_validate_configuration_capacity_over_bound() {
+        let resp = run_validate_configuration_capacity(&(DEFAULT_MAX_CAPACITY + 1).to_string());
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("expected at most 1024"));
+    }
+
+    #[test]
+    fn test_validate_configuration_capacity_configured_bound() {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                r#""capacity": 20,
+                    "discoveryHandler": {"#,
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        assert!(validate_configuration(&rqst, MaxCapacity(20)).allowed);
+        let resp = validate_configuration(&rqst, MaxCapacity(10));
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid capacity (20), expected at most 10"));
+    }
+
+    fn run_validate_configuration_discovery_poll_interval(interval: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""discoveryPollIntervalSecs": {},
+                    "discoveryHandler": {{"#,
+                    interval
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_discovery_poll_interval() {
+        assert!(run_validate_configuration_discovery_poll_interval("300").allowed);
+        for invalid in ["0", "-5", "1.5", "\"5m\""] {
+            let resp = run_validate_configuration_discovery_poll_interval(invalid);
+            assert!(!resp.allowed);
+            assert!(resp
+                .status
+                .unwrap()
+                .message
+                .unwrap()
+                .contains(&format!("invalid discoveryPollIntervalSecs ({})", invalid)));
+        }
+    }
+
+    fn run_validate_configuration_broker_scheduler_name(scheduler_name: &str) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""brokerSchedulerName": {:?},
+                    "discoveryHandler": {{"#,
+                    scheduler_name
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_valid_broker_scheduler_name() {
+        assert!(run_validate_configuration_broker_scheduler_name("edge-scheduler").allowed);
+    }
+
+    #[test]
+    fn test_validate_configuration_empty_broker_scheduler_name() {
+        let resp = run_validate_configuration_broker_scheduler_name("");
+        assert!(!resp.allowed);
+        assert!(resp
+            .status
+            .unwrap()
+            .message
+            .unwrap()
+            .contains("invalid brokerSchedulerName"));
+    }
+
+    fn run_validate_configuration_node_affinity_preference_weight(
+        weight: i32,
+    ) -> AdmissionResponse {
+        let review: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec().replace(
+                r#""discoveryHandler": {"#,
+                &format!(
+                    r#""nodeAffinityPreferences": [{{"weight": {}, "matchLabels": {{"gpu": "true"}}}}],
+                    "discoveryHandler": {{"#,
+                    weight
+                ),
+            ))
+            .expect("v1.AdmissionReview JSON");
+        let rqst = review.request.expect("v1.AdmissionRequest JSON");
+        validate_configuration(&rqst, MaxCapacity::default())
+    }
+
+    #[test]
+    fn test_validate_configuration_node_affinity_preference_weight() {
+        assert!(run_validate_configuration_node_affinity_preference_weight(1).allowed);
+        assert!(run_validate_configuration_node_affinity_preference_weight(100).allowed);
+        for invalid in [0, -1, 101] {
+            let resp = run_validate_configuration_node_affinity_preference_weight(invalid);
+            assert!(!resp.allowed);
+            assert!(resp.status.unwrap().message.unwrap().contains(&format!(
+                "invalid nodeAffinityPreferences weight ({})",
+                invalid
+            )));
+        }
+    }
+
+    fn get_image_pull_secret_mock(
+        exists: bool,
+        expected_namespace: &'static str,
+    ) -> Arc<dyn IntoApi<Secret>> {
+        let mut mock_secret_api = MockApi::new();
+        mock_secret_api
+            .expect_get()
+            .times(1)
+            .withf(|name| name == "name")
+            .returning(move |_| Ok(exists.then(Secret::default)));
+        let mut mock_kube_client = MockIntoApi::<Secret>::new();
+        mock_kube_client
+            .expect_namespaced()
+            .withf(move |namespace| namespace == expected_namespace)
+            .return_once(|_| Box::new(mock_secret_api));
+        Arc::new(mock_kube_client)
+    }
+
+    async fn run_validate_with_image_pull_secret_check(exists: bool) -> AdmissionResponse {
+        let valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
+                .expect("v1.AdmissionReview JSON");
+        run_validate_review_with_image_pull_secret_check(valid, exists, "default").await
+    }
+
+    async fn run_validate_review_with_image_pull_secret_check(
+        valid: AdmissionReview,
+        exists: bool,
+        expected_namespace: &'static str,
+    ) -> AdmissionResponse {
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::from(get_image_pull_secret_mock(
+                    exists,
+                    expected_namespace,
+                )))
+                .service(validate),
+        )
+        .await;
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/validate")
+            .set_json(&valid)
+            .to_request();
+        let resp: AdmissionReview = actix_web::test::call_and_read_body_json(&app, rqst).await;
+        resp.response.expect("v1.AdmissionResponse JSON")
+    }
+
+    #[actix_web::test]
+    async fn test_validate_image_pull_secret_exists() {
+        assert!(
+            run_validate_with_image_pull_secret_check(true)
+                .await
+                .allowed
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_validate_image_pull_secret_missing() {
+        let resp = run_validate_with_image_pull_secret_check(false).await;
+        assert!(!resp.allowed);
+        assert_eq!(
+            resp.status.unwrap().message.unwrap(),
+            r#"imagePullSecret ("name") not found in namespace ("default")"#
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_validate_image_pull_secret_request_namespace() {
+        // The object does not carry its namespace, the secret is looked up in the request's one
+        let mut valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
+                .expect("v1.AdmissionReview JSON");
+        let rqst = valid.request.as_mut().unwrap();
+        rqst.namespace = Some("ns-b".to_string());
+        rqst.object.as_mut().unwrap()["metadata"]
+            .as_object_mut()
+            .unwrap()
+            .remove("namespace");
+        assert!(
+            run_validate_review_with_image_pull_secret_check(valid, true, "ns-b")
+                .await
+                .allowed
+        );
     }
 
     #[actix_web::test]
@@ -1142,4 +2711,28 @@ mod tests {
         let resp = actix_web::test::call_service(&app, rqst).await;
         assert!(resp.status().is_success());
     }
+
+    #[actix_web::test]
+    async fn test_validate_oversized_payload() {
+        let app = actix_web::test::init_service(
+            App::new().app_data(get_json_config(1024)).service(validate),
+        )
+        .await;
+        let valid: AdmissionReview =
+            serde_json::from_str(&get_valid_admission_review_with_broker_pod_spec())
+                .expect("v1.AdmissionReview JSON");
+        let rqst = actix_web::test::TestRequest::post()
+            .uri("/validate")
+            .set_json(&valid)
+            .to_request();
+        let resp = actix_web::test::call_service(&app, rqst).await;
+        assert_eq!(
+            resp.status(),
+            actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+        let body = actix_web::test::read_body(resp).await;
+        assert!(String::from_utf8(body.to_vec())
+            .unwrap()
+            .contains("exceeds the configured limit (1024 bytes)"));
+    }
 }